@@ -1,9 +1,25 @@
-use touchdictionary_core::cli;
+use clap::error::ErrorKind;
+use clap::Parser;
+use touchdictionary_core::{cli, logging};
 
 #[tokio::main]
 async fn main() {
+    // Logs go to stderr so stdout stays clean for --format json
+    logging::init_stderr();
+
+    let args = match cli::Cli::try_parse() {
+        Ok(args) => args,
+        Err(e) => {
+            let _ = e.print();
+            // Asking for help isn't a failure. Usage errors fail like any
+            // other error; 2 means not found.
+            let help = matches!(e.kind(), ErrorKind::DisplayHelp | ErrorKind::DisplayHelpOnMissingArgumentOrSubcommand);
+            std::process::exit(if help { 0 } else { 1 });
+        }
+    };
+
     // CLI mode only
-    if let Err(e) = cli::run_cli().await {
+    if let Err(e) = cli::run_cli(args).await {
         eprintln!("Error: {}", e);
         std::process::exit(1);
     }
@@ -0,0 +1,20 @@
+// `word_stats::frequency` should cost one hash lookup whatever the word, so
+// the most and least frequent words and a miss should all take about as long
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::hint::black_box;
+use touchdictionary_core::sources::word_stats;
+
+fn frequency(c: &mut Criterion) {
+    // Built on first use; keep that out of the timings
+    word_stats::frequency("the");
+
+    let mut group = c.benchmark_group("word_stats::frequency");
+    for word in ["the", "serendipity", "sesquipedalian", "zzxq"] {
+        group.bench_function(word, |b| b.iter(|| word_stats::frequency(black_box(word))));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, frequency);
+criterion_main!(benches);
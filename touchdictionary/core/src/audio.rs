@@ -0,0 +1,148 @@
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+use tracing::info;
+
+use crate::http;
+
+// Hosts pronunciation audio is served from. Anything else is refused so the
+// frontend can't be used to fetch and play arbitrary files.
+pub const ALLOWED_HOSTS: &[&str] = &["api.dictionaryapi.dev", "upload.wikimedia.org"];
+
+#[derive(Debug)]
+pub enum AudioError {
+    DisallowedUrl { url: String },
+    Download(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for AudioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AudioError::DisallowedUrl { url } => write!(f, "Refusing to fetch audio from '{}'", url),
+            AudioError::Download(e) => write!(f, "Failed to download audio: {}", e),
+            AudioError::Io(e) => write!(f, "Audio cache error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for AudioError {}
+
+impl From<io::Error> for AudioError {
+    fn from(e: io::Error) -> Self {
+        AudioError::Io(e)
+    }
+}
+
+// Only https URLs on an allow-listed host
+pub fn validate_url(url: &str) -> Result<reqwest::Url, AudioError> {
+    let disallowed = || AudioError::DisallowedUrl { url: url.to_string() };
+    let parsed = reqwest::Url::parse(url).map_err(|_| disallowed())?;
+
+    if parsed.scheme() != "https" || !parsed.host_str().is_some_and(|host| ALLOWED_HOSTS.contains(&host)) {
+        return Err(disallowed());
+    }
+    Ok(parsed)
+}
+
+// Downloaded pronunciation files, named by a hash of their URL
+#[derive(Debug, Clone)]
+pub struct AudioCache {
+    dir: PathBuf,
+}
+
+impl Default for AudioCache {
+    fn default() -> Self {
+        AudioCache::new(std::env::temp_dir().join("touchdictionary-audio"))
+    }
+}
+
+impl AudioCache {
+    pub fn new(dir: PathBuf) -> Self {
+        AudioCache { dir }
+    }
+
+    pub fn path_for(&self, url: &reqwest::Url) -> PathBuf {
+        let digest = Sha256::digest(url.as_str().as_bytes());
+        let extension = Path::new(url.path())
+            .extension()
+            .and_then(|e| e.to_str())
+            .filter(|e| e.len() <= 4 && e.chars().all(|c| c.is_ascii_alphanumeric()))
+            .unwrap_or("mp3");
+        self.dir.join(format!("{}.{}", hex::encode(&digest[..16]), extension))
+    }
+
+    // Local copy of the audio at `url`, downloading it on first use
+    pub async fn fetch(&self, url: &str) -> Result<PathBuf, AudioError> {
+        let url = validate_url(url)?;
+        let path = self.path_for(&url);
+        if tokio::fs::try_exists(&path).await.unwrap_or(false) {
+            return Ok(path);
+        }
+
+        info!("Downloading {}", url);
+        let response = http::client()
+            .get(url.clone())
+            .send()
+            .await
+            .map_err(|e| AudioError::Download(e.to_string()))?;
+        // The client follows redirects, which could lead anywhere
+        validate_url(response.url().as_str())?;
+        if !response.status().is_success() {
+            return Err(AudioError::Download(format!("server returned status {}", response.status())));
+        }
+        let bytes = response.bytes().await.map_err(|e| AudioError::Download(e.to_string()))?;
+
+        // Write then rename so a half-written file is never played
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let partial = path.with_extension("part");
+        tokio::fs::write(&partial, &bytes).await?;
+        tokio::fs::rename(&partial, &path).await?;
+        Ok(path)
+    }
+}
+
+// Warm the cache so playing the recording later starts immediately
+pub async fn prefetch(url: &str) -> Result<(), AudioError> {
+    AudioCache::default().fetch(url).await.map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_url() {
+        assert!(validate_url("https://api.dictionaryapi.dev/media/pronunciations/en/hello-uk.mp3").is_ok());
+        assert!(validate_url("https://upload.wikimedia.org/wikipedia/commons/a/a1/En-us-hello.ogg").is_ok());
+
+        for url in [
+            "http://api.dictionaryapi.dev/media/hello.mp3",
+            "https://evil.example.com/hello.mp3",
+            "https://api.dictionaryapi.dev.evil.example.com/hello.mp3",
+            "file:///etc/passwd",
+            "not a url",
+        ] {
+            assert!(matches!(validate_url(url), Err(AudioError::DisallowedUrl { .. })), "{}", url);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_reuses_cached_file() {
+        let dir = std::env::temp_dir().join(format!("touchdictionary-audio-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let cache = AudioCache::new(dir.clone());
+
+        let url = "https://upload.wikimedia.org/wikipedia/commons/a/a1/En-us-hello.ogg";
+        let path = cache.path_for(&validate_url(url).unwrap());
+        assert_eq!(path.extension().unwrap(), "ogg");
+
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&path, b"cached").unwrap();
+        assert_eq!(cache.fetch(url).await.unwrap(), path);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,140 @@
+use std::future::Future;
+use std::sync::Arc;
+
+use futures_util::stream::{FuturesOrdered, StreamExt};
+use tokio::sync::Semaphore;
+
+use crate::error::LookupError;
+
+// Lookups in flight at once, enough to hide latency without tripping the
+// upstream rate limits
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+// Outcome counts for a whole batch
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BatchSummary {
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+}
+
+impl BatchSummary {
+    // Only a batch where nothing worked counts as a failure
+    pub fn all_failed(&self) -> bool {
+        self.total > 0 && self.succeeded == 0
+    }
+}
+
+// One query per line; blank lines and `#` comments are skipped
+pub fn read_queries(input: &str) -> Vec<String> {
+    input
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+// Run `lookup` over every query with at most `concurrency` in flight.
+// `on_result` sees each outcome in input order, so output lines up with the
+// list that was fed in; a failed query never stops the rest.
+pub async fn run<T, F, Fut, R>(queries: Vec<String>, concurrency: usize, lookup: F, mut on_result: R) -> BatchSummary
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<T, LookupError>>,
+    R: FnMut(usize, &str, &Result<T, LookupError>),
+{
+    let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+    let mut summary = BatchSummary {
+        total: queries.len(),
+        ..BatchSummary::default()
+    };
+
+    let mut pending: FuturesOrdered<_> = queries
+        .into_iter()
+        .map(|query| {
+            let semaphore = semaphore.clone();
+            // Futures are lazy, so the lookup only starts once a permit is held
+            let fetch = lookup(query.clone());
+            async move {
+                let _permit = semaphore.acquire_owned().await.expect("batch semaphore closed");
+                let outcome = fetch.await;
+                (query, outcome)
+            }
+        })
+        .collect();
+
+    let mut index = 0;
+    while let Some((query, outcome)) = pending.next().await {
+        index += 1;
+        match &outcome {
+            Ok(_) => summary.succeeded += 1,
+            Err(_) => summary.failed += 1,
+        }
+        on_result(index, &query, &outcome);
+    }
+
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[test]
+    fn test_read_queries_skips_blanks_and_comments() {
+        let input = "apple\n\n  # fruit list\n  banana  \r\ncherry\n";
+        assert_eq!(read_queries(input), vec!["apple", "banana", "cherry"]);
+    }
+
+    #[tokio::test]
+    async fn test_run_bounds_concurrency_and_keeps_order() {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let queries: Vec<String> = (0..10).map(|i| format!("word{}", i)).collect();
+
+        let mut seen = Vec::new();
+        let summary = run(
+            queries.clone(),
+            DEFAULT_CONCURRENCY,
+            |query| {
+                let in_flight = in_flight.clone();
+                let peak = peak.clone();
+                async move {
+                    let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    if query == "word3" {
+                        Err(LookupError::NotFound { query })
+                    } else {
+                        Ok(query.len())
+                    }
+                }
+            },
+            |index, query, _| seen.push((index, query.to_string())),
+        )
+        .await;
+
+        assert_eq!(peak.load(Ordering::SeqCst), DEFAULT_CONCURRENCY);
+        assert_eq!(seen.iter().map(|(_, q)| q.clone()).collect::<Vec<_>>(), queries);
+        assert_eq!(seen.last().unwrap().0, 10);
+        assert_eq!(summary, BatchSummary { total: 10, succeeded: 9, failed: 1 });
+        assert!(!summary.all_failed());
+    }
+
+    #[tokio::test]
+    async fn test_run_all_failed() {
+        let summary = run(
+            vec!["a".to_string(), "b".to_string()],
+            2,
+            |query| async move { Err::<(), _>(LookupError::NotFound { query }) },
+            |_, _, _| {},
+        )
+        .await;
+        assert!(summary.all_failed());
+        assert!(!BatchSummary::default().all_failed());
+    }
+}
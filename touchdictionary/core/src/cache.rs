@@ -0,0 +1,471 @@
+use std::io;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use crate::config::Config;
+use crate::language;
+use crate::lookup::LookupOptions;
+use crate::storage::{self, CacheRepo, CacheRow};
+
+// Default freshness window for cached responses
+pub const DEFAULT_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+// Entries older than this are still served, but checked with upstream
+// first so edits show up within a day
+pub const DEFAULT_SOFT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+// Freshness window for entries fetched ahead of time rather than for a
+// lookup. Most are never looked at, so they shouldn't stick around.
+pub const PREFETCH_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+
+// Bumped whenever `LookupResult` changes shape, so entries cached by an
+// older build are never found rather than failing to read
+pub const SCHEMA_VERSION: u32 = 1;
+
+// On-disk response cache: a table in the shared database, one row per
+// (source, query) pair. Entries older than the TTL are not served normally
+// but are kept around as an offline fallback.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    repo: CacheRepo,
+    ttl: Duration,
+    soft_ttl: Duration,
+}
+
+// Where a whole lookup result is cached: a hash of everything that shapes
+// it, so a compact or filtered result is never served for a full one. The
+// same key tells identical lookups apart from running ones they can join.
+// Responses from single sources are cached under (source, query) instead;
+// they're stored as the source sent them and shaped after.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey(String);
+
+impl CacheKey {
+    // Hashed a field at a time, each named and length-prefixed, so the key
+    // only changes when what the result depends on does: not when
+    // `LookupOptions` gains a field or how it serializes changes. Without a
+    // language in `options` it's the one `config` looks words up in.
+    pub fn from(config: &Config, options: &LookupOptions, query: &str) -> Self {
+        let query = query.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+        let lang = options.lang.as_deref().map(language::normalize).unwrap_or_else(|| config.lookup_language());
+        let sources = if options.offline {
+            "offline"
+        } else if options.private {
+            "local"
+        } else {
+            "all"
+        };
+        let detail = if options.compact {
+            "compact"
+        } else if options.all_definitions {
+            "all"
+        } else {
+            "default"
+        };
+        let mut pos_filter: Vec<String> = options.pos_filter.iter().flatten().map(|pos| pos.trim().to_lowercase()).collect();
+        pos_filter.sort();
+        pos_filter.dedup();
+        let translate_to = options.translate_to.as_deref().map(language::normalize).unwrap_or_default();
+
+        let mut hasher = Sha256::new();
+        let mut field = |name: &str, value: &str| {
+            for part in [name, value] {
+                hasher.update((part.len() as u64).to_le_bytes());
+                hasher.update(part.as_bytes());
+            }
+        };
+        field("schema", &SCHEMA_VERSION.to_string());
+        field("query", &query);
+        field("lang", &lang);
+        field("sources", sources);
+        field("detail", detail);
+        field("pos", &pos_filter.join(","));
+        field("translate", &translate_to);
+        field("auto_correct", &options.auto_correct.to_string());
+        CacheKey(hex::encode(&hasher.finalize()[..16]))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CachedValue<T> {
+    pub value: T,
+    pub is_fresh: bool,
+    // Fresh, but old enough that upstream should be asked whether it changed
+    pub needs_revalidation: bool,
+    // What upstream said identifies this version, if it said anything
+    pub validator: Option<Validator>,
+    // Hash of the stored value, to tell whether a refetch brought anything new
+    pub content_hash: String,
+    // Stored by `put_prefetched` and not refetched for a lookup since
+    pub prefetched: bool,
+}
+
+// HTTP validators for conditional requests (If-None-Match and
+// If-Modified-Since)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Validator {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl Validator {
+    // Validators from a response's headers; None when it sent neither
+    pub fn from_headers(headers: &reqwest::header::HeaderMap) -> Option<Self> {
+        let header = |name| headers.get(name).and_then(|value| value.to_str().ok()).map(str::to_string);
+        let validator = Validator {
+            etag: header(reqwest::header::ETAG),
+            last_modified: header(reqwest::header::LAST_MODIFIED),
+        };
+        (validator.etag.is_some() || validator.last_modified.is_some()).then_some(validator)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub entries: usize,
+    // Lookups answered from a fresh entry, and ones that had to go upstream
+    pub hits: u64,
+    pub misses: u64,
+    // Fresh entries checked with upstream before being served
+    pub revalidations: u64,
+}
+
+impl Cache {
+    pub fn new(repo: CacheRepo, ttl: Duration) -> Self {
+        Cache { repo, ttl, soft_ttl: ttl.min(DEFAULT_SOFT_TTL) }
+    }
+
+    pub fn with_soft_ttl(self, soft_ttl: Duration) -> Self {
+        Cache { soft_ttl: soft_ttl.min(self.ttl), ..self }
+    }
+
+    // Cache in the default location. The TTL can be overridden with
+    // `TOUCHDICTIONARY_CACHE_TTL` (seconds).
+    pub fn open_default() -> Option<Self> {
+        let ttl = std::env::var("TOUCHDICTIONARY_CACHE_TTL")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_TTL);
+
+        storage::open_default().map(|storage| Cache::new(storage.cache(), ttl))
+    }
+
+    pub async fn get<T: Serialize + DeserializeOwned>(&self, source: &str, query: &str) -> Option<CachedValue<T>> {
+        let row = match self.repo.get(source, query).await {
+            Ok(row) => row?,
+            Err(e) => {
+                warn!("Failed to read cache entry for {} '{}': {}", source, query, e);
+                return None;
+            }
+        };
+        match serde_json::from_str::<T>(&row.value) {
+            Ok(value) => Some(self.cached_value(value, row.stored_at, row.prefetched, row.validator)),
+            Err(e) => {
+                warn!("Discarding unreadable cache entry for {} '{}': {}", source, query, e);
+                let _ = self.repo.remove(source, query).await;
+                None
+            }
+        }
+    }
+
+    fn cached_value<T: Serialize>(&self, value: T, stored_at: u64, prefetched: bool, validator: Option<Validator>) -> CachedValue<T> {
+        let age = now_secs().saturating_sub(stored_at);
+        let ttl = if prefetched { self.ttl.min(PREFETCH_TTL) } else { self.ttl };
+        let is_fresh = age < ttl.as_secs();
+        CachedValue {
+            content_hash: content_hash(&value),
+            value,
+            is_fresh,
+            needs_revalidation: is_fresh && age >= self.soft_ttl.as_secs(),
+            validator,
+            prefetched,
+        }
+    }
+
+    pub async fn put<T: Serialize>(&self, source: &str, query: &str, value: &T) {
+        self.put_validated(source, query, value, None).await
+    }
+
+    // `put` that also keeps the validators upstream sent with the value
+    pub async fn put_validated<T: Serialize>(&self, source: &str, query: &str, value: &T, validator: Option<Validator>) {
+        self.store(source, query, value, validator, false).await
+    }
+
+    // `put_validated` for a value fetched in case it's wanted, which expires
+    // after `PREFETCH_TTL` unless a lookup refetches it first
+    pub async fn put_prefetched<T: Serialize>(&self, source: &str, query: &str, value: &T, validator: Option<Validator>) {
+        self.store(source, query, value, validator, true).await
+    }
+
+    async fn store<T: Serialize>(&self, source: &str, query: &str, value: &T, validator: Option<Validator>, prefetched: bool) {
+        let result = match serde_json::to_string(value) {
+            Ok(value) => {
+                let row = CacheRow { stored_at: now_secs(), prefetched, validator, value };
+                self.repo.put(source, query, row).await.map_err(io::Error::from)
+            }
+            Err(e) => Err(io::Error::other(e)),
+        };
+        if let Err(e) = result {
+            warn!("Failed to write cache entry for {} '{}': {}", source, query, e);
+        }
+    }
+
+    // Restart an entry's freshness window, for when upstream confirmed it
+    // hasn't changed
+    pub async fn touch(&self, source: &str, query: &str) {
+        if let Err(e) = self.repo.touch(source, query, now_secs()).await {
+            warn!("Failed to refresh cache entry for {} '{}': {}", source, query, e);
+        }
+    }
+
+    pub async fn record_hit(&self) {
+        self.record(|stats| stats.hits += 1).await
+    }
+
+    pub async fn record_miss(&self) {
+        self.record(|stats| stats.misses += 1).await
+    }
+
+    pub async fn record_revalidation(&self) {
+        self.record(|stats| stats.revalidations += 1).await
+    }
+
+    async fn record(&self, update: impl FnOnce(&mut CacheStats)) {
+        let mut delta = CacheStats::default();
+        update(&mut delta);
+        if let Err(e) = self.repo.count(delta.hits, delta.misses, delta.revalidations).await {
+            warn!("Failed to update cache stats: {}", e);
+        }
+    }
+
+    // How many entries there are and how the cache has done since the
+    // counters were created
+    pub async fn stats(&self) -> io::Result<CacheStats> {
+        Ok(self.repo.stats().await?)
+    }
+
+    // Remove every cache entry, returning how many were deleted
+    pub async fn clear(&self) -> io::Result<usize> {
+        Ok(self.repo.clear().await?)
+    }
+
+    // Delete entries nobody should need any more: expired ones stored over
+    // `max_age` ago and prefetched ones that expired without being looked
+    // up. Returns how many entries went and their size.
+    pub async fn prune(&self, max_age: Duration) -> io::Result<(usize, u64)> {
+        let now = now_secs();
+        let prefetched_before = now.saturating_sub(self.ttl.min(PREFETCH_TTL).as_secs());
+        let stored_before = now.saturating_sub(self.ttl.max(max_age).as_secs());
+        Ok(self.repo.prune(prefetched_before, stored_before).await?)
+    }
+}
+
+// Clear the cache in its default location
+pub async fn clear() -> io::Result<usize> {
+    match Cache::open_default() {
+        Some(cache) => cache.clear().await,
+        None => Ok(0),
+    }
+}
+
+// Stats of the cache in its default location
+pub async fn stats() -> io::Result<CacheStats> {
+    match Cache::open_default() {
+        Some(cache) => cache.stats().await,
+        None => Ok(CacheStats::default()),
+    }
+}
+
+// Hash to compare a freshly fetched value against `CachedValue::content_hash`
+pub fn content_hash<T: Serialize>(value: &T) -> String {
+    let data = serde_json::to_vec(value).unwrap_or_default();
+    hex::encode(&Sha256::digest(&data)[..16])
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Storage;
+
+    fn temp_cache(name: &str, ttl: Duration) -> Cache {
+        let dir = std::env::temp_dir().join(format!("touchdictionary-cache-test-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        Cache::new(Storage::open(&dir.join(storage::FILE_NAME)).unwrap().cache(), ttl)
+    }
+
+    // Make an entry look like it was stored `age` seconds ago
+    async fn backdate(cache: &Cache, source: &str, query: &str, age: u64) {
+        cache.repo.touch(source, query, now_secs() - age).await.unwrap();
+    }
+
+    #[test]
+    fn test_cache_key_covers_what_shapes_a_result() {
+        let config = Config { language: Some("en".to_string()), ..Config::default() };
+        let key = |options: &LookupOptions, query: &str| CacheKey::from(&config, options, query);
+        let base = LookupOptions::default();
+        let plain = key(&base, "serendipity");
+        // Pinned so a change to the key is a decision, not an accident
+        assert_eq!(plain.as_str(), "3604cfe83fada187dbc096aca146c5c3");
+
+        let changed = [
+            key(&base, "serendipitous"),
+            key(&LookupOptions { lang: Some("de".to_string()), ..LookupOptions::default() }, "serendipity"),
+            key(&LookupOptions { offline: true, ..LookupOptions::default() }, "serendipity"),
+            key(&LookupOptions { private: true, ..LookupOptions::default() }, "serendipity"),
+            key(&LookupOptions { compact: true, ..LookupOptions::default() }, "serendipity"),
+            key(&LookupOptions { all_definitions: true, ..LookupOptions::default() }, "serendipity"),
+            key(&LookupOptions { pos_filter: Some(vec!["noun".to_string()]), ..LookupOptions::default() }, "serendipity"),
+            key(&LookupOptions { translate_to: Some("es".to_string()), ..LookupOptions::default() }, "serendipity"),
+            key(&LookupOptions { auto_correct: true, ..LookupOptions::default() }, "serendipity"),
+        ];
+        for (i, other) in changed.iter().enumerate() {
+            assert_ne!(*other, plain, "component {} left the key alone", i);
+        }
+
+        // Spelled differently but the same lookup
+        assert_eq!(key(&base, "  Serendipity "), plain);
+        assert_eq!(key(&LookupOptions { lang: Some("EN".to_string()), ..LookupOptions::default() }, "serendipity"), plain);
+        // The configured language stands in for an unset one
+        let german = Config { language: Some("de".to_string()), ..Config::default() };
+        let in_german = LookupOptions { lang: Some("de".to_string()), ..LookupOptions::default() };
+        assert_eq!(CacheKey::from(&german, &base, "serendipity"), key(&in_german, "serendipity"));
+        let filtered = |pos: &[&str]| LookupOptions {
+            pos_filter: Some(pos.iter().map(|p| p.to_string()).collect()),
+            ..LookupOptions::default()
+        };
+        assert_eq!(key(&filtered(&["verb", "noun"]), "run"), key(&filtered(&["noun", "Verb"]), "run"));
+        // Options that don't change the result don't change the key
+        let unrelated =
+            LookupOptions { use_cache: false, referrer: Some("sprint".to_string()), deferred: true, ..LookupOptions::default() };
+        assert_eq!(key(&unrelated, "serendipity"), plain);
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_entries_are_misses_and_removed() {
+        let cache = temp_cache("schema", DEFAULT_TTL);
+        cache.put("dictionary", "happy", &"glad".to_string()).await;
+        assert!(cache.get::<Vec<String>>("dictionary", "happy").await.is_none());
+        assert!(cache.get::<String>("dictionary", "happy").await.is_none());
+        assert_eq!(cache.stats().await.unwrap().entries, 0);
+    }
+
+    #[tokio::test]
+    async fn test_put_get_and_clear() {
+        let cache = temp_cache("roundtrip", DEFAULT_TTL);
+        cache.put("dictionary", "happy", &vec!["glad".to_string()]).await;
+
+        let hit = cache.get::<Vec<String>>("dictionary", "happy").await.unwrap();
+        assert!(hit.is_fresh);
+        assert_eq!(hit.value, vec!["glad"]);
+        assert!(cache.get::<Vec<String>>("wikipedia", "happy").await.is_none());
+
+        assert_eq!(cache.clear().await.unwrap(), 1);
+        assert!(cache.get::<Vec<String>>("dictionary", "happy").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_expired_entries_are_stale() {
+        let cache = temp_cache("stale", Duration::ZERO);
+        cache.put("wikipedia", "paris", &"summary".to_string()).await;
+
+        let hit = cache.get::<String>("wikipedia", "paris").await.unwrap();
+        assert!(!hit.is_fresh);
+        assert_eq!(hit.value, "summary");
+
+        cache.clear().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_revalidation_window_and_touch() {
+        let cache = temp_cache("soft", DEFAULT_TTL).with_soft_ttl(Duration::ZERO);
+        let validator = Validator { etag: Some("\"abc\"".to_string()), last_modified: None };
+        cache.put_validated("wikipedia", "paris", &"summary".to_string(), Some(validator.clone())).await;
+
+        let hit = cache.get::<String>("wikipedia", "paris").await.unwrap();
+        assert!(hit.is_fresh && hit.needs_revalidation);
+        assert_eq!(hit.validator, Some(validator));
+        assert_eq!(hit.content_hash, content_hash(&"summary".to_string()));
+        assert_ne!(hit.content_hash, content_hash(&"new summary".to_string()));
+
+        // Touching keeps the value and validator
+        cache.touch("wikipedia", "paris").await;
+        let hit = cache.get::<String>("wikipedia", "paris").await.unwrap();
+        assert_eq!(hit.value, "summary");
+        assert!(hit.validator.is_some());
+
+        cache.clear().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_prefetched_entries_expire_sooner() {
+        let cache = temp_cache("prefetch", DEFAULT_TTL);
+        cache.put_prefetched("dictionary", "glad", &"happy".to_string(), None).await;
+        let hit = cache.get::<String>("dictionary", "glad").await.unwrap();
+        assert!(hit.prefetched && hit.is_fresh);
+
+        backdate(&cache, "dictionary", "glad", PREFETCH_TTL.as_secs() + 1).await;
+        assert!(!cache.get::<String>("dictionary", "glad").await.unwrap().is_fresh);
+
+        // A lookup's own fetch replaces it with an ordinary entry
+        cache.put("dictionary", "glad", &"happy".to_string()).await;
+        let hit = cache.get::<String>("dictionary", "glad").await.unwrap();
+        assert!(!hit.prefetched && hit.is_fresh);
+
+        cache.clear().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_prune_keeps_recent_stale_entries() {
+        let cache = temp_cache("prune", Duration::from_secs(60));
+        cache.put("dictionary", "fresh", &"a".to_string()).await;
+        cache.put("dictionary", "stale", &"b".to_string()).await;
+        backdate(&cache, "dictionary", "stale", 120).await;
+        cache.put("dictionary", "ancient", &"c".to_string()).await;
+        backdate(&cache, "dictionary", "ancient", 7200).await;
+        cache.put_prefetched("dictionary", "unused", &"d".to_string(), None).await;
+        backdate(&cache, "dictionary", "unused", PREFETCH_TTL.as_secs() + 1).await;
+
+        let (removed, freed) = cache.prune(Duration::from_secs(3600)).await.unwrap();
+        assert_eq!(removed, 2);
+        assert!(freed > 0);
+        assert!(cache.get::<String>("dictionary", "fresh").await.is_some());
+        assert!(cache.get::<String>("dictionary", "stale").await.is_some());
+        assert!(cache.get::<String>("dictionary", "ancient").await.is_none());
+        assert!(cache.get::<String>("dictionary", "unused").await.is_none());
+
+        cache.clear().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_stats_survive_clear() {
+        let cache = temp_cache("stats", DEFAULT_TTL);
+        cache.put("dictionary", "happy", &"glad".to_string()).await;
+        cache.record_hit().await;
+        cache.record_hit().await;
+        cache.record_miss().await;
+        cache.record_revalidation().await;
+
+        let stats = cache.stats().await.unwrap();
+        assert_eq!(stats, CacheStats { entries: 1, hits: 2, misses: 1, revalidations: 1 });
+
+        assert_eq!(cache.clear().await.unwrap(), 1);
+        assert_eq!(cache.stats().await.unwrap().entries, 0);
+        assert_eq!(cache.stats().await.unwrap().hits, 2);
+    }
+}
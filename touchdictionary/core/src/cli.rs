@@ -1,103 +1,1372 @@
-use std::env;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+#[cfg(unix)]
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use clap::{ArgGroup, Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use tokio::io::AsyncReadExt;
+#[cfg(unix)]
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+use crate::batch;
+use crate::clipboard::{self, Selection};
+use crate::config::{self, Config};
+use crate::context::LookupContext;
+use crate::deferred;
+use crate::error::LookupError;
+use crate::export::{self, ExportFormat};
+use crate::favorites;
+use crate::format::{self, OutputFormat, TextOptions};
+use crate::guard;
+use crate::history;
+use crate::images::{self, ImageCache};
+use crate::language;
 use crate::lookup;
+use crate::maintenance;
+use crate::notify::{self, Notification};
+use crate::progress::{self, Spinner};
+use crate::repl;
+#[cfg(unix)]
+use crate::server::{self, Response};
+use crate::sources::{glossary, SourceRegistry};
+use crate::sources::wikipedia::{self, ArticlePage};
+use crate::watch;
 
-pub async fn run_cli() -> Result<(), Box<dyn std::error::Error>> {
-    let args: Vec<String> = env::args().collect();
+// Exit status for a failed lookup: 2 when the word simply wasn't found,
+// 1 for everything else (sources failing, rate limiting, bad responses)
+pub fn exit_code(error: &LookupError) -> i32 {
+    match error {
+        LookupError::NotFound { .. } => 2,
+        _ => 1,
+    }
+}
 
-    let query = if args.len() > 1 {
-        args[1..].join(" ")
-    } else {
-        println!("TouchDictionary - Modern Dictionary Lookup");
-        println!("Usage: touchdictionary <word>");
-        println!("       touchdictionary --selection");
-        return Ok(());
+// How long `--wait-for-network` waits when not told
+pub const DEFAULT_NETWORK_WAIT_SECS: u64 = 60;
+
+// Shown under `--help`, after the commands and options
+const AFTER_HELP: &str = "\
+A word with wildcards, like 'c?t' or 's*ment', lists the words that fit it:
+? stands for one letter and * for any number.
+
+Use `touchdictionary lookup <word>` to look up a word that is also a command name.";
+
+// Parsed command line. With no command the words are looked up, as
+// `lookup` would; `effective_command()` says what to run either way.
+#[derive(Debug, Clone, PartialEq, Parser)]
+#[command(
+    name = "touchdictionary",
+    bin_name = "touchdictionary",
+    about = "TouchDictionary - Modern Dictionary Lookup",
+    after_help = AFTER_HELP,
+    arg_required_else_help = true,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true,
+    group = ArgGroup::new("query").required(true).multiple(true).args([
+        "words", "selection", "watch", "batch", "stdin", "wotd", "interactive", "legacy_history", "clear_history", "clear_cache",
+    ]),
+)]
+pub struct Cli {
+    #[command(flatten)]
+    pub global: GlobalArgs,
+    #[command(flatten)]
+    pub lookup: LookupCommand,
+    #[arg(short, long, group = "target", conflicts_with = "words", help = "Shorthand for `repl`")]
+    pub interactive: bool,
+    // Spellings from before the subcommands existed
+    #[arg(long = "history", value_name = "N", num_args = 0..=1, hide = true, group = "target", conflicts_with = "words")]
+    pub legacy_history: Option<Option<usize>>,
+    #[arg(long, hide = true, group = "target", conflicts_with = "words")]
+    pub clear_history: bool,
+    #[arg(long, hide = true, group = "target", conflicts_with = "words")]
+    pub clear_cache: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+// Flags accepted before or after any command
+#[derive(Debug, Clone, Default, PartialEq, Args)]
+pub struct GlobalArgs {
+    #[arg(long, global = true, help = "Shorthand for --format json")]
+    pub json: bool,
+    // Kept as text: `history export` and `fav export` have a --format of
+    // their own under the same id, and clap hands values between the two
+    #[arg(long, global = true, value_name = "FMT", value_parser = parse_as::<OutputFormat>, help = "text, markdown, json or ndjson")]
+    pub format: Option<String>,
+    #[arg(long, global = true, value_name = "CODE", help = "Language to look words up in, e.g. de")]
+    pub lang: Option<String>,
+    #[arg(long = "translate", global = true, value_name = "CODE", help = "Translate definitions, e.g. es")]
+    pub translate_to: Option<String>,
+    #[arg(long, global = true, help = "Only use the cache and offline sources")]
+    pub offline: bool,
+    // Private mode for this run, whatever the config says
+    #[arg(long, global = true, help = "Ask no network source and save nothing: no cache entries, history or favorites")]
+    pub private: bool,
+    #[arg(long, global = true, help = "Don't read or write the lookup cache")]
+    pub no_cache: bool,
+    // Seconds to wait for the network when a lookup can't reach it; None
+    // inside when given without a number
+    #[arg(
+        long,
+        global = true,
+        value_name = "SECS",
+        num_args = 0..=1,
+        require_equals = true,
+        help = "When the network is down, wait up to SECS (default 60) for it and look up again"
+    )]
+    pub wait_for_network: Option<Option<u64>>,
+    #[arg(long, global = true, help = "Look up the best spelling suggestion instead")]
+    pub auto_correct: bool,
+    #[arg(long = "all", global = true, help = "Show every definition, not just the top ones")]
+    pub all_definitions: bool,
+    #[arg(long, global = true, help = "Only the top three definitions, one example and a two-sentence Wikipedia summary")]
+    pub compact: bool,
+    // Parts of speech given with --pos; empty keeps them all
+    #[arg(
+        long = "pos",
+        global = true,
+        value_name = "PART",
+        help = "Only show definitions of this part of speech, e.g. verb or adj; repeat for more than one"
+    )]
+    pub pos_filter: Vec<String>,
+    #[arg(long, global = true, value_name = "FILE", help = "Append each result as an Anki card")]
+    pub anki_out: Option<PathBuf>,
+    #[arg(long, global = true, help = "Show how each source did")]
+    pub verbose: bool,
+    // No "Looking up" line and no spinner
+    #[arg(long, global = true, help = "No progress while looking up")]
+    pub quiet: bool,
+    // Columns text output wraps at; the terminal's when unset
+    #[arg(
+        long,
+        global = true,
+        value_name = "COLUMNS",
+        value_parser = clap::value_parser!(u16).range(1..),
+        help = "Wrap text output to this width instead of the terminal's"
+    )]
+    pub width: Option<u16>,
+    #[arg(long, global = true, help = "Plain text output even on a terminal; setting NO_COLOR does the same")]
+    pub no_color: bool,
+}
+
+impl GlobalArgs {
+    // --format when given, else JSON for --json and text otherwise
+    pub fn output_format(&self) -> OutputFormat {
+        let format = self.format.as_deref().and_then(|f| f.parse().ok());
+        format.or(self.json.then_some(OutputFormat::Json)).unwrap_or_default()
+    }
+
+    pub fn network_wait(&self) -> Option<Duration> {
+        self.wait_for_network.map(|secs| Duration::from_secs(secs.unwrap_or(DEFAULT_NETWORK_WAIT_SECS)))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Subcommand)]
+pub enum Command {
+    #[command(
+        about = "Look up a word (the default)",
+        group = ArgGroup::new("query").required(true).multiple(true).args(["words", "selection", "watch", "batch", "stdin", "wotd"]),
+    )]
+    Lookup(LookupCommand),
+    #[command(about = "Show the N most recent lookups, or clear or export them", args_conflicts_with_subcommands = true)]
+    History {
+        #[arg(value_name = "N")]
+        limit: Option<usize>,
+        #[command(subcommand)]
+        command: Option<HistoryCommand>,
+    },
+    #[command(about = "Save words for later and list them")]
+    Fav {
+        #[command(subcommand)]
+        command: FavCommand,
+    },
+    #[command(about = "Clear, inspect or prune the lookup cache")]
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+    #[command(about = "Show the effective config, or where it lives")]
+    Config {
+        #[command(subcommand)]
+        command: Option<ConfigCommand>,
+    },
+    #[command(about = "Check glossary files")]
+    Glossary {
+        #[command(subcommand)]
+        command: GlossaryCommand,
+    },
+    #[command(about = "Ask one source directly")]
+    Source {
+        #[command(subcommand)]
+        command: SourceCommand,
+    },
+    #[command(about = "Show two words' definitions side by side")]
+    Compare { first: String, second: String },
+    #[command(about = "Look words up one line at a time")]
+    Repl,
+    #[command(about = "Answer lookups from editors and scripts over a Unix socket until stopped")]
+    Serve {
+        // The default socket when None
+        #[arg(long, value_name = "PATH")]
+        socket: Option<PathBuf>,
+    },
+    #[command(about = "Look up through a running `serve`, or directly when none is running")]
+    Client {
+        #[arg(long, value_name = "PATH")]
+        socket: Option<PathBuf>,
+        #[command(flatten)]
+        result: ResultArgs,
+        #[arg(value_name = "WORD", required = true)]
+        words: Vec<String>,
+    },
+    #[command(about = "Check which sources can be reached")]
+    Doctor,
+    #[command(about = "Print a bash, zsh or fish completion script")]
+    Completions { shell: Shell },
+}
+
+// `lookup` and the words given without a command
+#[derive(Debug, Clone, Default, PartialEq, Args)]
+pub struct LookupCommand {
+    #[command(flatten)]
+    pub target: TargetArgs,
+    #[command(flatten)]
+    pub result: ResultArgs,
+    #[arg(value_name = "WORD")]
+    pub words: Vec<String>,
+}
+
+// What to look up in place of words. At most one can be given.
+#[derive(Debug, Clone, Default, PartialEq, Args)]
+pub struct TargetArgs {
+    #[arg(
+        long,
+        value_name = "WHICH",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "primary",
+        group = "target",
+        conflicts_with = "words",
+        help = "Look up the selected text; WHICH is primary (default), clipboard or auto"
+    )]
+    pub selection: Option<Selection>,
+    #[arg(long, group = "target", conflicts_with_all = ["words", "html_out", "copy"], help = "Look up every new selection until interrupted")]
+    pub watch: bool,
+    #[arg(long, value_name = "FILE", group = "target", conflicts_with_all = ["words", "html_out", "copy"], help = "Look up one word per line")]
+    pub batch: Option<String>,
+    #[arg(long, group = "target", conflicts_with_all = ["words", "html_out", "copy"], help = "Look up one word per line of stdin")]
+    pub stdin: bool,
+    #[arg(long, group = "target", conflicts_with = "words", help = "Show the word of the day")]
+    pub wotd: bool,
+    #[arg(long, group = "target", conflicts_with_all = ["html_out", "copy"], help = "Print the whole Wikipedia article the words name")]
+    pub full_article: bool,
+}
+
+// Where a single result goes besides stdout
+#[derive(Debug, Clone, Default, PartialEq, Args)]
+pub struct ResultArgs {
+    #[arg(long, value_name = "FILE", help = "Save the result as a page to open, print or share")]
+    pub html_out: Option<PathBuf>,
+    #[arg(long, help = "Put the word and its top definition on the clipboard as one line")]
+    pub copy: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LookupTarget {
+    Words(String),
+    Selection(Selection),
+    Watch,
+    Batch(BatchInput),
+    WordOfTheDay,
+    FullArticle(String),
+}
+
+impl LookupCommand {
+    pub fn target(&self) -> LookupTarget {
+        let words = self.words.join(" ");
+        let target = &self.target;
+        if let Some(selection) = target.selection {
+            LookupTarget::Selection(selection)
+        } else if target.watch {
+            LookupTarget::Watch
+        } else if let Some(file) = &target.batch {
+            LookupTarget::Batch(BatchInput::File(file.clone()))
+        } else if target.stdin {
+            LookupTarget::Batch(BatchInput::Stdin)
+        } else if target.wotd {
+            LookupTarget::WordOfTheDay
+        } else if target.full_article {
+            LookupTarget::FullArticle(words)
+        } else {
+            LookupTarget::Words(words)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Subcommand)]
+pub enum HistoryCommand {
+    #[command(about = "Forget every recorded lookup")]
+    Clear,
+    #[command(about = "Write out the whole history")]
+    Export(Export),
+}
+
+// `history export` / `fav export`: stdout unless `out` is given. Its
+// --format stands in for the global one, which has no csv.
+#[derive(Debug, Clone, Default, PartialEq, Args)]
+pub struct Export {
+    #[arg(long, value_name = "FMT", value_parser = parse_as::<ExportFormat>, help = "csv or json; CSV for a .csv file and JSON otherwise when not given")]
+    pub format: Option<String>,
+    #[arg(long, value_name = "FILE", help = "File to write to instead of stdout")]
+    pub out: Option<PathBuf>,
+}
+
+impl Export {
+    pub fn format(&self) -> ExportFormat {
+        let csv_file = self.out.as_deref().and_then(Path::extension).is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+        let format = self.format.as_deref().and_then(|f| f.parse().ok());
+        format.unwrap_or(if csv_file { ExportFormat::Csv } else { ExportFormat::Json })
+    }
+}
+
+// Check a --format value against `T` but keep the text
+fn parse_as<T: FromStr<Err = String>>(value: &str) -> Result<String, String> {
+    value.parse::<T>().map(|_| value.to_string())
+}
+
+#[derive(Debug, Clone, PartialEq, Subcommand)]
+pub enum FavCommand {
+    #[command(about = "Save a word with a snapshot of its lookup")]
+    Add {
+        #[arg(value_name = "WORD", required = true)]
+        word: Vec<String>,
+        #[arg(long, value_name = "TEXT")]
+        note: Option<String>,
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+    },
+    #[command(name = "rm", alias = "remove", about = "Forget a saved word")]
+    Remove {
+        #[arg(value_name = "WORD", required = true)]
+        word: Vec<String>,
+    },
+    #[command(about = "List saved words, newest first")]
+    List {
+        #[arg(long, value_name = "TAG")]
+        tag: Option<String>,
+    },
+    #[command(about = "Write out every saved word")]
+    Export(Export),
+}
+
+#[derive(Debug, Clone, PartialEq, Subcommand)]
+pub enum CacheCommand {
+    #[command(about = "Remove every cached lookup")]
+    Clear,
+    #[command(about = "Show how well the cache is doing")]
+    Stats,
+    #[command(about = "Drop expired entries and trim the image cache and history")]
+    Prune,
+}
+
+#[derive(Debug, Clone, PartialEq, Subcommand)]
+pub enum ConfigCommand {
+    #[command(about = "Print where the config file lives")]
+    Path,
+    #[command(about = "Check the config for typos and bad values")]
+    Validate {
+        // The default config file when None
+        file: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Subcommand)]
+pub enum GlossaryCommand {
+    #[command(about = "Check glossary files for malformed entries")]
+    Validate {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Subcommand)]
+pub enum SourceCommand {
+    #[command(about = "Ask one source and show what it returned")]
+    Test {
+        name: String,
+        // The source's own probe word when empty
+        #[arg(value_name = "WORD")]
+        query: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Cli {
+    // What to run, with the old top-level spellings and a bare lookup
+    // turned into their commands
+    pub fn effective_command(&self) -> Command {
+        if self.interactive {
+            Command::Repl
+        } else if let Some(limit) = self.legacy_history {
+            Command::History { limit, command: None }
+        } else if self.clear_history {
+            Command::History { limit: None, command: Some(HistoryCommand::Clear) }
+        } else if self.clear_cache {
+            Command::Cache { command: CacheCommand::Clear }
+        } else {
+            self.command.clone().unwrap_or_else(|| Command::Lookup(self.lookup.clone()))
+        }
+    }
+
+    fn lookup_options(&self) -> lookup::LookupOptions {
+        lookup::LookupOptions {
+            use_cache: !self.global.no_cache,
+            auto_correct: self.global.auto_correct,
+            all_definitions: self.global.all_definitions,
+            compact: self.global.compact,
+            offline: self.global.offline,
+            private: self.global.private,
+            translate_to: self.global.translate_to.clone(),
+            lang: self.global.lang.clone(),
+            pos_filter: (!self.global.pos_filter.is_empty()).then(|| self.global.pos_filter.clone()),
+            ..lookup::LookupOptions::default()
+        }
+    }
+}
+
+pub async fn run_cli(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
+    let options = cli.lookup_options();
+    let command = cli.effective_command();
+    let output = cli.global.output_format();
+    let network_wait = cli.global.network_wait();
+    let GlobalArgs { anki_out, verbose, quiet, width, no_color, .. } = cli.global;
+    let saves = Saves { anki_out: anki_out.as_deref(), ..Saves::default() };
+    let text = TextOptions::for_stdout(width.map(usize::from), no_color);
+
+    let lookup = match command {
+        Command::History { limit, command: None } => {
+            print_history(&history::recent(limit.unwrap_or(history::DEFAULT_RECENT_LIMIT)).await?);
+            return Ok(());
+        }
+        Command::History { command: Some(HistoryCommand::Clear), .. } => {
+            let removed = history::clear().await?;
+            println!("Removed {} history entries", removed);
+            return Ok(());
+        }
+        Command::History { command: Some(HistoryCommand::Export(export)), .. } => {
+            match &export.out {
+                Some(path) => {
+                    let count = export::export_history(export.format(), path).await?;
+                    println!("Exported {} history entries to {}", count, path.display());
+                }
+                None => print!("{}", export::render_history(&history::all().await?, export.format())?),
+            }
+            return Ok(());
+        }
+        Command::Cache { command: CacheCommand::Clear } => {
+            let removed = crate::cache::clear().await?;
+            println!("Removed {} cached entries", removed);
+            return Ok(());
+        }
+        Command::Cache { command: CacheCommand::Stats } => {
+            let stats = crate::cache::stats().await?;
+            match output {
+                OutputFormat::Json | OutputFormat::Ndjson => println!("{}", serde_json::to_string(&stats)?),
+                OutputFormat::Text | OutputFormat::Markdown => print_cache_stats(&stats),
+            }
+            return Ok(());
+        }
+        Command::Cache { command: CacheCommand::Prune } => {
+            let report = maintenance::run_maintenance(&LookupContext::load()).await?;
+            match output {
+                OutputFormat::Json | OutputFormat::Ndjson => println!("{}", serde_json::to_string(&report)?),
+                OutputFormat::Text | OutputFormat::Markdown => print_maintenance_report(&report),
+            }
+            return Ok(());
+        }
+        Command::Config { command } => return show_config(command),
+        Command::Glossary { command: GlossaryCommand::Validate { files } } => return validate_glossaries(&files),
+        Command::Source { command: SourceCommand::Test { name, query } } => {
+            let query = Some(query.join(" ")).filter(|query| !query.is_empty());
+            return test_source(&name, query, options.lang, output).await;
+        }
+        Command::Compare { first, second } => return compare_words(&first, &second, &options, output, text).await,
+        Command::Fav { command } => return run_favorites(command, options.private).await,
+        Command::Repl => return Ok(repl::run(options, output, text).await?),
+        Command::Serve { socket } => return serve(socket, options).await,
+        Command::Client { socket, result, words } => {
+            // No context up front: loading one is the startup a server saves
+            let query = words.join(" ");
+            let saves = Saves { html_out: result.html_out.as_deref(), copy: result.copy || Config::load().copy_summary, ..saves };
+            let lookup = DirectLookup { options, quiet, network_wait };
+            client_lookup(&query, socket, lookup, output, text, saves, verbose).await;
+            return Ok(());
+        }
+        Command::Doctor => {
+            let health = lookup::health_check(&run_context(options.private)).await;
+            match output {
+                OutputFormat::Json | OutputFormat::Ndjson => println!("{}", serde_json::to_string(&health)?),
+                OutputFormat::Text | OutputFormat::Markdown => println!("{}", format::render_source_health(&health, &text)),
+            }
+            // Nothing answering at all points at the network, not a source
+            if !health.is_empty() && health.iter().all(|source| !source.reachable) {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Command::Completions { shell } => {
+            print!("{}", completion_script(shell));
+            return Ok(());
+        }
+        Command::Lookup(lookup) => lookup,
     };
 
-    if query == "--selection" {
-        match crate::clipboard::get_selected_text() {
-            Some(text) => {
-                println!("Looking up selected text: '{}'", text);
-                match lookup::lookup(&text).await {
-                    Ok(result) => print_lookup_result(&result),
+    // One context for the whole run, so batch and watch lookups share
+    // connections
+    let ctx = run_context(options.private);
+    let saves = Saves {
+        html_out: lookup.result.html_out.as_deref(),
+        copy: lookup.result.copy || ctx.config().copy_summary,
+        ..saves
+    };
+    match lookup.target() {
+        LookupTarget::Watch => watch_selection(&ctx, &options, output, text).await?,
+        LookupTarget::Batch(input) => run_batch(&ctx, input, &options, output, text, saves.anki_out).await?,
+        LookupTarget::WordOfTheDay => match lookup::word_of_the_day(&ctx).await {
+            Ok(result) => show_result(&result, output, text, saves, verbose),
+            Err(e) => {
+                error!("Failed to look up the word of the day: {}", e);
+                std::process::exit(exit_code(&e));
+            }
+        },
+        LookupTarget::FullArticle(title) => {
+            let lang = options.lang.clone().unwrap_or_else(|| ctx.config().lookup_language());
+            if let Err(e) = print_article(&title, &lang, output).await {
+                error!("Failed to fetch the article '{}': {}", title, e);
+                std::process::exit(exit_code(&e));
+            }
+        }
+        LookupTarget::Selection(selection) => match clipboard::get_text(selection, ctx.config().clipboard_backend()) {
+            Ok(selected) => {
+                let selected = match guard::check_selection(&selected, ctx.config().selection.max_length) {
+                    Ok(selected) => selected,
                     Err(e) => {
-                        eprintln!("[ERROR] [touchdictionary] [lookup] Failed to lookup '{}': {}", text, e);
-                        std::process::exit(1);
+                        eprintln!("{}", e);
+                        std::process::exit(exit_code(&e));
                     }
+                };
+                // Keep progress chatter out of markdown/json so they can be piped
+                if output == OutputFormat::Text && !quiet {
+                    println!("Looking up selected text: '{}'", selected);
                 }
+                let outcome = lookup_showing_progress(&ctx, &selected, &options, quiet, network_wait).await;
+                finish_lookup(&selected, outcome, output, text, saves, verbose);
             }
-            None => {
-                eprintln!("[ERROR] [touchdictionary] [clipboard] No text selected or could not access clipboard");
+            Err(e) => {
+                error!("{}", e);
                 std::process::exit(1);
             }
+        },
+        LookupTarget::Words(query) => {
+            if output == OutputFormat::Text && !quiet {
+                println!("Looking up: '{}'", query);
+            }
+            let outcome = lookup_showing_progress(&ctx, &query, &options, quiet, network_wait).await;
+            finish_lookup(&query, outcome, output, text, saves, verbose);
+        }
+    }
+
+    Ok(())
+}
+
+// `touchdictionary config`; the effective config without a subcommand
+fn show_config(command: Option<ConfigCommand>) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        Some(ConfigCommand::Path) => match config::default_path() {
+            Some(path) => println!("{}", path.display()),
+            None => return Err("No config directory on this system".into()),
+        },
+        None => print!("{}", toml::to_string_pretty(&Config::load())?),
+        Some(ConfigCommand::Validate { file }) => {
+            let path = file.or_else(config::default_path).ok_or("No config directory on this system")?;
+            match Config::load_from(&path) {
+                Ok(_) => println!("{}: no problems", path.display()),
+                Err(config::ConfigError::Invalid(problems)) => {
+                    for problem in &problems {
+                        println!("{}: {}", path.display(), problem);
+                    }
+                    return Err(format!("{} problems found", problems.len()).into());
+                }
+                Err(e) => return Err(format!("{}: {}", path.display(), e).into()),
+            }
         }
-    } else {
-        println!("Looking up: '{}'", query);
-        match lookup::lookup(&query).await {
-            Ok(result) => print_lookup_result(&result),
+    }
+    Ok(())
+}
+
+// Every malformed entry as `file:line: message`, like a compiler would
+// report it. Fails when there was anything to report.
+fn validate_glossaries(files: &[PathBuf]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut problems = 0;
+    for file in files {
+        match glossary::read(file) {
+            Ok((entries, found)) if found.is_empty() => {
+                println!("{}: {} entries, no problems", file.display(), entries.len());
+            }
+            Ok((_, found)) => {
+                for problem in &found {
+                    println!("{}:{}: {}", file.display(), problem.line, problem.message);
+                }
+                problems += found.len();
+            }
             Err(e) => {
-                eprintln!("[ERROR] [touchdictionary] [lookup] Failed to lookup '{}': {}", query, e);
-                std::process::exit(1);
+                println!("{}: {}", file.display(), e);
+                problems += 1;
             }
         }
     }
+    if problems > 0 {
+        return Err(format!("{} problems found", problems).into());
+    }
+    Ok(())
+}
+
+// `touchdictionary source test <name>`: one source asked directly, past the
+// cache, with what it returned printed as JSON
+async fn test_source(
+    name: &str,
+    query: Option<String>,
+    lang: Option<String>,
+    output: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let config = Config::load();
+    let registry = SourceRegistry::from_config(&config);
+    let sources: Vec<_> = registry.sources().chain(registry.fallback().into_iter().flat_map(|f| f.sources())).collect();
+    let Some(source) = sources.iter().find(|source| source.name() == name) else {
+        let names: Vec<&str> = sources.iter().map(|source| source.name()).collect();
+        return Err(format!("No enabled source named '{}'; try one of {}", name, names.join(", ")).into());
+    };
+    let query = query.unwrap_or_else(|| source.probe_query().to_string());
+    let lang = lang.unwrap_or_else(|| config.lookup_language());
+    let lang = if source.supports_language(&lang) { lang } else { language::DEFAULT_LANGUAGE.to_string() };
+
+    let started = Instant::now();
+    let result = source.fetch(&query, &lang).await;
+    let elapsed = started.elapsed().as_millis();
+    let result = result.map_err(|e| format!("{} failed after {}ms: {}", name, elapsed, e))?;
+    match output {
+        OutputFormat::Ndjson => println!("{}", serde_json::to_string(&result)?),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&result)?),
+        OutputFormat::Text | OutputFormat::Markdown => {
+            let found = if result.is_empty() { "nothing" } else { "this" };
+            println!("{} returned {} for '{}' in {}ms:", name, found, query, elapsed);
+            println!("{}", serde_json::to_string_pretty(&result)?);
+        }
+    }
+    Ok(())
+}
 
+// `touchdictionary compare`: both words looked up at once. Fails only when
+// neither could be.
+async fn compare_words(
+    first: &str,
+    second: &str,
+    options: &lookup::LookupOptions,
+    output: OutputFormat,
+    text: TextOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let compared = lookup::compare_with(&run_context(options.private), first, second, options).await;
+    match output {
+        OutputFormat::Json | OutputFormat::Ndjson => println!("{}", serde_json::to_string(&compared)?),
+        OutputFormat::Text | OutputFormat::Markdown => println!("{}", format::render_comparison(&compared, &text)),
+    }
+    if let (Some(e), Some(_)) = (&compared.first.error, &compared.second.error) {
+        std::process::exit(exit_code(e));
+    }
     Ok(())
 }
 
-fn print_lookup_result(result: &lookup::LookupResult) {
-    println!("\n=== TouchDictionary Result ===");
-    println!("Query: {}", result.query);
-    println!("Content Type: {:?}", result.content_type);
-    println!();
-
-    // Print definitions
-    if let Some(definitions) = &result.sections.definitions {
-        for section in definitions {
-            println!("[DEFINITION] Source: {}", section.source);
-            for def in &section.definitions {
-                // Only show part of speech if it's not empty
-                if let Some(pos) = &def.part_of_speech {
-                    if !pos.is_empty() {
-                        println!("  - ({}): {}", pos, def.definition);
-                    } else {
-                        println!("  - {}", def.definition);
+// Look up every new selection until interrupted
+async fn watch_selection(
+    ctx: &LookupContext,
+    options: &lookup::LookupOptions,
+    output: OutputFormat,
+    text: TextOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    println!("Watching selection, press Ctrl+C to stop");
+    let config = watch::WatchConfig { backend: ctx.config().clipboard_backend(), ..watch::WatchConfig::from_env() };
+    let mut selections = watch::spawn(config);
+    let mut throttle = notify::Throttle::default();
+
+    while let Some(event) = selections.recv().await {
+        let selected = match guard::check_selection(&event.text, ctx.config().selection.max_length) {
+            Ok(selected) => selected,
+            Err(e) => {
+                eprintln!("{}", e);
+                continue;
+            }
+        };
+        if output == OutputFormat::Text {
+            println!("Looking up selected text: '{}'", selected);
+        }
+        match lookup::lookup_with(ctx, &selected, options).await {
+            Ok(result) if result.found_nothing() => println!("No results found for '{}'", selected),
+            Ok(result) => {
+                print_lookup_result(&result, output, &text);
+                let config = ctx.config();
+                let notification = Notification::for_result(&result).filter(|_| config.notifications);
+                if let Some(notification) = notification {
+                    if throttle.allow(Instant::now(), config.notification_interval()) {
+                        // No window to open from the terminal, so no action
+                        tokio::spawn(async move {
+                            if let Err(e) = notify::send(&notification, None).await {
+                                warn!("{}", e);
+                            }
+                        });
                     }
-                } else {
-                    println!("  - {}", def.definition);
                 }
-                if let Some(example) = &def.example {
-                    println!("    Example: {}", example);
+            }
+            Err(e) => error!("Failed to lookup '{}': {}", selected, e),
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BatchInput {
+    File(String),
+    Stdin,
+}
+
+// Look up every line of a word list, a few at a time. Each result is printed
+// as it lands; failures are reported on their own line and don't stop the
+// batch. Only exits nonzero if nothing could be looked up at all.
+async fn run_batch(
+    ctx: &LookupContext,
+    input: BatchInput,
+    options: &lookup::LookupOptions,
+    output: OutputFormat,
+    text_options: TextOptions,
+    anki_out: Option<&Path>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let text = match input {
+        BatchInput::File(path) => tokio::fs::read_to_string(&path)
+            .await
+            .map_err(|e| format!("Failed to read batch file '{}': {}", path, e))?,
+        BatchInput::Stdin => {
+            let mut text = String::new();
+            tokio::io::stdin().read_to_string(&mut text).await?;
+            text
+        }
+    };
+
+    let queries = batch::read_queries(&text);
+    let total = queries.len();
+    let summary = batch::run(
+        queries,
+        batch::DEFAULT_CONCURRENCY,
+        |query| async move {
+            // Counted as failed, as a miss always has been
+            match lookup::lookup_with(ctx, &query, options).await {
+                Ok(result) if result.found_nothing() => Err(LookupError::NotFound { query }),
+                outcome => outcome,
+            }
+        },
+        |index, query, outcome| {
+            info!("{}/{} '{}'", index, total, query);
+            match outcome {
+                Ok(result) => show_result(result, output, text_options, Saves { anki_out, ..Saves::default() }, false),
+                Err(e) => print_batch_error(query, e, output),
+            }
+        },
+    )
+    .await;
+
+    info!("Batch finished: {} succeeded, {} failed", summary.succeeded, summary.failed);
+    if summary.all_failed() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn print_batch_error(query: &str, error: &LookupError, output: OutputFormat) {
+    match output {
+        OutputFormat::Ndjson => {
+            println!("{}", serde_json::json!({ "query": query, "error": error }));
+        }
+        _ => error!("Failed to lookup '{}': {}", query, error),
+    }
+}
+
+// `touchdictionary serve`: answer lookups on the socket until SIGTERM or
+// Ctrl+C
+#[cfg(unix)]
+async fn serve(socket: Option<PathBuf>, options: lookup::LookupOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let path = socket
+        .or_else(server::default_socket_path)
+        .ok_or("no runtime or cache directory to put the socket in; pass --socket")?;
+    let listener = server::bind(&path).map_err(|e| format!("Can't serve on {}: {}", path.display(), e))?;
+    let ctx = Arc::new(run_context(options.private));
+
+    let shutdown = CancellationToken::new();
+    let signalled = shutdown.clone();
+    tokio::spawn(async move {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = terminate.recv() => {}
+                    _ = tokio::signal::ctrl_c() => {}
                 }
             }
-            println!();
+            Err(e) => {
+                warn!("Can't listen for SIGTERM: {}", e);
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+        signalled.cancel();
+    });
+
+    eprintln!("Serving lookups on {}", path.display());
+    server::serve(ctx, listener, &path, options, shutdown).await?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+async fn serve(_socket: Option<PathBuf>, _options: lookup::LookupOptions) -> Result<(), Box<dyn std::error::Error>> {
+    Err("`serve` needs Unix domain sockets, which this system doesn't have".into())
+}
+
+// How `client` looks a word up itself when there's no server to ask
+struct DirectLookup {
+    options: lookup::LookupOptions,
+    quiet: bool,
+    network_wait: Option<Duration>,
+}
+
+// `touchdictionary client <word>`: ask the server, passing the lookup
+// flags along, or look the word up here when none answers
+async fn client_lookup(
+    query: &str,
+    socket: Option<PathBuf>,
+    direct: DirectLookup,
+    output: OutputFormat,
+    text: TextOptions,
+    saves: Saves<'_>,
+    verbose: bool,
+) {
+    #[cfg(unix)]
+    if let Some(path) = socket.or_else(server::default_socket_path) {
+        let request = server::Request { format: Some("json".to_string()), ..server::Request::new(query, &direct.options) };
+        match server::request(&path, &request).await {
+            Ok(Some(Response::Result { result })) => return finish_lookup(query, Ok(*result), output, text, saves, verbose),
+            Ok(Some(Response::Text { output })) => {
+                println!("{}", output);
+                return;
+            }
+            Ok(Some(Response::Error { error })) => {
+                error!("Lookup failed for '{}': {}", query, error.message);
+                std::process::exit(if error.kind == "not_found" { 2 } else { 1 });
+            }
+            Ok(None) => info!("No server on {}, looking up directly", path.display()),
+            Err(e) => warn!("Server on {} didn't answer ({}), looking up directly", path.display(), e),
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = socket;
+
+    let ctx = run_context(direct.options.private);
+    let outcome = lookup_showing_progress(&ctx, query, &direct.options, direct.quiet, direct.network_wait).await;
+    finish_lookup(query, outcome, output, text, saves, verbose);
+}
+
+// The context lookups in this run go through; --private can only turn
+// private mode on, never off
+fn run_context(private: bool) -> LookupContext {
+    let ctx = LookupContext::load();
+    if private {
+        ctx.set_private_mode(true);
+    }
+    ctx
+}
+
+// `touchdictionary fav <action> ...`
+async fn run_favorites(command: FavCommand, private: bool) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        FavCommand::Add { word, .. } if private || Config::load().private_mode => {
+            return Err(format!("'{}' not saved: private mode is on", word.join(" ")).into());
+        }
+        FavCommand::Add { word, note, tags } => {
+            let entry = favorites::add(&word.join(" "), note, tags).await?;
+            println!("Saved '{}'", entry.word);
+        }
+        FavCommand::Remove { word } => {
+            let word = word.join(" ");
+            if favorites::remove(&word).await? {
+                println!("Removed '{}'", word);
+            } else {
+                println!("'{}' is not in your favorites", word);
+            }
+        }
+        FavCommand::List { tag } => {
+            let entries = match tag {
+                Some(tag) => favorites::list_by_tag(&tag).await?,
+                None => favorites::list().await?,
+            };
+            print_favorites(&entries);
+        }
+        FavCommand::Export(export) => match &export.out {
+            Some(path) => {
+                let count = export::export_favorites(export.format(), path).await?;
+                println!("Exported {} favorites to {}", count, path.display());
+            }
+            None => print!("{}", export::render_favorites(&favorites::list().await?, export.format())?),
+        },
+    }
+
+    Ok(())
+}
+
+fn print_favorites(entries: &[favorites::FavoriteEntry]) {
+    if entries.is_empty() {
+        println!("No favorites saved yet");
+        return;
+    }
+
+    for entry in entries {
+        let mut line = format!("{}  {:<24}", local_time(entry.saved_at), entry.word);
+        if !entry.tags.is_empty() {
+            line.push_str(&format!(" [{}]", entry.tags.join(", ")));
+        }
+        if let Some(note) = &entry.note {
+            line.push_str(&format!("  {}", note));
+        }
+        println!("{}", line.trim_end());
+    }
+}
+
+fn local_time(timestamp: u64) -> String {
+    chrono::DateTime::from_timestamp(timestamp as i64, 0)
+        .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+fn print_history(entries: &[history::HistoryEntry]) {
+    if entries.is_empty() {
+        println!("No lookups recorded yet");
+        return;
+    }
+
+    for entry in entries {
+        println!("{}  {:<24} {:?}  [{}]", local_time(entry.timestamp), entry.display_query, entry.content_type, entry.sources.join(", "));
+    }
+}
+
+fn print_cache_stats(stats: &crate::cache::CacheStats) {
+    let lookups = stats.hits + stats.misses;
+    println!("Entries:        {}", stats.entries);
+    println!("Hits:           {}", stats.hits);
+    println!("Misses:         {}", stats.misses);
+    println!("Revalidations:  {}", stats.revalidations);
+    if lookups > 0 {
+        println!("Hit rate:       {:.0}%", stats.hits as f64 * 100.0 / lookups as f64);
+    }
+}
+
+fn print_maintenance_report(report: &maintenance::MaintenanceReport) {
+    println!("Cache entries removed:    {}", report.removed_cache_entries);
+    println!("Images removed:           {}", report.removed_images);
+    println!("History entries removed:  {}", report.removed_history_entries);
+    println!("Freed:                    {:.1} KiB", report.freed_bytes as f64 / 1024.0);
+    println!("Took:                     {:.2?}", report.duration);
+}
+
+// `lookup_with` behind a spinner on stderr saying how each source is doing,
+// when stderr is a terminal and `quiet` isn't set. The spinner is gone by
+// the time this returns. With `network_wait`, a lookup that couldn't reach
+// the network waits up to that long for it and runs once more, recorded in
+// the history as deferred.
+async fn lookup_showing_progress(
+    ctx: &LookupContext,
+    query: &str,
+    options: &lookup::LookupOptions,
+    quiet: bool,
+    network_wait: Option<Duration>,
+) -> Result<lookup::LookupResult, LookupError> {
+    let outcome = lookup_once_showing_progress(ctx, query, options, quiet).await;
+    let wait = match (&outcome, network_wait) {
+        (Err(e), Some(wait)) if e.is_network() && !options.offline => wait,
+        _ => return outcome,
+    };
+    eprintln!("Network unreachable, waiting up to {}s for it", wait.as_secs());
+    if !deferred::wait_for_network(ctx, wait).await {
+        return outcome;
+    }
+    let options = lookup::LookupOptions { deferred: true, ..options.clone() };
+    lookup_once_showing_progress(ctx, query, &options, quiet).await
+}
+
+async fn lookup_once_showing_progress(
+    ctx: &LookupContext,
+    query: &str,
+    options: &lookup::LookupOptions,
+    quiet: bool,
+) -> Result<lookup::LookupResult, LookupError> {
+    if !progress::enabled(quiet) {
+        return lookup::lookup_with(ctx, query, options).await;
+    }
+    let spinner = Spinner::start();
+    let outcome = lookup::lookup_with_progress(ctx, query, options, spinner.sink()).await;
+    spinner.finish().await;
+    outcome
+}
+
+// Where a result is written besides stdout: `--anki-out` appends a card,
+// `--html-out` saves a page and `--copy` puts a summary on the clipboard
+#[derive(Debug, Clone, Copy, Default)]
+struct Saves<'a> {
+    anki_out: Option<&'a Path>,
+    html_out: Option<&'a Path>,
+    copy: bool,
+}
+
+// Show a single lookup, exiting with `exit_code` when it found nothing
+// ("no results") or failed ("lookup failed"). Structured output still gets
+// the empty result so scripts can read its completeness.
+fn finish_lookup(
+    query: &str,
+    outcome: Result<lookup::LookupResult, LookupError>,
+    output: OutputFormat,
+    text: TextOptions,
+    saves: Saves,
+    verbose: bool,
+) {
+    match outcome {
+        Ok(result) if result.found_nothing() => {
+            let miss = LookupError::NotFound { query: query.to_string() };
+            match output {
+                OutputFormat::Json | OutputFormat::Ndjson => print_lookup_result(&result, output, &text),
+                OutputFormat::Text | OutputFormat::Markdown => eprintln!("{}", miss),
+            }
+            std::process::exit(exit_code(&miss));
+        }
+        Ok(result) => show_result(&result, output, text, saves, verbose),
+        Err(e) => {
+            error!("Lookup failed for '{}': {}", query, e);
+            std::process::exit(exit_code(&e));
+        }
+    }
+}
+
+fn show_result(
+    result: &lookup::LookupResult,
+    output: OutputFormat,
+    text: TextOptions,
+    saves: Saves,
+    verbose: bool,
+) {
+    print_lookup_result(result, output, &text);
+    if verbose {
+        // Keep structured output on stdout parseable
+        let table = format::render_source_statuses(&result.source_statuses);
+        if output == OutputFormat::Text {
+            println!("\n{}", table);
+        } else {
+            eprintln!("{}", table);
+        }
+    }
+    if let Some(path) = saves.anki_out {
+        if let Err(e) = format::append_anki(path, &[format::render_anki(result)]) {
+            error!("Failed to write Anki card to {}: {}", path.display(), e);
+        }
+    }
+    if let Some(path) = saves.html_out {
+        if let Err(e) = save_html(result, path) {
+            error!("Failed to save {}: {}", path.display(), e);
+        }
+    }
+    if let Some(summary) = format::one_line_summary(result).filter(|_| saves.copy) {
+        if let Err(e) = clipboard::set_clipboard_text(&summary) {
+            error!("Failed to copy the result: {}", e);
         }
     }
+}
+
+// The page for `--html-out`, with the article image inlined if the GUI has
+// already downloaded it
+fn save_html(result: &lookup::LookupResult, path: &Path) -> std::io::Result<()> {
+    let mut options = format::HtmlOptions::now();
+    let image_url = result.sections.wikipedia.as_ref().and_then(|wiki| wiki.image_url.as_deref());
+    if let (Some(url), Some(dir)) = (image_url, images::default_dir()) {
+        options.image = ImageCache::new(dir).cached_path(url).and_then(|cached| std::fs::read(cached).ok());
+    }
+    std::fs::write(path, format::render_html_with(result, &options))
+}
 
-    // Print Wikipedia section
-    if let Some(wiki) = &result.sections.wikipedia {
-        println!("[WIKIPEDIA] {}", wiki.title);
-        println!("{}", wiki.summary);
-        if !wiki.url.is_empty() {
-            println!("URL: {}", wiki.url);
+// Every section of an article, fetched a page at a time
+async fn print_article(title: &str, lang: &str, output: OutputFormat) -> Result<(), LookupError> {
+    let mut offset = 0;
+    let mut first: Option<ArticlePage> = None;
+    loop {
+        let page = wikipedia::get_article_sections(title, lang, offset, wikipedia::MAX_ARTICLE_PAGE_SIZE).await?;
+        let next_offset = page.next_offset;
+        match &mut first {
+            Some(first) => first.sections.extend(page.sections),
+            None => first = Some(page),
+        }
+        match next_offset {
+            Some(next) => offset = next,
+            None => break,
         }
-        println!();
     }
+    let Some(mut article) = first else {
+        return Ok(());
+    };
+    article.next_offset = None;
 
-    // Print thesaurus
-    if let Some(thesaurus) = &result.sections.thesaurus {
-        println!("[THESAURUS]");
-        if !thesaurus.synonyms.is_empty() {
-            println!("  Synonyms: {}", thesaurus.synonyms.join(", "));
+    match output {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&article).unwrap_or_default()),
+        OutputFormat::Ndjson => println!("{}", serde_json::to_string(&article).unwrap_or_default()),
+        OutputFormat::Text | OutputFormat::Markdown => {
+            for section in &article.sections {
+                println!("{} {}\n", "#".repeat(section.level as usize), section.heading);
+                if !section.text.is_empty() {
+                    println!("{}\n", section.text);
+                }
+            }
+            println!("{}", article.url);
         }
-        if !thesaurus.antonyms.is_empty() {
-            println!("  Antonyms: {}", thesaurus.antonyms.join(", "));
+    }
+    Ok(())
+}
+
+fn print_lookup_result(result: &lookup::LookupResult, output: OutputFormat, text: &TextOptions) {
+    println!("{}", format::render(result, output, text));
+}
+
+// The whole command tree, options and values included, e.g.
+// `source <(touchdictionary completions bash)`
+pub fn completion_script(shell: Shell) -> String {
+    let generator = match shell {
+        Shell::Bash => clap_complete::Shell::Bash,
+        Shell::Zsh => clap_complete::Shell::Zsh,
+        Shell::Fish => clap_complete::Shell::Fish,
+    };
+    let mut script = Vec::new();
+    clap_complete::generate(generator, &mut Cli::command(), "touchdictionary", &mut script);
+    String::from_utf8_lossy(&script).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::error::ErrorKind;
+
+    fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+        Cli::try_parse_from(std::iter::once("touchdictionary").chain(args.iter().copied()))
+    }
+
+    fn command(args: &[&str]) -> Command {
+        parse(args).unwrap().effective_command()
+    }
+
+    fn target(args: &[&str]) -> LookupTarget {
+        match command(args) {
+            Command::Lookup(lookup) => lookup.target(),
+            other => panic!("{:?} is not a lookup", other),
         }
-        if !thesaurus.related_terms.is_empty() {
-            println!("  Related: {}", thesaurus.related_terms.join(", "));
+    }
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_parse_plain_lookup() {
+        let cli = parse(&["serendipity"]).unwrap();
+        assert_eq!(cli.global, GlobalArgs::default());
+        assert_eq!(target(&["serendipity"]), LookupTarget::Words("serendipity".to_string()));
+
+        let cli = parse(&["--lang", "de", "New", "York", "--json", "--no-cache"]).unwrap();
+        assert_eq!(target(&["--lang", "de", "New", "York", "--json"]), LookupTarget::Words("New York".to_string()));
+        assert_eq!(cli.global.lang.as_deref(), Some("de"));
+        assert_eq!(cli.global.output_format(), OutputFormat::Json);
+        assert!(cli.global.no_cache && !cli.lookup_options().use_cache);
+        let cli = parse(&["--width", "72", "--no-color", "word"]).unwrap();
+        assert_eq!((cli.global.width, cli.global.no_color), (Some(72), true));
+        let cli = parse(&["word", "--quiet", "--verbose"]).unwrap();
+        assert!(cli.global.quiet && cli.global.verbose);
+        let wait = |args: &[&str]| parse(args).unwrap().global.network_wait();
+        assert_eq!(wait(&["--wait-for-network", "word"]), Some(Duration::from_secs(DEFAULT_NETWORK_WAIT_SECS)));
+        assert_eq!(wait(&["word", "--wait-for-network=5"]), Some(Duration::from_secs(5)));
+        assert_eq!(wait(&["word"]), None);
+        assert!(parse(&["--wait-for-network=soon", "word"]).is_err());
+        assert!(parse(&["--copy", "ephemeral"]).unwrap().lookup.result.copy);
+        assert!(parse(&["history", "--copy"]).is_err());
+        assert!(parse(&["--private", "word"]).unwrap().lookup_options().private);
+        assert!(parse(&["--width", "0", "word"]).is_err());
+        assert!(parse(&["--width", "wide", "word"]).is_err());
+        assert!(parse(&["set", "--all"]).unwrap().lookup_options().all_definitions);
+        assert!(parse(&["--compact", "set"]).unwrap().lookup_options().compact);
+        assert_eq!(parse(&["set"]).unwrap().lookup_options().pos_filter, None);
+        assert_eq!(
+            parse(&["--pos", "verb", "set", "--pos", "adj"]).unwrap().lookup_options().pos_filter,
+            Some(vec!["verb".to_string(), "adj".to_string()])
+        );
+
+        // A word that is also a command name
+        assert_eq!(target(&["lookup", "history"]), LookupTarget::Words("history".to_string()));
+        assert_eq!(target(&["lookup", "--selection"]), LookupTarget::Selection(Selection::Primary));
+        assert_eq!(target(&["--selection=clipboard"]), LookupTarget::Selection(Selection::Clipboard));
+        assert!(parse(&["--selection=secondary"]).is_err());
+        // Global options work after the command too
+        assert_eq!(parse(&["lookup", "word", "--lang", "fr"]).unwrap().lookup_options().lang.as_deref(), Some("fr"));
+
+        let cli = parse(&["serendipity", "--html-out", "serendipity.html"]).unwrap();
+        assert_eq!(cli.lookup.result.html_out, Some(PathBuf::from("serendipity.html")));
+        assert!(parse(&["--wotd", "--html-out", "wotd.html"]).is_ok());
+    }
+
+    #[test]
+    fn test_parse_subcommands() {
+        assert_eq!(command(&["history", "5"]), Command::History { limit: Some(5), command: None });
+        assert_eq!(command(&["history", "clear"]), Command::History { limit: None, command: Some(HistoryCommand::Clear) });
+        assert_eq!(command(&["cache", "clear"]), Command::Cache { command: CacheCommand::Clear });
+        assert_eq!(command(&["cache", "stats"]), Command::Cache { command: CacheCommand::Stats });
+        assert_eq!(command(&["cache", "prune"]), Command::Cache { command: CacheCommand::Prune });
+        assert_eq!(command(&["config"]), Command::Config { command: None });
+        assert_eq!(command(&["config", "path"]), Command::Config { command: Some(ConfigCommand::Path) });
+        assert_eq!(command(&["config", "validate"]), Command::Config { command: Some(ConfigCommand::Validate { file: None }) });
+        assert_eq!(
+            command(&["config", "validate", "my.toml"]),
+            Command::Config { command: Some(ConfigCommand::Validate { file: Some(PathBuf::from("my.toml")) }) }
+        );
+        assert_eq!(
+            command(&["fav", "add", "petrichor", "--tag", "nature", "--note", "rain smell"]),
+            Command::Fav {
+                command: FavCommand::Add {
+                    word: vec!["petrichor".to_string()],
+                    note: Some("rain smell".to_string()),
+                    tags: vec!["nature".to_string()],
+                }
+            }
+        );
+        assert_eq!(
+            command(&["fav", "list", "--tag", "nature"]),
+            Command::Fav { command: FavCommand::List { tag: Some("nature".to_string()) } }
+        );
+        assert_eq!(
+            command(&["fav", "remove", "petrichor"]),
+            Command::Fav { command: FavCommand::Remove { word: vec!["petrichor".to_string()] } }
+        );
+        assert_eq!(command(&["repl"]), Command::Repl);
+        assert_eq!(command(&["serve"]), Command::Serve { socket: None });
+        assert_eq!(
+            command(&["client", "--socket", "/tmp/td.sock", "New", "York"]),
+            Command::Client {
+                socket: Some(PathBuf::from("/tmp/td.sock")),
+                result: ResultArgs::default(),
+                words: vec!["New".to_string(), "York".to_string()],
+            }
+        );
+        assert!(parse(&["serve", "word"]).is_err());
+        assert!(parse(&["client"]).is_err());
+        assert!(parse(&["--socket", "/tmp/td.sock", "word"]).is_err());
+        assert_eq!(command(&["doctor"]), Command::Doctor);
+        assert!(parse(&["doctor", "now"]).is_err());
+        // The word itself is still a `lookup` away
+        assert_eq!(target(&["lookup", "doctor"]), LookupTarget::Words("doctor".to_string()));
+        assert_eq!(command(&["-i", "--lang", "de"]), Command::Repl);
+        assert_eq!(command(&["completions", "fish"]), Command::Completions { shell: Shell::Fish });
+        assert_eq!(
+            command(&["glossary", "validate", "team.toml", "terms.csv"]),
+            Command::Glossary {
+                command: GlossaryCommand::Validate { files: vec![PathBuf::from("team.toml"), PathBuf::from("terms.csv")] }
+            }
+        );
+
+        let export = |args: &[&str]| match command(args) {
+            Command::History { command: Some(HistoryCommand::Export(export)), .. } => export,
+            Command::Fav { command: FavCommand::Export(export) } => export,
+            other => panic!("{:?} is not an export", other),
+        };
+        let history = export(&["history", "export", "--format", "csv", "--out", "h.csv"]);
+        assert_eq!((history.format(), history.out), (ExportFormat::Csv, Some(PathBuf::from("h.csv"))));
+        assert_eq!(export(&["fav", "export", "--out", "favs.CSV"]).format(), ExportFormat::Csv);
+        assert_eq!(export(&["fav", "export"]), Export::default());
+        assert_eq!(export(&["fav", "export"]).format(), ExportFormat::Json);
+    }
+
+    #[test]
+    fn test_completion_scripts() {
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish] {
+            let script = completion_script(shell);
+            assert!(script.contains("repl"), "{:?}", shell);
+            assert!(script.contains("full-article"), "{:?}", shell);
         }
-        println!();
     }
 
-    println!("========================");
+    #[test]
+    fn test_parse_legacy_flags_and_help() {
+        assert_eq!(command(&["--history"]), Command::History { limit: None, command: None });
+        assert_eq!(command(&["--history", "3"]), Command::History { limit: Some(3), command: None });
+        assert_eq!(command(&["--clear-history"]), Command::History { limit: None, command: Some(HistoryCommand::Clear) });
+        assert_eq!(command(&["--clear-cache"]), Command::Cache { command: CacheCommand::Clear });
+        assert_eq!(target(&["--full-article", "Photon"]), LookupTarget::FullArticle("Photon".to_string()));
+        assert_eq!(target(&["--batch", "words.txt"]), LookupTarget::Batch(BatchInput::File("words.txt".to_string())));
+        assert_eq!(target(&["--stdin"]), LookupTarget::Batch(BatchInput::Stdin));
+
+        assert_eq!(parse(&[]).unwrap_err().kind(), ErrorKind::DisplayHelpOnMissingArgumentOrSubcommand);
+        assert_eq!(parse(&["fav", "--help"]).unwrap_err().kind(), ErrorKind::DisplayHelp);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse(&["--lang"]).is_err());
+        assert!(parse(&["--lang", "de"]).is_err());
+        assert!(parse(&["--format", "yaml", "word"]).is_err());
+        assert!(parse(&["--frobnicate", "word"]).is_err());
+        assert!(parse(&["--selection", "word"]).is_err());
+        assert!(parse(&["history", "--wotd"]).is_err());
+        assert!(parse(&["--full-article", "--wotd"]).is_err());
+        assert!(parse(&["--full-article"]).is_err());
+        assert!(parse(&["--history", "word"]).is_err());
+        assert!(parse(&["word", "--tag", "x"]).is_err());
+        assert!(parse(&["fav", "add"]).is_err());
+        assert!(parse(&["cache"]).is_err());
+        assert!(parse(&["repl", "word"]).is_err());
+        assert!(parse(&["word", "--format", "csv"]).is_err());
+        assert!(parse(&["history", "--out", "h.csv"]).is_err());
+        assert!(parse(&["fav", "export", "--format", "markdown"]).is_err());
+        assert!(parse(&["completions", "powershell"]).is_err());
+        assert!(parse(&["glossary", "validate"]).is_err());
+        assert_eq!(
+            command(&["source", "test", "company", "topic", "branch"]),
+            Command::Source {
+                command: SourceCommand::Test {
+                    name: "company".to_string(),
+                    query: vec!["topic".to_string(), "branch".to_string()],
+                }
+            }
+        );
+        assert_eq!(
+            command(&["source", "test", "company"]),
+            Command::Source { command: SourceCommand::Test { name: "company".to_string(), query: Vec::new() } }
+        );
+        assert!(parse(&["source", "company"]).is_err());
+        assert_eq!(command(&["compare", "affect", "effect"]), Command::Compare { first: "affect".to_string(), second: "effect".to_string() });
+        assert!(parse(&["compare", "affect"]).is_err());
+        assert!(parse(&["--batch", "words.txt", "--html-out", "out.html"]).is_err());
+        assert!(parse(&["history", "--html-out", "out.html"]).is_err());
+        assert!(parse(&["word", "--html-out"]).is_err());
+    }
 }
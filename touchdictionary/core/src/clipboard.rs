@@ -1,50 +1,381 @@
-use std::process::Command;
+use std::fmt;
+#[cfg(all(unix, not(target_os = "macos")))]
+use std::io;
+#[cfg(all(unix, not(target_os = "macos")))]
+use std::process::{Command, Output};
+use std::str::FromStr;
 
-// Get selected text from primary clipboard (Linux/X11)
-pub fn get_selected_text() -> Option<String> {
-    // Try Wayland first
-    if let Ok(output) = Command::new("wl-paste")
-        .args(&["--primary", "--no-newline"])
-        .output()
-    {
-        if output.status.success() {
-            let text = String::from_utf8_lossy(&output.stdout);
-            let trimmed = text.trim();
-            if !trimmed.is_empty() {
-                return Some(trimmed.to_string());
+use serde::{Deserialize, Serialize};
+#[cfg(all(unix, not(target_os = "macos")))]
+use tracing::debug;
+use tracing::warn;
+
+// Pins the backend, overriding `clipboard_backend` in the config
+pub const BACKEND_ENV: &str = "TOUCHDICTIONARY_CLIPBOARD_BACKEND";
+
+// Why the selection couldn't be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClipboardError {
+    // No way to reach a selection on this system: no Wayland or X display
+    // and none of wl-paste/xsel/xclip installed
+    NoBackend,
+    // A backend answered but nothing is selected
+    Empty,
+    NotUtf8,
+    // Names the backend and what went wrong with it
+    BackendFailed(String),
+}
+
+impl fmt::Display for ClipboardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClipboardError::NoBackend => {
+                write!(f, "No clipboard backend available (need a Wayland or X display, or wl-paste, xsel or xclip)")
+            }
+            ClipboardError::Empty => write!(f, "Nothing is selected"),
+            ClipboardError::NotUtf8 => write!(f, "Selection is not UTF-8 text"),
+            ClipboardError::BackendFailed(message) => write!(f, "Clipboard backend failed: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ClipboardError {}
+
+impl ClipboardError {
+    // When every backend fails, report the one that says the most: an empty
+    // selection beats a broken backend, which beats a missing one
+    #[cfg_attr(not(all(unix, not(target_os = "macos"))), allow(dead_code))]
+    fn rank(&self) -> u8 {
+        match self {
+            ClipboardError::NoBackend => 0,
+            ClipboardError::BackendFailed(_) => 1,
+            ClipboardError::NotUtf8 => 2,
+            ClipboardError::Empty => 3,
+        }
+    }
+}
+
+// Why text couldn't be put on the clipboard
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClipboardWriteError {
+    // No clipboard to write to, as in a session without a display
+    NoBackend,
+    BackendFailed(String),
+}
+
+impl fmt::Display for ClipboardWriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClipboardWriteError::NoBackend => write!(f, "No clipboard available"),
+            ClipboardWriteError::BackendFailed(message) => write!(f, "Couldn't write the clipboard: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ClipboardWriteError {}
+
+// Which selection to read. PRIMARY is whatever is highlighted; CLIPBOARD is
+// what was last copied, and the only one some Wayland apps fill in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Selection {
+    #[default]
+    Primary,
+    Clipboard,
+    // PRIMARY, or CLIPBOARD when nothing is highlighted
+    Auto,
+}
+
+impl FromStr for Selection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "primary" => Ok(Selection::Primary),
+            "clipboard" => Ok(Selection::Clipboard),
+            "auto" => Ok(Selection::Auto),
+            other => Err(format!("unknown selection '{}' (expected primary, clipboard or auto)", other)),
+        }
+    }
+}
+
+// Ways of reading a selection, in the order they're tried when none is
+// pinned. Native asks the Wayland compositor or X server directly. Writes
+// don't go through these.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClipboardBackend {
+    Native,
+    WlPaste,
+    Xsel,
+    Xclip,
+}
+
+impl ClipboardBackend {
+    pub const ALL: [ClipboardBackend; 4] =
+        [ClipboardBackend::Native, ClipboardBackend::WlPaste, ClipboardBackend::Xsel, ClipboardBackend::Xclip];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ClipboardBackend::Native => "native",
+            ClipboardBackend::WlPaste => "wl-paste",
+            ClipboardBackend::Xsel => "xsel",
+            ClipboardBackend::Xclip => "xclip",
+        }
+    }
+
+    // Program and arguments that print `selection`; None for Native
+    #[cfg_attr(not(all(unix, not(target_os = "macos"))), allow(dead_code))]
+    fn command(self, selection: Selection) -> Option<(&'static str, &'static [&'static str])> {
+        let clipboard = selection == Selection::Clipboard;
+        match self {
+            ClipboardBackend::Native => None,
+            ClipboardBackend::WlPaste if clipboard => Some(("wl-paste", &["--no-newline"])),
+            ClipboardBackend::WlPaste => Some(("wl-paste", &["--primary", "--no-newline"])),
+            ClipboardBackend::Xsel if clipboard => Some(("xsel", &["-o", "-b"])),
+            ClipboardBackend::Xsel => Some(("xsel", &["-o", "-p"])),
+            ClipboardBackend::Xclip if clipboard => Some(("xclip", &["-o", "-selection", "clipboard"])),
+            ClipboardBackend::Xclip => Some(("xclip", &["-o", "-selection", "primary"])),
+        }
+    }
+}
+
+impl FromStr for ClipboardBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ClipboardBackend::ALL
+            .into_iter()
+            .find(|backend| backend.name() == s)
+            .ok_or_else(|| format!("unknown clipboard backend '{}' (expected native, wl-paste, xsel or xclip)", s))
+    }
+}
+
+// Backend pinned through `TOUCHDICTIONARY_CLIPBOARD_BACKEND`, if any
+pub fn backend_from_env() -> Option<ClipboardBackend> {
+    let value = std::env::var(BACKEND_ENV).ok()?;
+    match value.parse() {
+        Ok(backend) => Some(backend),
+        Err(e) => {
+            warn!("Ignoring {}: {}", BACKEND_ENV, e);
+            None
+        }
+    }
+}
+
+// Runs the command-line backends; swapped out in tests
+#[cfg(all(unix, not(target_os = "macos")))]
+pub trait CommandRunner {
+    fn output(&self, program: &str, args: &[&str]) -> io::Result<Output>;
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+pub struct SystemRunner;
+
+#[cfg(all(unix, not(target_os = "macos")))]
+impl CommandRunner for SystemRunner {
+    fn output(&self, program: &str, args: &[&str]) -> io::Result<Output> {
+        Command::new(program).args(args).output()
+    }
+}
+
+// Highlighted text, through the backend pinned in the environment or
+// whichever one works
+pub fn get_selected_text() -> Result<String, ClipboardError> {
+    get_text(Selection::Primary, backend_from_env())
+}
+
+// Last copied text, the same way
+pub fn get_clipboard_text() -> Result<String, ClipboardError> {
+    get_text(Selection::Clipboard, backend_from_env())
+}
+
+// Read `selection` with `backend`, or with each backend in turn when it's
+// None: the compositor or X server directly first, the command-line tools
+// as a last resort. Once the direct read finds the selection empty, so
+// would the tools, and they aren't run.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn get_text(selection: Selection, backend: Option<ClipboardBackend>) -> Result<String, ClipboardError> {
+    read_with(selection, backend, &SystemRunner, native::read_selection)
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn read_with(
+    selection: Selection,
+    pinned: Option<ClipboardBackend>,
+    runner: &dyn CommandRunner,
+    native: impl Fn(Selection) -> Result<String, ClipboardError>,
+) -> Result<String, ClipboardError> {
+    let selections: &[Selection] = match selection {
+        Selection::Auto => &[Selection::Primary, Selection::Clipboard],
+        _ => std::slice::from_ref(&selection),
+    };
+    let backends = match pinned {
+        Some(backend) => vec![backend],
+        None => ClipboardBackend::ALL.to_vec(),
+    };
+
+    let mut best = ClipboardError::NoBackend;
+    for &selection in selections {
+        for &backend in &backends {
+            let result = match backend.command(selection) {
+                Some((program, args)) => run_tool(runner, program, args),
+                None => native(selection),
+            };
+            let e = match result {
+                Ok(text) => {
+                    debug!("Read {:?} with {}", selection, backend.name());
+                    return Ok(text);
+                }
+                Err(e) => e,
+            };
+
+            debug!("{} couldn't read {:?}: {}", backend.name(), selection, e);
+            let e = match e {
+                ClipboardError::BackendFailed(message) => {
+                    ClipboardError::BackendFailed(format!("{}: {}", backend.name(), message))
+                }
+                // Asked for by name, so say it's missing rather than that
+                // nothing could be found
+                ClipboardError::NoBackend if pinned.is_some() => {
+                    ClipboardError::BackendFailed(format!("{}: not available on this system", backend.name()))
+                }
+                e => e,
+            };
+            if !matches!(e, ClipboardError::NoBackend | ClipboardError::Empty) {
+                warn!("{}", e);
+            }
+            if e.rank() > best.rank() {
+                best = e;
+            }
+            if backend == ClipboardBackend::Native && best == ClipboardError::Empty {
+                break;
             }
         }
     }
 
-    // Fallback to X11
-    if let Ok(output) = Command::new("xsel")
-        .args(&["-o", "-p"])
-        .output()
-    {
-        if output.status.success() {
-            let text = String::from_utf8_lossy(&output.stdout);
-            let trimmed = text.trim();
-            if !trimmed.is_empty() {
-                return Some(trimmed.to_string());
+    Err(best)
+}
+
+// Selections aren't reachable this way on Windows or macOS
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+pub fn get_text(_selection: Selection, _backend: Option<ClipboardBackend>) -> Result<String, ClipboardError> {
+    Err(ClipboardError::NoBackend)
+}
+
+// Put `text` on the CLIPBOARD selection (what Ctrl+V pastes), never
+// PRIMARY. On X11 the text is handed to the clipboard manager once the
+// clipboard is dropped, so it outlives a CLI run when one is running.
+pub fn set_clipboard_text(text: &str) -> Result<(), ClipboardWriteError> {
+    let write_error = |e| match e {
+        arboard::Error::ClipboardNotSupported => ClipboardWriteError::NoBackend,
+        e => ClipboardWriteError::BackendFailed(e.to_string()),
+    };
+    let mut clipboard = arboard::Clipboard::new().map_err(write_error)?;
+    clipboard.set_text(text).map_err(write_error)
+}
+
+#[cfg_attr(not(all(unix, not(target_os = "macos"))), allow(dead_code))]
+fn text_from_bytes(bytes: Vec<u8>) -> Result<String, ClipboardError> {
+    let text = String::from_utf8(bytes).map_err(|_| ClipboardError::NotUtf8)?;
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        Err(ClipboardError::Empty)
+    } else {
+        Ok(trimmed.to_string())
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn run_tool(runner: &dyn CommandRunner, program: &str, args: &[&str]) -> Result<String, ClipboardError> {
+    let output = match runner.output(program, args) {
+        Ok(output) => output,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Err(ClipboardError::NoBackend),
+        Err(e) => return Err(ClipboardError::BackendFailed(e.to_string())),
+    };
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        // wl-paste exits nonzero when there's simply no selection
+        if stderr.contains("Nothing is copied") || stderr.contains("No selection") {
+            return Err(ClipboardError::Empty);
+        }
+        return Err(ClipboardError::BackendFailed(format!("exited with {}: {}", output.status, stderr)));
+    }
+
+    text_from_bytes(output.stdout)
+}
+
+// Reads a selection without running anything: from the Wayland compositor
+// when there is one, else from the X server. Systems with neither fall
+// through to the command-line tools.
+#[cfg(all(unix, not(target_os = "macos")))]
+mod native {
+    use std::io::Read;
+    use std::time::Duration;
+
+    use wl_clipboard_rs::paste::{self, ClipboardType, MimeType, Seat};
+    use x11_clipboard::Clipboard;
+
+    use super::{text_from_bytes, ClipboardError, Selection};
+
+    // How long the X selection owner gets to answer before giving up
+    const CONVERT_TIMEOUT: Duration = Duration::from_millis(500);
+
+    pub(super) fn read_selection(selection: Selection) -> Result<String, ClipboardError> {
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            match wayland(selection) {
+                // GNOME has no protocol for reading selections; XWayland
+                // may still have them
+                Err(ClipboardError::NoBackend) => {}
+                result => return result,
             }
         }
+        if std::env::var_os("DISPLAY").is_some() {
+            return x11(selection);
+        }
+        Err(ClipboardError::NoBackend)
     }
 
-    // Try xclip as another fallback
-    if let Ok(output) = Command::new("xclip")
-        .args(&["-o", "-selection", "primary"])
-        .output()
-    {
-        if output.status.success() {
-            let text = String::from_utf8_lossy(&output.stdout);
-            let trimmed = text.trim();
-            if !trimmed.is_empty() {
-                return Some(trimmed.to_string());
+    fn wayland(selection: Selection) -> Result<String, ClipboardError> {
+        let clipboard = match selection {
+            Selection::Clipboard => ClipboardType::Regular,
+            _ => ClipboardType::Primary,
+        };
+        match paste::get_contents(clipboard, Seat::Unspecified, MimeType::Text) {
+            Ok((mut pipe, _)) => {
+                let mut bytes = Vec::new();
+                pipe.read_to_end(&mut bytes).map_err(|e| ClipboardError::BackendFailed(e.to_string()))?;
+                text_from_bytes(bytes)
             }
+            Err(paste::Error::ClipboardEmpty | paste::Error::NoMimeType) => Err(ClipboardError::Empty),
+            Err(
+                paste::Error::NoSeats
+                | paste::Error::SocketOpenError(_)
+                | paste::Error::WaylandConnection(_)
+                | paste::Error::MissingProtocol { .. }
+                | paste::Error::PrimarySelectionUnsupported,
+            ) => Err(ClipboardError::NoBackend),
+            Err(e) => Err(ClipboardError::BackendFailed(e.to_string())),
         }
     }
 
-    None
+    fn x11(selection: Selection) -> Result<String, ClipboardError> {
+        let clipboard =
+            Clipboard::new().map_err(|e| ClipboardError::BackendFailed(format!("cannot open X display: {}", e)))?;
+        let atoms = &clipboard.getter.atoms;
+        let name = match selection {
+            Selection::Clipboard => atoms.clipboard,
+            _ => atoms.primary,
+        };
+        match clipboard.load(name, atoms.utf8_string, atoms.property, CONVERT_TIMEOUT) {
+            // No owner, or one that can't produce text, gives nothing back
+            Ok(bytes) => text_from_bytes(bytes),
+            Err(x11_clipboard::error::Error::Timeout) => {
+                Err(ClipboardError::BackendFailed("selection owner did not respond".to_string()))
+            }
+            Err(e) => Err(ClipboardError::BackendFailed(e.to_string())),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -59,4 +390,105 @@ mod tests {
         // We can't assert specific values since clipboard content varies
         println!("Selected text: {:?}", result);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_text_from_bytes() {
+        assert_eq!(text_from_bytes(b"  serendipity\n".to_vec()), Ok("serendipity".to_string()));
+        assert_eq!(text_from_bytes(b" \n".to_vec()), Err(ClipboardError::Empty));
+        assert_eq!(text_from_bytes(vec![0xff, 0xfe]), Err(ClipboardError::NotUtf8));
+    }
+
+    #[test]
+    fn test_parse_selection_and_backend() {
+        assert_eq!("clipboard".parse(), Ok(Selection::Clipboard));
+        assert_eq!("wl-paste".parse(), Ok(ClipboardBackend::WlPaste));
+        assert!("pbpaste".parse::<ClipboardBackend>().is_err());
+        let backend: ClipboardBackend = serde_json::from_str("\"xclip\"").unwrap();
+        assert_eq!(backend, ClipboardBackend::Xclip);
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    mod fallback {
+        use std::cell::RefCell;
+        use std::collections::HashMap;
+        use std::os::unix::process::ExitStatusExt;
+        use std::process::ExitStatus;
+
+        use super::*;
+
+        // Canned answers per program; programs not listed aren't installed
+        #[derive(Default)]
+        struct MockRunner {
+            answers: HashMap<&'static str, (i32, &'static str, &'static str)>,
+            calls: RefCell<Vec<String>>,
+        }
+
+        impl MockRunner {
+            fn answer(mut self, program: &'static str, code: i32, stdout: &'static str, stderr: &'static str) -> Self {
+                self.answers.insert(program, (code, stdout, stderr));
+                self
+            }
+        }
+
+        impl CommandRunner for MockRunner {
+            fn output(&self, program: &str, args: &[&str]) -> io::Result<Output> {
+                self.calls.borrow_mut().push(format!("{} {}", program, args.join(" ")));
+                let (code, stdout, stderr) = self.answers.get(program).ok_or(io::ErrorKind::NotFound)?;
+                Ok(Output {
+                    status: ExitStatus::from_raw(code << 8),
+                    stdout: stdout.as_bytes().to_vec(),
+                    stderr: stderr.as_bytes().to_vec(),
+                })
+            }
+        }
+
+        fn no_display(_: Selection) -> Result<String, ClipboardError> {
+            Err(ClipboardError::NoBackend)
+        }
+
+        #[test]
+        fn test_falls_through_backends_in_order() {
+            let runner = MockRunner::default().answer("xsel", 1, "", "can't open display").answer("xclip", 0, "petrichor\n", "");
+            assert_eq!(read_with(Selection::Primary, None, &runner, no_display), Ok("petrichor".to_string()));
+            assert_eq!(
+                *runner.calls.borrow(),
+                vec!["wl-paste --primary --no-newline", "xsel -o -p", "xclip -o -selection primary"]
+            );
+        }
+
+        #[test]
+        fn test_pinned_backend_is_the_only_one_tried() {
+            let runner = MockRunner::default().answer("xclip", 0, "petrichor", "");
+            let result = read_with(Selection::Primary, Some(ClipboardBackend::Xsel), &runner, no_display);
+            assert_eq!(result, Err(ClipboardError::BackendFailed("xsel: not available on this system".to_string())));
+            assert_eq!(*runner.calls.borrow(), vec!["xsel -o -p"]);
+
+            let runner = MockRunner::default().answer("xsel", 1, "", "can't open display");
+            let result = read_with(Selection::Primary, Some(ClipboardBackend::Xsel), &runner, no_display);
+            assert_eq!(result, Err(ClipboardError::BackendFailed("xsel: exited with exit status: 1: can't open display".to_string())));
+        }
+
+        #[test]
+        fn test_auto_falls_back_to_clipboard() {
+            let runner = MockRunner::default().answer("wl-paste", 1, "", "No selection");
+            let native = |selection| match selection {
+                Selection::Clipboard => Ok("ephemeral".to_string()),
+                _ => Err(ClipboardError::Empty),
+            };
+            assert_eq!(read_with(Selection::Auto, None, &runner, native), Ok("ephemeral".to_string()));
+            assert_eq!(read_with(Selection::Primary, None, &runner, native), Err(ClipboardError::Empty));
+            // Nothing selected natively means the tools aren't asked
+            assert!(runner.calls.borrow().is_empty());
+
+            let result = read_with(Selection::Clipboard, Some(ClipboardBackend::WlPaste), &runner, native);
+            assert_eq!(result, Err(ClipboardError::Empty));
+            assert_eq!(*runner.calls.borrow(), vec!["wl-paste --no-newline".to_string()]);
+        }
+    }
+
+    #[test]
+    fn test_rank_prefers_empty_over_missing_backend() {
+        assert!(ClipboardError::Empty.rank() > ClipboardError::BackendFailed("x".into()).rank());
+        assert!(ClipboardError::BackendFailed("x".into()).rank() > ClipboardError::NoBackend.rank());
+    }
+}
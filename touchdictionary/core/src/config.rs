@@ -0,0 +1,950 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::clipboard::{self, ClipboardBackend};
+use crate::deferred;
+use crate::format;
+use crate::guard;
+use crate::http::{self, HttpConfig};
+use crate::language;
+use crate::maintenance;
+use crate::notify;
+use crate::passage;
+use crate::pattern;
+use crate::ranking::{self, DefinitionLimits};
+use crate::ratelimit;
+use crate::session;
+use crate::sources::{dictionary, external, urban_dictionary, wikidata, wikipedia};
+
+// User settings from `$XDG_CONFIG_HOME/touchdictionary/config.toml`. Every
+// field is optional in the file; missing ones take their defaults.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    // Language used when a lookup doesn't ask for one; the system locale's
+    // when unset, or English if that isn't supported
+    pub language: Option<String>,
+    // Look a query up in its own language when it's clearly not in
+    // `language`, e.g. a German word selected in an English session
+    pub auto_detect_language: bool,
+    // Per-source switches keyed by source name; sources not listed are on,
+    // except the opt-in ones
+    pub sources: BTreeMap<String, bool>,
+    pub http: HttpSettings,
+    pub network: NetworkSettings,
+    pub dictionary: DictionarySettings,
+    pub wikipedia: WikipediaSettings,
+    pub wikidata: WikidataSettings,
+    // Requests per second per upstream API; 0 means unlimited
+    pub rate_limits: BTreeMap<String, f64>,
+    // Also write the GUI's logs to a rotating file under the data dir
+    pub log_to_file: bool,
+    // Notify about selections looked up in the background, at most once
+    // every `notification_interval_secs`
+    pub notifications: bool,
+    pub notification_interval_secs: u64,
+    // Definitions kept from each source and from all of them together, the
+    // best-ranked first; 0 means no limit
+    pub max_definitions_per_source: usize,
+    pub max_definitions_total: usize,
+    // Merge near-identical definitions from different sources into one
+    pub dedupe_definitions: bool,
+    // Put a one-line summary of each command-line lookup on the clipboard,
+    // as `--copy` does
+    pub copy_summary: bool,
+    pub offline: OfflineSettings,
+    // Start in private mode: only local sources and the cache are read, and
+    // nothing is cached or recorded. The CLI and the GUI can switch it for
+    // a session.
+    pub private_mode: bool,
+    pub glossary: GlossarySettings,
+    // Language to translate definitions into; no translation when unset
+    pub translate_to: Option<String>,
+    pub translate: TranslateSettings,
+    pub merriam_webster: MerriamWebsterSettings,
+    pub window: WindowSettings,
+    // Read selections only with this backend ("native", "wl-paste", "xsel"
+    // or "xclip") instead of trying each in turn
+    pub clipboard_backend: Option<ClipboardBackend>,
+    pub selection: SelectionSettings,
+    pub urban_dictionary: UrbanDictionarySettings,
+    pub passage: PassageSettings,
+    pub session: SessionSettings,
+    pub pattern: PatternSettings,
+    pub maintenance: MaintenanceSettings,
+    pub deferred: DeferredSettings,
+    // Commands asked like any other source, as `[[external_sources]]`
+    pub external_sources: Vec<ExternalSourceSettings>,
+    // The system's language as the GUI got it from the platform, which
+    // comes before the locale variables. Never read from the file.
+    #[serde(skip)]
+    pub system_language: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpSettings {
+    pub connect_timeout_ms: u64,
+    pub request_timeout_ms: u64,
+    // Upstream requests in flight at once across all sources; 0 means no
+    // limit
+    pub max_concurrent_requests: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkSettings {
+    // Proxy for every request, e.g. "http://proxy.example:3128"; when unset
+    // HTTP_PROXY, HTTPS_PROXY and NO_PROXY apply
+    pub proxy: Option<String>,
+    // User-Agent sent to every source; the crate name and version when unset
+    pub user_agent: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DictionarySettings {
+    // Base URLs of the Free Dictionary API and any mirrors, tried in order
+    pub endpoints: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WikipediaSettings {
+    // Related articles fetched alongside the summary; 0 turns them off
+    pub max_related: usize,
+    // Base URL of the REST API, with `{lang}` standing for the edition
+    pub endpoint: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WikidataSettings {
+    // The action API entity facts are read from
+    pub endpoint: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OfflineSettings {
+    // WordNet SQLite database; `wordnet.sqlite` in the data dir when unset
+    pub database: Option<PathBuf>,
+    // Answer every lookup from the database and never touch the network
+    pub offline_only: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GlossarySettings {
+    // Directory of `.toml` and `.csv` glossaries; `glossaries` in the config
+    // dir when unset
+    pub directory: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TranslateSettings {
+    // Base URL of a LibreTranslate server, e.g. `http://localhost:5000`
+    pub endpoint: Option<String>,
+    pub api_key: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MerriamWebsterSettings {
+    // Collegiate Dictionary key; `MW_API_KEY` is used when unset
+    pub api_key: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UrbanDictionarySettings {
+    // Best-voted entries shown, and the votes an entry needs to be shown
+    pub max_results: usize,
+    pub min_thumbs_up: u32,
+    // Ask alongside the dictionaries instead of only when they find nothing
+    pub always_include: bool,
+}
+
+// A definition service run as a command, e.g.
+//
+//     [[external_sources]]
+//     name = "company"
+//     command = "/usr/local/bin/my-dict"
+//     args = ["--define", "{query}"]
+//
+// `{query}` and `{lang}` in `args` are filled in for each lookup. What the
+// command prints is described in `sources::external`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExternalSourceSettings {
+    // Shown with its results, and the key under `[sources]` that turns it off
+    pub name: String,
+    pub command: PathBuf,
+    pub args: Vec<String>,
+    // The command is killed after this long
+    pub timeout_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SelectionSettings {
+    // Longer selections are refused rather than sent to the sources
+    pub max_length: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PassageSettings {
+    // Selections with more words than this (or several sentences) are
+    // treated as a passage and looked up by their keywords
+    pub max_phrase_words: usize,
+    pub max_keywords: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PatternSettings {
+    // Words listed for a wildcard query like "c?t"
+    pub max_matches: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SessionSettings {
+    // Results the window keeps, across restarts, for going back to; 0
+    // keeps none
+    pub max_results: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MaintenanceSettings {
+    // Lookups the history keeps; older ones are dropped by maintenance. 0
+    // keeps them all.
+    pub max_history_entries: usize,
+    // How long expired cache entries are kept as an offline fallback
+    pub max_cache_age_days: u64,
+    // Share of the database's pages that have to be free before
+    // maintenance rebuilds it to give the space back
+    pub vacuum_threshold: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeferredSettings {
+    // Keep lookups that failed for want of a network and run them again
+    // once it's back
+    pub enabled: bool,
+    // Asked with a HEAD request to tell whether the network is back; any
+    // answer at all counts
+    pub probe_url: String,
+    pub probe_interval_secs: u64,
+    // Queued lookups older than this are given up on
+    pub max_age_secs: u64,
+}
+
+// A setting in the config file that can't be used
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigProblem {
+    // Dotted path like "http.connect_timeout_ms"; empty when the file as a
+    // whole couldn't be read
+    pub key: String,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.key.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.key, self.message)
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Invalid(Vec<ConfigProblem>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "{}", e),
+            ConfigError::Invalid(problems) => {
+                let problems: Vec<String> = problems.iter().map(ToString::to_string).collect();
+                write!(f, "{}", problems.join("; "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<io::Error> for ConfigError {
+    fn from(e: io::Error) -> Self {
+        ConfigError::Io(e)
+    }
+}
+
+// Allowed values for numeric settings where zero or a negative number makes
+// no sense, or a huge one would hang or flood a lookup. "*" stands for
+// every key of a table.
+const RANGES: &[(&str, f64, f64)] = &[
+    ("http.connect_timeout_ms", 1.0, 600_000.0),
+    ("http.request_timeout_ms", 1.0, 600_000.0),
+    ("http.max_concurrent_requests", 0.0, 64.0),
+    ("rate_limits.*", 0.0, 1000.0),
+    ("notification_interval_secs", 0.0, 86_400.0),
+    ("wikipedia.max_related", 0.0, 20.0),
+    ("urban_dictionary.max_results", 1.0, 10.0),
+    ("selection.max_length", 1.0, 10_000.0),
+    ("passage.max_phrase_words", 1.0, 100.0),
+    ("passage.max_keywords", 1.0, 20.0),
+    ("pattern.max_matches", 1.0, 1000.0),
+    ("maintenance.max_cache_age_days", 0.0, 3650.0),
+    ("maintenance.vacuum_threshold", 0.0, 1.0),
+    ("deferred.probe_interval_secs", 5.0, 3600.0),
+    ("deferred.max_age_secs", 60.0, 604_800.0),
+];
+
+// Gives up on a file with more problems than this rather than loop forever
+const MAX_PROBLEMS: usize = 100;
+
+// Sources that stay off unless the config turns them on
+const OPT_IN_SOURCES: &[&str] = &["urban_dictionary", "rhymes"];
+
+const BUILT_IN_SOURCES: &[&str] = &[
+    "local_glossary",
+    "dictionary",
+    "merriam_webster",
+    "wiktionary",
+    "wikipedia",
+    "thesaurus",
+    "etymology",
+    "examples",
+    "acronym",
+    "rhymes",
+    "offline",
+    "urban_dictionary",
+    "word_stats",
+    "wikidata",
+];
+
+// Longest an external source may be given to answer
+const MAX_EXTERNAL_TIMEOUT_MS: u64 = 600_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowSettings {
+    // Open selection lookups next to the pointer; off keeps the window
+    // wherever it was last left
+    pub follow_cursor: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let sources = BUILT_IN_SOURCES
+            .iter()
+            .copied()
+            .map(|name| (name.to_string(), !OPT_IN_SOURCES.contains(&name)))
+            .collect();
+
+        Config {
+            language: None,
+            auto_detect_language: true,
+            sources,
+            http: HttpSettings::default(),
+            network: NetworkSettings::default(),
+            dictionary: DictionarySettings::default(),
+            wikipedia: WikipediaSettings::default(),
+            wikidata: WikidataSettings::default(),
+            rate_limits: ratelimit::default_limits(),
+            log_to_file: false,
+            notifications: true,
+            notification_interval_secs: notify::DEFAULT_MIN_INTERVAL_SECS,
+            max_definitions_per_source: ranking::DEFAULT_MAX_DEFINITIONS_PER_SOURCE,
+            max_definitions_total: 0,
+            dedupe_definitions: true,
+            copy_summary: false,
+            offline: OfflineSettings::default(),
+            private_mode: false,
+            glossary: GlossarySettings::default(),
+            translate_to: None,
+            translate: TranslateSettings::default(),
+            merriam_webster: MerriamWebsterSettings::default(),
+            window: WindowSettings::default(),
+            clipboard_backend: None,
+            selection: SelectionSettings::default(),
+            urban_dictionary: UrbanDictionarySettings::default(),
+            passage: PassageSettings::default(),
+            session: SessionSettings::default(),
+            pattern: PatternSettings::default(),
+            maintenance: MaintenanceSettings::default(),
+            deferred: DeferredSettings::default(),
+            external_sources: Vec::new(),
+            system_language: None,
+        }
+    }
+}
+
+impl Default for ExternalSourceSettings {
+    fn default() -> Self {
+        ExternalSourceSettings {
+            name: String::new(),
+            command: PathBuf::new(),
+            args: Vec::new(),
+            timeout_ms: external::DEFAULT_TIMEOUT_MS,
+        }
+    }
+}
+
+impl Default for UrbanDictionarySettings {
+    fn default() -> Self {
+        UrbanDictionarySettings {
+            max_results: urban_dictionary::DEFAULT_MAX_RESULTS,
+            min_thumbs_up: urban_dictionary::DEFAULT_MIN_THUMBS_UP,
+            always_include: false,
+        }
+    }
+}
+
+impl Default for SelectionSettings {
+    fn default() -> Self {
+        SelectionSettings { max_length: guard::DEFAULT_MAX_LENGTH }
+    }
+}
+
+impl Default for PassageSettings {
+    fn default() -> Self {
+        PassageSettings {
+            max_phrase_words: passage::DEFAULT_MAX_PHRASE_WORDS,
+            max_keywords: passage::DEFAULT_MAX_KEYWORDS,
+        }
+    }
+}
+
+impl Default for PatternSettings {
+    fn default() -> Self {
+        PatternSettings { max_matches: pattern::DEFAULT_MAX_MATCHES }
+    }
+}
+
+impl Default for SessionSettings {
+    fn default() -> Self {
+        SessionSettings { max_results: session::DEFAULT_MAX_RESULTS }
+    }
+}
+
+impl Default for MaintenanceSettings {
+    fn default() -> Self {
+        MaintenanceSettings {
+            max_history_entries: maintenance::DEFAULT_MAX_HISTORY_ENTRIES,
+            max_cache_age_days: maintenance::DEFAULT_MAX_CACHE_AGE_DAYS,
+            vacuum_threshold: maintenance::DEFAULT_VACUUM_THRESHOLD,
+        }
+    }
+}
+
+impl Default for DeferredSettings {
+    fn default() -> Self {
+        DeferredSettings {
+            enabled: true,
+            probe_url: deferred::DEFAULT_PROBE_URL.to_string(),
+            probe_interval_secs: deferred::DEFAULT_PROBE_INTERVAL_SECS,
+            max_age_secs: deferred::DEFAULT_MAX_AGE_SECS,
+        }
+    }
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        WindowSettings { follow_cursor: true }
+    }
+}
+
+impl Default for DictionarySettings {
+    fn default() -> Self {
+        DictionarySettings {
+            endpoints: vec![dictionary::DEFAULT_ENDPOINT.to_string()],
+        }
+    }
+}
+
+impl Default for WikipediaSettings {
+    fn default() -> Self {
+        WikipediaSettings {
+            max_related: wikipedia::DEFAULT_MAX_RELATED,
+            endpoint: wikipedia::DEFAULT_ENDPOINT.to_string(),
+        }
+    }
+}
+
+impl Default for WikidataSettings {
+    fn default() -> Self {
+        WikidataSettings { endpoint: wikidata::DEFAULT_ENDPOINT.to_string() }
+    }
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        HttpSettings {
+            connect_timeout_ms: http::DEFAULT_CONNECT_TIMEOUT.as_millis() as u64,
+            request_timeout_ms: http::DEFAULT_REQUEST_TIMEOUT.as_millis() as u64,
+            max_concurrent_requests: ratelimit::DEFAULT_MAX_CONCURRENT,
+        }
+    }
+}
+
+impl Config {
+    // Load the config from its default location. A missing file gives the
+    // defaults; settings that can't be used are reported and take their
+    // defaults, so one typo doesn't throw away the rest of the file.
+    pub fn load() -> Self {
+        let Some(path) = default_path() else {
+            return Config::default();
+        };
+
+        match std::fs::read_to_string(&path) {
+            Ok(text) => {
+                let (config, problems) = Config::check(&text);
+                for problem in problems {
+                    warn!("Ignoring {} in {}", problem, path.display());
+                }
+                config
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Config::default(),
+            Err(e) => {
+                warn!("Ignoring {}: {}", path.display(), e);
+                Config::default()
+            }
+        }
+    }
+
+    // The config at `path`, failing with every problem in it
+    pub fn load_from(path: &Path) -> Result<Self, ConfigError> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Config::parse(&text).map_err(ConfigError::Invalid),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(ConfigError::Io(e)),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, Vec<ConfigProblem>> {
+        match Config::check(text) {
+            (config, problems) if problems.is_empty() => Ok(config),
+            (_, problems) => Err(problems),
+        }
+    }
+
+    // Every problem in `text`, and the config it gives with each unusable
+    // setting left at its default
+    pub fn check(text: &str) -> (Self, Vec<ConfigProblem>) {
+        match text.parse::<toml::Table>() {
+            Ok(table) => Config::check_table(table),
+            Err(e) => {
+                let message = e.message().trim().replace('\n', ": ");
+                let message = match e.span() {
+                    Some(span) => format!("line {}: {}", text[..span.start].matches('\n').count() + 1, message),
+                    None => message,
+                };
+                (Config::default(), vec![ConfigProblem { key: String::new(), message }])
+            }
+        }
+    }
+
+    // The same checks `check` runs on a file, for a config built in memory
+    // such as the settings window's
+    pub fn validate(&self) -> Vec<ConfigProblem> {
+        match toml::Table::try_from(self) {
+            Ok(table) => Config::check_table(table).1,
+            Err(e) => vec![ConfigProblem { key: String::new(), message: e.to_string() }],
+        }
+    }
+
+    // Out-of-range numbers are taken out first, then whatever serde rejects
+    // is taken out one key at a time until the rest deserializes. Problems
+    // come back in key order.
+    fn check_table(mut table: toml::Table) -> (Self, Vec<ConfigProblem>) {
+        let mut problems = check_ranges(&mut table);
+        let mut config = None;
+        while problems.len() < MAX_PROBLEMS {
+            let e = match Config::deserialize(toml::Value::Table(table.clone())) {
+                Ok(mut checked) => {
+                    problems.extend(checked.check_languages());
+                    problems.extend(checked.check_external_sources());
+                    config = Some(checked);
+                    break;
+                }
+                Err(e) => e,
+            };
+            let (key, message) = describe_error(&e);
+            let removed = remove_key(&mut table, &key);
+            problems.push(ConfigProblem { key: key.join("."), message });
+            if !removed {
+                break;
+            }
+        }
+        problems.sort_by(|a, b| a.key.cmp(&b.key));
+        (config.unwrap_or_default(), problems)
+    }
+
+    // Language codes the lookups can't use are dropped
+    fn check_languages(&mut self) -> Vec<ConfigProblem> {
+        let mut problems = Vec::new();
+        for (key, code) in [("language", &mut self.language), ("translate_to", &mut self.translate_to)] {
+            if let Some(lang) = code.as_deref().filter(|lang| !language::is_known(&language::normalize(lang))) {
+                problems.push(ConfigProblem { key: key.to_string(), message: format!("unknown language code `{}`", lang) });
+                *code = None;
+            }
+        }
+        problems
+    }
+
+    // External sources that can't be run, or that share a name with another
+    // source, are dropped
+    fn check_external_sources(&mut self) -> Vec<ConfigProblem> {
+        let mut problems = Vec::new();
+        let mut names: Vec<String> = Vec::new();
+        for (index, source) in std::mem::take(&mut self.external_sources).into_iter().enumerate() {
+            let problem = if source.name.trim().is_empty() {
+                Some(("name", "is required".to_string()))
+            } else if BUILT_IN_SOURCES.contains(&source.name.as_str()) || names.contains(&source.name) {
+                Some(("name", format!("`{}` is already the name of another source", source.name)))
+            } else if source.command.as_os_str().is_empty() {
+                Some(("command", "is required".to_string()))
+            } else if !(1..=MAX_EXTERNAL_TIMEOUT_MS).contains(&source.timeout_ms) {
+                Some(("timeout_ms", format!("{} is out of range; allowed 1 to {}", source.timeout_ms, MAX_EXTERNAL_TIMEOUT_MS)))
+            } else {
+                None
+            };
+            match problem {
+                Some((key, message)) => {
+                    problems.push(ConfigProblem { key: format!("external_sources.{}.{}", index, key), message })
+                }
+                None => {
+                    names.push(source.name.clone());
+                    self.external_sources.push(source);
+                }
+            }
+        }
+        problems
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = default_path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+        self.save_to(&path)
+    }
+
+    pub fn save_to(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let text = toml::to_string_pretty(self).map_err(io::Error::other)?;
+        std::fs::write(path, text)
+    }
+
+    pub fn notification_interval(&self) -> Duration {
+        Duration::from_secs(self.notification_interval_secs)
+    }
+
+    // The pinned clipboard backend; `TOUCHDICTIONARY_CLIPBOARD_BACKEND` wins
+    // over the file
+    pub fn clipboard_backend(&self) -> Option<ClipboardBackend> {
+        clipboard::backend_from_env().or(self.clipboard_backend)
+    }
+
+    pub fn definition_limits(&self) -> DefinitionLimits {
+        let limit = |max: usize| (max > 0).then_some(max);
+        DefinitionLimits {
+            per_source: limit(self.max_definitions_per_source),
+            total: limit(self.max_definitions_total),
+        }
+    }
+
+    // Language for lookups that don't name one: the configured one, else the
+    // system locale's, else English
+    pub fn lookup_language(&self) -> String {
+        self.language
+            .as_deref()
+            .map(language::normalize)
+            .or_else(|| self.locale_language())
+            .unwrap_or_else(|| language::DEFAULT_LANGUAGE.to_string())
+    }
+
+    // The system locale's language: the platform's when the GUI passed it
+    // on, else the one from the locale variables
+    pub fn locale_language(&self) -> Option<String> {
+        self.system_language.clone().or_else(language::detect)
+    }
+
+    pub fn source_enabled(&self, name: &str) -> bool {
+        self.sources.get(name).copied().unwrap_or(!OPT_IN_SOURCES.contains(&name))
+    }
+
+    // Timeouts from the file, still overridable through the environment
+    pub fn http_config(&self) -> HttpConfig {
+        HttpConfig {
+            connect_timeout: Duration::from_millis(self.http.connect_timeout_ms),
+            request_timeout: Duration::from_millis(self.http.request_timeout_ms),
+            proxy: self.network.proxy.clone().filter(|proxy| !proxy.trim().is_empty()),
+            user_agent: self
+                .network
+                .user_agent
+                .clone()
+                .filter(|agent| !agent.trim().is_empty())
+                .unwrap_or_else(|| http::DEFAULT_USER_AGENT.to_string()),
+        }
+        .with_env_overrides()
+    }
+
+    // Listing one upstream in `[rate_limits]` shouldn't unpace the others
+    pub fn rate_limits_with_defaults(&self) -> BTreeMap<String, f64> {
+        let mut limits = ratelimit::default_limits();
+        limits.extend(self.rate_limits.iter().map(|(name, rate)| (name.clone(), *rate)));
+        limits
+    }
+}
+
+pub fn default_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("touchdictionary").join("config.toml"))
+}
+
+// Numbers outside `RANGES`, each taken out of `table`
+fn check_ranges(table: &mut toml::Table) -> Vec<ConfigProblem> {
+    let mut problems = Vec::new();
+    for &(path, min, max) in RANGES {
+        let (parent_path, name) = path.rsplit_once('.').unwrap_or(("", path));
+        let Some(parent) = table_at(table, parent_path) else {
+            continue;
+        };
+        let names: Vec<String> = match name {
+            "*" => parent.keys().cloned().collect(),
+            name => vec![name.to_string()],
+        };
+        for name in names {
+            let number = match parent.get(&name) {
+                Some(toml::Value::Integer(n)) => *n as f64,
+                Some(toml::Value::Float(n)) => *n,
+                _ => continue,
+            };
+            if !(min..=max).contains(&number) {
+                problems.push(ConfigProblem {
+                    key: if parent_path.is_empty() { name.clone() } else { format!("{}.{}", parent_path, name) },
+                    message: format!("{} is out of range; allowed {} to {}", parent[&name], min, max),
+                });
+                parent.remove(&name);
+            }
+        }
+    }
+    problems
+}
+
+fn table_at<'a>(table: &'a mut toml::Table, path: &str) -> Option<&'a mut toml::Table> {
+    if path.is_empty() {
+        return Some(table);
+    }
+    path.split('.').try_fold(table, |table, name| table.get_mut(name)?.as_table_mut())
+}
+
+// Take the key at `path` out of `table`, reporting whether it was there
+fn remove_key(table: &mut toml::Table, path: &[String]) -> bool {
+    let Some((name, parents)) = path.split_last() else {
+        return false;
+    };
+    table_at(table, &parents.join(".")).and_then(|parent| parent.remove(name)).is_some()
+}
+
+// Where a deserialization error is, as key names, and what to tell the user.
+// toml puts the table the error is in after the message, as "in `a.b`".
+fn describe_error(e: &toml::de::Error) -> (Vec<String>, String) {
+    let text = e.to_string();
+    let (message, table) = match text.trim_end().rsplit_once("\nin `") {
+        Some((message, table)) => (message.to_string(), table.trim_end_matches('`').to_string()),
+        None => (text.trim_end().to_string(), String::new()),
+    };
+    let mut key: Vec<String> = table.split('.').filter(|name| !name.is_empty()).map(str::to_string).collect();
+
+    let quoted = |text: &str| -> Vec<String> { text.split('`').skip(1).step_by(2).map(str::to_string).collect() };
+    if let Some(rest) = message.strip_prefix("unknown field ") {
+        let (field, expected) = rest.split_once(", expected").unwrap_or((rest, ""));
+        let field = field.trim_matches('`').to_string();
+        let message = match did_you_mean(&field, &quoted(expected)) {
+            Some(known) => format!("unknown key; did you mean `{}`?", known),
+            None => "unknown key".to_string(),
+        };
+        key.push(field);
+        return (key, message);
+    }
+    if let Some(rest) = message.strip_prefix("unknown variant ") {
+        let (value, expected) = rest.split_once(", expected").unwrap_or((rest, ""));
+        let allowed = quoted(expected);
+        let message = match did_you_mean(value.trim_matches('`'), &allowed) {
+            Some(known) => format!("unknown value {}; did you mean `{}`?", value, known),
+            None => format!("unknown value {}; expected one of {}", value, allowed.join(", ")),
+        };
+        return (key, message);
+    }
+    (key, message)
+}
+
+// The known name closest to a misspelt one, if it's close enough to be
+// what was meant
+fn did_you_mean<'a>(name: &str, known: &'a [String]) -> Option<&'a str> {
+    known
+        .iter()
+        .map(|candidate| (format::edit_distance(&name.to_lowercase(), candidate), candidate))
+        .filter(|(distance, candidate)| *distance <= (candidate.chars().count() / 3).max(1))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("touchdictionary-config-test-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir.join("config.toml")
+    }
+
+    #[test]
+    fn test_missing_file_gives_defaults() {
+        let config = Config::load_from(&temp_path("missing")).unwrap();
+        assert_eq!(config, Config::default());
+        assert!(config.source_enabled("wikipedia"));
+    }
+
+    #[test]
+    fn test_partial_file_keeps_other_defaults() {
+        let path = temp_path("partial");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "[sources]\nwikipedia = false\n\n[rate_limits]\nwikipedia = 1.0\n").unwrap();
+
+        let config = Config::load_from(&path).unwrap();
+        assert!(!config.source_enabled("wikipedia"));
+        assert!(config.source_enabled("dictionary"));
+        assert!(config.source_enabled("some-future-source"));
+        assert!(!config.source_enabled("urban_dictionary"));
+        assert!(!config.source_enabled("rhymes"));
+        assert_eq!(config.language, None);
+        assert_eq!(config.http, HttpSettings::default());
+        let limits = config.rate_limits_with_defaults();
+        assert_eq!(limits["wikipedia"], 1.0);
+        assert_eq!(limits["dictionary"], 2.0);
+    }
+
+    #[test]
+    fn test_save_round_trips() {
+        let path = temp_path("roundtrip");
+        let mut config = Config { language: Some("de".to_string()), ..Config::default() };
+        config.sources.insert("thesaurus".to_string(), false);
+        config.http.request_timeout_ms = 2500;
+
+        config.save_to(&path).unwrap();
+        assert_eq!(Config::load_from(&path).unwrap(), config);
+
+        std::fs::write(&path, "language = [").unwrap();
+        assert!(matches!(Config::load_from(&path), Err(ConfigError::Invalid(_))));
+    }
+
+    fn problems(text: &str) -> Vec<String> {
+        Config::check(text).1.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_check_reports_every_problem() {
+        let text = "\
+langauge = \"de\"
+clipboard_backend = \"xsell\"
+notification_interval_secs = \"often\"
+
+[http]
+conect_timeout_ms = 500
+request_timeout_ms = -1
+max_concurrent_requests = 4
+
+[rate_limits]
+wikipedia = -2.0
+
+[pattern]
+max_matches = 0
+
+[wikipedia]
+max_related = 3
+";
+        assert_eq!(
+            problems(text),
+            [
+                "clipboard_backend: unknown value `xsell`; did you mean `xsel`?",
+                "http.conect_timeout_ms: unknown key; did you mean `connect_timeout_ms`?",
+                "http.request_timeout_ms: -1 is out of range; allowed 1 to 600000",
+                "langauge: unknown key; did you mean `language`?",
+                "notification_interval_secs: invalid type: string \"often\", expected u64",
+                "pattern.max_matches: 0 is out of range; allowed 1 to 1000",
+                "rate_limits.wikipedia: -2.0 is out of range; allowed 0 to 1000",
+            ]
+        );
+
+        // What could be read still applies
+        let (config, _) = Config::check(text);
+        assert_eq!(config.http.max_concurrent_requests, 4);
+        assert_eq!(config.wikipedia.max_related, 3);
+        assert_eq!(config.http.request_timeout_ms, HttpSettings::default().request_timeout_ms);
+        assert!(Config::parse(text).is_err());
+    }
+
+    #[test]
+    fn test_check_other_problems() {
+        assert_eq!(problems("frobnicate = 1\n"), ["frobnicate: unknown key"]);
+        assert_eq!(problems("language = \"xx\"\n"), ["language: unknown language code `xx`"]);
+        assert_eq!(problems("[http]\nrequest_timeout_ms = 5000\n\n[http]\n"), ["line 4: invalid table header: duplicate key `http` in document root"]);
+        assert!(problems("[sources]\nmy_source = true\n\n[rate_limits]\nwikipedia = 1.5\n").is_empty());
+        assert!(Config::default().validate().is_empty());
+
+        let text = "\
+[[external_sources]]
+name = \"company\"
+command = \"/usr/local/bin/my-dict\"
+args = [\"{query}\"]
+
+[[external_sources]]
+name = \"company\"
+command = \"/usr/local/bin/other-dict\"
+
+[[external_sources]]
+name = \"wikipedia\"
+command = \"/usr/local/bin/wiki\"
+
+[[external_sources]]
+name = \"intranet\"
+timeout_ms = 0
+";
+        let (config, problems) = Config::check(text);
+        let problems: Vec<String> = problems.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            problems,
+            [
+                "external_sources.1.name: `company` is already the name of another source",
+                "external_sources.2.name: `wikipedia` is already the name of another source",
+                "external_sources.3.command: is required",
+            ]
+        );
+        assert_eq!(config.external_sources.len(), 1);
+        assert_eq!(config.external_sources[0].timeout_ms, external::DEFAULT_TIMEOUT_MS);
+
+        let mut config = Config::default();
+        config.passage.max_keywords = 0;
+        assert_eq!(config.validate()[0].to_string(), "passage.max_keywords: 0 is out of range; allowed 1 to 20");
+    }
+}
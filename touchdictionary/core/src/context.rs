@@ -0,0 +1,246 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::SystemTime;
+
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+use crate::cache::Cache;
+use crate::config::{self, Config};
+use crate::deferred::DeferredQueue;
+use crate::error::LookupError;
+use crate::http;
+use crate::language;
+use crate::lookup::LookupResult;
+use crate::ratelimit;
+use crate::sources::glossary;
+
+// What lookups share for as long as the app runs: the config, the pooled
+// HTTP client, the cache and the lookups in flight. Build one at startup
+// and hand it to `lookup::lookup_with`. The config file is re-read when it
+// changes, so edits still apply without a restart.
+pub struct LookupContext {
+    // Config file being followed; None for a context built from a fixed config
+    config_path: Option<PathBuf>,
+    state: RwLock<Loaded>,
+    cache: Option<Cache>,
+    // Lookups running now, by `CacheKey`. The value is filled in once
+    // the lookup finishes.
+    in_flight: Mutex<BTreeMap<String, watch::Receiver<Option<Outcome>>>>,
+    // Private mode for every lookup through this context. Starts as the
+    // config says and is never written back, so a restart resets it.
+    private: AtomicBool,
+    // Lookups to run again once the network is back
+    deferred: DeferredQueue,
+}
+
+type Outcome = Result<LookupResult, LookupError>;
+
+struct Loaded {
+    config: Arc<Config>,
+    modified: Option<SystemTime>,
+    client: Arc<reqwest::Client>,
+}
+
+impl LookupContext {
+    // Context for the config in its default location
+    pub fn load() -> Self {
+        let config_path = config::default_path();
+        let modified = config_path.as_deref().and_then(modified_time);
+        let config = Config::load();
+        let private = AtomicBool::new(config.private_mode);
+        LookupContext {
+            config_path,
+            state: RwLock::new(Loaded::apply(config, modified)),
+            cache: Cache::open_default(),
+            in_flight: Mutex::new(BTreeMap::new()),
+            private,
+            deferred: DeferredQueue::default(),
+        }
+    }
+
+    // Context for `config` as given, never re-read
+    pub fn with_config(config: Config) -> Self {
+        let private = AtomicBool::new(config.private_mode);
+        LookupContext {
+            config_path: None,
+            state: RwLock::new(Loaded::apply(config, None)),
+            cache: Cache::open_default(),
+            in_flight: Mutex::new(BTreeMap::new()),
+            private,
+            deferred: DeferredQueue::default(),
+        }
+    }
+
+    pub fn config(&self) -> Arc<Config> {
+        let modified = self.config_path.as_deref().and_then(modified_time);
+        let state = self.state.read().unwrap_or_else(|e| e.into_inner());
+        if state.modified == modified {
+            return state.config.clone();
+        }
+        drop(state);
+
+        info!("Config changed, reloading");
+        let mut state = self.state.write().unwrap_or_else(|e| e.into_inner());
+        let system_language = state.config.system_language.clone();
+        *state = Loaded::apply(Config { system_language, ..Config::load() }, modified);
+        state.config.clone()
+    }
+
+    // Take the system's language from `locale` as the platform reports it
+    // ("de-DE"). GUI launches on macOS and Windows rarely have the locale
+    // variables set. Kept when the config is reloaded.
+    pub fn set_system_locale(&self, locale: &str) {
+        let mut state = self.state.write().unwrap_or_else(|e| e.into_inner());
+        let mut config = Config::clone(&state.config);
+        config.system_language = language::from_locale(locale);
+        state.config = Arc::new(config);
+    }
+
+    pub fn client(&self) -> Arc<reqwest::Client> {
+        self.state.read().unwrap_or_else(|e| e.into_inner()).client.clone()
+    }
+
+    pub fn cache(&self) -> Option<&Cache> {
+        self.cache.as_ref()
+    }
+
+    pub fn is_private(&self) -> bool {
+        self.private.load(Ordering::Relaxed)
+    }
+
+    pub fn deferred(&self) -> &DeferredQueue {
+        &self.deferred
+    }
+
+    // Switch private mode for the rest of this session
+    pub fn set_private_mode(&self, private: bool) {
+        info!("Private mode {}", if private { "on" } else { "off" });
+        self.private.store(private, Ordering::Relaxed);
+    }
+
+    // Run `lookup` unless one with the same `key` is already running, in
+    // which case wait for that one's outcome instead. A lookup that was
+    // cancelled or dropped leaves its waiters to run their own.
+    pub(crate) async fn coalesce<F>(&self, key: &str, cancel: &CancellationToken, lookup: F) -> Outcome
+    where
+        F: Future<Output = Outcome>,
+    {
+        let sender = loop {
+            let running = {
+                let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+                match in_flight.get(key) {
+                    Some(receiver) => receiver.clone(),
+                    None => {
+                        let (sender, receiver) = watch::channel(None);
+                        in_flight.insert(key.to_string(), receiver);
+                        break sender;
+                    }
+                }
+            };
+
+            info!("Waiting for the identical lookup already running");
+            let outcome = tokio::select! {
+                outcome = wait_for(running) => outcome,
+                _ = cancel.cancelled() => return Err(LookupError::Cancelled),
+            };
+            match outcome {
+                Some(Err(LookupError::Cancelled)) | None => continue,
+                Some(outcome) => return outcome,
+            }
+        };
+
+        let entry = InFlightEntry { context: self, key };
+        let outcome = lookup.await;
+        // Gone before the outcome is sent, so waiters that have to retry
+        // don't find it again
+        drop(entry);
+        sender.send_replace(Some(outcome.clone()));
+        outcome
+    }
+}
+
+// None when the lookup was dropped before it finished
+async fn wait_for(mut running: watch::Receiver<Option<Outcome>>) -> Option<Outcome> {
+    let outcome = running.wait_for(Option::is_some).await.ok()?;
+    outcome.clone()
+}
+
+// Takes a finished, failed or dropped lookup out of `in_flight`
+struct InFlightEntry<'a> {
+    context: &'a LookupContext,
+    key: &'a str,
+}
+
+impl Drop for InFlightEntry<'_> {
+    fn drop(&mut self) {
+        self.context.in_flight.lock().unwrap_or_else(|e| e.into_inner()).remove(self.key);
+    }
+}
+
+impl Loaded {
+    // Sources reach the client and rate limiters through `http` and
+    // `ratelimit`, so point those at this config. The client is only
+    // rebuilt when the timeouts changed. The glossaries are read here too,
+    // so the first lookup doesn't wait on them.
+    fn apply(config: Config, modified: Option<SystemTime>) -> Self {
+        http::configure(&config.http_config());
+        ratelimit::configure(&config.rate_limits_with_defaults());
+        ratelimit::configure_concurrency(config.http.max_concurrent_requests);
+        if let Some(dir) = glossary::directory(&config).filter(|dir| dir.is_dir()) {
+            if config.source_enabled("local_glossary") {
+                glossary::index(&dir);
+            }
+        }
+        Loaded { config: Arc::new(config), modified, client: http::client() }
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::OfflineSettings;
+    use crate::lookup::{self, LookupOptions};
+
+    #[tokio::test]
+    async fn test_sequential_lookups_share_one_client() {
+        // Offline against a missing database, so nothing goes out
+        let config = Config {
+            offline: OfflineSettings {
+                database: Some(PathBuf::from("/nonexistent/wordnet.sqlite")),
+                offline_only: true,
+            },
+            ..Config::default()
+        };
+        let ctx = LookupContext::with_config(config);
+        let client = ctx.client();
+        let options = LookupOptions { use_cache: false, ..LookupOptions::default() };
+
+        for word in ["serendipity", "petrichor"] {
+            let _ = lookup::lookup_with(&ctx, word, &options).await;
+            assert!(Arc::ptr_eq(&client, &ctx.client()));
+            assert!(Arc::ptr_eq(&client, &http::client()));
+        }
+    }
+
+    #[test]
+    fn test_system_locale_comes_before_the_environment() {
+        let ctx = LookupContext::with_config(Config::default());
+        ctx.set_system_locale("de-DE");
+        assert_eq!(ctx.config().lookup_language(), "de");
+        assert_eq!(ctx.config().locale_language().as_deref(), Some("de"));
+
+        // The configured language still wins
+        let ctx = LookupContext::with_config(Config { language: Some("fr".to_string()), ..Config::default() });
+        ctx.set_system_locale("de-DE");
+        assert_eq!(ctx.config().lookup_language(), "fr");
+    }
+}
@@ -0,0 +1,253 @@
+// Lookups that failed because the network was down, kept to run again once
+// it's back rather than leaving the user to remember them. Only failures
+// to reach the sources are queued, at most `MAX_QUEUED` of them, and each
+// is given up on after `deferred.max_age_secs`. The GUI probes the network
+// while anything is queued and shows each result as it comes in; the CLI's
+// `--wait-for-network` waits on the probe instead of failing.
+
+use std::collections::VecDeque;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use futures_util::StreamExt;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::cache::CacheKey;
+use crate::config::Config;
+use crate::context::LookupContext;
+use crate::error::LookupError;
+use crate::lookup::{self, LookupOptions, LookupResult};
+
+pub const DEFAULT_PROBE_URL: &str = "https://www.wikipedia.org/";
+pub const DEFAULT_PROBE_INTERVAL_SECS: u64 = 30;
+pub const DEFAULT_MAX_AGE_SECS: u64 = 3600;
+
+// Lookups queued at once; the oldest goes to make room
+pub const MAX_QUEUED: usize = 20;
+
+// Queued lookups rerun at once
+const RETRY_CONCURRENCY: usize = 2;
+
+// Shortest time between two runs of the queue, however often the network
+// is reported back
+const MIN_RETRY_INTERVAL: Duration = Duration::from_secs(10);
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// How often `wait_for_network` probes
+const WAIT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+// Held by the `LookupContext`, so it lasts as long as the app does and no
+// longer: nothing queued is written anywhere
+#[derive(Default)]
+pub struct DeferredQueue {
+    entries: Mutex<VecDeque<Deferred>>,
+    last_run: Mutex<Option<Instant>>,
+}
+
+struct Deferred {
+    // To tell the same lookup queued twice
+    key: CacheKey,
+    query: String,
+    options: LookupOptions,
+    queued_at: Instant,
+}
+
+impl DeferredQueue {
+    // Queue `query` to run again with `options`, replacing the same lookup
+    // queued before
+    pub fn push(&self, config: &Config, query: &str, options: &LookupOptions) {
+        let options = LookupOptions { cancel: CancellationToken::new(), progress: None, deferred: true, ..options.clone() };
+        self.requeue(Deferred {
+            key: CacheKey::from(config, &options, query),
+            query: query.to_string(),
+            options,
+            queued_at: Instant::now(),
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        lock(&self.entries).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Queries waiting, oldest first
+    pub fn queries(&self) -> Vec<String> {
+        lock(&self.entries).iter().map(|entry| entry.query.clone()).collect()
+    }
+
+    fn requeue(&self, entry: Deferred) {
+        let mut entries = lock(&self.entries);
+        entries.retain(|queued| queued.key != entry.key);
+        entries.push_back(entry);
+        while entries.len() > MAX_QUEUED {
+            if let Some(dropped) = entries.pop_front() {
+                info!("Deferred lookups full, dropping '{}'", dropped.query);
+            }
+        }
+    }
+
+    // Everything queued no longer than `max_age` ago, leaving the queue
+    // empty
+    fn take(&self, max_age: Duration) -> Vec<Deferred> {
+        let entries = std::mem::take(&mut *lock(&self.entries));
+        let (fresh, expired): (Vec<_>, Vec<_>) = entries.into_iter().partition(|entry| entry.queued_at.elapsed() <= max_age);
+        if !expired.is_empty() {
+            info!("Gave up on {} deferred lookups queued over {}s ago", expired.len(), max_age.as_secs());
+        }
+        fresh
+    }
+
+    // Whether the queue may run now, noting that it does
+    fn start_run(&self) -> bool {
+        let mut last_run = lock(&self.last_run);
+        if last_run.is_some_and(|at| at.elapsed() < MIN_RETRY_INTERVAL) {
+            return false;
+        }
+        *last_run = Some(Instant::now());
+        true
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// Queue a lookup that failed with `error`, if running it again later could
+// help. Lookups that were offline or private on purpose aren't.
+pub(crate) fn defer_if_unreachable(ctx: &LookupContext, query: &str, options: &LookupOptions, error: &LookupError) {
+    let deferring = ctx.config().deferred.enabled && error.is_network();
+    if !deferring || options.deferred || options.skip_history || options.offline || options.private {
+        return;
+    }
+    info!("No network for '{}'; queued to run again once there is", query);
+    ctx.deferred().push(&ctx.config(), query, options);
+}
+
+// Whether the network is back, going by a HEAD request to the probe URL.
+// Any answer counts, even an error status.
+pub async fn probe(ctx: &LookupContext) -> bool {
+    let url = ctx.config().deferred.probe_url.clone();
+    match ctx.client().head(&url).timeout(PROBE_TIMEOUT).send().await {
+        Ok(_) => true,
+        Err(e) => {
+            info!("Network probe to {} failed: {}", url, e);
+            false
+        }
+    }
+}
+
+// `retry` once the probe says the network is back. Does nothing, without
+// probing, while the queue is empty.
+pub async fn retry_if_online(ctx: &LookupContext) -> Vec<LookupResult> {
+    if ctx.deferred().is_empty() || !probe(ctx).await {
+        return Vec::new();
+    }
+    retry(ctx).await
+}
+
+// Run the queue again, a few lookups at a time. Lookups that still can't
+// reach the network go back on it; the rest are done with either way.
+// Returns what the successful ones found, each also recorded in the
+// history as deferred. Runs at most once every `MIN_RETRY_INTERVAL`.
+pub async fn retry(ctx: &LookupContext) -> Vec<LookupResult> {
+    let queue = ctx.deferred();
+    if queue.is_empty() || ctx.is_private() || !queue.start_run() {
+        return Vec::new();
+    }
+    let entries = queue.take(Duration::from_secs(ctx.config().deferred.max_age_secs));
+    if entries.is_empty() {
+        return Vec::new();
+    }
+
+    info!("Running {} deferred lookups", entries.len());
+    let results: Vec<Option<LookupResult>> = futures_util::stream::iter(entries)
+        .map(|entry| async move {
+            match lookup::lookup_with(ctx, &entry.query, &entry.options).await {
+                Ok(result) => Some(result),
+                Err(e) if e.is_network() => {
+                    queue.requeue(entry);
+                    None
+                }
+                Err(e) => {
+                    warn!("Deferred lookup of '{}' failed: {}", entry.query, e);
+                    None
+                }
+            }
+        })
+        .buffer_unordered(RETRY_CONCURRENCY)
+        .collect()
+        .await;
+    results.into_iter().flatten().collect()
+}
+
+// Probe until the network answers or `timeout` runs out, returning whether
+// it came back
+pub async fn wait_for_network(ctx: &LookupContext, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if probe(ctx).await {
+            return true;
+        }
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return false;
+        }
+        tokio::time::sleep(WAIT_POLL_INTERVAL.min(left)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queued(queue: &DeferredQueue, query: &str, age: Duration) {
+        queue.requeue(Deferred {
+            key: CacheKey::from(&Config::default(), &LookupOptions::default(), query),
+            query: query.to_string(),
+            options: LookupOptions::default(),
+            queued_at: Instant::now() - age,
+        });
+    }
+
+    #[test]
+    fn test_queue_drops_oldest_and_expired() {
+        let config = Config { language: Some("en".to_string()), ..Config::default() };
+        let queue = DeferredQueue::default();
+        for i in 0..MAX_QUEUED + 2 {
+            queue.push(&config, &format!("word{}", i), &LookupOptions::default());
+        }
+        let queries = queue.queries();
+        assert_eq!(queries.len(), MAX_QUEUED);
+        assert_eq!(queries[0], "word2");
+
+        // The same lookup again moves to the back rather than adding one
+        queue.push(&config, "word2", &LookupOptions::default());
+        assert_eq!(queue.len(), MAX_QUEUED);
+        assert_eq!(queue.queries().last().unwrap(), "word2");
+        // In another language it's another lookup, pushing out the oldest
+        queue.push(&config, "word3", &LookupOptions { lang: Some("de".to_string()), ..LookupOptions::default() });
+        assert_eq!(queue.len(), MAX_QUEUED);
+        assert_eq!(queue.queries()[0], "word4");
+
+        let queue = DeferredQueue::default();
+        queued(&queue, "stale", Duration::from_secs(7200));
+        queued(&queue, "fresh", Duration::from_secs(60));
+        let taken: Vec<_> = queue.take(Duration::from_secs(3600)).into_iter().map(|entry| entry.query).collect();
+        assert_eq!(taken, ["fresh"]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_queue_runs_are_throttled() {
+        let queue = DeferredQueue::default();
+        assert!(queue.start_run());
+        assert!(!queue.start_run());
+        *queue.last_run.lock().unwrap() = Some(Instant::now() - MIN_RETRY_INTERVAL);
+        assert!(queue.start_run());
+    }
+}
@@ -0,0 +1,251 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+use crate::guard::RejectReason;
+
+// Structured lookup errors so the CLI and GUI can tell "word not found"
+// apart from "network down" or "rate limited". Cloneable so lookups that
+// joined an identical one in flight can each get its failure.
+#[derive(Debug, Clone)]
+pub enum LookupError {
+    EmptyQuery,
+    NotFound { query: String },
+    Network(Arc<reqwest::Error>),
+    Timeout { source: String },
+    ParseError { source: String, body_snippet: String },
+    RateLimited { retry_after: Option<Duration> },
+    UpstreamStatus { source: String, status: u16 },
+    // The upstream answered with an error object of its own instead of data
+    Upstream { title: String, message: String },
+    UnsupportedLanguage { lang: String },
+    // A wildcard pattern with no letters in it
+    InvalidPattern { pattern: String },
+    // A transient failure that persisted through every retry
+    RetriesExhausted { attempts: u32, last: Box<LookupError> },
+    // Abandoned because a newer lookup superseded it
+    Cancelled,
+    // The offline dictionary is missing or unreadable
+    OfflineUnavailable { reason: String },
+    // Nothing was found and at least one source failed rather than coming
+    // back empty, so the word may well exist
+    AllSourcesFailed { details: Vec<SourceFailure> },
+    // A selection too long, or too likely private, to send anywhere
+    SelectionRejected { reason: RejectReason },
+    // A network source left alone because private mode is on
+    PrivateModeBlocked { source: String },
+}
+
+// One source's failure, with the name of the source
+#[derive(Debug, Clone)]
+pub struct SourceFailure {
+    pub source: String,
+    pub error: LookupError,
+}
+
+impl LookupError {
+    // Stable machine-readable identifier forwarded to the frontend. Exhausted
+    // retries report the kind of the last failure.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            LookupError::RetriesExhausted { last, .. } => last.kind(),
+            LookupError::EmptyQuery => "empty_query",
+            LookupError::NotFound { .. } => "not_found",
+            LookupError::Network(_) => "network",
+            LookupError::Timeout { .. } => "timeout",
+            LookupError::ParseError { .. } => "parse_error",
+            LookupError::RateLimited { .. } => "rate_limited",
+            LookupError::UpstreamStatus { .. } => "upstream_status",
+            LookupError::Upstream { .. } => "upstream",
+            LookupError::UnsupportedLanguage { .. } => "unsupported_language",
+            LookupError::InvalidPattern { .. } => "invalid_pattern",
+            LookupError::Cancelled => "cancelled",
+            LookupError::OfflineUnavailable { .. } => "offline_unavailable",
+            LookupError::AllSourcesFailed { .. } => "all_sources_failed",
+            LookupError::SelectionRejected { .. } => "selection_rejected",
+            LookupError::PrivateModeBlocked { .. } => "private_mode_blocked",
+        }
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        match self {
+            LookupError::RetriesExhausted { last, .. } => last.is_not_found(),
+            _ => matches!(self, LookupError::NotFound { .. }),
+        }
+    }
+
+    // The upstream couldn't be reached at all (connection failure or timeout)
+    pub fn is_network(&self) -> bool {
+        match self {
+            LookupError::RetriesExhausted { last, .. } => last.is_network(),
+            LookupError::AllSourcesFailed { details } => {
+                !details.is_empty() && details.iter().all(|d| d.error.is_network())
+            }
+            _ => matches!(self, LookupError::Network(_) | LookupError::Timeout { .. }),
+        }
+    }
+
+    pub fn is_timeout(&self) -> bool {
+        match self {
+            LookupError::RetriesExhausted { last, .. } => last.is_timeout(),
+            LookupError::Network(e) => e.is_timeout(),
+            _ => matches!(self, LookupError::Timeout { .. }),
+        }
+    }
+
+    // The upstream itself is down: unreachable, or failing with a server error
+    pub fn is_unavailable(&self) -> bool {
+        match self {
+            LookupError::RetriesExhausted { last, .. } => last.is_unavailable(),
+            LookupError::UpstreamStatus { status, .. } => *status >= 500,
+            LookupError::AllSourcesFailed { details } => {
+                !details.is_empty() && details.iter().all(|d| d.error.is_unavailable())
+            }
+            _ => self.is_network(),
+        }
+    }
+
+    // Worth retrying: the upstream was unreachable, overloaded or rate limited
+    pub fn is_transient(&self) -> bool {
+        match self {
+            LookupError::Network(e) => e.is_connect() || e.is_timeout(),
+            LookupError::Timeout { .. } | LookupError::RateLimited { .. } => true,
+            LookupError::UpstreamStatus { status, .. } => *status >= 500,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for LookupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LookupError::EmptyQuery => write!(f, "Empty query"),
+            LookupError::NotFound { query } => write!(f, "No results found for '{}'", query),
+            LookupError::Network(e) => write!(f, "Network error: {}", e),
+            LookupError::Timeout { source } => write!(f, "{} source timed out", source),
+            LookupError::ParseError { source, body_snippet } => {
+                write!(f, "Failed to parse {} response: {}", source, body_snippet)
+            }
+            LookupError::RateLimited { retry_after: Some(delay) } => {
+                write!(f, "Rate limited, retry after {}s", delay.as_secs())
+            }
+            LookupError::RateLimited { retry_after: None } => write!(f, "Rate limited"),
+            LookupError::UpstreamStatus { source, status } => {
+                write!(f, "{} returned status: {}", source, status)
+            }
+            LookupError::Upstream { title, message } if message.is_empty() => write!(f, "{}", title),
+            LookupError::Upstream { title, message } => write!(f, "{}: {}", title, message),
+            LookupError::UnsupportedLanguage { lang } => write!(f, "Unsupported language: '{}'", lang),
+            LookupError::InvalidPattern { pattern } => write!(f, "Pattern '{}' needs at least one letter", pattern),
+            LookupError::RetriesExhausted { attempts, last } => {
+                write!(f, "{} (gave up after {} attempts)", last, attempts)
+            }
+            LookupError::Cancelled => write!(f, "Lookup cancelled by a newer query"),
+            LookupError::OfflineUnavailable { reason } => write!(f, "Offline dictionary unavailable: {}", reason),
+            LookupError::AllSourcesFailed { details } => {
+                let failures: Vec<String> = details.iter().map(|d| format!("{}: {}", d.source, d.error)).collect();
+                write!(f, "Sources failed: {}", failures.join("; "))
+            }
+            LookupError::SelectionRejected { reason } => write!(f, "Selection not looked up: {}", reason),
+            LookupError::PrivateModeBlocked { source } => write!(f, "{} not asked: private mode is on", source),
+        }
+    }
+}
+
+impl std::error::Error for LookupError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LookupError::Network(e) => Some(e.as_ref()),
+            LookupError::RetriesExhausted { last, .. } => Some(last.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for LookupError {
+    fn from(e: reqwest::Error) -> Self {
+        LookupError::Network(Arc::new(e))
+    }
+}
+
+// Serialized as `{ kind, message, ...details }` so the Tauri command can
+// forward it to the frontend as-is.
+impl Serialize for LookupError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("LookupError", 3)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        match self {
+            LookupError::NotFound { query } => state.serialize_field("query", query)?,
+            LookupError::Timeout { source } => state.serialize_field("source", source)?,
+            LookupError::ParseError { source, body_snippet } => {
+                state.serialize_field("source", source)?;
+                state.serialize_field("body_snippet", body_snippet)?;
+            }
+            LookupError::RateLimited { retry_after } => {
+                state.serialize_field("retry_after_secs", &retry_after.map(|d| d.as_secs()))?;
+            }
+            LookupError::UpstreamStatus { source, status } => {
+                state.serialize_field("source", source)?;
+                state.serialize_field("status", status)?;
+            }
+            LookupError::Upstream { title, .. } => state.serialize_field("title", title)?,
+            LookupError::UnsupportedLanguage { lang } => state.serialize_field("lang", lang)?,
+            LookupError::InvalidPattern { pattern } => state.serialize_field("pattern", pattern)?,
+            LookupError::OfflineUnavailable { reason } => state.serialize_field("reason", reason)?,
+            LookupError::RetriesExhausted { attempts, .. } => state.serialize_field("attempts", attempts)?,
+            LookupError::AllSourcesFailed { details } => state.serialize_field("details", details)?,
+            LookupError::SelectionRejected { reason } => {
+                state.serialize_field("reason", reason.kind())?;
+                state.serialize_field("open_target", &reason.open_target())?;
+            }
+            LookupError::PrivateModeBlocked { source } => state.serialize_field("source", source)?,
+            _ => {}
+        }
+        state.end()
+    }
+}
+
+impl Serialize for SourceFailure {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("SourceFailure", 3)?;
+        state.serialize_field("source", &self.source)?;
+        state.serialize_field("kind", self.error.kind())?;
+        state.serialize_field("message", &self.error.to_string())?;
+        state.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serializes_kind_and_message() {
+        let err = LookupError::NotFound { query: "definately".to_string() };
+        let value = serde_json::to_value(&err).unwrap();
+        assert_eq!(value["kind"], "not_found");
+        assert_eq!(value["query"], "definately");
+        assert_eq!(value["message"], "No results found for 'definately'");
+
+        let err = LookupError::RateLimited { retry_after: Some(Duration::from_secs(30)) };
+        let value = serde_json::to_value(&err).unwrap();
+        assert_eq!(value["kind"], "rate_limited");
+        assert_eq!(value["retry_after_secs"], 30);
+
+        let err = LookupError::AllSourcesFailed {
+            details: vec![SourceFailure {
+                source: "wikipedia".to_string(),
+                error: LookupError::Timeout { source: "Wikipedia".to_string() },
+            }],
+        };
+        let value = serde_json::to_value(&err).unwrap();
+        assert_eq!(value["kind"], "all_sources_failed");
+        assert_eq!(value["details"][0]["source"], "wikipedia");
+        assert_eq!(value["details"][0]["kind"], "timeout");
+        assert!(err.is_network());
+    }
+}
@@ -0,0 +1,179 @@
+// History and favorites as CSV or JSON files, and the field quoting the
+// Anki export shares
+
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::favorites::FavoriteEntry;
+use crate::history::HistoryEntry;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    Csv,
+    #[default]
+    Json,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            other => Err(format!("Unknown export format '{}', expected csv or json", other)),
+        }
+    }
+}
+
+// Quote a field holding the separator, a line break or a quote, doubling
+// the quotes inside (RFC 4180; Anki's importer reads the same)
+pub fn quote_field(value: &str, separator: char) -> String {
+    if value.contains([separator, '\n', '\r', '"']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// CSV rows end in CRLF, as RFC 4180 has it
+fn csv_row<S: AsRef<str>>(fields: &[S]) -> String {
+    let fields: Vec<String> = fields.iter().map(|f| quote_field(f.as_ref(), ',')).collect();
+    fields.join(",") + "\r\n"
+}
+
+// UTC, so a spreadsheet sorts it the same wherever it was exported
+fn iso_time(timestamp: u64) -> String {
+    chrono::DateTime::from_timestamp(timestamp as i64, 0)
+        .map(|t| t.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+        .unwrap_or_default()
+}
+
+// Multi-valued fields (sources, tags) share one cell
+const LIST_SEPARATOR: &str = "; ";
+
+pub fn history_csv(entries: &[HistoryEntry]) -> String {
+    let mut csv = csv_row(&["query", "display_query", "content_type", "time", "timestamp", "sources", "referrer"]);
+    for entry in entries {
+        csv.push_str(&csv_row(&[
+            entry.query.clone(),
+            entry.display_query.clone(),
+            format!("{:?}", entry.content_type),
+            iso_time(entry.timestamp),
+            entry.timestamp.to_string(),
+            entry.sources.join(LIST_SEPARATOR),
+            entry.referrer.clone().unwrap_or_default(),
+        ]));
+    }
+    csv
+}
+
+// The lookup snapshot stays out of the CSV; the JSON export keeps it
+pub fn favorites_csv(entries: &[FavoriteEntry]) -> String {
+    let mut csv = csv_row(&["word", "note", "tags", "saved", "saved_at"]);
+    for entry in entries {
+        csv.push_str(&csv_row(&[
+            entry.word.clone(),
+            entry.note.clone().unwrap_or_default(),
+            entry.tags.join(LIST_SEPARATOR),
+            iso_time(entry.saved_at),
+            entry.saved_at.to_string(),
+        ]));
+    }
+    csv
+}
+
+fn json<T: serde::Serialize>(entries: &[T]) -> io::Result<String> {
+    serde_json::to_string_pretty(entries).map(|json| json + "\n").map_err(io::Error::other)
+}
+
+pub fn render_history(entries: &[HistoryEntry], format: ExportFormat) -> io::Result<String> {
+    match format {
+        ExportFormat::Csv => Ok(history_csv(entries)),
+        ExportFormat::Json => json(entries),
+    }
+}
+
+pub fn render_favorites(entries: &[FavoriteEntry], format: ExportFormat) -> io::Result<String> {
+    match format {
+        ExportFormat::Csv => Ok(favorites_csv(entries)),
+        ExportFormat::Json => json(entries),
+    }
+}
+
+// The whole history, oldest first, written to `path`. Returns how many
+// entries there were.
+pub async fn export_history(format: ExportFormat, path: &Path) -> io::Result<usize> {
+    let entries = crate::history::all().await?;
+    write(path, &render_history(&entries, format)?).await?;
+    Ok(entries.len())
+}
+
+pub async fn export_favorites(format: ExportFormat, path: &Path) -> io::Result<usize> {
+    let entries = crate::favorites::list().await?;
+    write(path, &render_favorites(&entries, format)?).await?;
+    Ok(entries.len())
+}
+
+// e.g. "touchdictionary-history-2024-05-01.csv"
+pub fn default_file_name(what: &str, format: ExportFormat) -> String {
+    format!("touchdictionary-{}-{}.{}", what, chrono::Local::now().format("%Y-%m-%d"), format.extension())
+}
+
+async fn write(path: &Path, contents: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    tokio::fs::write(path, contents).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lookup::ContentType;
+
+    #[test]
+    fn test_favorites_csv_quotes_fields() {
+        let entries = vec![FavoriteEntry {
+            word: "petrichor".to_string(),
+            note: Some("smell of rain, after \"dry\" spells\nlovely".to_string()),
+            tags: vec!["nature".to_string(), "weather".to_string()],
+            saved_at: 1_700_000_000,
+            result: None,
+        }];
+
+        assert_eq!(
+            favorites_csv(&entries),
+            "word,note,tags,saved,saved_at\r\n\
+             petrichor,\"smell of rain, after \"\"dry\"\" spells\nlovely\",nature; weather,2023-11-14T22:13:20Z,1700000000\r\n"
+        );
+    }
+
+    #[test]
+    fn test_history_csv() {
+        let entries = vec![HistoryEntry {
+            query: "bank".to_string(),
+            display_query: "bank".to_string(),
+            content_type: ContentType::Word,
+            timestamp: 0,
+            sources: vec!["Free Dictionary API".to_string(), "Wikipedia".to_string()],
+            referrer: Some("money".to_string()),
+            deferred: false,
+        }];
+
+        let csv = history_csv(&entries);
+        assert_eq!(csv.lines().nth(1), Some("bank,bank,Word,1970-01-01T00:00:00Z,0,Free Dictionary API; Wikipedia,money"));
+        assert_eq!("csv".parse(), Ok(ExportFormat::Csv));
+        assert!("xlsx".parse::<ExportFormat>().is_err());
+    }
+}
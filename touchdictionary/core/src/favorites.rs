@@ -0,0 +1,230 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::lookup::{self, LookupOptions, LookupResult};
+use crate::storage::{self, FavoritesRepo, Storage};
+
+// A word saved for later review
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FavoriteEntry {
+    pub word: String,
+    pub note: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    // Seconds since the Unix epoch
+    pub saved_at: u64,
+    // The lookup as it was when saved, so favorites can be reviewed offline
+    pub result: Option<LookupResult>,
+}
+
+// Saved words: a table in the shared database, one row per word
+#[derive(Debug, Clone)]
+pub struct Favorites {
+    repo: FavoritesRepo,
+}
+
+impl Favorites {
+    pub fn new(repo: FavoritesRepo) -> Self {
+        Favorites { repo }
+    }
+
+    pub fn open_default() -> Option<Self> {
+        storage::open_default().map(|storage| Favorites::new(storage.favorites()))
+    }
+
+    // Save `entry`, replacing an earlier favorite for the same word
+    pub async fn add(&self, entry: FavoriteEntry) -> io::Result<()> {
+        Ok(self.repo.add(&entry).await?)
+    }
+
+    // Returns whether the word was saved
+    pub async fn remove(&self, word: &str) -> io::Result<bool> {
+        Ok(self.repo.remove(word).await?)
+    }
+
+    // Most recently saved first
+    pub async fn list(&self) -> io::Result<Vec<FavoriteEntry>> {
+        let mut entries = self.repo.all().await?;
+        entries.reverse();
+        Ok(entries)
+    }
+
+    // Favorites carrying `tag` (case-insensitive), most recently saved first
+    pub async fn list_by_tag(&self, tag: &str) -> io::Result<Vec<FavoriteEntry>> {
+        let tag = tag.trim().to_lowercase();
+        let mut entries = self.list().await?;
+        entries.retain(|e| e.tags.iter().any(|t| t.to_lowercase() == tag));
+        Ok(entries)
+    }
+
+    pub async fn export_json(&self) -> io::Result<String> {
+        serde_json::to_string_pretty(&self.list().await?).map_err(io::Error::other)
+    }
+}
+
+// Where the favorites were kept before the database
+const LEGACY_FILE: &str = "favorites.json";
+
+fn legacy_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("touchdictionary").join(LEGACY_FILE))
+}
+
+pub(crate) fn import_legacy_file(storage: &std::sync::Arc<Storage>) {
+    if let Some(path) = legacy_path() {
+        import_file(storage, &path);
+    }
+}
+
+// Move a favorites file from before the database into it, like the history's
+fn import_file(storage: &std::sync::Arc<Storage>, path: &Path) {
+    let Ok(data) = std::fs::read_to_string(path) else {
+        return;
+    };
+    let result = serde_json::from_str::<Vec<FavoriteEntry>>(&data)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        .and_then(|entries| Ok((storage.favorites().import_blocking(LEGACY_FILE, &entries)?, entries.len())));
+    match result {
+        Ok((true, count)) => info!("Imported {} favorites from {}", count, path.display()),
+        Ok((false, _)) => {}
+        Err(e) => {
+            warn!("Failed to import {}: {}", path.display(), e);
+            return;
+        }
+    }
+    if let Err(e) = std::fs::rename(path, path.with_extension("json.imported")) {
+        warn!("Failed to move {} aside after importing it: {}", path.display(), e);
+    }
+}
+
+// Save `word` with a snapshot of its lookup. The lookup goes through the
+// cache, so a word looked up recently is saved without a network round
+// trip, and is left out of the history since saving isn't looking up. If
+// it fails the word is still saved, just without a snapshot.
+pub async fn add(word: &str, note: Option<String>, tags: Vec<String>) -> io::Result<FavoriteEntry> {
+    let favorites = open_or_err()?;
+
+    let options = LookupOptions { skip_history: true, ..LookupOptions::default() };
+    let result = match lookup::lookup(word, &options).await {
+        Ok(result) => Some(result),
+        Err(e) => {
+            warn!("Saving '{}' without a lookup snapshot: {}", word, e);
+            None
+        }
+    };
+
+    let entry = FavoriteEntry {
+        word: word.trim().to_string(),
+        note: note.filter(|n| !n.trim().is_empty()),
+        tags: normalize_tags(tags),
+        saved_at: now_secs(),
+        result,
+    };
+    favorites.add(entry.clone()).await?;
+    info!("Saved '{}'", entry.word);
+    Ok(entry)
+}
+
+pub async fn remove(word: &str) -> io::Result<bool> {
+    match Favorites::open_default() {
+        Some(favorites) => favorites.remove(word).await,
+        None => Ok(false),
+    }
+}
+
+pub async fn list() -> io::Result<Vec<FavoriteEntry>> {
+    match Favorites::open_default() {
+        Some(favorites) => favorites.list().await,
+        None => Ok(Vec::new()),
+    }
+}
+
+pub async fn list_by_tag(tag: &str) -> io::Result<Vec<FavoriteEntry>> {
+    match Favorites::open_default() {
+        Some(favorites) => favorites.list_by_tag(tag).await,
+        None => Ok(Vec::new()),
+    }
+}
+
+pub async fn export_json() -> io::Result<String> {
+    match Favorites::open_default() {
+        Some(favorites) => favorites.export_json().await,
+        None => Ok("[]".to_string()),
+    }
+}
+
+fn open_or_err() -> io::Result<Favorites> {
+    Favorites::open_default().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory"))
+}
+
+// Trimmed, without blanks or repeats
+fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().to_string();
+        if !tag.is_empty() && !normalized.iter().any(|t| t.eq_ignore_ascii_case(&tag)) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(word: &str, tags: &[&str], saved_at: u64) -> FavoriteEntry {
+        FavoriteEntry {
+            word: word.to_string(),
+            note: None,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            saved_at,
+            result: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_list_tag_remove() {
+        let dir = std::env::temp_dir().join(format!("touchdictionary-favorites-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let favorites = Favorites::new(Storage::open(&dir.join(storage::FILE_NAME)).unwrap().favorites());
+
+        assert!(favorites.list().await.unwrap().is_empty());
+
+        favorites.add(entry("serendipity", &["gre"], 1)).await.unwrap();
+        favorites.add(entry("ephemeral", &["GRE", "poetry"], 2)).await.unwrap();
+        favorites.add(entry("Serendipity", &["luck"], 3)).await.unwrap();
+
+        let words: Vec<String> = favorites.list().await.unwrap().into_iter().map(|e| e.word).collect();
+        assert_eq!(words, vec!["Serendipity", "ephemeral"]);
+
+        let tagged = favorites.list_by_tag("gre").await.unwrap();
+        assert_eq!(tagged.len(), 1);
+        assert_eq!(tagged[0].word, "ephemeral");
+
+        let exported: Vec<FavoriteEntry> = serde_json::from_str(&favorites.export_json().await.unwrap()).unwrap();
+        assert_eq!(exported.len(), 2);
+
+        assert!(favorites.remove("SERENDIPITY").await.unwrap());
+        assert!(!favorites.remove("serendipity").await.unwrap());
+        assert_eq!(favorites.list().await.unwrap().len(), 1);
+
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+    }
+
+    #[test]
+    fn test_normalize_tags() {
+        let tags = vec![" gre ".to_string(), String::new(), "GRE".to_string(), "poetry".to_string()];
+        assert_eq!(normalize_tags(tags), vec!["gre", "poetry"]);
+    }
+}
@@ -0,0 +1,1750 @@
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use base64::Engine;
+use serde::Serialize;
+
+use crate::export;
+use crate::language;
+use crate::lookup::{CompareResult, ComparedWord, Completeness, LookupResult, Sections, SourceHealth, SourceOutcome, SourceStatus};
+use crate::terminal;
+
+// Characters of context kept on each side of a search match
+const SNIPPET_CONTEXT_CHARS: usize = 40;
+
+// Synonyms shown after a definition in text output
+const MAX_INLINE_SYNONYMS: usize = 5;
+
+// How a lookup result is rendered for output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Markdown,
+    Json,
+    // One compact JSON object per line, for batch output
+    Ndjson,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "text" | "plain" => Ok(OutputFormat::Text),
+            "markdown" | "md" => Ok(OutputFormat::Markdown),
+            "json" => Ok(OutputFormat::Json),
+            "ndjson" | "jsonl" => Ok(OutputFormat::Ndjson),
+            other => Err(format!("Unknown format '{}', expected text, markdown, json or ndjson", other)),
+        }
+    }
+}
+
+// How `render_text` lays its report out
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextOptions {
+    // ANSI colors: the word in bold, parts of speech in cyan, sources dimmed
+    pub color: bool,
+    // Wrap definitions and the Wikipedia summary to this many columns
+    pub width: Option<usize>,
+}
+
+impl TextOptions {
+    // For printing to stdout. Colors only go to a terminal, and not with
+    // `no_color` or NO_COLOR; lines wrap at `width`, or the terminal's.
+    pub fn for_stdout(width: Option<usize>, no_color: bool) -> Self {
+        let on_terminal = terminal::stdout_is_terminal();
+        TextOptions {
+            color: on_terminal && !no_color && !terminal::no_color_requested(),
+            width: width.or_else(|| on_terminal.then(terminal::width).flatten()),
+        }
+    }
+
+    fn paint(&self, code: &str, text: &str) -> String {
+        if self.color {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn bold(&self, text: &str) -> String {
+        self.paint("1", text)
+    }
+
+    fn dim(&self, text: &str) -> String {
+        self.paint("2", text)
+    }
+
+    fn part_of_speech(&self, text: &str) -> String {
+        self.paint("36", text)
+    }
+
+    // `line` split at spaces to fit the width, continuation lines indented
+    // by `indent`. A word longer than the width gets a line to itself.
+    fn wrap(&self, line: &str, indent: &str) -> Vec<String> {
+        let Some(width) = self.width else {
+            return vec![line.to_string()];
+        };
+        let leading = &line[..line.len() - line.trim_start().len()];
+        let mut lines = Vec::new();
+        let mut current = leading.to_string();
+        let mut empty = true;
+        for word in line.split_whitespace() {
+            let length = current.chars().count() + usize::from(!empty) + word.chars().count();
+            if !empty && length > width {
+                lines.push(std::mem::replace(&mut current, indent.to_string()));
+                empty = true;
+            }
+            if !empty {
+                current.push(' ');
+            }
+            current.push_str(word);
+            empty = false;
+        }
+        lines.push(current);
+        lines
+    }
+}
+
+pub fn render(result: &LookupResult, format: OutputFormat, text: &TextOptions) -> String {
+    match format {
+        OutputFormat::Text => render_text(result, text),
+        OutputFormat::Markdown => render_markdown(result),
+        OutputFormat::Json => render_json(result),
+        OutputFormat::Ndjson => render_ndjson(result),
+    }
+}
+
+pub fn render_json(result: &LookupResult) -> String {
+    serde_json::to_string_pretty(result).unwrap_or_else(|e| format!("{{\"error\": \"{}\"}}", e))
+}
+
+pub fn render_ndjson(result: &LookupResult) -> String {
+    serde_json::to_string(result).unwrap_or_else(|e| format!("{{\"error\": \"{}\"}}", e))
+}
+
+// The plain-text report printed by the CLI
+// Which sources are missing from a partial result
+fn incomplete_note(result: &LookupResult) -> Option<String> {
+    match &result.completeness {
+        Completeness::Partial { failed_sources } => {
+            Some(format!("results may be incomplete, {} failed", failed_sources.join(", ")))
+        }
+        _ => None,
+    }
+}
+
+// "common (rank 1,204)"
+fn frequency_note(result: &LookupResult) -> Option<String> {
+    let frequency = result.frequency?;
+    Some(format!("{} (rank {})", frequency.band.label(), group_digits(frequency.rank.into())))
+}
+
+pub(crate) fn group_digits(n: u64) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+// "3 noun and 1 verb definitions hidden" for a part-of-speech filter
+fn hidden_note(result: &LookupResult) -> Option<String> {
+    if result.hidden_definitions.is_empty() {
+        return None;
+    }
+    let counts: Vec<String> = result
+        .hidden_definitions
+        .iter()
+        .map(|(part_of_speech, count)| format!("{} {}", count, part_of_speech))
+        .collect();
+    let total: usize = result.hidden_definitions.values().sum();
+    let noun = if total == 1 { "definition" } else { "definitions" };
+    Some(format!("{} {} hidden by the part-of-speech filter", counts.join(" and "), noun))
+}
+
+pub fn render_text(result: &LookupResult, options: &TextOptions) -> String {
+    let mut lines = vec![String::new(), "=== TouchDictionary Result ===".to_string()];
+    lines.push(format!("Query: {}", options.bold(&result.query)));
+    if result.is_word_of_the_day {
+        lines.push("(word of the day)".to_string());
+    }
+    if let Some(original) = &result.corrected_from {
+        lines.push(format!("(corrected from '{}')", original));
+    }
+    if let Some(inflected) = &result.lemma_of {
+        lines.push(format!("(showing results for '{}' from '{}')", result.query, inflected));
+    }
+    let pronunciations = collect_pronunciations(result);
+    if !pronunciations.is_empty() {
+        lines.push(format!("Pronunciation: {}", pronunciations.join(", ")));
+    }
+    if let Some(note) = frequency_note(result) {
+        lines.push(format!("Frequency: {}", note));
+    }
+    lines.push(format!("Content Type: {:?}", result.content_type));
+    if !result.language_used.is_empty() && result.language_used != result.language {
+        lines.push(format!("Language: {} (detected; {} was requested)", result.language_used, result.language));
+    } else if result.language != language::DEFAULT_LANGUAGE {
+        lines.push(format!("Language: {}", result.language));
+    }
+    if result.from_cache {
+        lines.push("(served from cache)".to_string());
+    }
+    for warning in &result.warnings {
+        lines.push(format!("Note: {}", warning));
+    }
+    if let Some(note) = incomplete_note(result) {
+        lines.push(format!("Note: {}", note));
+    }
+    if let Some(note) = hidden_note(result) {
+        lines.push(format!("Note: {}", note));
+    }
+    lines.push(String::new());
+
+    push_text_sections(&mut lines, result, options);
+
+    // A passage: each keyword under its own heading
+    for keyword in result.sections.keyword_lookups.iter().flatten() {
+        lines.push(format!("--- {} ---", options.bold(&keyword.keyword)));
+        lines.push(String::new());
+        push_text_sections(&mut lines, &keyword.result, options);
+    }
+
+    lines.push("========================".to_string());
+    lines.join("\n")
+}
+
+fn push_text_sections(lines: &mut Vec<String>, result: &LookupResult, options: &TextOptions) {
+    // A quantity converted, or facts about a year
+    if let Some(computed) = &result.sections.computed {
+        lines.push(format!("[COMPUTED] {}", computed.input));
+        for interpretation in &computed.interpretations {
+            lines.push(format!("  - {}: {}", interpretation.label, options.bold(&interpretation.value)));
+        }
+        lines.push(String::new());
+    }
+
+    // Words fitting a wildcard pattern
+    if let Some(pattern) = &result.sections.pattern {
+        lines.push("[PATTERN] Source: Datamuse".to_string());
+        for found in &pattern.matches {
+            lines.push(format!("  - {} ({})", found.word, found.score));
+        }
+        lines.push(String::new());
+    }
+
+    // What an acronym stands for
+    if let Some(acronym) = &result.sections.acronym {
+        lines.push("[ACRONYM]".to_string());
+        for expansion in &acronym.expansions {
+            match &expansion.description {
+                Some(description) => lines.push(format!("  - {}: {}", expansion.expansion, description)),
+                None => lines.push(format!("  - {}", expansion.expansion)),
+            }
+            if let Some(url) = &expansion.url {
+                lines.push(format!("    {}", url));
+            }
+        }
+        lines.push(String::new());
+    }
+
+    // Definitions
+    if let Some(definitions) = &result.sections.definitions {
+        for section in definitions {
+            if section.language != result.language {
+                lines.push(format!("[DEFINITION] Source: {}", options.dim(&format!("{} ({})", section.source, section.language))));
+            } else {
+                lines.push(format!("[DEFINITION] Source: {}", options.dim(&section.source)));
+            }
+            for def in &section.definitions {
+                let synonyms = if def.synonyms.is_empty() {
+                    String::new()
+                } else {
+                    let shown: Vec<&str> = def.synonyms.iter().take(MAX_INLINE_SYNONYMS).map(String::as_str).collect();
+                    format!(" (syn: {})", shown.join(", "))
+                };
+                // Only show part of speech if it's not empty. Wrapped before
+                // it's colored so escapes don't count towards the width.
+                let mut wrapped = match def.part_of_speech.as_deref() {
+                    Some(pos) if !pos.is_empty() => {
+                        let mut wrapped = options.wrap(&format!("  - ({}): {}{}", pos, def.definition, synonyms), "    ");
+                        let label = format!("({})", pos);
+                        wrapped[0] = wrapped[0].replacen(&label, &options.part_of_speech(&label), 1);
+                        wrapped
+                    }
+                    _ => options.wrap(&format!("  - {}{}", def.definition, synonyms), "    "),
+                };
+                if let Some(translated) = &def.translated {
+                    wrapped.extend(options.wrap(&format!("    Translation: {}", translated), "      "));
+                }
+                if let Some(example) = &def.example {
+                    wrapped.extend(options.wrap(&format!("    Example: {}", example), "      "));
+                }
+                if let Some(also) = also_in(&section.source, &def.sources) {
+                    wrapped.push(options.dim(&format!("    Also in: {}", also)));
+                }
+                lines.extend(wrapped);
+            }
+            if section.truncated {
+                let hidden = section.total_available - section.definitions.len();
+                lines.push(format!("  ({} more, use --all to see them)", hidden));
+            }
+            lines.push(String::new());
+        }
+    }
+
+    // Etymology
+    if let Some(etymology) = &result.sections.etymology {
+        lines.push(format!("[ETYMOLOGY] Source: {}", options.dim(&etymology.source)));
+        lines.push(etymology.text.clone());
+        if let Some(origin) = &etymology.language_of_origin {
+            lines.push(format!("  Origin: {}", origin));
+        }
+        lines.push(String::new());
+    }
+
+    // Example sentences
+    if let Some(examples) = &result.sections.examples {
+        lines.push(format!("[EXAMPLES] Source: {}", options.dim(&examples.source)));
+        for sentence in &examples.sentences {
+            lines.push(format!("  - {}", sentence));
+        }
+        lines.push(String::new());
+    }
+
+    // Wikipedia section
+    if let Some(wiki) = &result.sections.wikipedia {
+        lines.push(format!("[WIKIPEDIA] {}", wiki.title));
+        if wiki.redirected_from.is_some() {
+            lines.push(format!("→ redirected to {}", wiki.title));
+        }
+        for line in wiki.summary.lines() {
+            lines.extend(options.wrap(line, ""));
+        }
+        if let Some(translated) = &wiki.translated_summary {
+            lines.push(format!("Translation: {}", translated));
+        }
+        if !wiki.url.is_empty() {
+            lines.push(format!("URL: {}", wiki.url));
+        }
+        if !wiki.related.is_empty() {
+            lines.push("Related:".to_string());
+            for page in &wiki.related {
+                match &page.description {
+                    Some(description) => lines.push(format!("  - {} ({})", page.title, description)),
+                    None => lines.push(format!("  - {}", page.title)),
+                }
+            }
+        }
+        lines.push(String::new());
+    }
+
+    // Facts about the entity from Wikidata
+    if let Some(infobox) = &result.sections.infobox {
+        lines.push("[FACTS] Source: Wikidata".to_string());
+        for fact in &infobox.facts {
+            lines.push(format!("  - {}: {}", fact.label, fact.value));
+        }
+        lines.push(String::new());
+    }
+
+    // Thesaurus
+    if let Some(thesaurus) = &result.sections.thesaurus {
+        lines.push("[THESAURUS]".to_string());
+        if !thesaurus.synonyms.is_empty() {
+            lines.push(format!("  Synonyms: {}", thesaurus.synonyms.join(", ")));
+        }
+        if !thesaurus.antonyms.is_empty() {
+            lines.push(format!("  Antonyms: {}", thesaurus.antonyms.join(", ")));
+        }
+        if !thesaurus.related_terms.is_empty() {
+            lines.push(format!("  Related: {}", thesaurus.related_terms.join(", ")));
+        }
+        lines.push(String::new());
+    }
+
+    // Rhymes
+    if let Some(rhymes) = &result.sections.rhymes {
+        lines.push("[RHYMES]".to_string());
+        if !rhymes.perfect.is_empty() {
+            lines.push(format!("  Perfect: {}", rhymes.perfect.join(", ")));
+        }
+        if !rhymes.near.is_empty() {
+            lines.push(format!("  Near: {}", rhymes.near.join(", ")));
+        }
+        if !rhymes.sounds_like.is_empty() {
+            lines.push(format!("  Sounds like: {}", rhymes.sounds_like.join(", ")));
+        }
+        lines.push(String::new());
+    }
+
+    if let Some(suggestions) = &result.suggestions {
+        lines.push(format!("Did you mean: {}?", suggestions.join(", ")));
+        lines.push(String::new());
+    }
+}
+
+// Markdown suited to pasting into notes: a heading per section, numbered
+// definitions with bold parts of speech, the Wikipedia summary quoted
+pub fn render_markdown(result: &LookupResult) -> String {
+    let mut lines = vec![format!("# {}", result.display_query)];
+    lines.push(String::new());
+    if result.is_word_of_the_day {
+        lines.push("_Word of the day_".to_string());
+        lines.push(String::new());
+    }
+
+    let pronunciations = collect_pronunciations(result);
+    if !pronunciations.is_empty() {
+        lines.push(format!("*{}*", pronunciations.join(", ")));
+        lines.push(String::new());
+    }
+    if let Some(note) = frequency_note(result) {
+        lines.push(format!("Frequency: {}", note));
+        lines.push(String::new());
+    }
+    if let Some(original) = &result.corrected_from {
+        lines.push(format!("_Corrected from \"{}\"_", original));
+        lines.push(String::new());
+    }
+    if let Some(inflected) = &result.lemma_of {
+        lines.push(format!("_Showing results for \"{}\" (from \"{}\")_", result.query, inflected));
+        lines.push(String::new());
+    }
+    for warning in &result.warnings {
+        lines.push(format!("_Note: {}_", warning));
+        lines.push(String::new());
+    }
+    if let Some(note) = incomplete_note(result) {
+        lines.push(format!("_Note: {}_", note));
+        lines.push(String::new());
+    }
+    if let Some(note) = hidden_note(result) {
+        lines.push(format!("_Note: {}_", note));
+        lines.push(String::new());
+    }
+
+    if let Some(computed) = &result.sections.computed {
+        lines.push("## Computed".to_string());
+        lines.push(String::new());
+        for interpretation in &computed.interpretations {
+            lines.push(format!("- {}: **{}**", interpretation.label, interpretation.value));
+        }
+        lines.push(String::new());
+    }
+
+    if let Some(pattern) = &result.sections.pattern {
+        lines.push("## Matches".to_string());
+        lines.push(String::new());
+        for found in &pattern.matches {
+            lines.push(format!("- **{}** ({})", found.word, found.score));
+        }
+        lines.push(String::new());
+    }
+
+    if let Some(acronym) = &result.sections.acronym {
+        lines.push("## Stands for".to_string());
+        lines.push(String::new());
+        for expansion in &acronym.expansions {
+            let name = match &expansion.url {
+                Some(url) => format!("[{}]({})", expansion.expansion, url),
+                None => expansion.expansion.clone(),
+            };
+            match &expansion.description {
+                Some(description) => lines.push(format!("- {}: {}", name, description)),
+                None => lines.push(format!("- {}", name)),
+            }
+        }
+        lines.push(String::new());
+    }
+
+    if let Some(definitions) = &result.sections.definitions {
+        lines.push("## Definitions".to_string());
+        lines.push(String::new());
+        for section in definitions {
+            lines.push(format!("### {}", section.source));
+            lines.push(String::new());
+            for (i, def) in section.definitions.iter().enumerate() {
+                match def.part_of_speech.as_deref() {
+                    Some(pos) if !pos.is_empty() => lines.push(format!("{}. **{}** {}", i + 1, pos, def.definition)),
+                    _ => lines.push(format!("{}. {}", i + 1, def.definition)),
+                }
+                if let Some(translated) = &def.translated {
+                    lines.push(format!("   _{}_", translated));
+                }
+                if let Some(example) = &def.example {
+                    lines.push(format!("   > {}", example));
+                }
+                if let Some(also) = also_in(&section.source, &def.sources) {
+                    lines.push(format!("   _Also in {}_", also));
+                }
+            }
+            lines.push(String::new());
+        }
+    }
+
+    if let Some(etymology) = &result.sections.etymology {
+        lines.push("## Etymology".to_string());
+        lines.push(String::new());
+        for paragraph in etymology.text.lines() {
+            lines.push(paragraph.to_string());
+            lines.push(String::new());
+        }
+        if let Some(origin) = &etymology.language_of_origin {
+            lines.push(format!("**Origin:** {}", origin));
+            lines.push(String::new());
+        }
+    }
+
+    if let Some(examples) = &result.sections.examples {
+        lines.push("## Examples".to_string());
+        lines.push(String::new());
+        for (i, sentence) in examples.sentences.iter().enumerate() {
+            let occurrences = examples.occurrences.get(i).map(Vec::as_slice).unwrap_or_default();
+            lines.push(format!("- {}", embolden(sentence, occurrences)));
+        }
+        lines.push(String::new());
+    }
+
+    if let Some(wiki) = &result.sections.wikipedia {
+        lines.push(format!("## Wikipedia: {}", wiki.title));
+        if let Some(from) = &wiki.redirected_from {
+            lines.push(String::new());
+            lines.push(format!("_Redirected from {}_", from));
+        }
+        lines.push(String::new());
+        let paragraphs: Vec<&str> = if wiki.paragraphs.is_empty() {
+            vec![wiki.summary.as_str()]
+        } else {
+            wiki.paragraphs.iter().map(String::as_str).collect()
+        };
+        for (i, paragraph) in paragraphs.iter().enumerate() {
+            if i > 0 {
+                lines.push(">".to_string());
+            }
+            lines.push(format!("> {}", paragraph));
+        }
+        lines.push(String::new());
+        if let Some(translated) = &wiki.translated_summary {
+            lines.push(format!("_{}_", translated));
+            lines.push(String::new());
+        }
+        if !wiki.url.is_empty() {
+            lines.push(format!("[Read more on Wikipedia]({})", wiki.url));
+            lines.push(String::new());
+        }
+        if !wiki.related.is_empty() {
+            lines.push("### See also".to_string());
+            lines.push(String::new());
+            for page in &wiki.related {
+                match &page.description {
+                    Some(description) => lines.push(format!("- [{}]({}) — {}", page.title, page.url, description)),
+                    None => lines.push(format!("- [{}]({})", page.title, page.url)),
+                }
+            }
+            lines.push(String::new());
+        }
+    }
+
+    if let Some(infobox) = &result.sections.infobox {
+        lines.push("## Facts".to_string());
+        lines.push(String::new());
+        for fact in &infobox.facts {
+            lines.push(format!("- {}: **{}**", fact.label, fact.value));
+        }
+        lines.push(String::new());
+    }
+
+    if let Some(thesaurus) = &result.sections.thesaurus {
+        lines.push("## Thesaurus".to_string());
+        lines.push(String::new());
+        for (label, terms) in [
+            ("Synonyms", &thesaurus.synonyms),
+            ("Antonyms", &thesaurus.antonyms),
+            ("Related", &thesaurus.related_terms),
+        ] {
+            if !terms.is_empty() {
+                lines.push(format!("- **{}:** {}", label, terms.join(", ")));
+            }
+        }
+        lines.push(String::new());
+    }
+
+    if let Some(rhymes) = &result.sections.rhymes {
+        lines.push("## Rhymes".to_string());
+        lines.push(String::new());
+        for (label, words) in [("Perfect", &rhymes.perfect), ("Near", &rhymes.near), ("Sounds like", &rhymes.sounds_like)] {
+            if !words.is_empty() {
+                lines.push(format!("- **{}:** {}", label, words.join(", ")));
+            }
+        }
+        lines.push(String::new());
+    }
+
+    if let Some(suggestions) = &result.suggestions {
+        lines.push(format!("Did you mean: {}?", suggestions.join(", ")));
+        lines.push(String::new());
+    }
+
+    // A passage: each keyword as a section of its own, headings one level down
+    for keyword in result.sections.keyword_lookups.iter().flatten() {
+        let rendered = render_markdown(&keyword.result);
+        lines.push(format!("## {}", keyword.keyword));
+        lines.push(String::new());
+        for line in rendered.lines().skip(1).skip_while(|line| line.is_empty()) {
+            if line.starts_with('#') {
+                lines.push(format!("#{}", line));
+            } else {
+                lines.push(line.to_string());
+            }
+        }
+    }
+
+    lines.join("\n").trim_end().to_string() + "\n"
+}
+
+// Styles inlined into the HTML export, so the file stands on its own
+const HTML_STYLE: &str = "\
+body { font-family: system-ui, sans-serif; max-width: 42em; margin: 2em auto; padding: 0 1em; color: #2c3e50; line-height: 1.5; }
+h1 { margin-bottom: 0; }
+h2 { border-bottom: 1px solid #dde3e8; padding-bottom: 4px; margin-top: 1.5em; }
+h3 { color: #7f8c8d; font-size: 0.9em; text-transform: uppercase; }
+.meta, .note, .source { color: #7f8c8d; font-size: 0.9em; }
+.pronunciation { font-style: italic; }
+.pos { color: #3daee9; font-style: italic; margin-right: 0.4em; }
+.example { color: #566573; font-style: italic; }
+blockquote { margin: 0.5em 0; padding-left: 1em; border-left: 3px solid #dde3e8; }
+img { max-width: 100%; border-radius: 4px; }
+";
+
+// When the HTML export was made and the article image, if it's cached
+#[derive(Debug, Clone, Default)]
+pub struct HtmlOptions {
+    pub generated_at: String,
+    // Embedded as a data URI instead of linking to the image's URL
+    pub image: Option<Vec<u8>>,
+}
+
+impl HtmlOptions {
+    pub fn now() -> Self {
+        HtmlOptions { generated_at: chrono::Local::now().format("%Y-%m-%d %H:%M").to_string(), image: None }
+    }
+}
+
+// A standalone HTML page of the result, stamped with the current time
+pub fn render_html(result: &LookupResult) -> String {
+    render_html_with(result, &HtmlOptions::now())
+}
+
+// The result as a page to save or print, sections in the order the window
+// shows them and the Wikipedia article last
+pub fn render_html_with(result: &LookupResult, options: &HtmlOptions) -> String {
+    let mut html = vec![
+        "<!DOCTYPE html>".to_string(),
+        format!("<html lang=\"{}\">", escape_html(&result.language)),
+        "<head>".to_string(),
+        "<meta charset=\"utf-8\">".to_string(),
+        "<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">".to_string(),
+        format!("<title>{} — TouchDictionary, {}</title>", escape_html(&result.display_query), escape_html(&options.generated_at)),
+        format!("<style>\n{}</style>", HTML_STYLE),
+        "</head>".to_string(),
+        "<body>".to_string(),
+        format!("<h1>{}</h1>", escape_html(&result.display_query)),
+        format!("<p class=\"meta\">Looked up {}</p>", escape_html(&options.generated_at)),
+    ];
+    push_html_sections(&mut html, result, options.image.as_deref(), 2);
+    html.push("</body>".to_string());
+    html.push("</html>".to_string());
+    html.join("\n") + "\n"
+}
+
+fn push_html_sections(html: &mut Vec<String>, result: &LookupResult, image: Option<&[u8]>, level: usize) {
+    let heading = |text: &str| format!("<h{level}>{}</h{level}>", escape_html(text));
+    let subheading = |text: &str| format!("<h{0}>{1}</h{0}>", level + 1, escape_html(text));
+    let list = |items: &[String]| escape_html(&items.join(", "));
+
+    if result.is_word_of_the_day {
+        html.push("<p class=\"note\">Word of the day</p>".to_string());
+    }
+    let pronunciations = collect_pronunciations(result);
+    if !pronunciations.is_empty() {
+        html.push(format!("<p class=\"pronunciation\">{}</p>", list(&pronunciations)));
+    }
+    if let Some(note) = frequency_note(result) {
+        html.push(format!("<p class=\"frequency\">Frequency: {}</p>", escape_html(&note)));
+    }
+    let mut notes = Vec::new();
+    if let Some(original) = &result.corrected_from {
+        notes.push(format!("Corrected from \"{}\"", original));
+    }
+    if let Some(inflected) = &result.lemma_of {
+        notes.push(format!("Showing results for \"{}\" (from \"{}\")", result.query, inflected));
+    }
+    notes.extend(result.warnings.iter().cloned());
+    notes.extend(incomplete_note(result));
+    notes.extend(hidden_note(result));
+    for note in notes {
+        html.push(format!("<p class=\"note\">{}</p>", escape_html(&note)));
+    }
+
+    if let Some(suggestions) = &result.suggestions {
+        html.push(format!("<p>Did you mean: {}?</p>", list(suggestions)));
+    }
+
+    // A passage: each keyword as a section of its own, headings one level down
+    for keyword in result.sections.keyword_lookups.iter().flatten() {
+        html.push("<section>".to_string());
+        html.push(heading(&keyword.keyword));
+        push_html_sections(html, &keyword.result, None, level + 1);
+        html.push("</section>".to_string());
+    }
+
+    if let Some(computed) = &result.sections.computed {
+        html.push(heading("Computed"));
+        html.push("<ul>".to_string());
+        for interpretation in &computed.interpretations {
+            html.push(format!(
+                "<li>{}: <strong>{}</strong></li>",
+                escape_html(&interpretation.label),
+                escape_html(&interpretation.value)
+            ));
+        }
+        html.push("</ul>".to_string());
+    }
+
+    if let Some(pattern) = &result.sections.pattern {
+        html.push(heading("Matches"));
+        html.push("<ul>".to_string());
+        for found in &pattern.matches {
+            html.push(format!("<li><strong>{}</strong> ({})</li>", escape_html(&found.word), found.score));
+        }
+        html.push("</ul>".to_string());
+    }
+
+    if let Some(acronym) = &result.sections.acronym {
+        html.push(heading("Stands for"));
+        html.push("<ul>".to_string());
+        for expansion in &acronym.expansions {
+            let name = match &expansion.url {
+                Some(url) => format!("<a href=\"{}\">{}</a>", escape_html(url), escape_html(&expansion.expansion)),
+                None => escape_html(&expansion.expansion),
+            };
+            match &expansion.description {
+                Some(description) => html.push(format!("<li>{}: {}</li>", name, escape_html(description))),
+                None => html.push(format!("<li>{}</li>", name)),
+            }
+        }
+        html.push("</ul>".to_string());
+    }
+
+    if let Some(definitions) = &result.sections.definitions {
+        html.push(heading("Definitions"));
+        for section in definitions {
+            html.push(subheading(&section.source));
+            html.push("<ol>".to_string());
+            for def in &section.definitions {
+                let mut item = String::from("<li>");
+                if let Some(pos) = def.part_of_speech.as_deref().filter(|pos| !pos.is_empty()) {
+                    item.push_str(&format!("<span class=\"pos\">{}</span>", escape_html(pos)));
+                }
+                item.push_str(&escape_html(&def.definition));
+                if let Some(translated) = &def.translated {
+                    item.push_str(&format!("<br><em>{}</em>", escape_html(translated)));
+                }
+                if let Some(example) = &def.example {
+                    item.push_str(&format!("<br><span class=\"example\">“{}”</span>", escape_html(example)));
+                }
+                if !def.synonyms.is_empty() {
+                    item.push_str(&format!("<br><span class=\"source\">Synonyms: {}</span>", list(&def.synonyms)));
+                }
+                if let Some(also) = also_in(&section.source, &def.sources) {
+                    item.push_str(&format!("<br><span class=\"source\">Also in {}</span>", escape_html(&also)));
+                }
+                item.push_str("</li>");
+                html.push(item);
+            }
+            html.push("</ol>".to_string());
+        }
+    }
+
+    if let Some(etymology) = &result.sections.etymology {
+        html.push(heading("Etymology"));
+        for paragraph in etymology.text.lines().filter(|line| !line.trim().is_empty()) {
+            html.push(format!("<p>{}</p>", escape_html(paragraph)));
+        }
+        if let Some(origin) = &etymology.language_of_origin {
+            html.push(format!("<p><strong>Origin:</strong> {}</p>", escape_html(origin)));
+        }
+    }
+
+    if let Some(examples) = &result.sections.examples {
+        html.push(heading("Examples"));
+        html.push("<ul>".to_string());
+        for (i, sentence) in examples.sentences.iter().enumerate() {
+            let occurrences = examples.occurrences.get(i).map(Vec::as_slice).unwrap_or_default();
+            html.push(format!("<li>{}</li>", embolden_html(sentence, occurrences)));
+        }
+        html.push("</ul>".to_string());
+    }
+
+    if let Some(thesaurus) = &result.sections.thesaurus {
+        html.push(heading("Thesaurus"));
+        html.push("<ul>".to_string());
+        for (label, terms) in [
+            ("Synonyms", &thesaurus.synonyms),
+            ("Antonyms", &thesaurus.antonyms),
+            ("Related", &thesaurus.related_terms),
+        ] {
+            if !terms.is_empty() {
+                html.push(format!("<li><strong>{}:</strong> {}</li>", label, list(terms)));
+            }
+        }
+        html.push("</ul>".to_string());
+    }
+
+    if let Some(rhymes) = &result.sections.rhymes {
+        html.push(heading("Rhymes"));
+        html.push("<ul>".to_string());
+        for (label, words) in [("Perfect", &rhymes.perfect), ("Near", &rhymes.near), ("Sounds like", &rhymes.sounds_like)] {
+            if !words.is_empty() {
+                html.push(format!("<li><strong>{}:</strong> {}</li>", label, list(words)));
+            }
+        }
+        html.push("</ul>".to_string());
+    }
+
+    if let Some(wiki) = &result.sections.wikipedia {
+        html.push(heading(&format!("Wikipedia: {}", wiki.title)));
+        let source = match (image.and_then(data_uri), &wiki.image_url) {
+            (Some(data), _) => Some(data),
+            (None, Some(url)) => Some(escape_html(url)),
+            (None, None) => None,
+        };
+        if let Some(source) = source {
+            html.push(format!("<p><img src=\"{}\" alt=\"{}\"></p>", source, escape_html(&wiki.title)));
+        }
+        let paragraphs: Vec<&str> = if wiki.paragraphs.is_empty() {
+            vec![wiki.summary.as_str()]
+        } else {
+            wiki.paragraphs.iter().map(String::as_str).collect()
+        };
+        html.push("<blockquote>".to_string());
+        for paragraph in paragraphs {
+            html.push(format!("<p>{}</p>", escape_html(paragraph)));
+        }
+        html.push("</blockquote>".to_string());
+        if let Some(translated) = &wiki.translated_summary {
+            html.push(format!("<p><em>{}</em></p>", escape_html(translated)));
+        }
+        if !wiki.url.is_empty() {
+            html.push(format!("<p><a href=\"{}\">Read more on Wikipedia</a></p>", escape_html(&wiki.url)));
+        }
+        if !wiki.related.is_empty() {
+            html.push(subheading("See also"));
+            html.push("<ul>".to_string());
+            for page in &wiki.related {
+                let link = format!("<a href=\"{}\">{}</a>", escape_html(&page.url), escape_html(&page.title));
+                match &page.description {
+                    Some(description) => html.push(format!("<li>{} — {}</li>", link, escape_html(description))),
+                    None => html.push(format!("<li>{}</li>", link)),
+                }
+            }
+            html.push("</ul>".to_string());
+        }
+    }
+
+    if let Some(infobox) = &result.sections.infobox {
+        html.push(heading("Facts"));
+        html.push("<dl class=\"facts\">".to_string());
+        for fact in &infobox.facts {
+            html.push(format!("<dt>{}</dt><dd>{}</dd>", escape_html(&fact.label), escape_html(&fact.value)));
+        }
+        html.push("</dl>".to_string());
+    }
+}
+
+// Escaped for both element text and attribute values
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// `image` as a data URI, for the formats browsers show; anything else is
+// linked by URL instead
+fn data_uri(image: &[u8]) -> Option<String> {
+    let mime = if image.starts_with(b"\x89PNG") {
+        "image/png"
+    } else if image.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        "image/jpeg"
+    } else if image.starts_with(b"GIF8") {
+        "image/gif"
+    } else if image.len() > 12 && image.starts_with(b"RIFF") && &image[8..12] == b"WEBP" {
+        "image/webp"
+    } else {
+        return None;
+    };
+    Some(format!("data:{};base64,{}", mime, base64::engine::general_purpose::STANDARD.encode(image)))
+}
+
+// Definitions on the back of an Anki card
+pub const ANKI_MAX_DEFINITIONS: usize = 3;
+
+// Header Anki reads to set up the import: tab separated, plain text, two
+// fields per note
+const ANKI_HEADER: &str = "#separator:tab\n#html:false\n#columns:Front\tBack\n";
+
+// One Anki note as a tab-separated row (no trailing newline). The front is
+// the word and its parts of speech; the back has the top definitions, the
+// first example and the pronunciation. Words with no definitions fall back
+// to the Wikipedia summary.
+pub fn render_anki(result: &LookupResult) -> String {
+    let definitions: Vec<_> = result
+        .sections
+        .definitions
+        .iter()
+        .flatten()
+        .flat_map(|section| &section.definitions)
+        .take(ANKI_MAX_DEFINITIONS)
+        .collect();
+
+    let mut parts_of_speech: Vec<&str> = Vec::new();
+    for def in &definitions {
+        if let Some(pos) = def.part_of_speech.as_deref().filter(|p| !p.is_empty()) {
+            if !parts_of_speech.contains(&pos) {
+                parts_of_speech.push(pos);
+            }
+        }
+    }
+    let front = if parts_of_speech.is_empty() {
+        result.display_query.clone()
+    } else {
+        format!("{} ({})", result.display_query, parts_of_speech.join(", "))
+    };
+
+    let mut back: Vec<String> = definitions
+        .iter()
+        .enumerate()
+        .map(|(i, def)| format!("{}. {}", i + 1, def.definition))
+        .collect();
+    if let Some(example) = definitions.iter().find_map(|def| def.example.as_ref()) {
+        back.push(format!("Example: {}", example));
+    }
+    if back.is_empty() {
+        if let Some(wiki) = &result.sections.wikipedia {
+            back.push(wiki.paragraphs.first().unwrap_or(&wiki.summary).clone());
+        }
+    }
+    let pronunciations = collect_pronunciations(result);
+    if !pronunciations.is_empty() {
+        back.push(pronunciations.join(", "));
+    }
+
+    format!("{}\t{}", export::quote_field(&front, '\t'), export::quote_field(&back.join("\n"), '\t'))
+}
+
+// Append rows from `render_anki` to `path`, writing the import header first
+// if the file is new or empty
+pub fn append_anki(path: &Path, rows: &[String]) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    let mut data = String::new();
+    if file.metadata()?.len() == 0 {
+        data.push_str(ANKI_HEADER);
+    }
+    for row in rows {
+        data.push_str(row);
+        data.push('\n');
+    }
+    file.write_all(data.as_bytes())
+}
+
+// One row per source: outcome, time taken and the error, if any
+pub fn render_source_statuses(statuses: &[SourceStatus]) -> String {
+    let width = statuses.iter().map(|s| s.name.len()).max().unwrap_or(0).max("Source".len());
+    let mut lines = vec![format!("{:<width$}  {:<9}  {:>7}  Error", "Source", "Outcome", "Time")];
+    for status in statuses {
+        let time = match status.outcome {
+            SourceOutcome::Skipped | SourceOutcome::PrivateModeBlocked => "-".to_string(),
+            _ => format!("{}ms", status.duration_ms),
+        };
+        let line = format!(
+            "{:<width$}  {:<9}  {:>7}  {}",
+            status.name,
+            outcome_label(status.outcome),
+            time,
+            status.error.as_deref().unwrap_or("")
+        );
+        lines.push(line.trim_end().to_string());
+    }
+    lines.join("\n")
+}
+
+// `touchdictionary doctor`'s table: whether each source answered, how fast,
+// and what it said
+pub fn render_source_health(health: &[SourceHealth], options: &TextOptions) -> String {
+    let width = health.iter().map(|h| h.name.len()).max().unwrap_or(0).max("Source".len());
+    let mut lines = vec![options.bold(&format!("{:<width$}  {:<6}  {:>7}  Detail", "Source", "Status", "Time"))];
+    for source in health {
+        let status = match source.reachable {
+            true => options.paint("32", "up    "),
+            false => options.paint("31", "down  "),
+        };
+        let line = format!("{:<width$}  {}  {:>7}  {}", source.name, status, format!("{}ms", source.latency_ms), source.status_detail);
+        lines.push(line.trim_end().to_string());
+    }
+    lines.join("\n")
+}
+
+// Definitions per word in `render_comparison`'s columns
+const COMPARE_DEFINITIONS: usize = 5;
+
+// Width `render_comparison` fits its columns in when no width is set
+const COMPARE_WIDTH: usize = 80;
+
+// `touchdictionary compare`: each word's definitions in a column of its
+// own, then the synonyms they share and don't
+pub fn render_comparison(compared: &CompareResult, options: &TextOptions) -> String {
+    let column_width = (options.width.unwrap_or(COMPARE_WIDTH).saturating_sub(3) / 2).max(20);
+    let columns = [&compared.first, &compared.second].map(|word| fit_column(comparison_column(word, column_width), column_width));
+    let heading = |word: &ComparedWord| format!("{:<column_width$}", word.query);
+    let mut lines =
+        vec![format!("{} │ {}", options.bold(&heading(&compared.first)), options.bold(&compared.second.query))];
+    for row in 0..columns[0].len().max(columns[1].len()) {
+        let left = columns[0].get(row).map_or("", String::as_str);
+        let right = columns[1].get(row).map_or("", String::as_str);
+        let line = format!("{:<column_width$} │ {}", left, right);
+        lines.push(line.trim_end().to_string());
+    }
+
+    if compared.first.result.is_some() && compared.second.result.is_some() {
+        lines.push(String::new());
+        let list = |terms: &[String]| if terms.is_empty() { "-".to_string() } else { terms.join(", ") };
+        lines.push(format!("Shared synonyms: {}", list(&compared.shared_synonyms)));
+        lines.push(format!("Only {}: {}", compared.first.query, list(&compared.first_only_synonyms)));
+        lines.push(format!("Only {}: {}", compared.second.query, list(&compared.second_only_synonyms)));
+        lines.push(format!("Parts of speech in common: {}", list(&compared.shared_parts_of_speech)));
+        if compared.thesaurus_link {
+            lines.push(format!("{} and {} are listed as related", compared.first.query, compared.second.query));
+        }
+    }
+    lines.join("\n")
+}
+
+fn comparison_column(word: &ComparedWord, width: usize) -> Vec<String> {
+    let wrapping = TextOptions { color: false, width: Some(width) };
+    let Some(result) = &word.result else {
+        let error = word.error.as_ref().map_or_else(|| "no result".to_string(), |e| e.to_string());
+        return wrapping.wrap(&format!("Lookup failed: {}", error), "");
+    };
+    let definitions = result.sections.definitions.iter().flatten().flat_map(|s| &s.definitions);
+    let mut lines: Vec<String> = definitions
+        .take(COMPARE_DEFINITIONS)
+        .flat_map(|def| match def.part_of_speech.as_deref() {
+            Some(pos) if !pos.is_empty() => wrapping.wrap(&format!("- ({}) {}", pos, def.definition), "  "),
+            _ => wrapping.wrap(&format!("- {}", def.definition), "  "),
+        })
+        .collect();
+    if lines.is_empty() {
+        lines.push("No definitions found".to_string());
+    }
+    lines
+}
+
+// `wrap` leaves a word longer than the width whole; in a column it would
+// push the other column out of line, so it's cut into pieces
+fn fit_column(lines: Vec<String>, width: usize) -> Vec<String> {
+    lines
+        .into_iter()
+        .flat_map(|line| {
+            let chars: Vec<char> = line.chars().collect();
+            chars.chunks(width).map(|chunk| chunk.iter().collect()).collect::<Vec<String>>()
+        })
+        .collect()
+}
+
+fn outcome_label(outcome: SourceOutcome) -> &'static str {
+    match outcome {
+        SourceOutcome::Ok => "ok",
+        SourceOutcome::Empty => "empty",
+        SourceOutcome::Error => "error",
+        SourceOutcome::Skipped => "skipped",
+        SourceOutcome::TimedOut => "timed out",
+        SourceOutcome::Cached => "cached",
+        SourceOutcome::PrivateModeBlocked => "blocked",
+    }
+}
+
+// IPA transcriptions across all definition sections, without repeats
+pub fn collect_pronunciations(result: &LookupResult) -> Vec<String> {
+    let mut pronunciations: Vec<String> = Vec::new();
+    for section in result.sections.definitions.iter().flatten() {
+        for phonetic in section.phonetics.iter().flatten() {
+            if let Some(text) = &phonetic.text {
+                if !pronunciations.contains(text) {
+                    pronunciations.push(text.clone());
+                }
+            }
+        }
+    }
+    pronunciations
+}
+
+// Where `search_sections` found the needle. `group` is the definition
+// section (0 for Wikipedia) and `index` the definition or paragraph in it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchMatch {
+    // "definition", "example" or "wikipedia"
+    pub section: &'static str,
+    pub group: usize,
+    pub index: usize,
+    // The text around the match, with "…" where it was cut
+    pub snippet: String,
+    // Character offset and length of the match within `snippet`
+    pub match_start: usize,
+    pub match_len: usize,
+    // Matched a word spelled almost like the needle rather than the needle
+    pub fuzzy: bool,
+}
+
+// Every definition, example and Wikipedia paragraph containing `needle`,
+// ignoring case, in display order. When nothing contains it, words within a
+// typo or two of it count instead.
+pub fn search_sections(sections: &Sections, needle: &str) -> Vec<SearchMatch> {
+    let needle = needle.trim().to_lowercase();
+    if needle.is_empty() {
+        return Vec::new();
+    }
+
+    let mut texts: Vec<(&'static str, usize, usize, &str)> = Vec::new();
+    for (group, section) in sections.definitions.iter().flatten().enumerate() {
+        for (index, def) in section.definitions.iter().enumerate() {
+            texts.push(("definition", group, index, &def.definition));
+            if let Some(example) = &def.example {
+                texts.push(("example", group, index, example));
+            }
+        }
+    }
+    if let Some(wiki) = &sections.wikipedia {
+        for (index, paragraph) in wiki.paragraphs.iter().enumerate() {
+            texts.push(("wikipedia", 0, index, paragraph));
+        }
+    }
+
+    let find = |fuzzy: bool| -> Vec<SearchMatch> {
+        texts
+            .iter()
+            .filter_map(|&(section, group, index, text)| {
+                let (start, len) = if fuzzy { find_similar_word(text, &needle)? } else { find_ignoring_case(text, &needle)? };
+                let (snippet, match_start) = snippet(text, start, len);
+                Some(SearchMatch { section, group, index, snippet, match_start, match_len: len, fuzzy })
+            })
+            .collect()
+    };
+    let exact = find(false);
+    if exact.is_empty() {
+        find(true)
+    } else {
+        exact
+    }
+}
+
+// Character offset and length of the first case-insensitive occurrence
+fn find_ignoring_case(text: &str, needle: &str) -> Option<(usize, usize)> {
+    let chars: Vec<char> = text.chars().collect();
+    let needle: Vec<char> = needle.chars().collect();
+    (0..chars.len().checked_sub(needle.len())? + 1)
+        .find(|&start| {
+            chars[start..start + needle.len()]
+                .iter()
+                .zip(&needle)
+                .all(|(c, n)| c.to_lowercase().eq(n.to_lowercase()))
+        })
+        .map(|start| (start, needle.len()))
+}
+
+// The first word within the needle's typo allowance, as a character range
+fn find_similar_word(text: &str, needle: &str) -> Option<(usize, usize)> {
+    let needle_len = needle.chars().count();
+    // Short needles would match half the text
+    let allowed = match needle_len {
+        0..=3 => return None,
+        4..=7 => 1,
+        _ => 2,
+    };
+
+    let mut offset = 0;
+    for token in text.split(' ') {
+        let trimmed = token.trim_start_matches(|c: char| !c.is_alphanumeric());
+        let lead = token.chars().count() - trimmed.chars().count();
+        let word = trimmed.trim_end_matches(|c: char| !c.is_alphanumeric());
+        let word_len = word.chars().count();
+        if word_len.abs_diff(needle_len) <= allowed && edit_distance(&word.to_lowercase(), needle) <= allowed {
+            return Some((offset + lead, word_len));
+        }
+        offset += token.chars().count() + 1;
+    }
+    None
+}
+
+// Sources besides `source` that gave a merged definition
+fn also_in(source: &str, sources: &[String]) -> Option<String> {
+    let others: Vec<&str> = sources.iter().map(String::as_str).filter(|s| *s != source).collect();
+    (!others.is_empty()).then(|| others.join(", "))
+}
+
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+// `sentence` with the character ranges in `occurrences` in bold
+fn embolden(sentence: &str, occurrences: &[(usize, usize)]) -> String {
+    let mut bolded = String::new();
+    for (i, c) in sentence.chars().enumerate() {
+        if occurrences.iter().any(|&(start, len)| i == start + len && len > 0) {
+            bolded.push_str("**");
+        }
+        if occurrences.iter().any(|&(start, _)| i == start) {
+            bolded.push_str("**");
+        }
+        bolded.push(c);
+    }
+    if occurrences.iter().any(|&(start, len)| start + len == sentence.chars().count() && len > 0) {
+        bolded.push_str("**");
+    }
+    bolded
+}
+
+// `embolden` for HTML, escaping the rest of the sentence
+fn embolden_html(sentence: &str, occurrences: &[(usize, usize)]) -> String {
+    let mut bolded = String::new();
+    let mut buffer = [0; 4];
+    for (i, c) in sentence.chars().enumerate() {
+        if occurrences.iter().any(|&(start, len)| i == start + len && len > 0) {
+            bolded.push_str("</strong>");
+        }
+        if occurrences.iter().any(|&(start, _)| i == start) {
+            bolded.push_str("<strong>");
+        }
+        bolded.push_str(&escape_html(c.encode_utf8(&mut buffer)));
+    }
+    if occurrences.iter().any(|&(start, len)| start + len == sentence.chars().count() && len > 0) {
+        bolded.push_str("</strong>");
+    }
+    bolded
+}
+
+// `text` cut to some context around the match, and where the match starts
+// in the cut text
+fn snippet(text: &str, start: usize, len: usize) -> (String, usize) {
+    let chars: Vec<char> = text.chars().collect();
+    let from = start.saturating_sub(SNIPPET_CONTEXT_CHARS);
+    let to = (start + len + SNIPPET_CONTEXT_CHARS).min(chars.len());
+
+    let mut snippet = String::new();
+    let mut match_start = start - from;
+    if from > 0 {
+        snippet.push('…');
+        match_start += 1;
+    }
+    snippet.extend(&chars[from..to]);
+    if to < chars.len() {
+        snippet.push('…');
+    }
+    (snippet, match_start)
+}
+
+// Abbreviations whose full stop doesn't end a sentence, lowercased and
+// without that last dot. Single letters, as in initials, are always taken
+// for abbreviations.
+const ABBREVIATIONS: &[&str] = &[
+    "dr", "mr", "mrs", "ms", "prof", "st", "jr", "sr", "rev", "gen", "col", "lt", "sgt", "mt", "ft", "vs", "e.g",
+    "i.e", "cf", "ca", "approx", "no", "nos", "vol", "fig", "inc", "ltd", "co", "corp", "u.s", "u.k", "jan", "feb",
+    "mar", "apr", "jun", "jul", "aug", "sep", "sept", "oct", "nov", "dec",
+];
+
+// The word and its top definition on one line, for other tools to pick up
+// from the clipboard: "ephemeral (adj.): lasting for a very short time".
+// The first sentence of the Wikipedia summary stands in when there are no
+// definitions.
+pub fn one_line_summary(result: &LookupResult) -> Option<String> {
+    let top = result.sections.definitions.iter().flatten().flat_map(|section| &section.definitions).next();
+    let (part_of_speech, text) = match top {
+        Some(definition) => (definition.part_of_speech.as_deref(), definition.definition.as_str()),
+        None => (None, first_sentences(&result.sections.wikipedia.as_ref()?.summary, 1)),
+    };
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let text = text.trim_end_matches('.');
+    if text.is_empty() {
+        return None;
+    }
+    Some(match part_of_speech.filter(|pos| !pos.is_empty()) {
+        Some(pos) => format!("{} ({}): {}", result.query, abbreviate_part_of_speech(pos), text),
+        None => format!("{}: {}", result.query, text),
+    })
+}
+
+fn abbreviate_part_of_speech(part_of_speech: &str) -> String {
+    let abbreviation = match part_of_speech.to_lowercase().as_str() {
+        "noun" => "n.",
+        "verb" => "v.",
+        "adjective" => "adj.",
+        "adverb" => "adv.",
+        "pronoun" => "pron.",
+        "preposition" => "prep.",
+        "conjunction" => "conj.",
+        "interjection" => "interj.",
+        _ => return part_of_speech.to_string(),
+    };
+    abbreviation.to_string()
+}
+
+// Split prose into sentences, each trimmed. A sentence ends at `.`, `!` or
+// `?` (with any closing quotes or brackets) followed by a space and what
+// could start the next one: not "Dr. Watson", "e.g. this", "J. R. R.
+// Tolkien" or "3.14".
+pub fn split_sentences(text: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    for end in sentence_ends(text).into_iter().chain([text.len()]) {
+        let sentence = text[start..end].trim();
+        if !sentence.is_empty() {
+            sentences.push(sentence);
+        }
+        start = end;
+    }
+    sentences
+}
+
+// The first `count` sentences of `text`, as written, or all of it when it
+// has no more than that
+pub fn first_sentences(text: &str, count: usize) -> &str {
+    if count == 0 {
+        return "";
+    }
+    match sentence_ends(text).get(count - 1) {
+        Some(&end) => text[..end].trim_end(),
+        None => text.trim_end(),
+    }
+}
+
+// Byte offsets just past each sentence's final punctuation, except the last
+// sentence's
+fn sentence_ends(text: &str) -> Vec<usize> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut ends = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while i < chars.len() {
+        let (at, c) = chars[i];
+        if !matches!(c, '.' | '!' | '?') {
+            i += 1;
+            continue;
+        }
+        let mut end = i + 1;
+        while end < chars.len() && matches!(chars[end].1, '.' | '!' | '?' | '"' | '\'' | ')' | ']' | '”' | '’') {
+            end += 1;
+        }
+        let spaced = chars.get(end).is_some_and(|(_, c)| c.is_whitespace());
+        let next = chars[end..].iter().map(|(_, c)| *c).find(|c| !c.is_whitespace());
+        let starts_sentence = next.is_some_and(|c| c.is_uppercase() || c.is_numeric() || matches!(c, '"' | '“' | '‘' | '('));
+        if spaced && starts_sentence && !(c == '.' && ends_in_abbreviation(&text[start..at])) {
+            let offset = chars[end].0;
+            ends.push(offset);
+            start = offset;
+        }
+        i = end;
+    }
+    ends
+}
+
+// Whether `before`, the text up to a full stop, ends in an abbreviation
+fn ends_in_abbreviation(before: &str) -> bool {
+    let word = before.rsplit(|c: char| c.is_whitespace() || c == '(').next().unwrap_or("").to_lowercase();
+    let mut letters = word.chars();
+    let initial = matches!((letters.next(), letters.next()), (Some(c), None) if c.is_alphabetic());
+    initial || ABBREVIATIONS.contains(&word.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::LookupError;
+    use crate::lookup::{ComputedSection, ContentType, Fact, InfoboxSection, KeywordLookup};
+    use crate::sources::word_stats::{FrequencyBand, WordFrequency};
+    use crate::units;
+
+    fn fixture() -> LookupResult {
+        serde_json::from_str(include_str!("../tests/fixtures/lookup_result_serendipity.json")).unwrap()
+    }
+
+    #[test]
+    fn test_search_sections() {
+        let sections = fixture().sections;
+
+        let matches = search_sections(&sections, "FORTUNATE");
+        let found: Vec<_> = matches.iter().map(|m| (m.section, m.group, m.index)).collect();
+        assert_eq!(found, vec![("definition", 0, 0), ("wikipedia", 0, 0)]);
+        let wiki = &matches[1];
+        let highlighted: String = wiki.snippet.chars().skip(wiki.match_start).take(wiki.match_len).collect();
+        assert_eq!(highlighted, "fortunate");
+        assert!(!wiki.fuzzy);
+
+        let example = &search_sections(&sections, "tiny shop")[0];
+        assert_eq!((example.section, example.match_start), ("example", 25));
+
+        // Long text is cut around the match
+        let long = &search_sections(&sections, "surprisingly")[0];
+        assert!(long.snippet.starts_with('…'), "{}", long.snippet);
+        assert!(long.snippet.chars().count() <= 2 * SNIPPET_CONTEXT_CHARS + "surprisingly".len() + 2);
+    }
+
+    #[test]
+    fn test_search_sections_fuzzy() {
+        let sections = fixture().sections;
+
+        let matches = search_sections(&sections, "Walpoke");
+        assert_eq!(matches.len(), 1);
+        let found = &matches[0];
+        assert!(found.fuzzy);
+        assert_eq!(found.snippet.chars().skip(found.match_start).take(found.match_len).collect::<String>(), "Walpole");
+
+        assert!(search_sections(&sections, "xyzzy").is_empty());
+        assert!(search_sections(&sections, "  ").is_empty());
+    }
+
+    #[test]
+    fn test_render_markdown_snapshot() {
+        assert_eq!(render_markdown(&fixture()), include_str!("../tests/fixtures/serendipity.md"));
+    }
+
+    #[test]
+    fn test_render_text_snapshot() {
+        assert_eq!(render_text(&fixture(), &TextOptions::default()) + "\n", include_str!("../tests/fixtures/serendipity.txt"));
+    }
+
+    #[test]
+    fn test_render_text_wrapped_snapshot() {
+        let options = TextOptions { color: false, width: Some(60) };
+        assert_eq!(render_text(&fixture(), &options) + "\n", include_str!("../tests/fixtures/serendipity_wrapped.txt"));
+    }
+
+    #[test]
+    fn test_render_text_color_snapshot() {
+        let options = TextOptions { color: true, width: Some(60) };
+        assert_eq!(render_text(&fixture(), &options) + "\n", include_str!("../tests/fixtures/serendipity_color.txt"));
+    }
+
+    #[test]
+    fn test_render_text_caps_synonyms() {
+        let mut result = fixture();
+        let definition = &mut result.sections.definitions.as_mut().unwrap()[0].definitions[0];
+        definition.synonyms = ["chance", "fluke", "luck", "fortune", "windfall", "godsend"].map(String::from).to_vec();
+        assert!(render_text(&result, &TextOptions::default()).contains("by accident. (syn: chance, fluke, luck, fortune, windfall)\n"));
+    }
+
+    #[test]
+    fn test_render_html_snapshot() {
+        let mut result = fixture();
+        result.sections.definitions.as_mut().unwrap()[0].definitions[0].definition =
+            "The faculty of making <happy> & unexpected discoveries by accident".to_string();
+        result.sections.wikipedia.as_mut().unwrap().image_url = Some("https://upload.wikimedia.org/serendipity.jpg".to_string());
+        let options = HtmlOptions { generated_at: "2024-05-01 09:30".to_string(), image: None };
+        assert_eq!(render_html_with(&result, &options), include_str!("../tests/fixtures/serendipity.html"));
+    }
+
+    #[test]
+    fn test_render_html_embeds_cached_image() {
+        let png = b"\x89PNG\r\n\x1a\n".to_vec();
+        let mut result = fixture();
+        result.sections.wikipedia.as_mut().unwrap().image_url = Some("https://upload.wikimedia.org/a.png".to_string());
+        let options = HtmlOptions { generated_at: String::new(), image: Some(png) };
+        let html = render_html_with(&result, &options);
+        assert!(html.contains("<img src=\"data:image/png;base64,iVBORw0KGgo=\""));
+        assert!(!html.contains("upload.wikimedia.org"));
+
+        // Not an image a browser would show, so linked instead
+        let options = HtmlOptions { generated_at: String::new(), image: Some(b"<svg".to_vec()) };
+        assert!(render_html_with(&result, &options).contains("<img src=\"https://upload.wikimedia.org/a.png\""));
+    }
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(escape_html(r#"<a href="x">Tom & Jerry's</a>"#), "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;");
+        assert_eq!(embolden_html("a <b> c", &[(2, 3)]), "a <strong>&lt;b&gt;</strong> c");
+    }
+
+    #[test]
+    fn test_render_merged_definition_sources() {
+        let mut result = fixture();
+        let section = &mut result.sections.definitions.as_mut().unwrap()[0];
+        let source = section.source.clone();
+        section.definitions[0].sources = vec![source, "Wiktionary".to_string(), "Merriam-Webster".to_string()];
+        assert!(render_text(&result, &TextOptions::default()).contains("\n    Also in: Wiktionary, Merriam-Webster\n"));
+        assert!(render_markdown(&result).contains("   _Also in Wiktionary, Merriam-Webster_"));
+    }
+
+    #[test]
+    fn test_render_hidden_definitions_note() {
+        let mut result = fixture();
+        assert!(!render_text(&result, &TextOptions::default()).contains("hidden"));
+        result.hidden_definitions.insert("noun".to_string(), 3);
+        assert!(render_text(&result, &TextOptions::default()).contains("Note: 3 noun definitions hidden by the part-of-speech filter"));
+        result.hidden_definitions.insert("adjective".to_string(), 1);
+        assert!(render_markdown(&result).contains("_Note: 1 adjective and 3 noun definitions hidden"));
+    }
+
+    #[test]
+    fn test_render_frequency() {
+        let mut result = fixture();
+        assert!(!render_text(&result, &TextOptions::default()).contains("Frequency"));
+        result.frequency = Some(WordFrequency { zipf: 5.2, rank: 1204, band: FrequencyBand::Common });
+        assert!(render_text(&result, &TextOptions::default()).contains("Frequency: common (rank 1,204)"));
+        assert_eq!(group_digits(12), "12");
+        assert_eq!(group_digits(1_234_567), "1,234,567");
+    }
+
+    #[test]
+    fn test_one_line_summary() {
+        let mut result = fixture();
+        let summary = one_line_summary(&result).unwrap();
+        assert!(summary.starts_with("serendipity (n.): "), "{}", summary);
+        assert!(!summary.contains('\n') && !summary.ends_with('.'));
+
+        result.sections.definitions = None;
+        let summary = one_line_summary(&result).unwrap();
+        assert!(summary.starts_with("serendipity: Serendipity is"), "{}", summary);
+        result.sections.wikipedia = None;
+        assert_eq!(one_line_summary(&result), None);
+    }
+
+    #[test]
+    fn test_render_computed() {
+        let mut result = fixture();
+        result.sections = Sections {
+            computed: Some(ComputedSection { input: "10 m".to_string(), interpretations: units::convert("10 m") }),
+            ..Sections::default()
+        };
+        let text = render_text(&result, &TextOptions::default());
+        assert!(text.contains("[COMPUTED] 10 m\n  - Metres in feet: 32.81 ft\n  - Metres in yards: 10.94 yd\n  - Minutes in seconds: 600 s"));
+        assert!(render_markdown(&result).contains("## Computed\n\n- Metres in feet: **32.81 ft**\n"));
+    }
+
+    #[test]
+    fn test_render_facts() {
+        let mut result = fixture();
+        let fact = |label: &str, value: &str| Fact { label: label.to_string(), value: value.to_string() };
+        result.sections.infobox =
+            Some(InfoboxSection { facts: vec![fact("Born", "14 March 1879"), fact("Occupation", "physicist, <teacher>")] });
+        let text = render_text(&result, &TextOptions::default());
+        assert!(text.contains("[FACTS] Source: Wikidata\n  - Born: 14 March 1879\n  - Occupation: physicist, <teacher>\n"), "{}", text);
+        assert!(render_markdown(&result).contains("## Facts\n\n- Born: **14 March 1879**\n"));
+        assert!(render_html(&result).contains("<dt>Occupation</dt><dd>physicist, &lt;teacher&gt;</dd>"));
+    }
+
+    #[test]
+    fn test_render_passage_keywords() {
+        let mut passage = fixture();
+        passage.display_query = "What a happy serendipity that was.".to_string();
+        passage.content_type = ContentType::Passage;
+        passage.sections = Sections {
+            keyword_lookups: Some(vec![KeywordLookup { keyword: "serendipity".to_string(), result: fixture() }]),
+            ..Sections::default()
+        };
+
+        let text = render_text(&passage, &TextOptions::default());
+        assert!(text.contains("--- serendipity ---\n\n[DEFINITION]"), "{}", text);
+        let markdown = render_markdown(&passage);
+        assert!(markdown.starts_with("# What a happy serendipity that was.\n\n## serendipity\n\n*"), "{}", markdown);
+        assert!(markdown.contains("\n### Definitions\n\n#### Free Dictionary API\n"), "{}", markdown);
+    }
+
+    #[test]
+    fn test_render_source_statuses() {
+        let status = |name: &str, outcome, duration_ms, error: Option<&str>| SourceStatus {
+            name: name.to_string(),
+            outcome,
+            duration_ms,
+            error: error.map(str::to_string),
+        };
+        let table = render_source_statuses(&[
+            status("dictionary", SourceOutcome::Cached, 2, None),
+            status("wikipedia", SourceOutcome::TimedOut, 8000, Some("wikipedia source timed out")),
+            status("etymology", SourceOutcome::Skipped, 0, None),
+        ]);
+        assert_eq!(
+            table,
+            "Source      Outcome       Time  Error\n\
+             dictionary  cached         2ms\n\
+             wikipedia   timed out   8000ms  wikipedia source timed out\n\
+             etymology   skipped          -"
+        );
+    }
+
+    #[test]
+    fn test_render_comparison() {
+        let compared = CompareResult {
+            first: ComparedWord { query: "serendipity".to_string(), result: Some(fixture()), error: None },
+            second: ComparedWord {
+                query: "serendipty".to_string(),
+                result: None,
+                error: Some(LookupError::NotFound { query: "serendipty".to_string() }),
+            },
+            shared_synonyms: Vec::new(),
+            first_only_synonyms: Vec::new(),
+            second_only_synonyms: Vec::new(),
+            shared_parts_of_speech: Vec::new(),
+            thesaurus_link: false,
+        };
+        let text = render_comparison(&compared, &TextOptions { color: false, width: Some(63) });
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "serendipity                    │ serendipty");
+        assert_eq!(lines[1], "- (noun) An unsought,          │ Lookup failed: No results");
+        assert!(lines.iter().all(|line| line.find('│') == Some(31)), "{}", text);
+        // Nothing to compare with only one side
+        assert!(!text.contains("Shared synonyms"));
+    }
+
+    #[test]
+    fn test_render_source_health() {
+        let health = |name: &str, reachable, latency_ms, detail: &str| SourceHealth {
+            name: name.to_string(),
+            reachable,
+            latency_ms,
+            status_detail: detail.to_string(),
+        };
+        let sources = [
+            health("dictionary", true, 120, "ok"),
+            health("wikipedia", false, 5000, "no answer within 5s"),
+        ];
+        assert_eq!(
+            render_source_health(&sources, &TextOptions::default()),
+            "Source      Status     Time  Detail\n\
+             dictionary  up        120ms  ok\n\
+             wikipedia   down     5000ms  no answer within 5s"
+        );
+
+        let colored = render_source_health(&sources, &TextOptions { color: true, width: None });
+        assert!(colored.contains("\x1b[32mup    \x1b[0m") && colored.contains("\x1b[31mdown  \x1b[0m"));
+    }
+
+    // Quote-aware TSV reader, the way Anki splits an import file
+    fn parse_tsv(data: &str) -> Vec<Vec<String>> {
+        let mut rows = Vec::new();
+        let mut row = Vec::new();
+        let mut field = String::new();
+        let mut chars = data.chars().peekable();
+        let mut quoted = false;
+        while let Some(c) = chars.next() {
+            match (quoted, c) {
+                (true, '"') if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                (true, '"') => quoted = false,
+                (true, c) => field.push(c),
+                (false, '"') if field.is_empty() => quoted = true,
+                (false, '\t') => row.push(std::mem::take(&mut field)),
+                (false, '\n') => {
+                    row.push(std::mem::take(&mut field));
+                    rows.push(std::mem::take(&mut row));
+                }
+                (false, c) => field.push(c),
+            }
+        }
+        rows
+    }
+
+    #[test]
+    fn test_render_anki_round_trips() {
+        let mut tricky = fixture();
+        let defs = &mut tricky.sections.definitions.as_mut().unwrap()[0].definitions;
+        defs[0].definition = "A \"lucky\"\tfind".to_string();
+        tricky.display_query = "serendipity\nagain".to_string();
+
+        let path = std::env::temp_dir().join(format!("touchdictionary-anki-test-{}.tsv", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        append_anki(&path, &[render_anki(&fixture())]).unwrap();
+        append_anki(&path, &[render_anki(&tricky)]).unwrap();
+
+        let data = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert!(data.starts_with(ANKI_HEADER));
+        assert_eq!(data.matches("#separator").count(), 1);
+
+        let rows = parse_tsv(&data[ANKI_HEADER.len()..]);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0][0], "serendipity (noun)");
+        assert_eq!(
+            rows[0][1],
+            "1. An unsought, unintended, and/or unexpected, but fortunate, discovery or learning experience that happens by accident.\n\
+             2. The faculty of making such discoveries.\n\
+             3. A combination of events which have come together by chance to make a surprisingly good or wonderful outcome.\n\
+             Example: Finding the book in that tiny shop was pure serendipity.\n\
+             /ˌsɛɹ.ənˈdɪp.ɪ.ti/"
+        );
+        assert_eq!(rows[1][0], "serendipity\nagain (noun)");
+        assert!(rows[1][1].starts_with("1. A \"lucky\"\tfind\n2. "));
+    }
+
+    #[test]
+    fn test_output_format_from_str() {
+        assert_eq!("Markdown".parse::<OutputFormat>(), Ok(OutputFormat::Markdown));
+        assert_eq!("json".parse::<OutputFormat>(), Ok(OutputFormat::Json));
+        assert_eq!("ndjson".parse::<OutputFormat>(), Ok(OutputFormat::Ndjson));
+        assert!("html".parse::<OutputFormat>().is_err());
+    }
+
+    #[test]
+    fn test_split_sentences() {
+        assert_eq!(split_sentences("One. Two! Three? Four"), vec!["One.", "Two!", "Three?", "Four"]);
+        assert_eq!(
+            split_sentences("Dr. Watson met Mr. Holmes in 1881. They shared rooms."),
+            vec!["Dr. Watson met Mr. Holmes in 1881.", "They shared rooms."]
+        );
+        assert_eq!(
+            split_sentences("Some fruit, e.g. apples, keep well. Others, i.e. Berries, don't."),
+            vec!["Some fruit, e.g. apples, keep well.", "Others, i.e. Berries, don't."]
+        );
+        assert_eq!(split_sentences("J. R. R. Tolkien wrote it. It sold."), vec!["J. R. R. Tolkien wrote it.", "It sold."]);
+        assert_eq!(split_sentences("Pi is 3.14 or so. Roughly."), vec!["Pi is 3.14 or so.", "Roughly."]);
+        assert_eq!(split_sentences("He said \"Stop.\" Then he left."), vec!["He said \"Stop.\"", "Then he left."]);
+        assert_eq!(split_sentences("the U.S. Army. and more"), vec!["the U.S. Army. and more"]);
+        assert_eq!(split_sentences("  "), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn test_first_sentences() {
+        let text = "Paris is the capital of France. It lies on the Seine.\nIt has St. Denis nearby. More.";
+        assert_eq!(first_sentences(text, 2), "Paris is the capital of France. It lies on the Seine.");
+        assert_eq!(first_sentences(text, 3), "Paris is the capital of France. It lies on the Seine.\nIt has St. Denis nearby.");
+        assert_eq!(first_sentences("Just one.", 2), "Just one.");
+        assert_eq!(first_sentences(text, 0), "");
+    }
+}
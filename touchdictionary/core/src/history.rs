@@ -0,0 +1,382 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::lookup::{ContentType, LookupResult};
+use crate::storage::{self, HistoryRepo, Storage};
+
+// Entries returned by `--history` when no count is given
+pub const DEFAULT_RECENT_LIMIT: usize = 20;
+
+// One successful lookup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub query: String,
+    pub display_query: String,
+    pub content_type: ContentType,
+    // Seconds since the Unix epoch
+    pub timestamp: u64,
+    // Sources that contributed a section to the result
+    pub sources: Vec<String>,
+    // Query of the result this lookup was opened from, for a word looked
+    // up out of another result's text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub referrer: Option<String>,
+    // Run again once the network came back, after failing at first
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub deferred: bool,
+}
+
+impl HistoryEntry {
+    pub fn from_result(result: &LookupResult, referrer: Option<&str>) -> Self {
+        HistoryEntry {
+            query: result.query.clone(),
+            display_query: result.display_query.clone(),
+            content_type: result.content_type.clone(),
+            timestamp: now_secs(),
+            sources: sources_with_data(result),
+            referrer: referrer.map(str::to_string),
+            deferred: false,
+        }
+    }
+
+    fn is_for(&self, query: &str) -> bool {
+        let query = query.trim().to_lowercase();
+        self.query == query || self.display_query.to_lowercase() == query
+    }
+}
+
+// Longest referrer chain `chain` follows, in case the history has a cycle
+const MAX_CHAIN_LENGTH: usize = 50;
+
+// Lookup history: a table in the shared database, oldest entry first
+#[derive(Debug, Clone)]
+pub struct History {
+    repo: HistoryRepo,
+}
+
+impl History {
+    pub fn new(repo: HistoryRepo) -> Self {
+        History { repo }
+    }
+
+    pub fn open_default() -> Option<Self> {
+        storage::open_default().map(|storage| History::new(storage.history()))
+    }
+
+    pub async fn append(&self, entry: &HistoryEntry) -> io::Result<()> {
+        Ok(self.repo.append(entry).await?)
+    }
+
+    // Most recent entries first
+    pub async fn recent(&self, limit: usize) -> io::Result<Vec<HistoryEntry>> {
+        Ok(self.repo.recent(limit).await?)
+    }
+
+    // Every entry, oldest first
+    pub async fn all(&self) -> io::Result<Vec<HistoryEntry>> {
+        Ok(self.repo.all().await?)
+    }
+
+    // Entries whose query contains `substring` (case-insensitive), most
+    // recent first
+    pub async fn search(&self, substring: &str) -> io::Result<Vec<HistoryEntry>> {
+        Ok(self.repo.search(&substring.to_lowercase()).await?)
+    }
+
+    // How the latest lookup of `query` was reached: the entries it was
+    // opened from through their referrers, oldest first and ending with
+    // `query`'s own entry. Empty when `query` isn't in the history.
+    pub async fn chain(&self, query: &str) -> io::Result<Vec<HistoryEntry>> {
+        let mut entries = self.all().await?;
+        let mut chain = Vec::new();
+        let mut wanted = query.to_string();
+        while chain.len() < MAX_CHAIN_LENGTH {
+            // The referrer was looked up before the entry that names it
+            let Some(position) = entries.iter().rposition(|e| e.is_for(&wanted)) else {
+                break;
+            };
+            entries.truncate(position + 1);
+            let entry = entries.pop().expect("position is in range");
+            let referrer = entry.referrer.clone();
+            chain.push(entry);
+            match referrer {
+                Some(referrer) if !chain.iter().any(|e| e.is_for(&referrer)) => wanted = referrer,
+                _ => break,
+            }
+        }
+        chain.reverse();
+        Ok(chain)
+    }
+
+    // Delete the history, returning how many entries it held
+    pub async fn clear(&self) -> io::Result<usize> {
+        Ok(self.repo.clear().await?)
+    }
+
+    // Drop all but the newest `max_entries` entries (0 keeps them all).
+    // Returns how many entries were dropped and roughly how many bytes they
+    // took.
+    pub async fn trim(&self, max_entries: usize) -> io::Result<(usize, u64)> {
+        Ok(self.repo.trim(max_entries).await?)
+    }
+}
+
+// Where the history was kept before the database
+const LEGACY_FILE: &str = "history.jsonl";
+
+fn legacy_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("touchdictionary").join(LEGACY_FILE))
+}
+
+pub(crate) fn import_legacy_file(storage: &std::sync::Arc<Storage>) {
+    if let Some(path) = legacy_path() {
+        import_file(storage, &path);
+    }
+}
+
+// Move the history file at `path` from before the database into it, then
+// rename the file out of the way. The database records the import, so a
+// failed rename is only tried again next start. Best-effort: a failed
+// import leaves the file to be tried again too.
+fn import_file(storage: &std::sync::Arc<Storage>, path: &Path) {
+    let Ok(data) = std::fs::read_to_string(path) else {
+        return;
+    };
+    let entries: Vec<HistoryEntry> = data.lines().filter_map(|line| serde_json::from_str(line).ok()).collect();
+    match storage.history().import_blocking(LEGACY_FILE, &entries) {
+        Ok(true) => info!("Imported {} history entries from {}", entries.len(), path.display()),
+        Ok(false) => {}
+        Err(e) => {
+            warn!("Failed to import {}: {}", path.display(), e);
+            return;
+        }
+    }
+    if let Err(e) = std::fs::rename(path, path.with_extension("jsonl.imported")) {
+        warn!("Failed to move {} aside after importing it: {}", path.display(), e);
+    }
+}
+
+// Record a successful lookup in the default history. Best-effort: failures
+// are logged and never surface to the caller.
+pub async fn record(result: &LookupResult, referrer: Option<&str>, deferred: bool) {
+    let Some(history) = History::open_default() else {
+        return;
+    };
+
+    let entry = HistoryEntry { deferred, ..HistoryEntry::from_result(result, referrer) };
+    if let Err(e) = history.append(&entry).await {
+        warn!("Failed to record '{}': {}", result.query, e);
+    }
+}
+
+pub async fn recent(limit: usize) -> io::Result<Vec<HistoryEntry>> {
+    match History::open_default() {
+        Some(history) => history.recent(limit).await,
+        None => Ok(Vec::new()),
+    }
+}
+
+pub async fn all() -> io::Result<Vec<HistoryEntry>> {
+    match History::open_default() {
+        Some(history) => history.all().await,
+        None => Ok(Vec::new()),
+    }
+}
+
+pub async fn search(substring: &str) -> io::Result<Vec<HistoryEntry>> {
+    match History::open_default() {
+        Some(history) => history.search(substring).await,
+        None => Ok(Vec::new()),
+    }
+}
+
+pub async fn chain(query: &str) -> io::Result<Vec<HistoryEntry>> {
+    match History::open_default() {
+        Some(history) => history.chain(query).await,
+        None => Ok(Vec::new()),
+    }
+}
+
+pub async fn clear() -> io::Result<usize> {
+    match History::open_default() {
+        Some(history) => history.clear().await,
+        None => Ok(0),
+    }
+}
+
+fn sources_with_data(result: &LookupResult) -> Vec<String> {
+    let mut sources: Vec<String> = Vec::new();
+    for section in result.sections.definitions.iter().flatten() {
+        if !sources.contains(&section.source) {
+            sources.push(section.source.clone());
+        }
+    }
+    if result.sections.wikipedia.is_some() {
+        sources.push("Wikipedia".to_string());
+    }
+    if result.sections.infobox.is_some() {
+        sources.push("Wikidata".to_string());
+    }
+    if result.sections.thesaurus.is_some() {
+        sources.push("Datamuse".to_string());
+    }
+    if let Some(etymology) = &result.sections.etymology {
+        if !sources.contains(&etymology.source) {
+            sources.push(etymology.source.clone());
+        }
+    }
+    if let Some(examples) = &result.sections.examples {
+        sources.push(examples.source.clone());
+    }
+    if (result.sections.pattern.is_some() || result.sections.rhymes.is_some()) && !sources.iter().any(|s| s == "Datamuse") {
+        sources.push("Datamuse".to_string());
+    }
+    // Expansions come from both wikis; the links say which
+    for expansion in result.sections.acronym.iter().flat_map(|a| &a.expansions) {
+        let site = match expansion.url.as_deref() {
+            Some(url) if url.contains(".wiktionary.org/") => "Wiktionary",
+            Some(url) if url.contains(".wikipedia.org/") => "Wikipedia",
+            _ => continue,
+        };
+        if !sources.iter().any(|s| s == site) {
+            sources.push(site.to_string());
+        }
+    }
+    sources
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(query: &str, timestamp: u64) -> HistoryEntry {
+        HistoryEntry {
+            query: query.to_lowercase(),
+            display_query: query.to_string(),
+            content_type: ContentType::Word,
+            timestamp,
+            sources: vec!["Free Dictionary API".to_string()],
+            referrer: None,
+            deferred: false,
+        }
+    }
+
+    fn referred(query: &str, timestamp: u64, referrer: &str) -> HistoryEntry {
+        HistoryEntry { referrer: Some(referrer.to_string()), ..entry(query, timestamp) }
+    }
+
+    fn temp_history(name: &str) -> (PathBuf, History) {
+        let dir = std::env::temp_dir().join(format!("touchdictionary-history-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let storage = Storage::open(&dir.join(storage::FILE_NAME)).unwrap();
+        (dir, History::new(storage.history()))
+    }
+
+    #[tokio::test]
+    async fn test_append_recent_search_clear() {
+        let (dir, history) = temp_history("test");
+
+        assert!(history.recent(5).await.unwrap().is_empty());
+
+        for (i, query) in ["serendipity", "Paris", "serene"].iter().enumerate() {
+            history.append(&entry(query, i as u64)).await.unwrap();
+        }
+
+        let recent = history.recent(2).await.unwrap();
+        assert_eq!(recent.iter().map(|e| e.query.as_str()).collect::<Vec<_>>(), vec!["serene", "paris"]);
+
+        let found = history.search("SERE").await.unwrap();
+        assert_eq!(found.iter().map(|e| e.query.as_str()).collect::<Vec<_>>(), vec!["serene", "serendipity"]);
+
+        assert_eq!(history.clear().await.unwrap(), 3);
+        assert!(history.recent(5).await.unwrap().is_empty());
+        assert_eq!(history.clear().await.unwrap(), 0);
+
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+    }
+
+    #[tokio::test]
+    async fn test_chain_follows_referrers() {
+        let (dir, history) = temp_history("chain");
+
+        for entry in [
+            entry("Paris", 1),
+            referred("capital", 2, "Paris"),
+            entry("serendipity", 3),
+            referred("seine", 4, "paris"),
+            referred("river", 5, "Seine"),
+            // A cycle stops where it comes back round
+            referred("paris", 6, "river"),
+        ] {
+            history.append(&entry).await.unwrap();
+        }
+
+        let queries = |chain: Vec<HistoryEntry>| chain.into_iter().map(|e| e.query).collect::<Vec<_>>();
+        assert_eq!(queries(history.chain("River").await.unwrap()), vec!["paris", "seine", "river"]);
+        assert_eq!(queries(history.chain("capital").await.unwrap()), vec!["paris", "capital"]);
+        assert_eq!(queries(history.chain("serendipity").await.unwrap()), vec!["serendipity"]);
+        assert_eq!(queries(history.chain("paris").await.unwrap()), vec!["seine", "river", "paris"]);
+        assert!(history.chain("unknown").await.unwrap().is_empty());
+
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+    }
+
+    #[tokio::test]
+    async fn test_trim_keeps_newest() {
+        let (dir, history) = temp_history("trim");
+        assert_eq!(history.trim(2).await.unwrap(), (0, 0));
+
+        for (i, query) in ["one", "two", "three"].iter().enumerate() {
+            history.append(&entry(query, i as u64)).await.unwrap();
+        }
+        assert_eq!(history.trim(0).await.unwrap(), (0, 0));
+        assert_eq!(history.trim(5).await.unwrap(), (0, 0));
+
+        let (dropped, freed) = history.trim(2).await.unwrap();
+        assert_eq!(dropped, 1);
+        assert!(freed > 0);
+        let left = history.all().await.unwrap();
+        assert_eq!(left.iter().map(|e| e.query.as_str()).collect::<Vec<_>>(), vec!["two", "three"]);
+
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+    }
+
+    #[tokio::test]
+    async fn test_legacy_file_is_imported_once() {
+        let dir = std::env::temp_dir().join(format!("touchdictionary-history-legacy-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let storage = Storage::open(&dir.join(storage::FILE_NAME)).unwrap();
+        let history = History::new(storage.history());
+
+        let path = dir.join(LEGACY_FILE);
+        let lines: Vec<String> = [entry("one", 1), entry("two", 2)].iter().map(|e| serde_json::to_string(e).unwrap()).collect();
+        std::fs::write(&path, lines.join("\n")).unwrap();
+        // A directory in the way makes moving the file aside fail
+        let blocker = path.with_extension("jsonl.imported");
+        std::fs::create_dir_all(blocker.join("in-the-way")).unwrap();
+
+        import_file(&storage, &path);
+        import_file(&storage, &path);
+        assert!(path.exists());
+        assert_eq!(history.all().await.unwrap().len(), 2);
+
+        // Once the way is clear the file is moved aside, still not imported again
+        std::fs::remove_dir_all(&blocker).unwrap();
+        import_file(&storage, &path);
+        assert!(!path.exists() && blocker.is_file());
+        assert_eq!(history.all().await.unwrap().len(), 2);
+
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+    }
+}
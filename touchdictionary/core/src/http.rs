@@ -0,0 +1,454 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use tracing::{error, warn};
+
+use crate::error::LookupError;
+
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(8);
+
+// Sent with every request. Wikimedia APIs reject requests without a
+// descriptive one.
+pub const DEFAULT_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+// How every upstream request is made
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpConfig {
+    pub connect_timeout: Duration,
+    pub request_timeout: Duration,
+    // Proxy for every request, e.g. "http://proxy.example:3128". Without
+    // one, HTTP_PROXY, HTTPS_PROXY and NO_PROXY are followed.
+    pub proxy: Option<String>,
+    pub user_agent: String,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        HttpConfig {
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            proxy: None,
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+        }
+    }
+}
+
+impl HttpConfig {
+    // Defaults overridden by `TOUCHDICTIONARY_CONNECT_TIMEOUT_MS` and
+    // `TOUCHDICTIONARY_REQUEST_TIMEOUT_MS`
+    pub fn from_env() -> Self {
+        HttpConfig::default().with_env_overrides()
+    }
+
+    pub fn with_env_overrides(self) -> Self {
+        HttpConfig {
+            connect_timeout: env_millis("TOUCHDICTIONARY_CONNECT_TIMEOUT_MS").unwrap_or(self.connect_timeout),
+            request_timeout: env_millis("TOUCHDICTIONARY_REQUEST_TIMEOUT_MS").unwrap_or(self.request_timeout),
+            ..self
+        }
+    }
+}
+
+fn env_millis(name: &str) -> Option<Duration> {
+    std::env::var(name)
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map(Duration::from_millis)
+}
+
+pub fn build_client(config: &HttpConfig) -> reqwest::Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder()
+        .connect_timeout(config.connect_timeout)
+        .timeout(config.request_timeout)
+        .user_agent(&config.user_agent);
+    // A configured proxy replaces the environment's, but NO_PROXY still
+    // keeps local hosts off it
+    if let Some(proxy) = &config.proxy {
+        builder = builder.proxy(reqwest::Proxy::all(proxy)?.no_proxy(reqwest::NoProxy::from_env()));
+    }
+    builder.build()
+}
+
+static CLIENT: RwLock<Option<(HttpConfig, Arc<reqwest::Client>)>> = RwLock::new(None);
+
+// Shared client for every source, reused across lookups so connections are
+// pooled and TLS sessions and DNS answers are kept warm
+pub fn client() -> Arc<reqwest::Client> {
+    if let Some((_, client)) = CLIENT.read().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        return client.clone();
+    }
+    configure(&HttpConfig::from_env());
+    client()
+}
+
+// Rebuild the shared client if its settings changed
+pub fn configure(config: &HttpConfig) {
+    let mut current = CLIENT.write().unwrap_or_else(|e| e.into_inner());
+    if current.as_ref().is_some_and(|(existing, _)| existing == config) {
+        return;
+    }
+
+    // A malformed proxy URL shouldn't cost the other settings
+    let client = build_client(config).or_else(|e| {
+        error!("Failed to build HTTP client, ignoring the proxy: {}", e);
+        build_client(&HttpConfig { proxy: None, ..config.clone() })
+    });
+    let client = client.unwrap_or_else(|e| {
+        error!("Failed to build HTTP client, using defaults: {}", e);
+        reqwest::Client::new()
+    });
+    *current = Some((config.clone(), Arc::new(client)));
+}
+
+// Map a reqwest failure to a source error, calling out timeouts explicitly
+pub fn request_error(source: &str, e: reqwest::Error) -> LookupError {
+    if e.is_timeout() {
+        LookupError::Timeout { source: source.to_string() }
+    } else {
+        LookupError::from(e)
+    }
+}
+
+// How transient failures are retried
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    // Delay before the first retry, doubled for each one after
+    pub base_delay: Duration,
+    // A `Retry-After` longer than this isn't worth waiting for
+    pub max_retry_after: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(200),
+            max_retry_after: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    // Exponential backoff plus up to 50% jitter so concurrent sources don't
+    // retry in lockstep
+    fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self.base_delay.saturating_mul(1 << (attempt - 1).min(16));
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0);
+        let jitter = delay.mul_f64((nanos % 1000) as f64 / 2000.0);
+        delay + jitter
+    }
+}
+
+// Run `operation` until it succeeds, fails with a non-transient error, or
+// runs out of attempts. A rate limit's `Retry-After` replaces the backoff.
+pub async fn retry<T, F, Fut>(source: &str, policy: &RetryPolicy, mut operation: F) -> Result<T, LookupError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, LookupError>>,
+{
+    let mut attempt = 1;
+    loop {
+        let error = match operation().await {
+            Ok(value) => return Ok(value),
+            Err(e) if !e.is_transient() => return Err(e),
+            Err(e) => e,
+        };
+
+        if attempt >= policy.max_attempts {
+            return Err(if attempt > 1 {
+                LookupError::RetriesExhausted { attempts: attempt, last: Box::new(error) }
+            } else {
+                error
+            });
+        }
+
+        let delay = match &error {
+            LookupError::RateLimited { retry_after: Some(wait) } if *wait > policy.max_retry_after => {
+                return Err(error);
+            }
+            LookupError::RateLimited { retry_after: Some(wait) } => *wait,
+            _ => policy.backoff(attempt),
+        };
+
+        warn!(source, "Attempt {}/{} failed ({}), retrying in {:?}", attempt, policy.max_attempts, error, delay);
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+// Remembers upstreams that just failed so they can be passed over until
+// `cooldown` has gone by, instead of every request waiting on them again
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    cooldown: Duration,
+    tripped: Mutex<BTreeMap<String, Instant>>,
+}
+
+impl CircuitBreaker {
+    pub const fn new(cooldown: Duration) -> Self {
+        CircuitBreaker { cooldown, tripped: Mutex::new(BTreeMap::new()) }
+    }
+
+    // Whether `key` failed recently enough to skip
+    pub fn is_open(&self, key: &str) -> bool {
+        let tripped = self.tripped.lock().unwrap_or_else(|e| e.into_inner());
+        tripped.get(key).is_some_and(|at| at.elapsed() < self.cooldown)
+    }
+
+    pub fn trip(&self, key: &str) {
+        let mut tripped = self.tripped.lock().unwrap_or_else(|e| e.into_inner());
+        tripped.insert(key.to_string(), Instant::now());
+    }
+
+    pub fn reset(&self, key: &str) {
+        self.tripped.lock().unwrap_or_else(|e| e.into_inner()).remove(key);
+    }
+}
+
+// Turn rate limiting and server errors into (retryable) errors, passing
+// every other response through for the source to interpret
+pub fn check_transient(response: reqwest::Response, upstream: &str) -> Result<reqwest::Response, LookupError> {
+    if response.status() == 429 {
+        return Err(LookupError::RateLimited { retry_after: retry_after(&response) });
+    }
+    if response.status().is_server_error() {
+        return Err(LookupError::UpstreamStatus {
+            source: upstream.to_string(),
+            status: response.status().as_u16(),
+        });
+    }
+    Ok(response)
+}
+
+// Parse a `Retry-After` header given in seconds
+pub fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+// Characters of an unparseable body kept for error reports
+const BODY_SNIPPET_CHARS: usize = 200;
+
+// Leading slice of an unparseable body, kept for error reports. Newlines and
+// runs of spaces are collapsed so it stays on one log line.
+pub fn body_snippet(body: &str) -> String {
+    let flattened = body.split_whitespace().collect::<Vec<_>>().join(" ");
+    if flattened.chars().count() <= BODY_SNIPPET_CHARS {
+        return flattened;
+    }
+    let mut snippet: String = flattened.chars().take(BODY_SNIPPET_CHARS).collect();
+    snippet.push('…');
+    snippet
+}
+
+// Everything but RFC 3986 unreserved characters is escaped, so a `/` or `?`
+// in a query can't turn into a path separator or a query string
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+
+// Percent-encode `segment` for use as a single URL path segment
+pub fn encode_path_segment(segment: &str) -> String {
+    utf8_percent_encode(segment, PATH_SEGMENT).to_string()
+}
+
+// `base` with a Wikimedia page title added as its last path segment, with
+// underscores for spaces. The url crate escapes the segment, so "AC/DC" stays
+// one title as `AC%2FDC` and `Mercury_(element)` keeps its parentheses. A
+// `base` that doesn't parse is left for the request to fail on.
+pub fn wiki_title_url(base: &str, title: &str) -> String {
+    let title = title.replace(' ', "_");
+    let Ok(mut url) = reqwest::Url::parse(base) else {
+        return format!("{}/{}", base.trim_end_matches('/'), title);
+    };
+    if let Ok(mut segments) = url.path_segments_mut() {
+        segments.pop_if_empty().push(&title);
+    }
+    url.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_circuit_breaker_cools_down() {
+        let breaker = CircuitBreaker::new(Duration::from_secs(60));
+        assert!(!breaker.is_open("a"));
+        breaker.trip("a");
+        assert!(breaker.is_open("a"));
+        assert!(!breaker.is_open("b"));
+        breaker.reset("a");
+        assert!(!breaker.is_open("a"));
+
+        let instant = CircuitBreaker::new(Duration::ZERO);
+        instant.trip("a");
+        assert!(!instant.is_open("a"));
+    }
+
+    #[test]
+    fn test_encode_path_segment() {
+        assert_eq!(encode_path_segment("café"), "caf%C3%A9");
+        assert_eq!(encode_path_segment("don't"), "don%27t");
+        assert_eq!(encode_path_segment("AC/DC"), "AC%2FDC");
+        assert_eq!(encode_path_segment("what?#x"), "what%3F%23x");
+    }
+
+    #[test]
+    fn test_wiki_title_url() {
+        let base = "https://en.wikipedia.org/wiki";
+        assert_eq!(wiki_title_url(base, "New York City"), format!("{}/New_York_City", base));
+        assert_eq!(wiki_title_url(&format!("{}/", base), "AC/DC"), format!("{}/AC%2FDC", base));
+        assert_eq!(wiki_title_url(base, "Mercury (element)"), format!("{}/Mercury_(element)", base));
+    }
+
+    #[test]
+    fn test_body_snippet_is_one_capped_line() {
+        assert_eq!(body_snippet("{\n  \"title\": \"Oops\"\n}\n"), "{ \"title\": \"Oops\" }");
+        let snippet = body_snippet(&"<p>\n".repeat(100));
+        assert_eq!(snippet.chars().count(), BODY_SNIPPET_CHARS + 1);
+        assert!(snippet.ends_with('…') && !snippet.contains('\n'));
+    }
+
+    #[tokio::test]
+    async fn test_unresponsive_server_times_out() {
+        // Accept connections but never answer
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut held = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                held.push(socket);
+            }
+        });
+
+        let client = build_client(&HttpConfig {
+            connect_timeout: Duration::from_millis(200),
+            request_timeout: Duration::from_millis(300),
+            ..HttpConfig::default()
+        })
+        .unwrap();
+
+        let err = client.get(format!("http://{}/", addr)).send().await.unwrap_err();
+        let err = request_error("dictionary", err);
+        assert!(matches!(err, LookupError::Timeout { ref source } if source == "dictionary"));
+        assert_eq!(err.to_string(), "dictionary source timed out");
+    }
+
+    // Answer one request with an empty 200 and hand back what was sent
+    async fn capture_request(listener: TcpListener) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0; 1024];
+        while !request.ends_with(b"\r\n\r\n") {
+            let n = socket.read(&mut buf).await.unwrap();
+            if n == 0 {
+                break;
+            }
+            request.extend_from_slice(&buf[..n]);
+        }
+        socket.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n").await.unwrap();
+        String::from_utf8_lossy(&request).to_lowercase()
+    }
+
+    #[tokio::test]
+    async fn test_client_sends_configured_user_agent() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(capture_request(listener));
+
+        // Through the server as a proxy, so an HTTP_PROXY in the
+        // environment can't send the request elsewhere
+        let client = build_client(&HttpConfig {
+            proxy: Some(format!("http://{}", addr)),
+            user_agent: "TestDictionary/9.9 (ops@example.org)".to_string(),
+            ..HttpConfig::default()
+        })
+        .unwrap();
+        client.get("http://dictionary.test/entry").send().await.unwrap();
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("get http://dictionary.test/entry http/1.1"), "{}", request);
+        assert!(request.contains("user-agent: testdictionary/9.9 (ops@example.org)\r\n"), "{}", request);
+        assert!(DEFAULT_USER_AGENT.starts_with("touchdictionary-core/"));
+    }
+
+    fn instant_policy() -> RetryPolicy {
+        RetryPolicy { base_delay: Duration::ZERO, ..RetryPolicy::default() }
+    }
+
+    #[tokio::test]
+    async fn test_retry_recovers_from_transient_failures() {
+        let mut calls = 0;
+        let result = retry("test", &instant_policy(), || {
+            calls += 1;
+            let attempt = calls;
+            async move {
+                if attempt < 3 {
+                    Err(LookupError::UpstreamStatus { source: "Test API".to_string(), status: 503 })
+                } else {
+                    Ok(attempt)
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_gives_up_and_reports_attempts() {
+        let mut calls = 0;
+        let err = retry("test", &instant_policy(), || {
+            calls += 1;
+            async { Err::<(), _>(LookupError::Timeout { source: "test".to_string() }) }
+        })
+        .await
+        .unwrap_err();
+
+        assert_eq!(calls, 3);
+        assert!(matches!(err, LookupError::RetriesExhausted { attempts: 3, .. }));
+        assert_eq!(err.kind(), "timeout");
+        assert!(err.is_network());
+        assert_eq!(err.to_string(), "test source timed out (gave up after 3 attempts)");
+    }
+
+    #[tokio::test]
+    async fn test_retry_skips_permanent_errors() {
+        let mut calls = 0;
+        let err = retry("test", &instant_policy(), || {
+            calls += 1;
+            async { Err::<(), _>(LookupError::UpstreamStatus { source: "Test API".to_string(), status: 400 }) }
+        })
+        .await
+        .unwrap_err();
+
+        assert_eq!(calls, 1);
+        assert!(matches!(err, LookupError::UpstreamStatus { status: 400, .. }));
+
+        // A Retry-After beyond the policy's patience isn't waited out
+        let mut calls = 0;
+        let err = retry("test", &instant_policy(), || {
+            calls += 1;
+            async { Err::<(), _>(LookupError::RateLimited { retry_after: Some(Duration::from_secs(60)) }) }
+        })
+        .await
+        .unwrap_err();
+
+        assert_eq!(calls, 1);
+        assert!(matches!(err, LookupError::RateLimited { .. }));
+    }
+}
@@ -0,0 +1,284 @@
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+
+use crate::http;
+
+// Hosts article images are served from. Anything else is refused so the
+// frontend can't be used to download arbitrary files.
+pub const ALLOWED_HOSTS: &[&str] = &["upload.wikimedia.org"];
+
+// Largest single image accepted, and the most the cache keeps before the
+// least recently used images are evicted
+pub const MAX_IMAGE_BYTES: u64 = 5 * 1024 * 1024;
+pub const MAX_CACHE_BYTES: u64 = 100 * 1024 * 1024;
+
+#[derive(Debug)]
+pub enum ImageError {
+    DisallowedUrl { url: String },
+    TooLarge { limit: u64 },
+    Download(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageError::DisallowedUrl { url } => write!(f, "Refusing to fetch image from '{}'", url),
+            ImageError::TooLarge { limit } => write!(f, "Image is larger than {} bytes", limit),
+            ImageError::Download(e) => write!(f, "Failed to download image: {}", e),
+            ImageError::Io(e) => write!(f, "Image cache error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ImageError {}
+
+impl From<io::Error> for ImageError {
+    fn from(e: io::Error) -> Self {
+        ImageError::Io(e)
+    }
+}
+
+// Only https URLs on an allow-listed host
+pub fn validate_url(url: &str) -> Result<reqwest::Url, ImageError> {
+    let disallowed = || ImageError::DisallowedUrl { url: url.to_string() };
+    let parsed = reqwest::Url::parse(url).map_err(|_| disallowed())?;
+
+    if parsed.scheme() != "https" || !parsed.host_str().is_some_and(|host| ALLOWED_HOSTS.contains(&host)) {
+        return Err(disallowed());
+    }
+    Ok(parsed)
+}
+
+// Downloaded images, named by a hash of their URL. A file's modification
+// time records when it was last used, which is what eviction goes by.
+#[derive(Debug, Clone)]
+pub struct ImageCache {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+impl ImageCache {
+    pub fn new(dir: PathBuf) -> Self {
+        ImageCache { dir, max_bytes: MAX_CACHE_BYTES }
+    }
+
+    pub fn with_max_bytes(self, max_bytes: u64) -> Self {
+        ImageCache { max_bytes, ..self }
+    }
+
+    pub fn path_for(&self, url: &reqwest::Url) -> PathBuf {
+        let digest = Sha256::digest(url.as_str().as_bytes());
+        let extension = Path::new(url.path())
+            .extension()
+            .and_then(|e| e.to_str())
+            .filter(|e| e.len() <= 4 && e.chars().all(|c| c.is_ascii_alphanumeric()))
+            .map(str::to_lowercase)
+            .unwrap_or_else(|| "img".to_string());
+        self.dir.join(format!("{}.{}", hex::encode(&digest[..16]), extension))
+    }
+
+    // Local copy of the image at `url` if it's already been downloaded
+    pub fn cached_path(&self, url: &str) -> Option<PathBuf> {
+        let path = self.path_for(&validate_url(url).ok()?);
+        path.is_file().then_some(path)
+    }
+
+    // Local copy of the image at `url`, downloading it on first use
+    pub async fn fetch(&self, url: &str) -> Result<PathBuf, ImageError> {
+        let url = validate_url(url)?;
+        let path = self.path_for(&url);
+        if tokio::fs::try_exists(&path).await.unwrap_or(false) {
+            touch(&path);
+            return Ok(path);
+        }
+
+        info!("Downloading {}", url);
+        let mut response = http::client()
+            .get(url.clone())
+            .send()
+            .await
+            .map_err(|e| ImageError::Download(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(ImageError::Download(format!("server returned status {}", response.status())));
+        }
+        let too_large = ImageError::TooLarge { limit: MAX_IMAGE_BYTES };
+        if response.content_length().is_some_and(|length| length > MAX_IMAGE_BYTES) {
+            return Err(too_large);
+        }
+
+        // The header can be missing or wrong, so count as the body arrives
+        let mut bytes = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(|e| ImageError::Download(e.to_string()))? {
+            if bytes.len() as u64 + chunk.len() as u64 > MAX_IMAGE_BYTES {
+                return Err(too_large);
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+
+        // Write then rename so a half-written file is never shown
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let partial = path.with_extension("part");
+        tokio::fs::write(&partial, &bytes).await?;
+        tokio::fs::rename(&partial, &path).await?;
+
+        if let Err(e) = self.evict(Some(&path)) {
+            warn!("Failed to trim image cache: {}", e);
+        }
+        Ok(path)
+    }
+
+    // Bring the cache back under `max_bytes`, returning how many images
+    // were deleted and their size
+    pub fn enforce_limit(&self) -> io::Result<(usize, u64)> {
+        self.evict(None)
+    }
+
+    // Delete the least recently used images until the cache fits in
+    // `max_bytes` again. `keep` is the image just fetched, which stays.
+    fn evict(&self, keep: Option<&Path>) -> io::Result<(usize, u64)> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok((0, 0)),
+            Err(e) => return Err(e),
+        };
+        let mut files = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if metadata.is_file() {
+                let used = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                files.push((used, metadata.len(), entry.path()));
+            }
+        }
+
+        let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+        files.sort_by_key(|(used, _, _)| *used);
+        let (mut removed, mut freed) = (0, 0);
+        for (_, size, path) in files {
+            if total <= self.max_bytes {
+                break;
+            }
+            if Some(path.as_path()) != keep {
+                std::fs::remove_file(&path)?;
+                total -= size;
+                removed += 1;
+                freed += size;
+            }
+        }
+        Ok((removed, freed))
+    }
+
+    // Delete every cached image, returning how many were removed
+    pub async fn clear(&self) -> io::Result<usize> {
+        let mut entries = match tokio::fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e),
+        };
+
+        let mut removed = 0;
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.file_type().await?.is_file() {
+                tokio::fs::remove_file(entry.path()).await?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+// Where images are cached: the GUI's cache directory, which is named after
+// its bundle identifier
+pub fn default_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("com.touchdictionary.app").join("images"))
+}
+
+// Mark a cached image as just used
+fn touch(path: &Path) {
+    let result = std::fs::File::options()
+        .write(true)
+        .open(path)
+        .and_then(|file| file.set_modified(SystemTime::now()));
+    if let Err(e) = result {
+        warn!("Failed to update {}: {}", path.display(), e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn temp_cache(name: &str) -> ImageCache {
+        let dir = std::env::temp_dir().join(format!("touchdictionary-images-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        ImageCache::new(dir)
+    }
+
+    fn write_aged(path: &Path, bytes: usize, age_secs: u64) {
+        std::fs::write(path, vec![0u8; bytes]).unwrap();
+        let file = std::fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(age_secs)).unwrap();
+    }
+
+    #[test]
+    fn test_validate_url() {
+        assert!(validate_url("https://upload.wikimedia.org/wikipedia/commons/thumb/4/4b/La_Tour_Eiffel.jpg/320px-La_Tour_Eiffel.jpg").is_ok());
+
+        for url in [
+            "http://upload.wikimedia.org/wikipedia/commons/a.jpg",
+            "https://evil.example.com/a.jpg",
+            "https://upload.wikimedia.org.evil.example.com/a.jpg",
+            "file:///etc/passwd",
+        ] {
+            assert!(matches!(validate_url(url), Err(ImageError::DisallowedUrl { .. })), "{}", url);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_reuses_cached_file() {
+        let cache = temp_cache("reuse");
+        let url = "https://upload.wikimedia.org/wikipedia/commons/a/af/Paris.JPG";
+        let path = cache.path_for(&validate_url(url).unwrap());
+        assert_eq!(path.extension().unwrap(), "jpg");
+
+        write_aged(&path, 10, 3600);
+        assert_eq!(cache.fetch(url).await.unwrap(), path);
+        // Served from the cache counts as a use
+        let used = std::fs::metadata(&path).unwrap().modified().unwrap();
+        assert!(used.elapsed().unwrap() < Duration::from_secs(60));
+
+        assert_eq!(cache.clear().await.unwrap(), 1);
+        let _ = std::fs::remove_dir_all(&cache.dir);
+    }
+
+    #[test]
+    fn test_evict_removes_least_recently_used() {
+        let cache = temp_cache("evict").with_max_bytes(250);
+        let (oldest, older, newest) = (cache.dir.join("a.jpg"), cache.dir.join("b.jpg"), cache.dir.join("c.jpg"));
+        write_aged(&oldest, 100, 300);
+        write_aged(&older, 100, 200);
+        write_aged(&newest, 100, 100);
+
+        assert_eq!(cache.evict(Some(&newest)).unwrap(), (1, 100));
+        assert!(!oldest.exists());
+        assert!(older.exists() && newest.exists());
+
+        // The image being kept survives even when it alone is over the cap
+        let cache = cache.with_max_bytes(50);
+        cache.evict(Some(&newest)).unwrap();
+        assert!(!older.exists() && newest.exists());
+
+        // Without one to keep, nothing is spared
+        assert_eq!(cache.enforce_limit().unwrap(), (1, 100));
+        assert!(!newest.exists());
+        let _ = std::fs::remove_dir_all(&cache.dir);
+    }
+}
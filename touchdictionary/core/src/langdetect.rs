@@ -0,0 +1,99 @@
+// Which language a selection is in, so a German word read in an English
+// session is looked up in German. Deliberately cautious: short words and
+// anything whatlang isn't confident about give None. Single words in Latin
+// script rarely get there on their own; a phrase around them usually does.
+
+use whatlang::Lang;
+
+// Fewer letters than this is too little to go on ("the", "die", "son")
+pub const MIN_LETTERS: usize = 5;
+
+// whatlang's confidence needed to switch languages. A little below its own
+// `is_reliable()` cut-off, which only long passages reach; English words
+// on their own stay well under it.
+const MIN_CONFIDENCE: f64 = 0.6;
+
+// The language of `text` when it's clear, as a code from
+// `language::KNOWN_LANGUAGES`
+pub fn detect(text: &str) -> Option<String> {
+    if text.chars().filter(|c| c.is_alphabetic()).count() < MIN_LETTERS {
+        return None;
+    }
+    let info = whatlang::detect(text)?;
+    if !info.is_reliable() && info.confidence() < MIN_CONFIDENCE {
+        return None;
+    }
+    code(info.lang()).map(str::to_string)
+}
+
+// whatlang's ISO 639-3 languages as the codes used everywhere else; the rest
+// have no Wikipedia edition here
+fn code(lang: Lang) -> Option<&'static str> {
+    Some(match lang {
+        Lang::Ara => "ar",
+        Lang::Ces => "cs",
+        Lang::Dan => "da",
+        Lang::Deu => "de",
+        Lang::Ell => "el",
+        Lang::Eng => "en",
+        Lang::Spa => "es",
+        Lang::Pes => "fa",
+        Lang::Fin => "fi",
+        Lang::Fra => "fr",
+        Lang::Heb => "he",
+        Lang::Hin => "hi",
+        Lang::Hun => "hu",
+        Lang::Ind => "id",
+        Lang::Ita => "it",
+        Lang::Jpn => "ja",
+        Lang::Kor => "ko",
+        Lang::Nld => "nl",
+        Lang::Nob => "no",
+        Lang::Pol => "pl",
+        Lang::Por => "pt",
+        Lang::Ron => "ro",
+        Lang::Rus => "ru",
+        Lang::Swe => "sv",
+        Lang::Tur => "tr",
+        Lang::Ukr => "uk",
+        Lang::Vie => "vi",
+        Lang::Cmn => "zh",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect("der Schmetterling fliegt über die Wiese").as_deref(), Some("de"));
+        assert_eq!(detect("bonjour à tous").as_deref(), Some("fr"));
+        assert_eq!(detect("Straße").as_deref(), Some("de"));
+        assert_eq!(detect("¿dónde está la biblioteca?").as_deref(), Some("es"));
+        assert_eq!(detect("ありがとう").as_deref(), Some("ja"));
+        assert_eq!(detect("你好世界中国人民").as_deref(), Some("zh"));
+    }
+
+    #[test]
+    fn test_short_and_ambiguous_stay_undetected() {
+        assert_eq!(detect("the"), None);
+        assert_eq!(detect("die"), None);
+        assert_eq!(detect("chat"), None);
+        assert_eq!(detect("日本"), None);
+        // Could be Norwegian as easily as German
+        assert_eq!(detect("Schmetterling"), None);
+        assert_eq!(detect("bonjour"), None);
+    }
+
+    #[test]
+    fn test_english_words_stay_english() {
+        let words = include_str!("../data/word_of_the_day.txt");
+        let switched: Vec<(&str, String)> = words
+            .lines()
+            .filter_map(|word| detect(word).filter(|lang| lang != "en").map(|lang| (word, lang)))
+            .collect();
+        assert_eq!(switched, Vec::new());
+    }
+}
@@ -0,0 +1,66 @@
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+// Language codes accepted by `--lang` / `LookupOptions::lang`. Each is a
+// Wikipedia edition; individual sources may support fewer.
+pub const KNOWN_LANGUAGES: &[&str] = &[
+    "ar", "cs", "da", "de", "el", "en", "es", "fa", "fi", "fr", "he", "hi", "hu", "id", "it",
+    "ja", "ko", "nl", "no", "pl", "pt", "ro", "ru", "sv", "tr", "uk", "vi", "zh",
+];
+
+pub fn is_known(lang: &str) -> bool {
+    KNOWN_LANGUAGES.contains(&lang)
+}
+
+// Lowercase and trim a user-supplied code ("DE " -> "de")
+pub fn normalize(lang: &str) -> String {
+    lang.trim().to_lowercase()
+}
+
+pub(crate) fn default_language() -> String {
+    DEFAULT_LANGUAGE.to_string()
+}
+
+// Locale variables in the order POSIX consults them for messages
+const LOCALE_VARS: &[&str] = &["LC_ALL", "LC_MESSAGES", "LANG"];
+
+// The language of a locale such as "de_DE.UTF-8" or "en_US.UTF-8@euro".
+// "C", "POSIX" and languages without a Wikipedia edition here give None.
+pub fn from_locale(locale: &str) -> Option<String> {
+    let name = locale.split(['.', '@']).next()?;
+    let lang = normalize(name.split(['_', '-']).next()?);
+    is_known(&lang).then_some(lang)
+}
+
+// The system locale's language, from the first locale variable that is set
+pub fn detect() -> Option<String> {
+    LOCALE_VARS
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.trim().is_empty())
+        .and_then(|locale| from_locale(&locale))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_and_validate() {
+        assert!(is_known(&normalize(" DE ")));
+        assert!(is_known(DEFAULT_LANGUAGE));
+        assert!(!is_known(&normalize("klingon")));
+    }
+
+    #[test]
+    fn test_from_locale() {
+        assert_eq!(from_locale("de_DE.UTF-8").as_deref(), Some("de"));
+        assert_eq!(from_locale("en_US.UTF-8@euro").as_deref(), Some("en"));
+        assert_eq!(from_locale("pt-BR").as_deref(), Some("pt"));
+        assert_eq!(from_locale("fr").as_deref(), Some("fr"));
+        assert_eq!(from_locale("C.UTF-8"), None);
+        assert_eq!(from_locale("POSIX"), None);
+        assert_eq!(from_locale("tlh_US.UTF-8"), None);
+        assert_eq!(from_locale(""), None);
+        assert_eq!(from_locale("@euro"), None);
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+// "form lemma" per line. Words that only look inflected ("lens", "need")
+// are listed as their own lemma so the suffix rules leave them alone.
+const IRREGULAR_FORMS: &str = include_str!("../data/irregular_forms.txt");
+
+fn irregular_forms() -> &'static HashMap<&'static str, &'static str> {
+    static FORMS: OnceLock<HashMap<&'static str, &'static str>> = OnceLock::new();
+    FORMS.get_or_init(|| {
+        IRREGULAR_FORMS
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .map(|(form, lemma)| (form.trim(), lemma.trim()))
+            .collect()
+    })
+}
+
+// Dictionary form of an inflected English word ("mice" -> "mouse",
+// "running" -> "run"), or None when `word` already looks like one. A cheap
+// guess rather than real morphology, only consulted after the dictionaries
+// came back empty for `word` itself.
+pub fn lemmatize(word: &str) -> Option<String> {
+    let word = word.trim().to_lowercase();
+    if word.is_empty() || !word.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+
+    let lemma = match irregular_forms().get(word.as_str()) {
+        Some(lemma) => lemma.to_string(),
+        None => regular_lemma(&word)?,
+    };
+    (lemma != word).then_some(lemma)
+}
+
+fn regular_lemma(word: &str) -> Option<String> {
+    if let Some(stem) = word.strip_suffix("ing") {
+        return verb_stem(stem);
+    }
+    if let Some(stem) = word.strip_suffix("ied") {
+        return (stem.len() >= 2).then(|| format!("{}y", stem));
+    }
+    if let Some(stem) = word.strip_suffix("ed") {
+        return verb_stem(stem);
+    }
+    plural_stem(word)
+}
+
+// What's left of a verb once -ing or -ed is gone: "runn" -> "run",
+// "mak" -> "make", "walk" -> "walk"
+fn verb_stem(stem: &str) -> Option<String> {
+    // "sing" and "shed" aren't inflections of anything
+    if stem.len() < 2 || !stem.chars().any(is_vowel) {
+        return None;
+    }
+
+    let chars: Vec<char> = stem.chars().collect();
+    let n = chars.len();
+    if chars[n - 1] == chars[n - 2] && !is_vowel(chars[n - 1]) && !"lsz".contains(chars[n - 1]) {
+        return Some(stem[..stem.len() - 1].to_string());
+    }
+    if ends_short_syllable(&chars) {
+        return Some(format!("{}e", stem));
+    }
+    Some(stem.to_string())
+}
+
+// A short stem ending consonant-vowel-consonant dropped a silent e:
+// "hop(ing)", "smil(ed)". Stems opening on a vowel ("edit", "open") kept
+// theirs intact.
+fn ends_short_syllable(chars: &[char]) -> bool {
+    let n = chars.len();
+    (3..=4).contains(&n)
+        && !is_vowel(chars[0])
+        && !is_vowel(chars[n - 3])
+        && is_vowel(chars[n - 2])
+        && !is_vowel(chars[n - 1])
+        && !"wxy".contains(chars[n - 1])
+}
+
+fn plural_stem(word: &str) -> Option<String> {
+    if let Some(stem) = word.strip_suffix("ies") {
+        if stem.len() >= 2 {
+            return Some(format!("{}y", stem));
+        }
+    }
+    if let Some(stem) = word.strip_suffix("es") {
+        if ["ch", "sh", "ss", "x", "z"].iter().any(|ending| stem.ends_with(ending)) {
+            return Some(stem.to_string());
+        }
+    }
+    // "glass", "bus" and "analysis" end in s without being plurals
+    let stem = word.strip_suffix('s')?;
+    if stem.len() < 2 || ["s", "u", "i"].iter().any(|ending| stem.ends_with(ending)) {
+        return None;
+    }
+    Some(stem.to_string())
+}
+
+fn is_vowel(c: char) -> bool {
+    "aeiou".contains(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_irregular_forms() {
+        assert_eq!(lemmatize("mice").as_deref(), Some("mouse"));
+        assert_eq!(lemmatize("Geese").as_deref(), Some("goose"));
+        assert_eq!(lemmatize("better").as_deref(), Some("good"));
+        assert_eq!(lemmatize("went").as_deref(), Some("go"));
+    }
+
+    #[test]
+    fn test_regular_inflections() {
+        assert_eq!(lemmatize("running").as_deref(), Some("run"));
+        assert_eq!(lemmatize("making").as_deref(), Some("make"));
+        assert_eq!(lemmatize("walked").as_deref(), Some("walk"));
+        assert_eq!(lemmatize("smiled").as_deref(), Some("smile"));
+        assert_eq!(lemmatize("opened").as_deref(), Some("open"));
+        assert_eq!(lemmatize("carried").as_deref(), Some("carry"));
+        assert_eq!(lemmatize("cities").as_deref(), Some("city"));
+        assert_eq!(lemmatize("churches").as_deref(), Some("church"));
+        assert_eq!(lemmatize("dogs").as_deref(), Some("dog"));
+    }
+
+    #[test]
+    fn test_leaves_lemmas_alone() {
+        for word in ["lens", "glass", "bus", "analysis", "sing", "need", "run", "serendipity", "New York"] {
+            assert_eq!(lemmatize(word), None, "{}", word);
+        }
+    }
+}
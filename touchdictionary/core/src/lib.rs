@@ -1,3 +1,41 @@
 pub mod lookup;
+pub mod audio;
+pub mod batch;
 pub mod clipboard;
-pub mod cli;
\ No newline at end of file
+pub mod config;
+pub mod context;
+pub mod deferred;
+pub mod cli;
+pub mod error;
+pub mod export;
+pub mod favorites;
+pub mod format;
+pub mod guard;
+pub mod cache;
+pub mod history;
+pub mod http;
+pub mod images;
+pub mod langdetect;
+pub mod language;
+pub mod lemma;
+pub mod logging;
+pub mod maintenance;
+pub mod notify;
+pub mod passage;
+pub mod pattern;
+pub mod placement;
+pub mod progress;
+pub mod ranking;
+pub mod ratelimit;
+pub mod repl;
+pub mod sanitize;
+#[cfg(unix)]
+pub mod server;
+pub mod session;
+pub mod sources;
+pub mod storage;
+pub mod terminal;
+pub mod theme;
+pub mod translate;
+pub mod units;
+pub mod watch;
\ No newline at end of file
@@ -0,0 +1,161 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use tracing::level_filters::LevelFilter;
+use tracing::{Event, Subscriber};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields, FormattedFields};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+use crate::progress;
+
+// Filter directives, e.g. `debug` or `warn,touchdictionary_core::http=trace`
+pub const ENV_FILTER: &str = "TOUCHDICTIONARY_LOG";
+pub const DEFAULT_FILTER: LevelFilter = LevelFilter::INFO;
+
+// The GUI's log file starts afresh each day, keeping this many
+pub const KEEP_LOG_FILES: usize = 3;
+
+// `TOUCHDICTIONARY_LOG`, or info and up. Unparseable directives are
+// skipped rather than failing startup.
+pub fn filter() -> EnvFilter {
+    EnvFilter::builder().with_default_directive(DEFAULT_FILTER.into()).with_env_var(ENV_FILTER).from_env_lossy()
+}
+
+// Short name shown in brackets: the last path segment, without the crate
+// prefix, so `touchdictionary_core::sources::dictionary` is `dictionary`
+fn component(target: &str) -> &str {
+    let last = target.rsplit("::").next().unwrap_or(target);
+    let name = last.strip_prefix("touchdictionary_").unwrap_or(last);
+    name.strip_suffix("_lib").unwrap_or(name)
+}
+
+// One line per event in the familiar
+// `[LEVEL] [touchdictionary] [component] span{fields}: message` shape
+pub struct LineFormat;
+
+impl<S, N> FormatEvent<S, N> for LineFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        let metadata = event.metadata();
+        write!(writer, "[{}] [touchdictionary] [{}] ", metadata.level(), component(metadata.target()))?;
+        for span in ctx.event_scope().into_iter().flat_map(|scope| scope.from_root()) {
+            let extensions = span.extensions();
+            match extensions.get::<FormattedFields<N>>().filter(|fields| !fields.is_empty()) {
+                Some(fields) => write!(writer, "{}{{{}}}: ", span.name(), fields)?,
+                None => write!(writer, "{}: ", span.name())?,
+            }
+        }
+        ctx.field_format().format_fields(writer.by_ref(), event)?;
+        writeln!(writer)
+    }
+}
+
+// stderr, with the CLI's progress line cleared before each log line so the
+// two don't end up on one line; the spinner redraws itself underneath
+struct Stderr;
+
+impl Write for Stderr {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        io::stderr().write(buf)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        let mut stderr = io::stderr().lock();
+        if progress::is_drawing() {
+            stderr.write_all(b"\r\x1b[2K")?;
+        }
+        stderr.write_all(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
+
+pub fn default_log_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("touchdictionary").join("logs"))
+}
+
+// Log to stderr, filtered by `TOUCHDICTIONARY_LOG`. Keeps stdout free for
+// results. Does nothing if logging is already set up.
+pub fn init_stderr() {
+    let _ = tracing_subscriber::fmt().with_env_filter(filter()).with_writer(|| Stderr).event_format(LineFormat).try_init();
+}
+
+// Like `init_stderr`, also appending to `touchdictionary.<date>.log` in `dir`
+pub fn init_with_file(dir: &Path) {
+    let file = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix("touchdictionary")
+        .filename_suffix("log")
+        .max_log_files(KEEP_LOG_FILES)
+        .build(dir);
+    let file = match file {
+        Ok(file) => file,
+        Err(e) => {
+            init_stderr();
+            tracing::warn!("Not writing a log file to {}: {}", dir.display(), e);
+            return;
+        }
+    };
+    let _ = tracing_subscriber::registry()
+        .with(filter())
+        .with(tracing_subscriber::fmt::layer().event_format(LineFormat).with_writer(|| Stderr))
+        .with(tracing_subscriber::fmt::layer().event_format(LineFormat).with_writer(file))
+        .try_init();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_lines_keep_the_familiar_shape() {
+        let captured = Captured::default();
+        let subscriber = tracing_subscriber::fmt()
+            .with_env_filter(EnvFilter::new("info"))
+            .with_writer({
+                let captured = captured.clone();
+                move || captured.clone()
+            })
+            .event_format(LineFormat)
+            .finish();
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("lookup", query = "serendipity");
+            let _guard = span.enter();
+            tracing::warn!(target: "touchdictionary_core::sources::wikipedia", source = "wikipedia", "No results found for '{}'", "serendipity");
+            tracing::debug!("filtered out");
+        });
+
+        let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(
+            output,
+            "[WARN] [touchdictionary] [wikipedia] lookup{query=\"serendipity\"}: No results found for 'serendipity' source=\"wikipedia\"\n"
+        );
+    }
+}
@@ -1,12 +1,145 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use chrono::{Datelike, NaiveDate};
+use futures_util::future::join_all;
+use futures_util::StreamExt;
 use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn, Instrument};
+
+use crate::cache::{self, Cache, CacheKey};
+use crate::config::{Config, PassageSettings, PatternSettings};
+use crate::context::LookupContext;
+use crate::deferred;
+use crate::translate;
+use crate::error::{LookupError, SourceFailure};
+use crate::format;
+use crate::history;
+use crate::langdetect;
+use crate::lemma;
+use crate::passage;
+use crate::pattern;
+use crate::sanitize;
+use crate::ranking::{self, DefinitionLimits};
+use crate::units;
+use crate::language::{self, default_language};
+use crate::sources::acronym;
+use crate::sources::wikidata;
+use crate::sources::wikipedia;
+use crate::sources::word_stats::{self, WordFrequency};
+use crate::sources::datamuse::{self, DatamuseWord};
+use crate::sources::{Revalidation, Source, SourceRegistry, SourceResult};
 
 // API Response Structures
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LookupResult {
     pub query: String,
+    pub display_query: String,
     pub content_type: ContentType,
     pub sections: Sections,
+    pub from_cache: bool,
+    // Reopened from the window's recent results rather than looked up
+    #[serde(default)]
+    pub from_session_cache: bool,
+    pub suggestions: Option<Vec<String>>,
+    pub corrected_from: Option<String>,
+    // The inflected form that was asked for when `query` is its lemma
+    #[serde(default)]
+    pub lemma_of: Option<String>,
+    // How common the word is in English, for single words
+    #[serde(default)]
+    pub frequency: Option<WordFrequency>,
+    // Language that was requested; each section records the one it used
+    pub language: String,
+    // Language the lookup ran in: `language`, or the query's own when
+    // auto-detection switched to it
+    #[serde(default)]
+    pub language_used: String,
+    // The query's language, when `langdetect` could tell
+    #[serde(default)]
+    pub detected_language: Option<String>,
+    // The system locale's language, when it's one lookups support. Equal
+    // to `language` when the locale picked it.
+    #[serde(default)]
+    pub locale_language: Option<String>,
+    // Definitions left out by `LookupOptions::pos_filter`, counted by part
+    // of speech ("other" for untagged ones)
+    #[serde(default)]
+    pub hidden_definitions: BTreeMap<String, usize>,
+    // Non-fatal notes for the user, e.g. a source falling back to English
+    pub warnings: Vec<String>,
+    #[serde(default)]
+    pub is_word_of_the_day: bool,
+    // How each source fared, in the order they were consulted
+    #[serde(default)]
+    pub source_statuses: Vec<SourceStatus>,
+    #[serde(default)]
+    pub completeness: Completeness,
+}
+
+impl LookupResult {
+    // Every source answered and none had anything, not even a spelling
+    // suggestion
+    pub fn found_nothing(&self) -> bool {
+        self.completeness == Completeness::Empty && self.suggestions.is_none()
+    }
+}
+
+// Whether the result is everything the sources had. A source that
+// answered with nothing doesn't make it partial; one that failed does.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Completeness {
+    #[default]
+    Full,
+    // Something was found, but these sources failed and may have had more
+    Partial { failed_sources: Vec<String> },
+    // Every source answered and none of them had anything
+    Empty,
+}
+
+impl Completeness {
+    fn assess(found: bool, failed_sources: Vec<String>) -> Self {
+        if !failed_sources.is_empty() {
+            Completeness::Partial { failed_sources }
+        } else if found {
+            Completeness::Full
+        } else {
+            Completeness::Empty
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SourceOutcome {
+    Ok,
+    // Answered, but had nothing for this query
+    Empty,
+    Error,
+    // Disabled in the config or not applicable to this kind of query
+    Skipped,
+    TimedOut,
+    // Answered from the cache without a request
+    Cached,
+    // A network source not asked because private mode is on
+    PrivateModeBlocked,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceStatus {
+    pub name: String,
+    pub outcome: SourceOutcome,
+    pub duration_ms: u64,
+    pub error: Option<String>,
+}
+
+impl SourceStatus {
+    fn skipped(name: &str) -> Self {
+        SourceStatus { name: name.to_string(), outcome: SourceOutcome::Skipped, duration_ms: 0, error: None }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,19 +147,71 @@ pub enum ContentType {
     Word,
     Entity,
     Mixed,
+    // A sentence or more, looked up by its keywords
+    Passage,
+    // "NASA", "LiDAR", "e.g.": looked up for what it stands for
+    Acronym,
+    // A wildcard pattern like "c?t", answered with the words that fit it
+    Pattern,
+    // A quantity like "3.5 km" or a year like "1789", answered locally
+    Computed,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Sections {
     pub definitions: Option<Vec<DefinitionSection>>,
     pub wikipedia: Option<WikipediaSection>,
     pub thesaurus: Option<ThesaurusSection>,
+    pub etymology: Option<EtymologySection>,
+    #[serde(default)]
+    pub examples: Option<ExamplesSection>,
+    #[serde(default)]
+    pub acronym: Option<AcronymSection>,
+    #[serde(default)]
+    pub pattern: Option<PatternSection>,
+    #[serde(default)]
+    pub rhymes: Option<RhymesSection>,
+    #[serde(default)]
+    pub computed: Option<ComputedSection>,
+    // Facts about an entity from Wikidata, like a person's birth date
+    #[serde(default)]
+    pub infobox: Option<InfoboxSection>,
+    // For a passage, each of its keywords looked up on its own
+    #[serde(default)]
+    pub keyword_lookups: Option<Vec<KeywordLookup>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeywordLookup {
+    pub keyword: String,
+    pub result: LookupResult,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DefinitionSection {
     pub source: String,
     pub definitions: Vec<Definition>,
+    pub phonetics: Option<Vec<Phonetic>>,
+    #[serde(default = "default_language")]
+    pub language: String,
+    // Whether `definitions` was cut down by the configured limits, and how
+    // many there were before. Set by `ranking::limit_definitions`.
+    #[serde(default)]
+    pub truncated: bool,
+    #[serde(default)]
+    pub total_available: usize,
+    // Refetched and different from the cached copy it replaced
+    #[serde(default)]
+    pub changed_since_cache: bool,
+    // Examples after the first were left out of a summary payload
+    #[serde(default)]
+    pub examples_truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Phonetic {
+    pub text: Option<String>,
+    pub audio_url: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,6 +220,18 @@ pub struct Definition {
     pub part_of_speech: Option<String>,
     pub definition: String,
     pub example: Option<String>,
+    // `definition` in the language asked for with `translate_to`
+    #[serde(default)]
+    pub translated: Option<String>,
+    // As the source lists them for this sense, where it does
+    #[serde(default)]
+    pub synonyms: Vec<String>,
+    #[serde(default)]
+    pub antonyms: Vec<String>,
+    // Every source that gave this definition, for one that `ranking::dedupe`
+    // merged from several; empty means just the section's own source
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,6 +241,88 @@ pub struct WikipediaSection {
     pub paragraphs: Vec<String>,
     pub image_url: Option<String>,
     pub url: String,
+    #[serde(default = "default_language")]
+    pub language: String,
+    // "See also" articles for exploring the topic further
+    #[serde(default)]
+    pub related: Vec<RelatedPage>,
+    // `summary` in the language asked for with `translate_to`
+    #[serde(default)]
+    pub translated_summary: Option<String>,
+    // Refetched and different from the cached copy it replaced
+    #[serde(default)]
+    pub changed_since_cache: bool,
+    // `paragraphs` were cut to a summary payload's budget
+    #[serde(default)]
+    pub is_truncated: bool,
+    // The article shown when the qualified title asked for, like "Mercury
+    // (element)", didn't exist and a search found this one instead
+    #[serde(default)]
+    pub resolved_title: Option<String>,
+    // The title as Wikipedia keys it, e.g. "United_States"
+    #[serde(default)]
+    pub canonical_title: Option<String>,
+    // What was looked up when Wikipedia redirected it here, like "colour"
+    // for "Color"
+    #[serde(default)]
+    pub redirected_from: Option<String>,
+    // The article's Wikidata item, e.g. "Q90" for Paris
+    #[serde(default)]
+    pub wikidata_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelatedPage {
+    pub title: String,
+    pub description: Option<String>,
+    pub thumbnail_url: Option<String>,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EtymologySection {
+    pub source: String,
+    pub text: String,
+    // Earliest language the word is traced back to
+    pub language_of_origin: Option<String>,
+}
+
+// Sentences using the word, with where it occurs in each
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExamplesSection {
+    pub source: String,
+    pub sentences: Vec<String>,
+    // (start, length) in characters of each form of the word in the
+    // sentence at the same index, for bolding it
+    #[serde(default)]
+    pub occurrences: Vec<Vec<(usize, usize)>>,
+}
+
+// What an acronym or abbreviation may stand for, most likely first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AcronymSection {
+    pub expansions: Vec<AcronymExpansion>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AcronymExpansion {
+    pub expansion: String,
+    pub description: Option<String>,
+    // Where the expansion was found
+    pub url: Option<String>,
+}
+
+// Words fitting a wildcard pattern, best first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatternSection {
+    pub matches: Vec<PatternMatch>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatternMatch {
+    pub word: String,
+    // Datamuse's ranking; higher is more common
+    pub score: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -51,335 +330,2074 @@ pub struct ThesaurusSection {
     pub synonyms: Vec<String>,
     pub antonyms: Vec<String>,
     pub related_terms: Vec<String>,
+    #[serde(default = "default_language")]
+    pub language: String,
 }
 
-// Core lookup function - the heart of the application
-pub async fn lookup(query: &str) -> Result<LookupResult, String> {
-    let cleaned_query = clean_query(query);
+impl ThesaurusSection {
+    pub fn is_empty(&self) -> bool {
+        self.synonyms.is_empty() && self.antonyms.is_empty() && self.related_terms.is_empty()
+    }
 
-    if cleaned_query.is_empty() {
-        return Err("Empty query".to_string());
+    // Add `other`'s terms after these, skipping any already listed in
+    // another case
+    pub fn merge(&mut self, other: ThesaurusSection) {
+        fn extend(terms: &mut Vec<String>, more: Vec<String>) {
+            for term in more {
+                if !terms.iter().any(|t| t.to_lowercase() == term.to_lowercase()) {
+                    terms.push(term);
+                }
+            }
+        }
+        extend(&mut self.synonyms, other.synonyms);
+        extend(&mut self.antonyms, other.antonyms);
+        extend(&mut self.related_terms, other.related_terms);
     }
+}
 
-    let content_type = classify_content(&cleaned_query);
+// A quantity in other units, or facts about a year. An ambiguous unit
+// ("10 m") has interpretations for each thing it could mean.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComputedSection {
+    pub input: String,
+    pub interpretations: Vec<Interpretation>,
+}
 
-    let sections = match content_type {
-        ContentType::Word => aggregate_word_sources(&cleaned_query).await,
-        ContentType::Entity => aggregate_entity_sources(&cleaned_query).await,
-        ContentType::Mixed => aggregate_all_sources(&cleaned_query).await,
-    };
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interpretation {
+    pub label: String,
+    pub value: String,
+}
+
+// A few structured facts about an entity, in a fixed order; ones Wikidata
+// doesn't have are left out
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InfoboxSection {
+    pub facts: Vec<Fact>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Fact {
+    // "Born", "Population"
+    pub label: String,
+    pub value: String,
+}
 
-    match sections {
-        Ok(sections) => Ok(LookupResult {
-            query: cleaned_query,
-            content_type,
-            sections,
-        }),
-        Err(e) => Err(format!("Failed to aggregate lookup results: {}", e)),
+// Words that rhyme with the query, best first. A word is only in one list.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RhymesSection {
+    pub perfect: Vec<String>,
+    pub near: Vec<String>,
+    pub sounds_like: Vec<String>,
+}
+
+impl RhymesSection {
+    pub fn is_empty(&self) -> bool {
+        self.perfect.is_empty() && self.near.is_empty() && self.sounds_like.is_empty()
     }
 }
 
-fn clean_query(query: &str) -> String {
-    query.trim()
-        .split_whitespace()
-        .collect::<Vec<&str>>()
-        .join(" ")
-        .to_lowercase()
+impl Sections {
+    pub fn is_empty(&self) -> bool {
+        self.definitions.is_none()
+            && self.wikipedia.is_none()
+            && self.thesaurus.is_none()
+            && self.etymology.is_none()
+            && self.examples.is_none()
+            && self.acronym.is_none()
+            && self.pattern.is_none()
+            && self.rhymes.is_none()
+            && self.computed.is_none()
+            && self.infobox.is_none()
+            && self.keyword_lookups.is_none()
+    }
+
+    // Cut down for a summary payload: Wikipedia paragraphs to `budget`
+    // characters in all, and each source's definitions to one example.
+    // Nothing is cut inside a word. Returns whether anything is left out.
+    pub fn truncate(&mut self, budget: usize) -> bool {
+        let mut truncated = false;
+        if let Some(wikipedia) = &mut self.wikipedia {
+            truncated |= wikipedia.truncate(budget);
+        }
+        for section in self.definitions.iter_mut().flatten() {
+            truncated |= section.truncate_examples();
+        }
+        for keyword in self.keyword_lookups.iter_mut().flatten() {
+            truncated |= keyword.result.sections.truncate(budget);
+        }
+        truncated
+    }
+
+    // Cut down for a small screen once definitions are ranked: the top
+    // three definitions, a Wikipedia summary of two sentences with no
+    // paragraphs or related articles, and no example after the first
+    pub fn compact(&mut self) {
+        if let Some(wikipedia) = &mut self.wikipedia {
+            let summary = format::first_sentences(&wikipedia.summary, COMPACT_SUMMARY_SENTENCES);
+            if summary.len() < wikipedia.summary.len() || !wikipedia.paragraphs.is_empty() {
+                wikipedia.summary = summary.to_string();
+                wikipedia.paragraphs.clear();
+                wikipedia.is_truncated = true;
+            }
+            wikipedia.related.clear();
+        }
+        // `limit_definitions` keeps one from every source, however many there are
+        if let Some(definitions) = &mut self.definitions {
+            let mut left = COMPACT_DEFINITIONS;
+            for section in definitions.iter_mut() {
+                let keep = section.definitions.len().min(left);
+                section.truncated |= keep < section.definitions.len();
+                section.definitions.truncate(keep);
+                left -= keep;
+            }
+            definitions.retain(|section| !section.definitions.is_empty());
+        }
+        let mut kept_example = false;
+        for section in self.definitions.iter_mut().flatten() {
+            if kept_example {
+                section.examples_truncated |= section.definitions.iter_mut().filter_map(|d| d.example.take()).count() > 0;
+            } else {
+                section.truncate_examples();
+                kept_example = section.definitions.iter().any(|d| d.example.is_some());
+            }
+        }
+        if kept_example {
+            self.examples = None;
+        } else if let Some(examples) = &mut self.examples {
+            examples.sentences.truncate(1);
+            examples.occurrences.truncate(1);
+        }
+    }
 }
 
-fn classify_content(query: &str) -> ContentType {
-    // Simple heuristic to classify content type
-    // In a real implementation, this would be more sophisticated
+impl WikipediaSection {
+    // Keep `budget` characters of paragraphs, the last one cut at a word
+    // and ended with an ellipsis
+    pub fn truncate(&mut self, budget: usize) -> bool {
+        let mut left = budget;
+        let mut kept = Vec::new();
+        for paragraph in self.paragraphs.drain(..) {
+            let length = paragraph.chars().count();
+            if length <= left {
+                left -= length;
+                kept.push(paragraph);
+                continue;
+            }
+            if let Some(cut) = cut_at_word(&paragraph, left) {
+                kept.push(format!("{}…", cut));
+            }
+            self.is_truncated = true;
+            break;
+        }
+        self.paragraphs = kept;
+        self.is_truncated
+    }
+}
 
-    // Check if it's likely a named entity (proper noun)
-    if query.chars().next().map_or(false, |c| c.is_uppercase()) {
-        return ContentType::Entity;
+impl DefinitionSection {
+    // Drop every example but the first
+    pub fn truncate_examples(&mut self) -> bool {
+        let mut kept_one = false;
+        for definition in &mut self.definitions {
+            if definition.example.is_some() {
+                if kept_one {
+                    definition.example = None;
+                    self.examples_truncated = true;
+                }
+                kept_one = true;
+            }
+        }
+        self.examples_truncated
     }
+}
 
-    // Check for multi-word phrases (likely entities)
-    if query.split_whitespace().count() > 2 {
-        return ContentType::Entity;
+// Scripts written without spaces, where any two characters are a word
+// boundary
+fn is_unspaced(c: char) -> bool {
+    matches!(c, '\u{3000}'..='\u{30ff}' | '\u{3400}'..='\u{4dbf}' | '\u{4e00}'..='\u{9fff}' | '\u{ff00}'..='\u{ffef}')
+}
+
+// The longest start of `text` of at most `max_chars` characters that ends
+// between words, without trailing whitespace; None when even the first
+// word is longer
+fn cut_at_word(text: &str, max_chars: usize) -> Option<&str> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    if chars.len() <= max_chars {
+        return Some(text);
     }
+    let boundary = (1..=max_chars).rev().find(|&i| {
+        let (before, after) = (chars[i - 1].1, chars[i].1);
+        before.is_whitespace() || after.is_whitespace() || is_unspaced(before) || is_unspaced(after)
+    })?;
+    Some(text[..chars[boundary].0].trim_end()).filter(|cut| !cut.is_empty())
+}
 
-    // Default to word for single lowercase terms
-    ContentType::Word
+// Maximum number of spelling suggestions attached to an empty result
+pub const MAX_SUGGESTIONS: usize = 5;
+
+// What a compact result keeps: the top definitions across all sources, and
+// the first sentences of the Wikipedia summary
+const COMPACT_DEFINITIONS: usize = 3;
+const COMPACT_SUMMARY_SENTENCES: usize = 2;
+
+// Knobs for a single lookup
+#[derive(Debug, Clone)]
+pub struct LookupOptions {
+    // Serve fresh cached responses without touching the network, and fall
+    // back to stale ones when the network is unreachable
+    pub use_cache: bool,
+    // Transparently look up a misspelled query again under its one
+    // confident suggestion
+    pub auto_correct: bool,
+    // Language code from `language::KNOWN_LANGUAGES`; English when unset
+    pub lang: Option<String>,
+    // Cancelling abandons the lookup with `LookupError::Cancelled` and drops
+    // its outstanding requests
+    pub cancel: CancellationToken,
+    // Answer from the offline dictionary alone, without any network access
+    pub offline: bool,
+    // Translate definitions and the Wikipedia summary into this language
+    pub translate_to: Option<String>,
+    // Sections are sent here as each source finishes, ahead of the result
+    pub progress: Option<ProgressSink>,
+    // When a selection counts as a passage; `lookup()` takes it from the
+    // config
+    pub passage: PassageSettings,
+    // How many words a wildcard pattern lists; also from the config
+    pub pattern: PatternSettings,
+    // Keep every definition instead of the configured maximum
+    pub all_definitions: bool,
+    // Only keep definitions with one of these parts of speech, e.g. "verb"
+    // or "adj"
+    pub pos_filter: Option<Vec<String>>,
+    // Query of the result this lookup was opened from, recorded with it in
+    // the history
+    pub referrer: Option<String>,
+    // Ask only local sources and the cache, and write nothing: no cache
+    // entries, no history. `lookup_with()` also sets it when the context is
+    // in private mode.
+    pub private: bool,
+    // Trim the result for a small screen with `Sections::compact`, keeping
+    // only the top three definitions
+    pub compact: bool,
+    // Run again from the `deferred` queue: recorded in the history as such,
+    // and not queued again by `lookup_with()` when it fails
+    pub deferred: bool,
+    // Made on the user's behalf rather than asked for, like a favorite's
+    // snapshot: not recorded in the history, and not queued when it fails
+    pub skip_history: bool,
+}
+
+// One source's contribution to a lookup that's still running. `section` is
+// "definitions", "wikipedia", "thesaurus", "etymology", "examples",
+// "acronym" or "rhymes"; `payload` is that section serialized as it appears
+// in `Sections`. Each source is also bracketed by a "source_started" update
+// with a null payload and a "source_finished" one carrying its
+// `SourceStatus`, for progress displays.
+#[derive(Debug, Clone, Serialize)]
+pub struct SectionUpdate {
+    pub source: String,
+    pub section: String,
+    pub payload: serde_json::Value,
+}
+
+pub type ProgressSink = mpsc::UnboundedSender<SectionUpdate>;
+
+impl Default for LookupOptions {
+    fn default() -> Self {
+        LookupOptions {
+            use_cache: true,
+            auto_correct: false,
+            lang: None,
+            cancel: CancellationToken::new(),
+            offline: false,
+            translate_to: None,
+            progress: None,
+            passage: PassageSettings::default(),
+            pattern: PatternSettings::default(),
+            all_definitions: false,
+            pos_filter: None,
+            referrer: None,
+            private: false,
+            compact: false,
+            deferred: false,
+            skip_history: false,
+        }
+    }
 }
 
-async fn aggregate_word_sources(query: &str) -> Result<Sections, String> {
-    let mut sections = Sections {
-        definitions: None,
-        wikipedia: None,
-        thesaurus: None,
+// Core lookup function - the heart of the application. One-off callers get
+// a fresh `LookupContext`; anything doing repeated lookups should keep one
+// and use `lookup_with`.
+pub async fn lookup(query: &str, options: &LookupOptions) -> Result<LookupResult, LookupError> {
+    lookup_with(&LookupContext::load(), query, options).await
+}
+
+// Lookup through a long-lived context, reusing its HTTP connections and
+// cache. Disabled sources are skipped and the config's language is used
+// unless `options` names one. Lookups that found something are recorded in
+// the history; ones that couldn't reach the network are queued to run again
+// once it's back, see `deferred`. A lookup identical to one still running
+// waits for that one rather than asking the sources again.
+pub async fn lookup_with(ctx: &LookupContext, query: &str, options: &LookupOptions) -> Result<LookupResult, LookupError> {
+    let config = ctx.config();
+    // A language the caller asked for is never second-guessed
+    let explicit = options.lang.is_some();
+    let options = LookupOptions {
+        lang: options.lang.clone().or_else(|| Some(config.lookup_language())),
+        offline: options.offline || config.offline.offline_only,
+        private: options.private || ctx.is_private(),
+        passage: config.passage.clone(),
+        pattern: config.pattern.clone(),
+        ..options.clone()
     };
+    let requested = options.lang.clone().unwrap_or_else(default_language);
+
+    let detected = if explicit { None } else { query_language(&config, query) };
 
-    // Try dictionary sources first
-    match get_dictionary_definitions(query).await {
-        Ok(defs) if !defs.is_empty() => {
-            sections.definitions = Some(defs);
+    // Offline sources only know the configured language, so there's nothing
+    // to switch to
+    if let Some(lang) = detected.as_ref().filter(|lang| **lang != requested && !options.offline) {
+        let switched = LookupOptions { lang: Some(lang.clone()), ..options.clone() };
+        let key = CacheKey::from(&config, &switched, query);
+        match ctx.coalesce(key.as_str(), &switched.cancel, lookup_configured(ctx, &config, query, &switched)).await {
+            Ok(mut result) if !result.sections.is_empty() => {
+                info!("Looked up {:?} in {} rather than {}", query, lang, requested);
+                result.language = requested;
+                result.detected_language = detected;
+                return Ok(result);
+            }
+            Err(LookupError::Cancelled) => return Err(LookupError::Cancelled),
+            // Nothing there after all; the requested language may still know it
+            _ => {}
         }
-        Ok(_) => {
-            eprintln!("[WARN] [touchdictionary] [dictionary] No definitions found for '{}'", query);
+    }
+
+    let key = CacheKey::from(&config, &options, query);
+    let outcome = ctx.coalesce(key.as_str(), &options.cancel, lookup_configured(ctx, &config, query, &options)).await;
+    if let Err(e) = &outcome {
+        deferred::defer_if_unreachable(ctx, query, &options, e);
+    }
+    let mut result = outcome?;
+    result.detected_language = detected;
+    Ok(result)
+}
+
+// The query's own language, when `config` lets it be detected
+fn query_language(config: &Config, query: &str) -> Option<String> {
+    let cleaned_query = clean_query(query);
+    if !config.auto_detect_language || pattern::is_pattern(&cleaned_query) || units::is_computed(&cleaned_query) {
+        return None;
+    }
+    langdetect::detect(&cleaned_query)
+}
+
+// `lookup_with()` once `options` is settled against `config`
+async fn lookup_configured(
+    ctx: &LookupContext,
+    config: &Config,
+    query: &str,
+    options: &LookupOptions,
+) -> Result<LookupResult, LookupError> {
+    let offline = options.offline;
+    let registry = if offline {
+        SourceRegistry::offline(config)
+    } else {
+        SourceRegistry::from_config(config)
+    };
+
+    let mut result = lookup_in(&registry, ctx.cache(), query, options).await?;
+    result.locale_language = config.locale_language();
+    let english = result.language_used == language::DEFAULT_LANGUAGE;
+    if matches!(result.content_type, ContentType::Word) && english && config.source_enabled("word_stats") {
+        result.frequency = word_stats::frequency(&result.query);
+    }
+    if matches!(result.content_type, ContentType::Entity) && !offline && !options.private && config.source_enabled("wikidata") {
+        if let Some(wiki) = &result.sections.wikipedia {
+            let cache = ctx.cache().filter(|_| options.use_cache);
+            result.sections.infobox = wikidata::infobox(config, cache, wiki).await;
         }
-        Err(e) => {
-            eprintln!("[ERROR] [touchdictionary] [dictionary] Failed to fetch definitions for '{}': {}", query, e);
+    }
+    if let Some(definitions) = result.sections.definitions.as_mut().filter(|_| config.dedupe_definitions) {
+        ranking::dedupe_definitions(definitions);
+    }
+    let pos_filter = options.pos_filter.as_deref().filter(|wanted| !wanted.is_empty());
+    if let (Some(definitions), Some(wanted)) = (&mut result.sections.definitions, pos_filter) {
+        result.hidden_definitions = ranking::filter_parts_of_speech(definitions, wanted);
+        if definitions.is_empty() {
+            result.sections.definitions = None;
         }
     }
-
-    // Try Wikipedia as supplemental
-    match get_wikipedia_summary(query).await {
-        Ok(wiki) => sections.wikipedia = Some(wiki),
-        Err(e) => {
-            eprintln!("[WARN] [touchdictionary] [wikipedia] Failed to fetch Wikipedia summary for '{}': {}", query, e);
+    if let Some(definitions) = &mut result.sections.definitions {
+        let limits = if options.compact {
+            DefinitionLimits { per_source: None, total: Some(COMPACT_DEFINITIONS) }
+        } else if options.all_definitions {
+            DefinitionLimits::UNLIMITED
+        } else {
+            config.definition_limits()
+        };
+        ranking::limit_definitions(definitions, limits);
+    }
+    if options.compact {
+        result.sections.compact();
+    }
+    if let Some(target) = options.translate_to.as_deref().or(config.translate_to.as_deref()) {
+        if !offline && !options.private {
+            translate::translate_result(&mut result, &config.translate, target).await;
         }
     }
+    if !result.sections.is_empty() && !options.private && !options.skip_history {
+        history::record(&result, options.referrer.as_deref(), options.deferred).await;
+    }
+    Ok(result)
+}
 
-    Ok(sections)
+// `lookup_with()` that also sends each section to `sink` as soon as its
+// source answers, for callers that want to show results progressively
+pub async fn lookup_with_progress(
+    ctx: &LookupContext,
+    query: &str,
+    options: &LookupOptions,
+    sink: ProgressSink,
+) -> Result<LookupResult, LookupError> {
+    let options = LookupOptions { progress: Some(sink), ..options.clone() };
+    lookup_with(ctx, query, &options).await
 }
 
-async fn aggregate_entity_sources(query: &str) -> Result<Sections, String> {
-    let mut sections = Sections {
-        definitions: None,
-        wikipedia: None,
-        thesaurus: None,
+// Words warmed by one `prefetch()` at most
+pub const MAX_PREFETCH_WORDS: usize = 10;
+
+// Prefetches running at once, so they stay out of the way of lookups
+const PREFETCH_CONCURRENCY: usize = 2;
+
+// Only definitions are prefetched; Wikipedia is too slow to be worth it
+const PREFETCH_SOURCE: &str = "dictionary";
+
+// Warm the cache for words the user is likely to look up next, such as the
+// synonyms on screen, so they open instantly. Only the dictionary is
+// asked, words already cached are skipped, and entries are stored as
+// prefetched so they expire sooner. Returns how many words were fetched.
+pub async fn prefetch(ctx: &LookupContext, words: &[String]) -> usize {
+    let config = ctx.config();
+    let Some(cache) = ctx.cache() else {
+        return 0;
     };
+    if config.offline.offline_only || ctx.is_private() {
+        return 0;
+    }
+    let registry = SourceRegistry::from_config(&config);
+    let Some(source) = registry.sources().find(|source| source.name() == PREFETCH_SOURCE) else {
+        return 0;
+    };
+    let lang = config.lookup_language();
+    let lang = if source.supports_language(&lang) { lang.as_str() } else { language::DEFAULT_LANGUAGE };
+    let cache_source = cache_source_name(source, lang);
 
-    // Prioritize Wikipedia for entities
-    match get_wikipedia_summary(query).await {
-        Ok(wiki) => sections.wikipedia = Some(wiki),
-        Err(e) => {
-            eprintln!("[ERROR] [touchdictionary] [wikipedia] Failed to fetch Wikipedia for entity '{}': {}", query, e);
+    let words: Vec<String> = words
+        .iter()
+        .map(|word| trim_selection(&normalize_whitespace(word)).to_string())
+        .filter(|word| !word.is_empty())
+        .take(MAX_PREFETCH_WORDS)
+        .collect();
+    let fetched = AtomicUsize::new(0);
+    let (cache_source, fetched) = (&cache_source, &fetched);
+    futures_util::stream::iter(&words)
+        .for_each_concurrent(PREFETCH_CONCURRENCY, |word| async move {
+            if cache.get::<SourceResult>(cache_source, word).await.is_some_and(|hit| hit.is_fresh) {
+                return;
+            }
+            match source.fetch_validated(word, lang).await {
+                Ok((value, validator)) if !value.is_empty() => {
+                    cache.put_prefetched(cache_source, word, &value, validator).await;
+                    fetched.fetch_add(1, Ordering::Relaxed);
+                }
+                Ok(_) => {}
+                Err(e) => info!("Couldn't prefetch '{}': {}", word, e),
+            }
+        })
+        .await;
+
+    let fetched = fetched.load(Ordering::Relaxed);
+    info!("Prefetched {} of {} words", fetched, words.len());
+    fetched
+}
+
+// How long `health_check` waits for each source
+pub const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Whether a source answered its probe, and how
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceHealth {
+    pub name: String,
+    pub reachable: bool,
+    pub latency_ms: u64,
+    pub status_detail: String,
+}
+
+// Ask every enabled source for its probe query at once, to tell a network
+// problem from an upstream one. Answers bypass the cache entirely. In
+// private mode only the local sources are checked.
+pub async fn health_check(ctx: &LookupContext) -> Vec<SourceHealth> {
+    let config = ctx.config();
+    let registry = if config.offline.offline_only || ctx.is_private() {
+        SourceRegistry::offline(&config)
+    } else {
+        SourceRegistry::from_config(&config)
+    };
+    check_sources(&registry, HEALTH_CHECK_TIMEOUT).await
+}
+
+async fn check_sources(registry: &SourceRegistry, timeout: Duration) -> Vec<SourceHealth> {
+    let sources: Vec<_> = registry.sources().chain(registry.fallback().into_iter().flat_map(|f| f.sources())).collect();
+    join_all(sources.into_iter().map(|source| async move {
+        let started = Instant::now();
+        let outcome = tokio::time::timeout(timeout, source.fetch(source.probe_query(), language::DEFAULT_LANGUAGE)).await;
+        let latency_ms = started.elapsed().as_millis() as u64;
+        let (reachable, status_detail) = match outcome {
+            Ok(Ok(result)) if result.is_empty() => (true, "answered, nothing found".to_string()),
+            Ok(Ok(_)) => (true, "ok".to_string()),
+            Ok(Err(e)) if e.is_not_found() => (true, "answered, nothing found".to_string()),
+            Ok(Err(e)) => (!e.is_unavailable() && !matches!(e, LookupError::OfflineUnavailable { .. }), e.to_string()),
+            Err(_) => (false, format!("no answer within {}s", timeout.as_secs_f32())),
+        };
+        SourceHealth { name: source.name().to_string(), reachable, latency_ms, status_detail }
+    }))
+    .await
+}
+
+// Curated words for the word of the day, one per line
+const WORD_OF_THE_DAY_LIST: &str = include_str!("../data/word_of_the_day.txt");
+
+// Cache namespace for word-of-the-day results, keyed by date
+const WORD_OF_THE_DAY_CACHE: &str = "word-of-the-day";
+
+// Today's word, looked up like any other query. The result is cached under
+// the date and its `CacheKey`, so every call on the same day returns it
+// without refetching.
+pub async fn word_of_the_day(ctx: &LookupContext) -> Result<LookupResult, LookupError> {
+    let today = chrono::Local::now().date_naive();
+    let word = word_for_date(today);
+    // The list is English whatever the locale
+    let options = LookupOptions { lang: Some(language::DEFAULT_LANGUAGE.to_string()), ..LookupOptions::default() };
+    let key = format!("{}/{}", today.format("%Y-%m-%d"), CacheKey::from(&ctx.config(), &options, word).as_str());
+    let cache = ctx.cache();
+
+    // The key already pins the day, so an entry is good however old it is
+    if let Some(cache) = &cache {
+        if let Some(cached) = cache.get::<LookupResult>(WORD_OF_THE_DAY_CACHE, &key).await {
+            let mut result = cached.value;
+            result.from_cache = true;
+            return Ok(result);
         }
     }
 
-    Ok(sections)
+    info!("Word of the day for {}: {}", today, word);
+    let mut result = lookup_with(ctx, word, &options).await?;
+    result.is_word_of_the_day = true;
+
+    // A partial result would otherwise stick for the rest of the day
+    if let Some(cache) = cache.filter(|_| result.completeness == Completeness::Full && !ctx.is_private()) {
+        cache.put(WORD_OF_THE_DAY_CACHE, &key, &result).await;
+    }
+    Ok(result)
+}
+
+// The same date always picks the same word. Days are scrambled before
+// indexing so consecutive days don't walk the list alphabetically.
+pub fn word_for_date(date: NaiveDate) -> &'static str {
+    let words: Vec<&'static str> = WORD_OF_THE_DAY_LIST
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+    let index = splitmix64(date.num_days_from_ce() as u64) % words.len() as u64;
+    words[index as usize]
+}
+
+fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+// Two words looked up side by side, like "affect" and "effect"
+#[derive(Debug, Clone, Serialize)]
+pub struct CompareResult {
+    pub first: ComparedWord,
+    pub second: ComparedWord,
+    // The comparison itself, left empty unless both lookups succeeded.
+    // Terms are matched ignoring case.
+    pub shared_synonyms: Vec<String>,
+    pub first_only_synonyms: Vec<String>,
+    pub second_only_synonyms: Vec<String>,
+    pub shared_parts_of_speech: Vec<String>,
+    // Either word is among the other's synonyms, antonyms or related terms
+    pub thesaurus_link: bool,
+}
+
+// One side of a comparison: its result, or why there isn't one
+#[derive(Debug, Clone, Serialize)]
+pub struct ComparedWord {
+    pub query: String,
+    pub result: Option<LookupResult>,
+    pub error: Option<LookupError>,
+}
+
+// `compare_with` using the default options
+pub async fn compare(ctx: &LookupContext, first: &str, second: &str) -> CompareResult {
+    compare_with(ctx, first, second, &LookupOptions::default()).await
+}
+
+// Look both words up at once. One failing still gives the other's result.
+pub async fn compare_with(ctx: &LookupContext, first: &str, second: &str, options: &LookupOptions) -> CompareResult {
+    let (first_result, second_result) =
+        futures_util::future::join(lookup_with(ctx, first, options), lookup_with(ctx, second, options)).await;
+    let side = |query: &str, outcome: Result<LookupResult, LookupError>| match outcome {
+        Ok(result) => ComparedWord { query: query.to_string(), result: Some(result), error: None },
+        Err(e) => {
+            warn!("Couldn't look up '{}' to compare: {}", query, e);
+            ComparedWord { query: query.to_string(), result: None, error: Some(e) }
+        }
+    };
+    compare_results(side(first, first_result), side(second, second_result))
 }
 
-async fn aggregate_all_sources(query: &str) -> Result<Sections, String> {
-    let mut sections = Sections {
-        definitions: None,
-        wikipedia: None,
-        thesaurus: None,
+fn compare_results(first: ComparedWord, second: ComparedWord) -> CompareResult {
+    let mut compared = CompareResult {
+        first,
+        second,
+        shared_synonyms: Vec::new(),
+        first_only_synonyms: Vec::new(),
+        second_only_synonyms: Vec::new(),
+        shared_parts_of_speech: Vec::new(),
+        thesaurus_link: false,
+    };
+    let (Some(a), Some(b)) = (&compared.first.result, &compared.second.result) else {
+        return compared;
     };
 
-    // Try all sources and aggregate results
-    match get_dictionary_definitions(query).await {
-        Ok(defs) if !defs.is_empty() => {
-            sections.definitions = Some(defs);
+    let contains = |terms: &[String], term: &str| terms.iter().any(|t| t.to_lowercase() == term.to_lowercase());
+    let (a_synonyms, b_synonyms) = (synonyms_of(a), synonyms_of(b));
+    for synonym in &a_synonyms {
+        match contains(&b_synonyms, synonym) {
+            true => compared.shared_synonyms.push(synonym.clone()),
+            false => compared.first_only_synonyms.push(synonym.clone()),
         }
-        Ok(_) => {
-            eprintln!("[WARN] [touchdictionary] [dictionary] No definitions found for '{}'", query);
+    }
+    compared.second_only_synonyms = b_synonyms.into_iter().filter(|synonym| !contains(&a_synonyms, synonym)).collect();
+
+    let b_parts = parts_of_speech(b);
+    compared.shared_parts_of_speech = parts_of_speech(a).into_iter().filter(|pos| b_parts.contains(pos)).collect();
+
+    let listed_in = |word: &LookupResult, other: &LookupResult| {
+        let terms = thesaurus_terms(other);
+        contains(&terms, &word.query) || contains(&terms, &word.display_query)
+    };
+    compared.thesaurus_link = listed_in(a, b) || listed_in(b, a);
+    compared
+}
+
+// The thesaurus's synonyms, then those given with definitions, each once
+fn synonyms_of(result: &LookupResult) -> Vec<String> {
+    let thesaurus = result.sections.thesaurus.iter().flat_map(|t| &t.synonyms);
+    let definitions = result.sections.definitions.iter().flatten().flat_map(|s| &s.definitions).flat_map(|d| &d.synonyms);
+    let mut synonyms: Vec<String> = Vec::new();
+    for synonym in thesaurus.chain(definitions) {
+        if !synonyms.iter().any(|s| s.to_lowercase() == synonym.to_lowercase()) {
+            synonyms.push(synonym.clone());
         }
-        Err(e) => {
-            eprintln!("[ERROR] [touchdictionary] [dictionary] Failed to fetch definitions for '{}': {}", query, e);
+    }
+    synonyms
+}
+
+// Every term the result relates its word to
+fn thesaurus_terms(result: &LookupResult) -> Vec<String> {
+    let thesaurus = result.sections.thesaurus.iter().flat_map(|t| t.synonyms.iter().chain(&t.antonyms).chain(&t.related_terms));
+    let definitions = result
+        .sections
+        .definitions
+        .iter()
+        .flatten()
+        .flat_map(|s| &s.definitions)
+        .flat_map(|d| d.synonyms.iter().chain(&d.antonyms));
+    thesaurus.chain(definitions).cloned().collect()
+}
+
+// Lowercased, in the order they first appear
+fn parts_of_speech(result: &LookupResult) -> Vec<String> {
+    let mut parts: Vec<String> = Vec::new();
+    for definition in result.sections.definitions.iter().flatten().flat_map(|s| &s.definitions) {
+        let Some(pos) = definition.part_of_speech.as_deref().map(str::to_lowercase).filter(|pos| !pos.is_empty()) else {
+            continue;
+        };
+        if !parts.contains(&pos) {
+            parts.push(pos);
         }
     }
+    parts
+}
 
-    match get_wikipedia_summary(query).await {
-        Ok(wiki) => sections.wikipedia = Some(wiki),
-        Err(e) => {
-            eprintln!("[WARN] [touchdictionary] [wikipedia] Failed to fetch Wikipedia summary for '{}': {}", query, e);
-        }
-    }
-
-    Ok(sections)
-}
-
-async fn get_dictionary_definitions(query: &str) -> Result<Vec<DefinitionSection>, String> {
-    eprintln!("[INFO] [touchdictionary] [dictionary] Fetching definitions for '{}' from Free Dictionary API", query);
-    
-    let url = format!("https://api.dictionaryapi.dev/api/v2/entries/en/{}", query);
-    
-    match reqwest::get(&url).await {
-        Ok(response) => {
-            if response.status().is_success() {
-                let json_text = response.text().await
-                    .map_err(|e| format!("Failed to read dictionary response: {}", e))?;
-                
-                // Log the actual response for debugging
-                eprintln!("[DEBUG] [touchdictionary] [dictionary] Raw response: {}", &json_text[0..json_text.len().min(200)]);
-                
-                match serde_json::from_str::<Vec<DictionaryApiResponse>>(&json_text) {
-                    Ok(entries) => {
-                        if entries.is_empty() {
-                            return Ok(vec![]);
-                        }
-                        
-                        let mut sections = Vec::new();
-                        
-                        for entry in entries {
-                            let source = "Free Dictionary API".to_string();
-                            let mut definitions = Vec::new();
-                            
-                            for meaning in entry.meanings {
-                                for def in meaning.definitions {
-                                    definitions.push(Definition {
-                                        word: entry.word.clone(),
-                                        part_of_speech: Some(meaning.part_of_speech.clone()),
-                                        definition: def.definition,
-                                        example: def.example,
-                                    });
-                                }
-                            }
-                            
-                            sections.push(DefinitionSection {
-                                source,
-                                definitions,
-                            });
-                        }
-                        
-                        eprintln!("[INFO] [touchdictionary] [dictionary] Successfully fetched {} definitions for '{}'", 
-                                 sections.iter().map(|s| s.definitions.len()).sum::<usize>(), query);
-                        Ok(sections)
+// `lookup()` against a caller-supplied set of sources
+#[tracing::instrument(name = "lookup", skip_all, fields(query = %query))]
+pub async fn lookup_with_sources(
+    registry: &SourceRegistry,
+    query: &str,
+    options: &LookupOptions,
+) -> Result<LookupResult, LookupError> {
+    let cache = Cache::open_default();
+    lookup_in(registry, cache.as_ref(), query, options).await
+}
+
+async fn lookup_in(
+    registry: &SourceRegistry,
+    cache: Option<&Cache>,
+    query: &str,
+    options: &LookupOptions,
+) -> Result<LookupResult, LookupError> {
+    // Dropping the lookup future aborts every request it still has in flight
+    tokio::select! {
+        biased;
+        _ = options.cancel.cancelled() => {
+            info!("Lookup cancelled");
+            Err(LookupError::Cancelled)
+        }
+        result = run_lookup(registry, cache, query, options) => result,
+    }
+}
+
+async fn run_lookup(
+    registry: &SourceRegistry,
+    cache: Option<&Cache>,
+    query: &str,
+    options: &LookupOptions,
+) -> Result<LookupResult, LookupError> {
+    // Classification and Wikipedia need the original casing ("Paris" vs
+    // "paris"); case-insensitive sources lowercase the query themselves
+    let display_query = trim_selection(&normalize_whitespace(query)).to_string();
+    let mut cleaned_query = clean_query(query);
+
+    if cleaned_query.is_empty() {
+        return Err(LookupError::EmptyQuery);
+    }
+
+    let lang = options
+        .lang
+        .as_deref()
+        .map(language::normalize)
+        .unwrap_or_else(default_language);
+    if !language::is_known(&lang) {
+        return Err(LookupError::UnsupportedLanguage { lang });
+    }
+
+    if pattern::is_pattern(&cleaned_query) {
+        return lookup_pattern(registry, display_query, cleaned_query, lang, options).await;
+    }
+
+    let content_type = classify_content(&display_query, options.passage.max_phrase_words);
+    if matches!(content_type, ContentType::Passage) {
+        return lookup_passage(registry, cache, display_query, cleaned_query, lang, options).await;
+    }
+    if matches!(content_type, ContentType::Computed) {
+        return lookup_computed(registry, cache, display_query, cleaned_query, lang, options).await;
+    }
+    let cache = cache.filter(|_| options.use_cache);
+    let from_cache = AtomicBool::new(false);
+    let fetch = FetchContext {
+        cache,
+        from_cache: &from_cache,
+        progress: options.progress.as_ref(),
+        private: options.private,
+    };
+
+    let Aggregated { mut sections, mut errors, mut warnings, mut statuses, suggestions: source_suggestions } =
+        aggregate_sources(registry, &fetch, &display_query, &content_type, &lang).await;
+
+    // Dictionaries tend to list "run" but not "running"; retry an inflected
+    // word under its lemma everywhere but Wikipedia, which keeps the
+    // results for the query as typed
+    let mut lemma_of = None;
+    if sections.definitions.is_none() && matches!(content_type, ContentType::Word) && lang == language::DEFAULT_LANGUAGE {
+        if let Some(lemma) = lemma::lemmatize(&cleaned_query) {
+            info!("No definitions for '{}', trying '{}'", cleaned_query, lemma);
+            let retry =
+                aggregate_selected(registry, &fetch, &lemma, &content_type, &lang, |s| s.accepts_lemma()).await;
+            if retry.sections.definitions.is_some() {
+                sections.definitions = retry.sections.definitions;
+                sections.thesaurus = sections.thesaurus.or(retry.sections.thesaurus);
+                sections.etymology = sections.etymology.or(retry.sections.etymology);
+                sections.examples = sections.examples.or(retry.sections.examples);
+                warnings.extend(retry.warnings);
+                errors.retain(|failure| !retry.statuses.iter().any(|s| s.name == failure.source));
+                errors.extend(retry.errors);
+                for status in retry.statuses {
+                    if let Some(earlier) = statuses.iter_mut().find(|s| s.name == status.name) {
+                        *earlier = status;
                     }
-                    Err(e) => {
-                        let err_msg = format!("Failed to parse dictionary response: {}", e);
-                        eprintln!("[ERROR] [touchdictionary] [dictionary] {}", err_msg);
-                        Err(err_msg)
+                }
+                lemma_of = Some(std::mem::replace(&mut cleaned_query, lemma));
+            }
+        }
+    }
+
+    // No definitions from the dictionaries, because there's no network or
+    // the word is slang: see whether the offline dictionary or Urban
+    // Dictionary knows it
+    if let Some(fallback) = registry.fallback() {
+        if sections.definitions.is_none() {
+            let backup = aggregate_sources(fallback, &fetch, &display_query, &content_type, &lang).await;
+            if backup.sections.definitions.is_some() {
+                sections.definitions = backup.sections.definitions;
+                sections.thesaurus = sections.thesaurus.or(backup.sections.thesaurus);
+                warnings.extend(backup.warnings);
+            }
+            errors.extend(backup.errors);
+            statuses.extend(backup.statuses);
+        } else {
+            statuses.extend(fallback.sources().map(|s| SourceStatus::skipped(s.name())));
+        }
+    }
+
+    // Nothing from either the dictionaries or Wikipedia usually means a
+    // misspelling; ask Datamuse what the user probably meant. Datamuse only
+    // knows English spellings, and acronyms aren't misspelled words.
+    let mut suggestions = None;
+    if sections.definitions.is_none()
+        && sections.wikipedia.is_none()
+        && sections.acronym.is_none()
+        && !matches!(content_type, ContentType::Acronym)
+        && lang == language::DEFAULT_LANGUAGE
+        && !options.offline
+        && !options.private
+    {
+        match get_spelling_suggestions(&cleaned_query).await {
+            Ok(candidates) if !candidates.is_empty() => {
+                if options.auto_correct {
+                    if let Some(correction) = pick_correction(&candidates) {
+                        info!("Auto-correcting '{}' to '{}'", cleaned_query, correction);
+                        let retry = LookupOptions { auto_correct: false, ..options.clone() };
+                        let mut corrected = Box::pin(lookup_in(registry, cache, &correction, &retry)).await?;
+                        corrected.corrected_from = Some(display_query);
+                        return Ok(corrected);
                     }
                 }
-            } else if response.status() == 404 {
-                eprintln!("[INFO] [touchdictionary] [dictionary] No definitions found for '{}' (404)", query);
-                Ok(vec![])
-            } else {
-                let err_msg = format!("Dictionary API returned status: {}", response.status());
-                eprintln!("[ERROR] [touchdictionary] [dictionary] {}", err_msg);
-                Err(err_msg)
+                suggestions = Some(
+                    candidates
+                        .into_iter()
+                        .map(|c| c.word)
+                        .take(MAX_SUGGESTIONS)
+                        .collect(),
+                );
+            }
+            Ok(_) => {}
+            Err(e) => {
+                warn!("Failed to fetch spelling suggestions for '{}': {}", cleaned_query, e);
             }
         }
-        Err(e) => {
-            let err_msg = format!("Failed to connect to dictionary API: {}", e);
-            eprintln!("[ERROR] [touchdictionary] [dictionary] {}", err_msg);
-            Err(err_msg)
+    }
+
+    // Dictionaries that suggest spellings themselves stand in when Datamuse
+    // had nothing (or wasn't asked)
+    if suggestions.is_none()
+        && sections.definitions.is_none()
+        && sections.wikipedia.is_none()
+        && !source_suggestions.is_empty()
+    {
+        let mut unique: Vec<String> = Vec::new();
+        for suggestion in source_suggestions {
+            if !unique.contains(&suggestion) {
+                unique.push(suggestion);
+            }
         }
+        unique.truncate(MAX_SUGGESTIONS);
+        suggestions = Some(unique);
+    }
+
+    // A source that couldn't say whether it had the word turns "nothing
+    // found" into a failure, so callers can tell "offline" from "no such
+    // word"
+    let failures: Vec<SourceFailure> = errors.into_iter().filter(|f| !f.error.is_not_found()).collect();
+    if sections.is_empty() && suggestions.is_none() && !failures.is_empty() {
+        return Err(LookupError::AllSourcesFailed { details: failures });
     }
+    let completeness = Completeness::assess(!sections.is_empty(), failures.into_iter().map(|f| f.source).collect());
+
+    Ok(LookupResult {
+        query: cleaned_query,
+        display_query,
+        content_type,
+        sections,
+        from_cache: from_cache.load(Ordering::Relaxed),
+        suggestions,
+        corrected_from: None,
+        frequency: None,
+        lemma_of,
+        language: lang.clone(),
+        language_used: lang,
+        detected_language: None,
+        locale_language: None,
+        hidden_definitions: BTreeMap::new(),
+        warnings,
+        from_session_cache: false,
+        is_word_of_the_day: false,
+        source_statuses: statuses,
+        completeness,
+    })
 }
 
-async fn get_wikipedia_summary(query: &str) -> Result<WikipediaSection, String> {
-    eprintln!("[INFO] [touchdictionary] [wikipedia] Fetching summary for '{}' from Wikipedia API", query);
-    
-    let formatted_query = query.replace(" ", "_");
-    let url = format!("https://en.wikipedia.org/api/rest_v1/page/summary/{}", formatted_query);
-    
-    let client = reqwest::Client::new();
-    match client
-        .get(&url)
-        .header("User-Agent", "TouchDictionary/0.1.0 (https://github.com/yourusername/touchdictionary)")
-        .send()
-        .await 
-    {
-        Ok(response) => {
-            if response.status().is_success() {
-                match response.json::<WikipediaApiResponse>().await {
-                    Ok(data) => {
-                        if data.extract.is_empty() || data.extract.to_lowercase().contains("may refer to") {
-                            eprintln!("[WARN] [touchdictionary] [wikipedia] Disambiguation page or no content for '{}'", query);
-                            return Err("Disambiguation page or no content".to_string());
-                        }
-                        
-                        eprintln!("[INFO] [touchdictionary] [wikipedia] Successfully fetched summary for '{}'", query);
-                        
-                        // Parse the summary into paragraphs for better formatting
-                        let paragraphs: Vec<String> = data.extract
-                            .split("\n")
-                            .filter(|p| !p.trim().is_empty())
-                            .map(|p| p.trim().to_string())
-                            .collect();
-                        
-                        Ok(WikipediaSection {
-                            title: data.title,
-                            summary: data.extract,
-                            paragraphs,
-                            image_url: data.thumbnail.map(|t| t.source),
-                            url: data.content_urls.desktop.page,
-                        })
+// No title matches a whole sentence, so skip the usual sources and look up
+// the passage's keywords instead, each as a lookup of its own
+async fn lookup_passage(
+    registry: &SourceRegistry,
+    cache: Option<&Cache>,
+    display_query: String,
+    cleaned_query: String,
+    lang: String,
+    options: &LookupOptions,
+) -> Result<LookupResult, LookupError> {
+    let keywords = passage::keywords(&display_query, options.passage.max_keywords);
+    info!("Looking up passage keywords {:?}", keywords);
+
+    let keyword_options = LookupOptions { auto_correct: false, progress: None, ..options.clone() };
+    let outcomes =
+        join_all(keywords.iter().map(|keyword| Box::pin(run_lookup(registry, cache, keyword, &keyword_options)))).await;
+
+    let mut keyword_lookups = Vec::new();
+    let mut errors = Vec::new();
+    let mut failed_sources = Vec::new();
+    for (keyword, outcome) in keywords.into_iter().zip(outcomes) {
+        match outcome {
+            // A keyword with nothing but spelling suggestions isn't worth showing
+            Ok(result) if !result.sections.is_empty() => {
+                if let Completeness::Partial { failed_sources: failed } = &result.completeness {
+                    failed_sources.extend(failed.iter().cloned());
+                }
+                keyword_lookups.push(KeywordLookup { keyword, result });
+            }
+            Ok(_) => {}
+            Err(e) => {
+                warn!("Failed to look up keyword '{}': {}", keyword, e);
+                if let LookupError::AllSourcesFailed { details } = &e {
+                    failed_sources.extend(details.iter().map(|d| d.source.clone()));
+                }
+                errors.push(e);
+            }
+        }
+    }
+    if keyword_lookups.is_empty() {
+        if let Some(e) = errors.into_iter().find(|e| !e.is_not_found()) {
+            return Err(e);
+        }
+    }
+    // Keywords share sources, so a failing one is named once per keyword
+    failed_sources.sort();
+    failed_sources.dedup();
+    let completeness = Completeness::assess(!keyword_lookups.is_empty(), failed_sources);
+    let keyword_lookups = Some(keyword_lookups).filter(|lookups| !lookups.is_empty());
+
+    Ok(LookupResult {
+        query: cleaned_query,
+        display_query,
+        content_type: ContentType::Passage,
+        from_cache: keyword_lookups.iter().flatten().all(|k| k.result.from_cache),
+        sections: Sections { keyword_lookups, ..Sections::default() },
+        suggestions: None,
+        corrected_from: None,
+        frequency: None,
+        lemma_of: None,
+        language: lang.clone(),
+        language_used: lang,
+        detected_language: None,
+        locale_language: None,
+        hidden_definitions: BTreeMap::new(),
+        warnings: Vec::new(),
+        from_session_cache: false,
+        is_word_of_the_day: false,
+        source_statuses: registry.sources().map(|s| SourceStatus::skipped(s.name())).collect(),
+        completeness,
+    })
+}
+
+// Wildcards make it a crossword question rather than a word, so only
+// Datamuse's spelled-like search is asked, which private mode rules out
+async fn lookup_pattern(
+    registry: &SourceRegistry,
+    display_query: String,
+    cleaned_query: String,
+    lang: String,
+    options: &LookupOptions,
+) -> Result<LookupResult, LookupError> {
+    if options.private {
+        return Err(LookupError::PrivateModeBlocked { source: "pattern".to_string() });
+    }
+    let section = pattern::search(&cleaned_query, options.pattern.max_matches).await.map_err(|error| match error {
+        LookupError::InvalidPattern { .. } => error,
+        error => LookupError::AllSourcesFailed { details: vec![SourceFailure { source: "pattern".to_string(), error }] },
+    })?;
+    let found = !section.matches.is_empty();
+    let pattern = Some(section).filter(|_| found);
+
+    Ok(LookupResult {
+        query: cleaned_query,
+        display_query,
+        content_type: ContentType::Pattern,
+        from_cache: false,
+        sections: Sections { pattern, ..Sections::default() },
+        suggestions: None,
+        corrected_from: None,
+        frequency: None,
+        lemma_of: None,
+        language: lang.clone(),
+        language_used: lang,
+        detected_language: None,
+        locale_language: None,
+        hidden_definitions: BTreeMap::new(),
+        warnings: Vec::new(),
+        from_session_cache: false,
+        is_word_of_the_day: false,
+        source_statuses: registry.sources().map(|s| SourceStatus::skipped(s.name())).collect(),
+        completeness: Completeness::assess(found, Vec::new()),
+    })
+}
+
+// Events from a year's article shown with it
+const YEAR_EVENTS: usize = 3;
+
+// Quantities are converted without asking any source, so they work offline.
+// A year also gets its Wikipedia article and a few of the events it lists.
+async fn lookup_computed(
+    registry: &SourceRegistry,
+    cache: Option<&Cache>,
+    display_query: String,
+    cleaned_query: String,
+    lang: String,
+    options: &LookupOptions,
+) -> Result<LookupResult, LookupError> {
+    let mut interpretations = units::convert(&display_query);
+    let mut sections = Sections::default();
+    let mut statuses: Vec<SourceStatus> = registry.sources().map(|s| SourceStatus::skipped(s.name())).collect();
+    let mut errors = Vec::new();
+    let mut from_cache = false;
+
+    if let Some(year) = units::parse_year(&cleaned_query) {
+        interpretations.extend(units::describe_year(year, chrono::Local::now().year()));
+
+        let is_wikipedia = |source: &dyn Source| source.name() == "wikipedia";
+        if !options.offline && registry.sources().any(is_wikipedia) {
+            let cache_flag = AtomicBool::new(false);
+            let fetch = FetchContext {
+                cache: cache.filter(|_| options.use_cache),
+                from_cache: &cache_flag,
+                progress: options.progress.as_ref(),
+                private: options.private,
+            };
+            let (aggregated, events) = tokio::join!(
+                aggregate_selected(registry, &fetch, &cleaned_query, &ContentType::Computed, &lang, is_wikipedia),
+                async {
+                    if options.private {
+                        return Ok(Vec::new());
                     }
-                    Err(e) => {
-                        let err_msg = format!("Failed to parse Wikipedia response: {}", e);
-                        eprintln!("[ERROR] [touchdictionary] [wikipedia] {}", err_msg);
-                        Err(err_msg)
+                    wikipedia::year_events(year, &lang, YEAR_EVENTS).await
+                }
+            );
+            sections.wikipedia = aggregated.sections.wikipedia;
+            errors = aggregated.errors;
+            from_cache = cache_flag.load(Ordering::Relaxed);
+            for status in aggregated.statuses {
+                if let Some(slot) = statuses.iter_mut().find(|s| s.name == status.name) {
+                    *slot = status;
+                }
+            }
+            match events {
+                Ok(events) => interpretations.extend(
+                    events.into_iter().map(|event| Interpretation { label: "On this year".to_string(), value: event }),
+                ),
+                Err(e) => warn!("Failed to fetch events for {}: {}", year, e),
+            }
+        }
+    }
+
+    let found = !interpretations.is_empty();
+    let computed = Some(ComputedSection { input: display_query.clone(), interpretations }).filter(|_| found);
+    Ok(LookupResult {
+        query: cleaned_query,
+        display_query,
+        content_type: ContentType::Computed,
+        from_cache,
+        sections: Sections { computed, ..sections },
+        suggestions: None,
+        corrected_from: None,
+        frequency: None,
+        lemma_of: None,
+        language: lang.clone(),
+        language_used: lang,
+        detected_language: None,
+        locale_language: None,
+        hidden_definitions: BTreeMap::new(),
+        warnings: Vec::new(),
+        from_session_cache: false,
+        is_word_of_the_day: false,
+        source_statuses: statuses,
+        completeness: Completeness::assess(found, errors.into_iter().map(|e| e.source).collect()),
+    })
+}
+
+fn normalize_whitespace(query: &str) -> String {
+    query
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+// Selections drag in surrounding quotes and the comma or full stop after
+// a word. `?` and `!` stay ("Jeopardy!"), as does the final dot of an
+// abbreviation like "U.S.".
+fn trim_selection(query: &str) -> &str {
+    const QUOTES: &[char] = &['"', '\'', '`', '“', '”', '‘', '’', '«', '»'];
+    let mut trimmed = query;
+    loop {
+        let next = trimmed.trim_matches(QUOTES).trim_end_matches([',', ';', ':']);
+        let next = match next.strip_suffix('.') {
+            Some(rest) if !rest.contains('.') => rest,
+            _ => next,
+        };
+        let next = next.trim();
+        if next == trimmed {
+            return trimmed;
+        }
+        trimmed = next;
+    }
+}
+
+fn clean_query(query: &str) -> String {
+    trim_selection(&normalize_whitespace(query)).to_lowercase()
+}
+
+fn classify_content(query: &str, max_phrase_words: usize) -> ContentType {
+    // Simple heuristic to classify content type
+    // In a real implementation, this would be more sophisticated
+    if units::is_computed(query) {
+        return ContentType::Computed;
+    }
+
+    if passage::is_passage(query, max_phrase_words) {
+        return ContentType::Passage;
+    }
+
+    if acronym::is_acronym(query) {
+        return ContentType::Acronym;
+    }
+
+    // Check if it's likely a named entity (proper noun)
+    if query.chars().next().is_some_and(|c| c.is_uppercase()) {
+        return ContentType::Entity;
+    }
+
+    // Check for multi-word phrases (likely entities)
+    if query.split_whitespace().count() > 2 {
+        return ContentType::Entity;
+    }
+
+    // Default to word for single lowercase terms
+    ContentType::Word
+}
+
+// Per-lookup cache access shared by every source fetch
+struct FetchContext<'a> {
+    cache: Option<&'a Cache>,
+    from_cache: &'a AtomicBool,
+    progress: Option<&'a ProgressSink>,
+    // Network sources only get what's already cached, and nothing is written
+    private: bool,
+}
+
+impl FetchContext<'_> {
+    // Serve a source from the cache when fresh, otherwise fetch and store the
+    // result. Entries past the soft TTL are checked with the source first:
+    // unchanged ones are served with their freshness restarted, the rest are
+    // refetched and flagged `changed_since_cache` if they differ from the
+    // cached copy. If the fetch fails because the network is unreachable,
+    // fall back to a stale entry rather than showing nothing.
+    // The flag alongside the value says whether it came from the cache.
+    async fn cached(
+        &self,
+        cache_source: &str,
+        source: &dyn Source,
+        query: &str,
+        lang: &str,
+    ) -> Result<(SourceResult, bool), LookupError> {
+        if self.private && !source.is_local() {
+            return self.cached_only(cache_source, source, query).await;
+        }
+        let Some(cache) = self.cache.filter(|_| source.cacheable() && !self.private) else {
+            return source.fetch(query, lang).await.map(|value| (value, false));
+        };
+
+        let cached = cache.get::<SourceResult>(cache_source, query).await;
+        match &cached {
+            Some(hit) if hit.is_fresh && !hit.needs_revalidation => {
+                info!("Serving {} for '{}' from cache", cache_source, query);
+                cache.record_hit().await;
+                self.from_cache.store(true, Ordering::Relaxed);
+                return Ok((hit.value.clone(), true));
+            }
+            Some(hit) if hit.is_fresh => {
+                cache.record_revalidation().await;
+                if let Some(validator) = &hit.validator {
+                    match source.revalidate(query, lang, validator).await {
+                        Ok(Revalidation::NotModified) => {
+                            cache.touch(cache_source, query).await;
+                            cache.record_hit().await;
+                            self.from_cache.store(true, Ordering::Relaxed);
+                            return Ok((hit.value.clone(), true));
+                        }
+                        Ok(_) => {}
+                        Err(e) => warn!("Couldn't revalidate {} for '{}': {}", cache_source, query, e),
                     }
                 }
-            } else if response.status() == 404 {
-                eprintln!("[INFO] [touchdictionary] [wikipedia] Page not found for '{}' (404)", query);
-                Err("Page not found".to_string())
-            } else {
-                let err_msg = format!("Wikipedia API returned status: {}", response.status());
-                eprintln!("[ERROR] [touchdictionary] [wikipedia] {}", err_msg);
-                Err(err_msg)
             }
+            _ => cache.record_miss().await,
         }
-        Err(e) => {
-            let err_msg = format!("Failed to connect to Wikipedia API: {}", e);
-            eprintln!("[ERROR] [touchdictionary] [wikipedia] {}", err_msg);
-            Err(err_msg)
+
+        match source.fetch_validated(query, lang).await {
+            Ok((mut value, validator)) => {
+                cache.put_validated(cache_source, query, &value, validator).await;
+                if cached.as_ref().is_some_and(|hit| hit.content_hash != cache::content_hash(&value)) {
+                    info!("{} for '{}' changed since it was cached", cache_source, query);
+                    value.mark_changed_since_cache();
+                }
+                Ok((value, false))
+            }
+            Err(e) if e.is_network() => match cached {
+                Some(stale) => {
+                    warn!("Network unreachable, serving stale {} for '{}': {}", cache_source, query, e);
+                    self.from_cache.store(true, Ordering::Relaxed);
+                    Ok((stale.value, true))
+                }
+                None => Err(e),
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    // Private mode's stand-in for asking a network source: whatever the
+    // cache holds, however old, or nothing. Not even the cache's counters
+    // are touched.
+    async fn cached_only(
+        &self,
+        cache_source: &str,
+        source: &dyn Source,
+        query: &str,
+    ) -> Result<(SourceResult, bool), LookupError> {
+        let hit = match self.cache.filter(|_| source.cacheable()) {
+            Some(cache) => cache.get::<SourceResult>(cache_source, query).await,
+            None => None,
+        };
+        match hit {
+            Some(hit) => {
+                info!("Private mode, serving {} for '{}' from cache", cache_source, query);
+                self.from_cache.store(true, Ordering::Relaxed);
+                Ok((hit.value, true))
+            }
+            None => Err(LookupError::PrivateModeBlocked { source: source.name().to_string() }),
         }
     }
 }
 
-async fn get_thesaurus_data(_query: &str) -> Result<ThesaurusSection, String> {
-    // TODO: Implement real thesaurus API integration
-    // For now, return empty data as we don't have a free thesaurus API integrated
-    eprintln!("[INFO] [touchdictionary] [thesaurus] Thesaurus API not yet implemented, returning empty data");
-    
-    Ok(ThesaurusSection {
-        synonyms: vec![],
-        antonyms: vec![],
-        related_terms: vec![],
-    })
+// What the sources of one registry produced for a query
+#[derive(Default)]
+struct Aggregated {
+    sections: Sections,
+    errors: Vec<SourceFailure>,
+    warnings: Vec<String>,
+    statuses: Vec<SourceStatus>,
+    // Spellings offered by sources that had no entry
+    suggestions: Vec<String>,
 }
 
-// API Response Structures
+// Run every source that applies to the content type concurrently, so a
+// lookup takes as long as the slowest source rather than the sum of all.
+// Each source fails independently: a failing Wikipedia call never drops
+// definitions and vice versa. Results are merged in registry order and
+// source errors are returned alongside the sections so `lookup()` can
+// report why nothing was found; every source, including the ones that were
+// skipped, gets a status. Sources that don't support `lang` are queried in
+// English, with a warning.
+async fn aggregate_sources(
+    registry: &SourceRegistry,
+    fetch: &FetchContext<'_>,
+    query: &str,
+    content_type: &ContentType,
+    lang: &str,
+) -> Aggregated {
+    aggregate_selected(registry, fetch, query, content_type, lang, |_| true).await
+}
 
-#[derive(Debug, Deserialize)]
-struct DictionaryApiResponse {
-    word: String,
-    meanings: Vec<Meaning>,
-    phonetics: Option<Vec<Phonetic>>,
+// Where `source`'s answers in `lang` are cached. English entries keep their
+// original keys.
+fn cache_source_name(source: &dyn Source, lang: &str) -> String {
+    if lang == language::DEFAULT_LANGUAGE {
+        source.name().to_string()
+    } else {
+        format!("{}-{}", source.name(), lang)
+    }
 }
 
-#[derive(Debug, Deserialize)]
-struct Phonetic {
-    text: Option<String>,
-    audio: Option<String>,
+// `aggregate_sources` limited to the sources `select` picks; the others get
+// no status at all
+async fn aggregate_selected(
+    registry: &SourceRegistry,
+    fetch: &FetchContext<'_>,
+    query: &str,
+    content_type: &ContentType,
+    lang: &str,
+    select: impl Fn(&dyn Source) -> bool,
+) -> Aggregated {
+    let mut warnings = Vec::new();
+    let sources: Vec<_> = registry
+        .applicable(content_type)
+        .filter(|source| select(*source))
+        .map(|source| {
+            if source.supports_language(lang) {
+                (source, lang)
+            } else {
+                warn!(source = source.name(), "Language '{}' not supported, falling back to English", lang);
+                warnings.push(format!("{} doesn't support '{}'; showing English results", source.name(), lang));
+                (source, language::DEFAULT_LANGUAGE)
+            }
+        })
+        .collect();
+
+    let results = join_all(sources.iter().map(|(source, lang)| {
+        let cache_source = cache_source_name(*source, lang);
+        let span = tracing::info_span!("source", name = source.name());
+        async move {
+            if let Some(sink) = fetch.progress {
+                send_progress(sink, source.name(), "source_started", serde_json::Value::Null);
+            }
+            let started = Instant::now();
+            let mut result = fetch.cached(&cache_source, *source, query, lang).await;
+            if let Ok((result, _)) = &mut result {
+                sanitize::clean_result(result);
+            }
+            let elapsed = started.elapsed();
+            if let Some(sink) = fetch.progress {
+                if let Ok((result, _)) = &result {
+                    send_sections(sink, source.name(), result);
+                }
+                let status = SourceStatus {
+                    name: source.name().to_string(),
+                    outcome: source_outcome(&result),
+                    duration_ms: elapsed.as_millis() as u64,
+                    error: result.as_ref().err().map(|e| e.to_string()),
+                };
+                send_progress(sink, source.name(), "source_finished", serde_json::json!(status));
+            }
+            (result, elapsed)
+        }
+        .instrument(span)
+    }))
+    .await;
+
+    let mut aggregated = Aggregated { warnings, ..Aggregated::default() };
+
+    for ((source, _), (result, elapsed)) in sources.iter().zip(results) {
+        let outcome = source_outcome(&result);
+        let error = match result {
+            Ok((result, _)) if result.is_empty() => {
+                warn!(source = source.name(), "No results found for '{}'", query);
+                None
+            }
+            Ok((SourceResult::Suggestions(suggestions), _)) => {
+                aggregated.suggestions.extend(suggestions);
+                None
+            }
+            Ok((result, _)) => {
+                merge_result(&mut aggregated.sections, result);
+                None
+            }
+            // Left alone on purpose, so not a failure
+            Err(e @ LookupError::PrivateModeBlocked { .. }) => {
+                info!(source = source.name(), "{}", e);
+                Some(e.to_string())
+            }
+            Err(e) => {
+                error!(source = source.name(), "Failed to fetch results for '{}': {}", query, e);
+                let message = e.to_string();
+                aggregated.errors.push(SourceFailure { source: source.name().to_string(), error: e });
+                Some(message)
+            }
+        };
+        aggregated.statuses.push(SourceStatus {
+            name: source.name().to_string(),
+            outcome,
+            duration_ms: elapsed.as_millis() as u64,
+            error,
+        });
+    }
+
+    let skipped = registry
+        .sources()
+        .filter(|source| select(*source) && !source.applies_to(content_type))
+        .map(|source| source.name())
+        .chain(registry.disabled());
+    aggregated.statuses.extend(skipped.map(SourceStatus::skipped));
+
+    aggregated
 }
 
-#[derive(Debug, Deserialize)]
-struct Meaning {
-    #[serde(default)]
-    part_of_speech: String,
-    definitions: Vec<DefinitionResponse>,
+// How a source's fetch went, the flag saying whether it came from the cache
+fn source_outcome(result: &Result<(SourceResult, bool), LookupError>) -> SourceOutcome {
+    match result {
+        Ok((result, _)) if result.is_empty() => SourceOutcome::Empty,
+        Ok((SourceResult::Suggestions(_), _)) => SourceOutcome::Empty,
+        Ok((_, true)) => SourceOutcome::Cached,
+        Ok((_, false)) => SourceOutcome::Ok,
+        Err(e) if e.is_timeout() => SourceOutcome::TimedOut,
+        Err(LookupError::PrivateModeBlocked { .. }) => SourceOutcome::PrivateModeBlocked,
+        Err(_) => SourceOutcome::Error,
+    }
 }
 
-#[derive(Debug, Deserialize)]
-struct DefinitionResponse {
-    #[serde(rename = "definition")]
-    definition: String,
-    example: Option<String>,
-    synonyms: Option<Vec<String>>,
-    antonyms: Option<Vec<String>>,
+fn merge_result(sections: &mut Sections, result: SourceResult) {
+    match result {
+        SourceResult::Definitions(defs) => {
+            sections.definitions.get_or_insert_with(Vec::new).extend(defs);
+        }
+        SourceResult::Wikipedia(wiki) => sections.wikipedia = Some(wiki),
+        // The dictionary's synonyms and Datamuse's end up in one list
+        SourceResult::Thesaurus(thesaurus) => match &mut sections.thesaurus {
+            Some(existing) => existing.merge(thesaurus),
+            None => sections.thesaurus = Some(thesaurus),
+        },
+        SourceResult::Etymology(etymology) => sections.etymology = Some(etymology),
+        SourceResult::Examples(examples) => sections.examples = Some(examples),
+        SourceResult::Acronym(acronym) => sections.acronym = Some(acronym),
+        SourceResult::Rhymes(rhymes) => sections.rhymes = Some(rhymes),
+        SourceResult::Combined(results) => {
+            for result in results.into_iter().filter(|r| !r.is_empty()) {
+                merge_result(sections, result);
+            }
+        }
+        // Collected separately by `aggregate_sources`
+        SourceResult::Suggestions(_) => {}
+    }
 }
 
-#[derive(Debug, Deserialize)]
-struct WikipediaApiResponse {
-    title: String,
-    extract: String,
-    thumbnail: Option<Thumbnail>,
-    content_urls: ContentUrls,
+// Report the non-empty sections in one source's result
+fn send_sections(sink: &ProgressSink, source: &str, result: &SourceResult) {
+    if result.is_empty() {
+        return;
+    }
+    let (section, payload) = match result {
+        SourceResult::Definitions(defs) => ("definitions", serde_json::to_value(defs)),
+        SourceResult::Wikipedia(wiki) => ("wikipedia", serde_json::to_value(wiki)),
+        SourceResult::Thesaurus(thesaurus) => ("thesaurus", serde_json::to_value(thesaurus)),
+        SourceResult::Etymology(etymology) => ("etymology", serde_json::to_value(etymology)),
+        SourceResult::Examples(examples) => ("examples", serde_json::to_value(examples)),
+        SourceResult::Acronym(acronym) => ("acronym", serde_json::to_value(acronym)),
+        SourceResult::Rhymes(rhymes) => ("rhymes", serde_json::to_value(rhymes)),
+        SourceResult::Combined(results) => {
+            for result in results {
+                send_sections(sink, source, result);
+            }
+            return;
+        }
+        SourceResult::Suggestions(_) => return,
+    };
+
+    match payload {
+        Ok(payload) => send_progress(sink, source, section, payload),
+        Err(e) => warn!(source, "Failed to serialize {} section: {}", section, e),
+    }
 }
 
-#[derive(Debug, Deserialize)]
-struct Thumbnail {
-    source: String,
-    width: u32,
-    height: u32,
+fn send_progress(sink: &ProgressSink, source: &str, section: &str, payload: serde_json::Value) {
+    // Nobody listening any more isn't an error; the lookup still finishes
+    let _ = sink.send(SectionUpdate { source: source.to_string(), section: section.to_string(), payload });
 }
 
-#[derive(Debug, Deserialize)]
-struct ContentUrls {
-    desktop: DesktopUrls,
+// Words spelled like the query, best match first, excluding the query itself
+async fn get_spelling_suggestions(query: &str) -> Result<Vec<DatamuseWord>, LookupError> {
+    info!("Fetching spelling suggestions for '{}' from Datamuse API", query);
+
+    let words = datamuse::fetch_words("suggestions", &[("sp", query), ("max", "10")]).await?;
+    Ok(words
+        .into_iter()
+        .filter(|w| !w.word.eq_ignore_ascii_case(query))
+        .collect())
 }
 
-#[derive(Debug, Deserialize)]
-struct DesktopUrls {
-    page: String,
+// A suggestion is confident enough to auto-correct to when it's the only
+// candidate or scores at least twice as well as the runner-up
+fn pick_correction(candidates: &[DatamuseWord]) -> Option<String> {
+    match candidates {
+        [only] => Some(only.word.clone()),
+        [first, second, ..] if first.score >= second.score.saturating_mul(2) => Some(first.word.clone()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sources::Source;
+    use async_trait::async_trait;
+    use std::time::{Duration, Instant};
+
+    struct MockSource {
+        name: &'static str,
+        delay_ms: u64,
+        entities: bool,
+        multilingual: bool,
+        result: fn() -> Result<SourceResult, LookupError>,
+    }
+
+    #[async_trait]
+    impl Source for MockSource {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn applies_to(&self, content_type: &ContentType) -> bool {
+            self.entities || !matches!(content_type, ContentType::Entity)
+        }
+
+        fn supports_language(&self, lang: &str) -> bool {
+            self.multilingual || lang == language::DEFAULT_LANGUAGE
+        }
+
+        async fn fetch(&self, _query: &str, _lang: &str) -> Result<SourceResult, LookupError> {
+            tokio::time::sleep(Duration::from_millis(self.delay_ms)).await;
+            (self.result)()
+        }
+    }
+
+    fn mock_registry() -> SourceRegistry {
+        let mut registry = SourceRegistry::new();
+        registry.register(Box::new(MockSource {
+            name: "dictionary",
+            delay_ms: 300,
+            entities: false,
+            multilingual: false,
+            result: || {
+                Ok(SourceResult::Definitions(vec![DefinitionSection {
+                    source: "Mock Dictionary".to_string(),
+                    definitions: vec![],
+                    phonetics: None,
+                    language: default_language(),
+                    truncated: false,
+                    total_available: 0,
+                    changed_since_cache: false,
+                    examples_truncated: false,
+                }]))
+            },
+        }));
+        registry.register(Box::new(MockSource {
+            name: "wikipedia",
+            delay_ms: 400,
+            entities: true,
+            multilingual: true,
+            result: || Err(LookupError::NotFound { query: "test".to_string() }),
+        }));
+        registry.register(Box::new(MockSource {
+            name: "thesaurus",
+            delay_ms: 200,
+            entities: false,
+            multilingual: false,
+            result: || {
+                Ok(SourceResult::Thesaurus(ThesaurusSection {
+                    synonyms: vec!["exam".to_string()],
+                    antonyms: vec![],
+                    related_terms: vec![],
+                    language: default_language(),
+                }))
+            },
+        }));
+        registry
+    }
+
+    #[tokio::test]
+    async fn test_check_sources_runs_concurrently_with_a_timeout() {
+        let mut registry = mock_registry();
+        registry.register(Box::new(MockSource {
+            name: "etymology",
+            delay_ms: 0,
+            entities: false,
+            multilingual: false,
+            result: || Err(LookupError::Timeout { source: "etymology".to_string() }),
+        }));
+        registry.register(Box::new(MockSource {
+            name: "rhymes",
+            delay_ms: 5000,
+            entities: false,
+            multilingual: false,
+            result: || Err(LookupError::EmptyQuery),
+        }));
+
+        let start = Instant::now();
+        let health = check_sources(&registry, Duration::from_millis(500)).await;
+        assert!(start.elapsed() < Duration::from_millis(900), "took {:?}", start.elapsed());
+
+        let reachable: Vec<_> = health.iter().map(|h| (h.name.as_str(), h.reachable)).collect();
+        assert_eq!(
+            reachable,
+            vec![("dictionary", true), ("wikipedia", true), ("thesaurus", true), ("etymology", false), ("rhymes", false)]
+        );
+        assert_eq!(health[1].status_detail, "answered, nothing found");
+        assert!(health[4].status_detail.starts_with("no answer within"));
+        assert!(health[0].latency_ms >= 300);
+    }
+
+    #[tokio::test]
+    async fn test_aggregate_sources_fetches_concurrently() {
+        let registry = mock_registry();
+        let from_cache = AtomicBool::new(false);
+        let fetch = FetchContext { cache: None, from_cache: &from_cache, progress: None, private: false };
+
+        let start = Instant::now();
+        let Aggregated { sections, errors, warnings, statuses, .. } =
+            aggregate_sources(&registry, &fetch, "test", &ContentType::Word, "en").await;
+        let elapsed = start.elapsed();
+
+        // Total latency should track the slowest source, not the sum of all.
+        // The empty dictionary result is dropped; the Wikipedia failure
+        // doesn't affect the thesaurus.
+        assert!(elapsed >= Duration::from_millis(400));
+        assert!(elapsed < Duration::from_millis(650), "took {:?}", elapsed);
+        assert!(sections.definitions.is_none());
+        assert!(sections.wikipedia.is_none());
+        assert!(sections.thesaurus.is_some());
+        assert_eq!(errors.len(), 1);
+        assert!(warnings.is_empty());
+
+        let outcomes: Vec<_> = statuses.iter().map(|s| (s.name.as_str(), s.outcome)).collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                ("dictionary", SourceOutcome::Empty),
+                ("wikipedia", SourceOutcome::Error),
+                ("thesaurus", SourceOutcome::Ok),
+            ]
+        );
+        assert!(statuses[1].error.as_deref().unwrap().contains("No results found"));
+        assert!(statuses[1].duration_ms >= 400);
+    }
+
+    #[tokio::test]
+    async fn test_private_mode_blocks_network_sources() {
+        let registry = mock_registry();
+        let options = LookupOptions { use_cache: false, private: true, ..LookupOptions::default() };
+
+        let start = Instant::now();
+        let result = lookup_in(&registry, None, "test", &options).await.unwrap();
+        assert!(start.elapsed() < Duration::from_millis(200), "took {:?}", start.elapsed());
+
+        // Nothing was asked, so nothing failed either
+        assert!(result.sections.is_empty());
+        assert_eq!(result.completeness, Completeness::Empty);
+        assert!(result.source_statuses.iter().all(|s| s.outcome == SourceOutcome::PrivateModeBlocked));
+        assert_eq!(result.source_statuses[0].error.as_deref(), Some("dictionary not asked: private mode is on"));
+        assert!(matches!(
+            lookup_in(&registry, None, "c?t", &options).await,
+            Err(LookupError::PrivateModeBlocked { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_aggregate_sources_skips_inapplicable_sources() {
+        let registry = mock_registry();
+        let from_cache = AtomicBool::new(false);
+        let fetch = FetchContext { cache: None, from_cache: &from_cache, progress: None, private: false };
+
+        let start = Instant::now();
+        let Aggregated { sections, errors, statuses, .. } =
+            aggregate_sources(&registry, &fetch, "Paris", &ContentType::Entity, "en").await;
+
+        assert!(start.elapsed() < Duration::from_millis(650));
+        assert!(sections.is_empty());
+        assert!(matches!(errors.as_slice(), [SourceFailure { error: LookupError::NotFound { .. }, .. }]));
+        let skipped: Vec<_> = statuses
+            .iter()
+            .filter(|s| s.outcome == SourceOutcome::Skipped)
+            .map(|s| s.name.as_str())
+            .collect();
+        assert_eq!(skipped, vec!["dictionary", "thesaurus"]);
+    }
+
+    #[tokio::test]
+    async fn test_aggregate_sources_falls_back_to_english() {
+        let registry = mock_registry();
+        let from_cache = AtomicBool::new(false);
+        let fetch = FetchContext { cache: None, from_cache: &from_cache, progress: None, private: false };
+
+        let Aggregated { warnings, .. } = aggregate_sources(&registry, &fetch, "test", &ContentType::Word, "de").await;
+
+        assert_eq!(
+            warnings,
+            vec![
+                "dictionary doesn't support 'de'; showing English results".to_string(),
+                "thesaurus doesn't support 'de'; showing English results".to_string(),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lookup_completeness() {
+        let options = LookupOptions { use_cache: false, offline: true, ..LookupOptions::default() };
+        let timing_out = || MockSource {
+            name: "wikipedia",
+            delay_ms: 0,
+            entities: true,
+            multilingual: true,
+            result: || Err(LookupError::Timeout { source: "Wikipedia".to_string() }),
+        };
+
+        // Wikipedia answering "not found" is a miss, not a failure
+        let result = lookup_with_sources(&mock_registry(), "test", &options).await.unwrap();
+        assert_eq!(result.completeness, Completeness::Full);
+
+        let mut registry = mock_registry();
+        registry.register(Box::new(timing_out()));
+        let result = lookup_with_sources(&registry, "test", &options).await.unwrap();
+        assert_eq!(result.completeness, Completeness::Partial { failed_sources: vec!["wikipedia".to_string()] });
+
+        // Only Wikipedia takes entities: the mock one misses, the other times out
+        let result = lookup_with_sources(&mock_registry(), "Paris", &options).await.unwrap();
+        assert_eq!(result.completeness, Completeness::Empty);
+        assert!(result.found_nothing());
+
+        let err = lookup_with_sources(&registry, "Paris", &options).await.unwrap_err();
+        assert!(matches!(err, LookupError::AllSourcesFailed { ref details } if details.len() == 1));
+    }
+
+    #[tokio::test]
+    async fn test_lookup_computes_units_and_years_offline() {
+        let options = LookupOptions { use_cache: false, offline: true, ..LookupOptions::default() };
+
+        let result = lookup_with_sources(&mock_registry(), "10 m", &options).await.unwrap();
+        assert!(matches!(result.content_type, ContentType::Computed));
+        let computed = result.sections.computed.unwrap();
+        assert_eq!(computed.input, "10 m");
+        assert_eq!(computed.interpretations.len(), 4);
+        assert!(result.sections.definitions.is_none() && result.sections.wikipedia.is_none());
+        assert!(result.source_statuses.iter().all(|s| s.outcome == SourceOutcome::Skipped));
+        assert_eq!(result.completeness, Completeness::Full);
+
+        let result = lookup_with_sources(&mock_registry(), "1789", &options).await.unwrap();
+        let labels: Vec<_> = result.sections.computed.unwrap().interpretations.into_iter().map(|i| i.label).collect();
+        assert_eq!(labels, ["Year", "Century", "Roman numerals", "Leap year"]);
+    }
+
+    #[tokio::test]
+    async fn test_lookup_rejects_unknown_language() {
+        let options = LookupOptions { lang: Some("xx".to_string()), ..LookupOptions::default() };
+        let err = lookup_with_sources(&SourceRegistry::new(), "test", &options).await.unwrap_err();
+        assert!(matches!(err, LookupError::UnsupportedLanguage { ref lang } if lang == "xx"));
+    }
+
+    #[tokio::test]
+    async fn test_lookup_reports_sections_as_sources_finish() {
+        let registry = mock_registry();
+        let (sink, mut updates) = mpsc::unbounded_channel();
+        let options = LookupOptions { use_cache: false, offline: true, progress: Some(sink), ..LookupOptions::default() };
+
+        let result = lookup_with_sources(&registry, "test", &options).await.unwrap();
+        drop(options);
+
+        let mut sections = Vec::new();
+        let mut finished = Vec::new();
+        while let Some(update) = updates.recv().await {
+            match update.section.as_str() {
+                "source_started" => assert!(!finished.contains(&update.source)),
+                "source_finished" => {
+                    assert_eq!(update.payload["name"], serde_json::json!(update.source));
+                    finished.push(update.source);
+                }
+                _ => sections.push(update),
+            }
+        }
+
+        // The empty dictionary and the failed Wikipedia lookup send no section
+        assert_eq!(sections.len(), 1);
+        assert_eq!((sections[0].source.as_str(), sections[0].section.as_str()), ("thesaurus", "thesaurus"));
+        assert_eq!(sections[0].payload["synonyms"], serde_json::json!(["exam"]));
+        assert!(result.sections.thesaurus.is_some());
+        // ...but every source that ran says how it went
+        finished.sort();
+        let mut ran: Vec<String> =
+            result.source_statuses.iter().filter(|s| s.outcome != SourceOutcome::Skipped).map(|s| s.name.clone()).collect();
+        ran.sort();
+        assert_eq!(finished, ran);
+    }
+
+    #[tokio::test]
+    async fn test_lookup_passage_looks_up_keywords() {
+        let registry = mock_registry();
+        let options = LookupOptions { use_cache: false, offline: true, ..LookupOptions::default() };
+
+        let result =
+            lookup_with_sources(&registry, "Tides are caused by the gravitational pull of the Moon.", &options).await.unwrap();
+
+        // "Moon" is an entity only the failing Wikipedia mock takes, so it's left out
+        assert!(matches!(result.content_type, ContentType::Passage));
+        assert!(result.sections.thesaurus.is_none());
+        let keywords: Vec<_> =
+            result.sections.keyword_lookups.iter().flatten().map(|k| k.keyword.as_str()).collect();
+        assert_eq!(keywords, vec!["gravitational", "caused"]);
+        assert!(result.source_statuses.iter().all(|s| s.outcome == SourceOutcome::Skipped));
+    }
+
+    #[tokio::test]
+    async fn test_lookup_stops_when_cancelled() {
+        let registry = mock_registry();
+        let options = LookupOptions { use_cache: false, ..LookupOptions::default() };
+        let cancel = options.cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            cancel.cancel();
+        });
+
+        let start = Instant::now();
+        let err = lookup_with_sources(&registry, "test", &options).await.unwrap_err();
+
+        // The slow mock sources are abandoned rather than awaited
+        assert!(matches!(err, LookupError::Cancelled));
+        assert!(start.elapsed() < Duration::from_millis(250), "took {:?}", start.elapsed());
+    }
+
+    #[test]
+    fn test_classify_content_uses_original_casing() {
+        let words = passage::DEFAULT_MAX_PHRASE_WORDS;
+        assert!(matches!(classify_content(&normalize_whitespace("Paris"), words), ContentType::Entity));
+        assert!(matches!(classify_content(&normalize_whitespace("paris"), words), ContentType::Word));
+        assert!(matches!(classify_content(&normalize_whitespace("  New   York City "), words), ContentType::Entity));
+        assert!(matches!(classify_content(&normalize_whitespace("bank"), words), ContentType::Word));
+        assert!(matches!(classify_content("NASA", words), ContentType::Acronym));
+        assert!(matches!(classify_content("LiDAR", words), ContentType::Acronym));
+        assert!(matches!(classify_content(trim_selection("e.g."), words), ContentType::Acronym));
+        assert!(matches!(classify_content("McCoy", words), ContentType::Entity));
+        assert!(matches!(
+            classify_content("the bank was closed on account of the holiday", words),
+            ContentType::Passage
+        ));
+        assert!(matches!(classify_content("3.5 km", words), ContentType::Computed));
+        assert!(matches!(classify_content("1789", words), ContentType::Computed));
+        assert!(matches!(classify_content("300 K", words), ContentType::Computed));
+    }
+
+    fn wikipedia(paragraphs: &[&str]) -> WikipediaSection {
+        WikipediaSection {
+            title: "Topic".to_string(),
+            summary: "A topic.".to_string(),
+            paragraphs: paragraphs.iter().map(|p| p.to_string()).collect(),
+            image_url: None,
+            url: "https://en.wikipedia.org/wiki/Topic".to_string(),
+            language: "en".to_string(),
+            related: Vec::new(),
+            translated_summary: None,
+            changed_since_cache: false,
+            is_truncated: false,
+            resolved_title: None,
+            canonical_title: None,
+            redirected_from: None,
+            wikidata_id: None,
+        }
+    }
+
+    #[test]
+    fn test_compact_sections() {
+        let fixture: LookupResult =
+            serde_json::from_str(include_str!("../tests/fixtures/lookup_result_serendipity.json")).unwrap();
+        let mut sections = fixture.sections;
+        let wiktionary = &mut sections.definitions.as_mut().unwrap()[1];
+        wiktionary.definitions[0].example = Some("A lucky find.".to_string());
+        wiktionary.definitions.push(wiktionary.definitions[0].clone());
+        let wiki = sections.wikipedia.as_mut().unwrap();
+        wiki.summary = "Dr. Walpole coined it. It means luck. It is old.".to_string();
+
+        sections.compact();
+        let definitions = sections.definitions.as_ref().unwrap();
+        assert_eq!(definitions.iter().map(|s| s.definitions.len()).sum::<usize>(), 3);
+        assert!(definitions[1].truncated && definitions[1].examples_truncated);
+        let examples: Vec<_> = definitions.iter().flat_map(|s| &s.definitions).filter(|d| d.example.is_some()).collect();
+        assert_eq!(examples.len(), 1);
+
+        let wiki = sections.wikipedia.unwrap();
+        assert_eq!(wiki.summary, "Dr. Walpole coined it. It means luck.");
+        assert!(wiki.paragraphs.is_empty() && wiki.related.is_empty() && wiki.is_truncated);
+    }
+
+    #[test]
+    fn test_compare_results() {
+        let serendipity: LookupResult =
+            serde_json::from_str(include_str!("../tests/fixtures/lookup_result_serendipity.json")).unwrap();
+        let mut fortune = serendipity.clone();
+        fortune.query = "fortune".to_string();
+        fortune.display_query = "fortune".to_string();
+        fortune.sections.thesaurus = Some(ThesaurusSection {
+            synonyms: vec!["Luck".to_string(), "wealth".to_string()],
+            antonyms: vec![],
+            related_terms: vec![],
+            language: default_language(),
+        });
+        for definition in fortune.sections.definitions.iter_mut().flatten().flat_map(|s| &mut s.definitions) {
+            definition.synonyms.clear();
+            definition.part_of_speech = Some("Noun".to_string());
+        }
+        let side = |result: &LookupResult| ComparedWord { query: result.query.clone(), result: Some(result.clone()), error: None };
+
+        let compared = compare_results(side(&serendipity), side(&fortune));
+        assert_eq!(compared.shared_synonyms, ["luck"]);
+        assert_eq!(compared.first_only_synonyms, ["chance", "fluke", "fortune"]);
+        assert_eq!(compared.second_only_synonyms, ["wealth"]);
+        assert_eq!(compared.shared_parts_of_speech, ["noun"]);
+        // "fortune" is one of serendipity's synonyms
+        assert!(compared.thesaurus_link);
+
+        let failed = ComparedWord {
+            query: "fortune".to_string(),
+            result: None,
+            error: Some(LookupError::NotFound { query: "fortune".to_string() }),
+        };
+        let compared = compare_results(side(&serendipity), failed);
+        assert!(compared.first.result.is_some());
+        assert!(compared.shared_synonyms.is_empty() && compared.first_only_synonyms.is_empty());
+        assert!(compared.shared_parts_of_speech.is_empty() && !compared.thesaurus_link);
+    }
+
+    #[test]
+    fn test_truncate_wikipedia_at_word() {
+        let mut section = wikipedia(&["First paragraph.", "Second one is longer than the rest", "Third."]);
+        assert!(section.truncate(30));
+        assert_eq!(section.paragraphs, vec!["First paragraph.", "Second one is…"]);
+        assert!(section.is_truncated);
+
+        let mut section = wikipedia(&["Short.", "Also short."]);
+        assert!(!section.truncate(100));
+        assert_eq!(section.paragraphs.len(), 2);
+
+        // A first word longer than what's left is dropped, not split
+        let mut section = wikipedia(&["Antidisestablishmentarianism rules"]);
+        assert!(section.truncate(10));
+        assert!(section.paragraphs.is_empty());
+    }
+
+    #[test]
+    fn test_truncate_never_splits_a_character() {
+        assert_eq!(cut_at_word("naïve café crème brûlée", 15), Some("naïve café"));
+        assert_eq!(cut_at_word("Über Straße", 8), Some("Über"));
+        // Unspaced scripts can be cut between any two characters
+        assert_eq!(cut_at_word("東京は日本の首都です", 4), Some("東京は日"));
+        assert_eq!(cut_at_word("emoji 🦋🦋🦋 here", 8), Some("emoji"));
+        assert_eq!(cut_at_word("word", 0), None);
+    }
+
+    #[test]
+    fn test_truncate_keeps_first_example() {
+        let definition = |example: Option<&str>| Definition {
+            word: "run".to_string(),
+            part_of_speech: Some("verb".to_string()),
+            definition: "To move fast.".to_string(),
+            example: example.map(str::to_string),
+            translated: None,
+            synonyms: Vec::new(),
+            antonyms: Vec::new(),
+            sources: Vec::new(),
+        };
+        let mut sections = Sections {
+            definitions: Some(vec![DefinitionSection {
+                source: "dictionary".to_string(),
+                definitions: vec![definition(None), definition(Some("She runs.")), definition(Some("They ran."))],
+                phonetics: None,
+                language: "en".to_string(),
+                truncated: false,
+                total_available: 3,
+                changed_since_cache: false,
+                examples_truncated: false,
+            }]),
+            ..Sections::default()
+        };
+        assert!(sections.truncate(100));
+        let section = &sections.definitions.as_ref().unwrap()[0];
+        let examples: Vec<_> = section.definitions.iter().map(|d| d.example.as_deref()).collect();
+        assert_eq!(examples, vec![None, Some("She runs."), None]);
+        assert!(section.examples_truncated);
+    }
+
+    #[test]
+    fn test_thesaurus_merge_dedupes_case_insensitively() {
+        let section = |synonyms: &[&str], antonyms: &[&str]| ThesaurusSection {
+            synonyms: synonyms.iter().map(|s| s.to_string()).collect(),
+            antonyms: antonyms.iter().map(|s| s.to_string()).collect(),
+            related_terms: Vec::new(),
+            language: "en".to_string(),
+        };
+        let mut sections = Sections::default();
+        merge_result(&mut sections, SourceResult::Thesaurus(section(&["glad", "Cheerful"], &[])));
+        merge_result(&mut sections, SourceResult::Thesaurus(section(&["cheerful", "content", "GLAD"], &["sad"])));
+        let thesaurus = sections.thesaurus.unwrap();
+        assert_eq!(thesaurus.synonyms, vec!["glad", "Cheerful", "content"]);
+        assert_eq!(thesaurus.antonyms, vec!["sad"]);
+    }
+
+    #[test]
+    fn test_query_language() {
+        let config = Config::default();
+        assert_eq!(query_language(&config, "Straßenbahn").as_deref(), Some("de"));
+        assert_eq!(query_language(&config, " bonjour à  tous ").as_deref(), Some("fr"));
+        assert_eq!(query_language(&config, "Schmetterling"), None);
+        assert_eq!(query_language(&config, "the"), None);
+        assert_eq!(query_language(&config, "sch??tt*"), None);
+
+        let config = Config { auto_detect_language: false, ..Config::default() };
+        assert_eq!(query_language(&config, "Straßenbahn"), None);
+    }
+
+    #[test]
+    fn test_clean_query_keeps_display_form() {
+        assert_eq!(normalize_whitespace("  New   York City "), "New York City");
+        assert_eq!(clean_query("  New   York City "), "new york city");
+    }
+
+    #[test]
+    fn test_clean_query_strips_selection_punctuation() {
+        assert_eq!(clean_query("\"serendipity,\""), "serendipity");
+        assert_eq!(clean_query("“Café.”"), "café");
+        assert_eq!(clean_query("naïve;"), "naïve");
+        assert_eq!(clean_query("'don't'"), "don't");
+        assert_eq!(clean_query("AC/DC."), "ac/dc");
+        assert_eq!(trim_selection("“Python (programming language)”."), "Python (programming language)");
+        assert_eq!(clean_query("Mercury (element)"), "mercury (element)");
+        assert_eq!(clean_query("U.S."), "u.s.");
+        assert_eq!(clean_query("Why?"), "why?");
+        assert_eq!(clean_query("\"...\""), "...");
+    }
+
+    #[test]
+    fn test_pick_correction_requires_clear_winner() {
+        let word = |word: &str, score: u64| DatamuseWord { word: word.to_string(), score };
+
+        assert_eq!(pick_correction(&[word("definitely", 120)]), Some("definitely".to_string()));
+        assert_eq!(
+            pick_correction(&[word("definitely", 3000), word("defiantly", 900)]),
+            Some("definitely".to_string())
+        );
+        assert_eq!(pick_correction(&[word("definitely", 1000), word("defiantly", 900)]), None);
+        assert_eq!(pick_correction(&[]), None);
+    }
+
+    #[test]
+    fn test_word_for_date_is_stable() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 14).unwrap();
+        let word = word_for_date(date);
+        assert_eq!(word, word_for_date(date));
+        assert!(WORD_OF_THE_DAY_LIST.lines().any(|line| line == word));
+
+        // A week of days shouldn't all land on the same word
+        let week: std::collections::HashSet<_> =
+            (0..7).map(|offset| word_for_date(date + chrono::Days::new(offset))).collect();
+        assert!(week.len() > 1);
+    }
 }
@@ -0,0 +1,244 @@
+// Housekeeping for what TouchDictionary keeps on disk: expired cache
+// entries, the image cache and the history. The GUI runs it in the
+// background; `touchdictionary cache prune` runs it by hand.
+
+use std::fs::{File, TryLockError};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::cache::Cache;
+use crate::config::MaintenanceSettings;
+use crate::context::LookupContext;
+use crate::history::History;
+use crate::images::{self, ImageCache};
+use crate::storage::{self, Storage};
+
+pub const DEFAULT_MAX_HISTORY_ENTRIES: usize = 10_000;
+pub const DEFAULT_MAX_CACHE_AGE_DAYS: u64 = 30;
+pub const DEFAULT_VACUUM_THRESHOLD: f64 = 0.25;
+
+// How long the GUI waits after starting before the first run, and how often
+// it runs after that
+pub const STARTUP_DELAY: Duration = Duration::from_secs(5 * 60);
+pub const INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceReport {
+    pub removed_cache_entries: usize,
+    pub removed_images: usize,
+    pub removed_history_entries: usize,
+    pub freed_bytes: u64,
+    pub duration: Duration,
+}
+
+// What one run looks after. Anything left as None is skipped.
+#[derive(Debug, Clone, Default)]
+pub struct Maintenance {
+    pub cache: Option<Cache>,
+    pub images: Option<ImageCache>,
+    pub history: Option<History>,
+    // The database the cache and history live in, vacuumed after they're
+    // trimmed
+    pub storage: Option<Arc<Storage>>,
+    // Held for the whole run so two processes never work on the same files
+    pub lock_path: Option<PathBuf>,
+    pub settings: MaintenanceSettings,
+}
+
+impl Maintenance {
+    // Everything in its default location, with the context's cache and
+    // settings
+    pub fn for_context(ctx: &LookupContext) -> Self {
+        Maintenance {
+            cache: ctx.cache().cloned(),
+            images: images::default_dir().map(ImageCache::new),
+            history: History::open_default(),
+            storage: storage::open_default(),
+            lock_path: default_lock_path(),
+            settings: ctx.config().maintenance.clone(),
+        }
+    }
+
+    // Fails with `WouldBlock` when another process is already running it.
+    // A step that fails is logged and the rest still run.
+    pub async fn run(&self) -> io::Result<MaintenanceReport> {
+        let _lock = match &self.lock_path {
+            Some(path) => Some(lock(path)?),
+            None => None,
+        };
+
+        let started = Instant::now();
+        let mut report = MaintenanceReport::default();
+
+        if let Some(cache) = &self.cache {
+            let max_age = Duration::from_secs(self.settings.max_cache_age_days * 24 * 60 * 60);
+            match cache.prune(max_age).await {
+                Ok((removed, freed)) => {
+                    report.removed_cache_entries = removed;
+                    report.freed_bytes += freed;
+                }
+                Err(e) => warn!("Failed to prune the cache: {}", e),
+            }
+        }
+
+        if let Some(images) = self.images.clone() {
+            match tokio::task::spawn_blocking(move || images.enforce_limit()).await.map_err(io::Error::other)? {
+                Ok((removed, freed)) => {
+                    report.removed_images = removed;
+                    report.freed_bytes += freed;
+                }
+                Err(e) => warn!("Failed to trim the image cache: {}", e),
+            }
+        }
+
+        if let Some(history) = &self.history {
+            match history.trim(self.settings.max_history_entries).await {
+                Ok((removed, freed)) => {
+                    report.removed_history_entries = removed;
+                    report.freed_bytes += freed;
+                }
+                Err(e) => warn!("Failed to trim the history: {}", e),
+            }
+        }
+
+        if let Some(storage) = &self.storage {
+            match storage.vacuum(self.settings.vacuum_threshold).await {
+                Ok(freed) => report.freed_bytes += freed,
+                Err(e) => warn!("Failed to vacuum {}: {}", storage.path().display(), e),
+            }
+        }
+
+        report.duration = started.elapsed();
+        info!(
+            "Maintenance removed {} cache entries, {} images and {} history entries ({} bytes) in {:?}",
+            report.removed_cache_entries,
+            report.removed_images,
+            report.removed_history_entries,
+            report.freed_bytes,
+            report.duration
+        );
+        Ok(report)
+    }
+}
+
+pub async fn run_maintenance(ctx: &LookupContext) -> io::Result<MaintenanceReport> {
+    Maintenance::for_context(ctx).run().await
+}
+
+pub fn default_lock_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("touchdictionary").join("maintenance.lock"))
+}
+
+// Take the lock without waiting. It's released when the file is dropped,
+// including when the process dies.
+fn lock(path: &Path) -> io::Result<File> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let file = File::options().create(true).truncate(false).write(true).open(path)?;
+    match file.try_lock() {
+        Ok(()) => Ok(file),
+        Err(TryLockError::WouldBlock) => {
+            Err(io::Error::new(io::ErrorKind::WouldBlock, "maintenance is already running in another process"))
+        }
+        Err(TryLockError::Error(e)) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::HistoryEntry;
+    use crate::lookup::ContentType;
+
+    #[tokio::test]
+    async fn test_run_trims_everything_under_one_lock() {
+        let dir = std::env::temp_dir().join(format!("touchdictionary-maintenance-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let storage = Storage::open(&dir.join(storage::FILE_NAME)).unwrap();
+        let cache = Cache::new(storage.cache(), Duration::ZERO);
+        cache.put("dictionary", "old", &"value".to_string()).await;
+        let images = ImageCache::new(dir.join("images")).with_max_bytes(10);
+        std::fs::create_dir_all(dir.join("images")).unwrap();
+        std::fs::write(dir.join("images").join("a.jpg"), [0u8; 20]).unwrap();
+        let history = History::new(storage.history());
+        for query in ["one", "two", "three"] {
+            let entry = HistoryEntry {
+                query: query.to_string(),
+                display_query: query.to_string(),
+                content_type: ContentType::Word,
+                timestamp: 0,
+                sources: Vec::new(),
+                referrer: None,
+                deferred: false,
+            };
+            history.append(&entry).await.unwrap();
+        }
+
+        let maintenance = Maintenance {
+            cache: Some(cache),
+            images: Some(images),
+            history: Some(history),
+            storage: None,
+            lock_path: Some(dir.join("maintenance.lock")),
+            settings: MaintenanceSettings { max_history_entries: 1, max_cache_age_days: 0, vacuum_threshold: 1.0 },
+        };
+
+        // Someone else holding the lock keeps this run from starting
+        let held = lock(&dir.join("maintenance.lock")).unwrap();
+        let busy = maintenance.run().await.unwrap_err();
+        assert_eq!(busy.kind(), io::ErrorKind::WouldBlock);
+        drop(held);
+
+        let report = maintenance.run().await.unwrap();
+        assert_eq!(report.removed_cache_entries, 1);
+        assert_eq!(report.removed_images, 1);
+        assert_eq!(report.removed_history_entries, 2);
+        assert!(report.freed_bytes > 20);
+
+        // Nothing left to do the second time
+        let again = maintenance.run().await.unwrap();
+        assert_eq!((again.removed_cache_entries, again.removed_images, again.removed_history_entries), (0, 0, 0));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_run_vacuums_a_mostly_empty_database() {
+        let dir = std::env::temp_dir().join(format!("touchdictionary-vacuum-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join(storage::FILE_NAME);
+
+        let storage = Storage::open(&path).unwrap();
+        let cache = Cache::new(storage.cache(), Duration::ZERO);
+        for i in 0..200 {
+            cache.put("dictionary", &format!("word{}", i), &"x".repeat(4096)).await;
+        }
+        let size = |path: &Path| std::fs::metadata(path).unwrap().len();
+        storage.run(|db| Ok(db.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?)).await.unwrap();
+        let filled = size(&path);
+
+        let maintenance = Maintenance {
+            cache: Some(cache),
+            storage: Some(storage.clone()),
+            settings: MaintenanceSettings { max_cache_age_days: 0, vacuum_threshold: 0.5, ..MaintenanceSettings::default() },
+            ..Maintenance::default()
+        };
+        let report = maintenance.run().await.unwrap();
+        assert_eq!(report.removed_cache_entries, 200);
+        assert!(size(&path) < filled / 4, "{} bytes left of {}", size(&path), filled);
+        assert!(report.freed_bytes >= filled - size(&path));
+
+        // Nothing free to give back the second time
+        let again = maintenance.run().await.unwrap();
+        assert_eq!(again.freed_bytes, 0);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
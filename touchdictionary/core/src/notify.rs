@@ -0,0 +1,164 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::lookup::LookupResult;
+
+// Longest notification body before it's cut off
+pub const BODY_MAX_CHARS: usize = 120;
+
+// Fewest seconds between two notifications, so a burst of selections
+// doesn't bury the desktop
+pub const DEFAULT_MIN_INTERVAL_SECS: u64 = 30;
+
+// Action offered on a notification to open the full result
+pub const OPEN_ACTION: &str = "open";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotifyError {
+    // No notification server to talk to, or it refused the notification
+    Failed(String),
+}
+
+impl fmt::Display for NotifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotifyError::Failed(message) => write!(f, "Failed to show notification: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for NotifyError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub title: String,
+    pub body: String,
+}
+
+impl Notification {
+    // The word and its top definition, or the start of the Wikipedia
+    // summary when there are no definitions
+    pub fn for_result(result: &LookupResult) -> Option<Notification> {
+        let first_definition = result
+            .sections
+            .definitions
+            .iter()
+            .flatten()
+            .flat_map(|section| &section.definitions)
+            .next()
+            .map(|def| match def.part_of_speech.as_deref() {
+                Some(pos) if !pos.is_empty() => format!("({}) {}", pos, def.definition),
+                _ => def.definition.clone(),
+            });
+        let text = first_definition.or_else(|| result.sections.wikipedia.as_ref().map(|wiki| wiki.summary.clone()))?;
+
+        Some(Notification {
+            title: result.display_query.clone(),
+            body: truncate(&text, BODY_MAX_CHARS),
+        })
+    }
+}
+
+// `text` cut to at most `max_chars` characters at a word boundary, with an
+// ellipsis when anything was dropped
+pub fn truncate(text: &str, max_chars: usize) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= max_chars {
+        return text;
+    }
+
+    let keep = max_chars.saturating_sub(1);
+    let cut: String = text.chars().take(keep).collect();
+    let at_word_end = text.chars().nth(keep) == Some(' ');
+    let cut = match cut.rfind(' ') {
+        Some(space) if !at_word_end && space > cut.len() / 2 => &cut[..space],
+        _ => &cut[..],
+    };
+    format!("{}…", cut.trim_end_matches([' ', ',', ';', ':', '.']))
+}
+
+// Lets a notification through at most once per interval
+#[derive(Debug, Default)]
+pub struct Throttle {
+    last: Option<Instant>,
+}
+
+impl Throttle {
+    pub fn allow(&mut self, now: Instant, min_interval: Duration) -> bool {
+        if self.last.is_some_and(|last| now.duration_since(last) < min_interval) {
+            return false;
+        }
+        self.last = Some(now);
+        true
+    }
+}
+
+// Show `notification` on the desktop. With `action`, waits until the
+// notification is clicked or dismissed and says whether it was clicked.
+// Only freedesktop notification servers report clicks; elsewhere the
+// notification is just shown.
+pub async fn send(notification: &Notification, action: Option<&str>) -> Result<bool, NotifyError> {
+    let mut desktop = notify_rust::Notification::new();
+    desktop.appname("TouchDictionary").summary(&notification.title).body(&notification.body);
+    let action = action.map(str::to_string);
+    tokio::task::spawn_blocking(move || show(desktop, action.as_deref()))
+        .await
+        .map_err(|e| NotifyError::Failed(e.to_string()))?
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn show(mut desktop: notify_rust::Notification, action: Option<&str>) -> Result<bool, NotifyError> {
+    if let Some(action) = action {
+        desktop.action(action, "Open");
+    }
+    let handle = desktop.show().map_err(|e| NotifyError::Failed(e.to_string()))?;
+    let Some(action) = action else {
+        return Ok(false);
+    };
+    let mut clicked = false;
+    handle.wait_for_action(|chosen| clicked = chosen == action);
+    Ok(clicked)
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+fn show(desktop: notify_rust::Notification, _action: Option<&str>) -> Result<bool, NotifyError> {
+    desktop.show().map(|_| false).map_err(|e| NotifyError::Failed(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> LookupResult {
+        serde_json::from_str(include_str!("../tests/fixtures/lookup_result_serendipity.json")).unwrap()
+    }
+
+    #[test]
+    fn test_notification_for_result() {
+        let notification = Notification::for_result(&fixture()).unwrap();
+        assert_eq!(notification.title, "serendipity");
+        assert!(notification.body.starts_with("(noun) An unsought, unintended"), "{}", notification.body);
+        assert!(notification.body.chars().count() <= BODY_MAX_CHARS);
+
+        let mut empty = fixture();
+        empty.sections = Default::default();
+        assert_eq!(Notification::for_result(&empty), None);
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("short  and\nsweet", 120), "short and sweet");
+        assert_eq!(truncate("one two three four five", 14), "one two three…");
+        assert_eq!(truncate("supercalifragilistic", 6), "super…");
+    }
+
+    #[test]
+    fn test_throttle() {
+        let mut throttle = Throttle::default();
+        let start = Instant::now();
+        let interval = Duration::from_secs(30);
+        assert!(throttle.allow(start, interval));
+        assert!(!throttle.allow(start + Duration::from_secs(10), interval));
+        assert!(throttle.allow(start + Duration::from_secs(30), interval));
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+// Longest selection still looked up as a phrase, and how many of a
+// passage's keywords get looked up in its place
+pub const DEFAULT_MAX_PHRASE_WORDS: usize = 6;
+pub const DEFAULT_MAX_KEYWORDS: usize = 3;
+
+// Function words and everyday vocabulary, one per line. Nobody selects a
+// paragraph to find out what "because" means.
+const COMMON_WORDS: &str = include_str!("../data/common_words.txt");
+
+// Shorter words are rarely the interesting part of a sentence
+const MIN_KEYWORD_CHARS: usize = 4;
+
+fn common_words() -> &'static HashSet<&'static str> {
+    static WORDS: OnceLock<HashSet<&'static str>> = OnceLock::new();
+    WORDS.get_or_init(|| COMMON_WORDS.lines().map(str::trim).filter(|w| !w.is_empty()).collect())
+}
+
+fn is_common(word: &str) -> bool {
+    common_words().contains(word.to_lowercase().as_str())
+}
+
+// A selection too long to be a title: more than `max_phrase_words` words,
+// or a sentence break somewhere inside it
+pub fn is_passage(text: &str, max_phrase_words: usize) -> bool {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    if words.len() < 2 {
+        return false;
+    }
+    words.len() > max_phrase_words || words[..words.len() - 1].iter().any(|word| ends_sentence(word))
+}
+
+// "rain." ends a sentence; "Mr." and "U.S." don't
+fn ends_sentence(word: &str) -> bool {
+    let stem = word.trim_end_matches(['"', '\'', ')', '”', '’']);
+    let Some(stem) = stem.strip_suffix(['.', '?', '!']) else {
+        return false;
+    };
+    !stem.contains('.') && stem.chars().filter(|c| c.is_alphabetic()).count() > 3
+}
+
+// Up to `max` words worth looking up from `text`, best first: runs of
+// capitalized words ("Marie Curie", "Paris") ahead of the longest
+// uncommon words
+pub fn keywords(text: &str, max: usize) -> Vec<String> {
+    // (is a name, the candidate, position), ranked below
+    let mut candidates: Vec<(bool, String, usize)> = Vec::new();
+    let mut span: Vec<&str> = Vec::new();
+    let mut sentence_start = true;
+
+    let tokens: Vec<&str> = text.split_whitespace().collect();
+    for (position, token) in tokens.iter().enumerate() {
+        let word = token.trim_matches(|c: char| !c.is_alphanumeric());
+        let capitalized = word.chars().next().is_some_and(char::is_uppercase);
+        let breaks_span = token.ends_with([',', ';', ':', '.', '?', '!', ')']) || ends_sentence(token);
+
+        // The first word of a sentence is capitalized anyway; it only
+        // counts as a name when the next word is one too
+        let next_capitalized = tokens
+            .get(position + 1)
+            .and_then(|next| next.trim_start_matches(|c: char| !c.is_alphanumeric()).chars().next())
+            .is_some_and(char::is_uppercase);
+        let starts_name = !sentence_start || (next_capitalized && !breaks_span);
+
+        if capitalized && !is_common(word) && (starts_name || !span.is_empty()) {
+            span.push(word);
+        } else {
+            push_span(&mut candidates, &mut span, position);
+            if is_keyword(word) {
+                candidates.push((false, word.to_lowercase(), position));
+            }
+        }
+        if breaks_span {
+            push_span(&mut candidates, &mut span, position + 1);
+        }
+        sentence_start = ends_sentence(token);
+    }
+    push_span(&mut candidates, &mut span, tokens.len());
+
+    candidates.sort_by(|a, b| {
+        b.0.cmp(&a.0)
+            .then(b.1.chars().count().cmp(&a.1.chars().count()))
+            .then(a.2.cmp(&b.2))
+    });
+    let mut keywords: Vec<String> = Vec::new();
+    for (_, candidate, _) in candidates {
+        if !keywords.iter().any(|k| k.eq_ignore_ascii_case(&candidate)) {
+            keywords.push(candidate);
+        }
+    }
+    keywords.truncate(max);
+    keywords
+}
+
+fn push_span(candidates: &mut Vec<(bool, String, usize)>, span: &mut Vec<&str>, position: usize) {
+    if !span.is_empty() {
+        candidates.push((true, span.join(" "), position - span.len()));
+        span.clear();
+    }
+}
+
+fn is_keyword(word: &str) -> bool {
+    word.chars().count() >= MIN_KEYWORD_CHARS
+        && word.chars().all(|c| c.is_alphabetic() || c == '-' || c == '\'')
+        && !is_common(word)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_passage() {
+        assert!(is_passage("the quick brown fox jumps over the lazy dog", DEFAULT_MAX_PHRASE_WORDS));
+        assert!(is_passage("It rained. Then it stopped", DEFAULT_MAX_PHRASE_WORDS));
+        assert!(!is_passage("New York City", DEFAULT_MAX_PHRASE_WORDS));
+        assert!(!is_passage("Jeopardy!", DEFAULT_MAX_PHRASE_WORDS));
+        assert!(!is_passage("Mr. Smith Goes to Washington", DEFAULT_MAX_PHRASE_WORDS));
+        assert!(!is_passage("the U.S. Supreme Court", DEFAULT_MAX_PHRASE_WORDS));
+        assert!(is_passage("a long selection", 2));
+    }
+
+    #[test]
+    fn test_keywords_prefers_names_then_long_words() {
+        let text = "The experiments of Marie Curie showed that radioactivity was a property of atoms.";
+        assert_eq!(keywords(text, 3), vec!["Marie Curie", "radioactivity", "experiments"]);
+
+        // Sentence-initial capitals aren't names on their own
+        let text = "Photosynthesis turns sunlight into chemical energy. Plants rely on it.";
+        assert_eq!(keywords(text, 2), vec!["photosynthesis", "sunlight"]);
+    }
+
+    #[test]
+    fn test_keywords_skips_common_words_and_duplicates() {
+        assert!(keywords("it is what it is and that is that", 3).is_empty());
+        assert_eq!(keywords("Serendipity, serendipity everywhere", 3), vec!["serendipity", "everywhere"]);
+    }
+}
@@ -0,0 +1,60 @@
+use tracing::info;
+
+use crate::error::LookupError;
+use crate::lookup::{PatternMatch, PatternSection};
+use crate::sources::datamuse;
+
+// Matches listed for a pattern when the config doesn't say
+pub const DEFAULT_MAX_MATCHES: usize = 20;
+
+// `?` stands for one letter and `*` for any number, as Datamuse's
+// spelled-like search takes them
+const WILDCARDS: &[char] = &['?', '*'];
+
+// A crossword-style pattern like "c?t" or "s*ment" rather than a word
+pub fn is_pattern(query: &str) -> bool {
+    !query.contains(char::is_whitespace) && query.contains(WILDCARDS)
+}
+
+// Patterns need a letter to go on; "???" would list every three-letter word
+pub fn validate(pattern: &str) -> Result<(), LookupError> {
+    if pattern.chars().any(|c| c.is_alphanumeric()) {
+        Ok(())
+    } else {
+        Err(LookupError::InvalidPattern { pattern: pattern.to_string() })
+    }
+}
+
+// Words spelled like `pattern`, most common first
+pub async fn search(pattern: &str, max_matches: usize) -> Result<PatternSection, LookupError> {
+    validate(pattern)?;
+    info!("Searching Datamuse for words spelled like '{}'", pattern);
+
+    let max = max_matches.to_string();
+    let words = datamuse::fetch_words("pattern", &[("sp", pattern), ("max", &max)]).await?;
+    Ok(PatternSection {
+        matches: words
+            .into_iter()
+            .take(max_matches)
+            .map(|w| PatternMatch { word: w.word, score: w.score })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_pattern_and_validate() {
+        assert!(is_pattern("c?t"));
+        assert!(is_pattern("s*ment"));
+        assert!(!is_pattern("cat"));
+        assert!(!is_pattern("what is this?"));
+
+        assert!(validate("c?t").is_ok());
+        let err = validate("?*?").unwrap_err();
+        assert_eq!(err.kind(), "invalid_pattern");
+        assert_eq!(err.to_string(), "Pattern '?*?' needs at least one letter");
+    }
+}
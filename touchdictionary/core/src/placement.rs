@@ -0,0 +1,68 @@
+// Where to put the window for a selection lookup. Everything is in physical
+// pixels of the desktop the cursor is on; the GUI converts sizes between
+// monitors with different scale factors before asking.
+
+// Logical pixels between the cursor and the window, so the window doesn't
+// cover the selection it was opened for
+pub const CURSOR_OFFSET: f64 = 16.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    fn right(&self) -> i64 {
+        self.x as i64 + self.width as i64
+    }
+
+    fn bottom(&self) -> i64 {
+        self.y as i64 + self.height as i64
+    }
+}
+
+// Top-left corner for a `width` x `height` window near `cursor`: below and
+// to the right of it, flipped above or to the left when that would run off
+// `work_area`, and finally clamped inside it
+pub fn near_cursor(cursor: (i32, i32), width: u32, height: u32, work_area: Rect, offset: i32) -> (i32, i32) {
+    let x = axis(cursor.0, width, work_area.x, work_area.right(), offset);
+    let y = axis(cursor.1, height, work_area.y, work_area.bottom(), offset);
+    (x, y)
+}
+
+fn axis(cursor: i32, size: u32, start: i32, end: i64, offset: i32) -> i32 {
+    let (cursor, size, start, offset) = (cursor as i64, size as i64, start as i64, offset as i64);
+    let mut position = cursor + offset;
+    if position + size > end {
+        position = cursor - offset - size;
+    }
+    // A window larger than the work area keeps its top/left edge visible
+    position.min(end - size).max(start) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: Rect = Rect { x: 0, y: 0, width: 1920, height: 1080 };
+
+    #[test]
+    fn test_near_cursor_offsets_and_flips() {
+        assert_eq!(near_cursor((100, 100), 400, 300, SCREEN, 16), (116, 116));
+        // Too close to the right and bottom edges: open above-left instead
+        assert_eq!(near_cursor((1800, 1000), 400, 300, SCREEN, 16), (1384, 684));
+        // No room on either side: stay inside the work area
+        assert_eq!(near_cursor((500, 500), 400, 1200, SCREEN, 16), (516, 0));
+    }
+
+    #[test]
+    fn test_near_cursor_on_secondary_monitor() {
+        // A monitor left of and above the primary, with a panel at the top
+        let work_area = Rect { x: -2560, y: -1400, width: 2560, height: 1400 };
+        assert_eq!(near_cursor((-100, -1390), 800, 600, work_area, 32), (-932, -1358));
+        assert_eq!(near_cursor((-2550, -10), 800, 600, work_area, 32), (-2518, -642));
+    }
+}
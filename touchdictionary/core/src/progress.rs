@@ -0,0 +1,179 @@
+// The CLI's progress line on stderr while a lookup runs: a spinner and how
+// each source is doing, redrawn in place from the lookup's progress updates
+// and erased before the result is printed, so nothing is left behind in
+// output piped through a pager.
+
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio::time::{self, Instant};
+
+use crate::lookup::{ProgressSink, SectionUpdate, SourceOutcome};
+use crate::terminal;
+
+const FRAMES: &[char] = &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+// Also how long a lookup runs before anything is drawn, so cache hits
+// don't flicker
+const TICK: Duration = Duration::from_millis(100);
+
+// Where the line is cut when the terminal's width is unknown
+const DEFAULT_WIDTH: usize = 80;
+
+// A source as the line shows it: still running, or how it went and how
+// long it took
+type SourceProgress = (String, Option<(SourceOutcome, u64)>);
+
+// Set while the line is on screen, for the stderr logger to clear it first
+static DRAWING: AtomicBool = AtomicBool::new(false);
+
+pub struct Spinner {
+    sink: ProgressSink,
+    stop: oneshot::Sender<()>,
+    task: JoinHandle<()>,
+}
+
+impl Spinner {
+    // Start drawing on stderr. Callers check `enabled` first.
+    pub fn start() -> Spinner {
+        let (sink, updates) = mpsc::unbounded_channel();
+        let (stop, stopped) = oneshot::channel();
+        let task = tokio::spawn(draw(updates, stopped));
+        Spinner { sink, stop, task }
+    }
+
+    // Where the lookup sends its progress
+    pub fn sink(&self) -> ProgressSink {
+        self.sink.clone()
+    }
+
+    // Stop and erase the line, leaving the cursor where it started
+    pub async fn finish(self) {
+        let _ = self.stop.send(());
+        let _ = self.task.await;
+    }
+}
+
+// Whether to show a spinner: only on a terminal, and not with --quiet
+pub fn enabled(quiet: bool) -> bool {
+    !quiet && terminal::stderr_is_terminal()
+}
+
+pub fn is_drawing() -> bool {
+    DRAWING.load(Ordering::Relaxed)
+}
+
+async fn draw(mut updates: mpsc::UnboundedReceiver<SectionUpdate>, mut stopped: oneshot::Receiver<()>) {
+    let mut sources: Vec<SourceProgress> = Vec::new();
+    let mut ticks = time::interval_at(Instant::now() + TICK, TICK);
+    let mut frame = 0;
+    let mut drawn = false;
+    let width = terminal::stderr_width().unwrap_or(DEFAULT_WIDTH);
+
+    loop {
+        tokio::select! {
+            _ = &mut stopped => break,
+            Some(update) = updates.recv() => apply(&mut sources, update),
+            _ = ticks.tick() => {
+                let mut stderr = std::io::stderr().lock();
+                let _ = write!(stderr, "\r\x1b[2K{}", render_line(&sources, frame, width));
+                let _ = stderr.flush();
+                frame += 1;
+                drawn = true;
+                DRAWING.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    if drawn {
+        let mut stderr = std::io::stderr().lock();
+        DRAWING.store(false, Ordering::Relaxed);
+        let _ = write!(stderr, "\r\x1b[2K");
+        let _ = stderr.flush();
+    }
+}
+
+fn apply(sources: &mut Vec<SourceProgress>, update: SectionUpdate) {
+    let finished = match update.section.as_str() {
+        "source_started" => None,
+        "source_finished" => {
+            let outcome = serde_json::from_value(update.payload["outcome"].clone()).unwrap_or(SourceOutcome::Ok);
+            Some((outcome, update.payload["duration_ms"].as_u64().unwrap_or(0)))
+        }
+        _ => return,
+    };
+    match sources.iter_mut().find(|(name, _)| *name == update.source) {
+        Some(source) => source.1 = finished,
+        None => sources.push((update.source, finished)),
+    }
+}
+
+// "⠹ dictionary ✓ 230ms · wikipedia …", cut to fit in `width` columns so
+// it never wraps onto a line the erase would miss
+fn render_line(sources: &[SourceProgress], frame: usize, width: usize) -> String {
+    let statuses: Vec<String> = sources
+        .iter()
+        .map(|(name, finished)| match finished {
+            None => format!("{} …", name),
+            Some((SourceOutcome::Cached, _)) => format!("{} ✓ cached", name),
+            Some((SourceOutcome::Ok, ms)) => format!("{} ✓ {}ms", name, ms),
+            Some((SourceOutcome::Empty, ms)) => format!("{} – {}ms", name, ms),
+            Some((SourceOutcome::TimedOut, _)) => format!("{} ✗ timed out", name),
+            Some((SourceOutcome::PrivateModeBlocked, _)) => format!("{} – private", name),
+            Some((_, ms)) => format!("{} ✗ {}ms", name, ms),
+        })
+        .collect();
+    let status = if statuses.is_empty() { "looking up…".to_string() } else { statuses.join(" · ") };
+    let line = format!("{} {}", FRAMES[frame % FRAMES.len()], status);
+
+    // The last column is left free; some terminals wrap as soon as it's used
+    let max = width.saturating_sub(1).max(1);
+    if line.chars().count() <= max {
+        return line;
+    }
+    let mut cut: String = line.chars().take(max - 1).collect();
+    cut.push('…');
+    cut
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(source: &str, section: &str, payload: serde_json::Value) -> SectionUpdate {
+        SectionUpdate { source: source.to_string(), section: section.to_string(), payload }
+    }
+
+    #[test]
+    fn test_line_follows_sources() {
+        let mut sources = Vec::new();
+        assert_eq!(render_line(&sources, 0, 80), "⠋ looking up…");
+
+        apply(&mut sources, update("dictionary", "source_started", serde_json::Value::Null));
+        apply(&mut sources, update("wikipedia", "source_started", serde_json::Value::Null));
+        apply(&mut sources, update("dictionary", "definitions", serde_json::json!([])));
+        apply(
+            &mut sources,
+            update("dictionary", "source_finished", serde_json::json!({"name": "dictionary", "outcome": "Ok", "duration_ms": 230})),
+        );
+        assert_eq!(render_line(&sources, 2, 80), "⠹ dictionary ✓ 230ms · wikipedia …");
+
+        apply(
+            &mut sources,
+            update("wikipedia", "source_finished", serde_json::json!({"name": "wikipedia", "outcome": "TimedOut", "duration_ms": 5000})),
+        );
+        assert_eq!(render_line(&sources, 0, 80), "⠋ dictionary ✓ 230ms · wikipedia ✗ timed out");
+    }
+
+    #[test]
+    fn test_line_never_wraps() {
+        let sources = vec![("dictionary".to_string(), None), ("wikipedia".to_string(), None)];
+        let line = render_line(&sources, 0, 20);
+        assert_eq!(line.chars().count(), 19);
+        assert!(line.ends_with('…'));
+        assert_eq!(render_line(&sources, 0, 0), "…");
+    }
+}
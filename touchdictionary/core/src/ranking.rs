@@ -0,0 +1,380 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::format;
+use crate::lookup::{Definition, DefinitionSection};
+
+// Definitions kept from each source unless the config says otherwise.
+// Common words like "set" have dozens.
+pub const DEFAULT_MAX_DEFINITIONS_PER_SOURCE: usize = 8;
+
+// Parts of speech in order of how often they're what someone is after;
+// anything else comes after these
+const PART_OF_SPEECH_ORDER: &[&str] = &["noun", "verb", "adjective", "adverb"];
+
+// How many definitions a lookup keeps. `None` keeps everything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DefinitionLimits {
+    pub per_source: Option<usize>,
+    pub total: Option<usize>,
+}
+
+impl DefinitionLimits {
+    pub const UNLIMITED: DefinitionLimits = DefinitionLimits { per_source: None, total: None };
+}
+
+// Share of their words two definitions need in common to count as the same
+// sense (Jaccard similarity of their word sets)
+const DUPLICATE_SIMILARITY: f64 = 0.6;
+
+// Definitions this many words long or shorter are compared by spelling
+// instead, allowing one edit per so many letters ("colour" and "color", but
+// not "sad" and "mad")
+const SHORT_DEFINITION_WORDS: usize = 3;
+const LETTERS_PER_EDIT: usize = 6;
+
+// Words that say nothing about which sense a definition is
+const STOPWORDS: &[&str] = &[
+    "a", "an", "the", "of", "to", "or", "and", "in", "on", "for", "by", "with", "as", "at", "from", "is", "be",
+    "that", "which", "who", "its", "one", "something", "someone",
+];
+
+// Definitions without a part of speech are counted under this when filtered
+pub const OTHER_PART_OF_SPEECH: &str = "other";
+
+// Lowercased, with the usual dictionary abbreviations spelled out
+// ("Adj." -> "adjective")
+pub fn normalize_part_of_speech(part_of_speech: &str) -> String {
+    let part_of_speech = part_of_speech.trim().to_lowercase();
+    let full = match part_of_speech.trim_end_matches('.') {
+        "n" => "noun",
+        "v" | "vb" => "verb",
+        "adj" => "adjective",
+        "adv" => "adverb",
+        "prep" => "preposition",
+        "pron" => "pronoun",
+        "conj" => "conjunction",
+        "interj" => "interjection",
+        "det" => "determiner",
+        "abbr" | "abbrev" => "abbreviation",
+        _ => return part_of_speech,
+    };
+    full.to_string()
+}
+
+fn part_of_speech_rank(part_of_speech: Option<&str>) -> usize {
+    let part_of_speech = normalize_part_of_speech(part_of_speech.unwrap_or_default());
+    PART_OF_SPEECH_ORDER
+        .iter()
+        .position(|pos| *pos == part_of_speech)
+        .unwrap_or(PART_OF_SPEECH_ORDER.len())
+}
+
+// Keep only definitions whose part of speech is one of `wanted`, dropping
+// sections left empty. Returns how many were dropped per part of speech.
+pub fn filter_parts_of_speech(sections: &mut Vec<DefinitionSection>, wanted: &[String]) -> BTreeMap<String, usize> {
+    let wanted: Vec<String> = wanted.iter().map(|pos| normalize_part_of_speech(pos)).collect();
+    let mut hidden = BTreeMap::new();
+    for section in sections.iter_mut() {
+        section.definitions.retain(|def| {
+            let part_of_speech = def
+                .part_of_speech
+                .as_deref()
+                .map(normalize_part_of_speech)
+                .filter(|pos| !pos.is_empty());
+            let keep = part_of_speech.as_ref().is_some_and(|pos| wanted.contains(pos));
+            if !keep {
+                let key = part_of_speech.unwrap_or_else(|| OTHER_PART_OF_SPEECH.to_string());
+                *hidden.entry(key).or_insert(0) += 1;
+            }
+            keep
+        });
+    }
+    sections.retain(|section| !section.definitions.is_empty());
+    hidden
+}
+
+// Best definitions first: grouped by part of speech (nouns, verbs,
+// adjectives, adverbs, then the rest), and within a group the ones with an
+// example ahead of those without. Otherwise the source's order is kept.
+pub fn rank_definitions(definitions: &mut [Definition]) {
+    definitions.sort_by_key(|def| (part_of_speech_rank(def.part_of_speech.as_deref()), def.example.is_none()));
+}
+
+// Merge definitions that different sources give in nearly the same words.
+// Of each pair the more detailed one stays, takes over what only the other
+// had (an example, synonyms), and lists both sources in `sources`. Sections
+// left with nothing to show are dropped. Returns how many were merged away.
+pub fn dedupe_definitions(sections: &mut Vec<DefinitionSection>) -> usize {
+    let positions: Vec<(usize, usize)> = sections
+        .iter()
+        .enumerate()
+        .flat_map(|(s, section)| (0..section.definitions.len()).map(move |d| (s, d)))
+        .collect();
+    let words: Vec<Vec<String>> = positions.iter().map(|&(s, d)| content_words(&sections[s].definitions[d].definition)).collect();
+
+    let mut merged = BTreeSet::new();
+    for (a, &(section_a, def_a)) in positions.iter().enumerate() {
+        for (b, &(section_b, def_b)) in positions.iter().enumerate().skip(a + 1) {
+            if merged.contains(&a) {
+                break;
+            }
+            if merged.contains(&b) || sections[section_a].source == sections[section_b].source {
+                continue;
+            }
+            let (first, second) = (&sections[section_a].definitions[def_a], &sections[section_b].definitions[def_b]);
+            if !same_part_of_speech(first, second) || !similar_words(&words[a], &words[b]) {
+                continue;
+            }
+            // Ties go to the source listed first
+            let (keep, lose, lost) = if detail(second) > detail(first) {
+                ((section_b, def_b), (section_a, def_a), a)
+            } else {
+                ((section_a, def_a), (section_b, def_b), b)
+            };
+            let loser = sections[lose.0].definitions[lose.1].clone();
+            let loser_source = sections[lose.0].source.clone();
+            let keeper_source = sections[keep.0].source.clone();
+            absorb(&mut sections[keep.0].definitions[keep.1], &keeper_source, loser, &loser_source);
+            merged.insert(lost);
+        }
+    }
+
+    for (i, &(s, d)) in positions.iter().enumerate().rev() {
+        if merged.contains(&i) {
+            sections[s].definitions.remove(d);
+        }
+    }
+    sections.retain(|section| !section.definitions.is_empty() || section.phonetics.as_ref().is_some_and(|p| !p.is_empty()));
+    merged.len()
+}
+
+// Lowercased words of `text` without punctuation or stopwords, in order
+fn content_words(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty() && !STOPWORDS.contains(word))
+        .map(str::to_string)
+        .collect()
+}
+
+fn similar_words(a: &[String], b: &[String]) -> bool {
+    if a.is_empty() || b.is_empty() {
+        return false;
+    }
+    if a.len() <= SHORT_DEFINITION_WORDS && b.len() <= SHORT_DEFINITION_WORDS {
+        let (a, b) = (a.join(" "), b.join(" "));
+        let allowed = a.chars().count().min(b.chars().count()) / LETTERS_PER_EDIT;
+        return format::edit_distance(&a, &b) <= allowed;
+    }
+    let a: BTreeSet<&String> = a.iter().collect();
+    let b: BTreeSet<&String> = b.iter().collect();
+    let shared = a.intersection(&b).count();
+    shared as f64 / (a.len() + b.len() - shared) as f64 >= DUPLICATE_SIMILARITY
+}
+
+// A missing part of speech matches any
+fn same_part_of_speech(a: &Definition, b: &Definition) -> bool {
+    match (a.part_of_speech.as_deref(), b.part_of_speech.as_deref()) {
+        (Some(a), Some(b)) if !a.is_empty() && !b.is_empty() => normalize_part_of_speech(a) == normalize_part_of_speech(b),
+        _ => true,
+    }
+}
+
+// Longer text first, then having an example
+fn detail(definition: &Definition) -> (usize, bool) {
+    (definition.definition.chars().count(), definition.example.is_some())
+}
+
+fn absorb(keeper: &mut Definition, keeper_source: &str, loser: Definition, loser_source: &str) {
+    if keeper.sources.is_empty() {
+        keeper.sources.push(keeper_source.to_string());
+    }
+    let loser_sources = if loser.sources.is_empty() { vec![loser_source.to_string()] } else { loser.sources };
+    for source in loser_sources {
+        if !keeper.sources.contains(&source) {
+            keeper.sources.push(source);
+        }
+    }
+    keeper.part_of_speech = keeper.part_of_speech.take().filter(|pos| !pos.is_empty()).or(loser.part_of_speech);
+    keeper.example = keeper.example.take().or(loser.example);
+    for (kept, extra) in [(&mut keeper.synonyms, loser.synonyms), (&mut keeper.antonyms, loser.antonyms)] {
+        for word in extra {
+            if !kept.iter().any(|k| k.eq_ignore_ascii_case(&word)) {
+                kept.push(word);
+            }
+        }
+    }
+}
+
+// Rank each section's definitions and cut them down to `limits`, recording
+// how many there were so the full list can be asked for. The total is
+// shared across sections in order, but every section keeps at least one
+// definition so each source still shows up.
+pub fn limit_definitions(sections: &mut [DefinitionSection], limits: DefinitionLimits) {
+    let mut remaining = limits.total;
+    for section in sections {
+        rank_definitions(&mut section.definitions);
+        section.total_available = section.definitions.len();
+
+        let mut keep = section.definitions.len();
+        if let Some(per_source) = limits.per_source {
+            keep = keep.min(per_source);
+        }
+        if let Some(remaining) = remaining {
+            keep = keep.min(remaining.max(1));
+        }
+        section.definitions.truncate(keep);
+        section.truncated = keep < section.total_available;
+        remaining = remaining.map(|r| r.saturating_sub(keep));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn definition(part_of_speech: &str, text: &str, example: bool) -> Definition {
+        Definition {
+            word: "set".to_string(),
+            part_of_speech: Some(part_of_speech.to_string()),
+            definition: text.to_string(),
+            example: example.then(|| format!("an example of {}", text)),
+            translated: None,
+            synonyms: Vec::new(),
+            antonyms: Vec::new(),
+            sources: Vec::new(),
+        }
+    }
+
+    fn section(definitions: Vec<Definition>) -> DefinitionSection {
+        DefinitionSection {
+            source: "Test".to_string(),
+            definitions,
+            phonetics: None,
+            language: "en".to_string(),
+            truncated: false,
+            total_available: 0,
+            changed_since_cache: false,
+            examples_truncated: false,
+        }
+    }
+
+    fn texts(definitions: &[Definition]) -> Vec<&str> {
+        definitions.iter().map(|def| def.definition.as_str()).collect()
+    }
+
+    #[test]
+    fn test_rank_definitions() {
+        let mut definitions = vec![
+            definition("interjection", "hey", true),
+            definition("verb", "to put", false),
+            definition("adjective", "fixed", true),
+            definition("noun", "a collection", false),
+            definition("verb", "to harden", true),
+            definition("noun", "a stage set", true),
+            definition("adj", "ready", false),
+        ];
+        rank_definitions(&mut definitions);
+        assert_eq!(
+            texts(&definitions),
+            vec!["a stage set", "a collection", "to harden", "to put", "fixed", "ready", "hey"]
+        );
+    }
+
+    #[test]
+    fn test_limit_definitions_per_source_and_total() {
+        let many = || (0..12).map(|i| definition("noun", &format!("sense {}", i), false)).collect::<Vec<_>>();
+        let mut sections = vec![section(many()), section(many()), section(many())];
+        limit_definitions(&mut sections, DefinitionLimits { per_source: Some(8), total: Some(10) });
+
+        let kept: Vec<_> = sections.iter().map(|s| (s.definitions.len(), s.total_available, s.truncated)).collect();
+        assert_eq!(kept, vec![(8, 12, true), (2, 12, true), (1, 12, true)]);
+
+        let mut sections = vec![section(many())];
+        limit_definitions(&mut sections, DefinitionLimits::UNLIMITED);
+        assert_eq!((sections[0].definitions.len(), sections[0].total_available, sections[0].truncated), (12, 12, false));
+    }
+
+    fn from(source: &str, definitions: Vec<Definition>) -> DefinitionSection {
+        DefinitionSection { source: source.to_string(), ..section(definitions) }
+    }
+
+    #[test]
+    fn test_dedupe_merges_the_same_sense_from_two_sources() {
+        let mut short = definition("noun", "The occurrence of events by chance in a happy or beneficial way.", false);
+        short.synonyms = vec!["fluke".to_string()];
+        let mut sections = vec![
+            from("Free Dictionary API", vec![short, definition("noun", "a lucky find", false)]),
+            from(
+                "Wiktionary",
+                vec![definition("Noun", "The occurrence and development of events by chance, in a happy or beneficial way", true)],
+            ),
+        ];
+
+        assert_eq!(dedupe_definitions(&mut sections), 1);
+        // The more detailed Wiktionary sense stays and names both sources
+        assert_eq!(texts(&sections[0].definitions), vec!["a lucky find"]);
+        let merged = &sections[1].definitions[0];
+        assert!(merged.definition.contains("and development"));
+        assert_eq!(merged.sources, vec!["Wiktionary", "Free Dictionary API"]);
+        assert_eq!(merged.synonyms, vec!["fluke"]);
+        // Unmerged ones keep just their section's source
+        assert!(sections[0].definitions[0].sources.is_empty());
+    }
+
+    #[test]
+    fn test_dedupe_leaves_near_misses_alone() {
+        let mut sections = vec![
+            from("Free Dictionary API", vec![definition("noun", "A large body of water surrounded by land.", false)]),
+            from(
+                "Wiktionary",
+                vec![
+                    definition("noun", "A large body of salt water.", false),
+                    // Same words, different part of speech
+                    definition("verb", "A large body of water surrounded by land.", false),
+                ],
+            ),
+            // The same source twice isn't a duplicate across sources
+            from("Free Dictionary API", vec![definition("noun", "A large body of water surrounded by land", false)]),
+        ];
+        assert_eq!(dedupe_definitions(&mut sections), 0);
+        assert_eq!(sections.iter().map(|s| s.definitions.len()).sum::<usize>(), 4);
+    }
+
+    #[test]
+    fn test_dedupe_compares_short_definitions_by_spelling() {
+        let mut sections = vec![
+            from("Free Dictionary API", vec![definition("adjective", "Glad.", false), definition("adjective", "Happy.", false)]),
+            from("Merriam-Webster", vec![definition("adj", "happy", false)]),
+            from("Wiktionary", vec![definition("adjective", "gladly", false), definition("adjective", "sad", false)]),
+            from("Urban Dictionary", vec![definition("adjective", "mad", false), definition("adjective", "happy feelings", false)]),
+        ];
+        assert_eq!(dedupe_definitions(&mut sections), 1);
+        // Merriam-Webster had nothing else, so its section goes
+        assert_eq!(
+            sections.iter().map(|s| s.source.as_str()).collect::<Vec<_>>(),
+            vec!["Free Dictionary API", "Wiktionary", "Urban Dictionary"]
+        );
+        assert_eq!(sections[0].definitions[1].sources, vec!["Free Dictionary API", "Merriam-Webster"]);
+    }
+
+    #[test]
+    fn test_filter_parts_of_speech() {
+        let mut sections = vec![
+            section(vec![definition("Verb", "to put", false), definition("noun", "a collection", false)]),
+            section(vec![definition("n.", "a stage set", true), definition("adj", "ready", false)]),
+        ];
+        sections[1].definitions.push(Definition { part_of_speech: None, ..definition("", "untagged", false) });
+
+        let hidden = filter_parts_of_speech(&mut sections, &["v".to_string()]);
+        assert_eq!(sections.len(), 1);
+        assert_eq!(texts(&sections[0].definitions), vec!["to put"]);
+        let hidden: Vec<(&str, usize)> = hidden.iter().map(|(pos, n)| (pos.as_str(), *n)).collect();
+        assert_eq!(hidden, vec![("adjective", 1), ("noun", 2), ("other", 1)]);
+
+        let hidden = filter_parts_of_speech(&mut sections, &["ADJECTIVE".to_string()]);
+        assert!(sections.is_empty());
+        assert_eq!(hidden["verb"], 1);
+    }
+
+}
@@ -0,0 +1,160 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use governor::{DefaultDirectRateLimiter, Quota, RateLimiter};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+// Upstream requests in flight at once, across every upstream, unless the
+// config says otherwise
+pub const DEFAULT_MAX_CONCURRENT: usize = 8;
+
+// Upstream APIs that get their own pacing. Sources sharing a host share a
+// limiter, so Wiktionary definitions and etymology count against one budget.
+pub const DICTIONARY: &str = "dictionary";
+pub const WIKIPEDIA: &str = "wikipedia";
+pub const WIKTIONARY: &str = "wiktionary";
+pub const DATAMUSE: &str = "datamuse";
+pub const MERRIAM_WEBSTER: &str = "merriam_webster";
+pub const URBAN_DICTIONARY: &str = "urban_dictionary";
+pub const TATOEBA: &str = "tatoeba";
+pub const WIKIDATA: &str = "wikidata";
+
+// Requests per second each upstream is held to unless the config says
+// otherwise. The free dictionary API is the one that pushes back first.
+pub fn default_limits() -> BTreeMap<String, f64> {
+    [
+        (DICTIONARY, 2.0),
+        (WIKIPEDIA, 5.0),
+        (WIKTIONARY, 5.0),
+        (DATAMUSE, 5.0),
+        (MERRIAM_WEBSTER, 2.0),
+        (URBAN_DICTIONARY, 2.0),
+        (TATOEBA, 2.0),
+        (WIKIDATA, 5.0),
+    ]
+        .into_iter()
+        .map(|(name, rate)| (name.to_string(), rate))
+        .collect()
+}
+
+// Spaces requests evenly at `rate` per second, without bursts. Callers over
+// the limit wait rather than fail. The quota is built from the interval
+// rather than `Quota::per_second`, which only takes whole rates and lets a
+// full second's worth through at once. None for a rate of 0 or less.
+fn limiter(rate: f64) -> Option<DefaultDirectRateLimiter> {
+    if !(rate > 0.0 && rate.is_finite()) {
+        return None;
+    }
+    Quota::with_period(Duration::from_secs_f64(1.0 / rate)).map(RateLimiter::direct)
+}
+
+// One limiter per upstream with its rate, shared by every lookup in the
+// process
+type Limiters = BTreeMap<String, (f64, Arc<DefaultDirectRateLimiter>)>;
+static LIMITERS: Mutex<Limiters> = Mutex::new(BTreeMap::new());
+
+// Apply per-upstream limits. Upstreams whose rate is unchanged keep their
+// limiter, so requests already queued stay paced; a rate of 0 or less, or a
+// missing entry, leaves that upstream unlimited.
+pub fn configure(limits: &BTreeMap<String, f64>) {
+    update(&mut LIMITERS.lock().unwrap_or_else(|e| e.into_inner()), limits);
+}
+
+fn update(limiters: &mut Limiters, limits: &BTreeMap<String, f64>) {
+    limiters.retain(|name, (current, _)| limits.get(name) == Some(current));
+    for (name, &rate) in limits {
+        if limiters.contains_key(name) {
+            continue;
+        }
+        if let Some(limiter) = limiter(rate) {
+            limiters.insert(name.clone(), (rate, Arc::new(limiter)));
+        }
+    }
+}
+
+// Bounds requests in flight across every upstream; None when unlimited
+static IN_FLIGHT: Mutex<Option<(usize, Arc<Semaphore>)>> = Mutex::new(None);
+
+// Allow at most `max` upstream requests at once, 0 meaning no limit. An
+// unchanged limit keeps its semaphore, so requests already waiting keep
+// their place.
+pub fn configure_concurrency(max: usize) {
+    let mut in_flight = IN_FLIGHT.lock().unwrap_or_else(|e| e.into_inner());
+    if in_flight.as_ref().map(|(current, _)| *current) == Some(max) {
+        return;
+    }
+    *in_flight = (max > 0).then(|| (max, Arc::new(Semaphore::new(max))));
+}
+
+// Held for as long as a request is in flight
+#[derive(Debug)]
+pub struct Permit {
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+// Wait for a request slot on `upstream`, then for one of the slots shared
+// by every upstream. Keep the permit until the response has arrived.
+pub async fn acquire(upstream: &str) -> Permit {
+    let limiter = LIMITERS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(upstream)
+        .map(|(_, limiter)| limiter.clone());
+    if let Some(limiter) = limiter {
+        limiter.until_ready().await;
+    }
+
+    let semaphore = IN_FLIGHT
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .map(|(_, semaphore)| semaphore.clone());
+    match semaphore {
+        // The semaphore is never closed
+        Some(semaphore) => Permit { _permit: semaphore.acquire_owned().await.ok() },
+        None => Permit { _permit: None },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[tokio::test]
+    async fn test_requests_are_spaced_not_rejected() {
+        let limiter = Arc::new(limiter(50.0).unwrap());
+        let start = Instant::now();
+
+        let waits = (0..5).map(|_| {
+            let limiter = limiter.clone();
+            async move { limiter.until_ready().await }
+        });
+        futures_util::future::join_all(waits).await;
+
+        // First request goes straight through, the other four wait 20ms each
+        assert!(start.elapsed() >= Duration::from_millis(80));
+        assert!(start.elapsed() < Duration::from_millis(500));
+    }
+
+    // On a map of its own; the process-wide one paces every other test
+    #[tokio::test]
+    async fn test_configure_keeps_unchanged_limiters() {
+        let mut limiters = Limiters::new();
+        let mut limits = BTreeMap::new();
+        limits.insert("test-upstream".to_string(), 1.0);
+        update(&mut limiters, &limits);
+        let before = limiters.get("test-upstream").cloned().unwrap();
+
+        update(&mut limiters, &limits);
+        let after = limiters.get("test-upstream").cloned().unwrap();
+        assert!(Arc::ptr_eq(&before.1, &after.1));
+
+        limits.insert("test-upstream".to_string(), 0.0);
+        update(&mut limiters, &limits);
+        assert!(!limiters.contains_key("test-upstream"));
+        // Upstreams without a limiter never wait
+        tokio::time::timeout(Duration::from_millis(50), acquire("test-upstream")).await.unwrap();
+    }
+}
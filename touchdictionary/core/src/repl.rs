@@ -0,0 +1,219 @@
+// `touchdictionary repl`: look words up one line at a time without
+// starting a new process (and a new connection pool) for each one
+
+use std::io;
+
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{CompletionType, Config, Editor, Helper};
+use tracing::error;
+
+use crate::context::LookupContext;
+use crate::error::LookupError;
+use crate::favorites;
+use crate::format::{self, OutputFormat, TextOptions};
+use crate::language;
+use crate::lookup::{self, LookupOptions};
+
+const PROMPT: &str = "touchdictionary> ";
+
+// Completed by Tab at the start of a line
+const COMMANDS: &[&str] = &[":lang", ":json", ":text", ":markdown", ":format", ":fav", ":help", ":quit"];
+
+const HELP: &str = "\
+Type a word to look it up. Commands:
+  :lang [code]     Look words up in another language, e.g. :lang de
+  :json            Print results as JSON
+  :text            Print results as text
+  :markdown        Print results as markdown
+  :format <fmt>    text, markdown, json or ndjson
+  :fav [word]      Save a word to favorites; the last one looked up by default
+  :help            Show this help
+  :quit            Leave (so does Ctrl+D)
+Ctrl+C cancels a lookup that's taking too long.";
+
+#[derive(Debug, Clone, PartialEq)]
+enum ReplCommand {
+    Empty,
+    Lookup(String),
+    // None shows the current language
+    Lang(Option<String>),
+    Format(OutputFormat),
+    Fav(Option<String>),
+    Help,
+    Quit,
+}
+
+fn parse_line(line: &str) -> Result<ReplCommand, String> {
+    let line = line.trim();
+    let Some(command) = line.strip_prefix(':') else {
+        return Ok(if line.is_empty() { ReplCommand::Empty } else { ReplCommand::Lookup(line.to_string()) });
+    };
+    let (name, argument) = match command.split_once(char::is_whitespace) {
+        Some((name, argument)) => (name, Some(argument.trim().to_string()).filter(|a| !a.is_empty())),
+        None => (command, None),
+    };
+    match (name, argument) {
+        ("lang", None) => Ok(ReplCommand::Lang(None)),
+        ("lang", Some(code)) => {
+            let code = language::normalize(&code);
+            if language::is_known(&code) {
+                Ok(ReplCommand::Lang(Some(code)))
+            } else {
+                Err(format!("Unknown language '{}'", code))
+            }
+        }
+        ("json", None) => Ok(ReplCommand::Format(OutputFormat::Json)),
+        ("text", None) => Ok(ReplCommand::Format(OutputFormat::Text)),
+        ("markdown" | "md", None) => Ok(ReplCommand::Format(OutputFormat::Markdown)),
+        ("format", Some(format)) => format.parse().map(ReplCommand::Format),
+        ("format", None) => Err(":format requires one of text, markdown, json, ndjson".to_string()),
+        ("fav", word) => Ok(ReplCommand::Fav(word)),
+        ("help" | "h" | "?", None) => Ok(ReplCommand::Help),
+        ("quit" | "q" | "exit", None) => Ok(ReplCommand::Quit),
+        (name, _) => Err(format!("Unknown command ':{}', try :help", name)),
+    }
+}
+
+// Tab completion for `:` commands; words are left alone
+struct ReplHelper;
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _: &rustyline::Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok((0, complete(&line[..pos])))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+fn complete(typed: &str) -> Vec<String> {
+    if !typed.starts_with(':') || typed.contains(char::is_whitespace) {
+        return Vec::new();
+    }
+    COMMANDS.iter().filter(|command| command.starts_with(typed)).map(|command| command.to_string()).collect()
+}
+
+pub async fn run(mut options: LookupOptions, mut output: OutputFormat, text: TextOptions) -> io::Result<()> {
+    let ctx = LookupContext::load();
+    let config = Config::builder().completion_type(CompletionType::List).auto_add_history(true).build();
+    let mut editor: Editor<ReplHelper, DefaultHistory> = Editor::with_config(config).map_err(io::Error::other)?;
+    editor.set_helper(Some(ReplHelper));
+    let mut last_query: Option<String> = None;
+    println!("Type a word to look it up, :help for commands, :quit to leave.");
+
+    loop {
+        // Reading blocks, so it happens off the runtime; the editor moves
+        // there and back to keep its history
+        let (returned, line) = tokio::task::spawn_blocking(move || {
+            let line = editor.readline(PROMPT);
+            (editor, line)
+        })
+        .await
+        .map_err(io::Error::other)?;
+        editor = returned;
+
+        let line = match line {
+            Ok(line) => line,
+            // Ctrl+C throws the line away
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(io::Error::other(e)),
+        };
+        let command = match parse_line(&line) {
+            Ok(command) => command,
+            Err(e) => {
+                eprintln!("{}", e);
+                continue;
+            }
+        };
+
+        match command {
+            ReplCommand::Empty => {}
+            ReplCommand::Quit => break,
+            ReplCommand::Help => println!("{}", HELP),
+            ReplCommand::Lang(None) => {
+                let lang = options.lang.clone().unwrap_or_else(|| ctx.config().lookup_language());
+                println!("Looking words up in '{}'", lang);
+            }
+            ReplCommand::Lang(Some(lang)) => {
+                println!("Looking words up in '{}'", lang);
+                options.lang = Some(lang);
+            }
+            ReplCommand::Format(format) => output = format,
+            ReplCommand::Fav(word) => match word.or_else(|| last_query.clone()) {
+                Some(word) if options.private || ctx.is_private() => {
+                    eprintln!("'{}' not saved: private mode is on", word)
+                }
+                Some(word) => match favorites::add(&word, None, Vec::new()).await {
+                    Ok(entry) => println!("Saved '{}' to favorites", entry.word),
+                    Err(e) => error!("Failed to save '{}': {}", word, e),
+                },
+                None => eprintln!("Nothing to save yet; look a word up or use :fav <word>"),
+            },
+            ReplCommand::Lookup(query) => {
+                // Ctrl+C only reaches us as a signal while a lookup runs; at
+                // the prompt the editor reads it as a key
+                let outcome = tokio::select! {
+                    outcome = lookup::lookup_with(&ctx, &query, &options) => outcome,
+                    _ = tokio::signal::ctrl_c() => Err(LookupError::Cancelled),
+                };
+                match outcome {
+                    Ok(result) if result.found_nothing() => {
+                        eprintln!("{}", LookupError::NotFound { query: query.clone() });
+                    }
+                    Ok(result) => {
+                        println!("{}", format::render(&result, output, &text));
+                        last_query = Some(result.query);
+                    }
+                    Err(LookupError::Cancelled) => eprintln!("Cancelled"),
+                    Err(e) => error!("Lookup failed for '{}': {}", query, e),
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        assert_eq!(parse_line("   "), Ok(ReplCommand::Empty));
+        assert_eq!(parse_line(" New York "), Ok(ReplCommand::Lookup("New York".to_string())));
+        assert_eq!(parse_line(":lang DE"), Ok(ReplCommand::Lang(Some("de".to_string()))));
+        assert_eq!(parse_line(":lang"), Ok(ReplCommand::Lang(None)));
+        assert!(parse_line(":lang klingon").is_err());
+        assert_eq!(parse_line(":json"), Ok(ReplCommand::Format(OutputFormat::Json)));
+        assert_eq!(parse_line(":format md"), Ok(ReplCommand::Format(OutputFormat::Markdown)));
+        assert!(parse_line(":format").is_err());
+        assert_eq!(parse_line(":fav"), Ok(ReplCommand::Fav(None)));
+        assert_eq!(parse_line(":fav petrichor"), Ok(ReplCommand::Fav(Some("petrichor".to_string()))));
+        assert_eq!(parse_line(":q"), Ok(ReplCommand::Quit));
+        assert!(parse_line(":quit now").is_err());
+        assert!(parse_line(":frobnicate").is_err());
+    }
+
+    #[test]
+    fn test_complete_commands() {
+        assert_eq!(complete(":la"), [":lang"]);
+        assert_eq!(complete(":f"), [":format", ":fav"]);
+        assert!(complete("la").is_empty());
+        assert!(complete(":lang d").is_empty());
+    }
+}
@@ -0,0 +1,355 @@
+// Cleanup for the text sources hand over, so it reads as prose: HTML tags
+// and entities, wiki markup, citation marks, list numbering and trailing
+// "see X" cross-references are taken out. Only well-formed tags of real
+// HTML elements are stripped, so "x < y" or "<3" in an example survive.
+
+use crate::lookup::DefinitionSection;
+use crate::sources::SourceResult;
+
+const ENTITIES: &[(&str, &str)] = &[
+    ("amp", "&"),
+    ("lt", "<"),
+    ("gt", ">"),
+    ("quot", "\""),
+    ("apos", "'"),
+    ("nbsp", " "),
+    ("ensp", " "),
+    ("emsp", " "),
+    ("thinsp", " "),
+    ("shy", ""),
+    ("ndash", "–"),
+    ("mdash", "—"),
+    ("minus", "−"),
+    ("hellip", "…"),
+    ("lsquo", "‘"),
+    ("rsquo", "’"),
+    ("ldquo", "“"),
+    ("rdquo", "”"),
+    ("laquo", "«"),
+    ("raquo", "»"),
+    ("middot", "·"),
+    ("bull", "•"),
+    ("deg", "°"),
+    ("times", "×"),
+    ("divide", "÷"),
+    ("frac12", "½"),
+    ("eacute", "é"),
+];
+
+// Elements whose tags are stripped; anything else in angle brackets is text
+const TAGS: &[&str] = &[
+    "a", "abbr", "b", "bdi", "bdo", "big", "blockquote", "br", "center", "cite", "code", "dd", "del", "dfn", "div", "dl",
+    "dt", "em", "font", "h1", "h2", "h3", "h4", "h5", "h6", "hr", "i", "img", "ins", "kbd", "li", "link", "mark", "meta",
+    "ol", "p", "pre", "q", "rp", "rt", "ruby", "s", "samp", "section", "small", "span", "strike", "strong", "sub", "sup",
+    "table", "tbody", "td", "th", "time", "tr", "tt", "u", "ul", "var", "wbr",
+];
+
+// Elements that separate words, so their tags become a space
+const BLOCK_TAGS: &[&str] = &[
+    "blockquote", "br", "dd", "div", "dl", "dt", "h1", "h2", "h3", "h4", "h5", "h6", "hr", "li", "ol", "p", "pre",
+    "section", "table", "td", "th", "tr", "ul",
+];
+
+// Cross-references after this many words are taken for part of the text
+const MAX_CROSS_REFERENCE_WORDS: usize = 8;
+
+// A definition or example as it should be shown
+pub fn clean_definition_text(text: &str) -> String {
+    strip_list_marker(&clean_paragraph(text)).to_string()
+}
+
+// `clean_definition_text` without the list markers, which a Wikipedia
+// paragraph can legitimately start with ("1. FC Köln is a football club")
+pub fn clean_paragraph(text: &str) -> String {
+    let text = strip_tags(&strip_wiki_markup(text));
+    let text = decode_entities(&text);
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let text = strip_bracketed_references(&text);
+    strip_trailing_reference(&text).to_string()
+}
+
+// Clean every definition, example and Wikipedia paragraph in `result`,
+// dropping those left with nothing
+pub(crate) fn clean_result(result: &mut SourceResult) {
+    match result {
+        SourceResult::Definitions(sections) => sections.iter_mut().for_each(clean_section),
+        SourceResult::Wikipedia(wiki) => {
+            // The summary keeps its line breaks, which separate paragraphs
+            wiki.summary = clean_lines(wiki.summary.lines()).join("\n");
+            wiki.paragraphs = clean_lines(wiki.paragraphs.iter().map(String::as_str));
+        }
+        SourceResult::Combined(results) => results.iter_mut().for_each(clean_result),
+        SourceResult::Thesaurus(_)
+        | SourceResult::Etymology(_)
+        | SourceResult::Examples(_)
+        | SourceResult::Acronym(_)
+        | SourceResult::Rhymes(_)
+        | SourceResult::Suggestions(_) => {}
+    }
+}
+
+fn clean_section(section: &mut DefinitionSection) {
+    for definition in &mut section.definitions {
+        definition.definition = clean_definition_text(&definition.definition);
+        definition.example = definition.example.as_deref().map(clean_definition_text).filter(|e| !e.is_empty());
+    }
+    section.definitions.retain(|definition| !definition.definition.is_empty());
+}
+
+fn clean_lines<'a>(lines: impl Iterator<Item = &'a str>) -> Vec<String> {
+    lines.map(clean_paragraph).filter(|line| !line.is_empty()).collect()
+}
+
+// `[[target|label]]` becomes its label, `[[target]]` its target; `{{...}}`
+// templates and `''` / `'''` emphasis go
+fn strip_wiki_markup(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    loop {
+        let link = rest.find("[[");
+        let template = rest.find("{{");
+        let (at, open, close) = match (link, template) {
+            (Some(l), Some(t)) if t < l => (t, "{{", "}}"),
+            (Some(l), _) => (l, "[[", "]]"),
+            (None, Some(t)) => (t, "{{", "}}"),
+            (None, None) => break,
+        };
+        let Some(length) = rest[at + 2..].find(close) else {
+            break;
+        };
+        out.push_str(&rest[..at]);
+        let inner = &rest[at + 2..at + 2 + length];
+        if open == "[[" {
+            out.push_str(inner.rsplit('|').next().unwrap_or(inner));
+        }
+        rest = &rest[at + 2 + length + 2..];
+    }
+    out.push_str(rest);
+    out.replace("'''", "").replace("''", "")
+}
+
+fn strip_tags(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find('<') {
+        out.push_str(&rest[..at]);
+        rest = &rest[at..];
+        match tag_at(rest) {
+            Some((length, breaks)) => {
+                if breaks {
+                    out.push(' ');
+                }
+                rest = &rest[length..];
+            }
+            None => {
+                out.push('<');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+// How long the tag or comment `text` starts with is, and whether it
+// separates words; None unless it's well-formed
+fn tag_at(text: &str) -> Option<(usize, bool)> {
+    if let Some(comment) = text.strip_prefix("<!--") {
+        return comment.find("-->").map(|end| (4 + end + 3, false));
+    }
+    let rest = text.strip_prefix('<')?;
+    let rest = rest.strip_prefix('/').unwrap_or(rest);
+    let name_length = rest.find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(rest.len());
+    let name = rest[..name_length].to_ascii_lowercase();
+    if !TAGS.contains(&name.as_str()) {
+        return None;
+    }
+
+    let after_name = &rest[name_length..];
+    let mut quote = None;
+    let mut end = None;
+    for (i, c) in after_name.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => {
+                end = Some(i);
+                break;
+            }
+            (None, '<') => return None,
+            _ => {}
+        }
+    }
+    let end = end?;
+    let inside = &after_name[..end];
+    if !(inside.is_empty() || inside == "/" || inside.starts_with(char::is_whitespace)) || !attributes_ok(inside) {
+        return None;
+    }
+    let length = text.len() - after_name.len() + end + 1;
+    Some((length, BLOCK_TAGS.contains(&name.as_str())))
+}
+
+// Whether what follows a tag's name is a list of `name`, `name=value` or
+// `name="value"` attributes
+fn attributes_ok(inside: &str) -> bool {
+    let inside = inside.trim_end();
+    let mut rest = inside.strip_suffix('/').unwrap_or(inside).trim_start();
+    while !rest.is_empty() {
+        let name_length = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ':' | '.')))
+            .unwrap_or(rest.len());
+        if name_length == 0 {
+            return false;
+        }
+        rest = rest[name_length..].trim_start();
+        if let Some(value) = rest.strip_prefix('=') {
+            let value = value.trim_start();
+            let value_length = match value.chars().next() {
+                Some(quote @ ('"' | '\'')) => match value[1..].find(quote) {
+                    Some(end) => end + 2,
+                    None => return false,
+                },
+                Some(_) => value.find(char::is_whitespace).unwrap_or(value.len()),
+                None => return false,
+            };
+            rest = &value[value_length..];
+            if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+                return false;
+            }
+            rest = rest.trim_start();
+        }
+    }
+    true
+}
+
+// Named entities from `ENTITIES` and numeric ones; anything else after an
+// `&` is left as it is, as in "AT&T"
+pub(crate) fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find('&') {
+        out.push_str(&rest[..at]);
+        rest = &rest[at..];
+        match entity_at(rest) {
+            Some((decoded, length)) => {
+                out.push_str(&decoded);
+                rest = &rest[length..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn entity_at(text: &str) -> Option<(String, usize)> {
+    let end = text.char_indices().take(12).find(|&(_, c)| c == ';').map(|(i, _)| i)?;
+    let name = &text[1..end];
+    let decoded = match name.strip_prefix('#') {
+        Some(number) => {
+            let code = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            };
+            char::from_u32(code)?.to_string()
+        }
+        None => ENTITIES.iter().find(|(entity, _)| *entity == name)?.1.to_string(),
+    };
+    Some((decoded, end + 1))
+}
+
+// Whether a bracketed aside is a reference rather than part of the text:
+// "(see run)", "[cf. sprint]", a citation mark like "[12]"
+fn is_reference(inner: &str) -> bool {
+    let inner = inner.trim().to_lowercase();
+    inner.starts_with("see ")
+        || inner.starts_with("see:")
+        || inner.starts_with("cf.")
+        || inner.starts_with('→')
+        || inner == "citation needed"
+        || (!inner.is_empty() && inner.chars().all(|c| c.is_ascii_digit()))
+}
+
+fn strip_bracketed_references(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find(['(', '[']) {
+        let close = if rest[at..].starts_with('(') { ')' } else { ']' };
+        let Some(length) = rest[at + 1..].find(close) else {
+            break;
+        };
+        let inner = &rest[at + 1..at + 1 + length];
+        if is_reference(inner) {
+            out.push_str(rest[..at].trim_end());
+        } else {
+            out.push_str(&rest[..at + 1 + length + 1]);
+        }
+        rest = &rest[at + 1 + length + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+// "A large cat. See also: tiger." ends at "cat."; "A feline; see cat" at
+// "feline". A definition that is nothing but a reference comes back empty.
+fn strip_trailing_reference(text: &str) -> &str {
+    let lower = text.to_lowercase();
+    // Lowercasing can change byte lengths; give up on the rare text where it does
+    if lower.len() != text.len() {
+        return text;
+    }
+    let starts = lower
+        .match_indices("see")
+        .map(|(at, _)| at)
+        .filter(|&at| at == 0 || lower[..at].ends_with(". ") || lower[..at].trim_end().ends_with([';', ':', '—', '–', ',']));
+    for at in starts {
+        let tail = &lower[at + 3..];
+        let is_reference = (tail.starts_with(' ') || tail.starts_with(':') || tail.starts_with(" also"))
+            && !tail.trim_end_matches(['.', ' ']).contains(['.', '!', '?'])
+            && tail.split_whitespace().count() <= MAX_CROSS_REFERENCE_WORDS;
+        if is_reference {
+            let kept = text[..at].trim_end();
+            // A full stop ends the text; any other separator goes with the reference
+            return match kept.strip_suffix([';', ':', '—', '–', ',']) {
+                Some(kept) => kept.trim_end(),
+                None => kept,
+            };
+        }
+    }
+    text
+}
+
+// "1. ", "(a) ", "- " and the like at the start
+fn strip_list_marker(text: &str) -> &str {
+    let mut rest = text;
+    loop {
+        let next = list_marker_length(rest).map_or(rest, |length| rest[length..].trim_start());
+        if next.len() == rest.len() {
+            return rest;
+        }
+        rest = next;
+    }
+}
+
+fn list_marker_length(text: &str) -> Option<usize> {
+    let marker = text.split_whitespace().next()?;
+    if text.len() == marker.len() {
+        return None;
+    }
+    let is_marker = match marker {
+        "-" | "*" | "•" | "·" | "–" | "—" | "#" => true,
+        _ => {
+            let bare = marker.strip_prefix('(').map_or(marker, |m| m.strip_suffix(')').unwrap_or(""));
+            let numbered = marker.strip_suffix(['.', ')']).unwrap_or("");
+            let lettered = marker.strip_suffix(')').unwrap_or("");
+            let is_number = |s: &str| !s.is_empty() && s.len() <= 3 && s.chars().all(|c| c.is_ascii_digit());
+            let is_letter = |s: &str| s.len() == 1 && s.chars().all(|c| c.is_ascii_lowercase());
+            is_number(numbered) || is_number(bare) || is_letter(lettered) || is_letter(bare)
+        }
+    };
+    is_marker.then_some(marker.len())
+}
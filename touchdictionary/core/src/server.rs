@@ -0,0 +1,404 @@
+// `touchdictionary serve`: a long-lived process editors and scripts ask
+// over a Unix socket, so each lookup skips starting up and shares one
+// `LookupContext` (cache, connection pool, rate limits) with the others.
+//
+// On connecting the server sends a `Hello` line. Each line the client then
+// sends is a `Request` and gets one `Response` line back, in order:
+//
+//     <- {"protocol":2,"version":"0.1.0"}
+//     -> {"query":"ephemeral","lang":"en","format":"json","private":true}
+//     <- {"status":"result","result":{...LookupResult...}}
+//     -> {"query":"zzxq"}
+//     <- {"status":"error","error":{"kind":"not_found","message":"..."}}
+//
+// "text" and "markdown" formats answer with `{"status":"text","output":..}`
+// instead. A request's flags can turn the server's on but not off, so a
+// `--private` client is never recorded by a server that isn't. Connections are served concurrently; lookups are limited the
+// same way as anywhere else.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::UnixStream;
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::context::LookupContext;
+use crate::error::LookupError;
+use crate::format::{self, OutputFormat, TextOptions};
+use crate::lookup::{self, LookupOptions, LookupResult};
+
+// Raised whenever a change would confuse a client written for the old
+// protocol; clients that don't match look words up themselves instead
+pub const PROTOCOL_VERSION: u32 = 2;
+
+// Per-user socket `serve` and `client` use when not given one
+pub fn default_socket_path() -> Option<PathBuf> {
+    dirs::runtime_dir()
+        .or_else(dirs::cache_dir)
+        .map(|dir| dir.join("touchdictionary-server.sock"))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hello {
+    pub protocol: u32,
+    // The server's crate version, for the client's logs
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Request {
+    pub query: String,
+    // The server's language when unset
+    #[serde(default)]
+    pub lang: Option<String>,
+    // json (the default), ndjson, text or markdown
+    #[serde(default)]
+    pub format: Option<String>,
+    // The rest are the client's lookup flags
+    #[serde(default)]
+    pub private: bool,
+    #[serde(default)]
+    pub offline: bool,
+    #[serde(default)]
+    pub no_cache: bool,
+    #[serde(default)]
+    pub auto_correct: bool,
+    #[serde(default)]
+    pub all_definitions: bool,
+    #[serde(default)]
+    pub compact: bool,
+    #[serde(default)]
+    pub translate_to: Option<String>,
+    #[serde(default)]
+    pub pos_filter: Option<Vec<String>>,
+}
+
+impl Request {
+    // A request for `query` carrying what `options` asks for
+    pub fn new(query: &str, options: &LookupOptions) -> Self {
+        Request {
+            query: query.to_string(),
+            lang: options.lang.clone(),
+            format: None,
+            private: options.private,
+            offline: options.offline,
+            no_cache: !options.use_cache,
+            auto_correct: options.auto_correct,
+            all_definitions: options.all_definitions,
+            compact: options.compact,
+            translate_to: options.translate_to.clone(),
+            pos_filter: options.pos_filter.clone(),
+        }
+    }
+
+    // The server's `options` with this request's applied over them
+    fn options(&self, options: &LookupOptions) -> LookupOptions {
+        LookupOptions {
+            lang: self.lang.clone().or_else(|| options.lang.clone()),
+            private: options.private || self.private,
+            offline: options.offline || self.offline,
+            use_cache: options.use_cache && !self.no_cache,
+            auto_correct: options.auto_correct || self.auto_correct,
+            all_definitions: options.all_definitions || self.all_definitions,
+            compact: options.compact || self.compact,
+            translate_to: self.translate_to.clone().or_else(|| options.translate_to.clone()),
+            pos_filter: self.pos_filter.clone().or_else(|| options.pos_filter.clone()),
+            ..options.clone()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Response {
+    Result { result: Box<LookupResult> },
+    Text { output: String },
+    Error { error: ErrorReply },
+}
+
+// A `LookupError` as it's serialized, or a request the server couldn't
+// read ("bad_request")
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorReply {
+    pub kind: String,
+    pub message: String,
+    // Whatever else the error carries, like the source that failed
+    #[serde(flatten)]
+    pub details: Map<String, Value>,
+}
+
+impl ErrorReply {
+    fn bad_request(message: String) -> Self {
+        ErrorReply { kind: "bad_request".to_string(), message, details: Map::new() }
+    }
+}
+
+impl From<&LookupError> for ErrorReply {
+    fn from(error: &LookupError) -> Self {
+        match serde_json::to_value(error).ok().and_then(|value| serde_json::from_value(value).ok()) {
+            Some(reply) => reply,
+            None => ErrorReply { kind: error.kind().to_string(), message: error.to_string(), details: Map::new() },
+        }
+    }
+}
+
+// Claim `path`, refusing when another server answers on it. A socket left
+// behind by one that crashed is replaced.
+pub fn bind(path: &Path) -> io::Result<std::os::unix::net::UnixListener> {
+    claim(path).map_err(|e| match e.kind() {
+        io::ErrorKind::AddrInUse => {
+            io::Error::new(e.kind(), format!("another server is already listening on {}", path.display()))
+        }
+        _ => e,
+    })
+}
+
+fn claim(path: &Path) -> io::Result<std::os::unix::net::UnixListener> {
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    match UnixListener::bind(path) {
+        Ok(listener) => Ok(listener),
+        Err(e) if e.kind() == io::ErrorKind::AddrInUse => {
+            if UnixStream::connect(path).is_ok() {
+                return Err(e);
+            }
+            warn!("Removing stale socket {}", path.display());
+            std::fs::remove_file(path)?;
+            UnixListener::bind(path)
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            UnixListener::bind(path)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+// Answer connections until `shutdown` is cancelled, then let each finish
+// the request it's on and remove the socket. `options` are the server's
+// own, which each request's are applied over.
+pub async fn serve(
+    ctx: Arc<LookupContext>,
+    listener: std::os::unix::net::UnixListener,
+    path: &Path,
+    options: LookupOptions,
+    shutdown: CancellationToken,
+) -> io::Result<()> {
+    listener.set_nonblocking(true)?;
+    let listener = tokio::net::UnixListener::from_std(listener)?;
+    let options = Arc::new(options);
+    let mut connections = JoinSet::new();
+    info!("Serving lookups on {}", path.display());
+
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => break,
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    let (ctx, options, shutdown) = (ctx.clone(), options.clone(), shutdown.clone());
+                    connections.spawn(async move {
+                        match answer(&ctx, &options, stream, shutdown).await {
+                            // Clients hang up as soon as they have their answer
+                            Err(e) if matches!(e.kind(), io::ErrorKind::ConnectionReset | io::ErrorKind::BrokenPipe) => {}
+                            Err(e) => warn!("Connection closed: {}", e),
+                            Ok(()) => {}
+                        }
+                    });
+                }
+                Err(e) => warn!("Failed to accept connection: {}", e),
+            },
+            // Reap finished connections as they go rather than at shutdown
+            Some(_) = connections.join_next(), if !connections.is_empty() => {}
+        }
+    }
+
+    info!("Shutting down, waiting for {} connections", connections.len());
+    while connections.join_next().await.is_some() {}
+    let _ = std::fs::remove_file(path);
+    Ok(())
+}
+
+async fn answer(ctx: &LookupContext, options: &LookupOptions, stream: UnixStream, shutdown: CancellationToken) -> io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let hello = Hello { protocol: PROTOCOL_VERSION, version: env!("CARGO_PKG_VERSION").to_string() };
+    write_line(&mut writer, &hello).await?;
+
+    let mut lines = BufReader::new(reader).lines();
+    loop {
+        let line = tokio::select! {
+            _ = shutdown.cancelled() => return Ok(()),
+            line = lines.next_line() => line?,
+        };
+        let Some(line) = line else {
+            return Ok(());
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => respond(ctx, options, request).await,
+            Err(e) => Response::Error { error: ErrorReply::bad_request(format!("not a request: {}", e)) },
+        };
+        write_line(&mut writer, &response).await?;
+    }
+}
+
+async fn respond(ctx: &LookupContext, options: &LookupOptions, request: Request) -> Response {
+    let output = match request.format.as_deref().map(str::parse::<OutputFormat>).transpose() {
+        Ok(output) => output.unwrap_or(OutputFormat::Json),
+        Err(e) => return Response::Error { error: ErrorReply::bad_request(e) },
+    };
+    let options = request.options(options);
+    let result = match lookup::lookup_with(ctx, &request.query, &options).await {
+        Ok(result) => result,
+        Err(e) => return Response::Error { error: ErrorReply::from(&e) },
+    };
+
+    // Text is for showing as-is, so an empty result is a miss; structured
+    // output keeps it, for its completeness
+    let text = TextOptions::default();
+    match output {
+        OutputFormat::Json | OutputFormat::Ndjson => Response::Result { result: Box::new(result) },
+        _ if result.found_nothing() => {
+            Response::Error { error: ErrorReply::from(&LookupError::NotFound { query: request.query }) }
+        }
+        OutputFormat::Text => Response::Text { output: format::render_text(&result, &text) },
+        OutputFormat::Markdown => Response::Text { output: format::render_markdown(&result) },
+    }
+}
+
+async fn write_line<T: Serialize>(writer: &mut (impl AsyncWrite + Unpin), value: &T) -> io::Result<()> {
+    let mut line = serde_json::to_vec(value).map_err(io::Error::other)?;
+    line.push(b'\n');
+    writer.write_all(&line).await
+}
+
+// Send `request` to the server on `path` and wait for its answer. None when
+// no server is listening or it speaks another protocol version, for the
+// caller to look the word up itself.
+pub async fn request(path: &Path, request: &Request) -> io::Result<Option<Response>> {
+    let stream = match UnixStream::connect(path).await {
+        Ok(stream) => stream,
+        Err(e) if matches!(e.kind(), io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    let hello: Hello = read_line(&mut lines).await?;
+    if hello.protocol != PROTOCOL_VERSION {
+        warn!(
+            "Server {} speaks protocol {}, this client {}; looking up without it",
+            hello.version, hello.protocol, PROTOCOL_VERSION
+        );
+        return Ok(None);
+    }
+    write_line(&mut writer, request).await?;
+    read_line(&mut lines).await.map(Some)
+}
+
+async fn read_line<T: serde::de::DeserializeOwned>(
+    lines: &mut tokio::io::Lines<BufReader<tokio::net::unix::OwnedReadHalf>>,
+) -> io::Result<T> {
+    let line = lines.next_line().await?.ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+    serde_json::from_str(&line).map_err(io::Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn temp_socket(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("touchdictionary-server-{}-{}.sock", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    // Every source off: lookups answer from nothing, without the network
+    fn offline_context() -> Arc<LookupContext> {
+        let mut config = Config::default();
+        for enabled in config.sources.values_mut() {
+            *enabled = false;
+        }
+        Arc::new(LookupContext::with_config(config))
+    }
+
+    #[test]
+    fn test_bind_replaces_stale_socket() {
+        let path = temp_socket("stale");
+        // A server that goes away leaves its socket file behind
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+
+        let _listener = bind(&path).unwrap();
+        assert_eq!(bind(&path).unwrap_err().kind(), io::ErrorKind::AddrInUse);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_request_options_only_add_to_the_servers() {
+        let client = LookupOptions {
+            private: true,
+            use_cache: false,
+            compact: true,
+            lang: Some("de".to_string()),
+            ..LookupOptions::default()
+        };
+        let server = LookupOptions { offline: true, ..LookupOptions::default() };
+        let options = Request::new("word", &client).options(&server);
+        assert!(options.private && options.offline && options.compact && !options.use_cache);
+        assert_eq!(options.lang.as_deref(), Some("de"));
+
+        // An old client's request, without the flags, keeps the server's
+        let request: Request = serde_json::from_str(r#"{"query":"word"}"#).unwrap();
+        let server = LookupOptions { private: true, ..LookupOptions::default() };
+        let options = request.options(&server);
+        assert!(options.private && options.use_cache && !options.compact);
+    }
+
+    #[tokio::test]
+    async fn test_requests_and_shutdown() {
+        let path = temp_socket("requests");
+        let listener = bind(&path).unwrap();
+        assert!(bind(&path).unwrap_err().to_string().contains("already listening"));
+
+        let shutdown = CancellationToken::new();
+        let options = LookupOptions { use_cache: false, private: true, ..LookupOptions::default() };
+        let server = tokio::spawn({
+            let (path, shutdown) = (path.clone(), shutdown.clone());
+            async move { serve(offline_context(), listener, &path, options, shutdown).await }
+        });
+
+        let computed = Request { query: "10 m".to_string(), format: Some("json".to_string()), ..Request::default() };
+        let Some(Response::Result { result }) = request(&path, &computed).await.unwrap() else {
+            panic!("expected a result");
+        };
+        assert_eq!(result.query, "10 m");
+        assert!(result.sections.computed.is_some());
+
+        let bad = Request { query: "word".to_string(), format: Some("yaml".to_string()), ..Request::default() };
+        let Some(Response::Error { error }) = request(&path, &bad).await.unwrap() else {
+            panic!("expected an error");
+        };
+        assert_eq!(error.kind, "bad_request");
+        let unsupported = Request { query: "word".to_string(), lang: Some("xx".to_string()), ..Request::default() };
+        let Some(Response::Error { error }) = request(&path, &unsupported).await.unwrap() else {
+            panic!("expected an error");
+        };
+        assert_eq!((error.kind.as_str(), error.details.get("lang")), ("unsupported_language", Some(&Value::from("xx"))));
+
+        shutdown.cancel();
+        server.await.unwrap().unwrap();
+        assert!(!path.exists());
+        assert!(request(&path, &computed).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::VecDeque;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::lookup::LookupResult;
+
+// Results kept for reopening when the config doesn't say
+pub const DEFAULT_MAX_RESULTS: usize = 20;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredResult {
+    query: String,
+    result: LookupResult,
+}
+
+// The last few results the window showed, keyed by the query they were
+// asked for, least recently used first. Mirrored to
+// `$XDG_DATA_HOME/touchdictionary/session.json` so they survive a restart.
+#[derive(Debug)]
+pub struct ResultStore {
+    path: Option<PathBuf>,
+    capacity: usize,
+    entries: VecDeque<StoredResult>,
+}
+
+impl ResultStore {
+    // Start from whatever `path` holds; a missing or unreadable file gives
+    // an empty store
+    pub fn load(path: Option<PathBuf>, capacity: usize) -> Self {
+        let mut entries: VecDeque<StoredResult> = match &path {
+            Some(path) => match std::fs::read_to_string(path) {
+                Ok(data) => serde_json::from_str(&data).unwrap_or_else(|e| {
+                    warn!("Ignoring unreadable {}: {}", path.display(), e);
+                    VecDeque::new()
+                }),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => VecDeque::new(),
+                Err(e) => {
+                    warn!("Failed to read {}: {}", path.display(), e);
+                    VecDeque::new()
+                }
+            },
+            None => VecDeque::new(),
+        };
+        while entries.len() > capacity {
+            entries.pop_front();
+        }
+        ResultStore { path, capacity, entries }
+    }
+
+    pub fn open_default(capacity: usize) -> Self {
+        ResultStore::load(default_path(), capacity)
+    }
+
+    // Keep `result` as the newest one for `query`, dropping the least
+    // recently used when full
+    pub fn remember(&mut self, query: &str, result: &LookupResult) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.entries.retain(|entry| entry.query != query);
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        let mut result = result.clone();
+        result.from_session_cache = false;
+        self.entries.push_back(StoredResult { query: query.to_string(), result });
+        self.save();
+    }
+
+    // The result for `query`, as it was shown
+    pub fn find(&self, query: &str) -> Option<&LookupResult> {
+        self.entries.iter().find(|entry| entry.query == query).map(|entry| &entry.result)
+    }
+
+    // The newest result, marked as restored
+    pub fn last(&self) -> Option<(String, LookupResult)> {
+        self.recent(1).pop()
+    }
+
+    // Up to `limit` results with the queries they were asked for, newest
+    // first and marked as restored
+    pub fn recent(&self, limit: usize) -> Vec<(String, LookupResult)> {
+        self.entries
+            .iter()
+            .rev()
+            .take(limit)
+            .map(|entry| {
+                let mut result = entry.result.clone();
+                result.from_session_cache = true;
+                (entry.query.clone(), result)
+            })
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Best-effort: a store that can't be written still works for this run
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if let Err(e) = self.write(path) {
+            warn!("Failed to save recent results to {}: {}", path.display(), e);
+        }
+    }
+
+    // Write to a temporary file first so a crash never leaves half a store
+    fn write(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let data = serde_json::to_vec(&self.entries).map_err(io::Error::other)?;
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, data)?;
+        std::fs::rename(&tmp, path)
+    }
+}
+
+pub fn default_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("touchdictionary").join("session.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(query: &str) -> LookupResult {
+        let mut result: LookupResult =
+            serde_json::from_str(include_str!("../tests/fixtures/lookup_result_serendipity.json")).unwrap();
+        result.query = query.to_string();
+        result
+    }
+
+    #[test]
+    fn test_result_store_evicts_least_recent_and_persists() {
+        let dir = std::env::temp_dir().join(format!("touchdictionary-session-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join("session.json");
+
+        let mut store = ResultStore::load(Some(path.clone()), 2);
+        store.remember("one", &result("one"));
+        store.remember("two", &result("two"));
+        // Looking "one" up again makes "two" the least recently used
+        store.remember("one", &result("one"));
+        store.remember("three", &result("three"));
+
+        let queries: Vec<String> = store.recent(10).into_iter().map(|(query, _)| query).collect();
+        assert_eq!(queries, vec!["three", "one"]);
+        assert!(store.find("two").is_none());
+        assert!(!store.find("one").unwrap().from_session_cache);
+
+        let reopened = ResultStore::load(Some(path), 2);
+        let (query, last) = reopened.last().unwrap();
+        assert_eq!(query, "three");
+        assert_eq!(last.query, "three");
+        assert!(last.from_session_cache);
+        assert_eq!(reopened.len(), 2);
+
+        // A smaller limit keeps the newest
+        let smaller = ResultStore::load(Some(dir.join("session.json")), 1);
+        assert_eq!(smaller.recent(10)[0].0, "three");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_result_store_ignores_unreadable_file() {
+        let dir = std::env::temp_dir().join(format!("touchdictionary-session-bad-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("session.json");
+        std::fs::write(&path, "not json").unwrap();
+        assert!(ResultStore::load(Some(path), DEFAULT_MAX_RESULTS).is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,326 @@
+use async_trait::async_trait;
+use tracing::{error, info, warn};
+
+use crate::http;
+use crate::lookup::{AcronymExpansion, AcronymSection, ContentType, DefinitionSection};
+use crate::ratelimit;
+use crate::sources::{wiktionary, Source, SourceError, SourceResult};
+
+// Titles asked for per search; most are things named after the acronym
+// rather than what it stands for, so ask for plenty
+const SEARCH_LIMIT: usize = 20;
+
+// Shortest and longest acronyms recognized, in letters
+const MIN_LETTERS: usize = 2;
+const MAX_LETTERS: usize = 6;
+
+// How Wiktionary starts the sense of an abbreviation: "Initialism of
+// National Aeronautics and Space Administration"
+const EXPANSION_PREFIXES: &[&str] = &["initialism of ", "acronym of ", "abbreviation of ", "contraction of "];
+
+// Words left out of an acronym's letters: "NASA" is National Aeronautics
+// and Space Administration
+const MINOR_WORDS: &[&str] = &["a", "an", "and", "for", "in", "of", "on", "the", "to"];
+
+// Whether `query` looks like an acronym or abbreviation: 2 to 6 letters,
+// either mostly capitals ("NASA", "LiDAR", "PhD") or single letters each
+// followed by a period ("e.g.", "U.S.")
+pub fn is_acronym(query: &str) -> bool {
+    if query.contains(char::is_whitespace) {
+        return false;
+    }
+    let letters = query.chars().filter(|c| c.is_alphabetic()).count();
+    if !(MIN_LETTERS..=MAX_LETTERS).contains(&letters) {
+        return false;
+    }
+    if query.contains('.') {
+        return is_dotted(query);
+    }
+    if !query.chars().all(char::is_alphabetic) {
+        return false;
+    }
+    let capitals = query.chars().filter(|c| c.is_uppercase()).count();
+    capitals >= 2 && capitals >= letters - capitals
+}
+
+fn is_dotted(query: &str) -> bool {
+    let query = query.strip_suffix('.').unwrap_or(query);
+    query.split('.').all(|part| part.chars().count() == 1 && part.chars().all(char::is_alphabetic))
+}
+
+// The letters an acronym stands for, lowercased: "e.g." gives "eg"
+fn letters(acronym: &str) -> String {
+    acronym.chars().filter(|c| c.is_alphabetic()).flat_map(char::to_lowercase).collect()
+}
+
+// What an acronym stands for, from Wiktionary's abbreviation entries and
+// Wikipedia's page titles
+pub struct AcronymSource;
+
+#[async_trait]
+impl Source for AcronymSource {
+    fn name(&self) -> &str {
+        "acronym"
+    }
+
+    fn applies_to(&self, content_type: &ContentType) -> bool {
+        matches!(content_type, ContentType::Acronym)
+    }
+
+    fn accepts_lemma(&self) -> bool {
+        false
+    }
+
+    fn probe_query(&self) -> &str {
+        "NASA"
+    }
+
+    async fn fetch(&self, query: &str, lang: &str) -> Result<SourceResult, SourceError> {
+        get_expansions(query, lang).await.map(SourceResult::Acronym)
+    }
+}
+
+async fn get_expansions(acronym: &str, lang: &str) -> Result<AcronymSection, SourceError> {
+    info!("Looking up expansions of '{}'", acronym);
+
+    let (wiktionary, wikipedia) = tokio::join!(wiktionary_expansions(acronym), wikipedia_expansions(acronym, lang));
+
+    // One side failing still leaves the other's expansions worth showing
+    let mut failure = None;
+    let mut candidates = Vec::new();
+    for found in [wiktionary, wikipedia] {
+        match found {
+            Ok(expansions) => candidates.extend(expansions),
+            Err(e) if e.is_not_found() => {}
+            Err(e) => {
+                warn!("Failed to look up expansions of '{}': {}", acronym, e);
+                failure.get_or_insert(e);
+            }
+        }
+    }
+
+    let expansions = merge_expansions(candidates);
+    if expansions.is_empty() {
+        return Err(failure.unwrap_or_else(|| SourceError::NotFound { query: acronym.to_string() }));
+    }
+    info!("Found {} expansions of '{}'", expansions.len(), acronym);
+    Ok(AcronymSection { expansions })
+}
+
+// Wiktionary titles are case-sensitive and tend to file mixed-case
+// acronyms under their capitals ("LIDAR" for "LiDAR")
+async fn wiktionary_expansions(acronym: &str) -> Result<Vec<AcronymExpansion>, SourceError> {
+    let mut titles = vec![acronym.to_string()];
+    let capitals = acronym.to_uppercase();
+    if capitals != acronym {
+        titles.push(capitals);
+    }
+
+    for title in titles {
+        let sections = wiktionary::get_definitions(&title).await?;
+        let expansions = expansions_from_definitions(&title, &sections);
+        if !expansions.is_empty() {
+            return Ok(expansions);
+        }
+    }
+    Ok(Vec::new())
+}
+
+// The "Initialism of ..." senses of a Wiktionary entry. Anything after a
+// colon or semicolon is the sense's explanation.
+pub(crate) fn expansions_from_definitions(title: &str, sections: &[DefinitionSection]) -> Vec<AcronymExpansion> {
+    let url = http::wiki_title_url("https://en.wiktionary.org/wiki", title);
+    let mut expansions = Vec::new();
+    for definition in sections.iter().flat_map(|s| &s.definitions) {
+        let text = definition.definition.trim();
+        let lower = text.to_lowercase();
+        let Some(prefix) = EXPANSION_PREFIXES.iter().find(|prefix| lower.starts_with(*prefix)) else {
+            continue;
+        };
+        let rest = &text[prefix.len()..];
+        let (expansion, description) = match rest.split_once([':', ';']) {
+            Some((expansion, description)) => (expansion, Some(description)),
+            None => (rest, None),
+        };
+        let expansion = expansion.trim().trim_end_matches('.').trim();
+        if expansion.is_empty() {
+            continue;
+        }
+        expansions.push(AcronymExpansion {
+            expansion: expansion.to_string(),
+            description: description
+                .map(|d| d.trim().trim_end_matches('.').trim().to_string())
+                .filter(|d| !d.is_empty()),
+            url: Some(url.clone()),
+        });
+    }
+    expansions
+}
+
+async fn wikipedia_expansions(acronym: &str, lang: &str) -> Result<Vec<AcronymExpansion>, SourceError> {
+    let url = format!("https://{}.wikipedia.org/w/api.php", lang);
+    let request = || async {
+        let _permit = ratelimit::acquire(ratelimit::WIKIPEDIA).await;
+        let response = http::client()
+            .get(&url)
+            .query(&[
+                ("action", "opensearch"),
+                ("search", acronym),
+                ("limit", &SEARCH_LIMIT.to_string()),
+                ("namespace", "0"),
+                ("redirects", "resolve"),
+                ("format", "json"),
+            ])
+            .send()
+            .await
+            .map_err(|e| http::request_error("acronym", e))?;
+        http::check_transient(response, "Wikipedia API")
+    };
+
+    let response = http::retry("acronym", &http::RetryPolicy::default(), request).await?;
+    if !response.status().is_success() {
+        error!("Wikipedia API returned status: {}", response.status());
+        return Err(SourceError::UpstreamStatus {
+            source: "Wikipedia API".to_string(),
+            status: response.status().as_u16(),
+        });
+    }
+
+    let body = response.text().await.map_err(|e| http::request_error("acronym", e))?;
+    parse_opensearch(&body, acronym)
+}
+
+// Search results that may be what `acronym` stands for: pages titled with
+// the acronym itself or marked "(abbreviation)", and, since redirects are
+// resolved, pages whose words start with its letters ("RAII" redirects to
+// "Resource acquisition is initialization")
+pub(crate) fn parse_opensearch(body: &str, acronym: &str) -> Result<Vec<AcronymExpansion>, SourceError> {
+    // [query, [titles], [descriptions], [urls]]
+    let (_, titles, descriptions, urls): (String, Vec<String>, Vec<String>, Vec<String>) =
+        serde_json::from_str(body).map_err(|e| {
+            error!("Failed to parse Wikipedia search response: {}", e);
+            SourceError::ParseError {
+                source: "acronym".to_string(),
+                body_snippet: http::body_snippet(body),
+            }
+        })?;
+
+    let wanted = letters(acronym);
+    let mut expansions = Vec::new();
+    for (i, title) in titles.iter().enumerate() {
+        if title.ends_with("(disambiguation)") {
+            continue;
+        }
+        let abbreviation = title.contains("(abbreviation)");
+        let named = starts_with_acronym(title, &wanted);
+        if !abbreviation && !named && !initials_match(title, &wanted) {
+            continue;
+        }
+        expansions.push(AcronymExpansion {
+            expansion: title.replace("(abbreviation)", "").trim().to_string(),
+            description: descriptions.get(i).filter(|d| !d.is_empty()).cloned(),
+            url: urls.get(i).cloned(),
+        });
+    }
+    Ok(expansions)
+}
+
+// "NASA Ames Research Center" and "Lidar" for "NASA" and "LiDAR", but not
+// "NASAMS"
+fn starts_with_acronym(title: &str, wanted: &str) -> bool {
+    let first = title.split([' ', '(']).next().unwrap_or_default();
+    letters(first) == wanted && first.chars().all(|c| c.is_alphabetic() || c == '.')
+}
+
+// Whether the first letters of the title's words spell `wanted`, with or
+// without the minor words
+fn initials_match(title: &str, wanted: &str) -> bool {
+    let words: Vec<String> = title
+        .split(|c: char| c.is_whitespace() || c == '-')
+        .filter(|word| !word.is_empty() && !word.starts_with('('))
+        .map(str::to_lowercase)
+        .collect();
+    if words.len() < MIN_LETTERS {
+        return false;
+    }
+    let all: String = words.iter().filter_map(|word| word.chars().next()).collect();
+    let major: String = words
+        .iter()
+        .filter(|word| !MINOR_WORDS.contains(&word.as_str()))
+        .filter_map(|word| word.chars().next())
+        .collect();
+    all == wanted || major == wanted
+}
+
+// Wiktionary's first, since its senses spell the expansion out; the same
+// expansion from both keeps whichever description and link it has
+fn merge_expansions(candidates: Vec<AcronymExpansion>) -> Vec<AcronymExpansion> {
+    let mut merged: Vec<AcronymExpansion> = Vec::new();
+    for candidate in candidates {
+        let key = comparison_key(&candidate.expansion);
+        match merged.iter_mut().find(|kept| comparison_key(&kept.expansion) == key) {
+            Some(kept) => {
+                kept.description = kept.description.take().or(candidate.description);
+                kept.url = kept.url.take().or(candidate.url);
+            }
+            None => merged.push(candidate),
+        }
+    }
+    merged
+}
+
+fn comparison_key(expansion: &str) -> String {
+    expansion.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_acronym() {
+        for acronym in ["NASA", "RAII", "LiDAR", "PhD", "e.g.", "U.S.", "i.e", "OK"] {
+            assert!(is_acronym(acronym), "{}", acronym);
+        }
+        for other in ["nasa", "Paris", "McCoy", "I", "A.", "ABCDEFG", "NASA rocket", "B2B", "etc."] {
+            assert!(!is_acronym(other), "{}", other);
+        }
+    }
+
+    #[test]
+    fn test_parse_opensearch_raii() {
+        let body = include_str!("../../tests/fixtures/wikipedia_opensearch_raii.json");
+        let expansions = parse_opensearch(body, "RAII").unwrap();
+        let titles: Vec<&str> = expansions.iter().map(|e| e.expansion.as_str()).collect();
+        // The disambiguation page and the unrelated match are dropped
+        assert_eq!(titles, vec!["Resource acquisition is initialization", "RAII", "Raii Dam"]);
+        assert_eq!(
+            expansions[0].url.as_deref(),
+            Some("https://en.wikipedia.org/wiki/Resource_acquisition_is_initialization")
+        );
+        assert_eq!(expansions[0].description.as_deref(), Some("Programming idiom for managing resources"));
+        assert_eq!(expansions[1].description, None);
+        assert!(parse_opensearch("<html>", "RAII").is_err());
+    }
+
+    #[test]
+    fn test_expansions_from_wiktionary_and_merge() {
+        let section = wiktionary::parse_definitions("e.g.", include_str!("../../tests/fixtures/wiktionary_eg.json"))
+            .unwrap()
+            .unwrap();
+        let expansions = expansions_from_definitions("e.g.", &[section]);
+        assert_eq!(expansions.len(), 1);
+        assert_eq!(expansions[0].expansion, "exempli gratia");
+        assert_eq!(expansions[0].description.as_deref(), Some("for example"));
+        assert_eq!(expansions[0].url.as_deref(), Some("https://en.wiktionary.org/wiki/e.g."));
+
+        let from_wikipedia = AcronymExpansion {
+            expansion: "Exempli gratia".to_string(),
+            description: None,
+            url: Some("https://en.wikipedia.org/wiki/Exempli_gratia".to_string()),
+        };
+        let merged = merge_expansions(expansions.into_iter().chain([from_wikipedia]).collect());
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].url.as_deref(), Some("https://en.wiktionary.org/wiki/e.g."));
+    }
+}
@@ -0,0 +1,40 @@
+use serde::Deserialize;
+
+use crate::http;
+use crate::ratelimit;
+use crate::sources::SourceError;
+
+// Shared Datamuse `/words` client used by the thesaurus and spelling
+// suggestions. `source` names the caller in error messages.
+pub async fn fetch_words(source: &str, params: &[(&str, &str)]) -> Result<Vec<DatamuseWord>, SourceError> {
+    let _permit = ratelimit::acquire(ratelimit::DATAMUSE).await;
+    let response = http::client()
+        .get("https://api.datamuse.com/words")
+        .query(params)
+        .send()
+        .await
+        .map_err(|e| http::request_error(source, e))?;
+
+    if response.status() == 429 {
+        return Err(SourceError::RateLimited { retry_after: http::retry_after(&response) });
+    }
+    if !response.status().is_success() {
+        return Err(SourceError::UpstreamStatus {
+            source: "Datamuse API".to_string(),
+            status: response.status().as_u16(),
+        });
+    }
+
+    let body = response.text().await.map_err(|e| http::request_error(source, e))?;
+    serde_json::from_str::<Vec<DatamuseWord>>(&body).map_err(|_| SourceError::ParseError {
+        source: source.to_string(),
+        body_snippet: http::body_snippet(&body),
+    })
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DatamuseWord {
+    pub word: String,
+    #[serde(default)]
+    pub score: u64,
+}
@@ -0,0 +1,404 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use tracing::{debug, error, info, warn};
+
+use crate::http;
+use crate::ratelimit;
+use crate::lookup::{ContentType, Definition, DefinitionSection, Phonetic, ThesaurusSection};
+use crate::sources::{Source, SourceError, SourceResult};
+
+// Languages with an `/entries/{lang}/` endpoint on dictionaryapi.dev
+const SUPPORTED_LANGUAGES: &[&str] = &["ar", "de", "en", "es", "fr", "hi", "it", "ja", "ko", "ru", "tr"];
+
+// The official Free Dictionary API; mirrors serve the same `/{lang}/{word}`
+// paths under their own base URL
+pub const DEFAULT_ENDPOINT: &str = "https://api.dictionaryapi.dev/api/v2/entries";
+
+// How long an endpoint that was down is skipped before it's tried again
+const DEAD_ENDPOINT_COOLDOWN: Duration = Duration::from_secs(180);
+
+static DEAD_ENDPOINTS: http::CircuitBreaker = http::CircuitBreaker::new(DEAD_ENDPOINT_COOLDOWN);
+
+// Free Dictionary API (dictionaryapi.dev). Endpoints are tried in order,
+// moving on when one is unreachable or failing.
+pub struct DictionarySource {
+    pub endpoints: Vec<String>,
+}
+
+impl Default for DictionarySource {
+    fn default() -> Self {
+        DictionarySource { endpoints: vec![DEFAULT_ENDPOINT.to_string()] }
+    }
+}
+
+#[async_trait]
+impl Source for DictionarySource {
+    fn name(&self) -> &str {
+        "dictionary"
+    }
+
+    fn applies_to(&self, content_type: &ContentType) -> bool {
+        matches!(content_type, ContentType::Word | ContentType::Mixed)
+    }
+
+    fn supports_language(&self, lang: &str) -> bool {
+        SUPPORTED_LANGUAGES.contains(&lang)
+    }
+
+    // The API lists synonyms and antonyms alongside the senses, so they
+    // make a thesaurus section without another request
+    async fn fetch(&self, query: &str, lang: &str) -> Result<SourceResult, SourceError> {
+        let query = query.to_lowercase();
+        let sections = get_with_failover(&self.endpoints, &query, lang).await?;
+        let thesaurus = thesaurus_from(&sections, &query, lang);
+        Ok(SourceResult::Combined(vec![SourceResult::Definitions(sections), SourceResult::Thesaurus(thesaurus)]))
+    }
+}
+
+fn entries_url(endpoint: &str, lang: &str, word: &str) -> String {
+    format!("{}/{}/{}", endpoint.trim_end_matches('/'), lang, http::encode_path_segment(word))
+}
+
+// Sections are labelled with the endpoint that answered when it isn't the
+// official one
+fn source_label(endpoint: &str) -> String {
+    if endpoint == DEFAULT_ENDPOINT {
+        return "Free Dictionary API".to_string();
+    }
+    let host = reqwest::Url::parse(endpoint)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| endpoint.to_string());
+    format!("Free Dictionary API ({})", host)
+}
+
+// Endpoints still cooling down after a failure, in order, are skipped. If
+// that leaves nothing, all of them are tried anyway rather than giving up.
+fn endpoints_to_try(endpoints: &[String]) -> Vec<&str> {
+    let live: Vec<&str> = endpoints
+        .iter()
+        .map(String::as_str)
+        .filter(|endpoint| !DEAD_ENDPOINTS.is_open(endpoint))
+        .collect();
+    if live.is_empty() {
+        endpoints.iter().map(String::as_str).collect()
+    } else {
+        live
+    }
+}
+
+async fn get_with_failover(endpoints: &[String], query: &str, lang: &str) -> Result<Vec<DefinitionSection>, SourceError> {
+    let candidates = endpoints_to_try(endpoints);
+    let mut last_error = None;
+    for (i, endpoint) in candidates.iter().enumerate() {
+        // Retrying only pays off once there's nowhere else to go
+        let policy = if i + 1 == candidates.len() {
+            http::RetryPolicy::default()
+        } else {
+            http::RetryPolicy { max_attempts: 1, ..http::RetryPolicy::default() }
+        };
+
+        match get_dictionary_definitions(endpoint, &policy, query, lang).await {
+            Ok(sections) => {
+                DEAD_ENDPOINTS.reset(endpoint);
+                return Ok(sections);
+            }
+            Err(e) if e.is_unavailable() => {
+                warn!("Dictionary endpoint {} unavailable: {}", endpoint, e);
+                DEAD_ENDPOINTS.trip(endpoint);
+                last_error = Some(e);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    match last_error {
+        Some(e) => Err(e),
+        None => {
+            warn!("No dictionary endpoints configured");
+            Ok(Vec::new())
+        }
+    }
+}
+
+async fn get_dictionary_definitions(
+    endpoint: &str,
+    policy: &http::RetryPolicy,
+    query: &str,
+    lang: &str,
+) -> Result<Vec<DefinitionSection>, SourceError> {
+    info!("Fetching {} definitions for '{}' from {}", lang, query, endpoint);
+    
+    let url = entries_url(endpoint, lang, query);
+    
+    let request = || async {
+        let _permit = ratelimit::acquire(ratelimit::DICTIONARY).await;
+        let response = http::client()
+            .get(&url)
+            .send()
+            .await
+            .map_err(|e| http::request_error("dictionary", e))?;
+        http::check_transient(response, "Dictionary API")
+    };
+
+    match http::retry("dictionary", policy, request).await {
+        Ok(response) => {
+            if response.status().is_success() {
+                let json_text = response.text().await
+                    .map_err(|e| http::request_error("dictionary", e))?;
+                
+                // Log the actual response for debugging
+                debug!("Raw response: {}", http::body_snippet(&json_text));
+                
+                let sections = parse_entries(&json_text, &source_label(endpoint), lang)?;
+                if sections.is_empty() {
+                    info!("No definitions found for '{}'", query);
+                } else {
+                    info!(
+                        "Successfully fetched {} definitions for '{}'",
+                        sections.iter().map(|s| s.definitions.len()).sum::<usize>(), query
+                    );
+                }
+                Ok(sections)
+            } else if response.status() == 404 {
+                info!("No definitions found for '{}' (404)", query);
+                Ok(vec![])
+            } else {
+                error!("Dictionary API returned status: {}", response.status());
+                Err(SourceError::UpstreamStatus {
+                    source: "Dictionary API".to_string(),
+                    status: response.status().as_u16(),
+                })
+            }
+        }
+        Err(e) => {
+            error!("Dictionary API request failed: {}", e);
+            Err(e)
+        }
+    }
+}
+
+// Sections for each entry in a response body. Some CDNs in front of the API
+// pass its "no definitions" error object through with a 200, so a body that
+// isn't a list of entries is tried as one of those before giving up.
+fn parse_entries(body: &str, source: &str, lang: &str) -> Result<Vec<DefinitionSection>, SourceError> {
+    let entries = match serde_json::from_str::<Vec<DictionaryApiResponse>>(body) {
+        Ok(entries) => entries,
+        Err(e) => {
+            return match serde_json::from_str::<DictionaryApiError>(body) {
+                Ok(error) if error.title == NO_DEFINITIONS_TITLE => Ok(Vec::new()),
+                Ok(error) => {
+                    error!("Dictionary API answered with an error: {}", error.title);
+                    Err(SourceError::Upstream { title: error.title, message: error.message })
+                }
+                Err(_) => {
+                    error!("Failed to parse dictionary response: {}", e);
+                    Err(SourceError::ParseError {
+                        source: "dictionary".to_string(),
+                        body_snippet: http::body_snippet(body),
+                    })
+                }
+            };
+        }
+    };
+
+    let sections = entries
+        .into_iter()
+        .map(|entry| {
+            let definitions = entry
+                .meanings
+                .into_iter()
+                .flat_map(|meaning| {
+                    let word = entry.word.clone();
+                    // Synonyms for the meaning as a whole go with its first
+                    // sense rather than being repeated on every one
+                    let mut shared = Some((
+                        meaning.synonyms.unwrap_or_default(),
+                        meaning.antonyms.unwrap_or_default(),
+                    ));
+                    meaning.definitions.into_iter().map(move |def| {
+                        let (mut synonyms, mut antonyms) = (def.synonyms.unwrap_or_default(), def.antonyms.unwrap_or_default());
+                        if let Some((more_synonyms, more_antonyms)) = shared.take() {
+                            synonyms.extend(more_synonyms);
+                            antonyms.extend(more_antonyms);
+                        }
+                        Definition {
+                            word: word.clone(),
+                            part_of_speech: Some(meaning.part_of_speech.clone()),
+                            definition: def.definition,
+                            example: def.example,
+                            translated: None,
+                            synonyms: distinct(synonyms, &word),
+                            antonyms: distinct(antonyms, &word),
+                            sources: Vec::new(),
+                        }
+                    })
+                })
+                .collect();
+            DefinitionSection {
+                source: source.to_string(),
+                definitions,
+                phonetics: convert_phonetics(entry.phonetics.unwrap_or_default()),
+                language: lang.to_string(),
+                truncated: false,
+                total_available: 0,
+                changed_since_cache: false,
+                examples_truncated: false,
+            }
+        })
+        .collect();
+    Ok(sections)
+}
+
+// `terms` without blanks, `word` itself or repeats in another case
+fn distinct(terms: Vec<String>, word: &str) -> Vec<String> {
+    let mut seen = std::collections::HashSet::from([word.to_lowercase()]);
+    terms
+        .into_iter()
+        .map(|term| term.trim().to_string())
+        .filter(|term| !term.is_empty() && seen.insert(term.to_lowercase()))
+        .collect()
+}
+
+// Every sense's synonyms and antonyms together, in the order given
+fn thesaurus_from(sections: &[DefinitionSection], query: &str, lang: &str) -> ThesaurusSection {
+    let definitions = || sections.iter().flat_map(|section| &section.definitions);
+    ThesaurusSection {
+        synonyms: distinct(definitions().flat_map(|def| def.synonyms.clone()).collect(), query),
+        antonyms: distinct(definitions().flat_map(|def| def.antonyms.clone()).collect(), query),
+        related_terms: Vec::new(),
+        language: lang.to_string(),
+    }
+}
+
+// Keep phonetic entries that carry IPA text or audio, merging entries with
+// identical text so the same transcription isn't listed once per accent
+// recording
+fn convert_phonetics(phonetics: Vec<PhoneticResponse>) -> Option<Vec<Phonetic>> {
+    let non_empty = |v: Option<String>| v.filter(|v| !v.trim().is_empty());
+    let mut result: Vec<Phonetic> = Vec::new();
+
+    for phonetic in phonetics {
+        let text = non_empty(phonetic.text);
+        let audio_url = non_empty(phonetic.audio);
+        if text.is_none() && audio_url.is_none() {
+            continue;
+        }
+
+        match result.iter_mut().find(|p| text.is_some() && p.text == text) {
+            Some(existing) => {
+                if existing.audio_url.is_none() {
+                    existing.audio_url = audio_url;
+                }
+            }
+            None => result.push(Phonetic { text, audio_url }),
+        }
+    }
+
+    if result.is_empty() {
+        None
+    } else {
+        Some(result)
+    }
+}
+
+// API Response Structures
+
+#[derive(Debug, Deserialize)]
+struct DictionaryApiResponse {
+    word: String,
+    meanings: Vec<Meaning>,
+    phonetics: Option<Vec<PhoneticResponse>>,
+}
+
+// What the API sends instead of entries when it has none (or fails)
+const NO_DEFINITIONS_TITLE: &str = "No Definitions Found";
+
+#[derive(Debug, Deserialize)]
+struct DictionaryApiError {
+    title: String,
+    #[serde(default)]
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PhoneticResponse {
+    text: Option<String>,
+    audio: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Meaning {
+    #[serde(default)]
+    part_of_speech: String,
+    definitions: Vec<DefinitionResponse>,
+    synonyms: Option<Vec<String>>,
+    antonyms: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DefinitionResponse {
+    #[serde(rename = "definition")]
+    definition: String,
+    example: Option<String>,
+    synonyms: Option<Vec<String>>,
+    antonyms: Option<Vec<String>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entries_url_encodes_word() {
+        let url = |lang, word| entries_url(DEFAULT_ENDPOINT, lang, word);
+        assert_eq!(url("en", "serendipity"), "https://api.dictionaryapi.dev/api/v2/entries/en/serendipity");
+        assert_eq!(url("fr", "café"), "https://api.dictionaryapi.dev/api/v2/entries/fr/caf%C3%A9");
+        assert_eq!(url("en", "naïve"), "https://api.dictionaryapi.dev/api/v2/entries/en/na%C3%AFve");
+        assert_eq!(url("en", "don't"), "https://api.dictionaryapi.dev/api/v2/entries/en/don%27t");
+        assert_eq!(
+            entries_url("https://dict.example.org/entries/", "en", "bank"),
+            "https://dict.example.org/entries/en/bank"
+        );
+    }
+
+    #[test]
+    fn test_source_label_names_mirrors() {
+        assert_eq!(source_label(DEFAULT_ENDPOINT), "Free Dictionary API");
+        assert_eq!(source_label("https://dict.example.org/api/v2/entries"), "Free Dictionary API (dict.example.org)");
+    }
+
+    #[tokio::test]
+    async fn test_failover_skips_unreachable_endpoint() {
+        // Nothing listens on these ports, so both connections are refused
+        let endpoints = vec!["http://127.0.0.1:9/a".to_string(), "http://127.0.0.1:9/b".to_string()];
+        let err = get_with_failover(&endpoints, "bank", "en").await.unwrap_err();
+        assert!(err.is_unavailable());
+
+        // Both are now cooling down, so both are tried again rather than none
+        assert!(DEAD_ENDPOINTS.is_open("http://127.0.0.1:9/a"));
+        assert_eq!(endpoints_to_try(&endpoints), vec!["http://127.0.0.1:9/a", "http://127.0.0.1:9/b"]);
+        DEAD_ENDPOINTS.reset("http://127.0.0.1:9/b");
+        assert_eq!(endpoints_to_try(&endpoints), vec!["http://127.0.0.1:9/b"]);
+    }
+
+    #[test]
+    fn test_convert_phonetics_dedupes_identical_text() {
+        let phonetics = vec![
+            PhoneticResponse { text: Some("/həˈləʊ/".to_string()), audio: Some("".to_string()) },
+            PhoneticResponse { text: Some("/həˈləʊ/".to_string()), audio: Some("https://example.org/hello-uk.mp3".to_string()) },
+            PhoneticResponse { text: Some("/hɛˈləʊ/".to_string()), audio: None },
+            PhoneticResponse { text: None, audio: None },
+        ];
+
+        let phonetics = convert_phonetics(phonetics).unwrap();
+        assert_eq!(phonetics.len(), 2);
+        assert_eq!(phonetics[0].text.as_deref(), Some("/həˈləʊ/"));
+        assert_eq!(phonetics[0].audio_url.as_deref(), Some("https://example.org/hello-uk.mp3"));
+        assert_eq!(phonetics[1].text.as_deref(), Some("/hɛˈləʊ/"));
+        assert!(convert_phonetics(vec![]).is_none());
+    }
+}
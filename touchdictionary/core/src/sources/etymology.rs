@@ -0,0 +1,223 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use tracing::{error, info};
+
+use crate::http;
+use crate::ratelimit;
+use crate::lookup::{ContentType, EtymologySection};
+use crate::sources::wiktionary::{strip_html, SOURCE_NAME};
+use crate::sources::{Source, SourceError, SourceResult};
+
+// Etymology paragraphs from the English Wiktionary entry, via the MediaWiki
+// parse API. Only plain words have one worth showing.
+pub struct EtymologySource;
+
+#[async_trait]
+impl Source for EtymologySource {
+    fn name(&self) -> &str {
+        "etymology"
+    }
+
+    fn applies_to(&self, content_type: &ContentType) -> bool {
+        matches!(content_type, ContentType::Word)
+    }
+
+    async fn fetch(&self, query: &str, _lang: &str) -> Result<SourceResult, SourceError> {
+        get_etymology(&query.to_lowercase()).await.map(SourceResult::Etymology)
+    }
+}
+
+async fn get_etymology(query: &str) -> Result<EtymologySection, SourceError> {
+    info!("Fetching etymology for '{}' from Wiktionary", query);
+
+    let request = || async {
+        let _permit = ratelimit::acquire(ratelimit::WIKTIONARY).await;
+        let response = http::client()
+            .get("https://en.wiktionary.org/w/api.php")
+            .query(&[
+                ("action", "parse"),
+                ("page", query),
+                ("prop", "text"),
+                ("format", "json"),
+                ("formatversion", "2"),
+                ("redirects", "1"),
+            ])
+            .send()
+            .await
+            .map_err(|e| http::request_error("etymology", e))?;
+        http::check_transient(response, "Wiktionary API")
+    };
+
+    let response = http::retry("etymology", &http::RetryPolicy::default(), request).await?;
+    if !response.status().is_success() {
+        error!("Wiktionary API returned status: {}", response.status());
+        return Err(SourceError::UpstreamStatus {
+            source: "Wiktionary API".to_string(),
+            status: response.status().as_u16(),
+        });
+    }
+
+    let body = response.text().await.map_err(|e| http::request_error("etymology", e))?;
+    match parse_etymology(&body)? {
+        Some(section) => {
+            info!("Successfully fetched etymology for '{}'", query);
+            Ok(section)
+        }
+        None => {
+            info!("No etymology found for '{}'", query);
+            Err(SourceError::NotFound { query: query.to_string() })
+        }
+    }
+}
+
+// Pull the first Etymology heading out of the English section of a parsed
+// page. `None` when the page is missing or has no etymology.
+pub(crate) fn parse_etymology(body: &str) -> Result<Option<EtymologySection>, SourceError> {
+    let response: ParseResponse = serde_json::from_str(body).map_err(|e| {
+        error!("Failed to parse Wiktionary response: {}", e);
+        SourceError::ParseError {
+            source: "etymology".to_string(),
+            body_snippet: http::body_snippet(body),
+        }
+    })?;
+
+    // Missing pages come back as `{"error": {"code": "missingtitle", ...}}`
+    let Some(page) = response.parse else {
+        return Ok(None);
+    };
+
+    let headings = headings(&page.text);
+    let Some(english) = headings.iter().position(|h| h.level == 2 && h.id == "English") else {
+        return Ok(None);
+    };
+    let english_end = headings[english + 1..]
+        .iter()
+        .find(|h| h.level == 2)
+        .map_or(page.text.len(), |h| h.start);
+
+    let Some(etymology) = headings[english + 1..]
+        .iter()
+        .position(|h| h.start < english_end && h.id.starts_with("Etymology"))
+        .map(|i| english + 1 + i)
+    else {
+        return Ok(None);
+    };
+    let end = headings.get(etymology + 1).map_or(english_end, |h| h.start.min(english_end));
+    let html = &page.text[headings[etymology].start..end];
+
+    let paragraphs: Vec<String> = elements(html, "<p", "</p>")
+        .map(strip_html)
+        .filter(|p| !p.is_empty())
+        .collect();
+    if paragraphs.is_empty() {
+        return Ok(None);
+    }
+
+    // The last language named is the earliest one the word is traced to
+    let language_of_origin = elements(html, "<span class=\"etyl\"", "</span>")
+        .map(strip_html)
+        .filter(|l| !l.is_empty())
+        .last();
+
+    Ok(Some(EtymologySection {
+        source: SOURCE_NAME.to_string(),
+        text: paragraphs.join("\n"),
+        language_of_origin,
+    }))
+}
+
+struct Heading {
+    start: usize,
+    level: u8,
+    id: String,
+}
+
+// Every `<hN id="...">` tag in the page, in document order
+fn headings(html: &str) -> Vec<Heading> {
+    let mut headings = Vec::new();
+    let mut offset = 0;
+
+    while let Some(found) = html[offset..].find("<h") {
+        let start = offset + found;
+        offset = start + 2;
+
+        let level = match html.as_bytes().get(start + 2) {
+            Some(digit @ b'1'..=b'6') => digit - b'0',
+            _ => continue,
+        };
+        let Some(tag_end) = html[start..].find('>').map(|i| start + i) else {
+            break;
+        };
+        let id = html[start..tag_end]
+            .split("id=\"")
+            .nth(1)
+            .and_then(|rest| rest.split('"').next())
+            .unwrap_or_default()
+            .to_string();
+
+        headings.push(Heading { start, level, id });
+    }
+
+    headings
+}
+
+// Inner HTML of each element opened by `open` and closed by `close`
+fn elements<'a>(html: &'a str, open: &'a str, close: &'a str) -> impl Iterator<Item = &'a str> {
+    let mut rest = html;
+    std::iter::from_fn(move || {
+        let start = rest.find(open)?;
+        let after_open = start + rest[start..].find('>')? + 1;
+        let end = after_open + rest[after_open..].find(close)?;
+        let inner = &rest[after_open..end];
+        rest = &rest[end + close.len()..];
+        Some(inner)
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct ParseResponse {
+    parse: Option<ParsedPage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ParsedPage {
+    text: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_etymology_nice() {
+        let body = include_str!("../../tests/fixtures/wiktionary_etymology_nice.json");
+        let section = parse_etymology(body).unwrap().unwrap();
+
+        assert_eq!(section.source, "Wiktionary");
+        assert_eq!(
+            section.text,
+            "From Middle English nice (\"foolish, silly\"), from Old French nice, from Latin nescius (\"ignorant, unaware\").\n\
+             The modern sense \"pleasant\" is first attested in the 18th century."
+        );
+        assert_eq!(section.language_of_origin.as_deref(), Some("Latin"));
+    }
+
+    #[test]
+    fn test_parse_etymology_robot() {
+        let body = include_str!("../../tests/fixtures/wiktionary_etymology_robot.json");
+        let section = parse_etymology(body).unwrap().unwrap();
+
+        assert!(section.text.starts_with("Borrowed from Czech robot, coined by Josef Čapek"));
+        assert!(section.text.contains("Karel Čapek's 1920 play R.U.R."));
+        assert_eq!(section.language_of_origin.as_deref(), Some("Czech"));
+    }
+
+    #[test]
+    fn test_parse_etymology_absent() {
+        let missing = r#"{"error":{"code":"missingtitle","info":"The page you specified doesn't exist."}}"#;
+        assert!(parse_etymology(missing).unwrap().is_none());
+
+        let no_heading = r#"{"parse":{"title":"x","text":"<h2 id=\"English\">English</h2><h3 id=\"Noun\">Noun</h3><p>x</p>"}}"#;
+        assert!(parse_etymology(no_heading).unwrap().is_none());
+    }
+}
@@ -0,0 +1,204 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use tracing::{error, info};
+
+use crate::http;
+use crate::lemma;
+use crate::lookup::{ContentType, ExamplesSection};
+use crate::ratelimit;
+use crate::sources::{Source, SourceError, SourceResult};
+
+pub const SOURCE_NAME: &str = "Tatoeba";
+
+// Default number of sentences kept per lookup
+pub const DEFAULT_MAX_SENTENCES: usize = 5;
+
+// Longer sentences are paragraphs of context rather than examples
+const MAX_SENTENCE_CHARS: usize = 140;
+
+// Sentences per request; extra so there are enough left after filtering
+const PAGE_SIZE: usize = 30;
+
+// Real usage sentences containing the word, from Tatoeba
+pub struct ExamplesSource {
+    pub max_sentences: usize,
+}
+
+impl Default for ExamplesSource {
+    fn default() -> Self {
+        ExamplesSource { max_sentences: DEFAULT_MAX_SENTENCES }
+    }
+}
+
+#[async_trait]
+impl Source for ExamplesSource {
+    fn name(&self) -> &str {
+        "examples"
+    }
+
+    fn applies_to(&self, content_type: &ContentType) -> bool {
+        matches!(content_type, ContentType::Word | ContentType::Mixed)
+    }
+
+    async fn fetch(&self, query: &str, _lang: &str) -> Result<SourceResult, SourceError> {
+        get_examples(&query.to_lowercase(), self.max_sentences)
+            .await
+            .map(SourceResult::Examples)
+    }
+}
+
+async fn get_examples(query: &str, max_sentences: usize) -> Result<ExamplesSection, SourceError> {
+    info!("Fetching example sentences for '{}' from Tatoeba", query);
+
+    let request = || async {
+        let _permit = ratelimit::acquire(ratelimit::TATOEBA).await;
+        let response = http::client()
+            .get("https://tatoeba.org/en/api_v0/search")
+            .query(&[
+                ("query", query),
+                ("from", "eng"),
+                ("orphans", "no"),
+                ("unapproved", "no"),
+                ("limit", &PAGE_SIZE.to_string()),
+            ])
+            .send()
+            .await
+            .map_err(|e| http::request_error("examples", e))?;
+        http::check_transient(response, "Tatoeba API")
+    };
+
+    let response = http::retry("examples", &http::RetryPolicy::default(), request).await?;
+    if !response.status().is_success() {
+        error!("Tatoeba API returned status: {}", response.status());
+        return Err(SourceError::UpstreamStatus {
+            source: "Tatoeba API".to_string(),
+            status: response.status().as_u16(),
+        });
+    }
+
+    let body = response.text().await.map_err(|e| http::request_error("examples", e))?;
+    let section = parse_examples(&body, query, max_sentences)?;
+    if section.sentences.is_empty() {
+        info!("No example sentences found for '{}'", query);
+        return Err(SourceError::NotFound { query: query.to_string() });
+    }
+    info!("Found {} example sentences for '{}'", section.sentences.len(), query);
+    Ok(section)
+}
+
+// Short, distinct sentences from a search response, each with where `word`
+// occurs in it
+pub(crate) fn parse_examples(body: &str, word: &str, max_sentences: usize) -> Result<ExamplesSection, SourceError> {
+    let response: SearchResponse = serde_json::from_str(body).map_err(|e| {
+        error!("Failed to parse Tatoeba response: {}", e);
+        SourceError::ParseError {
+            source: "examples".to_string(),
+            body_snippet: http::body_snippet(body),
+        }
+    })?;
+
+    let mut sentences: Vec<String> = Vec::new();
+    for result in response.results {
+        let text = result.text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if text.is_empty() || text.chars().count() >= MAX_SENTENCE_CHARS {
+            continue;
+        }
+        // Tatoeba has the same sentence under several ids, sometimes with
+        // different punctuation or casing
+        let key = comparison_key(&text);
+        if sentences.iter().any(|kept| comparison_key(kept) == key) {
+            continue;
+        }
+        sentences.push(text);
+        if sentences.len() == max_sentences {
+            break;
+        }
+    }
+
+    let occurrences = sentences.iter().map(|sentence| occurrences(sentence, word)).collect();
+    Ok(ExamplesSection {
+        source: SOURCE_NAME.to_string(),
+        sentences,
+        occurrences,
+    })
+}
+
+fn comparison_key(sentence: &str) -> String {
+    sentence
+        .chars()
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+// Start and length in characters of every form of `word` in `sentence`:
+// the word itself and inflections of it ("ran", "running")
+fn occurrences(sentence: &str, word: &str) -> Vec<(usize, usize)> {
+    let chars: Vec<char> = sentence.chars().collect();
+    let mut found = Vec::new();
+    let mut start = 0;
+    while start < chars.len() {
+        if !chars[start].is_alphabetic() {
+            start += 1;
+            continue;
+        }
+        let mut end = start;
+        while end < chars.len() && (chars[end].is_alphabetic() || (chars[end] == '\'' && end > start)) {
+            end += 1;
+        }
+        let token: String = chars[start..end].iter().collect::<String>().to_lowercase();
+        let token = token.trim_end_matches("'s").trim_end_matches('\'');
+        if token == word || lemma::lemmatize(token).as_deref() == Some(word) {
+            found.push((start, token.chars().count()));
+        }
+        start = end;
+    }
+    found
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    #[serde(default)]
+    results: Vec<SearchResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResult {
+    text: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_examples_run() {
+        let body = include_str!("../../tests/fixtures/tatoeba_run.json");
+        let section = parse_examples(body, "run", DEFAULT_MAX_SENTENCES).unwrap();
+
+        assert_eq!(section.source, "Tatoeba");
+        // The repeat with different punctuation and the over-long sentence
+        // are dropped
+        assert_eq!(
+            section.sentences,
+            vec![
+                "I run every morning.",
+                "Tom ran as fast as he could.",
+                "She's running late again.",
+                "Don't run in the hallway!",
+                "We had to run to catch the train, but it was already running out of the station.",
+            ]
+        );
+        assert_eq!(section.occurrences[0], vec![(2, 3)]);
+        assert_eq!(section.occurrences[1], vec![(4, 3)]);
+        assert_eq!(section.occurrences[2], vec![(6, 7)]);
+        assert_eq!(section.occurrences[4], vec![(10, 3), (53, 7)]);
+    }
+
+    #[test]
+    fn test_parse_examples_empty() {
+        let section = parse_examples(r#"{"paging":{},"results":[]}"#, "zzxq", DEFAULT_MAX_SENTENCES).unwrap();
+        assert!(section.sentences.is_empty());
+        assert!(parse_examples("<html>", "run", DEFAULT_MAX_SENTENCES).is_err());
+    }
+}
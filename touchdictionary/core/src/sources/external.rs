@@ -0,0 +1,315 @@
+// Sources run as commands, for definition services this crate doesn't know
+// about. Each `[[external_sources]]` entry is asked like any other source:
+// its command runs, never through a shell, with `{query}` and `{lang}`
+// filled into its arguments, and prints one JSON document on stdout:
+//
+//     {
+//       "definitions": [
+//         {
+//           "definition": "A branch for a single change",
+//           "part_of_speech": "noun",
+//           "example": "Push the fix to a topic branch.",
+//           "synonyms": ["feature branch"],
+//           "antonyms": []
+//         }
+//       ],
+//       "phonetic": "/ˈtɒpɪk/",
+//       "synonyms": ["feature branch"],
+//       "antonyms": [],
+//       "etymology": "From topic + branch."
+//     }
+//
+// Only `definitions`, and `definition` in each of them, are required; an
+// empty list means there's no entry. Fields not listed here are ignored. A
+// command that exits with an error, prints anything else or runs past its
+// timeout fails as a source, leaving the others alone.
+
+use std::process::Stdio;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use tokio::process::Command;
+use tracing::{info, warn};
+
+use crate::config::ExternalSourceSettings;
+use crate::lookup::{ContentType, Definition, DefinitionSection, EtymologySection, Phonetic, ThesaurusSection};
+use crate::sources::{Source, SourceError, SourceResult};
+
+pub const DEFAULT_TIMEOUT_MS: u64 = 5000;
+
+// Characters of a failing command's stderr quoted in its error
+const STDERR_SNIPPET_CHARS: usize = 200;
+
+pub struct ExternalSource {
+    pub settings: ExternalSourceSettings,
+}
+
+#[async_trait]
+impl Source for ExternalSource {
+    fn name(&self) -> &str {
+        &self.settings.name
+    }
+
+    fn applies_to(&self, content_type: &ContentType) -> bool {
+        !matches!(content_type, ContentType::Passage | ContentType::Pattern | ContentType::Computed)
+    }
+
+    // `{lang}` is passed on, so the command decides
+    fn supports_language(&self, _lang: &str) -> bool {
+        true
+    }
+
+    async fn fetch(&self, query: &str, lang: &str) -> Result<SourceResult, SourceError> {
+        let stdout = self.run(query, lang).await?;
+        parse_output(&self.settings.name, query, lang, &stdout)
+    }
+}
+
+impl ExternalSource {
+    async fn run(&self, query: &str, lang: &str) -> Result<Vec<u8>, SourceError> {
+        let name = &self.settings.name;
+        info!("Running {} for '{}'", name, query);
+        let args = self.settings.args.iter().map(|arg| substitute(arg, query, lang));
+        let child = Command::new(&self.settings.command)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| failed(format!("{} couldn't be started", name), e.to_string()))?;
+
+        // Giving up drops the child, which kills it
+        let timeout = Duration::from_millis(self.settings.timeout_ms);
+        let output = match tokio::time::timeout(timeout, child.wait_with_output()).await {
+            Ok(output) => output.map_err(|e| failed(format!("{} failed", name), e.to_string()))?,
+            Err(_) => {
+                warn!("{} gave no answer within {}ms", name, self.settings.timeout_ms);
+                return Err(SourceError::Timeout { source: name.clone() });
+            }
+        };
+        if !output.status.success() {
+            let stderr: String = String::from_utf8_lossy(&output.stderr).trim().chars().take(STDERR_SNIPPET_CHARS).collect();
+            let title = match output.status.code() {
+                Some(code) => format!("{} exited with status {}", name, code),
+                None => format!("{} was killed", name),
+            };
+            return Err(failed(title, stderr));
+        }
+        Ok(output.stdout)
+    }
+}
+
+// `arg` with `{query}` and `{lang}` filled in, left to right in one pass, so
+// a query that itself says `{lang}` reaches the command as it was typed
+fn substitute(arg: &str, query: &str, lang: &str) -> String {
+    let placeholders = [("{query}", query), ("{lang}", lang)];
+    let mut substituted = String::with_capacity(arg.len());
+    let mut rest = arg;
+    while let Some(start) = rest.find('{') {
+        substituted.push_str(&rest[..start]);
+        rest = &rest[start..];
+        match placeholders.iter().find(|(placeholder, _)| rest.starts_with(placeholder)) {
+            Some((placeholder, value)) => {
+                substituted.push_str(value);
+                rest = &rest[placeholder.len()..];
+            }
+            None => {
+                substituted.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    substituted.push_str(rest);
+    substituted
+}
+
+fn failed(title: String, message: String) -> SourceError {
+    SourceError::Upstream { title, message }
+}
+
+// What `source` printed for `query`, checked against the schema above. Each
+// problem names the field it's in, like `definitions[1].definition`.
+fn parse_output(source: &str, query: &str, lang: &str, stdout: &[u8]) -> Result<SourceResult, SourceError> {
+    let invalid = |message: String| SourceError::ParseError { source: source.to_string(), body_snippet: message };
+    let document: Value = serde_json::from_slice(stdout).map_err(|e| invalid(format!("not JSON: {}", e)))?;
+    let Some(document) = document.as_object() else {
+        return Err(invalid("expected a JSON object".to_string()));
+    };
+
+    let entries: Vec<Value> = required(document, "", "definitions").map_err(invalid)?;
+    let mut definitions = Vec::new();
+    for (index, entry) in entries.iter().enumerate() {
+        let path = format!("definitions[{}]", index);
+        let Some(entry) = entry.as_object() else {
+            return Err(invalid(format!("`{}` should be an object", path)));
+        };
+        definitions.push(Definition {
+            word: query.to_string(),
+            part_of_speech: optional(entry, &path, "part_of_speech").map_err(invalid)?,
+            definition: required(entry, &path, "definition").map_err(invalid)?,
+            example: optional(entry, &path, "example").map_err(invalid)?,
+            translated: None,
+            synonyms: optional(entry, &path, "synonyms").map_err(invalid)?,
+            antonyms: optional(entry, &path, "antonyms").map_err(invalid)?,
+            sources: Vec::new(),
+        });
+    }
+    let phonetic: Option<String> = optional(document, "", "phonetic").map_err(invalid)?;
+    let synonyms: Vec<String> = optional(document, "", "synonyms").map_err(invalid)?;
+    let antonyms: Vec<String> = optional(document, "", "antonyms").map_err(invalid)?;
+    let etymology: Option<String> = optional(document, "", "etymology").map_err(invalid)?;
+
+    let mut results = vec![SourceResult::Definitions(vec![DefinitionSection {
+        source: source.to_string(),
+        definitions,
+        phonetics: phonetic.map(|text| vec![Phonetic { text: Some(text), audio_url: None }]),
+        language: lang.to_string(),
+        truncated: false,
+        total_available: 0,
+        changed_since_cache: false,
+        examples_truncated: false,
+    }])];
+    if !synonyms.is_empty() || !antonyms.is_empty() {
+        results.push(SourceResult::Thesaurus(ThesaurusSection {
+            synonyms,
+            antonyms,
+            related_terms: Vec::new(),
+            language: lang.to_string(),
+        }));
+    }
+    if let Some(text) = etymology.filter(|text| !text.trim().is_empty()) {
+        results.push(SourceResult::Etymology(EtymologySection {
+            source: source.to_string(),
+            text,
+            language_of_origin: None,
+        }));
+    }
+    Ok(match results.len() {
+        1 => results.remove(0),
+        _ => SourceResult::Combined(results),
+    })
+}
+
+fn field_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+fn required<T: DeserializeOwned>(object: &Map<String, Value>, path: &str, key: &str) -> Result<T, String> {
+    match object.get(key) {
+        None | Some(Value::Null) => Err(format!("`{}` is required", field_path(path, key))),
+        Some(value) => T::deserialize(value).map_err(|e| format!("`{}`: {}", field_path(path, key), e)),
+    }
+}
+
+// Missing and null both give the default
+fn optional<T: DeserializeOwned + Default>(object: &Map<String, Value>, path: &str, key: &str) -> Result<T, String> {
+    match object.get(key) {
+        None | Some(Value::Null) => Ok(T::default()),
+        Some(value) => T::deserialize(value).map_err(|e| format!("`{}`: {}", field_path(path, key), e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(stdout: &str) -> Result<SourceResult, SourceError> {
+        parse_output("company", "topic branch", "en", stdout.as_bytes())
+    }
+
+    #[test]
+    fn test_substitute() {
+        assert_eq!(substitute("--word={query}", "serendipity", "en"), "--word=serendipity");
+        assert_eq!(substitute("{query}/{lang}", "{lang}", "de"), "{lang}/de");
+        assert_eq!(substitute("{{query}} {x} {", "a{query}", "de"), "{a{query}} {x} {");
+    }
+
+    #[test]
+    fn test_parse_output() {
+        let stdout = r#"{
+            "definitions": [
+                {"definition": "A branch for a single change", "part_of_speech": "noun", "owner": "git team"},
+                {"definition": "A subject", "example": null}
+            ],
+            "synonyms": ["feature branch"],
+            "etymology": "From topic + branch.",
+            "version": 2
+        }"#;
+        let SourceResult::Combined(results) = parse(stdout).unwrap() else {
+            panic!("expected definitions, synonyms and an etymology");
+        };
+        let [SourceResult::Definitions(sections), SourceResult::Thesaurus(thesaurus), SourceResult::Etymology(etymology)] =
+            results.as_slice()
+        else {
+            panic!("unexpected sections: {:?}", results);
+        };
+        assert_eq!(sections[0].source, "company");
+        let definitions: Vec<_> = sections[0].definitions.iter().map(|d| d.definition.as_str()).collect();
+        assert_eq!(definitions, ["A branch for a single change", "A subject"]);
+        assert_eq!(sections[0].definitions[0].word, "topic branch");
+        assert_eq!(sections[0].definitions[0].part_of_speech.as_deref(), Some("noun"));
+        assert_eq!(thesaurus.synonyms, ["feature branch"]);
+        assert_eq!(etymology.text, "From topic + branch.");
+
+        assert!(parse(r#"{"definitions": []}"#).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_output_names_the_bad_field() {
+        let message = |stdout: &str| parse(stdout).unwrap_err().to_string();
+        assert_eq!(message("{}"), "Failed to parse company response: `definitions` is required");
+        assert_eq!(
+            message(r#"{"definitions": [{"definition": "ok"}, {"part_of_speech": "noun"}]}"#),
+            "Failed to parse company response: `definitions[1].definition` is required"
+        );
+        assert_eq!(
+            message(r#"{"definitions": [{"definition": "ok", "example": 3}]}"#),
+            "Failed to parse company response: `definitions[0].example`: invalid type: integer `3`, expected a string"
+        );
+        assert_eq!(message(r#"["definitions"]"#), "Failed to parse company response: expected a JSON object");
+        assert!(message("Definition: a branch").starts_with("Failed to parse company response: not JSON"));
+    }
+
+    #[cfg(unix)]
+    fn script(body: &str, timeout_ms: u64) -> ExternalSource {
+        ExternalSource {
+            settings: ExternalSourceSettings {
+                name: "company".to_string(),
+                command: "/bin/sh".into(),
+                args: vec!["-c".to_string(), body.to_string(), "sh".to_string(), "{query}".to_string(), "{lang}".to_string()],
+                timeout_ms,
+            },
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_fetch_runs_the_command() {
+        let source = script(r#"printf '{"definitions": [{"definition": "%s in %s"}]}' "$1" "$2""#, 5000);
+        let SourceResult::Definitions(sections) = source.fetch("it's; $(rm -rf /)", "de").await.unwrap() else {
+            panic!("expected definitions");
+        };
+        assert_eq!(sections[0].definitions[0].definition, "it's; $(rm -rf /) in de");
+        assert_eq!(sections[0].language, "de");
+
+        let SourceResult::Definitions(sections) = source.fetch("{lang} and {query}", "de").await.unwrap() else {
+            panic!("expected definitions");
+        };
+        assert_eq!(sections[0].definitions[0].definition, "{lang} and {query} in de");
+
+        let error = script("echo 'no such word list' >&2; exit 3", 5000).fetch("word", "en").await.unwrap_err();
+        assert_eq!(error.to_string(), "company exited with status 3: no such word list");
+
+        let started = std::time::Instant::now();
+        let error = script("sleep 5", 200).fetch("word", "en").await.unwrap_err();
+        assert!(error.is_timeout());
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+}
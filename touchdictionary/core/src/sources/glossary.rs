@@ -0,0 +1,495 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::language::default_language;
+use crate::lookup::{ContentType, Definition, DefinitionSection};
+use crate::sources::{Source, SourceError, SourceResult};
+
+// The user's own glossaries (company jargon, project names): every `.toml`
+// and `.csv` file in a directory, one definition section per file. Read
+// when the lookup context is created and again whenever a file changes.
+pub struct LocalGlossarySource {
+    pub directory: PathBuf,
+}
+
+#[async_trait]
+impl Source for LocalGlossarySource {
+    fn name(&self) -> &str {
+        "local_glossary"
+    }
+
+    // Terms can be words, names, acronyms or phrases like "pull request"
+    fn applies_to(&self, content_type: &ContentType) -> bool {
+        !matches!(content_type, ContentType::Passage | ContentType::Pattern)
+    }
+
+    // Glossaries are in whatever language their author wrote them in
+    fn supports_language(&self, _lang: &str) -> bool {
+        true
+    }
+
+    fn cacheable(&self) -> bool {
+        false
+    }
+
+    fn is_local(&self) -> bool {
+        true
+    }
+
+    async fn fetch(&self, query: &str, _lang: &str) -> Result<SourceResult, SourceError> {
+        Ok(SourceResult::Definitions(index(&self.directory).lookup(query)))
+    }
+}
+
+// The configured directory, or `glossaries` next to the config file
+pub fn directory(config: &Config) -> Option<PathBuf> {
+    config.glossary.directory.clone().or_else(default_directory)
+}
+
+pub fn default_directory() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("touchdictionary").join("glossaries"))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlossaryEntry {
+    pub term: String,
+    pub definition: String,
+    pub part_of_speech: Option<String>,
+    pub examples: Vec<String>,
+    pub aliases: Vec<String>,
+}
+
+// A malformed entry, or what stopped the file from being read at all
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlossaryFormat {
+    Toml,
+    Csv,
+}
+
+impl GlossaryFormat {
+    pub fn of(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "toml" => Some(GlossaryFormat::Toml),
+            "csv" => Some(GlossaryFormat::Csv),
+            _ => None,
+        }
+    }
+}
+
+// A glossary file's well-formed entries, and what's wrong with the rest
+pub fn read(path: &Path) -> io::Result<(Vec<GlossaryEntry>, Vec<Problem>)> {
+    let format = GlossaryFormat::of(path)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "expected a .toml or .csv file"))?;
+    Ok(parse(&std::fs::read_to_string(path)?, format))
+}
+
+pub fn parse(text: &str, format: GlossaryFormat) -> (Vec<GlossaryEntry>, Vec<Problem>) {
+    match format {
+        GlossaryFormat::Toml => parse_toml(text),
+        GlossaryFormat::Csv => parse_csv(text),
+    }
+}
+
+// [[term]]
+// term = "pull request"
+// definition = "A proposed change, reviewed before it's merged"
+// part_of_speech = "noun"
+// examples = ["Open a pull request once CI passes."]
+// aliases = ["PR", "merge request"]
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TomlFile {
+    #[serde(default)]
+    term: Vec<toml::Spanned<toml::Value>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TomlEntry {
+    term: String,
+    definition: String,
+    part_of_speech: Option<String>,
+    #[serde(default)]
+    examples: Vec<String>,
+    #[serde(default)]
+    aliases: Vec<String>,
+}
+
+fn parse_toml(text: &str) -> (Vec<GlossaryEntry>, Vec<Problem>) {
+    let file: TomlFile = match toml::from_str(text) {
+        Ok(file) => file,
+        Err(e) => {
+            let line = e.span().map_or(1, |span| line_of(text, span.start));
+            return (Vec::new(), vec![Problem { line, message: e.message().to_string() }]);
+        }
+    };
+
+    let (mut entries, mut problems) = (Vec::new(), Vec::new());
+    for spanned in file.term {
+        let line = line_of(text, spanned.span().start);
+        let entry = TomlEntry::deserialize(spanned.into_inner())
+            .map_err(|e| e.message().to_string())
+            .and_then(|e| entry(e.term, e.definition, e.part_of_speech, e.examples, e.aliases));
+        match entry {
+            Ok(entry) => entries.push(entry),
+            Err(message) => problems.push(Problem { line, message }),
+        }
+    }
+    (entries, problems)
+}
+
+// term,definition,part_of_speech,example,aliases
+// pull request,"A proposed change, reviewed before it's merged",noun,,PR; merge request
+//
+// Only the first two columns are required. The header row is optional, and
+// lines starting with # are comments.
+const CSV_COLUMNS: usize = 5;
+const ALIAS_SEPARATOR: char = ';';
+
+fn parse_csv(text: &str) -> (Vec<GlossaryEntry>, Vec<Problem>) {
+    let records = match csv_records(text) {
+        Ok(records) => records,
+        Err(problem) => return (Vec::new(), vec![problem]),
+    };
+
+    let (mut entries, mut problems) = (Vec::new(), Vec::new());
+    let mut first = true;
+    for (line, fields) in records {
+        if fields.iter().all(|f| f.trim().is_empty()) || fields[0].trim_start().starts_with('#') {
+            continue;
+        }
+        let header = first && fields[0].trim().eq_ignore_ascii_case("term");
+        first = false;
+        if header {
+            continue;
+        }
+        if fields.len() > CSV_COLUMNS {
+            let message = format!(
+                "expected at most {} columns (term, definition, part_of_speech, example, aliases), found {}",
+                CSV_COLUMNS,
+                fields.len()
+            );
+            problems.push(Problem { line, message });
+            continue;
+        }
+
+        let field = |i: usize| fields.get(i).map(|f| f.trim().to_string()).unwrap_or_default();
+        let split = |value: String| -> Vec<String> { value.split(ALIAS_SEPARATOR).map(str::to_string).collect() };
+        let part_of_speech = Some(field(2));
+        match entry(field(0), field(1), part_of_speech, vec![field(3)], split(field(4))) {
+            Ok(entry) => entries.push(entry),
+            Err(message) => problems.push(Problem { line, message }),
+        }
+    }
+    (entries, problems)
+}
+
+// RFC 4180 records, each with the line it starts on. Quoted fields may hold
+// commas, doubled quotes and line breaks.
+fn csv_records(text: &str) -> Result<Vec<(usize, Vec<String>)>, Problem> {
+    let mut records = Vec::new();
+    let (mut fields, mut field) = (Vec::new(), String::new());
+    let (mut line, mut start, mut quoted_since) = (1, 1, None);
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted_since.is_some() => {
+                if chars.next_if_eq(&'"').is_some() {
+                    field.push('"');
+                } else {
+                    quoted_since = None;
+                }
+            }
+            '"' if field.trim().is_empty() => {
+                field.clear();
+                quoted_since = Some(line);
+            }
+            '\n' => {
+                line += 1;
+                if quoted_since.is_some() {
+                    field.push('\n');
+                } else {
+                    fields.push(std::mem::take(&mut field));
+                    records.push((start, std::mem::take(&mut fields)));
+                    start = line;
+                }
+            }
+            '\r' if quoted_since.is_none() && chars.peek() == Some(&'\n') => {}
+            ',' if quoted_since.is_none() => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+
+    if let Some(line) = quoted_since {
+        return Err(Problem { line, message: "quoted field is never closed".to_string() });
+    }
+    if !field.is_empty() || !fields.is_empty() {
+        fields.push(field);
+        records.push((start, fields));
+    }
+    Ok(records)
+}
+
+// Trimmed, with blank optional fields dropped; Err says what's missing
+fn entry(
+    term: String,
+    definition: String,
+    part_of_speech: Option<String>,
+    examples: Vec<String>,
+    aliases: Vec<String>,
+) -> Result<GlossaryEntry, String> {
+    let term = term.trim().to_string();
+    if term.is_empty() {
+        return Err("entry has no term".to_string());
+    }
+    let definition = definition.trim().to_string();
+    if definition.is_empty() {
+        return Err(format!("'{}' has no definition", term));
+    }
+    let non_empty = |values: Vec<String>| -> Vec<String> {
+        values.into_iter().map(|v| v.trim().to_string()).filter(|v| !v.is_empty()).collect()
+    };
+    Ok(GlossaryEntry {
+        term,
+        definition,
+        part_of_speech: part_of_speech.map(|p| p.trim().to_string()).filter(|p| !p.is_empty()),
+        examples: non_empty(examples),
+        aliases: non_empty(aliases),
+    })
+}
+
+fn line_of(text: &str, offset: usize) -> usize {
+    text[..offset.min(text.len())].matches('\n').count() + 1
+}
+
+// Terms and aliases match whatever their casing and spacing
+fn key(term: &str) -> String {
+    term.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+struct Glossary {
+    file_name: String,
+    entries: Vec<GlossaryEntry>,
+}
+
+// Every glossary in a directory, indexed by term and alias
+pub struct GlossaryIndex {
+    glossaries: Vec<Glossary>,
+    // key() of a term or alias -> (glossary, entry) positions
+    terms: HashMap<String, Vec<(usize, usize)>>,
+    // The files read and their modification times when they were
+    stamps: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+impl GlossaryIndex {
+    // Malformed entries are logged and left out; `glossary validate` says
+    // what's wrong with them
+    fn read(stamps: Vec<(PathBuf, Option<SystemTime>)>) -> Self {
+        let mut glossaries = Vec::new();
+        for (path, _) in &stamps {
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+            match read(path) {
+                Ok((entries, problems)) => {
+                    for problem in problems {
+                        warn!("Skipping an entry in glossary {}, {}", path.display(), problem);
+                    }
+                    glossaries.push(Glossary { file_name, entries });
+                }
+                Err(e) => warn!("Couldn't read glossary {}: {}", path.display(), e),
+            }
+        }
+        Self::new(glossaries, stamps)
+    }
+
+    fn new(glossaries: Vec<Glossary>, stamps: Vec<(PathBuf, Option<SystemTime>)>) -> Self {
+        let mut terms: HashMap<String, Vec<(usize, usize)>> = HashMap::new();
+        for (g, glossary) in glossaries.iter().enumerate() {
+            for (e, entry) in glossary.entries.iter().enumerate() {
+                for name in std::iter::once(&entry.term).chain(&entry.aliases) {
+                    let positions = terms.entry(key(name)).or_default();
+                    if !positions.contains(&(g, e)) {
+                        positions.push((g, e));
+                    }
+                }
+            }
+        }
+        GlossaryIndex { glossaries, terms, stamps }
+    }
+
+    // One section per glossary with a match, in file name order. Entries
+    // found by an alias are listed under their term.
+    pub fn lookup(&self, query: &str) -> Vec<DefinitionSection> {
+        let Some(positions) = self.terms.get(&key(query)) else {
+            return Vec::new();
+        };
+
+        let mut sections: Vec<(usize, DefinitionSection)> = Vec::new();
+        for &(g, e) in positions {
+            let entry = &self.glossaries[g].entries[e];
+            let definition = Definition {
+                word: entry.term.clone(),
+                part_of_speech: entry.part_of_speech.clone(),
+                definition: entry.definition.clone(),
+                example: entry.examples.first().cloned(),
+                translated: None,
+                synonyms: Vec::new(),
+                antonyms: Vec::new(),
+                sources: Vec::new(),
+            };
+            match sections.iter_mut().find(|(index, _)| *index == g) {
+                Some((_, section)) => section.definitions.push(definition),
+                None => sections.push((g, DefinitionSection {
+                    source: self.glossaries[g].file_name.clone(),
+                    definitions: vec![definition],
+                    phonetics: None,
+                    language: default_language(),
+                    truncated: false,
+                    total_available: 0,
+                    changed_since_cache: false,
+                    examples_truncated: false,
+                })),
+            }
+        }
+        sections.sort_by_key(|(index, _)| *index);
+        sections.into_iter().map(|(_, section)| section).collect()
+    }
+}
+
+// The glossary files in `dir`, sorted by name, with their mtimes. Empty
+// when the directory doesn't exist.
+fn stamps(dir: &Path) -> Vec<(PathBuf, Option<SystemTime>)> {
+    let Ok(listing) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut stamps: Vec<_> = listing
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && GlossaryFormat::of(path).is_some())
+        .map(|path| {
+            let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+            (path, modified)
+        })
+        .collect();
+    stamps.sort();
+    stamps
+}
+
+// The index for `dir`, kept for the life of the process and read again
+// when a file in it is added, removed or modified
+pub fn index(dir: &Path) -> Arc<GlossaryIndex> {
+    static LOADED: Mutex<Option<(PathBuf, Arc<GlossaryIndex>)>> = Mutex::new(None);
+
+    let stamps = stamps(dir);
+    let mut loaded = LOADED.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((loaded_dir, index)) = loaded.as_ref() {
+        if loaded_dir == dir && index.stamps == stamps {
+            return index.clone();
+        }
+    }
+
+    info!("Loading {} glossaries from {}", stamps.len(), dir.display());
+    let index = Arc::new(GlossaryIndex::read(stamps));
+    *loaded = Some((dir.to_path_buf(), index.clone()));
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOML_FIXTURE: &str = include_str!("../../tests/fixtures/glossary_engineering.toml");
+    const CSV_FIXTURE: &str = include_str!("../../tests/fixtures/glossary_company.csv");
+
+    fn glossary(file_name: &str, text: &str, format: GlossaryFormat) -> Glossary {
+        let (entries, problems) = parse(text, format);
+        assert_eq!(problems, Vec::new(), "{}", file_name);
+        Glossary { file_name: file_name.to_string(), entries }
+    }
+
+    fn fixture_index() -> GlossaryIndex {
+        GlossaryIndex::new(
+            vec![
+                glossary("company.csv", CSV_FIXTURE, GlossaryFormat::Csv),
+                glossary("engineering.toml", TOML_FIXTURE, GlossaryFormat::Toml),
+            ],
+            Vec::new(),
+        )
+    }
+
+    #[test]
+    fn test_lookup_by_term_and_alias() {
+        let index = fixture_index();
+
+        let sections = index.lookup("Pull   Request");
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].source, "engineering.toml");
+        let definition = &sections[0].definitions[0];
+        assert_eq!(definition.word, "pull request");
+        assert_eq!(definition.part_of_speech.as_deref(), Some("noun"));
+        assert_eq!(definition.example.as_deref(), Some("Open a pull request once CI passes."));
+
+        let by_alias = index.lookup("pr");
+        assert_eq!(by_alias[0].definitions[0].word, "pull request");
+
+        // Defined in both files, listed in file name order
+        let sections = index.lookup("LGTM");
+        let sources: Vec<_> = sections.iter().map(|s| s.source.as_str()).collect();
+        assert_eq!(sources, vec!["company.csv", "engineering.toml"]);
+        assert_eq!(sections[0].definitions[0].definition, "Looks good to me, \"ship it\"");
+
+        assert!(index.lookup("serendipity").is_empty());
+    }
+
+    #[test]
+    fn test_csv_fields() {
+        let (entries, _) = parse(CSV_FIXTURE, GlossaryFormat::Csv);
+        let standup = entries.iter().find(|e| e.term == "stand-up").unwrap();
+        assert_eq!(standup.aliases, vec!["standup", "daily"]);
+        assert_eq!(standup.definition, "A short daily meeting,\nheld standing up");
+        assert_eq!(standup.part_of_speech, None);
+    }
+
+    #[test]
+    fn test_problems_have_line_numbers() {
+        let toml = "[[term]]\nterm = \"ok\"\ndefinition = \"fine\"\n\n[[term]]\nterm = \"broken\"\n\n[[term]]\nterm = \"typo\"\ndefintion = \"oops\"\n";
+        let (entries, problems) = parse(toml, GlossaryFormat::Toml);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(problems.iter().map(|p| p.line).collect::<Vec<_>>(), vec![5, 8]);
+        assert!(problems[0].message.contains("definition"), "{}", problems[0]);
+
+        let (_, problems) = parse("[[term]\nterm = \"x\"\n", GlossaryFormat::Toml);
+        assert_eq!(problems[0].line, 1);
+
+        let csv = "term,definition\nok,fine\n,no term\nempty,\n\"multi\nline\",one,two,three,four,five\n";
+        let (entries, problems) = parse(csv, GlossaryFormat::Csv);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(problems.iter().map(|p| p.line).collect::<Vec<_>>(), vec![3, 4, 5]);
+        assert_eq!(problems[1].message, "'empty' has no definition");
+
+        let (_, problems) = parse("ok,fine\n\"open,quote\n", GlossaryFormat::Csv);
+        assert_eq!(problems, vec![Problem { line: 2, message: "quoted field is never closed".to_string() }]);
+    }
+}
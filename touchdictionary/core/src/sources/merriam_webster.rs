@@ -0,0 +1,219 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::{error, info};
+
+use crate::error::LookupError;
+use crate::http;
+use crate::language;
+use crate::lookup::{ContentType, Definition, DefinitionSection};
+use crate::ratelimit;
+use crate::sources::{Source, SourceError, SourceResult};
+
+pub const SOURCE_NAME: &str = "Merriam-Webster";
+pub const API_KEY_ENV: &str = "MW_API_KEY";
+
+// Merriam-Webster's Collegiate Dictionary API. Needs a (free, personal) key;
+// without one the source isn't registered at all.
+pub struct MerriamWebsterSource {
+    pub api_key: String,
+}
+
+#[async_trait]
+impl Source for MerriamWebsterSource {
+    fn name(&self) -> &str {
+        "merriam_webster"
+    }
+
+    fn applies_to(&self, content_type: &ContentType) -> bool {
+        matches!(content_type, ContentType::Word | ContentType::Mixed)
+    }
+
+    async fn fetch(&self, query: &str, _lang: &str) -> Result<SourceResult, SourceError> {
+        get_definitions(&query.to_lowercase(), &self.api_key).await
+    }
+}
+
+// The key from the config, or from `MW_API_KEY` when the config has none
+pub fn api_key(configured: Option<&str>) -> Option<String> {
+    configured
+        .map(str::to_string)
+        .or_else(|| std::env::var(API_KEY_ENV).ok())
+        .map(|key| key.trim().to_string())
+        .filter(|key| !key.is_empty())
+}
+
+async fn get_definitions(query: &str, api_key: &str) -> Result<SourceResult, LookupError> {
+    info!("Fetching definitions for '{}' from Merriam-Webster", query);
+
+    let url = format!(
+        "https://dictionaryapi.com/api/v3/references/collegiate/json/{}",
+        http::encode_path_segment(query)
+    );
+    let request = || async {
+        let _permit = ratelimit::acquire(ratelimit::MERRIAM_WEBSTER).await;
+        let response = http::client()
+            .get(&url)
+            .query(&[("key", api_key)])
+            .send()
+            .await
+            .map_err(|e| http::request_error("merriam_webster", e))?;
+        http::check_transient(response, "Merriam-Webster API")
+    };
+
+    let response = http::retry("merriam_webster", &http::RetryPolicy::default(), request).await?;
+    if !response.status().is_success() {
+        error!("Merriam-Webster API returned status: {}", response.status());
+        return Err(LookupError::UpstreamStatus {
+            source: "Merriam-Webster API".to_string(),
+            status: response.status().as_u16(),
+        });
+    }
+
+    let body = response.text().await.map_err(|e| http::request_error("merriam_webster", e))?;
+    parse_response(query, &body)
+}
+
+// A known word comes back as a list of entries; an unknown one as a bare
+// list of similarly spelled words
+fn parse_response(word: &str, body: &str) -> Result<SourceResult, LookupError> {
+    let parse_error = || LookupError::ParseError {
+        source: "merriam_webster".to_string(),
+        body_snippet: http::body_snippet(body),
+    };
+
+    let values: Vec<Value> = serde_json::from_str(body).map_err(|_| parse_error())?;
+    if values.iter().all(Value::is_string) {
+        let suggestions = values.into_iter().filter_map(|v| v.as_str().map(str::to_string)).collect();
+        return Ok(SourceResult::Suggestions(suggestions));
+    }
+
+    let entries: Vec<Entry> = values
+        .into_iter()
+        .map(serde_json::from_value)
+        .collect::<Result<_, _>>()
+        .map_err(|e| {
+            error!("Failed to parse Merriam-Webster response: {}", e);
+            parse_error()
+        })?;
+
+    // Entries for related words ("serendipitous" for "serendipity") come
+    // along too; keep the ones for the word itself
+    let definitions = entries
+        .iter()
+        .filter(|entry| entry.headword().eq_ignore_ascii_case(word))
+        .flat_map(|entry| {
+            let example = entry.def.as_ref().and_then(first_example);
+            entry.shortdef.iter().enumerate().map(move |(i, definition)| Definition {
+                word: entry.headword(),
+                part_of_speech: entry.fl.clone(),
+                definition: definition.clone(),
+                // The first verbal illustration belongs to the first sense
+                example: if i == 0 { example.clone() } else { None },
+                translated: None,
+                synonyms: Vec::new(),
+                antonyms: Vec::new(),
+                sources: Vec::new(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    if definitions.is_empty() {
+        return Ok(SourceResult::Definitions(Vec::new()));
+    }
+    Ok(SourceResult::Definitions(vec![DefinitionSection {
+        source: SOURCE_NAME.to_string(),
+        definitions,
+        phonetics: None,
+        language: language::DEFAULT_LANGUAGE.to_string(),
+        truncated: false,
+        total_available: 0,
+        changed_since_cache: false,
+        examples_truncated: false,
+    }]))
+}
+
+// First `["vis", [{"t": ...}]]` anywhere in the sense sequence
+fn first_example(def: &Value) -> Option<String> {
+    match def {
+        Value::Array(items) => {
+            if items.first().and_then(Value::as_str) == Some("vis") {
+                let text = items.get(1)?.get(0)?.get("t")?.as_str()?;
+                return Some(strip_markup(text));
+            }
+            items.iter().find_map(first_example)
+        }
+        Value::Object(fields) => fields.values().find_map(first_example),
+        _ => None,
+    }
+}
+
+// Drop formatting tokens like `{it}`, `{/it}` and `{bc}`
+fn strip_markup(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut in_token = false;
+    for c in text.chars() {
+        match c {
+            '{' => in_token = true,
+            '}' => in_token = false,
+            c if !in_token => plain.push(c),
+            _ => {}
+        }
+    }
+    plain.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[derive(Debug, Deserialize)]
+struct Entry {
+    meta: Meta,
+    fl: Option<String>,
+    #[serde(default)]
+    shortdef: Vec<String>,
+    def: Option<Value>,
+}
+
+impl Entry {
+    // `meta.id` carries a homograph suffix ("bank:2")
+    fn headword(&self) -> String {
+        self.meta.id.split(':').next().unwrap_or_default().to_string()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Meta {
+    id: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_response_fixture() {
+        let body = include_str!("../../tests/fixtures/merriam_webster_serendipity.json");
+        let SourceResult::Definitions(sections) = parse_response("serendipity", body).unwrap() else {
+            panic!("expected definitions");
+        };
+
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].source, "Merriam-Webster");
+        let definitions = &sections[0].definitions;
+        assert_eq!(definitions.len(), 1);
+        assert_eq!(definitions[0].part_of_speech.as_deref(), Some("noun"));
+        assert!(definitions[0].definition.starts_with("the faculty or phenomenon"));
+        assert_eq!(
+            definitions[0].example.as_deref(),
+            Some("a series of serendipities that led to the discovery")
+        );
+    }
+
+    #[test]
+    fn test_parse_response_suggestions() {
+        let result = parse_response("serendipidy", r#"["serendipity", "serendipitous"]"#).unwrap();
+        assert!(matches!(result, SourceResult::Suggestions(ref s) if s == &["serendipity", "serendipitous"]));
+
+        // An empty list is a miss with nothing to suggest
+        assert!(parse_response("zzyzx", "[]").unwrap().is_empty());
+        assert!(parse_response("zzyzx", "Invalid API key").is_err());
+    }
+}
@@ -0,0 +1,263 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::cache::Validator;
+use crate::config::Config;
+use crate::error::LookupError;
+use crate::language;
+use crate::lookup::{
+    AcronymSection, ContentType, DefinitionSection, EtymologySection, ExamplesSection, RhymesSection, ThesaurusSection,
+    WikipediaSection,
+};
+
+pub mod acronym;
+pub mod datamuse;
+pub mod dictionary;
+pub mod etymology;
+pub mod examples;
+pub mod external;
+pub mod glossary;
+pub mod merriam_webster;
+pub mod offline;
+pub mod rhymes;
+pub mod thesaurus;
+pub mod urban_dictionary;
+pub mod wikipedia;
+pub mod wikidata;
+pub mod wiktionary;
+pub mod word_stats;
+
+// Sources report failures with the same structured error lookups use
+pub type SourceError = LookupError;
+
+// What a single source contributes to `Sections`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SourceResult {
+    Definitions(Vec<DefinitionSection>),
+    Wikipedia(WikipediaSection),
+    Thesaurus(ThesaurusSection),
+    Etymology(EtymologySection),
+    Examples(ExamplesSection),
+    Acronym(AcronymSection),
+    Rhymes(RhymesSection),
+    // Sources that fill more than one section at once
+    Combined(Vec<SourceResult>),
+    // No entry, but the source knows what the user may have meant
+    Suggestions(Vec<String>),
+}
+
+impl SourceResult {
+    pub fn is_empty(&self) -> bool {
+        match self {
+            SourceResult::Definitions(sections) => sections.iter().all(|s| s.definitions.is_empty()),
+            SourceResult::Wikipedia(_) => false,
+            SourceResult::Thesaurus(thesaurus) => thesaurus.is_empty(),
+            SourceResult::Etymology(etymology) => etymology.text.is_empty(),
+            SourceResult::Examples(examples) => examples.sentences.is_empty(),
+            SourceResult::Acronym(acronym) => acronym.expansions.is_empty(),
+            SourceResult::Rhymes(rhymes) => rhymes.is_empty(),
+            SourceResult::Combined(results) => results.iter().all(SourceResult::is_empty),
+            SourceResult::Suggestions(suggestions) => suggestions.is_empty(),
+        }
+    }
+
+    // Flag every section as different from the copy that was cached
+    pub fn mark_changed_since_cache(&mut self) {
+        match self {
+            SourceResult::Definitions(sections) => {
+                sections.iter_mut().for_each(|section| section.changed_since_cache = true)
+            }
+            SourceResult::Wikipedia(wiki) => wiki.changed_since_cache = true,
+            SourceResult::Combined(results) => results.iter_mut().for_each(SourceResult::mark_changed_since_cache),
+            SourceResult::Thesaurus(_)
+            | SourceResult::Etymology(_)
+            | SourceResult::Examples(_)
+            | SourceResult::Acronym(_)
+            | SourceResult::Rhymes(_)
+            | SourceResult::Suggestions(_) => {}
+        }
+    }
+}
+
+// Answer to "has the entry behind this validator changed?"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Revalidation {
+    NotModified,
+    Modified,
+    // The source has no way to ask; fetch it again
+    Unsupported,
+}
+
+// A lookup provider. `fetch` receives the whitespace-normalized query in its
+// original casing; case-insensitive sources lowercase it themselves. `lang`
+// is always a language the source reported it supports.
+#[async_trait]
+pub trait Source: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn applies_to(&self, content_type: &ContentType) -> bool;
+
+    // Sources are English-only unless they say otherwise
+    fn supports_language(&self, lang: &str) -> bool {
+        lang == language::DEFAULT_LANGUAGE
+    }
+
+    // Whether an inflected query may be retried under its lemma here.
+    // Encyclopedic sources want titles exactly as typed.
+    fn accepts_lemma(&self) -> bool {
+        true
+    }
+
+    // Query `lookup::health_check` asks for: something cheap the source is
+    // sure to have
+    fn probe_query(&self) -> &str {
+        "test"
+    }
+
+    // Whether answers go through the lookup cache. Sources that read local
+    // files are quicker than the cache, and edits to them should show at once.
+    fn cacheable(&self) -> bool {
+        true
+    }
+
+    // Whether answers come from this machine alone. Private mode asks only
+    // these.
+    fn is_local(&self) -> bool {
+        false
+    }
+
+    async fn fetch(&self, query: &str, lang: &str) -> Result<SourceResult, SourceError>;
+
+    // `fetch`, plus the validators upstream sent for the entry, for APIs
+    // that support conditional requests
+    async fn fetch_validated(&self, query: &str, lang: &str) -> Result<(SourceResult, Option<Validator>), SourceError> {
+        self.fetch(query, lang).await.map(|result| (result, None))
+    }
+
+    // Ask upstream whether the entry `validator` came with has changed,
+    // without downloading it again
+    async fn revalidate(&self, _query: &str, _lang: &str, _validator: &Validator) -> Result<Revalidation, SourceError> {
+        Ok(Revalidation::Unsupported)
+    }
+}
+
+// Ordered set of sources consulted by `lookup()`. Results are merged in
+// registration order, so earlier dictionaries list their definitions first.
+pub struct SourceRegistry {
+    sources: Vec<Box<dyn Source>>,
+    // Consulted only when these sources find no definitions
+    fallback: Option<Box<SourceRegistry>>,
+    // Names of the sources the config switched off
+    disabled: Vec<String>,
+}
+
+impl SourceRegistry {
+    pub fn new() -> Self {
+        SourceRegistry { sources: Vec::new(), fallback: None, disabled: Vec::new() }
+    }
+
+    // The built-in sources, tuned by the config and without the ones it
+    // switches off
+    pub fn from_config(config: &Config) -> Self {
+        let mut registry = SourceRegistry::new();
+        // The user's own glossaries come before every dictionary
+        if let Some(directory) = glossary::directory(config).filter(|dir| dir.is_dir()) {
+            registry.register(Box::new(glossary::LocalGlossarySource { directory }));
+        }
+        registry.register(Box::new(dictionary::DictionarySource {
+            endpoints: config.dictionary.endpoints.clone(),
+        }));
+        // Only with a key; there's nothing to query without one
+        if let Some(api_key) = merriam_webster::api_key(config.merriam_webster.api_key.as_deref()) {
+            registry.register(Box::new(merriam_webster::MerriamWebsterSource { api_key }));
+        }
+        registry.register(Box::new(wiktionary::WiktionarySource));
+        registry.register(Box::new(wikipedia::WikipediaSource {
+            max_related: config.wikipedia.max_related,
+            endpoint: config.wikipedia.endpoint.clone(),
+        }));
+        registry.register(Box::new(thesaurus::ThesaurusSource::default()));
+        registry.register(Box::new(etymology::EtymologySource));
+        registry.register(Box::new(examples::ExamplesSource::default()));
+        registry.register(Box::new(acronym::AcronymSource));
+        registry.register(Box::new(rhymes::RhymesSource::default()));
+        for settings in &config.external_sources {
+            registry.register(Box::new(external::ExternalSource { settings: settings.clone() }));
+        }
+        let urban = || Box::new(urban_dictionary::UrbanDictionarySource::from(&config.urban_dictionary));
+        if config.urban_dictionary.always_include {
+            registry.register(urban());
+        }
+        registry.retain_enabled(config);
+
+        // Stand-ins for when the dictionaries above find nothing: the offline
+        // dictionary for when the network lets us down, and slang
+        let mut fallback = SourceRegistry::new();
+        if let Some(database) = offline::database_path(config).filter(|path| path.is_file()) {
+            if config.source_enabled("offline") {
+                fallback.register(Box::new(offline::OfflineSource { database }));
+            }
+        }
+        if !config.urban_dictionary.always_include && config.source_enabled("urban_dictionary") {
+            fallback.register(urban());
+        }
+        if fallback.sources().next().is_some() {
+            registry.set_fallback(fallback);
+        }
+        registry
+    }
+
+    // Only the offline dictionary and the glossaries, for lookups that
+    // mustn't touch the network
+    pub fn offline(config: &Config) -> Self {
+        let mut registry = SourceRegistry::new();
+        if let Some(directory) = glossary::directory(config).filter(|dir| dir.is_dir()) {
+            if config.source_enabled("local_glossary") {
+                registry.register(Box::new(glossary::LocalGlossarySource { directory }));
+            }
+        }
+        if let Some(database) = offline::database_path(config) {
+            registry.register(Box::new(offline::OfflineSource { database }));
+        }
+        registry
+    }
+
+    pub fn register(&mut self, source: Box<dyn Source>) {
+        self.sources.push(source);
+    }
+
+    pub fn set_fallback(&mut self, fallback: SourceRegistry) {
+        self.fallback = Some(Box::new(fallback));
+    }
+
+    pub fn fallback(&self) -> Option<&SourceRegistry> {
+        self.fallback.as_deref()
+    }
+
+    // Drop the sources the user has switched off
+    pub fn retain_enabled(&mut self, config: &Config) {
+        let (enabled, disabled): (Vec<_>, Vec<_>) = std::mem::take(&mut self.sources)
+            .into_iter()
+            .partition(|s| config.source_enabled(s.name()));
+        self.sources = enabled;
+        self.disabled.extend(disabled.iter().map(|s| s.name().to_string()));
+    }
+
+    pub fn disabled(&self) -> impl Iterator<Item = &str> {
+        self.disabled.iter().map(String::as_str)
+    }
+
+    pub fn sources(&self) -> impl Iterator<Item = &dyn Source> {
+        self.sources.iter().map(|s| s.as_ref())
+    }
+
+    pub fn applicable<'a>(&'a self, content_type: &'a ContentType) -> impl Iterator<Item = &'a dyn Source> {
+        self.sources().filter(move |s| s.applies_to(content_type))
+    }
+}
+
+impl Default for SourceRegistry {
+    fn default() -> Self {
+        SourceRegistry::from_config(&Config::default())
+    }
+}
@@ -0,0 +1,210 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use tracing::info;
+
+use crate::config::Config;
+use crate::error::LookupError;
+use crate::language::default_language;
+use crate::lookup::{ContentType, Definition, DefinitionSection, ThesaurusSection};
+use crate::sources::{Source, SourceError, SourceResult};
+
+pub const SOURCE_LABEL: &str = "WordNet (offline)";
+
+// Every sense of a lemma, most common first.
+// Expects the WordNet SQL schema (`words`, `senses`, `synsets`, `samples`).
+const SENSES_QUERY: &str = "SELECT y.synsetid, y.pos, y.definition FROM words w \
+     JOIN senses s ON s.wordid = w.wordid JOIN synsets y ON y.synsetid = s.synsetid \
+     WHERE w.lemma = ?1 ORDER BY s.sensenum";
+const SAMPLE_QUERY: &str = "SELECT sample FROM samples WHERE synsetid = ?1 ORDER BY sampleid LIMIT 1";
+const SYNONYMS_QUERY: &str = "SELECT w.lemma FROM senses s JOIN words w ON w.wordid = s.wordid \
+     WHERE s.synsetid = ?1 AND w.lemma <> ?2 ORDER BY s.sensenum";
+
+// Local WordNet database, for when the network isn't there. Only consulted
+// after the online dictionaries come back empty, or instead of them in
+// offline mode.
+pub struct OfflineSource {
+    pub database: PathBuf,
+}
+
+#[async_trait]
+impl Source for OfflineSource {
+    fn name(&self) -> &str {
+        "offline"
+    }
+
+    fn applies_to(&self, content_type: &ContentType) -> bool {
+        matches!(content_type, ContentType::Word | ContentType::Mixed)
+    }
+
+    fn is_local(&self) -> bool {
+        true
+    }
+
+    async fn fetch(&self, query: &str, _lang: &str) -> Result<SourceResult, SourceError> {
+        let database = self.database.clone();
+        let query = query.to_lowercase();
+        tokio::task::spawn_blocking(move || lookup_word(&database, &query))
+            .await
+            .map_err(|e| unavailable(e.to_string()))?
+    }
+}
+
+// The configured database, or `wordnet.sqlite` in the data directory
+pub fn database_path(config: &Config) -> Option<PathBuf> {
+    config.offline.database.clone().or_else(default_database_path)
+}
+
+pub fn default_database_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("touchdictionary").join("wordnet.sqlite"))
+}
+
+// Whether lookups can be answered without a network: a database is in place
+pub fn is_available() -> bool {
+    database_path(&Config::load()).is_some_and(|path| path.is_file())
+}
+
+// The database is opened on first use and kept for the life of the process
+fn connection(path: &Path) -> Result<Arc<Mutex<Connection>>, LookupError> {
+    static OPEN: Mutex<Option<(PathBuf, Arc<Mutex<Connection>>)>> = Mutex::new(None);
+
+    let mut open = OPEN.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((open_path, connection)) = open.as_ref() {
+        if open_path == path {
+            return Ok(connection.clone());
+        }
+    }
+
+    if !path.is_file() {
+        return Err(unavailable(format!("no WordNet database at {}", path.display())));
+    }
+    info!("Opening offline dictionary {}", path.display());
+    let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX)
+        .map_err(|e| unavailable(e.to_string()))?;
+    let connection = Arc::new(Mutex::new(connection));
+    *open = Some((path.to_path_buf(), connection.clone()));
+    Ok(connection)
+}
+
+fn lookup_word(database: &Path, word: &str) -> Result<SourceResult, LookupError> {
+    let connection = connection(database)?;
+    let connection = connection.lock().unwrap_or_else(|e| e.into_inner());
+    let (definitions, synonyms) = read_senses(&connection, word).map_err(|e| unavailable(e.to_string()))?;
+    if definitions.is_empty() {
+        return Err(LookupError::NotFound { query: word.to_string() });
+    }
+
+    let mut results = vec![SourceResult::Definitions(vec![DefinitionSection {
+        source: SOURCE_LABEL.to_string(),
+        definitions,
+        phonetics: None,
+        language: default_language(),
+        truncated: false,
+        total_available: 0,
+        changed_since_cache: false,
+        examples_truncated: false,
+    }])];
+    if !synonyms.is_empty() {
+        results.push(SourceResult::Thesaurus(ThesaurusSection {
+            synonyms,
+            antonyms: Vec::new(),
+            related_terms: Vec::new(),
+            language: default_language(),
+        }));
+    }
+    Ok(SourceResult::Combined(results))
+}
+
+// Each sense of `word` with its first example, and the other lemmas of
+// those senses' synsets
+fn read_senses(connection: &Connection, word: &str) -> rusqlite::Result<(Vec<Definition>, Vec<String>)> {
+    let senses: Vec<(i64, Option<String>, Option<String>)> = connection
+        .prepare_cached(SENSES_QUERY)?
+        .query_map(params![word], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut definitions = Vec::new();
+    let mut synonyms: Vec<String> = Vec::new();
+    for (synset, pos, definition) in senses {
+        let Some(definition) = definition else {
+            continue;
+        };
+        let example = connection.prepare_cached(SAMPLE_QUERY)?.query_row(params![synset], |row| row.get(0)).optional()?;
+        let mut statement = connection.prepare_cached(SYNONYMS_QUERY)?;
+        for synonym in statement.query_map(params![synset, word], |row| row.get::<_, String>(0))? {
+            let synonym = synonym?;
+            if !synonyms.contains(&synonym) {
+                synonyms.push(synonym);
+            }
+        }
+        definitions.push(Definition {
+            word: word.to_string(),
+            part_of_speech: pos.as_deref().map(part_of_speech).map(str::to_string),
+            definition,
+            example,
+            translated: None,
+            synonyms: Vec::new(),
+            antonyms: Vec::new(),
+            sources: Vec::new(),
+        });
+    }
+    Ok((definitions, synonyms))
+}
+
+// WordNet's one-letter part-of-speech codes; `s` is a satellite adjective
+fn part_of_speech(code: &str) -> &str {
+    match code {
+        "n" => "noun",
+        "v" => "verb",
+        "a" | "s" => "adjective",
+        "r" => "adverb",
+        other => other,
+    }
+}
+
+fn unavailable(reason: String) -> LookupError {
+    LookupError::OfflineUnavailable { reason }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> OfflineSource {
+        OfflineSource {
+            database: PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/wordnet_tiny.sqlite"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_from_fixture_database() {
+        let SourceResult::Combined(results) = fixture().fetch("Bank", "en").await.unwrap() else {
+            panic!("expected definitions and synonyms");
+        };
+        let [SourceResult::Definitions(sections), SourceResult::Thesaurus(thesaurus)] = results.as_slice() else {
+            panic!("unexpected results: {:?}", results);
+        };
+
+        let definitions = &sections[0].definitions;
+        assert_eq!(sections[0].source, SOURCE_LABEL);
+        assert_eq!(definitions.len(), 3);
+        assert_eq!(definitions[0].definition, "sloping land (especially the slope beside a body of water)");
+        assert_eq!(definitions[0].example.as_deref(), Some("they pulled the canoe up on the bank"));
+        assert_eq!(definitions[2].part_of_speech.as_deref(), Some("verb"));
+        assert!(thesaurus.synonyms.contains(&"depository financial institution".to_string()));
+        assert!(thesaurus.synonyms.contains(&"camber".to_string()));
+        assert!(!thesaurus.synonyms.contains(&"bank".to_string()));
+
+        let err = fixture().fetch("zzyzx", "en").await.unwrap_err();
+        assert!(err.is_not_found());
+    }
+
+    #[tokio::test]
+    async fn test_missing_database_is_unavailable() {
+        let source = OfflineSource { database: PathBuf::from("/nonexistent/wordnet.sqlite") };
+        let err = source.fetch("bank", "en").await.unwrap_err();
+        assert_eq!(err.kind(), "offline_unavailable");
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::HashSet;
+
+use async_trait::async_trait;
+use tracing::{error, info, warn};
+
+use crate::lookup::{ContentType, RhymesSection};
+use crate::sources::datamuse::{self, DatamuseWord};
+use crate::sources::{Source, SourceError, SourceResult};
+
+// Cap on each list (perfect rhymes, near rhymes, sound-alikes)
+pub const DEFAULT_MAX_WORDS: usize = 20;
+
+// Perfect and near rhymes and words that sound alike, from the Datamuse
+// API. Off unless `sources.rhymes` is switched on.
+pub struct RhymesSource {
+    pub max_words: usize,
+}
+
+impl Default for RhymesSource {
+    fn default() -> Self {
+        RhymesSource { max_words: DEFAULT_MAX_WORDS }
+    }
+}
+
+#[async_trait]
+impl Source for RhymesSource {
+    fn name(&self) -> &str {
+        "rhymes"
+    }
+
+    fn applies_to(&self, content_type: &ContentType) -> bool {
+        matches!(content_type, ContentType::Word)
+    }
+
+    async fn fetch(&self, query: &str, _lang: &str) -> Result<SourceResult, SourceError> {
+        get_rhymes(&query.to_lowercase(), self.max_words).await.map(SourceResult::Rhymes)
+    }
+}
+
+async fn get_rhymes(query: &str, max_words: usize) -> Result<RhymesSection, SourceError> {
+    info!("Fetching rhymes for '{}' from Datamuse API", query);
+
+    let (perfect_params, near_params, sl_params) = ([("rel_rhy", query)], [("rel_nry", query)], [("sl", query)]);
+    let (perfect, near, sounds_like) = tokio::join!(
+        datamuse::fetch_words("rhymes", &perfect_params),
+        datamuse::fetch_words("rhymes", &near_params),
+        datamuse::fetch_words("rhymes", &sl_params),
+    );
+
+    // As with the thesaurus, one failed relation shouldn't hide the others
+    if perfect.is_err() && near.is_err() {
+        if let Err(e) = sounds_like {
+            error!("All Datamuse rhyme requests failed for '{}': {}", query, e);
+            return Err(e);
+        }
+    }
+
+    let words = |relation: &str, words: Result<Vec<DatamuseWord>, SourceError>| {
+        words.unwrap_or_else(|e| {
+            warn!("Datamuse {} request failed for '{}': {}", relation, query, e);
+            Vec::new()
+        })
+    };
+    let section = build_section(
+        query,
+        words("rel_rhy", perfect),
+        words("rel_nry", near),
+        words("sl", sounds_like),
+        max_words,
+    );
+
+    info!(
+        "Found {} perfect rhymes, {} near rhymes, {} sound-alikes for '{}'",
+        section.perfect.len(), section.near.len(), section.sounds_like.len(), query
+    );
+    Ok(section)
+}
+
+// Each list ordered by score and capped at `max_words`. A word appears in
+// one list only, the first of perfect, near and sounds-like it's in, and
+// never when it's the query itself.
+fn build_section(
+    query: &str,
+    perfect: Vec<DatamuseWord>,
+    near: Vec<DatamuseWord>,
+    sounds_like: Vec<DatamuseWord>,
+    max_words: usize,
+) -> RhymesSection {
+    let mut seen = HashSet::new();
+    seen.insert(query.to_lowercase());
+
+    let mut keep = |mut words: Vec<DatamuseWord>| -> Vec<String> {
+        // Stable, so equal scores keep Datamuse's order
+        words.sort_by_key(|w| std::cmp::Reverse(w.score));
+        words
+            .into_iter()
+            .map(|w| w.word)
+            .filter(|w| seen.insert(w.to_lowercase()))
+            .take(max_words)
+            .collect()
+    };
+
+    let perfect = keep(perfect);
+    let near = keep(near);
+    let sounds_like = keep(sounds_like);
+    RhymesSection { perfect, near, sounds_like }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(list: &[(&str, u64)]) -> Vec<DatamuseWord> {
+        list.iter().map(|&(word, score)| DatamuseWord { word: word.to_string(), score }).collect()
+    }
+
+    #[test]
+    fn test_build_section_orders_caps_and_dedupes() {
+        let section = build_section(
+            "moon",
+            words(&[("tune", 900), ("june", 2000), ("spoon", 1500), ("noon", 1200)]),
+            words(&[("June", 500), ("room", 400), ("doom", 450)]),
+            words(&[("moan", 80), ("Moon", 90), ("mean", 70), ("room", 60)]),
+            3,
+        );
+
+        assert_eq!(section.perfect, vec!["june", "spoon", "noon"]);
+        // "June" was already a perfect rhyme, whatever its casing
+        assert_eq!(section.near, vec!["doom", "room"]);
+        assert_eq!(section.sounds_like, vec!["moan", "mean"]);
+    }
+}
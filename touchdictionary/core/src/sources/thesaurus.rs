@@ -0,0 +1,106 @@
+use async_trait::async_trait;
+use tracing::{error, info, warn};
+
+use crate::language;
+use crate::lookup::{ContentType, ThesaurusSection};
+use crate::sources::{datamuse, Source, SourceError, SourceResult};
+
+// Default cap on each thesaurus list (synonyms, antonyms, related terms)
+pub const DEFAULT_MAX_TERMS: usize = 15;
+
+// Synonyms, antonyms and related terms from the Datamuse API
+pub struct ThesaurusSource {
+    pub max_terms: usize,
+}
+
+impl Default for ThesaurusSource {
+    fn default() -> Self {
+        ThesaurusSource { max_terms: DEFAULT_MAX_TERMS }
+    }
+}
+
+#[async_trait]
+impl Source for ThesaurusSource {
+    fn name(&self) -> &str {
+        "thesaurus"
+    }
+
+    fn applies_to(&self, content_type: &ContentType) -> bool {
+        matches!(content_type, ContentType::Word | ContentType::Mixed)
+    }
+
+    async fn fetch(&self, query: &str, _lang: &str) -> Result<SourceResult, SourceError> {
+        get_thesaurus_data(&query.to_lowercase(), self.max_terms)
+            .await
+            .map(SourceResult::Thesaurus)
+    }
+}
+
+async fn get_thesaurus_data(query: &str, max_terms: usize) -> Result<ThesaurusSection, SourceError> {
+    info!("Fetching thesaurus data for '{}' from Datamuse API", query);
+
+    let (syn_params, ant_params, ml_params) = ([("rel_syn", query)], [("rel_ant", query)], [("ml", query)]);
+    let (synonyms, antonyms, related_terms) = tokio::join!(
+        datamuse::fetch_words("thesaurus", &syn_params),
+        datamuse::fetch_words("thesaurus", &ant_params),
+        datamuse::fetch_words("thesaurus", &ml_params),
+    );
+
+    // Only fail the section when every relation failed; a missing antonym
+    // list shouldn't hide perfectly good synonyms
+    if synonyms.is_err() && antonyms.is_err() {
+        if let Err(e) = related_terms {
+            error!("All Datamuse requests failed for '{}': {}", query, e);
+            return Err(e);
+        }
+    }
+
+    let keep = |relation: &str, words: Result<Vec<datamuse::DatamuseWord>, SourceError>| match words {
+        Ok(words) => dedupe_terms(words.into_iter().map(|w| w.word).collect(), query, max_terms),
+        Err(e) => {
+            warn!("Datamuse {} request failed for '{}': {}", relation, query, e);
+            vec![]
+        }
+    };
+
+    let section = ThesaurusSection {
+        synonyms: keep("rel_syn", synonyms),
+        antonyms: keep("rel_ant", antonyms),
+        related_terms: keep("ml", related_terms),
+        language: language::DEFAULT_LANGUAGE.to_string(),
+    };
+
+    info!(
+        "Successfully fetched {} synonyms, {} antonyms, {} related terms for '{}'",
+        section.synonyms.len(), section.antonyms.len(), section.related_terms.len(), query
+    );
+    Ok(section)
+}
+
+// Case-insensitive dedupe that keeps the first (highest scored) spelling,
+// drops the query itself, and caps the list at `max_terms`
+fn dedupe_terms(words: Vec<String>, query: &str, max_terms: usize) -> Vec<String> {
+    let mut seen = std::collections::HashSet::new();
+    seen.insert(query.to_lowercase());
+
+    words
+        .into_iter()
+        .filter(|w| seen.insert(w.to_lowercase()))
+        .take(max_terms)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dedupe_terms_case_insensitive() {
+        let words = vec!["Glad", "glad", "Happy", "cheerful", "GLAD", "content", "joyful"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+
+        assert_eq!(dedupe_terms(words, "happy", 3), vec!["Glad", "cheerful", "content"]);
+    }
+}
@@ -0,0 +1,170 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use tracing::{error, info};
+
+use crate::config::UrbanDictionarySettings;
+use crate::error::LookupError;
+use crate::http;
+use crate::language;
+use crate::lookup::{ContentType, Definition, DefinitionSection};
+use crate::ratelimit;
+use crate::sources::{Source, SourceError, SourceResult};
+
+pub const SOURCE_NAME: &str = "Urban Dictionary";
+pub const DEFAULT_MAX_RESULTS: usize = 3;
+pub const DEFAULT_MIN_THUMBS_UP: u32 = 10;
+
+// Urban Dictionary, for slang the real dictionaries don't cover. Opt-in, and
+// unless told otherwise only asked once they've come back empty.
+pub struct UrbanDictionarySource {
+    pub max_results: usize,
+    pub min_thumbs_up: u32,
+}
+
+impl From<&UrbanDictionarySettings> for UrbanDictionarySource {
+    fn from(settings: &UrbanDictionarySettings) -> Self {
+        UrbanDictionarySource {
+            max_results: settings.max_results,
+            min_thumbs_up: settings.min_thumbs_up,
+        }
+    }
+}
+
+#[async_trait]
+impl Source for UrbanDictionarySource {
+    fn name(&self) -> &str {
+        "urban_dictionary"
+    }
+
+    fn applies_to(&self, content_type: &ContentType) -> bool {
+        matches!(content_type, ContentType::Word)
+    }
+
+    async fn fetch(&self, query: &str, _lang: &str) -> Result<SourceResult, SourceError> {
+        info!("Fetching definitions for '{}' from Urban Dictionary", query);
+
+        let request = || async {
+            let _permit = ratelimit::acquire(ratelimit::URBAN_DICTIONARY).await;
+            let response = http::client()
+                .get("https://api.urbandictionary.com/v0/define")
+                .query(&[("term", query)])
+                .send()
+                .await
+                .map_err(|e| http::request_error("urban_dictionary", e))?;
+            http::check_transient(response, "Urban Dictionary API")
+        };
+
+        let response = http::retry("urban_dictionary", &http::RetryPolicy::default(), request).await?;
+        if !response.status().is_success() {
+            error!("Urban Dictionary API returned status: {}", response.status());
+            return Err(LookupError::UpstreamStatus {
+                source: "Urban Dictionary API".to_string(),
+                status: response.status().as_u16(),
+            });
+        }
+
+        let body = response.text().await.map_err(|e| http::request_error("urban_dictionary", e))?;
+        parse_response(&body, self.max_results, self.min_thumbs_up).map(SourceResult::Definitions)
+    }
+}
+
+// The best-voted entries with at least `min_thumbs_up`, at most
+// `max_results` of them
+fn parse_response(body: &str, max_results: usize, min_thumbs_up: u32) -> Result<Vec<DefinitionSection>, LookupError> {
+    let response: UrbanResponse = serde_json::from_str(body).map_err(|e| {
+        error!("Failed to parse Urban Dictionary response: {}", e);
+        LookupError::ParseError {
+            source: "urban_dictionary".to_string(),
+            body_snippet: http::body_snippet(body),
+        }
+    })?;
+
+    let mut entries: Vec<UrbanEntry> = response
+        .list
+        .into_iter()
+        .filter(|entry| entry.thumbs_up >= min_thumbs_up)
+        .collect();
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.thumbs_up));
+    entries.truncate(max_results);
+
+    if entries.is_empty() {
+        return Ok(Vec::new());
+    }
+    let definitions = entries
+        .into_iter()
+        .map(|entry| Definition {
+            word: entry.word,
+            part_of_speech: None,
+            definition: strip_links(&entry.definition),
+            example: Some(strip_links(&entry.example)).filter(|example| !example.is_empty()),
+            translated: None,
+            synonyms: Vec::new(),
+            antonyms: Vec::new(),
+            sources: Vec::new(),
+        })
+        .collect();
+
+    Ok(vec![DefinitionSection {
+        source: SOURCE_NAME.to_string(),
+        definitions,
+        phonetics: None,
+        language: language::DEFAULT_LANGUAGE.to_string(),
+        truncated: false,
+        total_available: 0,
+        changed_since_cache: false,
+        examples_truncated: false,
+    }])
+}
+
+// Cross-references are written `[like this]`; keep the words, drop the
+// brackets, and fold the `\r\n` line breaks into single spaces
+fn strip_links(text: &str) -> String {
+    text.replace(['[', ']'], "").split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[derive(Debug, Deserialize)]
+struct UrbanResponse {
+    #[serde(default)]
+    list: Vec<UrbanEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UrbanEntry {
+    word: String,
+    definition: String,
+    #[serde(default)]
+    example: String,
+    #[serde(default)]
+    thumbs_up: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = include_str!("../../tests/fixtures/urban_dictionary_yeet.json");
+
+    #[test]
+    fn test_parse_response_ranks_and_filters() {
+        let sections = parse_response(FIXTURE, 2, 10).unwrap();
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].source, "Urban Dictionary");
+
+        let definitions = &sections[0].definitions;
+        assert_eq!(definitions.len(), 2);
+        assert_eq!(definitions[0].definition, "To throw something with a lot of force.");
+        assert_eq!(definitions[0].example.as_deref(), Some("He yeeted the can across the parking lot."));
+        assert!(definitions[1].definition.starts_with("An exclamation of excitement"));
+
+        // Nothing clears the bar
+        assert!(parse_response(FIXTURE, 5, 100_000).unwrap().is_empty());
+        assert!(parse_response(r#"{"list": []}"#, 5, 0).unwrap().is_empty());
+        assert!(parse_response("<html>", 5, 0).is_err());
+    }
+
+    #[test]
+    fn test_strip_links() {
+        assert_eq!(strip_links("to [throw] with [force]"), "to throw with force");
+        assert_eq!(strip_links("first line\r\n\r\nsecond"), "first line second");
+    }
+}
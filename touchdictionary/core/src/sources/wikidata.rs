@@ -0,0 +1,375 @@
+// A few structured facts about an entity from Wikidata, shown beside its
+// Wikipedia summary. Not a `Source` of its own: it starts from the article
+// Wikipedia found, so it only runs once that has answered.
+
+use std::collections::{BTreeSet, HashMap};
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::cache::Cache;
+use crate::config::Config;
+use crate::error::LookupError;
+use crate::http;
+use crate::language;
+use crate::lookup::{Fact, InfoboxSection, WikipediaSection};
+use crate::ratelimit;
+use crate::sources::wikipedia;
+
+pub const DEFAULT_ENDPOINT: &str = "https://www.wikidata.org/w/api.php";
+
+// Cache namespace for facts, keyed by language and item
+const CACHE: &str = "wikidata";
+
+// Values shown for a property that has several, like occupations
+const MAX_VALUES: usize = 3;
+
+// Properties shown, in this order and under these labels, and whether each
+// lists several values or just the one Wikidata prefers
+const PROPERTIES: &[(&str, &str, bool)] = &[
+    ("P569", "Born", false),
+    ("P570", "Died", false),
+    ("P106", "Occupation", true),
+    ("P17", "Country", false),
+    ("P36", "Capital", false),
+    ("P1082", "Population", false),
+];
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+pub struct WikidataSource {
+    pub endpoint: String,
+}
+
+impl Default for WikidataSource {
+    fn default() -> Self {
+        WikidataSource { endpoint: DEFAULT_ENDPOINT.to_string() }
+    }
+}
+
+impl WikidataSource {
+    // The facts Wikidata has about item `id`, with linked items named in
+    // `lang` where they have a label in it and in English otherwise
+    pub async fn facts(&self, id: &str, lang: &str) -> Result<InfoboxSection, LookupError> {
+        info!("Fetching facts for {} from Wikidata", id);
+        let claims: ClaimsResponse = self.get(&[("action", "wbgetclaims"), ("entity", id)]).await?;
+        let picked = pick_values(&claims.claims);
+
+        let items: BTreeSet<&str> = picked.iter().flat_map(|(_, values)| values.iter().filter_map(|value| value.item())).collect();
+        let labels = if items.is_empty() {
+            HashMap::new()
+        } else {
+            // Without labels, facts naming an item are left out and the
+            // dates and numbers are still shown
+            self.labels(&items, lang).await.unwrap_or_else(|e| {
+                warn!("Failed to fetch Wikidata labels: {}", e);
+                HashMap::new()
+            })
+        };
+
+        let facts = picked
+            .into_iter()
+            .filter_map(|(label, values)| {
+                let values: Vec<String> = values.iter().filter_map(|value| value.display(&labels)).collect();
+                (!values.is_empty()).then(|| Fact { label: label.to_string(), value: values.join(", ") })
+            })
+            .collect();
+        Ok(InfoboxSection { facts })
+    }
+
+    // One wbgetentities call for the labels of every item in `ids`
+    async fn labels(&self, ids: &BTreeSet<&str>, lang: &str) -> Result<HashMap<String, String>, LookupError> {
+        let ids = ids.iter().copied().collect::<Vec<_>>().join("|");
+        let languages = if lang == language::DEFAULT_LANGUAGE {
+            lang.to_string()
+        } else {
+            format!("{}|{}", lang, language::DEFAULT_LANGUAGE)
+        };
+        let response: EntitiesResponse = self
+            .get(&[("action", "wbgetentities"), ("ids", &ids), ("props", "labels"), ("languages", &languages)])
+            .await?;
+        Ok(response
+            .entities
+            .into_iter()
+            .filter_map(|(id, entity)| {
+                let mut labels = entity.labels;
+                let label = labels.remove(lang).or_else(|| labels.remove(language::DEFAULT_LANGUAGE))?;
+                Some((id, label.value))
+            })
+            .collect())
+    }
+
+    async fn get<T: DeserializeOwned>(&self, params: &[(&str, &str)]) -> Result<T, LookupError> {
+        let request = || async {
+            let _permit = ratelimit::acquire(ratelimit::WIKIDATA).await;
+            let response = http::client()
+                .get(&self.endpoint)
+                .query(params)
+                .query(&[("format", "json")])
+                .send()
+                .await
+                .map_err(|e| http::request_error("wikidata", e))?;
+            http::check_transient(response, "Wikidata API")
+        };
+
+        let response = http::retry("wikidata", &http::RetryPolicy::default(), request).await?;
+        if !response.status().is_success() {
+            return Err(LookupError::UpstreamStatus { source: "Wikidata API".to_string(), status: response.status().as_u16() });
+        }
+        let body = response.text().await.map_err(|e| http::request_error("wikidata", e))?;
+        serde_json::from_str(&body).map_err(|_| LookupError::ParseError {
+            source: "wikidata".to_string(),
+            body_snippet: http::body_snippet(&body),
+        })
+    }
+}
+
+// The facts for the entity `wiki` is about, from the cache when they're
+// there. None when the article has no Wikidata item, Wikidata couldn't be
+// reached, or it has none of the properties shown; the lookup goes on
+// without them either way.
+pub async fn infobox(config: &Config, cache: Option<&Cache>, wiki: &WikipediaSection) -> Option<InfoboxSection> {
+    let id = match &wiki.wikidata_id {
+        Some(id) => id.clone(),
+        None => wikipedia::wikidata_id(&config.wikipedia.endpoint, &wiki.title, &wiki.language).await?,
+    };
+    let key = format!("{}:{}", wiki.language, id);
+    let cached = match cache {
+        Some(cache) => cache.get::<InfoboxSection>(CACHE, &key).await.filter(|c| c.is_fresh),
+        None => None,
+    };
+
+    let infobox = match cached {
+        Some(cached) => cached.value,
+        None => {
+            let source = WikidataSource { endpoint: config.wikidata.endpoint.clone() };
+            let infobox = match source.facts(&id, &wiki.language).await {
+                Ok(infobox) => infobox,
+                Err(e) => {
+                    warn!("Failed to fetch facts for {}: {}", id, e);
+                    return None;
+                }
+            };
+            if let Some(cache) = cache {
+                cache.put(CACHE, &key, &infobox).await;
+            }
+            infobox
+        }
+    };
+    Some(infobox).filter(|infobox| !infobox.facts.is_empty())
+}
+
+// The values worth showing for each property in `PROPERTIES` that has any:
+// the preferred statements when some are marked so, otherwise every one
+// that isn't deprecated
+fn pick_values(claims: &HashMap<String, Vec<Statement>>) -> Vec<(&'static str, Vec<&DataValue>)> {
+    PROPERTIES
+        .iter()
+        .filter_map(|&(property, label, many)| {
+            let statements: Vec<&Statement> = claims.get(property)?.iter().filter(|s| s.rank != Rank::Deprecated).collect();
+            let preferred = statements.iter().any(|s| s.rank == Rank::Preferred);
+            let values: Vec<&DataValue> = statements
+                .into_iter()
+                .filter(|s| !preferred || s.rank == Rank::Preferred)
+                .filter_map(|s| s.mainsnak.datavalue.as_ref())
+                .take(if many { MAX_VALUES } else { 1 })
+                .collect();
+            (!values.is_empty()).then_some((label, values))
+        })
+        .collect()
+}
+
+#[derive(Debug, Deserialize)]
+struct ClaimsResponse {
+    #[serde(default)]
+    claims: HashMap<String, Vec<Statement>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Statement {
+    mainsnak: Snak,
+    #[serde(default)]
+    rank: Rank,
+}
+
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Rank {
+    Preferred,
+    #[default]
+    Normal,
+    Deprecated,
+}
+
+// "Unknown value" and "no value" snaks come without a datavalue
+#[derive(Debug, Deserialize)]
+struct Snak {
+    datavalue: Option<DataValue>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "kebab-case")]
+enum DataValue {
+    String(String),
+    Monolingualtext { text: String },
+    WikibaseEntityid { id: String },
+    Quantity { amount: String, unit: String },
+    Time { time: String, precision: u8 },
+    #[serde(other)]
+    Other,
+}
+
+impl DataValue {
+    // The item this value needs the label of to be shown
+    fn item(&self) -> Option<&str> {
+        match self {
+            DataValue::WikibaseEntityid { id } => Some(id),
+            DataValue::Quantity { unit, .. } => unit_item(unit),
+            _ => None,
+        }
+    }
+
+    fn display(&self, labels: &HashMap<String, String>) -> Option<String> {
+        match self {
+            DataValue::String(text) | DataValue::Monolingualtext { text } => Some(text.clone()),
+            DataValue::WikibaseEntityid { id } => labels.get(id).cloned(),
+            DataValue::Quantity { amount, unit } => {
+                let amount = format_amount(amount);
+                match unit_item(unit) {
+                    Some(unit) => Some(format!("{} {}", amount, labels.get(unit)?)),
+                    None => Some(amount),
+                }
+            }
+            DataValue::Time { time, precision } => format_time(time, *precision),
+            DataValue::Other => None,
+        }
+    }
+}
+
+// Units are item URLs, or "1" for a plain count
+fn unit_item(unit: &str) -> Option<&str> {
+    unit.rsplit_once('/').map(|(_, id)| id).filter(|id| id.starts_with('Q'))
+}
+
+// "+8336817" as "8,336,817"; fractions are kept as Wikidata gives them
+fn format_amount(amount: &str) -> String {
+    let amount = amount.trim_start_matches('+');
+    let (sign, digits) = match amount.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", amount),
+    };
+    let (whole, fraction) = digits.split_once('.').map_or((digits, None), |(whole, fraction)| (whole, Some(fraction)));
+    let whole = whole.parse().map_or_else(|_| whole.to_string(), crate::format::group_digits);
+    match fraction {
+        Some(fraction) => format!("{}{}.{}", sign, whole, fraction),
+        None => format!("{}{}", sign, whole),
+    }
+}
+
+// "+1879-03-14T00:00:00Z" at day precision (11) as "14 March 1879", at
+// month precision (10) as "March 1879", and as just the year otherwise.
+// Years before the common era are written "44 BC".
+fn format_time(time: &str, precision: u8) -> Option<String> {
+    let (bc, rest) = match time.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, time.trim_start_matches('+')),
+    };
+    let mut parts = rest.split(['-', 'T']);
+    let year: u64 = parts.next()?.parse().ok()?;
+    let month: usize = parts.next()?.parse().ok()?;
+    let day: u32 = parts.next()?.parse().ok()?;
+    let year = if bc { format!("{} BC", year) } else { year.to_string() };
+
+    let month_name = month.checked_sub(1).and_then(|i| MONTHS.get(i));
+    Some(match (precision, month_name) {
+        (11.., Some(month)) if day > 0 => format!("{} {} {}", day, month, year),
+        (10.., Some(month)) => format!("{} {}", month, year),
+        _ => year,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct EntitiesResponse {
+    #[serde(default)]
+    entities: HashMap<String, Entity>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Entity {
+    #[serde(default)]
+    labels: HashMap<String, Label>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Label {
+    value: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_values() {
+        assert_eq!(format_time("+1879-03-14T00:00:00Z", 11).as_deref(), Some("14 March 1879"));
+        assert_eq!(format_time("+1879-03-00T00:00:00Z", 10).as_deref(), Some("March 1879"));
+        assert_eq!(format_time("+1879-00-00T00:00:00Z", 9).as_deref(), Some("1879"));
+        assert_eq!(format_time("-0044-03-15T00:00:00Z", 11).as_deref(), Some("15 March 44 BC"));
+        assert_eq!(format_time("not a date", 11), None);
+
+        assert_eq!(format_amount("+8336817"), "8,336,817");
+        assert_eq!(format_amount("-12.75"), "-12.75");
+        assert_eq!(format_amount("+1234.5"), "1,234.5");
+    }
+
+    #[test]
+    fn test_pick_values() {
+        let claims: ClaimsResponse = serde_json::from_value(serde_json::json!({
+            "claims": {
+                "P1082": [
+                    { "mainsnak": { "datavalue": { "type": "quantity", "value": { "amount": "+2000000", "unit": "1" } } } },
+                    {
+                        "rank": "preferred",
+                        "mainsnak": { "datavalue": { "type": "quantity", "value": { "amount": "+2145906", "unit": "1" } } }
+                    }
+                ],
+                "P106": [
+                    { "mainsnak": { "datavalue": { "type": "wikibase-entityid", "value": { "id": "Q169470" } } } },
+                    { "rank": "deprecated", "mainsnak": { "datavalue": { "type": "wikibase-entityid", "value": { "id": "Q1" } } } },
+                    { "mainsnak": { "snaktype": "somevalue" } },
+                    { "mainsnak": { "datavalue": { "type": "wikibase-entityid", "value": { "id": "Q121594" } } } }
+                ],
+                "P18": [{ "mainsnak": { "datavalue": { "type": "string", "value": "Paris.jpg" } } }]
+            }
+        }))
+        .unwrap();
+
+        let picked = pick_values(&claims.claims);
+        let labels = HashMap::from([("Q169470".to_string(), "physicist".to_string())]);
+        let shown: Vec<(&str, Vec<Option<String>>)> = picked
+            .iter()
+            .map(|(label, values)| (*label, values.iter().map(|value| value.display(&labels)).collect()))
+            .collect();
+        assert_eq!(
+            shown,
+            vec![
+                ("Occupation", vec![Some("physicist".to_string()), None]),
+                ("Population", vec![Some("2,145,906".to_string())]),
+            ]
+        );
+    }
+}
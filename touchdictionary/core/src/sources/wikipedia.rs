@@ -0,0 +1,1177 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+
+use async_trait::async_trait;
+use percent_encoding::percent_decode_str;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::cache::{Cache, Validator};
+use crate::error::LookupError;
+use crate::http;
+use crate::language;
+use crate::ratelimit;
+use crate::lookup::{ContentType, RelatedPage, WikipediaSection};
+use crate::sources::wiktionary::strip_html;
+use crate::sources::{Revalidation, Source, SourceError, SourceResult};
+
+// REST API base URL; `{lang}` is replaced by the edition's language code
+pub const DEFAULT_ENDPOINT: &str = "https://{lang}.wikipedia.org/api/rest_v1";
+
+// Default cap on the related articles shown with a summary
+pub const DEFAULT_MAX_RELATED: usize = 5;
+
+// Full articles are handed out a few sections at a time, so a long article
+// doesn't reach the webview as one multi-megabyte payload
+pub const DEFAULT_ARTICLE_PAGE_SIZE: usize = 5;
+pub const MAX_ARTICLE_PAGE_SIZE: usize = 20;
+
+// Redirects followed when resolving one by hand, in case they loop
+const MAX_REDIRECTS: usize = 5;
+
+// Search results looked through when a qualified title like "Mercury
+// (element)" doesn't exist as written
+const QUALIFIED_SEARCH_LIMIT: usize = 10;
+
+// Cache namespace for parsed full articles, keyed by language and title
+const ARTICLE_CACHE: &str = "wikipedia-article";
+
+// Sections made of citations and link lists rather than prose
+const SKIPPED_SECTIONS: &[&str] = &[
+    "See also",
+    "Notes",
+    "References",
+    "Citations",
+    "Sources",
+    "Footnotes",
+    "Further reading",
+    "Bibliography",
+    "External links",
+];
+
+// Elements an article section keeps
+const ALLOWED_ELEMENTS: &[&str] = &["p", "ul", "ol", "li", "b", "i", "br", "a"];
+// Elements dropped together with everything inside them
+const DROPPED_ELEMENTS: &[&str] = &["table", "style", "script", "sup", "figure", "math", "noscript", "audio", "video"];
+const DROPPED_CLASSES: &[&str] = &[
+    "shortdescription",
+    "hatnote",
+    "infobox",
+    "navbox",
+    "sidebar",
+    "thumb",
+    "reference",
+    "reflist",
+    "mw-references-wrap",
+    "mw-editsection",
+    "mw-empty-elt",
+    "metadata",
+    "noprint",
+];
+const VOID_ELEMENTS: &[&str] = &["br", "img", "hr", "wbr", "link", "meta", "input", "source"];
+
+// Namespaces whose pages aren't articles worth opening from a link
+const NON_ARTICLE_NAMESPACES: &[&str] = &["File", "Image", "Help", "Wikipedia", "Template", "Category", "Special", "Portal", "Talk"];
+
+// Wikipedia REST summary endpoint. Runs for every content type: the primary
+// source for entities and a supplement for plain words.
+pub struct WikipediaSource {
+    pub max_related: usize,
+    pub endpoint: String,
+}
+
+impl Default for WikipediaSource {
+    fn default() -> Self {
+        WikipediaSource { max_related: DEFAULT_MAX_RELATED, endpoint: DEFAULT_ENDPOINT.to_string() }
+    }
+}
+
+#[async_trait]
+impl Source for WikipediaSource {
+    fn name(&self) -> &str {
+        "wikipedia"
+    }
+
+    fn applies_to(&self, _content_type: &ContentType) -> bool {
+        true
+    }
+
+    // Every known language has its own Wikipedia edition
+    fn supports_language(&self, lang: &str) -> bool {
+        language::is_known(lang)
+    }
+
+    fn accepts_lemma(&self) -> bool {
+        false
+    }
+
+    fn probe_query(&self) -> &str {
+        "Earth"
+    }
+
+    async fn fetch(&self, query: &str, lang: &str) -> Result<SourceResult, SourceError> {
+        self.fetch_validated(query, lang).await.map(|(result, _)| result)
+    }
+
+    // The summary's ETag stands for the whole section; related articles
+    // shifting around on their own isn't worth a refetch
+    async fn fetch_validated(&self, query: &str, lang: &str) -> Result<(SourceResult, Option<Validator>), SourceError> {
+        let (section, validator) = get_wikipedia_article(&self.endpoint, query, lang, self.max_related).await?;
+        Ok((SourceResult::Wikipedia(section), validator))
+    }
+
+    async fn revalidate(&self, query: &str, lang: &str, validator: &Validator) -> Result<Revalidation, SourceError> {
+        revalidate_summary(&self.endpoint, query, lang, validator).await
+    }
+}
+
+// The summary plus related articles, fetched side by side. Related articles
+// are a bonus: if that call fails the summary is returned without them.
+async fn get_wikipedia_article(
+    endpoint: &str,
+    query: &str,
+    lang: &str,
+    max_related: usize,
+) -> Result<(WikipediaSection, Option<Validator>), SourceError> {
+    let (summary, related) = tokio::join!(
+        get_wikipedia_summary(endpoint, query, lang),
+        get_related_pages(endpoint, query, lang, max_related),
+    );
+
+    let (mut section, validator) = match summary {
+        Err(e) if e.is_not_found() => return get_missing_article(endpoint, query, lang, max_related).await,
+        summary => summary?,
+    };
+    section.related = related;
+    Ok((section, validator))
+}
+
+// A title without a summary of its own. The REST API doesn't follow every
+// redirect, so the action API is asked where it leads before giving up; a
+// qualified title that leads nowhere is then searched for.
+async fn get_missing_article(
+    endpoint: &str,
+    query: &str,
+    lang: &str,
+    max_related: usize,
+) -> Result<(WikipediaSection, Option<Validator>), SourceError> {
+    if let Some(target) = resolve_redirect(endpoint, query, lang).await {
+        info!("Following the redirect from '{}' to '{}'", query, target);
+        let (summary, related) = tokio::join!(
+            get_wikipedia_summary(endpoint, &target, lang),
+            get_related_pages(endpoint, &target, lang, max_related),
+        );
+        let (mut section, validator) = summary?;
+        section.related = related;
+        section.redirected_from = Some(query.to_string());
+        return Ok((section, validator));
+    }
+
+    match split_qualifier(query) {
+        Some((base, qualifier)) => get_qualified_article(endpoint, query, base, qualifier, lang, max_related).await,
+        None => Err(SourceError::NotFound { query: query.to_string() }),
+    }
+}
+
+// Where `title` redirects to, following a chain of redirects to its end.
+// None when it isn't a redirect to an existing article, or the action API
+// couldn't say; that's logged and the lookup reports nothing found.
+async fn resolve_redirect(endpoint: &str, title: &str, lang: &str) -> Option<String> {
+    let url = action_api_url(endpoint, lang);
+    let request = || async {
+        let _permit = ratelimit::acquire(ratelimit::WIKIPEDIA).await;
+        let response = http::client()
+            .get(&url)
+            .query(&[
+                ("action", "query"),
+                ("titles", title),
+                ("redirects", "1"),
+                ("format", "json"),
+                ("formatversion", "2"),
+            ])
+            .send()
+            .await
+            .map_err(|e| http::request_error("wikipedia", e))?;
+        http::check_transient(response, "Wikipedia API")
+    };
+
+    let body = match http::retry("wikipedia", &http::RetryPolicy::default(), request).await {
+        Ok(response) if response.status().is_success() => response.text().await.ok()?,
+        Ok(response) => {
+            warn!("Wikipedia redirect query returned status: {}", response.status());
+            return None;
+        }
+        Err(e) => {
+            warn!("Wikipedia redirect query failed: {}", e);
+            return None;
+        }
+    };
+    match redirect_target(&body, title) {
+        Ok(target) => target,
+        Err(e) => {
+            warn!("Failed to parse Wikipedia redirect query: {}", e);
+            None
+        }
+    }
+}
+
+// The end of the redirect chain starting at `title` in an action API
+// response, if it's an article that exists
+fn redirect_target(body: &str, title: &str) -> Result<Option<String>, serde_json::Error> {
+    let response: RedirectApiResponse = serde_json::from_str(body)?;
+    let query = response.query;
+    let mut current = query
+        .normalized
+        .iter()
+        .find(|step| step.from == title)
+        .map_or(title, |step| step.to.as_str())
+        .to_string();
+    let start = current.clone();
+    for _ in 0..MAX_REDIRECTS {
+        match query.redirects.iter().find(|step| step.from == current) {
+            Some(step) => current = step.to.clone(),
+            None => break,
+        }
+    }
+    if current == start {
+        return Ok(None);
+    }
+    let exists = query.pages.iter().any(|page| page.title == current && !page.missing);
+    Ok(exists.then_some(current))
+}
+
+// The Wikidata item behind the article `title`, from the action API, for a
+// section cached before summaries carried it. None when the article has
+// none or the API couldn't say.
+pub async fn wikidata_id(endpoint: &str, title: &str, lang: &str) -> Option<String> {
+    let url = action_api_url(endpoint, lang);
+    let _permit = ratelimit::acquire(ratelimit::WIKIPEDIA).await;
+    let response = http::client()
+        .get(&url)
+        .query(&[
+            ("action", "query"),
+            ("prop", "pageprops"),
+            ("ppprop", "wikibase_item"),
+            ("titles", title),
+            ("format", "json"),
+            ("formatversion", "2"),
+        ])
+        .send()
+        .await;
+    let body = match response {
+        Ok(response) if response.status().is_success() => response.text().await.ok()?,
+        Ok(response) => {
+            warn!("Wikipedia page props query returned status: {}", response.status());
+            return None;
+        }
+        Err(e) => {
+            warn!("Wikipedia page props query failed: {}", e);
+            return None;
+        }
+    };
+    let response: PagePropsApiResponse = serde_json::from_str(&body)
+        .map_err(|e| warn!("Failed to parse Wikipedia page props query: {}", e))
+        .ok()?;
+    response.query.pages.into_iter().find_map(|page| page.pageprops?.wikibase_item)
+}
+
+// `query` when the summary Wikipedia answered with is of another article
+// because it followed a redirect. Only the first letter of a title is
+// case-insensitive on Wikipedia, but a lookup is lowercased anyway, so a
+// difference in case alone isn't worth pointing out.
+fn redirected_from(query: &str, title: &str) -> Option<String> {
+    let key = |title: &str| title.replace('_', " ").trim().to_lowercase();
+    (key(query) != key(title)).then(|| query.to_string())
+}
+
+// A qualified title that doesn't exist as written, like "Mercury (element)"
+// for "Mercury (chemical element)": the best search result naming both the
+// base term and the qualifier stands in for it
+async fn get_qualified_article(
+    endpoint: &str,
+    query: &str,
+    base: &str,
+    qualifier: &str,
+    lang: &str,
+    max_related: usize,
+) -> Result<(WikipediaSection, Option<Validator>), SourceError> {
+    let Some(title) = search_qualified(endpoint, query, base, qualifier, lang).await? else {
+        info!("No article about '{}' matches '{}'", base, qualifier);
+        return Err(SourceError::NotFound { query: query.to_string() });
+    };
+    info!("Resolved '{}' to the article '{}'", query, title);
+
+    let (summary, related) = tokio::join!(
+        get_wikipedia_summary(endpoint, &title, lang),
+        get_related_pages(endpoint, &title, lang, max_related),
+    );
+    let (mut section, validator) = summary?;
+    section.related = related;
+    section.resolved_title = Some(title);
+    Ok((section, validator))
+}
+
+// "Python (programming language)" as ("Python", "programming language")
+fn split_qualifier(title: &str) -> Option<(&str, &str)> {
+    let (base, qualifier) = title.strip_suffix(')')?.rsplit_once(" (")?;
+    let (base, qualifier) = (base.trim(), qualifier.trim());
+    (!base.is_empty() && !qualifier.is_empty()).then_some((base, qualifier))
+}
+
+// The MediaWiki action API beside the REST endpoint: `.../api/rest_v1`
+// becomes `.../w/api.php`
+fn action_api_url(endpoint: &str, lang: &str) -> String {
+    let base = endpoint.replace("{lang}", lang);
+    let base = base.trim_end_matches('/');
+    format!("{}/w/api.php", base.strip_suffix("/api/rest_v1").unwrap_or(base))
+}
+
+async fn search_qualified(
+    endpoint: &str,
+    query: &str,
+    base: &str,
+    qualifier: &str,
+    lang: &str,
+) -> Result<Option<String>, SourceError> {
+    let url = action_api_url(endpoint, lang);
+    let search = format!("{} {}", base, qualifier);
+    let limit = QUALIFIED_SEARCH_LIMIT.to_string();
+    let request = || async {
+        let _permit = ratelimit::acquire(ratelimit::WIKIPEDIA).await;
+        let response = http::client()
+            .get(&url)
+            .query(&[
+                ("action", "query"),
+                ("list", "search"),
+                ("srsearch", search.as_str()),
+                ("srlimit", limit.as_str()),
+                ("srprop", ""),
+                ("format", "json"),
+            ])
+            .send()
+            .await
+            .map_err(|e| http::request_error("wikipedia", e))?;
+        http::check_transient(response, "Wikipedia API")
+    };
+
+    let response = http::retry("wikipedia", &http::RetryPolicy::default(), request).await?;
+    if !response.status().is_success() {
+        error!("Wikipedia search returned status: {}", response.status());
+        return Err(SourceError::UpstreamStatus {
+            source: "Wikipedia API".to_string(),
+            status: response.status().as_u16(),
+        });
+    }
+
+    let body = response.text().await.map_err(|e| http::request_error("wikipedia", e))?;
+    pick_qualified(&body, query, base, qualifier)
+}
+
+// The first search result whose title has both `base` and `qualifier` in
+// it, other than the title that was already missing
+fn pick_qualified(body: &str, query: &str, base: &str, qualifier: &str) -> Result<Option<String>, SourceError> {
+    let response: SearchApiResponse = serde_json::from_str(body).map_err(|e| {
+        error!("Failed to parse Wikipedia search response: {}", e);
+        SourceError::ParseError {
+            source: "wikipedia".to_string(),
+            body_snippet: http::body_snippet(body),
+        }
+    })?;
+
+    let (base, qualifier) = (base.to_lowercase(), qualifier.to_lowercase());
+    Ok(response.query.search.into_iter().map(|hit| hit.title).find(|title| {
+        let lower = title.to_lowercase();
+        lower.contains(&base) && lower.contains(&qualifier) && !title.eq_ignore_ascii_case(query)
+    }))
+}
+
+// Conditional request for the summary: 304 when the cached one is current
+async fn revalidate_summary(
+    endpoint: &str,
+    query: &str,
+    lang: &str,
+    validator: &Validator,
+) -> Result<Revalidation, SourceError> {
+    let url = summary_url(endpoint, lang, query);
+    let _permit = ratelimit::acquire(ratelimit::WIKIPEDIA).await;
+    let mut request = http::client().get(&url);
+    if let Some(etag) = &validator.etag {
+        request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = &validator.last_modified {
+        request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+    }
+
+    let response = request.send().await.map_err(|e| http::request_error("wikipedia", e))?;
+    if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+        info!("Summary for '{}' unchanged since it was cached", query);
+        Ok(Revalidation::NotModified)
+    } else {
+        Ok(Revalidation::Modified)
+    }
+}
+
+// REST endpoint `kind` ("summary", "related") for the page titled `title`
+fn page_url(endpoint: &str, lang: &str, kind: &str, title: &str) -> String {
+    let base = endpoint.replace("{lang}", lang);
+    http::wiki_title_url(&format!("{}/page/{}", base.trim_end_matches('/'), kind), title)
+}
+
+// The summary endpoint, asked to answer a redirect with its target's summary
+// rather than leave it to the client
+fn summary_url(endpoint: &str, lang: &str, title: &str) -> String {
+    format!("{}?redirect=true", page_url(endpoint, lang, "summary", title))
+}
+
+async fn get_wikipedia_summary(
+    endpoint: &str,
+    query: &str,
+    lang: &str,
+) -> Result<(WikipediaSection, Option<Validator>), SourceError> {
+    info!("Fetching summary for '{}' from {} Wikipedia API", query, lang);
+    
+    let url = summary_url(endpoint, lang, query);
+    
+    let request = || async {
+        let _permit = ratelimit::acquire(ratelimit::WIKIPEDIA).await;
+        let response = http::client()
+            .get(&url)
+            .send()
+            .await
+            .map_err(|e| http::request_error("wikipedia", e))?;
+        http::check_transient(response, "Wikipedia API")
+    };
+
+    match http::retry("wikipedia", &http::RetryPolicy::default(), request).await {
+        Ok(response) => {
+            if response.status().is_success() {
+                let validator = Validator::from_headers(response.headers());
+                let json_text = response.text().await
+                    .map_err(|e| http::request_error("wikipedia", e))?;
+
+                match serde_json::from_str::<WikipediaApiResponse>(&json_text) {
+                    Ok(data) => {
+                        if data.extract.is_empty() || data.extract.to_lowercase().contains("may refer to") {
+                            warn!("Disambiguation page or no content for '{}'", query);
+                            return Err(SourceError::NotFound { query: query.to_string() });
+                        }
+                        
+                        info!("Successfully fetched summary for '{}'", query);
+                        
+                        // Parse the summary into paragraphs for better formatting
+                        let paragraphs: Vec<String> = data.extract
+                            .split("\n")
+                            .filter(|p| !p.trim().is_empty())
+                            .map(|p| p.trim().to_string())
+                            .collect();
+                        
+                        let redirected_from = redirected_from(query, data.titles.as_ref().map_or(&data.title, |t| &t.normalized));
+                        Ok((WikipediaSection {
+                            title: data.title,
+                            summary: data.extract,
+                            paragraphs,
+                            image_url: data.thumbnail.map(|t| t.source),
+                            url: data.content_urls.desktop.page,
+                            language: lang.to_string(),
+                            related: Vec::new(),
+                            translated_summary: None,
+                            changed_since_cache: false,
+                            is_truncated: false,
+                            resolved_title: None,
+                            canonical_title: data.titles.map(|t| t.canonical),
+                            redirected_from,
+                            wikidata_id: data.wikibase_item,
+                        }, validator))
+                    }
+                    Err(e) => {
+                        error!("Failed to parse Wikipedia response: {}", e);
+                        Err(SourceError::ParseError {
+                            source: "wikipedia".to_string(),
+                            body_snippet: http::body_snippet(&json_text),
+                        })
+                    }
+                }
+            } else if response.status() == 404 {
+                info!("Page not found for '{}' (404)", query);
+                Err(SourceError::NotFound { query: query.to_string() })
+            } else {
+                error!("Wikipedia API returned status: {}", response.status());
+                Err(SourceError::UpstreamStatus {
+                    source: "Wikipedia API".to_string(),
+                    status: response.status().as_u16(),
+                })
+            }
+        }
+        Err(e) => {
+            error!("Wikipedia API request failed: {}", e);
+            Err(e)
+        }
+    }
+}
+
+async fn get_related_pages(endpoint: &str, query: &str, lang: &str, max_related: usize) -> Vec<RelatedPage> {
+    if max_related == 0 {
+        return Vec::new();
+    }
+
+    let url = page_url(endpoint, lang, "related", query);
+    let request = || async {
+        let _permit = ratelimit::acquire(ratelimit::WIKIPEDIA).await;
+        let response = http::client()
+            .get(&url)
+            .send()
+            .await
+            .map_err(|e| http::request_error("wikipedia", e))?;
+        http::check_transient(response, "Wikipedia API")
+    };
+
+    let response = match http::retry("wikipedia", &http::RetryPolicy::default(), request).await {
+        Ok(response) if response.status().is_success() => response,
+        Ok(response) => {
+            warn!("Related pages returned status: {}", response.status());
+            return Vec::new();
+        }
+        Err(e) => {
+            warn!("Related pages request failed: {}", e);
+            return Vec::new();
+        }
+    };
+
+    let body = match response.text().await {
+        Ok(body) => body,
+        Err(e) => {
+            warn!("Failed to read related pages: {}", e);
+            return Vec::new();
+        }
+    };
+    match parse_related(&body, max_related) {
+        Ok(related) => related,
+        Err(e) => {
+            warn!("Failed to parse related pages: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+fn parse_related(body: &str, max_related: usize) -> Result<Vec<RelatedPage>, serde_json::Error> {
+    let response: RelatedApiResponse = serde_json::from_str(body)?;
+    Ok(response
+        .pages
+        .into_iter()
+        .take(max_related)
+        .map(|page| RelatedPage {
+            title: page.title,
+            description: page.description,
+            thumbnail_url: page.thumbnail.map(|t| t.source),
+            url: page.content_urls.desktop.page,
+        })
+        .collect())
+}
+
+// One heading's worth of a full article
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArticleSection {
+    pub heading: String,
+    // 1 for the introduction under the title, 2 for top-level sections, ...
+    pub level: u8,
+    // Only paragraphs, lists, bold, italics and line breaks survive. Links
+    // to other articles become `<a data-title="...">` without an href, for
+    // the app to intercept; other links keep no attributes.
+    pub html: String,
+    // The same content as plain text, list items prefixed with "- "
+    pub text: String,
+}
+
+// A slice of an article's sections, starting at the requested offset
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArticlePage {
+    pub title: String,
+    pub url: String,
+    pub sections: Vec<ArticleSection>,
+    pub total_sections: usize,
+    // Offset of the next page; None on the last one
+    pub next_offset: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Article {
+    title: String,
+    sections: Vec<ArticleSection>,
+}
+
+// Up to `limit` sections of the article titled `title`, from `offset` on.
+// The whole article is fetched and parsed once and cached, so paging
+// through it doesn't refetch it.
+pub async fn get_article_sections(title: &str, lang: &str, offset: usize, limit: usize) -> Result<ArticlePage, LookupError> {
+    let key = format!("{}:{}", lang, title);
+    let cache = Cache::open_default();
+    let cached = match &cache {
+        Some(cache) => cache.get::<Article>(ARTICLE_CACHE, &key).await.filter(|c| c.is_fresh),
+        None => None,
+    };
+
+    let article = match cached {
+        Some(cached) => cached.value,
+        None => {
+            let article = fetch_article(title, lang).await?;
+            if let Some(cache) = &cache {
+                cache.put(ARTICLE_CACHE, &key, &article).await;
+            }
+            article
+        }
+    };
+    Ok(paginate(article, lang, offset, limit))
+}
+
+// The first `max` entries of the "Events" list in the article for `year`,
+// which is split into months or regions on most years' pages
+pub async fn year_events(year: i32, lang: &str, max: usize) -> Result<Vec<String>, LookupError> {
+    let page = get_article_sections(&year.to_string(), lang, 0, MAX_ARTICLE_PAGE_SIZE).await?;
+    Ok(events(&page.sections, max))
+}
+
+fn events(sections: &[ArticleSection], max: usize) -> Vec<String> {
+    let Some(start) = sections.iter().position(|s| s.heading.eq_ignore_ascii_case("Events")) else {
+        return Vec::new();
+    };
+    let level = sections[start].level;
+    let subsections = sections[start + 1..].iter().take_while(|s| s.level > level);
+    std::iter::once(&sections[start])
+        .chain(subsections)
+        .flat_map(|s| s.text.lines())
+        .filter_map(|line| line.strip_prefix("- "))
+        .map(str::trim)
+        .filter(|event| !event.is_empty())
+        .take(max)
+        .map(str::to_string)
+        .collect()
+}
+
+fn paginate(article: Article, lang: &str, offset: usize, limit: usize) -> ArticlePage {
+    let limit = limit.clamp(1, MAX_ARTICLE_PAGE_SIZE);
+    let total_sections = article.sections.len();
+    let next = offset.saturating_add(limit);
+
+    ArticlePage {
+        url: http::wiki_title_url(&format!("https://{}.wikipedia.org/wiki", lang), &article.title),
+        title: article.title,
+        sections: article.sections.into_iter().skip(offset).take(limit).collect(),
+        total_sections,
+        next_offset: (next < total_sections).then_some(next),
+    }
+}
+
+async fn fetch_article(title: &str, lang: &str) -> Result<Article, LookupError> {
+    info!("Fetching full article '{}' from {} Wikipedia", title, lang);
+
+    let url = format!("https://{}.wikipedia.org/w/api.php", lang);
+    let request = || async {
+        let _permit = ratelimit::acquire(ratelimit::WIKIPEDIA).await;
+        let response = http::client()
+            .get(&url)
+            .query(&[
+                ("action", "parse"),
+                ("page", title),
+                ("prop", "text"),
+                ("format", "json"),
+                ("formatversion", "2"),
+                ("redirects", "1"),
+                ("disableeditsection", "1"),
+                ("disabletoc", "1"),
+            ])
+            .send()
+            .await
+            .map_err(|e| http::request_error("wikipedia", e))?;
+        http::check_transient(response, "Wikipedia API")
+    };
+
+    let response = http::retry("wikipedia", &http::RetryPolicy::default(), request).await?;
+    if !response.status().is_success() {
+        error!("Wikipedia API returned status: {}", response.status());
+        return Err(LookupError::UpstreamStatus {
+            source: "Wikipedia API".to_string(),
+            status: response.status().as_u16(),
+        });
+    }
+
+    let body = response.text().await.map_err(|e| http::request_error("wikipedia", e))?;
+    parse_article(&body)?.ok_or_else(|| LookupError::NotFound { query: title.to_string() })
+}
+
+// `None` when the page doesn't exist
+fn parse_article(body: &str) -> Result<Option<Article>, LookupError> {
+    let response: ParseResponse = serde_json::from_str(body).map_err(|e| {
+        error!("Failed to parse Wikipedia article: {}", e);
+        LookupError::ParseError {
+            source: "wikipedia".to_string(),
+            body_snippet: http::body_snippet(body),
+        }
+    })?;
+    let Some(page) = response.parse else {
+        return Ok(None);
+    };
+
+    let headings = article_headings(&page.text);
+    let lead_end = headings.first().map_or(page.text.len(), |h| h.start);
+    let mut sections = Vec::new();
+    let lead = article_section(page.title.clone(), 1, &page.text[..lead_end]);
+    if !lead.text.is_empty() {
+        sections.push(lead);
+    }
+
+    // Skipping a section skips its subsections too
+    let mut skipping_below = None;
+    for (i, heading) in headings.iter().enumerate() {
+        if skipping_below.is_some_and(|level| heading.level > level) {
+            continue;
+        }
+        skipping_below = None;
+        if SKIPPED_SECTIONS.contains(&heading.text.as_str()) {
+            skipping_below = Some(heading.level);
+            continue;
+        }
+        let end = headings.get(i + 1).map_or(page.text.len(), |next| next.start);
+        sections.push(article_section(heading.text.clone(), heading.level, &page.text[heading.body_start..end]));
+    }
+
+    Ok(Some(Article { title: page.title, sections }))
+}
+
+fn article_section(heading: String, level: u8, html: &str) -> ArticleSection {
+    let html = sanitize_html(html);
+    let text = html_to_text(&html);
+    ArticleSection { heading, level, html, text }
+}
+
+struct ArticleHeading {
+    start: usize,
+    body_start: usize,
+    level: u8,
+    text: String,
+}
+
+// Every `<h2>` to `<h6>` in the page, in document order
+fn article_headings(html: &str) -> Vec<ArticleHeading> {
+    let mut headings = Vec::new();
+    let mut offset = 0;
+
+    while let Some(found) = html[offset..].find("<h") {
+        let start = offset + found;
+        offset = start + 2;
+
+        let level = match html.as_bytes().get(start + 2) {
+            Some(digit @ b'2'..=b'6') => digit - b'0',
+            _ => continue,
+        };
+        let Some(open_end) = html[start..].find('>').map(|i| start + i + 1) else {
+            break;
+        };
+        let close = format!("</h{}>", level);
+        let Some(close_start) = html[open_end..].find(&close).map(|i| open_end + i) else {
+            break;
+        };
+
+        let body_start = close_start + close.len();
+        headings.push(ArticleHeading {
+            start,
+            body_start,
+            level,
+            text: strip_html(&html[open_end..close_start]),
+        });
+        offset = body_start;
+    }
+
+    headings
+}
+
+// Article HTML cut down to paragraphs, lists, emphasis and article links,
+// with the page's boilerplate taken out first
+fn sanitize_html(html: &str) -> String {
+    static SANITIZER: OnceLock<ammonia::Builder<'static>> = OnceLock::new();
+    let sanitizer = SANITIZER.get_or_init(|| {
+        let mut builder = ammonia::Builder::empty();
+        builder
+            .tags(ALLOWED_ELEMENTS.iter().copied().collect())
+            .clean_content_tags(DROPPED_ELEMENTS.iter().copied().collect())
+            .tag_attributes(HashMap::from([("a", HashSet::from(["data-title"]))]))
+            .generic_attributes(HashSet::new())
+            .link_rel(None)
+            // A link keeps its target only when that's an article
+            .attribute_filter(|_, _, value| article_title(value).map(Cow::Owned));
+        builder
+    });
+    sanitizer.clean(&strip_boilerplate(html)).to_string()
+}
+
+// Drops elements with one of `DROPPED_CLASSES`, and moves each link's
+// href into the `data-title` the sanitizer looks at. Everything else is
+// left for the sanitizer.
+fn strip_boilerplate(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    // Element being dropped and how deeply it's nested in itself
+    let mut dropping = String::new();
+    let mut drop_depth = 0;
+
+    while let Some(lt) = rest.find('<') {
+        if drop_depth == 0 {
+            out.push_str(&rest[..lt]);
+        }
+        rest = &rest[lt..];
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        let Some(gt) = rest.find('>') else {
+            rest = "";
+            break;
+        };
+        let raw = &rest[..gt + 1];
+        let tag = Tag::parse(&rest[1..gt]);
+        rest = &rest[gt + 1..];
+
+        let void = VOID_ELEMENTS.contains(&tag.name.as_str());
+        if drop_depth > 0 {
+            if tag.name == dropping && !void {
+                if tag.closing {
+                    drop_depth -= 1;
+                } else {
+                    drop_depth += 1;
+                }
+            }
+            continue;
+        }
+        if !tag.closing && !void && tag.is_boilerplate() {
+            dropping = tag.name;
+            drop_depth = 1;
+            continue;
+        }
+
+        match tag.attribute("href") {
+            Some(href) if tag.name == "a" && !tag.closing => out.push_str(&format!("<a data-title=\"{}\">", href)),
+            _ => out.push_str(raw),
+        }
+    }
+    if drop_depth == 0 {
+        out.push_str(rest);
+    }
+    out
+}
+
+struct Tag<'a> {
+    name: String,
+    closing: bool,
+    attributes: &'a str,
+}
+
+impl<'a> Tag<'a> {
+    fn parse(inner: &'a str) -> Self {
+        let (closing, inner) = match inner.strip_prefix('/') {
+            Some(rest) => (true, rest),
+            None => (false, inner),
+        };
+        let name_end = inner.find(|c: char| c.is_whitespace() || c == '/').unwrap_or(inner.len());
+        Tag {
+            name: inner[..name_end].to_ascii_lowercase(),
+            closing,
+            attributes: &inner[name_end..],
+        }
+    }
+
+    fn attribute(&self, name: &str) -> Option<&'a str> {
+        let start = self.attributes.find(&format!(" {}=\"", name))? + name.len() + 3;
+        let end = self.attributes[start..].find('"')?;
+        Some(&self.attributes[start..start + end])
+    }
+
+    fn is_boilerplate(&self) -> bool {
+        self.attribute("class")
+            .is_some_and(|classes| classes.split_whitespace().any(|class| DROPPED_CLASSES.contains(&class)))
+    }
+}
+
+// Title of the article a link's href points to, if it points to one
+fn article_title(href: &str) -> Option<String> {
+    let path = href.strip_prefix("/wiki/").or_else(|| href.strip_prefix("./"))?;
+    let path = path.split('#').next().unwrap_or_default();
+    let title = percent_decode_str(path).decode_utf8().ok()?.replace('_', " ");
+    let namespace = title.split_once(':').map(|(namespace, _)| namespace);
+    if title.is_empty() || namespace.is_some_and(|namespace| NON_ARTICLE_NAMESPACES.contains(&namespace)) {
+        return None;
+    }
+    Some(title)
+}
+
+// Sanitized HTML as lines of text, one per paragraph or list item
+fn html_to_text(html: &str) -> String {
+    html.replace("<li>", "\n- ")
+        .replace("</li>", "\n")
+        .replace("<p>", "\n")
+        .replace("</p>", "\n")
+        .replace("<br>", "\n")
+        .lines()
+        .map(strip_html)
+        .filter(|line| !line.is_empty() && line != "-")
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// API Response Structures
+
+#[derive(Debug, Deserialize)]
+struct ParseResponse {
+    parse: Option<ParsedArticle>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ParsedArticle {
+    title: String,
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct WikipediaApiResponse {
+    title: String,
+    #[serde(default)]
+    titles: Option<Titles>,
+    extract: String,
+    thumbnail: Option<Thumbnail>,
+    content_urls: ContentUrls,
+    #[serde(default)]
+    wikibase_item: Option<String>,
+}
+
+// "United_States" and "United States"
+#[derive(Debug, Deserialize)]
+struct Titles {
+    canonical: String,
+    normalized: String,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+struct Thumbnail {
+    source: String,
+    width: u32,
+    height: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct ContentUrls {
+    desktop: DesktopUrls,
+}
+
+#[derive(Debug, Deserialize)]
+struct DesktopUrls {
+    page: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchApiResponse {
+    query: SearchQuery,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchQuery {
+    #[serde(default)]
+    search: Vec<SearchHit>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchHit {
+    title: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RedirectApiResponse {
+    query: RedirectQuery,
+}
+
+#[derive(Debug, Deserialize)]
+struct RedirectQuery {
+    #[serde(default)]
+    normalized: Vec<TitleStep>,
+    #[serde(default)]
+    redirects: Vec<TitleStep>,
+    #[serde(default)]
+    pages: Vec<QueryPage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TitleStep {
+    from: String,
+    to: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct QueryPage {
+    title: String,
+    #[serde(default)]
+    missing: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct PagePropsApiResponse {
+    query: PagePropsQuery,
+}
+
+#[derive(Debug, Deserialize)]
+struct PagePropsQuery {
+    #[serde(default)]
+    pages: Vec<PagePropsPage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PagePropsPage {
+    pageprops: Option<PageProps>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PageProps {
+    wikibase_item: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RelatedApiResponse {
+    #[serde(default)]
+    pages: Vec<RelatedApiPage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RelatedApiPage {
+    title: String,
+    description: Option<String>,
+    thumbnail: Option<Thumbnail>,
+    content_urls: ContentUrls,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_url_escapes_title() {
+        assert_eq!(page_url(DEFAULT_ENDPOINT, "en", "summary", "New York City"), "https://en.wikipedia.org/api/rest_v1/page/summary/New_York_City");
+        assert_eq!(page_url(DEFAULT_ENDPOINT, "en", "summary", "AC/DC"), "https://en.wikipedia.org/api/rest_v1/page/summary/AC%2FDC");
+        assert_eq!(page_url(DEFAULT_ENDPOINT, "en", "related", "Why?"), "https://en.wikipedia.org/api/rest_v1/page/related/Why%3F");
+        assert_eq!(page_url(DEFAULT_ENDPOINT, "fr", "summary", "Café"), "https://fr.wikipedia.org/api/rest_v1/page/summary/Caf%C3%A9");
+        assert_eq!(page_url("http://127.0.0.1:8080/", "en", "summary", "Paris"), "http://127.0.0.1:8080/page/summary/Paris");
+        assert_eq!(
+            page_url(DEFAULT_ENDPOINT, "en", "summary", "Python (programming language)"),
+            "https://en.wikipedia.org/api/rest_v1/page/summary/Python_(programming_language)"
+        );
+    }
+
+    #[test]
+    fn test_split_qualifier() {
+        assert_eq!(split_qualifier("Python (programming language)"), Some(("Python", "programming language")));
+        assert_eq!(split_qualifier("Mercury (element)"), Some(("Mercury", "element")));
+        assert_eq!(split_qualifier("Mercury"), None);
+        assert_eq!(split_qualifier("(element)"), None);
+        assert_eq!(split_qualifier("Mercury ()"), None);
+        assert_eq!(action_api_url(DEFAULT_ENDPOINT, "de"), "https://de.wikipedia.org/w/api.php");
+        assert_eq!(action_api_url("http://127.0.0.1:8080/", "en"), "http://127.0.0.1:8080/w/api.php");
+    }
+
+    #[test]
+    fn test_redirects() {
+        let body = include_str!("../../tests/fixtures/wikipedia_redirects_usa.json");
+        assert_eq!(redirect_target(body, "usa").unwrap().as_deref(), Some("United States"));
+        assert_eq!(redirect_target(body, "United States").unwrap(), None);
+
+        let missing = r#"{"query": {"redirects": [{"from": "Colour", "to": "Gone"}], "pages": [{"title": "Gone", "missing": true}]}}"#;
+        assert_eq!(redirect_target(missing, "Colour").unwrap(), None);
+        let looping = r#"{"query": {"redirects": [{"from": "A", "to": "B"}, {"from": "B", "to": "A"}], "pages": []}}"#;
+        assert_eq!(redirect_target(looping, "A").unwrap(), None);
+
+        assert_eq!(redirected_from("colour", "Color").as_deref(), Some("colour"));
+        assert_eq!(redirected_from("new york city", "New_York_City"), None);
+    }
+
+    #[test]
+    fn test_parse_article_sections() {
+        let body = include_str!("../../tests/fixtures/wikipedia_article_photon.json");
+        let article = parse_article(body).unwrap().unwrap();
+
+        let headings: Vec<_> = article.sections.iter().map(|s| (s.heading.as_str(), s.level)).collect();
+        assert_eq!(headings, vec![("Photon", 1), ("History", 2), ("Nomenclature", 3), ("Physical properties", 2)]);
+
+        // The infobox, hatnote, citation marks and the figure are gone; only
+        // article links keep a target
+        let lead = &article.sections[0];
+        assert_eq!(
+            lead.html,
+            "<p>A <b>photon</b> is an <a data-title=\"Elementary particle\">elementary particle</a> that is a quantum \
+             of the <a data-title=\"Electromagnetic field\">electromagnetic field</a>, including <i>light</i>. Photons \
+             are <a>massless</a> &amp; always move at the <a data-title=\"Speed of light\">speed of light</a>.</p>\n"
+        );
+        assert!(lead.text.starts_with("A photon is an elementary particle"));
+
+        let nomenclature = &article.sections[2];
+        assert!(!nomenclature.html.contains("script") && !nomenclature.html.contains("onclick"));
+        assert_eq!(
+            nomenclature.text,
+            "The word quanta was used before 1900. Names used include:\n- Lichtquant\n- light quantum\n- photon"
+        );
+
+        let missing = r#"{"error":{"code":"missingtitle","info":"The page you specified doesn't exist."}}"#;
+        assert!(parse_article(missing).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_paginate_article() {
+        let body = include_str!("../../tests/fixtures/wikipedia_article_photon.json");
+        let article = parse_article(body).unwrap().unwrap();
+
+        let page = paginate(article.clone(), "en", 0, 3);
+        assert_eq!(page.url, "https://en.wikipedia.org/wiki/Photon");
+        assert_eq!((page.sections.len(), page.total_sections, page.next_offset), (3, 4, Some(3)));
+
+        let page = paginate(article.clone(), "en", 3, 3);
+        assert_eq!(page.sections[0].heading, "Physical properties");
+        assert_eq!(page.next_offset, None);
+
+        // Oversized pages are capped
+        assert_eq!(paginate(article, "en", 0, 1000).sections.len(), 4);
+    }
+
+    #[test]
+    fn test_year_events() {
+        let section = |heading: &str, level: u8, text: &str| ArticleSection {
+            heading: heading.to_string(),
+            level,
+            html: String::new(),
+            text: text.to_string(),
+        };
+        let sections = vec![
+            section("1789", 1, "1789 was a common year starting on Thursday."),
+            section("Events", 2, ""),
+            section("January–March", 3, "- January 29 – George Washington is elected.\n- March 4 – Congress first meets."),
+            section("April–June", 3, "- July 14 – The Bastille is stormed."),
+            section("Births", 2, "- August 26 – Someone is born."),
+        ];
+        assert_eq!(
+            events(&sections, 3),
+            ["January 29 – George Washington is elected.", "March 4 – Congress first meets.", "July 14 – The Bastille is stormed."]
+        );
+        assert_eq!(events(&sections, 1).len(), 1);
+        assert!(events(&sections[..1], 3).is_empty());
+    }
+
+    #[test]
+    fn test_parse_related_caps_and_maps_pages() {
+        let body = include_str!("../../tests/fixtures/wikipedia_related_paris.json");
+        let related = parse_related(body, 2).unwrap();
+
+        assert_eq!(related.len(), 2);
+        assert_eq!(related[0].title, "Île-de-France");
+        assert_eq!(related[0].description.as_deref(), Some("Region of France"));
+        assert!(related[0].thumbnail_url.as_deref().unwrap().starts_with("https://upload.wikimedia.org/"));
+        assert_eq!(related[1].title, "Louvre");
+        assert_eq!(related[1].thumbnail_url, None);
+        assert_eq!(related[1].url, "https://en.wikipedia.org/wiki/Louvre");
+
+        assert_eq!(parse_related(body, DEFAULT_MAX_RELATED).unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use tracing::{error, info};
+
+use crate::error::LookupError;
+use crate::http;
+use crate::ratelimit;
+use crate::sanitize;
+use crate::language;
+use crate::lookup::{ContentType, Definition, DefinitionSection};
+use crate::sources::{Source, SourceError, SourceResult};
+
+pub const SOURCE_NAME: &str = "Wiktionary";
+
+pub struct WiktionarySource;
+
+#[async_trait]
+impl Source for WiktionarySource {
+    fn name(&self) -> &str {
+        "wiktionary"
+    }
+
+    fn applies_to(&self, content_type: &ContentType) -> bool {
+        matches!(content_type, ContentType::Word | ContentType::Mixed)
+    }
+
+    async fn fetch(&self, query: &str, _lang: &str) -> Result<SourceResult, SourceError> {
+        get_definitions(&query.to_lowercase())
+            .await
+            .map(SourceResult::Definitions)
+    }
+}
+
+// Fetch English definitions from the Wiktionary REST API. Returns an empty
+// list when Wiktionary has no entry (or no English entry) for the term.
+pub async fn get_definitions(query: &str) -> Result<Vec<DefinitionSection>, LookupError> {
+    info!("Fetching definitions for '{}' from Wiktionary API", query);
+
+    let url = http::wiki_title_url("https://en.wiktionary.org/api/rest_v1/page/definition", query);
+
+    let _permit = ratelimit::acquire(ratelimit::WIKTIONARY).await;
+    let response = http::client()
+        .get(&url)
+        .send()
+        .await
+        .map_err(|e| {
+            error!("Failed to connect to Wiktionary API: {}", e);
+            http::request_error("wiktionary", e)
+        })?;
+
+    if response.status() == 404 {
+        info!("No entry found for '{}' (404)", query);
+        return Ok(vec![]);
+    }
+    if !response.status().is_success() {
+        error!("Wiktionary API returned status: {}", response.status());
+        return Err(LookupError::UpstreamStatus {
+            source: "Wiktionary API".to_string(),
+            status: response.status().as_u16(),
+        });
+    }
+
+    let body = response.text().await.map_err(|e| http::request_error("wiktionary", e))?;
+    let section = parse_definitions(query, &body)?;
+
+    match section {
+        Some(section) => {
+            info!("Successfully fetched {} definitions for '{}'", section.definitions.len(), query);
+            Ok(vec![section])
+        }
+        None => Ok(vec![]),
+    }
+}
+
+// Parse the per-language definition lists, keeping the English entries
+pub(crate) fn parse_definitions(word: &str, body: &str) -> Result<Option<DefinitionSection>, LookupError> {
+    let mut languages: HashMap<String, Vec<WiktionaryUsage>> =
+        serde_json::from_str(body).map_err(|e| {
+            error!("Failed to parse Wiktionary response: {}", e);
+            LookupError::ParseError {
+                source: "wiktionary".to_string(),
+                body_snippet: http::body_snippet(body),
+            }
+        })?;
+
+    let usages = languages.remove("en").unwrap_or_default();
+    let mut definitions = Vec::new();
+
+    for usage in usages {
+        let part_of_speech = usage.part_of_speech.to_lowercase();
+        for sense in usage.definitions {
+            let text = strip_html(&sense.definition);
+            if text.is_empty() {
+                continue;
+            }
+
+            let example = sense
+                .parsed_examples
+                .into_iter()
+                .map(|e| e.example)
+                .chain(sense.examples)
+                .map(|e| strip_html(&e))
+                .find(|e| !e.is_empty());
+
+            definitions.push(Definition {
+                word: word.to_string(),
+                part_of_speech: Some(part_of_speech.clone()),
+                definition: text,
+                example,
+                translated: None,
+                synonyms: Vec::new(),
+                antonyms: Vec::new(),
+                sources: Vec::new(),
+            });
+        }
+    }
+
+    if definitions.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(DefinitionSection {
+        source: SOURCE_NAME.to_string(),
+        definitions,
+        phonetics: None,
+        language: language::DEFAULT_LANGUAGE.to_string(),
+        truncated: false,
+        total_available: 0,
+        changed_since_cache: false,
+        examples_truncated: false,
+    }))
+}
+
+// Wiktionary definitions are HTML fragments; drop the tags and decode the
+// entities
+pub(crate) fn strip_html(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+
+    sanitize::decode_entities(&text).split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WiktionaryUsage {
+    #[serde(default)]
+    part_of_speech: String,
+    #[serde(default)]
+    definitions: Vec<WiktionarySense>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WiktionarySense {
+    definition: String,
+    #[serde(default)]
+    parsed_examples: Vec<WiktionaryExample>,
+    #[serde(default)]
+    examples: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WiktionaryExample {
+    example: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_definitions_fixture() {
+        let body = include_str!("../../tests/fixtures/wiktionary_yeet.json");
+        let section = parse_definitions("yeet", body).unwrap().unwrap();
+
+        assert_eq!(section.source, "Wiktionary");
+        // The empty sense and the German usage are skipped
+        assert_eq!(section.definitions.len(), 3);
+
+        let first = &section.definitions[0];
+        assert_eq!(first.word, "yeet");
+        assert_eq!(first.part_of_speech.as_deref(), Some("verb"));
+        assert_eq!(first.definition, "(transitive, slang) To throw with force.");
+        assert_eq!(first.example.as_deref(), Some("He yeeted the ball across the field."));
+
+        let interjection = &section.definitions[2];
+        assert_eq!(interjection.part_of_speech.as_deref(), Some("interjection"));
+        assert_eq!(interjection.definition, "Used to express excitement & approval.");
+        assert!(interjection.example.is_none());
+    }
+
+    #[test]
+    fn test_parse_definitions_without_english_entry() {
+        let body = r#"{"de": [{"partOfSpeech": "Noun", "language": "German", "definitions": [{"definition": "Schmetterling"}]}]}"#;
+        assert!(parse_definitions("yeet", body).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_strip_html() {
+        assert_eq!(
+            strip_html("<span>To <a href=\"/wiki/throw\" title=\"throw\">throw</a>&nbsp;hard</span>"),
+            "To throw hard"
+        );
+    }
+}
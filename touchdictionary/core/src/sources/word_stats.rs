@@ -0,0 +1,94 @@
+// How common an English word is, from a frequency list compiled into the
+// binary. Nothing is fetched, so it works offline and costs one hash lookup
+// once the table has been built on first use.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+// "word zipf" per line, most frequent first
+const WORD_FREQUENCY: &str = include_str!("../../data/word_frequency.txt");
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WordFrequency {
+    // log10 of occurrences per billion words: 7 for "the", 3 for
+    // "serendipity"
+    pub zipf: f32,
+    // 1 for the most frequent word in the list
+    pub rank: u32,
+    pub band: FrequencyBand,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FrequencyBand {
+    VeryCommon,
+    Common,
+    Moderate,
+    Uncommon,
+    Rare,
+}
+
+impl FrequencyBand {
+    pub fn from_zipf(zipf: f32) -> Self {
+        match zipf {
+            z if z >= 6.0 => FrequencyBand::VeryCommon,
+            z if z >= 5.0 => FrequencyBand::Common,
+            z if z >= 4.0 => FrequencyBand::Moderate,
+            z if z >= 3.0 => FrequencyBand::Uncommon,
+            _ => FrequencyBand::Rare,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FrequencyBand::VeryCommon => "very common",
+            FrequencyBand::Common => "common",
+            FrequencyBand::Moderate => "moderately common",
+            FrequencyBand::Uncommon => "uncommon",
+            FrequencyBand::Rare => "rare",
+        }
+    }
+}
+
+fn table() -> &'static HashMap<&'static str, (u32, f32)> {
+    static TABLE: OnceLock<HashMap<&'static str, (u32, f32)>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        WORD_FREQUENCY
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| line.split_once(' '))
+            .filter_map(|(word, zipf)| Some((word.trim(), zipf.trim().parse().ok()?)))
+            .zip(1..)
+            .map(|((word, zipf), rank)| (word, (rank, zipf)))
+            .collect()
+    })
+}
+
+// How common `word` is in English, or None when it isn't in the list
+pub fn frequency(word: &str) -> Option<WordFrequency> {
+    let word = word.trim().to_lowercase();
+    let &(rank, zipf) = table().get(word.as_str())?;
+    Some(WordFrequency { zipf, rank, band: FrequencyBand::from_zipf(zipf) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frequency() {
+        let the = frequency("The").unwrap();
+        assert_eq!((the.rank, the.band), (1, FrequencyBand::VeryCommon));
+        let rare = frequency("serendipity").unwrap();
+        assert_eq!(rare.band, FrequencyBand::Rare);
+        assert!(rare.rank > frequency("dog").unwrap().rank);
+        assert_eq!(frequency("zzxq"), None);
+        assert_eq!(frequency("# English"), None);
+
+        // Ranks follow the file, so it has to stay sorted
+        let mut ranked: Vec<_> = table().values().collect();
+        ranked.sort_by_key(|(rank, _)| *rank);
+        assert!(ranked.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+    }
+}
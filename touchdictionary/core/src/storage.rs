@@ -0,0 +1,818 @@
+// The one SQLite database the response cache, the history and favorites
+// share, under `$XDG_DATA_HOME/touchdictionary/`. Connections come from a
+// small pool and are only ever used on tokio's blocking threads. WAL lets
+// the GUI and a CLI run read while the other writes, and the busy timeout
+// makes a writer wait out maintenance instead of failing.
+//
+// SQLite is built into the binary, so the stores never need another
+// format; files from before the database are imported once and renamed.
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::types::{Type, ValueRef};
+use rusqlite::{params, Connection, OptionalExtension, Row, TransactionBehavior};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::cache::{CacheStats, Validator};
+use crate::favorites::FavoriteEntry;
+use crate::history::HistoryEntry;
+
+pub const FILE_NAME: &str = "touchdictionary.db";
+
+// How long a statement waits on another connection's lock
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Most connections open at once; a caller past that waits for one
+const MAX_CONNECTIONS: u32 = 4;
+
+// Schema changes, oldest first. `PRAGMA user_version` records how many have
+// run. Forward only: a released migration is never edited, only followed by
+// a new one.
+const MIGRATIONS: &[&str] = &[
+    // 1: the three stores
+    "CREATE TABLE history (
+        id INTEGER PRIMARY KEY,
+        query TEXT NOT NULL,
+        display_query TEXT NOT NULL,
+        content_type TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        sources TEXT NOT NULL,
+        referrer TEXT
+    );
+    CREATE TABLE favorites (
+        id INTEGER PRIMARY KEY,
+        word_key TEXT NOT NULL UNIQUE,
+        word TEXT NOT NULL,
+        note TEXT,
+        tags TEXT NOT NULL,
+        saved_at INTEGER NOT NULL,
+        result TEXT
+    );
+    CREATE TABLE cache_entries (
+        source TEXT NOT NULL,
+        query TEXT NOT NULL,
+        stored_at INTEGER NOT NULL,
+        prefetched INTEGER NOT NULL,
+        validator TEXT,
+        value TEXT NOT NULL,
+        PRIMARY KEY (source, query)
+    );
+    CREATE TABLE cache_stats (
+        id INTEGER PRIMARY KEY CHECK (id = 1),
+        hits INTEGER NOT NULL,
+        misses INTEGER NOT NULL,
+        revalidations INTEGER NOT NULL
+    );
+    INSERT INTO cache_stats VALUES (1, 0, 0, 0);",
+    // 2: history search and pruning without full scans
+    "CREATE INDEX history_query ON history (query);
+    CREATE INDEX cache_entries_stored_at ON cache_entries (stored_at);",
+    // 3: lookups run again once the network was back
+    "ALTER TABLE history ADD COLUMN deferred INTEGER NOT NULL DEFAULT 0;",
+    // 4: files from before the database that were moved into it
+    "CREATE TABLE imports (name TEXT PRIMARY KEY);",
+];
+
+#[derive(Debug)]
+pub enum StorageError {
+    Sqlite(rusqlite::Error),
+    Pool(r2d2::Error),
+    Json(serde_json::Error),
+    Io(io::Error),
+    // The database was migrated by a newer release
+    NewerSchema { found: usize, supported: usize },
+    // The blocking task running a query panicked or was cancelled
+    Task(tokio::task::JoinError),
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Sqlite(e) => write!(f, "{}", e),
+            StorageError::Pool(e) => write!(f, "No database connection: {}", e),
+            StorageError::Json(e) => write!(f, "Unreadable row: {}", e),
+            StorageError::Io(e) => write!(f, "{}", e),
+            StorageError::NewerSchema { found, supported } => write!(
+                f,
+                "Database schema {} is newer than this version of TouchDictionary ({})",
+                found, supported
+            ),
+            StorageError::Task(e) => write!(f, "Database task failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(e: rusqlite::Error) -> Self {
+        StorageError::Sqlite(e)
+    }
+}
+
+impl From<r2d2::Error> for StorageError {
+    fn from(e: r2d2::Error) -> Self {
+        StorageError::Pool(e)
+    }
+}
+
+impl From<serde_json::Error> for StorageError {
+    fn from(e: serde_json::Error) -> Self {
+        StorageError::Json(e)
+    }
+}
+
+impl From<io::Error> for StorageError {
+    fn from(e: io::Error) -> Self {
+        StorageError::Io(e)
+    }
+}
+
+impl From<StorageError> for io::Error {
+    fn from(e: StorageError) -> Self {
+        match e {
+            StorageError::Io(e) => e,
+            e => io::Error::other(e),
+        }
+    }
+}
+
+pub struct Storage {
+    path: PathBuf,
+    pool: Pool<SqliteConnectionManager>,
+}
+
+impl fmt::Debug for Storage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Storage").field("path", &self.path).finish()
+    }
+}
+
+impl Storage {
+    // Open the database at `path`, creating it and bringing its schema up to
+    // date. Blocks; call it once at startup.
+    pub fn open(path: &Path) -> Result<Arc<Storage>, StorageError> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let manager = SqliteConnectionManager::file(path).with_init(configure);
+        let pool = Pool::builder().max_size(MAX_CONNECTIONS).min_idle(Some(1)).build(manager)?;
+
+        let mut connection = pool.get()?;
+        // WAL is a property of the file, so setting it once is enough
+        connection.query_row("PRAGMA journal_mode = WAL", [], |row| row.get::<_, String>(0))?;
+        migrate(&mut connection, MIGRATIONS.len())?;
+        drop(connection);
+        Ok(Arc::new(Storage { path: path.to_path_buf(), pool }))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Run `work` with a pooled connection on a blocking thread
+    pub async fn run<T, F>(self: &Arc<Self>, work: F) -> Result<T, StorageError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T, StorageError> + Send + 'static,
+    {
+        let storage = self.clone();
+        tokio::task::spawn_blocking(move || storage.run_blocking(work)).await.map_err(StorageError::Task)?
+    }
+
+    // `run` for callers already off the async threads
+    pub fn run_blocking<T>(&self, work: impl FnOnce(&mut Connection) -> Result<T, StorageError>) -> Result<T, StorageError> {
+        let mut connection = self.pool.get()?;
+        work(&mut connection)
+    }
+
+    // Rebuild the file when more than `threshold` of its pages are free,
+    // returning how many bytes it shrank by. Deleted rows only go on the
+    // freelist; without this the file never gets smaller.
+    pub async fn vacuum(self: &Arc<Self>, threshold: f64) -> Result<u64, StorageError> {
+        let path = self.path.clone();
+        self.run(move |db| {
+            let free: u64 = db.pragma_query_value(None, "freelist_count", |row| row.get(0))?;
+            let pages: u64 = db.pragma_query_value(None, "page_count", |row| row.get(0))?;
+            if pages == 0 || (free as f64) / (pages as f64) <= threshold {
+                return Ok(0);
+            }
+            let before = file_size(&path);
+            db.execute_batch("VACUUM")?;
+            // VACUUM goes through the WAL; the file only shrinks once it's
+            // checkpointed
+            db.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+            let freed = before.saturating_sub(file_size(&path));
+            info!("Vacuumed {} ({} of {} pages free, {} bytes freed)", path.display(), free, pages, freed);
+            Ok(freed)
+        })
+        .await
+    }
+
+    pub fn cache(self: &Arc<Self>) -> CacheRepo {
+        CacheRepo { storage: self.clone() }
+    }
+
+    pub fn history(self: &Arc<Self>) -> HistoryRepo {
+        HistoryRepo { storage: self.clone() }
+    }
+
+    pub fn favorites(self: &Arc<Self>) -> FavoritesRepo {
+        FavoritesRepo { storage: self.clone() }
+    }
+}
+
+pub fn default_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("touchdictionary").join(FILE_NAME))
+}
+
+// The database in its default location, opened on first use and shared by
+// the whole process. None without a data directory or when the file can't
+// be opened.
+pub fn open_default() -> Option<Arc<Storage>> {
+    static STORAGE: OnceLock<Option<Arc<Storage>>> = OnceLock::new();
+    STORAGE
+        .get_or_init(|| {
+            let path = default_path()?;
+            match Storage::open(&path) {
+                Ok(storage) => {
+                    crate::history::import_legacy_file(&storage);
+                    crate::favorites::import_legacy_file(&storage);
+                    Some(storage)
+                }
+                Err(e) => {
+                    warn!("Failed to open {}: {}", path.display(), e);
+                    None
+                }
+            }
+        })
+        .clone()
+}
+
+// The database file and its WAL together
+fn file_size(path: &Path) -> u64 {
+    let mut wal = path.as_os_str().to_owned();
+    wal.push("-wal");
+    [path.as_os_str(), &wal].iter().filter_map(|file| std::fs::metadata(file).ok()).map(|meta| meta.len()).sum()
+}
+
+// Run on every connection the pool opens
+fn configure(connection: &mut Connection) -> rusqlite::Result<()> {
+    connection.busy_timeout(BUSY_TIMEOUT)?;
+    connection.pragma_update(None, "synchronous", "NORMAL")
+}
+
+pub fn schema_version(connection: &Connection) -> rusqlite::Result<usize> {
+    connection.pragma_query_value(None, "user_version", |row| row.get(0))
+}
+
+// Run the migrations after the database's version up to `target`, each in
+// its own transaction with the version bump
+fn migrate(connection: &mut Connection, target: usize) -> Result<(), StorageError> {
+    let current = schema_version(connection)?;
+    if current > MIGRATIONS.len() {
+        return Err(StorageError::NewerSchema { found: current, supported: MIGRATIONS.len() });
+    }
+    for (index, migration) in MIGRATIONS.iter().enumerate().take(target).skip(current) {
+        let version = index + 1;
+        // Taking the write lock up front, so another process migrating at
+        // the same time waits here rather than failing halfway
+        let transaction = connection.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        // Another process may have migrated while this one waited
+        if schema_version(&transaction)? >= version {
+            continue;
+        }
+        transaction.execute_batch(migration)?;
+        transaction.pragma_update(None, "user_version", version)?;
+        transaction.commit()?;
+        info!("Migrated storage to schema {}", version);
+    }
+    Ok(())
+}
+
+// A JSON column, read into `T`
+fn json<T: DeserializeOwned>(row: &Row, index: usize) -> rusqlite::Result<T> {
+    let text: String = row.get(index)?;
+    serde_json::from_str(&text).map_err(|e| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(e)))
+}
+
+// `json` for a column that may be NULL
+fn optional_json<T: DeserializeOwned>(row: &Row, index: usize) -> rusqlite::Result<Option<T>> {
+    match row.get_ref(index)? {
+        ValueRef::Null => Ok(None),
+        _ => json(row, index).map(Some),
+    }
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String, StorageError> {
+    Ok(serde_json::to_string(value)?)
+}
+
+// Run `import` in one transaction with a record of `name`, unless `name` was
+// imported before. Returns whether it ran, so a file whose rename failed
+// after its import is never imported twice.
+fn import_once(
+    db: &mut Connection,
+    name: &str,
+    import: impl FnOnce(&Connection) -> Result<(), StorageError>,
+) -> Result<bool, StorageError> {
+    let transaction = db.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    if transaction.execute("INSERT OR IGNORE INTO imports (name) VALUES (?1)", params![name])? == 0 {
+        return Ok(false);
+    }
+    import(&transaction)?;
+    transaction.commit()?;
+    Ok(true)
+}
+
+// A cached response as stored; the value is still JSON
+#[derive(Debug, Clone)]
+pub struct CacheRow {
+    pub stored_at: u64,
+    pub prefetched: bool,
+    pub validator: Option<Validator>,
+    pub value: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct CacheRepo {
+    storage: Arc<Storage>,
+}
+
+impl CacheRepo {
+    pub async fn get(&self, source: &str, query: &str) -> Result<Option<CacheRow>, StorageError> {
+        let (source, query) = (source.to_string(), query.to_string());
+        self.storage
+            .run(move |db| {
+                let row = db
+                    .prepare_cached(
+                        "SELECT stored_at, prefetched, validator, value FROM cache_entries WHERE source = ?1 AND query = ?2",
+                    )?
+                    .query_row(params![source, query], |row| {
+                        Ok(CacheRow {
+                            stored_at: row.get(0)?,
+                            prefetched: row.get(1)?,
+                            validator: optional_json(row, 2)?,
+                            value: row.get(3)?,
+                        })
+                    })
+                    .optional()?;
+                Ok(row)
+            })
+            .await
+    }
+
+    pub async fn put(&self, source: &str, query: &str, row: CacheRow) -> Result<(), StorageError> {
+        let (source, query) = (source.to_string(), query.to_string());
+        let validator = row.validator.as_ref().map(to_json).transpose()?;
+        self.storage
+            .run(move |db| {
+                db.prepare_cached(
+                    "INSERT OR REPLACE INTO cache_entries (source, query, stored_at, prefetched, validator, value)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                )?
+                .execute(params![source, query, row.stored_at, row.prefetched, validator, row.value])?;
+                Ok(())
+            })
+            .await
+    }
+
+    pub async fn touch(&self, source: &str, query: &str, stored_at: u64) -> Result<(), StorageError> {
+        let (source, query) = (source.to_string(), query.to_string());
+        self.storage
+            .run(move |db| {
+                db.execute(
+                    "UPDATE cache_entries SET stored_at = ?3 WHERE source = ?1 AND query = ?2",
+                    params![source, query, stored_at],
+                )?;
+                Ok(())
+            })
+            .await
+    }
+
+    pub async fn remove(&self, source: &str, query: &str) -> Result<(), StorageError> {
+        let (source, query) = (source.to_string(), query.to_string());
+        self.storage
+            .run(move |db| {
+                db.execute("DELETE FROM cache_entries WHERE source = ?1 AND query = ?2", params![source, query])?;
+                Ok(())
+            })
+            .await
+    }
+
+    // Add to the hit, miss and revalidation counters
+    pub async fn count(&self, hits: u64, misses: u64, revalidations: u64) -> Result<(), StorageError> {
+        self.storage
+            .run(move |db| {
+                db.prepare_cached(
+                    "UPDATE cache_stats SET hits = hits + ?1, misses = misses + ?2, revalidations = revalidations + ?3",
+                )?
+                .execute(params![hits, misses, revalidations])?;
+                Ok(())
+            })
+            .await
+    }
+
+    pub async fn stats(&self) -> Result<CacheStats, StorageError> {
+        self.storage
+            .run(|db| {
+                let stats = db
+                    .query_row(
+                        "SELECT (SELECT COUNT(*) FROM cache_entries), hits, misses, revalidations FROM cache_stats",
+                        [],
+                        |row| {
+                            Ok(CacheStats {
+                                entries: row.get(0)?,
+                                hits: row.get(1)?,
+                                misses: row.get(2)?,
+                                revalidations: row.get(3)?,
+                            })
+                        },
+                    )
+                    .optional()?;
+                Ok(stats.unwrap_or_default())
+            })
+            .await
+    }
+
+    // Remove every entry, returning how many there were. The counters stay.
+    pub async fn clear(&self) -> Result<usize, StorageError> {
+        self.storage.run(|db| Ok(db.execute("DELETE FROM cache_entries", [])?)).await
+    }
+
+    // Delete prefetched entries stored before `prefetched_before` and the
+    // rest stored before `stored_before`. Returns how many went and the size
+    // of their values.
+    pub async fn prune(&self, prefetched_before: u64, stored_before: u64) -> Result<(usize, u64), StorageError> {
+        self.storage
+            .run(move |db| {
+                let transaction = db.transaction()?;
+                let expired = "(prefetched = 1 AND stored_at <= ?1) OR (prefetched = 0 AND stored_at <= ?2)";
+                let pruned = transaction.query_row(
+                    &format!("SELECT COUNT(*), COALESCE(SUM(length(value)), 0) FROM cache_entries WHERE {}", expired),
+                    params![prefetched_before, stored_before],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )?;
+                let deleted = format!("DELETE FROM cache_entries WHERE {}", expired);
+                transaction.execute(&deleted, params![prefetched_before, stored_before])?;
+                transaction.commit()?;
+                Ok(pruned)
+            })
+            .await
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HistoryRepo {
+    storage: Arc<Storage>,
+}
+
+impl HistoryRepo {
+    pub async fn append(&self, entry: &HistoryEntry) -> Result<(), StorageError> {
+        let entry = entry.clone();
+        self.storage.run(move |db| insert_history(db, &entry)).await
+    }
+
+    // Every entry, oldest first
+    pub async fn all(&self) -> Result<Vec<HistoryEntry>, StorageError> {
+        self.storage
+            .run(|db| {
+                let entries = db
+                    .prepare_cached(
+                        "SELECT query, display_query, content_type, timestamp, sources, referrer, deferred
+                         FROM history ORDER BY id",
+                    )?
+                    .query_map([], history_entry)?
+                    .collect::<rusqlite::Result<_>>()?;
+                Ok(entries)
+            })
+            .await
+    }
+
+    // The newest `limit` entries, newest first
+    pub async fn recent(&self, limit: usize) -> Result<Vec<HistoryEntry>, StorageError> {
+        self.storage
+            .run(move |db| {
+                let entries = db
+                    .prepare_cached(
+                        "SELECT query, display_query, content_type, timestamp, sources, referrer, deferred
+                         FROM history ORDER BY id DESC LIMIT ?1",
+                    )?
+                    .query_map(params![limit], history_entry)?
+                    .collect::<rusqlite::Result<_>>()?;
+                Ok(entries)
+            })
+            .await
+    }
+
+    // Entries whose query contains `needle`, which is already lowercase,
+    // newest first. Queries are stored lowercase; the display form is
+    // folded here, which SQLite only does for ASCII.
+    pub async fn search(&self, needle: &str) -> Result<Vec<HistoryEntry>, StorageError> {
+        let needle = needle.to_string();
+        self.storage
+            .run(move |db| {
+                let entries = db
+                    .prepare_cached(
+                        "SELECT query, display_query, content_type, timestamp, sources, referrer, deferred
+                         FROM history WHERE instr(query, ?1) > 0 OR instr(lower(display_query), ?1) > 0
+                         ORDER BY id DESC",
+                    )?
+                    .query_map(params![needle], history_entry)?
+                    .collect::<rusqlite::Result<_>>()?;
+                Ok(entries)
+            })
+            .await
+    }
+
+    // Delete every entry, returning how many there were
+    pub async fn clear(&self) -> Result<usize, StorageError> {
+        self.storage.run(|db| Ok(db.execute("DELETE FROM history", [])?)).await
+    }
+
+    // Drop all but the newest `max_entries` entries, returning how many went
+    // and roughly how many bytes they took
+    pub async fn trim(&self, max_entries: usize) -> Result<(usize, u64), StorageError> {
+        if max_entries == 0 {
+            return Ok((0, 0));
+        }
+        self.storage
+            .run(move |db| {
+                let transaction = db.transaction()?;
+                let older = "id <= (SELECT id FROM history ORDER BY id DESC LIMIT 1 OFFSET ?1)";
+                let trimmed = transaction.query_row(
+                    &format!(
+                        "SELECT COUNT(*), COALESCE(SUM(length(query) + length(display_query) + length(content_type)
+                         + length(sources) + COALESCE(length(referrer), 0)), 0) FROM history WHERE {}",
+                        older
+                    ),
+                    params![max_entries],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )?;
+                transaction.execute(&format!("DELETE FROM history WHERE {}", older), params![max_entries])?;
+                transaction.commit()?;
+                Ok(trimmed)
+            })
+            .await
+    }
+
+    // Add entries from the file `name`, oldest first, in one transaction.
+    // False when that file was imported before.
+    pub fn import_blocking(&self, name: &str, entries: &[HistoryEntry]) -> Result<bool, StorageError> {
+        self.storage.run_blocking(|db| {
+            import_once(db, name, |transaction| entries.iter().try_for_each(|entry| insert_history(transaction, entry)))
+        })
+    }
+}
+
+fn insert_history(db: &Connection, entry: &HistoryEntry) -> Result<(), StorageError> {
+    db.prepare_cached(
+        "INSERT INTO history (query, display_query, content_type, timestamp, sources, referrer, deferred)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+    )?
+    .execute(params![
+        entry.query,
+        entry.display_query,
+        to_json(&entry.content_type)?,
+        entry.timestamp,
+        to_json(&entry.sources)?,
+        entry.referrer,
+        entry.deferred,
+    ])?;
+    Ok(())
+}
+
+fn history_entry(row: &Row) -> rusqlite::Result<HistoryEntry> {
+    Ok(HistoryEntry {
+        query: row.get(0)?,
+        display_query: row.get(1)?,
+        content_type: json(row, 2)?,
+        timestamp: row.get(3)?,
+        sources: json(row, 4)?,
+        referrer: row.get(5)?,
+        deferred: row.get(6)?,
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct FavoritesRepo {
+    storage: Arc<Storage>,
+}
+
+impl FavoritesRepo {
+    // Save `entry`, replacing an earlier favorite for the same word
+    pub async fn add(&self, entry: &FavoriteEntry) -> Result<(), StorageError> {
+        let entry = entry.clone();
+        self.storage.run(move |db| insert_favorite(db, &entry)).await
+    }
+
+    // Returns whether the word was saved
+    pub async fn remove(&self, word: &str) -> Result<bool, StorageError> {
+        let key = word_key(word);
+        self.storage.run(move |db| Ok(db.execute("DELETE FROM favorites WHERE word_key = ?1", params![key])? > 0)).await
+    }
+
+    // Every favorite, oldest first
+    pub async fn all(&self) -> Result<Vec<FavoriteEntry>, StorageError> {
+        self.storage
+            .run(|db| {
+                let entries = db
+                    .prepare_cached("SELECT word, note, tags, saved_at, result FROM favorites ORDER BY id")?
+                    .query_map([], |row| {
+                        Ok(FavoriteEntry {
+                            word: row.get(0)?,
+                            note: row.get(1)?,
+                            tags: json(row, 2)?,
+                            saved_at: row.get(3)?,
+                            result: optional_json(row, 4)?,
+                        })
+                    })?
+                    .collect::<rusqlite::Result<_>>()?;
+                Ok(entries)
+            })
+            .await
+    }
+
+    // Add entries from the file `name`, oldest first, in one transaction.
+    // False when that file was imported before.
+    pub fn import_blocking(&self, name: &str, entries: &[FavoriteEntry]) -> Result<bool, StorageError> {
+        self.storage.run_blocking(|db| {
+            import_once(db, name, |transaction| entries.iter().try_for_each(|entry| insert_favorite(transaction, entry)))
+        })
+    }
+}
+
+// Favorites are one per word, whatever its case
+fn word_key(word: &str) -> String {
+    word.trim().to_lowercase()
+}
+
+fn insert_favorite(db: &Connection, entry: &FavoriteEntry) -> Result<(), StorageError> {
+    // REPLACE deletes the old row first, so the new one sorts as the newest
+    db.prepare_cached(
+        "INSERT OR REPLACE INTO favorites (word_key, word, note, tags, saved_at, result) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?
+    .execute(params![
+        word_key(&entry.word),
+        entry.word,
+        entry.note,
+        to_json(&entry.tags)?,
+        entry.saved_at,
+        entry.result.as_ref().map(to_json).transpose()?,
+    ])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lookup::ContentType;
+
+    fn temp_db(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("touchdictionary-storage-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir.join(FILE_NAME)
+    }
+
+    fn history_entry(query: &str, timestamp: u64) -> HistoryEntry {
+        HistoryEntry {
+            query: query.to_string(),
+            display_query: query.to_string(),
+            content_type: ContentType::Word,
+            timestamp,
+            sources: vec!["Free Dictionary API".to_string()],
+            referrer: None,
+            deferred: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_old_schema_upgrades_with_its_rows() {
+        let path = temp_db("upgrade");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+
+        // A database as the first release left it, with a lookup in it
+        let mut old = Connection::open(&path).unwrap();
+        migrate(&mut old, 1).unwrap();
+        assert_eq!(schema_version(&old).unwrap(), 1);
+        old.execute(
+            "INSERT INTO history (query, display_query, content_type, timestamp, sources) VALUES (?1, ?1, ?2, 5, ?3)",
+            params!["petrichor", "\"Word\"", "[]"],
+        )
+        .unwrap();
+        drop(old);
+
+        let storage = Storage::open(&path).unwrap();
+        storage
+            .run(|db| {
+                assert_eq!(schema_version(db)?, MIGRATIONS.len());
+                let indexes: usize = db.query_row(
+                    "SELECT COUNT(*) FROM sqlite_master WHERE type = 'index' AND name = 'history_query'",
+                    [],
+                    |row| row.get(0),
+                )?;
+                assert_eq!(indexes, 1);
+                let mode: String = db.pragma_query_value(None, "journal_mode", |row| row.get(0))?;
+                assert_eq!(mode, "wal");
+                Ok(())
+            })
+            .await
+            .unwrap();
+        let entries = storage.history().all().await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].query, "petrichor");
+
+        // Opening an up-to-date database again changes nothing
+        drop(storage);
+        let storage = Storage::open(&path).unwrap();
+        assert_eq!(storage.history().all().await.unwrap().len(), 1);
+
+        // A schema from a newer release is refused rather than guessed at
+        storage.run(|db| Ok(db.pragma_update(None, "user_version", 99)?)).await.unwrap();
+        drop(storage);
+        assert!(Storage::open(&path).is_err());
+
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_history_and_favorites_repos() {
+        let path = temp_db("repos");
+        let storage = Storage::open(&path).unwrap();
+
+        let history = storage.history();
+        for (i, query) in ["one", "two", "three"].iter().enumerate() {
+            history.append(&history_entry(query, i as u64)).await.unwrap();
+        }
+        let (dropped, freed) = history.trim(2).await.unwrap();
+        assert_eq!(dropped, 1);
+        assert!(freed > 0);
+        let left: Vec<String> = history.all().await.unwrap().into_iter().map(|e| e.query).collect();
+        assert_eq!(left, ["two", "three"]);
+        assert_eq!(history.trim(0).await.unwrap(), (0, 0));
+        assert_eq!(history.clear().await.unwrap(), 2);
+
+        let favorites = storage.favorites();
+        let favorite = |word: &str| FavoriteEntry {
+            word: word.to_string(),
+            note: None,
+            tags: vec!["gre".to_string()],
+            saved_at: 1,
+            result: None,
+        };
+        favorites.add(&favorite("serendipity")).await.unwrap();
+        favorites.add(&favorite("ephemeral")).await.unwrap();
+        favorites.add(&favorite("Serendipity")).await.unwrap();
+        let words: Vec<String> = favorites.all().await.unwrap().into_iter().map(|e| e.word).collect();
+        assert_eq!(words, ["ephemeral", "Serendipity"]);
+        assert!(favorites.remove(" SERENDIPITY ").await.unwrap());
+        assert!(!favorites.remove("serendipity").await.unwrap());
+
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_cache_repo() {
+        let path = temp_db("cache");
+        let cache = Storage::open(&path).unwrap().cache();
+
+        let row = |stored_at: u64, prefetched: bool| CacheRow {
+            stored_at,
+            prefetched,
+            validator: Some(Validator { etag: Some("\"abc\"".to_string()), last_modified: None }),
+            value: "\"glad\"".to_string(),
+        };
+        cache.put("dictionary", "happy", row(100, false)).await.unwrap();
+        cache.put("dictionary", "old", row(10, false)).await.unwrap();
+        cache.put("dictionary", "unused", row(60, true)).await.unwrap();
+
+        let hit = cache.get("dictionary", "happy").await.unwrap().unwrap();
+        assert_eq!((hit.stored_at, hit.prefetched, hit.value.as_str()), (100, false, "\"glad\""));
+        assert!(hit.validator.is_some());
+        assert!(cache.get("wikipedia", "happy").await.unwrap().is_none());
+
+        cache.touch("dictionary", "old", 20).await.unwrap();
+        assert_eq!(cache.get("dictionary", "old").await.unwrap().unwrap().stored_at, 20);
+
+        cache.count(2, 1, 0).await.unwrap();
+        let stats = cache.stats().await.unwrap();
+        assert_eq!(stats, CacheStats { entries: 3, hits: 2, misses: 1, revalidations: 0 });
+
+        let (removed, freed) = cache.prune(70, 50).await.unwrap();
+        assert_eq!((removed, freed), (2, 12));
+        assert!(cache.get("dictionary", "happy").await.unwrap().is_some());
+
+        assert_eq!(cache.clear().await.unwrap(), 1);
+        assert_eq!(cache.stats().await.unwrap().hits, 2);
+
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+}
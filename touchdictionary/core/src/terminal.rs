@@ -0,0 +1,37 @@
+// What stdout is connected to, for deciding on colors and line width
+
+use std::io::IsTerminal;
+
+use terminal_size::{Height, Width};
+
+pub fn stdout_is_terminal() -> bool {
+    std::io::stdout().is_terminal()
+}
+
+pub fn stderr_is_terminal() -> bool {
+    std::io::stderr().is_terminal()
+}
+
+// Columns of the terminal on stdout, or $COLUMNS where it can't be asked
+pub fn width() -> Option<usize> {
+    columns_of(terminal_size::terminal_size_of(std::io::stdout())).or_else(columns_from_env)
+}
+
+// `width` for the terminal on stderr, where progress is drawn
+pub fn stderr_width() -> Option<usize> {
+    columns_of(terminal_size::terminal_size_of(std::io::stderr())).or_else(columns_from_env)
+}
+
+fn columns_of(size: Option<(Width, Height)>) -> Option<usize> {
+    size.map(|(Width(columns), _)| columns as usize).filter(|&columns| columns > 0)
+}
+
+fn columns_from_env() -> Option<usize> {
+    std::env::var("COLUMNS").ok()?.trim().parse().ok().filter(|&columns| columns > 0)
+}
+
+// NO_COLOR set to anything but the empty string turns colors off
+// (https://no-color.org)
+pub fn no_color_requested() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
@@ -0,0 +1,115 @@
+// The desktop's light/dark preference, for where the window system doesn't
+// pass it on. Linux desktops publish it through the XDG settings portal.
+
+#[cfg(all(unix, not(target_os = "macos")))]
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+#[cfg(all(unix, not(target_os = "macos")))]
+use zbus::zvariant::{OwnedValue, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SystemTheme {
+    Light,
+    Dark,
+    // Nothing said which; better left to the webview than guessed
+    Unknown,
+}
+
+// How long to wait on the session bus before giving up
+#[cfg(all(unix, not(target_os = "macos")))]
+const PORTAL_TIMEOUT: Duration = Duration::from_secs(2);
+
+// The portal's `color-scheme` first, then the GTK theme GNOME publishes
+// through the same Settings interface
+#[cfg(all(unix, not(target_os = "macos")))]
+pub async fn portal_theme() -> SystemTheme {
+    let Ok(Ok(connection)) = tokio::time::timeout(PORTAL_TIMEOUT, zbus::Connection::session()).await else {
+        return SystemTheme::Unknown;
+    };
+    if let Some(value) = portal_read(&connection, "org.freedesktop.appearance", "color-scheme").await {
+        let theme = color_scheme(&value);
+        if theme != SystemTheme::Unknown {
+            return theme;
+        }
+    }
+    match portal_read(&connection, "org.gnome.desktop.interface", "gtk-theme").await {
+        Some(value) => gtk_theme(&value),
+        None => SystemTheme::Unknown,
+    }
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+pub async fn portal_theme() -> SystemTheme {
+    SystemTheme::Unknown
+}
+
+// A setting from org.freedesktop.portal.Settings.Read, or None when there's
+// no portal to ask or it doesn't know the key
+#[cfg(all(unix, not(target_os = "macos")))]
+async fn portal_read(connection: &zbus::Connection, namespace: &str, key: &str) -> Option<OwnedValue> {
+    let setting = (namespace, key);
+    let call = connection.call_method(
+        Some("org.freedesktop.portal.Desktop"),
+        "/org/freedesktop/portal/desktop",
+        Some("org.freedesktop.portal.Settings"),
+        "Read",
+        &setting,
+    );
+    let reply = tokio::time::timeout(PORTAL_TIMEOUT, call).await.ok()?.ok()?;
+    reply.body().deserialize::<OwnedValue>().ok()
+}
+
+// Read hands the setting back wrapped in a variant of its own, some
+// portals in two
+#[cfg(all(unix, not(target_os = "macos")))]
+fn unwrap_variant<'a, 'b>(value: &'b Value<'a>) -> &'b Value<'a> {
+    match value {
+        Value::Value(inner) => unwrap_variant(inner),
+        value => value,
+    }
+}
+
+// 1 prefers dark, 2 prefers light, 0 has no preference
+#[cfg(all(unix, not(target_os = "macos")))]
+fn color_scheme(value: &Value) -> SystemTheme {
+    match unwrap_variant(value) {
+        Value::U32(1) => SystemTheme::Dark,
+        Value::U32(2) => SystemTheme::Light,
+        _ => SystemTheme::Unknown,
+    }
+}
+
+// Only a dark theme says anything: plain Adwaita is drawn dark too when the
+// desktop prefers it
+#[cfg(all(unix, not(target_os = "macos")))]
+fn gtk_theme(value: &Value) -> SystemTheme {
+    match unwrap_variant(value) {
+        Value::Str(name) if name.to_lowercase().contains("dark") => SystemTheme::Dark,
+        _ => SystemTheme::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[test]
+    fn test_portal_values() {
+        let wrapped = |value: Value<'static>| Value::Value(Box::new(value));
+        assert_eq!(color_scheme(&wrapped(wrapped(Value::U32(1)))), SystemTheme::Dark);
+        assert_eq!(color_scheme(&wrapped(Value::U32(2))), SystemTheme::Light);
+        assert_eq!(color_scheme(&wrapped(Value::U32(0))), SystemTheme::Unknown);
+        assert_eq!(color_scheme(&wrapped(Value::from("dark"))), SystemTheme::Unknown);
+
+        assert_eq!(gtk_theme(&wrapped(wrapped(Value::from("Adwaita-dark")))), SystemTheme::Dark);
+        assert_eq!(gtk_theme(&wrapped(Value::from("Breeze"))), SystemTheme::Unknown);
+    }
+
+    #[test]
+    fn test_serializes_lowercase() {
+        assert_eq!(serde_json::to_string(&SystemTheme::Unknown).unwrap(), "\"unknown\"");
+    }
+}
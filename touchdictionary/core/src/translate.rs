@@ -0,0 +1,168 @@
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::config::TranslateSettings;
+use crate::error::LookupError;
+use crate::http;
+use crate::lookup::LookupResult;
+
+// Translates definitions and the Wikipedia summary through a LibreTranslate
+// server. Best effort: a translator that can't be reached leaves the result
+// untranslated with a warning, it never fails the lookup.
+pub async fn translate_result(result: &mut LookupResult, settings: &TranslateSettings, target: &str) {
+    let Some(endpoint) = settings.endpoint.as_deref().filter(|e| !e.trim().is_empty()) else {
+        warn!("Translation to '{}' requested but no LibreTranslate endpoint is configured", target);
+        result.warnings.push("Translation skipped: no LibreTranslate endpoint configured".to_string());
+        return;
+    };
+
+    let texts = collect_texts(result, target);
+    if texts.is_empty() {
+        return;
+    }
+
+    info!("Translating {} texts to '{}'", texts.len(), target);
+    match request(endpoint, settings.api_key.as_deref(), &texts, target).await {
+        Ok(translated) if translated.len() == texts.len() => apply_translations(result, target, translated),
+        Ok(translated) => {
+            warn!("Translator returned {} texts for {} sent", translated.len(), texts.len());
+            result.warnings.push(format!("Translation to '{}' failed: incomplete response", target));
+        }
+        Err(e) => {
+            warn!("Translation to '{}' failed: {}", target, e);
+            result.warnings.push(format!("Translation to '{}' failed: {}", target, e));
+        }
+    }
+}
+
+// Everything worth translating, in a fixed order that `apply_translations`
+// walks again. Sections already in the target language are left alone.
+fn collect_texts(result: &LookupResult, target: &str) -> Vec<String> {
+    let mut texts = Vec::new();
+    for section in result.sections.definitions.iter().flatten() {
+        if section.language != target {
+            texts.extend(section.definitions.iter().map(|d| d.definition.clone()));
+        }
+    }
+    if let Some(wiki) = &result.sections.wikipedia {
+        if wiki.language != target && !wiki.summary.is_empty() {
+            texts.push(wiki.summary.clone());
+        }
+    }
+    texts
+}
+
+fn apply_translations(result: &mut LookupResult, target: &str, translated: Vec<String>) {
+    let mut translated = translated.into_iter();
+    for section in result.sections.definitions.iter_mut().flatten() {
+        if section.language != target {
+            for definition in &mut section.definitions {
+                definition.translated = translated.next();
+            }
+        }
+    }
+    if let Some(wiki) = &mut result.sections.wikipedia {
+        if wiki.language != target && !wiki.summary.is_empty() {
+            wiki.translated_summary = translated.next();
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct TranslateRequest<'a> {
+    q: &'a [String],
+    source: &'a str,
+    target: &'a str,
+    format: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    api_key: Option<&'a str>,
+}
+
+// LibreTranslate answers a list of texts with a list, a single text with a
+// single string
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TranslatedText {
+    Many(Vec<String>),
+    One(String),
+}
+
+#[derive(Deserialize)]
+struct TranslateResponse {
+    #[serde(rename = "translatedText")]
+    translated_text: TranslatedText,
+}
+
+// All texts go out in one request
+async fn request(endpoint: &str, api_key: Option<&str>, texts: &[String], target: &str) -> Result<Vec<String>, LookupError> {
+    let url = format!("{}/translate", endpoint.trim_end_matches('/'));
+    let body = TranslateRequest {
+        q: texts,
+        source: "auto",
+        target,
+        format: "text",
+        api_key,
+    };
+
+    let response = http::client()
+        .post(&url)
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| http::request_error("translate", e))?;
+    if !response.status().is_success() {
+        return Err(LookupError::UpstreamStatus {
+            source: "LibreTranslate".to_string(),
+            status: response.status().as_u16(),
+        });
+    }
+
+    let text = response.text().await.map_err(|e| http::request_error("translate", e))?;
+    parse_response(&text)
+}
+
+fn parse_response(text: &str) -> Result<Vec<String>, LookupError> {
+    let response: TranslateResponse = serde_json::from_str(text).map_err(|_| LookupError::ParseError {
+        source: "translate".to_string(),
+        body_snippet: http::body_snippet(text),
+    })?;
+    Ok(match response.translated_text {
+        TranslatedText::Many(texts) => texts,
+        TranslatedText::One(text) => vec![text],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> LookupResult {
+        serde_json::from_str(include_str!("../tests/fixtures/lookup_result_serendipity.json")).unwrap()
+    }
+
+    #[test]
+    fn test_parse_response_shapes() {
+        assert_eq!(parse_response(r#"{"translatedText": ["uno", "dos"]}"#).unwrap(), vec!["uno", "dos"]);
+        assert_eq!(parse_response(r#"{"translatedText": "uno"}"#).unwrap(), vec!["uno"]);
+        assert!(parse_response(r#"{"error": "Invalid API key"}"#).is_err());
+    }
+
+    #[test]
+    fn test_translations_line_up_with_texts() {
+        let mut result = fixture();
+        let texts = collect_texts(&result, "es");
+        let definitions: usize = result.sections.definitions.iter().flatten().map(|s| s.definitions.len()).sum();
+        assert_eq!(texts.len(), definitions + 1);
+
+        let translated: Vec<String> = (0..texts.len()).map(|i| format!("es-{}", i)).collect();
+        apply_translations(&mut result, "es", translated);
+
+        let sections = result.sections.definitions.as_ref().unwrap();
+        assert_eq!(sections[0].definitions[0].translated.as_deref(), Some("es-0"));
+        let wiki = result.sections.wikipedia.as_ref().unwrap();
+        assert_eq!(wiki.translated_summary, Some(format!("es-{}", definitions)));
+
+        // Nothing to do when everything is already in the target language
+        assert!(collect_texts(&fixture(), "en").is_empty());
+    }
+}
@@ -0,0 +1,296 @@
+// Selections that are a quantity ("3.5 km", "20 °C") or a year ("1789")
+// rather than a word. Quantities are converted here from a small table, so
+// they work offline; years get a few facts worked out from the number.
+
+use crate::lookup::Interpretation;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Quantity {
+    Length,
+    Mass,
+    Temperature,
+    Volume,
+    Speed,
+    Time,
+}
+
+struct Unit {
+    symbol: &'static str,
+    // Plural, as it reads in "kilometres in miles"
+    name: &'static str,
+    // Lowercase spellings matched besides the symbol itself
+    aliases: &'static [&'static str],
+    quantity: Quantity,
+    // value * factor + offset is the amount in the quantity's base unit
+    factor: f64,
+    offset: f64,
+    // Symbols of the units it's converted to
+    to: &'static [&'static str],
+}
+
+const fn unit(
+    symbol: &'static str,
+    name: &'static str,
+    aliases: &'static [&'static str],
+    quantity: Quantity,
+    factor: f64,
+    to: &'static [&'static str],
+) -> Unit {
+    Unit { symbol, name, aliases, quantity, factor, offset: 0.0, to }
+}
+
+// "m" is both metres and minutes, so "10 m" lists both
+const UNITS: &[Unit] = &[
+    unit("km", "kilometres", &["km", "kms", "kilometre", "kilometres", "kilometer", "kilometers"], Quantity::Length, 1000.0, &["mi", "m"]),
+    unit("m", "metres", &["m", "metre", "metres", "meter", "meters"], Quantity::Length, 1.0, &["ft", "yd"]),
+    unit("cm", "centimetres", &["cm", "centimetre", "centimetres", "centimeter", "centimeters"], Quantity::Length, 0.01, &["in"]),
+    unit("mm", "millimetres", &["mm", "millimetre", "millimetres", "millimeter", "millimeters"], Quantity::Length, 0.001, &["in"]),
+    unit("mi", "miles", &["mi", "mile", "miles"], Quantity::Length, 1609.344, &["km"]),
+    unit("yd", "yards", &["yd", "yds", "yard", "yards"], Quantity::Length, 0.9144, &["m"]),
+    unit("ft", "feet", &["ft", "foot", "feet"], Quantity::Length, 0.3048, &["m"]),
+    unit("in", "inches", &["in", "inch", "inches"], Quantity::Length, 0.0254, &["cm"]),
+    unit("kg", "kilograms", &["kg", "kgs", "kilo", "kilos", "kilogram", "kilograms"], Quantity::Mass, 1.0, &["lb"]),
+    unit("g", "grams", &["g", "gram", "grams"], Quantity::Mass, 0.001, &["oz"]),
+    unit("lb", "pounds", &["lb", "lbs", "pound", "pounds"], Quantity::Mass, 0.453_592_37, &["kg"]),
+    unit("oz", "ounces", &["oz", "ounce", "ounces"], Quantity::Mass, 0.028_349_523_125, &["g"]),
+    Unit {
+        symbol: "°C",
+        name: "degrees Celsius",
+        aliases: &["°c", "℃", "c", "celsius", "degrees celsius", "degree celsius"],
+        quantity: Quantity::Temperature,
+        factor: 1.0,
+        offset: 273.15,
+        to: &["°F", "K"],
+    },
+    Unit {
+        symbol: "°F",
+        name: "degrees Fahrenheit",
+        aliases: &["°f", "℉", "f", "fahrenheit", "degrees fahrenheit", "degree fahrenheit"],
+        quantity: Quantity::Temperature,
+        factor: 5.0 / 9.0,
+        offset: 273.15 - 32.0 * 5.0 / 9.0,
+        to: &["°C"],
+    },
+    // Not "k", which is more often a thousand
+    unit("K", "kelvins", &["kelvin", "kelvins"], Quantity::Temperature, 1.0, &["°C"]),
+    unit("l", "litres", &["l", "litre", "litres", "liter", "liters"], Quantity::Volume, 1.0, &["gal"]),
+    unit("ml", "millilitres", &["ml", "millilitre", "millilitres", "milliliter", "milliliters"], Quantity::Volume, 0.001, &["fl oz"]),
+    unit("gal", "US gallons", &["gal", "gallon", "gallons"], Quantity::Volume, 3.785_411_784, &["l"]),
+    unit("fl oz", "US fluid ounces", &["fl oz", "fluid ounce", "fluid ounces"], Quantity::Volume, 0.029_573_529_562_5, &["ml"]),
+    unit("km/h", "kilometres per hour", &["km/h", "kmh", "kph"], Quantity::Speed, 1.0 / 3.6, &["mph"]),
+    unit("mph", "miles per hour", &["mph"], Quantity::Speed, 0.447_04, &["km/h"]),
+    unit("s", "seconds", &["s", "sec", "secs", "second", "seconds"], Quantity::Time, 1.0, &["min"]),
+    unit("min", "minutes", &["m", "min", "mins", "minute", "minutes"], Quantity::Time, 60.0, &["s", "h"]),
+    unit("h", "hours", &["h", "hr", "hrs", "hour", "hours"], Quantity::Time, 3600.0, &["min"]),
+];
+
+// Years from here to here are taken as years rather than plain numbers
+const FIRST_YEAR: i32 = 1000;
+const LAST_YEAR: i32 = 2999;
+
+// Significant digits conversions are shown with
+const SIGNIFICANT_DIGITS: i32 = 4;
+
+// "3.5 km", "-40°F", "10 m": a number followed by a unit from the table
+pub fn is_quantity(query: &str) -> bool {
+    !convert(query).is_empty()
+}
+
+// A bare four-digit year like "1789"
+pub fn parse_year(query: &str) -> Option<i32> {
+    let query = query.trim();
+    if query.len() != 4 || !query.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    query.parse().ok().filter(|year| (FIRST_YEAR..=LAST_YEAR).contains(year))
+}
+
+// Whether the query is answered by this module rather than the dictionaries
+pub fn is_computed(query: &str) -> bool {
+    parse_year(query).is_some() || is_quantity(query)
+}
+
+// The query in each unit it's converted to, once for every unit its
+// symbol could mean. Empty when it isn't a quantity.
+pub fn convert(query: &str) -> Vec<Interpretation> {
+    let Some((value, symbol)) = split_quantity(query) else {
+        return Vec::new();
+    };
+    let mut interpretations = Vec::new();
+    for from in UNITS.iter().filter(|unit| unit.matches(symbol)) {
+        let base = value * from.factor + from.offset;
+        for target in from.to.iter().filter_map(|symbol| find(symbol, from.quantity)) {
+            let converted = (base - target.offset) / target.factor;
+            interpretations.push(Interpretation {
+                label: format!("{} in {}", capitalize(from.name), target.name),
+                value: format!("{} {}", format_number(converted), target.symbol),
+            });
+        }
+    }
+    interpretations
+}
+
+// What can be said about `year` without looking anything up, counting
+// from `current_year`
+pub fn describe_year(year: i32, current_year: i32) -> Vec<Interpretation> {
+    let ago = match current_year - year {
+        0 => "This year".to_string(),
+        1 => "Last year".to_string(),
+        -1 => "Next year".to_string(),
+        n if n > 0 => format!("{} years ago", n),
+        n => format!("In {} years", -n),
+    };
+    let interpretation = |label: &str, value: String| Interpretation { label: label.to_string(), value };
+    vec![
+        interpretation("Year", ago),
+        interpretation("Century", format!("{} century", ordinal((year - 1) / 100 + 1))),
+        interpretation("Roman numerals", roman_numerals(year)),
+        interpretation("Leap year", if is_leap_year(year) { "Yes" } else { "No" }.to_string()),
+    ]
+}
+
+impl Unit {
+    fn matches(&self, symbol: &str) -> bool {
+        symbol == self.symbol || self.aliases.contains(&symbol.to_lowercase().as_str())
+    }
+}
+
+fn find(symbol: &str, quantity: Quantity) -> Option<&'static Unit> {
+    UNITS.iter().find(|unit| unit.symbol == symbol && unit.quantity == quantity)
+}
+
+// The number and the unit after it, which may be joined ("3.5km") or
+// spelled out ("20 degrees celsius")
+fn split_quantity(query: &str) -> Option<(f64, &str)> {
+    let query = query.trim();
+    let number_end = query
+        .char_indices()
+        .find(|&(i, c)| !(c.is_ascii_digit() || c == '.' || c == ',' || (i == 0 && (c == '-' || c == '−'))))
+        .map_or(query.len(), |(i, _)| i);
+    let (number, unit) = query.split_at(number_end);
+    let unit = unit.trim();
+    if unit.is_empty() || !number.chars().any(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some((parse_number(number)?, unit))
+}
+
+// "1,500" is fifteen hundred but "3,5" is three and a half
+fn parse_number(number: &str) -> Option<f64> {
+    let number = number.replace('−', "-");
+    let thousands = number.contains('.')
+        || number.rsplit(',').next().is_some_and(|last| last.len() == 3 && number.contains(','));
+    let number = if thousands { number.replace(',', "") } else { number.replace(',', ".") };
+    number.parse().ok().filter(|value: &f64| value.is_finite())
+}
+
+fn format_number(value: f64) -> String {
+    if value.abs() < 1e-9 {
+        return "0".to_string();
+    }
+    let magnitude = value.abs().log10().floor() as i32;
+    let decimals = (SIGNIFICANT_DIGITS - 1 - magnitude).clamp(0, 6) as usize;
+    let formatted = format!("{:.*}", decimals, value);
+    if formatted.contains('.') {
+        formatted.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        formatted
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn ordinal(n: i32) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", n, suffix)
+}
+
+fn roman_numerals(mut year: i32) -> String {
+    const NUMERALS: &[(i32, &str)] = &[
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+    let mut numerals = String::new();
+    for &(value, numeral) in NUMERALS {
+        while year >= value {
+            numerals.push_str(numeral);
+            year -= value;
+        }
+    }
+    numerals
+}
+
+fn is_leap_year(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(query: &str) -> Vec<String> {
+        convert(query).into_iter().map(|i| i.value).collect()
+    }
+
+    #[test]
+    fn test_convert_units() {
+        assert_eq!(values("3.5 km"), ["2.175 mi", "3500 m"]);
+        assert_eq!(values("3.5km"), values("3.5 km"));
+        assert_eq!(values("10 kg"), ["22.05 lb"]);
+        assert_eq!(values("20 °C"), ["68 °F", "293.1 K"]);
+        assert_eq!(values("-40°F"), ["-40 °C"]);
+        assert_eq!(values("32 degrees fahrenheit"), ["0 °C"]);
+        assert_eq!(values("1,500 mi"), ["2414 km"]);
+        assert_eq!(values("3,5 km"), values("3.5 km"));
+        assert_eq!(convert("5 lb")[0].label, "Pounds in kilograms");
+
+        assert!(!is_quantity("serendipity"));
+        assert!(!is_quantity("42"));
+        assert!(!is_quantity("5 apples"));
+        assert!(!is_quantity("km"));
+    }
+
+    #[test]
+    fn test_convert_ambiguous_unit() {
+        let labels: Vec<String> = convert("10 m").into_iter().map(|i| i.label).collect();
+        assert_eq!(labels, ["Metres in feet", "Metres in yards", "Minutes in seconds", "Minutes in hours"]);
+        assert_eq!(values("10 m"), ["32.81 ft", "10.94 yd", "600 s", "0.1667 h"]);
+    }
+
+    #[test]
+    fn test_years() {
+        assert_eq!(parse_year("1789"), Some(1789));
+        assert_eq!(parse_year("0042"), None);
+        assert_eq!(parse_year("12345"), None);
+        assert_eq!(parse_year("1789 km"), None);
+        assert!(is_computed("1789") && is_computed("10 m") && !is_computed("word"));
+
+        let facts: Vec<String> = describe_year(1789, 2024).into_iter().map(|i| i.value).collect();
+        assert_eq!(facts, ["235 years ago", "18th century", "MDCCLXXXIX", "No"]);
+        assert_eq!(describe_year(2000, 2000)[3].value, "Yes");
+        assert_eq!(describe_year(2001, 2000)[1].value, "21st century");
+    }
+}
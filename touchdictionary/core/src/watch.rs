@@ -0,0 +1,230 @@
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tracing::info;
+
+use crate::clipboard::{self, ClipboardBackend, Selection};
+
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(500);
+pub const DEFAULT_MAX_WORDS: usize = 5;
+
+// How the primary selection is watched
+#[derive(Debug, Clone)]
+pub struct WatchConfig {
+    pub poll_interval: Duration,
+    // A selection must stay unchanged this long before it's reported, so
+    // dragging across a sentence doesn't fire once per word
+    pub debounce: Duration,
+    // Longer selections are paragraphs being copied, not words to look up
+    pub max_words: usize,
+    // Clipboard backend to read with; every one in turn when None
+    pub backend: Option<ClipboardBackend>,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        WatchConfig {
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            debounce: DEFAULT_DEBOUNCE,
+            max_words: DEFAULT_MAX_WORDS,
+            backend: None,
+        }
+    }
+}
+
+impl WatchConfig {
+    // Defaults overridden by `TOUCHDICTIONARY_WATCH_INTERVAL_MS`,
+    // `TOUCHDICTIONARY_WATCH_DEBOUNCE_MS` and `TOUCHDICTIONARY_WATCH_MAX_WORDS`
+    pub fn from_env() -> Self {
+        let defaults = WatchConfig::default();
+        let env_u64 = |name: &str| std::env::var(name).ok().and_then(|v| v.trim().parse::<u64>().ok());
+        WatchConfig {
+            poll_interval: env_u64("TOUCHDICTIONARY_WATCH_INTERVAL_MS")
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.poll_interval),
+            debounce: env_u64("TOUCHDICTIONARY_WATCH_DEBOUNCE_MS")
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.debounce),
+            max_words: env_u64("TOUCHDICTIONARY_WATCH_MAX_WORDS")
+                .map(|n| n as usize)
+                .unwrap_or(defaults.max_words),
+            backend: clipboard::backend_from_env(),
+        }
+    }
+}
+
+// A new selection worth looking up
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SelectionEvent {
+    pub text: String,
+}
+
+// Start watching the primary selection in the background. The watcher stops
+// once the returned receiver is dropped.
+pub fn spawn(config: WatchConfig) -> mpsc::Receiver<SelectionEvent> {
+    let (tx, rx) = mpsc::channel(16);
+    tokio::spawn(run(config, tx));
+    rx
+}
+
+// Poll the primary selection until `tx` is closed
+pub async fn run(config: WatchConfig, tx: mpsc::Sender<SelectionEvent>) {
+    let backend = config.backend;
+    run_with(config, tx, move || clipboard::get_text(Selection::Primary, backend).ok()).await
+}
+
+async fn run_with<F>(config: WatchConfig, tx: mpsc::Sender<SelectionEvent>, read_selection: F)
+where
+    F: Fn() -> Option<String> + Send + Sync + Clone + 'static,
+{
+    info!("Watching selection every {:?}", config.poll_interval);
+
+    let mut debouncer = Debouncer::new(config.debounce, config.max_words);
+    let mut interval = tokio::time::interval(config.poll_interval);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    while !tx.is_closed() {
+        interval.tick().await;
+
+        // Reading the selection blocks on the X server or a wl-paste/xsel/xclip child
+        let read = read_selection.clone();
+        let selection = tokio::task::spawn_blocking(read).await.unwrap_or(None);
+
+        if let Some(text) = debouncer.observe(selection, Instant::now()) {
+            info!("Selection changed: '{}'", text);
+            if tx.send(SelectionEvent { text }).await.is_err() {
+                break;
+            }
+        }
+    }
+
+    info!("Selection watcher stopped");
+}
+
+// Turns raw selection polls into settled, de-duplicated selections
+struct Debouncer {
+    debounce: Duration,
+    max_words: usize,
+    pending: Option<(String, Instant)>,
+    last_emitted: Option<String>,
+}
+
+impl Debouncer {
+    fn new(debounce: Duration, max_words: usize) -> Self {
+        Debouncer {
+            debounce,
+            max_words,
+            pending: None,
+            last_emitted: None,
+        }
+    }
+
+    // Returns the selection once it has been stable for the debounce period.
+    // The same selection is never reported twice in a row, even if it is
+    // briefly cleared in between.
+    fn observe(&mut self, selection: Option<String>, now: Instant) -> Option<String> {
+        let text = match selection.map(|s| s.split_whitespace().collect::<Vec<_>>().join(" ")) {
+            Some(text)
+                if !text.is_empty()
+                    && text.split(' ').count() <= self.max_words
+                    && self.last_emitted.as_ref() != Some(&text) =>
+            {
+                text
+            }
+            _ => {
+                self.pending = None;
+                return None;
+            }
+        };
+
+        match &self.pending {
+            Some((pending, since)) if *pending == text => {
+                if now.duration_since(*since) >= self.debounce {
+                    self.pending = None;
+                    self.last_emitted = Some(text.clone());
+                    return Some(text);
+                }
+            }
+            _ => self.pending = Some((text, now)),
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_debouncer_waits_for_stable_selection() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut debouncer = Debouncer::new(Duration::from_millis(100), 3);
+
+        assert_eq!(debouncer.observe(Some("ser".into()), at(0)), None);
+        assert_eq!(debouncer.observe(Some("serendipity".into()), at(50)), None);
+        assert_eq!(debouncer.observe(Some("serendipity".into()), at(120)), None);
+        assert_eq!(debouncer.observe(Some("serendipity".into()), at(150)), Some("serendipity".to_string()));
+
+        // Same selection again, even after it was cleared, is not re-reported
+        assert_eq!(debouncer.observe(Some("serendipity".into()), at(300)), None);
+        assert_eq!(debouncer.observe(None, at(400)), None);
+        assert_eq!(debouncer.observe(Some(" serendipity ".into()), at(500)), None);
+        assert_eq!(debouncer.observe(Some("serendipity".into()), at(700)), None);
+    }
+
+    #[test]
+    fn test_debouncer_ignores_long_selections() {
+        let start = Instant::now();
+        let mut debouncer = Debouncer::new(Duration::ZERO, 3);
+
+        assert_eq!(debouncer.observe(Some("one two three four".into()), start), None);
+        assert_eq!(debouncer.observe(Some("one two three four".into()), start), None);
+        assert_eq!(debouncer.observe(Some("New York City".into()), start), None);
+        assert_eq!(debouncer.observe(Some("New York City".into()), start), Some("New York City".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_watcher_emits_each_new_selection_once() {
+        let selections = Arc::new(Mutex::new(
+            ["alpha", "alpha", "alpha", "beta", "beta", "beta", "beta"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect::<Vec<_>>(),
+        ));
+        let read = {
+            let selections = selections.clone();
+            move || {
+                let mut selections = selections.lock().unwrap();
+                if selections.len() > 1 {
+                    Some(selections.remove(0))
+                } else {
+                    selections.first().cloned()
+                }
+            }
+        };
+
+        let config = WatchConfig {
+            poll_interval: Duration::from_millis(5),
+            debounce: Duration::from_millis(1),
+            max_words: 5,
+            backend: None,
+        };
+        let (tx, mut rx) = mpsc::channel(16);
+        let watcher = tokio::spawn(run_with(config, tx, read));
+
+        assert_eq!(rx.recv().await.unwrap().text, "alpha");
+        assert_eq!(rx.recv().await.unwrap().text, "beta");
+
+        // "beta" stays selected but is never reported again
+        let again = tokio::time::timeout(Duration::from_millis(100), rx.recv()).await;
+        assert!(again.is_err());
+
+        drop(rx);
+        tokio::time::timeout(Duration::from_secs(1), watcher).await.unwrap().unwrap();
+    }
+}
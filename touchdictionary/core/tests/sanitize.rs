@@ -0,0 +1,57 @@
+// Definition text as the sources really send it, and what's left after
+// cleanup
+
+use touchdictionary_core::sanitize::{clean_definition_text, clean_paragraph};
+
+const DEFINITIONS: &[(&str, &str)] = &[
+    // Wiktionary sense with links and a non-breaking space
+    (
+        "<span class=\"use-with-mention\">To <a rel=\"mw:WikiLink\" href=\"/wiki/throw\" title=\"throw\">throw</a>&nbsp;hard</span>",
+        "To throw hard",
+    ),
+    // Escaped markup is text, and is only decoded once
+    ("The &lt;b&gt; element; written &amp;lt; in HTML", "The <b> element; written &lt; in HTML"),
+    // A bare comparison isn't a tag
+    ("A value x such that x < y and y > 0", "A value x such that x < y and y > 0"),
+    ("if i<n and n>0, step i", "if i<n and n>0, step i"),
+    ("Used to express affection, as in <3", "Used to express affection, as in <3"),
+    // Numeric entities, decimal and hex, and a stray ampersand
+    ("&#8220;Rock &#x26; roll&#8221; at AT&T", "“Rock & roll” at AT&T"),
+    // Wiki links, templates and emphasis
+    ("A [[domestic]] [[feline|cat]]{{lb|en|informal}}, ''esp.'' a '''kitten'''", "A domestic cat, esp. a kitten"),
+    // Numbering and bullets
+    ("1. (a) A small, round fruit", "A small, round fruit"),
+    ("• <li>Of or relating to cats</li>", "Of or relating to cats"),
+    // Trailing cross-references
+    ("A large wild cat of Africa and India. See also: tiger.", "A large wild cat of Africa and India."),
+    ("A young dog; see puppy", "A young dog"),
+    ("A domestic bird (see chicken) kept for eggs", "A domestic bird kept for eggs"),
+    ("See also: cat", ""),
+    // "see" in the middle of a sentence stays
+    ("To see what happens next", "To see what happens next"),
+    ("An overseer; one who oversees the work of others", "An overseer; one who oversees the work of others"),
+    // Whitespace from line breaks and tabs
+    ("  A\tlong\n\n  definition  <br/>spanning lines ", "A long definition spanning lines"),
+];
+
+const PARAGRAPHS: &[(&str, &str)] = &[
+    // Citation marks go
+    ("Paris is the capital of France.[1][23] It is on the Seine.[citation needed]", "Paris is the capital of France. It is on the Seine."),
+    // A paragraph can start with a number
+    ("1. FC Köln is a football club based in Cologne.", "1. FC Köln is a football club based in Cologne."),
+    ("<p>The <b>cat</b> (<i>Felis catus</i>) is a small carnivorous mammal.</p>", "The cat (Felis catus) is a small carnivorous mammal."),
+];
+
+#[test]
+fn test_clean_definition_text() {
+    for (raw, expected) in DEFINITIONS {
+        assert_eq!(clean_definition_text(raw), *expected, "cleaning {:?}", raw);
+    }
+}
+
+#[test]
+fn test_clean_paragraph() {
+    for (raw, expected) in PARAGRAPHS {
+        assert_eq!(clean_paragraph(raw), *expected, "cleaning {:?}", raw);
+    }
+}
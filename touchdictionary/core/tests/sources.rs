@@ -0,0 +1,504 @@
+// Sources against a local mock server standing in for the real APIs, so
+// parsing and error handling are checked on recorded response shapes
+
+mod support;
+
+use std::sync::{Arc, Once};
+use std::time::{Duration, Instant};
+
+use support::{get, json};
+use touchdictionary_core::config::Config;
+use touchdictionary_core::context::LookupContext;
+use touchdictionary_core::deferred;
+use touchdictionary_core::error::LookupError;
+use touchdictionary_core::lookup::{self, LookupOptions};
+use touchdictionary_core::sources::dictionary::DictionarySource;
+use touchdictionary_core::sources::wikipedia::WikipediaSource;
+use touchdictionary_core::sources::{Source, SourceRegistry, SourceResult};
+use wiremock::MockServer;
+
+const SERENDIPITY: &str = include_str!("fixtures/dictionary_serendipity.json");
+
+fn dictionary(server: &MockServer) -> DictionarySource {
+    DictionarySource { endpoints: vec![server.uri()] }
+}
+
+fn wikipedia(server: &MockServer) -> WikipediaSource {
+    WikipediaSource { max_related: 0, endpoint: server.uri() }
+}
+
+// Lookups through a context record history and fill the cache; keep both
+// in a scratch directory rather than the user's, fresh for each run
+fn use_scratch_dirs() {
+    static SCRATCH: Once = Once::new();
+    SCRATCH.call_once(|| {
+        let dir = std::env::temp_dir().join(format!("touchdictionary-sources-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::env::set_var("XDG_DATA_HOME", dir.join("data"));
+        std::env::set_var("XDG_CACHE_HOME", dir.join("cache"));
+    });
+}
+
+// Only the dictionary source, pointed at `server`
+fn dictionary_config(server: &MockServer) -> Config {
+    let mut config = Config::default();
+    for enabled in config.sources.values_mut() {
+        *enabled = false;
+    }
+    config.sources.insert("dictionary".to_string(), true);
+    config.dictionary.endpoints = vec![server.uri()];
+    config.http.request_timeout_ms = support::REQUEST_TIMEOUT.as_millis() as u64;
+    config
+}
+
+fn dictionary_context(server: &MockServer) -> LookupContext {
+    LookupContext::with_config(dictionary_config(server))
+}
+
+#[tokio::test]
+async fn test_dictionary_happy_path() {
+    support::configure_http();
+    let server = MockServer::start().await;
+    get("/en/serendipity").respond_with(json(200, SERENDIPITY)).mount(&server).await;
+
+    let result = dictionary(&server).fetch("Serendipity", "en").await.unwrap();
+    let SourceResult::Combined(results) = &result else {
+        panic!("expected definitions and synonyms, got {:?}", result);
+    };
+    let [SourceResult::Definitions(sections), SourceResult::Thesaurus(thesaurus)] = results.as_slice() else {
+        panic!("expected definitions and synonyms, got {:?}", results);
+    };
+    assert_eq!(sections.len(), 1);
+    assert_eq!(sections[0].source, "Free Dictionary API (127.0.0.1)");
+    assert_eq!(sections[0].definitions[0].part_of_speech.as_deref(), Some("noun"));
+    assert!(sections[0].definitions[0].definition.starts_with("An unsought, unintended"));
+    // The two entries with the same IPA are merged, keeping the audio
+    let phonetics = sections[0].phonetics.as_ref().unwrap();
+    assert_eq!(phonetics.len(), 1);
+    assert!(phonetics[0].audio_url.as_deref().unwrap().ends_with("serendipity-us.mp3"));
+    // The meaning's synonyms go with its first sense and into the thesaurus
+    assert_eq!(sections[0].definitions[0].synonyms, vec!["chance", "fluke", "luck"]);
+    assert_eq!(thesaurus.synonyms, vec!["chance", "fluke", "luck"]);
+    assert!(thesaurus.antonyms.is_empty());
+    assert_eq!(support::requests(&server).await, vec!["/en/serendipity"]);
+}
+
+#[tokio::test]
+async fn test_dictionary_not_found() {
+    support::configure_http();
+    let server = MockServer::start().await;
+    get("/en/zzxq").respond_with(json(404, include_str!("fixtures/dictionary_not_found.json"))).mount(&server).await;
+
+    let result = dictionary(&server).fetch("zzxq", "en").await.unwrap();
+    assert!(result.is_empty(), "{:?}", result);
+}
+
+// Some CDNs pass the API's error object through with a 200
+#[tokio::test]
+async fn test_dictionary_not_found_with_ok_status() {
+    support::configure_http();
+    let server = MockServer::start().await;
+    get("/en/zzxq").respond_with(json(200, include_str!("fixtures/dictionary_not_found.json"))).mount(&server).await;
+
+    let result = dictionary(&server).fetch("zzxq", "en").await.unwrap();
+    assert!(result.is_empty(), "{:?}", result);
+}
+
+#[tokio::test]
+async fn test_dictionary_error_object() {
+    support::configure_http();
+    let server = MockServer::start().await;
+    get("/en/serendipity").respond_with(json(200, include_str!("fixtures/dictionary_upstream_error.json"))).mount(&server).await;
+
+    let err = dictionary(&server).fetch("serendipity", "en").await.unwrap_err();
+    assert!(
+        matches!(&err, LookupError::Upstream { title, message } if title == "Something Went Wrong" && message.starts_with("Sorry pal")),
+        "{:?}",
+        err
+    );
+    assert_eq!(err.kind(), "upstream");
+}
+
+#[tokio::test]
+async fn test_dictionary_malformed_json() {
+    support::configure_http();
+    let server = MockServer::start().await;
+    get("/en/serendipity").respond_with(json(200, r#"[{"word": "serendipity", "meanings": "#)).mount(&server).await;
+
+    let err = dictionary(&server).fetch("serendipity", "en").await.unwrap_err();
+    assert!(
+        matches!(&err, LookupError::ParseError { source, body_snippet } if source == "dictionary" && body_snippet.starts_with("[{\"word\"")),
+        "{:?}",
+        err
+    );
+}
+
+#[tokio::test]
+async fn test_wikipedia_disambiguation() {
+    support::configure_http();
+    let server = MockServer::start().await;
+    get("/page/summary/Mercury?redirect=true")
+        .respond_with(json(200, include_str!("fixtures/wikipedia_summary_mercury.json")))
+        .mount(&server)
+        .await;
+
+    let err = wikipedia(&server).fetch("Mercury", "en").await.unwrap_err();
+    assert!(err.is_not_found(), "{:?}", err);
+}
+
+#[tokio::test]
+async fn test_wikipedia_not_found() {
+    support::configure_http();
+    let server = MockServer::start().await;
+
+    let err = wikipedia(&server).fetch("Zzxq Qxzz", "en").await.unwrap_err();
+    assert!(err.is_not_found(), "{:?}", err);
+    assert_eq!(
+        support::requests(&server).await,
+        vec![
+            "/page/summary/Zzxq_Qxzz?redirect=true",
+            "/w/api.php?action=query&titles=Zzxq+Qxzz&redirects=1&format=json&formatversion=2",
+        ]
+    );
+}
+
+// A summary response for the article `title`
+fn wikipedia_summary(title: &str) -> String {
+    serde_json::json!({
+        "title": title,
+        "extract": format!("{} is the subject of this article.", title),
+        "content_urls": { "desktop": { "page": format!("https://en.wikipedia.org/wiki/{}", title.replace(' ', "_")) } },
+    })
+    .to_string()
+}
+
+// A list=search response with these titles, best first
+fn wikipedia_search(titles: &[&str]) -> String {
+    let hits: Vec<_> = titles.iter().map(|title| serde_json::json!({ "ns": 0, "title": title })).collect();
+    serde_json::json!({ "query": { "search": hits } }).to_string()
+}
+
+#[tokio::test]
+async fn test_wikipedia_qualified_title_found_directly() {
+    support::configure_http();
+    let server = MockServer::start().await;
+    get("/page/summary/Python_(programming_language)?redirect=true")
+        .respond_with(json(200, wikipedia_summary("Python (programming language)")))
+        .mount(&server)
+        .await;
+
+    let SourceResult::Wikipedia(wiki) = wikipedia(&server).fetch("Python (programming language)", "en").await.unwrap() else {
+        panic!("expected a Wikipedia section");
+    };
+    assert_eq!(wiki.title, "Python (programming language)");
+    assert_eq!(wiki.resolved_title, None);
+    assert_eq!(support::requests(&server).await, vec!["/page/summary/Python_(programming_language)?redirect=true"]);
+}
+
+#[tokio::test]
+async fn test_wikipedia_qualified_title_resolved_by_search() {
+    support::configure_http();
+    let server = MockServer::start().await;
+    get("/w/api.php?action=query&list=search&srsearch=Mercury+element&srlimit=10&srprop=&format=json")
+        .respond_with(json(200, wikipedia_search(&["Mercury (planet)", "Mercury poisoning", "Mercury (chemical element)"])))
+        .mount(&server)
+        .await;
+    get("/page/summary/Mercury_(chemical_element)?redirect=true")
+        .respond_with(json(200, wikipedia_summary("Mercury (chemical element)")))
+        .mount(&server)
+        .await;
+
+    let SourceResult::Wikipedia(wiki) = wikipedia(&server).fetch("Mercury (element)", "en").await.unwrap() else {
+        panic!("expected a Wikipedia section");
+    };
+    assert_eq!(wiki.title, "Mercury (chemical element)");
+    assert_eq!(wiki.resolved_title.as_deref(), Some("Mercury (chemical element)"));
+}
+
+#[tokio::test]
+async fn test_wikipedia_qualifier_matching_nothing() {
+    support::configure_http();
+    let server = MockServer::start().await;
+    let search = "/w/api.php?action=query&list=search&srsearch=Python+kitchen+appliance&srlimit=10&srprop=&format=json";
+    get(search)
+        .respond_with(json(200, wikipedia_search(&["Python (programming language)", "Pythonidae", "Kitchen appliance"])))
+        .mount(&server)
+        .await;
+
+    let err = wikipedia(&server).fetch("Python (kitchen appliance)", "en").await.unwrap_err();
+    assert!(err.is_not_found(), "{:?}", err);
+    assert_eq!(
+        support::requests(&server).await,
+        vec![
+            "/page/summary/Python_(kitchen_appliance)?redirect=true",
+            "/w/api.php?action=query&titles=Python+%28kitchen+appliance%29&redirects=1&format=json&formatversion=2",
+            search,
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_wikipedia_redirect_followed_by_rest_api() {
+    support::configure_http();
+    let server = MockServer::start().await;
+    let mut color = serde_json::from_str::<serde_json::Value>(&wikipedia_summary("Color")).unwrap();
+    color["titles"] = serde_json::json!({ "canonical": "Color", "normalized": "Color" });
+    get("/page/summary/colour?redirect=true").respond_with(json(200, color.to_string())).mount(&server).await;
+
+    let SourceResult::Wikipedia(wiki) = wikipedia(&server).fetch("colour", "en").await.unwrap() else {
+        panic!("expected a Wikipedia section");
+    };
+    assert_eq!(wiki.title, "Color");
+    assert_eq!(wiki.canonical_title.as_deref(), Some("Color"));
+    assert_eq!(wiki.redirected_from.as_deref(), Some("colour"));
+}
+
+#[tokio::test]
+async fn test_wikipedia_redirect_chain_resolved_after_404() {
+    support::configure_http();
+    let server = MockServer::start().await;
+    get("/w/api.php?action=query&titles=usa&redirects=1&format=json&formatversion=2")
+        .respond_with(json(200, include_str!("fixtures/wikipedia_redirects_usa.json")))
+        .mount(&server)
+        .await;
+    get("/page/summary/United_States?redirect=true")
+        .respond_with(json(200, wikipedia_summary("United States")))
+        .mount(&server)
+        .await;
+
+    let SourceResult::Wikipedia(wiki) = wikipedia(&server).fetch("usa", "en").await.unwrap() else {
+        panic!("expected a Wikipedia section");
+    };
+    assert_eq!(wiki.title, "United States");
+    assert_eq!(wiki.redirected_from.as_deref(), Some("usa"));
+    assert_eq!(
+        support::requests(&server).await,
+        vec![
+            "/page/summary/usa?redirect=true",
+            "/w/api.php?action=query&titles=usa&redirects=1&format=json&formatversion=2",
+            "/page/summary/United_States?redirect=true",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_timeout_is_retried_then_reported() {
+    support::configure_http();
+    let server = MockServer::start().await;
+    get("/page/summary/Photon?redirect=true")
+        .respond_with(json(200, include_str!("fixtures/wikipedia_summary_photon.json")).set_delay(support::REQUEST_TIMEOUT * 3))
+        .mount(&server)
+        .await;
+
+    let err = wikipedia(&server).fetch("Photon", "en").await.unwrap_err();
+    match &err {
+        LookupError::RetriesExhausted { attempts, last } => {
+            assert_eq!(*attempts, 3);
+            assert!(matches!(**last, LookupError::Timeout { ref source } if source == "wikipedia"), "{:?}", last);
+        }
+        other => panic!("expected retries to run out, got {:?}", other),
+    }
+    assert_eq!(support::requests(&server).await.len(), 3);
+}
+
+#[tokio::test]
+async fn test_rate_limit_waits_for_retry_after() {
+    support::configure_http();
+    let server = MockServer::start().await;
+    get("/en/serendipity")
+        .respond_with(json(429, r#"{"message": "Too many requests"}"#).insert_header("Retry-After", "1"))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    get("/en/serendipity").respond_with(json(200, SERENDIPITY)).mount(&server).await;
+
+    let started = Instant::now();
+    let result = dictionary(&server).fetch("serendipity", "en").await.unwrap();
+    assert!(!result.is_empty());
+    assert!(started.elapsed() >= Duration::from_secs(1), "retried after {:?}", started.elapsed());
+    assert_eq!(support::requests(&server).await.len(), 2);
+}
+
+#[tokio::test]
+async fn test_rate_limit_beyond_patience_is_reported() {
+    support::configure_http();
+    let server = MockServer::start().await;
+    get("/en/serendipity").respond_with(json(429, "").insert_header("Retry-After", "120")).mount(&server).await;
+
+    let err = dictionary(&server).fetch("serendipity", "en").await.unwrap_err();
+    assert!(
+        matches!(err, LookupError::RateLimited { retry_after: Some(wait) } if wait == Duration::from_secs(120)),
+        "{:?}",
+        err
+    );
+    assert_eq!(support::requests(&server).await.len(), 1);
+}
+
+#[tokio::test]
+async fn test_config_points_sources_at_endpoints() {
+    support::configure_http();
+    let server = MockServer::start().await;
+    get("/en/serendipity").respond_with(json(200, SERENDIPITY)).mount(&server).await;
+    get("/page/summary/Photon?redirect=true")
+        .respond_with(json(200, include_str!("fixtures/wikipedia_summary_photon.json")))
+        .mount(&server)
+        .await;
+    get("/page/related/Photon")
+        .respond_with(json(200, include_str!("fixtures/wikipedia_related_paris.json")))
+        .mount(&server)
+        .await;
+
+    let mut config = Config::default();
+    config.dictionary.endpoints = vec![server.uri()];
+    config.wikipedia.endpoint = server.uri();
+    let registry = SourceRegistry::from_config(&config);
+    let source = |name: &str| registry.sources().find(|s| s.name() == name).unwrap();
+
+    let definitions = source("dictionary").fetch("serendipity", "en").await.unwrap();
+    assert!(!definitions.is_empty());
+
+    let SourceResult::Wikipedia(wiki) = source("wikipedia").fetch("Photon", "en").await.unwrap() else {
+        panic!("expected a Wikipedia section");
+    };
+    assert_eq!(wiki.title, "Photon");
+    assert_eq!(wiki.url, "https://en.wikipedia.org/wiki/Photon");
+    assert!(!wiki.related.is_empty());
+}
+
+#[tokio::test]
+async fn test_entity_lookup_adds_wikidata_facts() {
+    use_scratch_dirs();
+    let server = MockServer::start().await;
+    let mut summary = serde_json::from_str::<serde_json::Value>(&wikipedia_summary("Albert Einstein")).unwrap();
+    summary["wikibase_item"] = "Q937".into();
+    get("/page/summary/Albert_Einstein?redirect=true").respond_with(json(200, summary.to_string())).mount(&server).await;
+    let claim = |value: serde_json::Value| serde_json::json!({ "mainsnak": { "datavalue": value }, "rank": "normal" });
+    let claims = serde_json::json!({
+        "claims": {
+            "P569": [claim(serde_json::json!({
+                "type": "time",
+                "value": { "time": "+1879-03-14T00:00:00Z", "precision": 11 }
+            }))],
+            "P106": [
+                claim(serde_json::json!({ "type": "wikibase-entityid", "value": { "id": "Q169470" } })),
+                claim(serde_json::json!({ "type": "wikibase-entityid", "value": { "id": "Q37226" } }))
+            ],
+            "P19": [claim(serde_json::json!({ "type": "wikibase-entityid", "value": { "id": "Q3012" } }))]
+        }
+    });
+    get("/?action=wbgetclaims&entity=Q937&format=json").respond_with(json(200, claims.to_string())).mount(&server).await;
+    let labels = serde_json::json!({
+        "entities": {
+            "Q169470": { "labels": { "en": { "language": "en", "value": "physicist" } } },
+            "Q37226": { "labels": { "en": { "language": "en", "value": "teacher" } } }
+        }
+    });
+    get("/?action=wbgetentities&ids=Q169470%7CQ37226&props=labels&languages=en&format=json")
+        .respond_with(json(200, labels.to_string()))
+        .mount(&server)
+        .await;
+
+    let mut config = Config::default();
+    for enabled in config.sources.values_mut() {
+        *enabled = false;
+    }
+    config.sources.insert("wikipedia".to_string(), true);
+    config.sources.insert("wikidata".to_string(), true);
+    config.auto_detect_language = false;
+    config.wikipedia.max_related = 0;
+    config.wikipedia.endpoint = server.uri();
+    config.wikidata.endpoint = server.uri();
+    let ctx = LookupContext::with_config(config);
+
+    let options = LookupOptions { use_cache: false, lang: Some("en".to_string()), ..LookupOptions::default() };
+    let result = lookup::lookup_with(&ctx, "Albert Einstein", &options).await.unwrap();
+    let facts: Vec<_> = result.sections.infobox.unwrap().facts.into_iter().map(|f| (f.label, f.value)).collect();
+    assert_eq!(
+        facts,
+        vec![
+            ("Born".to_string(), "14 March 1879".to_string()),
+            ("Occupation".to_string(), "physicist, teacher".to_string()),
+        ]
+    );
+    assert_eq!(support::requests(&server).await.len(), 3);
+}
+
+#[tokio::test]
+async fn test_identical_concurrent_lookups_share_one_request() {
+    use_scratch_dirs();
+    let server = MockServer::start().await;
+    let shared = get("/en/serendipity")
+        .respond_with(json(200, SERENDIPITY).set_delay(Duration::from_millis(300)))
+        .expect(1)
+        .mount_as_scoped(&server)
+        .await;
+
+    let ctx = Arc::new(dictionary_context(&server));
+
+    let lookups: Vec<_> = (0..5)
+        .map(|_| {
+            let ctx = ctx.clone();
+            tokio::spawn(async move {
+                let options = LookupOptions { use_cache: false, ..LookupOptions::default() };
+                lookup::lookup_with(&ctx, "serendipity", &options).await
+            })
+        })
+        .collect();
+    for lookup in lookups {
+        let result = lookup.await.unwrap().unwrap();
+        assert!(result.sections.definitions.is_some());
+    }
+    drop(shared);
+
+    // Once it has finished, the same lookup goes out again
+    get("/en/serendipity").respond_with(json(200, SERENDIPITY)).expect(1).mount(&server).await;
+    let options = LookupOptions { use_cache: false, ..LookupOptions::default() };
+    lookup::lookup_with(&ctx, "serendipity", &options).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_prefetch_warms_the_dictionary_cache() {
+    use_scratch_dirs();
+    let server = MockServer::start().await;
+    get("/en/glad").respond_with(json(200, SERENDIPITY)).mount(&server).await;
+    get("/en/cheerful").respond_with(json(200, SERENDIPITY)).mount(&server).await;
+    let ctx = dictionary_context(&server);
+
+    let words = ["glad", "cheerful", "zzxq", " "].map(String::from);
+    assert_eq!(lookup::prefetch(&ctx, &words).await, 2);
+    assert_eq!(support::requests(&server).await.len(), 3);
+
+    // Cached words aren't fetched again; the one with no entry is
+    assert_eq!(lookup::prefetch(&ctx, &words).await, 0);
+    assert_eq!(support::requests(&server).await.len(), 4);
+
+    // Tapping a prefetched word is answered from the cache
+    let result = lookup::lookup_with(&ctx, "glad", &LookupOptions::default()).await.unwrap();
+    assert!(result.from_cache);
+    assert_eq!(support::requests(&server).await.len(), 4);
+}
+
+#[tokio::test]
+async fn test_lookup_failed_offline_runs_again_once_back() {
+    use_scratch_dirs();
+    let server = MockServer::start().await;
+    get("/en/whimsy")
+        .respond_with(json(200, SERENDIPITY).set_delay(support::REQUEST_TIMEOUT * 3))
+        .up_to_n_times(3)
+        .mount(&server)
+        .await;
+    get("/en/whimsy").respond_with(json(200, SERENDIPITY)).mount(&server).await;
+    let mut config = dictionary_config(&server);
+    config.deferred.probe_url = format!("{}/probe", server.uri());
+    let ctx = LookupContext::with_config(config);
+
+    let err = lookup::lookup_with(&ctx, "whimsy", &LookupOptions::default()).await.unwrap_err();
+    assert!(err.is_network(), "{:?}", err);
+    assert_eq!(ctx.deferred().queries(), ["whimsy"]);
+
+    let results = deferred::retry_if_online(&ctx).await;
+    assert_eq!(results.len(), 1);
+    assert!(results[0].sections.definitions.is_some());
+    assert!(ctx.deferred().is_empty());
+    assert_eq!(support::requests(&server).await.iter().filter(|path| *path == "/probe").count(), 1);
+}
@@ -0,0 +1,52 @@
+// Helpers for pointing sources at a wiremock server with canned responses.
+// Mocks match a path and query string exactly; anything unmatched gets an
+// empty 404.
+
+use std::time::Duration;
+
+use touchdictionary_core::http::{self, HttpConfig};
+use wiremock::matchers::method;
+use wiremock::{Match, MockBuilder, MockServer, Request, ResponseTemplate};
+
+// Request timeout every test runs with, short enough for timeout tests
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(1);
+
+// Sources share one global client; give it test timeouts and no proxy
+// from the environment in the way of localhost
+pub fn configure_http() {
+    http::configure(&HttpConfig {
+        connect_timeout: REQUEST_TIMEOUT,
+        request_timeout: REQUEST_TIMEOUT,
+        ..HttpConfig::default()
+    });
+}
+
+// The path with its query string, as the sources build it
+fn path_and_query(request: &Request) -> String {
+    match request.url.query() {
+        Some(query) => format!("{}?{}", request.url.path(), query),
+        None => request.url.path().to_string(),
+    }
+}
+
+struct PathAndQuery(String);
+
+impl Match for PathAndQuery {
+    fn matches(&self, request: &Request) -> bool {
+        path_and_query(request) == self.0
+    }
+}
+
+// A GET of exactly `path_and_query`
+pub fn get(path_and_query: &str) -> MockBuilder {
+    wiremock::Mock::given(method("GET")).and(PathAndQuery(path_and_query.to_string()))
+}
+
+pub fn json(status: u16, body: impl Into<String>) -> ResponseTemplate {
+    ResponseTemplate::new(status).set_body_raw(body.into(), "application/json")
+}
+
+// Paths asked for, in order
+pub async fn requests(server: &MockServer) -> Vec<String> {
+    server.received_requests().await.unwrap_or_default().iter().map(path_and_query).collect()
+}
@@ -1,15 +1,52 @@
-import { useState, useEffect } from 'react'
-import { invoke } from '@tauri-apps/api/core'
+import { useState, useEffect, useRef, type MouseEvent, type ReactNode } from 'react'
+import { convertFileSrc, invoke } from '@tauri-apps/api/core'
+import { listen } from '@tauri-apps/api/event'
 import { getCurrentWindow } from '@tauri-apps/api/window'
 import { open } from '@tauri-apps/plugin-shell'
 import './App.css'
 
+interface RelatedPage {
+  title: string
+  description?: string
+  thumbnail_url?: string
+  url: string
+}
+
 interface WikipediaSection {
   title: string
   summary: string
   paragraphs: string[]
   image_url?: string
   url: string
+  language: string
+  related: RelatedPage[]
+  translated_summary?: string
+  // Refetched and different from the copy that was cached
+  changed_since_cache?: boolean
+  // Paragraphs cut short in a summary result; get_full_section has them all
+  is_truncated?: boolean
+  // Article a search found when the qualified title asked for didn't exist
+  resolved_title?: string
+  canonical_title?: string
+  redirected_from?: string
+  wikidata_id?: string
+}
+
+// From get_wikipedia_article. `html` is already reduced to paragraphs,
+// lists and emphasis; links carry a data-title instead of an href.
+interface ArticleSection {
+  heading: string
+  level: number
+  html: string
+  text: string
+}
+
+interface ArticlePage {
+  title: string
+  url: string
+  sections: ArticleSection[]
+  total_sections: number
+  next_offset?: number
 }
 
 interface Definition {
@@ -17,21 +54,357 @@ interface Definition {
   part_of_speech?: string
   definition: string
   example?: string
+  translated?: string
+  synonyms?: string[]
+  antonyms?: string[]
+  // Every source that gave it, when near-identical ones were merged
+  sources?: string[]
+}
+
+interface Phonetic {
+  text?: string
+  audio_url?: string
 }
 
 interface DefinitionSection {
   source: string
   definitions: Definition[]
+  phonetics?: Phonetic[]
+  language: string
+  // Cut down to the configured maximum; total_available is how many there are
+  truncated?: boolean
+  total_available?: number
+  changed_since_cache?: boolean
+  // Examples after the first left out of a summary result
+  examples_truncated?: boolean
+}
+
+interface ThesaurusSection {
+  synonyms: string[]
+  antonyms: string[]
+  related_terms: string[]
+  language: string
+}
+
+interface EtymologySection {
+  source: string
+  text: string
+  language_of_origin?: string
+}
+
+// Usage sentences; occurrences[i] holds [start, length] of the word in
+// sentences[i], in characters
+interface ExamplesSection {
+  source: string
+  sentences: string[]
+  occurrences: [number, number][][]
+}
+
+// What an acronym may stand for, with the page each expansion came from
+interface AcronymSection {
+  expansions: { expansion: string; description?: string; url?: string }[]
+}
+
+// Rhymes for the word, best first; a word is only in one of the lists
+interface RhymesSection {
+  perfect: string[]
+  near: string[]
+  sounds_like: string[]
+}
+
+// Words fitting a wildcard pattern such as c?t, best first
+interface PatternSection {
+  matches: { word: string; score: number }[]
+}
+
+// A quantity in other units, or facts about a year
+interface ComputedSection {
+  input: string
+  interpretations: { label: string; value: string }[]
+}
+
+// Facts about an entity from Wikidata, like a birth date or population
+interface InfoboxSection {
+  facts: { label: string; value: string }[]
+}
+
+interface LookupError {
+  kind: 'empty_query' | 'invalid_pattern' | 'not_found' | 'network' | 'timeout' | 'parse_error' | 'rate_limited' | 'upstream_status' | 'upstream' | 'unsupported_language' | 'cancelled' | 'offline_unavailable' | 'all_sources_failed' | 'selection_rejected' | 'private_mode_blocked'
+  message: string
+  // For selection_rejected: a link or mailto: address to open instead
+  open_target?: string | null
+}
+
+// Kinds of failure worth checking the connection for
+const CONNECTIVITY_ERRORS: LookupError['kind'][] = ['network', 'timeout', 'all_sources_failed']
+
+// One source's answer to check_sources
+interface SourceHealth {
+  name: string
+  reachable: boolean
+  latency_ms: number
+  status_detail: string
+}
+
+// Full when every source answered; Partial names the ones that failed
+type Completeness = 'Full' | 'Empty' | { Partial: { failed_sources: string[] } }
+
+interface SourceStatus {
+  name: string
+  outcome: 'Ok' | 'Empty' | 'Error' | 'Skipped' | 'TimedOut' | 'Cached' | 'PrivateModeBlocked'
+  duration_ms: number
+  error?: string
+}
+
+// Sources feeding each section, for the status badges
+const SECTION_SOURCES: Record<string, string[]> = {
+  acronym: ['acronym'],
+  dictionary: ['local_glossary', 'dictionary', 'merriam_webster', 'wiktionary', 'offline', 'urban_dictionary'],
+  etymology: ['etymology'],
+  examples: ['examples'],
+  rhymes: ['rhymes'],
+  thesaurus: ['dictionary', 'thesaurus'],
+  wikipedia: ['wikipedia'],
+}
+
+// "de" -> "German", falling back to the code where the webview can't name it
+const languageName = (code: string) => {
+  try {
+    return new Intl.DisplayNames(['en'], { type: 'language' }).of(code) ?? code
+  } catch {
+    return code
+  }
+}
+
+// Horizontal distance in pixels that counts as a swipe through recent results
+const SWIPE_DISTANCE = 80
+
+type FrequencyBand = 'VeryCommon' | 'Common' | 'Moderate' | 'Uncommon' | 'Rare'
+
+const FREQUENCY_LABELS: Record<FrequencyBand, string> = {
+  VeryCommon: 'very common',
+  Common: 'common',
+  Moderate: 'moderately common',
+  Uncommon: 'uncommon',
+  Rare: 'rare',
+}
+
+interface WordFrequency {
+  zipf: number
+  rank: number
+  band: FrequencyBand
+}
+
+const BADGE_LABELS: Partial<Record<SourceStatus['outcome'], string>> = {
+  Cached: 'cached',
+  Error: 'error',
+  TimedOut: 'timed out',
+  PrivateModeBlocked: 'not asked',
 }
 
 interface LookupResult {
   query: string
-  content_type: 'Word' | 'Entity' | 'Mixed'
+  display_query: string
+  content_type: 'Word' | 'Entity' | 'Mixed' | 'Passage' | 'Acronym' | 'Pattern' | 'Computed'
   error?: string
+  from_cache: boolean
+  // Reopened from get_last_result or get_recent_results
+  from_session_cache?: boolean
+  suggestions?: string[]
+  corrected_from?: string
+  lemma_of?: string
+  // How common the word is in English, for single words
+  frequency?: WordFrequency | null
+  language: string
+  // Language the lookup ran in, when auto-detection switched from language
+  language_used?: string
+  // The query's own language, when it could be told
+  detected_language?: string
+  // The system locale's language, when lookups support it
+  locale_language?: string
+  // Definitions left out by a part-of-speech filter, by part of speech
+  hidden_definitions?: Record<string, number>
+  warnings: string[]
+  is_word_of_the_day?: boolean
+  // Query exactly as sent to run_lookup_command
+  requested_query?: string
+  source_statuses?: SourceStatus[]
+  completeness?: Completeness
   sections: {
     definitions?: DefinitionSection[]
     wikipedia?: WikipediaSection
+    thesaurus?: ThesaurusSection
+    etymology?: EtymologySection
+    examples?: ExamplesSection
+    acronym?: AcronymSection
+    pattern?: PatternSection
+    rhymes?: RhymesSection
+    computed?: ComputedSection
+    infobox?: InfoboxSection
+    keyword_lookups?: KeywordLookup[]
+  }
+}
+
+// One keyword of a selected passage, looked up on its own
+interface KeywordLookup {
+  keyword: string
+  result: LookupResult
+}
+
+// From get_initial_query and new-query events: command-line words, a
+// selection, or a touchdict:// link, which may name a language
+interface LookupRequest {
+  query: string
+  lang?: string
+}
+
+// One hit from search_result; group/index locate the definition or paragraph
+interface SearchMatch {
+  section: 'definition' | 'example' | 'wikipedia'
+  group: number
+  index: number
+  snippet: string
+  match_start: number
+  match_len: number
+  fuzzy: boolean
+}
+
+// One lookup in the breadcrumb trail from get_history_chain
+interface HistoryEntry {
+  query: string
+  display_query: string
+  referrer?: string
+}
+
+// A double-clicked selection worth looking up: a single word
+const NESTED_WORD = /^[\p{L}][\p{L}'’-]*$/u
+
+// DOM id of the element a search match is in
+const matchElementId = (match: SearchMatch) =>
+  match.section === 'wikipedia' ? `wiki-${match.index}` : `def-${match.group}-${match.index}`
+
+// A section streamed from run_lookup_streaming before the full result
+interface SectionUpdate {
+  source: string
+  section: 'definitions' | 'wikipedia' | 'thesaurus' | 'etymology' | 'examples' | 'acronym' | 'rhymes'
+    | 'source_started' | 'source_finished'
+  payload: unknown
+  requested_query: string
+}
+
+// A sentence with each occurrence of the word in bold
+const emboldened = (sentence: string, occurrences: [number, number][] = []) => {
+  const chars = Array.from(sentence)
+  const parts: ReactNode[] = []
+  let position = 0
+  for (const [start, length] of occurrences) {
+    parts.push(chars.slice(position, start).join(''))
+    parts.push(<strong key={start}>{chars.slice(start, start + length).join('')}</strong>)
+    position = start + length
+  }
+  parts.push(chars.slice(position).join(''))
+  return parts
+}
+
+// Fold a streamed section into the partial result shown while the slower
+// sources are still running
+const mergeSection = (previous: LookupResult | null, update: SectionUpdate): LookupResult => {
+  const base: LookupResult = previous ?? {
+    query: update.requested_query,
+    display_query: update.requested_query,
+    content_type: 'Word',
+    from_cache: false,
+    language: 'en',
+    warnings: [],
+    sections: {},
   }
+  const sections = update.section === 'definitions'
+    ? { ...base.sections, definitions: [...(base.sections.definitions ?? []), ...(update.payload as DefinitionSection[])] }
+    : { ...base.sections, [update.section]: update.payload }
+  return { ...base, sections }
+}
+
+// The `theme` preference; 'auto' follows the OS
+type ThemePreference = 'auto' | 'light' | 'dark'
+type SystemTheme = 'light' | 'dark' | 'unknown'
+
+// Sets data-theme on <html> from the preference, or with 'auto' from the
+// OS. When the backend can't tell, the webview's own color scheme decides.
+function useTheme() {
+  const [preference, setPreference] = useState<ThemePreference>('auto')
+  const [system, setSystem] = useState<SystemTheme>('unknown')
+  const [webviewDark, setWebviewDark] = useState(() => window.matchMedia('(prefers-color-scheme: dark)').matches)
+
+  useEffect(() => {
+    invoke<{ theme: ThemePreference }>('get_preferences')
+      .then((preferences) => setPreference(preferences.theme))
+      .catch((error) => console.warn('[WARN] [touchdictionary] [gui] Failed to load preferences:', error))
+    invoke<SystemTheme>('get_system_theme')
+      .then(setSystem)
+      .catch((error) => console.warn('[WARN] [touchdictionary] [gui] Failed to get the system theme:', error))
+
+    const unlistenPreferences = listen<{ theme: ThemePreference }>('preferences-changed', (event) => {
+      setPreference(event.payload.theme)
+    })
+    const unlistenSystem = listen<SystemTheme>('system-theme-changed', (event) => setSystem(event.payload))
+    const media = window.matchMedia('(prefers-color-scheme: dark)')
+    const onMediaChange = (event: MediaQueryListEvent) => setWebviewDark(event.matches)
+    media.addEventListener('change', onMediaChange)
+
+    return () => {
+      unlistenPreferences.then((stop) => stop())
+      unlistenSystem.then((stop) => stop())
+      media.removeEventListener('change', onMediaChange)
+    }
+  }, [])
+
+  useEffect(() => {
+    const followed = system === 'unknown' ? (webviewDark ? 'dark' : 'light') : system
+    document.documentElement.dataset.theme = preference === 'auto' ? followed : preference
+  }, [preference, system, webviewDark])
+}
+
+interface ExternalProvider {
+  name: string
+  url: string
+}
+
+// Sites the query can be opened on, kept in step with the preferences
+function useExternalProviders() {
+  const [providers, setProviders] = useState<ExternalProvider[]>([])
+
+  useEffect(() => {
+    invoke<ExternalProvider[]>('get_external_providers')
+      .then(setProviders)
+      .catch((error) => console.warn('[WARN] [touchdictionary] [gui] Failed to load external providers:', error))
+    const unlisten = listen<{ external_providers: ExternalProvider[] }>('preferences-changed', (event) => {
+      setProviders(event.payload.external_providers)
+    })
+    return () => {
+      unlisten.then((stop) => stop())
+    }
+  }, [])
+
+  return providers
+}
+
+// An image downloaded and cached by the backend instead of hot-linked, so
+// it shows offline and isn't refetched on every render. Nothing is shown
+// when it can't be fetched.
+function CachedImage({ url, className }: { url: string, className?: string }) {
+  const [src, setSrc] = useState<string | null>(null)
+
+  useEffect(() => {
+    let current = true
+    invoke<string>('fetch_image', { url })
+      .then((path) => current && setSrc(convertFileSrc(path)))
+      .catch((error) => console.warn('[WARN] [touchdictionary] [gui] Failed to fetch image:', error))
+    return () => {
+      current = false
+    }
+  }, [url])
+
+  return src ? <img src={src} className={className} alt="" /> : null
 }
 
 function App() {
@@ -39,20 +412,58 @@ function App() {
   const [result, setResult] = useState<LookupResult | null>(null)
   const [loading, setLoading] = useState(false)
   const [activeTab, setActiveTab] = useState<'dictionary' | 'wikipedia'>('dictionary')
+  // Sections of the full article loaded so far, past the summary
+  const [article, setArticle] = useState<ArticlePage | null>(null)
+  const [articleLoading, setArticleLoading] = useState(false)
+  // Find-in-result: the needle, its matches and the one scrolled to
+  const [findNeedle, setFindNeedle] = useState('')
+  const [findMatches, setFindMatches] = useState<SearchMatch[]>([])
+  const [findIndex, setFindIndex] = useState(0)
+  const [toast, setToast] = useState<string | null>(null)
+  const [toastLink, setToastLink] = useState<string | null>(null)
+  // Newest query sent to the backend; replies for anything else are stale
+  const latestQuery = useRef<string | null>(null)
+  // Position among the recent results while going back through them; 0 is
+  // the newest
+  const [recentIndex, setRecentIndex] = useState(0)
+  // Part of speech the definitions are narrowed to, if any
+  const [posFilter, setPosFilter] = useState<string | null>(null)
+  // Results a nested lookup can go back to, and how the shown one was reached
+  const [backDepth, setBackDepth] = useState(0)
+  const [breadcrumb, setBreadcrumb] = useState<HistoryEntry[]>([])
+  // Sources that didn't answer when a failed lookup made us check
+  const [unreachable, setUnreachable] = useState<SourceHealth[] | null>(null)
+  const [sourceCount, setSourceCount] = useState(0)
+  const swipeStart = useRef<number | null>(null)
+  const [offlineAvailable, setOfflineAvailable] = useState(false)
+  // Only local sources and the cache are used, and nothing is saved
+  const [privateMode, setPrivateMode] = useState(false)
+  useTheme()
+  const externalProviders = useExternalProviders()
+
+  useEffect(() => {
+    invoke<boolean>('is_offline_available')
+      .then(setOfflineAvailable)
+      .catch((error) => console.warn('[WARN] [touchdictionary] [gui] Failed to check offline dictionary:', error))
+    invoke<boolean>('get_private_mode')
+      .then(setPrivateMode)
+      .catch((error) => console.warn('[WARN] [touchdictionary] [gui] Failed to check private mode:', error))
+  }, [])
 
   useEffect(() => {
     // Get initial query from command-line arguments
     const loadInitialQuery = async () => {
       try {
-        const args: string[] = await invoke('get_initial_query')
-        if (args.length > 0) {
-          const initialQuery = args.join(' ')
-          console.log('[INFO] [touchdictionary] [gui] Initial query from args:', initialQuery)
-          handleLookup(initialQuery)
+        const request: LookupRequest | null = await invoke('get_initial_query')
+        if (request) {
+          console.log('[INFO] [touchdictionary] [gui] Initial query from args:', request.query)
+          handleLookup(request.query, request.lang)
+        } else if (await showRecent(0)) {
+          console.log('[INFO] [touchdictionary] [gui] Restored the last result')
         } else {
-          // No query provided - maybe show a prompt?
-          setLoading(false)
-          console.log('[INFO] [touchdictionary] [gui] No query provided on startup')
+          // No query provided - show the word of the day instead
+          console.log('[INFO] [touchdictionary] [gui] No query provided on startup, loading word of the day')
+          loadWordOfTheDay()
         }
       } catch (error) {
         console.error('[ERROR] [touchdictionary] [gui] Failed to get initial query:', error)
@@ -62,32 +473,455 @@ function App() {
     loadInitialQuery()
   }, [])
 
-  const handleLookup = async (searchQuery: string) => {
+  useEffect(() => {
+    // Shown again with nothing looked up yet: put the last result back
+    const unlisten = getCurrentWindow().onFocusChanged(({ payload: focused }) => {
+      if (focused && latestQuery.current === null) {
+        showRecent(0)
+      }
+    })
+
+    return () => {
+      unlisten.then((stop) => stop())
+    }
+  }, [])
+
+  useEffect(() => {
+    // Escape dismisses the window. A right click opens the webview's
+    // context menu, which takes focus away; hold the window open until the
+    // next click or key press so it doesn't hide under the menu.
+    let menuOpen = false
+    const holdWindow = (hold: boolean) => {
+      menuOpen = hold
+      invoke('hold_window_open', { hold }).catch((error) =>
+        console.warn('[WARN] [touchdictionary] [gui] Failed to hold window open:', error)
+      )
+    }
+    const onKeyDown = (event: KeyboardEvent) => {
+      if (menuOpen) {
+        holdWindow(false)
+      }
+      if (event.key === 'Escape') {
+        invoke('dismiss').catch((error) => console.error('[ERROR] [touchdictionary] [gui] Failed to dismiss window:', error))
+      }
+    }
+    const onContextMenu = () => holdWindow(true)
+    const onPointerDown = () => menuOpen && holdWindow(false)
+    window.addEventListener('keydown', onKeyDown)
+    window.addEventListener('contextmenu', onContextMenu)
+    window.addEventListener('pointerdown', onPointerDown)
+
+    return () => {
+      window.removeEventListener('keydown', onKeyDown)
+      window.removeEventListener('contextmenu', onContextMenu)
+      window.removeEventListener('pointerdown', onPointerDown)
+    }
+  }, [])
+
+  useEffect(() => {
+    // Dismissed with Escape: start from nothing next time. A lookup still
+    // running finishes in the background and its reply is ignored here.
+    const unlisten = listen('dismissed', () => {
+      latestQuery.current = null
+      setQuery('')
+      setResult(null)
+      setLoading(false)
+      setArticle(null)
+      setFindNeedle('')
+      setFindMatches([])
+      setPosFilter(null)
+      setRecentIndex(0)
+    })
+
+    return () => {
+      unlisten.then((stop) => stop())
+    }
+  }, [])
+
+  useEffect(() => {
+    // Queries forwarded by later invocations of the binary
+    const unlisten = listen<LookupRequest>('new-query', (event) => {
+      console.log('[INFO] [touchdictionary] [gui] Forwarded query:', event.payload.query)
+      handleLookup(event.payload.query, event.payload.lang)
+    })
+
+    return () => {
+      unlisten.then((stop) => stop())
+    }
+  }, [])
+
+  useEffect(() => {
+    // Selections reported by the background watcher (`--watch`)
+    const unlisten = listen<{ text: string }>('selection-changed', (event) => {
+      console.log('[INFO] [touchdictionary] [gui] Selection changed:', event.payload.text)
+      handleLookup(event.payload.text)
+    })
+
+    return () => {
+      unlisten.then((stop) => stop())
+    }
+  }, [])
+
+  useEffect(() => {
+    // Lookups made by the global shortcut, already finished
+    const unlisten = listen<{ query: string; result?: LookupResult; error?: LookupError }>('hotkey-lookup', (event) => {
+      const { query: hotkeyQuery, result: hotkeyResult, error } = event.payload
+      console.log('[INFO] [touchdictionary] [gui] Shortcut lookup:', hotkeyQuery)
+      latestQuery.current = hotkeyQuery
+      setRecentIndex(0)
+      setQuery(hotkeyQuery)
+      setLoading(false)
+      setArticle(null)
+      setFindNeedle('')
+      setFindMatches([])
+      setResult(
+        hotkeyResult
+          ? { ...hotkeyResult, requested_query: hotkeyQuery }
+          : ({ query: hotkeyQuery, sections: {}, error: error?.message ?? 'Lookup failed' } as LookupResult)
+      )
+    })
+
+    return () => {
+      unlisten.then((stop) => stop())
+    }
+  }, [])
+
+  useEffect(() => {
+    // Lookups that failed offline, run again once the network came back.
+    // The one on screen is replaced; any other is only mentioned.
+    const onOnline = () => {
+      invoke('network_online').catch((error) => console.error('[ERROR] [touchdictionary] [gui] Deferred lookups failed:', error))
+    }
+    window.addEventListener('online', onOnline)
+    const unlisten = listen<LookupResult>('deferred-result', (event) => {
+      const deferred = event.payload
+      console.log('[INFO] [touchdictionary] [gui] Deferred lookup finished:', deferred.query)
+      if (deferred.query === latestQuery.current) {
+        setLoading(false)
+        setResult({ ...deferred, requested_query: deferred.query })
+      } else {
+        showToast(`Looked up "${deferred.display_query}" now that you're back online`)
+      }
+    })
+
+    return () => {
+      window.removeEventListener('online', onOnline)
+      unlisten.then((stop) => stop())
+    }
+  }, [])
+
+  useEffect(() => {
+    // Selections refused before any lookup: too long, secret-looking, or a
+    // link or address to open instead. Only a toast; the result stays.
+    const unlisten = listen<LookupError>('selection-rejected', (event) => {
+      console.log('[INFO] [touchdictionary] [gui] Selection rejected:', event.payload.message)
+      showToast(event.payload.message, event.payload.open_target ?? undefined)
+    })
+
+    return () => {
+      unlisten.then((stop) => stop())
+    }
+  }, [])
+
+  useEffect(() => {
+    // Sections of the running lookup, as each source answers
+    const unlisten = listen<SectionUpdate>('lookup-section', (event) => {
+      if (event.payload.requested_query !== latestQuery.current) {
+        return
+      }
+      // Per-source progress is for the CLI's spinner; the statuses arrive
+      // with the full result
+      if (event.payload.section === 'source_started' || event.payload.section === 'source_finished') {
+        return
+      }
+      setResult((previous) => mergeSection(previous, event.payload))
+    })
+
+    return () => {
+      unlisten.then((stop) => stop())
+    }
+  }, [])
+
+  const loadWordOfTheDay = async () => {
+    setLoading(true)
+    try {
+      const result: LookupResult = await invoke('get_word_of_the_day')
+      setResult(result)
+      setQuery(result.display_query)
+    } catch (error) {
+      console.error('[ERROR] [touchdictionary] [gui] Failed to load word of the day:', error)
+    } finally {
+      setLoading(false)
+    }
+  }
+
+  // Show the recent result at `index` (0 is the newest) without looking it
+  // up again. False when there are fewer results than that.
+  const showRecent = async (index: number) => {
+    try {
+      const recent: LookupResult[] = await invoke('get_recent_results', { limit: index + 1 })
+      const restored = recent[index]
+      if (!restored) {
+        return false
+      }
+      latestQuery.current = restored.requested_query ?? restored.display_query
+      setQuery(restored.display_query)
+      setResult(restored)
+      setArticle(null)
+      setFindNeedle('')
+      setFindMatches([])
+      setRecentIndex(index)
+      return true
+    } catch (error) {
+      console.error('[ERROR] [touchdictionary] [gui] Failed to load recent results:', error)
+      return false
+    }
+  }
+
+  // Swiping right goes back to the lookup before, left comes forward again
+  const handleSwipeEnd = (x: number) => {
+    if (swipeStart.current === null) {
+      return
+    }
+    const distance = x - swipeStart.current
+    swipeStart.current = null
+    if (distance > SWIPE_DISTANCE) {
+      showRecent(recentIndex + 1)
+    } else if (distance < -SWIPE_DISTANCE && recentIndex > 0) {
+      showRecent(recentIndex - 1)
+    }
+  }
+
+  const handleLookup = async (searchQuery: string, lang?: string) => {
     if (!searchQuery.trim()) {
       return
     }
 
+    latestQuery.current = searchQuery
+    setRecentIndex(0)
+    setPosFilter(null)
+    setBackDepth(0)
+    setBreadcrumb([])
     setQuery(searchQuery)
     setLoading(true)
     setResult(null)
+    setArticle(null)
+    setFindNeedle('')
+    setFindMatches([])
 
     try {
-      const result: LookupResult = await invoke('run_lookup_command', { query: searchQuery })
+      const result: LookupResult = await invoke('run_lookup_streaming', { query: searchQuery, lang })
+      if (result.requested_query !== latestQuery.current) {
+        return
+      }
       setResult(result)
+      setUnreachable(null)
       console.log('[INFO] [touchdictionary] [gui] Lookup completed for:', searchQuery)
     } catch (error) {
+      // Superseded by a newer query, which now owns the result pane
+      if ((error as LookupError)?.kind === 'cancelled' || searchQuery !== latestQuery.current) {
+        return
+      }
       console.error('[ERROR] [touchdictionary] [gui] Lookup failed:', error)
+      if (CONNECTIVITY_ERRORS.includes((error as LookupError)?.kind)) {
+        checkSources()
+      }
       setResult({
         query: searchQuery,
         contentType: 'Word',
         sections: {},
-        error: error instanceof Error ? error.message : String(error)
+        error: (error as LookupError)?.message ?? String(error)
       } as LookupResult)
     } finally {
+      if (searchQuery === latestQuery.current) {
+        setLoading(false)
+      }
+    }
+  }
+
+  const checkSources = async () => {
+    try {
+      const health = await invoke<SourceHealth[]>('check_sources')
+      const down = health.filter((source) => !source.reachable)
+      setSourceCount(health.length)
+      setUnreachable(down.length > 0 ? down : null)
+    } catch (error) {
+      console.warn('[WARN] [touchdictionary] [gui] Source check failed:', error)
+    }
+  }
+
+  const loadBreadcrumb = async (query: string) => {
+    try {
+      setBreadcrumb(await invoke<HistoryEntry[]>('get_history_chain', { query }))
+    } catch (error) {
+      console.warn('[WARN] [touchdictionary] [gui] Failed to load the breadcrumb:', error)
+      setBreadcrumb([])
+    }
+  }
+
+  // Look up a word double-clicked in the shown result, keeping the result
+  // to go back to
+  const handleWordDoubleClick = async () => {
+    const word = window.getSelection()?.toString().trim()
+    if (!result || result.error || loading || !word || !NESTED_WORD.test(word)) {
+      return
+    }
+    const parentQuery = result.requested_query ?? result.query
+    latestQuery.current = word
+    setLoading(true)
+    try {
+      const nested: LookupResult = await invoke('lookup_nested', { parentQuery, word })
+      if (nested.requested_query !== latestQuery.current) {
+        return
+      }
+      setResult(nested)
+      setPosFilter(null)
+      setBackDepth((depth) => depth + 1)
+      loadBreadcrumb(word)
+    } catch (error) {
+      if ((error as LookupError)?.kind !== 'cancelled') {
+        console.error('[ERROR] [touchdictionary] [gui] Nested lookup failed:', error)
+      }
+    } finally {
+      if (word === latestQuery.current) {
+        setLoading(false)
+      }
+    }
+  }
+
+  const goBack = async () => {
+    try {
+      const previous = await invoke<LookupResult | null>('go_back')
+      if (!previous) {
+        setBackDepth(0)
+        return
+      }
+      latestQuery.current = previous.requested_query ?? previous.query
       setLoading(false)
+      setResult(previous)
+      setPosFilter(null)
+      setBackDepth((depth) => Math.max(depth - 1, 0))
+      loadBreadcrumb(latestQuery.current)
+    } catch (error) {
+      console.error('[ERROR] [touchdictionary] [gui] Failed to go back:', error)
+    }
+  }
+
+  const loadArticle = async (wikipedia: WikipediaSection, offset: number) => {
+    setArticleLoading(true)
+    try {
+      const page: ArticlePage = await invoke('get_wikipedia_article', {
+        title: wikipedia.title,
+        lang: wikipedia.language,
+        offset
+      })
+      setArticle((previous) =>
+        previous && offset > 0 ? { ...page, sections: [...previous.sections, ...page.sections] } : page
+      )
+    } catch (error) {
+      console.error('[ERROR] [touchdictionary] [gui] Failed to load article:', error)
+    } finally {
+      setArticleLoading(false)
+    }
+  }
+
+  // Links inside article sections look up the article they point to
+  const handleArticleClick = (e: MouseEvent<HTMLDivElement>) => {
+    const link = (e.target as HTMLElement).closest('a[data-title]')
+    if (link) {
+      e.preventDefault()
+      handleLookup(link.getAttribute('data-title')!)
+    }
+  }
+
+  // Same query again without the definition limits
+  const showAllDefinitions = async () => {
+    if (!result) {
+      return
+    }
+    try {
+      const full: LookupResult = await invoke('run_lookup_command', {
+        query: result.requested_query ?? result.query,
+        allDefinitions: true
+      })
+      if (full.requested_query === latestQuery.current) {
+        setResult(full)
+      }
+    } catch (error) {
+      console.error('[ERROR] [touchdictionary] [gui] Failed to load all definitions:', error)
+    }
+  }
+
+  // Swap a section cut down in the summary result for all of it
+  const showFullSection = async (section: 'wikipedia' | 'definitions') => {
+    if (!result) {
+      return
+    }
+    try {
+      const full = await invoke<LookupResult['sections'][typeof section]>('get_full_section', {
+        query: result.requested_query ?? result.query,
+        section
+      })
+      setResult((previous) => previous && { ...previous, sections: { ...previous.sections, [section]: full } })
+    } catch (error) {
+      console.error('[ERROR] [touchdictionary] [gui] Failed to load the full section:', error)
+    }
+  }
+
+  // Re-run the lookup keeping only `partOfSpeech` definitions, or all of
+  // them again for null
+  const filterDefinitions = async (partOfSpeech: string | null) => {
+    if (!result) {
+      return
+    }
+    setPosFilter(partOfSpeech)
+    try {
+      const filtered: LookupResult = await invoke('run_lookup_command', {
+        query: result.requested_query ?? result.query,
+        posFilter: partOfSpeech ? [partOfSpeech] : null
+      })
+      if (filtered.requested_query === latestQuery.current) {
+        setResult(filtered)
+      }
+    } catch (error) {
+      console.error('[ERROR] [touchdictionary] [gui] Failed to filter definitions:', error)
+    }
+  }
+
+  const findInResult = async (needle: string) => {
+    setFindNeedle(needle)
+    setFindIndex(0)
+    if (!result || !needle.trim()) {
+      setFindMatches([])
+      return
+    }
+    try {
+      const matches: SearchMatch[] = await invoke('search_result', {
+        queryId: result.requested_query ?? result.query,
+        needle
+      })
+      setFindMatches(matches)
+    } catch (error) {
+      console.warn('[WARN] [touchdictionary] [gui] Search failed:', error)
+      setFindMatches([])
     }
   }
 
+  const currentMatch = findMatches[findIndex]
+
+  useEffect(() => {
+    if (!currentMatch) {
+      return
+    }
+    setActiveTab(currentMatch.section === 'wikipedia' ? 'wikipedia' : 'dictionary')
+    // After the tab switch has rendered
+    requestAnimationFrame(() =>
+      document.getElementById(matchElementId(currentMatch))?.scrollIntoView({ block: 'center', behavior: 'smooth' })
+    )
+  }, [currentMatch])
+
+  const matchClass = (id: string) => (currentMatch && matchElementId(currentMatch) === id ? ' find-current' : '')
+
   const closeWindow = async () => {
     try {
       await invoke('close_window')
@@ -104,8 +938,119 @@ function App() {
     }
   }
 
-  const hasDictionary = result?.sections.definitions && result.sections.definitions.length > 0
+  const openExternal = async (provider: string, query: string) => {
+    try {
+      await invoke('open_external', { provider, query })
+    } catch (error) {
+      console.error('[ERROR] [touchdictionary] [gui] Failed to open external provider:', error)
+    }
+  }
+
+  const playAudio = async (url: string) => {
+    try {
+      await invoke('play_audio', { url })
+    } catch (error) {
+      console.error('[ERROR] [touchdictionary] [gui] Failed to play audio:', error)
+    }
+  }
+
+  // A toast with a link stays up longer, so there's time to click it
+  const showToast = (message: string, link?: string) => {
+    setToast(message)
+    setToastLink(link ?? null)
+    setTimeout(() => {
+      setToast(null)
+      setToastLink(null)
+    }, link ? 5000 : 2000)
+  }
+
+  const copyText = async (text: string) => {
+    try {
+      await invoke('copy_to_clipboard', { text })
+      showToast('Copied')
+    } catch (error) {
+      showToast(`Copy failed: ${error}`)
+    }
+  }
+
+  const togglePrivateMode = async () => {
+    try {
+      await invoke('set_private_mode', { enabled: !privateMode })
+      setPrivateMode(!privateMode)
+      showToast(privateMode ? 'Private mode off' : 'Private mode on: nothing leaves this machine or is saved')
+    } catch (error) {
+      showToast(`Couldn't switch private mode: ${error}`)
+    }
+  }
+
+  const copyResult = async () => {
+    if (!result) return
+    try {
+      await invoke('copy_result_markdown', { query: result.display_query })
+      showToast('Copied as Markdown')
+    } catch (error) {
+      showToast(`Copy failed: ${error}`)
+    }
+  }
+
+  const saveResultPage = async () => {
+    if (!result) return
+    try {
+      const path: string = await invoke('export_result_html', { query: result.display_query })
+      showToast(`Saved to ${path}`)
+    } catch (error) {
+      showToast(`Save failed: ${error}`)
+    }
+  }
+
+  const hasDefinitions = result?.sections.definitions && result.sections.definitions.length > 0
+  // Filter chips: the parts of speech shown plus any the filter hid
+  const hiddenDefinitions = Object.entries(result?.hidden_definitions ?? {})
+  const partsOfSpeech = [
+    ...new Set([
+      ...(result?.sections.definitions ?? []).flatMap((section) =>
+        section.definitions.flatMap((def) => (def.part_of_speech ? [def.part_of_speech.toLowerCase()] : []))
+      ),
+      ...hiddenDefinitions.map(([partOfSpeech]) => partOfSpeech).filter((partOfSpeech) => partOfSpeech !== 'other'),
+    ]),
+  ]
+  const hasThesaurus = result?.sections.thesaurus != null
+  const hasEtymology = result?.sections.etymology != null
+  const hasExamples = result?.sections.examples != null
+  const hasAcronym = result?.sections.acronym != null
+  const hasPattern = result?.sections.pattern != null
+  const hasRhymes = result?.sections.rhymes != null
+  const hasComputed = result?.sections.computed != null
+  const hasDictionary =
+    hasDefinitions || hasThesaurus || hasEtymology || hasExamples || hasAcronym || hasPattern || hasRhymes || hasComputed
   const hasWikipedia = result?.sections.wikipedia != null
+  const pronunciation = result?.sections.definitions
+    ?.flatMap((section) => section.phonetics ?? [])
+    .find((phonetic) => phonetic.text)?.text
+  const audioUrl = result?.sections.definitions
+    ?.flatMap((section) => section.phonetics ?? [])
+    .find((phonetic) => phonetic.audio_url)?.audio_url
+
+  // Warm the audio cache so tapping play starts right away
+  useEffect(() => {
+    if (audioUrl) {
+      invoke('prefetch_audio', { url: audioUrl }).catch((error) =>
+        console.warn('[WARN] [touchdictionary] [gui] Failed to prefetch audio:', error)
+      )
+    }
+  }, [audioUrl])
+
+  // Once the lookup is done, warm the cache for the synonyms on screen so
+  // tapping one shows its definition right away
+  const synonyms = loading ? undefined : result?.sections.thesaurus?.synonyms
+  const synonymsKey = synonyms?.join('\n')
+  useEffect(() => {
+    if (synonyms?.length) {
+      invoke('prefetch_lookups', { words: synonyms }).catch((error) =>
+        console.warn('[WARN] [touchdictionary] [gui] Failed to prefetch synonyms:', error)
+      )
+    }
+  }, [synonymsKey])
 
   // Auto-set tab if one type is missing
   useEffect(() => {
@@ -116,8 +1061,25 @@ function App() {
     }
   }, [hasDictionary, hasWikipedia])
 
+  // Small marker next to a section header when one of its sources was
+  // cached, failed or timed out
+  const sectionBadges = (section: string) => {
+    const statuses = (result?.source_statuses ?? []).filter(
+      (status) => SECTION_SOURCES[section]?.includes(status.name) && BADGE_LABELS[status.outcome]
+    )
+    return statuses.map((status) => (
+      <span
+        key={status.name}
+        className={`status-badge status-${status.outcome.toLowerCase()}`}
+        title={status.error ?? `${status.name}: ${status.duration_ms}ms`}
+      >
+        {status.name} {BADGE_LABELS[status.outcome]}
+      </span>
+    ))
+  }
+
   const renderContent = () => {
-    if (loading) {
+    if (loading && !result) {
       return null
     }
 
@@ -126,6 +1088,11 @@ function App() {
         <div className="error-content">
           <h3 className="error-title">Error</h3>
           <p className="error-message">{result.error}</p>
+          <p className="offline-note">
+            {offlineAvailable
+              ? 'Offline dictionary available'
+              : 'No offline dictionary installed, so lookups need a network connection'}
+          </p>
         </div>
       )
     }
@@ -137,39 +1104,327 @@ function App() {
     // Render based on active tab
     return (
       <>
-        {activeTab === 'dictionary' && hasDictionary && (
+        {result.is_word_of_the_day && (
+          <p className="wotd-label">Word of the day</p>
+        )}
+        {backDepth > 0 && (
+          <nav className="breadcrumb">
+            <button className="link-button" onClick={goBack}>Back</button>
+            {breadcrumb.length > 1 && (
+              <span>{breadcrumb.map((entry) => entry.display_query).join(' › ')}</span>
+            )}
+          </nav>
+        )}
+        {result.from_session_cache && (
+          <p className="correction-note">Earlier lookup, swipe right for the one before</p>
+        )}
+        {result.corrected_from && (
+          <p className="correction-note">Showing results for "{result.display_query}" (from "{result.corrected_from}")</p>
+        )}
+        {result.lemma_of && (
+          <p className="correction-note">Showing results for "{result.query}" (from "{result.lemma_of}")</p>
+        )}
+        {result.frequency && (
+          <p className="frequency-note" title={`Zipf ${result.frequency.zipf.toFixed(1)}`}>
+            Frequency: {FREQUENCY_LABELS[result.frequency.band]} (rank {result.frequency.rank.toLocaleString('en-US')})
+          </p>
+        )}
+
+        {result.language_used && result.language_used !== result.language && (
+          <p className="correction-note">
+            Looked up in {languageName(result.language_used)} —{' '}
+            <button className="link-button" onClick={() => handleLookup(result.requested_query ?? result.query, result.language)}>
+              search in {languageName(result.language)} instead
+            </button>
+          </p>
+        )}
+
+        {result.locale_language === result.language && result.language !== 'en' && (
+          <p className="correction-note">
+            Results in {languageName(result.language)} —{' '}
+            <button className="link-button" onClick={() => handleLookup(result.requested_query ?? result.query, 'en')}>
+              switch to English
+            </button>
+          </p>
+        )}
+
+        {externalProviders.length > 0 && !result.error && (
+          <p className="external-providers">
+            Open in{' '}
+            {externalProviders.map((provider) => (
+              <button key={provider.name} className="link-button" onClick={() => openExternal(provider.name, result.display_query)}>
+                {provider.name}
+              </button>
+            ))}
+          </p>
+        )}
+
+        {result.warnings?.map((warning) => (
+          <p key={warning} className="correction-note">{warning}</p>
+        ))}
+
+        {typeof result.completeness === 'object' && (
+          <p className="correction-note">
+            Results may be incomplete: {result.completeness.Partial.failed_sources.join(', ')} failed
+          </p>
+        )}
+        {result.completeness === 'Empty' && !result.suggestions?.length && (
+          <p className="no-results">No results found for "{result.display_query}"</p>
+        )}
+
+        {result.suggestions && result.suggestions.length > 0 && (
+          <section className="suggestions-section">
+            <h2 className="section-header">Did you mean</h2>
+            <div className="thesaurus-terms">
+              {result.suggestions.map((suggestion) => (
+                <button key={suggestion} className="thesaurus-term suggestion" onClick={() => handleLookup(suggestion)}>
+                  {suggestion}
+                </button>
+              ))}
+            </div>
+          </section>
+        )}
+
+        {result.sections.keyword_lookups && result.sections.keyword_lookups.length > 0 && (
+          <section className="keywords-section">
+            <h2 className="section-header">Keywords</h2>
+            {result.sections.keyword_lookups.map(({ keyword, result: keywordResult }) => {
+              const definition = keywordResult.sections.definitions?.[0]?.definitions[0]?.definition
+              return (
+                <div key={keyword} className="definition-item">
+                  <button className="thesaurus-term" onClick={() => handleLookup(keyword)}>{keyword}</button>
+                  <div className="definition-text">
+                    {definition ?? keywordResult.sections.wikipedia?.summary}
+                  </div>
+                </div>
+              )
+            })}
+          </section>
+        )}
+
+        {activeTab === 'dictionary' && hasComputed && (
+          <section className="computed-section">
+            <h2 className="section-header">Computed</h2>
+            <dl className="computed-values">
+              {result.sections.computed!.interpretations.map((interpretation, i) => (
+                <div key={i} className="computed-row">
+                  <dt>{interpretation.label}</dt>
+                  <dd>{interpretation.value}</dd>
+                </div>
+              ))}
+            </dl>
+          </section>
+        )}
+
+        {activeTab === 'dictionary' && hasPattern && (
+          <section className="pattern-section">
+            <h2 className="section-header">Matches</h2>
+            <ul className="pattern-matches">
+              {result.sections.pattern!.matches.map((match) => (
+                <li key={match.word}>
+                  <button className="thesaurus-term" onClick={() => handleLookup(match.word)}>{match.word}</button>
+                  <span className="pattern-score">{match.score}</span>
+                </li>
+              ))}
+            </ul>
+          </section>
+        )}
+
+        {activeTab === 'dictionary' && hasAcronym && (
+          <section className="acronym-section">
+            <h2 className="section-header">Stands for {sectionBadges('acronym')}</h2>
+            <ul className="acronym-expansions">
+              {result.sections.acronym!.expansions.map((expansion) => (
+                <li key={expansion.expansion}>
+                  {expansion.url ? (
+                    <a
+                      href="#"
+                      onClick={(e) => {
+                        e.preventDefault()
+                        openWikipediaLink(expansion.url!)
+                      }}
+                      className="acronym-expansion"
+                    >
+                      {expansion.expansion}
+                    </a>
+                  ) : (
+                    <span className="acronym-expansion">{expansion.expansion}</span>
+                  )}
+                  {expansion.description && <span className="acronym-description"> — {expansion.description}</span>}
+                </li>
+              ))}
+            </ul>
+          </section>
+        )}
+
+        {activeTab === 'dictionary' && partsOfSpeech.length > 1 && (
+          <div className="pos-filter">
+            {partsOfSpeech.map((partOfSpeech) => (
+              <button
+                key={partOfSpeech}
+                className={`pos-chip${posFilter === partOfSpeech ? ' active' : ''}`}
+                onClick={() => filterDefinitions(posFilter === partOfSpeech ? null : partOfSpeech)}
+              >
+                {partOfSpeech}
+              </button>
+            ))}
+          </div>
+        )}
+        {activeTab === 'dictionary' && hiddenDefinitions.length > 0 && (
+          <p className="correction-note">
+            {hiddenDefinitions.map(([partOfSpeech, count]) => `${count} ${partOfSpeech}`).join(', ')} definitions hidden
+          </p>
+        )}
+
+        {activeTab === 'dictionary' && hasDefinitions && (
           <section className="definition-section">
-            <h2 className="section-header">Dictionary</h2>
+            <h2 className="section-header">Dictionary {sectionBadges('dictionary')}</h2>
             <div className="definitions">
               {result.sections.definitions!.map((section: DefinitionSection, sectionIdx: number) => (
                 <div key={sectionIdx} className="definition-source">
-                  <div className="source-name">{section.source}</div>
+                  <div className="source-name">
+                    {section.source}
+                    {section.changed_since_cache && <span className="updated-badge">updated</span>}
+                  </div>
                   {section.definitions.map((def: Definition, defIdx: number) => (
-                    <div key={defIdx} className="definition-item">
+                    <div
+                      key={defIdx}
+                      id={`def-${sectionIdx}-${defIdx}`}
+                      className={`definition-item${matchClass(`def-${sectionIdx}-${defIdx}`)}`}
+                    >
                       {def.part_of_speech && (
                         <span className="part-of-speech">{def.part_of_speech}</span>
                       )}
-                      <div className="definition-text">{def.definition}</div>
+                      <div className="definition-text" onClick={() => copyText(def.definition)}>{def.definition}</div>
+                      {def.translated && (
+                        <div className="translation">{def.translated}</div>
+                      )}
                       {def.example && (
                         <div className="example">"{def.example}"</div>
                       )}
+                      {def.sources && def.sources.length > 1 && (
+                        <div className="also-in">
+                          Also in {def.sources.filter((source) => source !== section.source).join(', ')}
+                        </div>
+                      )}
                     </div>
                   ))}
+                  {section.truncated && (
+                    <button className="show-all" onClick={showAllDefinitions}>
+                      Show all {section.total_available}
+                    </button>
+                  )}
+                  {section.examples_truncated && (
+                    <button className="show-all" onClick={() => showFullSection('definitions')}>
+                      Show all examples
+                    </button>
+                  )}
                 </div>
               ))}
             </div>
           </section>
         )}
 
+        {activeTab === 'dictionary' && hasEtymology && (
+          <section className="etymology-section">
+            <h2 className="section-header">Etymology {sectionBadges('etymology')}</h2>
+            <div className="source-name">{result.sections.etymology!.source}</div>
+            {result.sections.etymology!.text.split('\n').map((paragraph, idx) => (
+              <p key={idx} className="etymology-paragraph">{paragraph}</p>
+            ))}
+            {result.sections.etymology!.language_of_origin && (
+              <div className="correction-note">Origin: {result.sections.etymology!.language_of_origin}</div>
+            )}
+          </section>
+        )}
+
+        {activeTab === 'dictionary' && hasExamples && (
+          <section className="examples-section">
+            <h2 className="section-header">Examples {sectionBadges('examples')}</h2>
+            <div className="source-name">{result.sections.examples!.source}</div>
+            <ul className="example-sentences">
+              {result.sections.examples!.sentences.map((sentence, idx) => (
+                <li key={idx} className="example">
+                  {emboldened(sentence, result.sections.examples!.occurrences[idx])}
+                </li>
+              ))}
+            </ul>
+          </section>
+        )}
+
+        {activeTab === 'dictionary' && hasThesaurus && (
+          <section className="thesaurus-section">
+            <h2 className="section-header">Thesaurus {sectionBadges('thesaurus')}</h2>
+            {([
+              ['Synonyms', result.sections.thesaurus!.synonyms],
+              ['Antonyms', result.sections.thesaurus!.antonyms],
+              ['Related', result.sections.thesaurus!.related_terms],
+            ] as [string, string[]][]).filter(([, terms]) => terms.length > 0).map(([label, terms]) => (
+              <div key={label} className="thesaurus-group">
+                <div className="source-name">{label}</div>
+                <div className="thesaurus-terms">
+                  {terms.map((term) => (
+                    <button key={term} className="thesaurus-term" onClick={() => handleLookup(term)}>{term}</button>
+                  ))}
+                </div>
+              </div>
+            ))}
+          </section>
+        )}
+
+        {activeTab === 'dictionary' && hasRhymes && (
+          <section className="thesaurus-section">
+            <h2 className="section-header">Rhymes {sectionBadges('rhymes')}</h2>
+            {([
+              ['Perfect', result.sections.rhymes!.perfect],
+              ['Near', result.sections.rhymes!.near],
+              ['Sounds like', result.sections.rhymes!.sounds_like],
+            ] as [string, string[]][]).filter(([, words]) => words.length > 0).map(([label, words]) => (
+              <div key={label} className="thesaurus-group">
+                <div className="source-name">{label}</div>
+                <div className="thesaurus-terms">
+                  {words.map((word) => (
+                    <button key={word} className="thesaurus-term" onClick={() => handleLookup(word)}>{word}</button>
+                  ))}
+                </div>
+              </div>
+            ))}
+          </section>
+        )}
+
         {activeTab === 'wikipedia' && hasWikipedia && (
           <section className="wikipedia-section">
-            <h2 className="section-header">Wikipedia</h2>
+            <h2 className="section-header">
+              Wikipedia {sectionBadges('wikipedia')}
+              {result.sections.wikipedia!.changed_since_cache && <span className="updated-badge">updated</span>}
+            </h2>
             <div className="wikipedia-content">
+              {result.sections.wikipedia!.image_url && (
+                <CachedImage url={result.sections.wikipedia!.image_url} className="wikipedia-image" />
+              )}
+              {result.sections.infobox && (
+                <dl className="computed-values infobox-facts">
+                  {result.sections.infobox.facts.map((fact) => (
+                    <div key={fact.label} className="computed-row">
+                      <dt>{fact.label}</dt>
+                      <dd>{fact.value}</dd>
+                    </div>
+                  ))}
+                </dl>
+              )}
               {result.sections.wikipedia!.paragraphs.map((para, idx) => (
-                <p key={idx} className="wikipedia-paragraph">
+                <p key={idx} id={`wiki-${idx}`} className={`wikipedia-paragraph${matchClass(`wiki-${idx}`)}`}>
                   {para}
                 </p>
               ))}
+              {result.sections.wikipedia!.is_truncated && (
+                <button className="show-all" onClick={() => showFullSection('wikipedia')}>
+                  Read more
+                </button>
+              )}
+              {result.sections.wikipedia!.translated_summary && (
+                <p className="translation">{result.sections.wikipedia!.translated_summary}</p>
+              )}
               <a 
                 href="#" 
                 onClick={(e) => {
@@ -180,6 +1435,44 @@ function App() {
               >
                 Read more on Wikipedia →
               </a>
+              {article && (
+                <div className="wikipedia-article" onClick={handleArticleClick}>
+                  {/* The lead repeats the summary above */}
+                  {article.sections.filter((section) => section.level > 1).map((section, idx) => (
+                    <div key={idx} className="article-section">
+                      <div className={`article-heading level-${section.level}`}>{section.heading}</div>
+                      <div dangerouslySetInnerHTML={{ __html: section.html }} />
+                    </div>
+                  ))}
+                </div>
+              )}
+              {(!article || article.next_offset != null) && (
+                <button
+                  className="action-button"
+                  disabled={articleLoading}
+                  onClick={() => loadArticle(result.sections.wikipedia!, article?.next_offset ?? 0)}
+                >
+                  {articleLoading ? 'Loading…' : article ? 'Load more sections' : 'Read full article'}
+                </button>
+              )}
+              {result.sections.wikipedia!.related.length > 0 && (
+                <div className="related-strip">
+                  <div className="source-name">See also</div>
+                  <div className="related-pages">
+                    {result.sections.wikipedia!.related.map((page) => (
+                      <button
+                        key={page.title}
+                        className="related-page"
+                        title={page.description}
+                        onClick={() => handleLookup(page.title)}
+                      >
+                        {page.thumbnail_url && <CachedImage url={page.thumbnail_url} />}
+                        <span>{page.title}</span>
+                      </button>
+                    ))}
+                  </div>
+                </div>
+              )}
             </div>
           </section>
         )}
@@ -202,22 +1495,100 @@ function App() {
         <div className="header" data-tauri-drag-region>
           <div className="word-title">
             <span className="word">{query}</span>
+            {pronunciation && (
+              <div className="phonetic">
+                {pronunciation}
+                {audioUrl && (
+                  <button className="play-audio" onClick={() => playAudio(audioUrl)} aria-label="Play pronunciation">
+                    &#9654;
+                  </button>
+                )}
+              </div>
+            )}
           </div>
+          {result && (
+            <>
+              <button className="copy-button" onClick={saveResultPage} aria-label="Save as a web page">
+                &#x2913;
+              </button>
+              <button className="copy-button" onClick={copyResult} aria-label="Copy result">
+                &#x2398;
+              </button>
+            </>
+          )}
+          <button
+            className={`copy-button private-toggle${privateMode ? ' active' : ''}`}
+            onClick={togglePrivateMode}
+            aria-label="Private mode"
+            aria-pressed={privateMode}
+            title={privateMode ? 'Private mode is on' : 'Private mode is off'}
+          >
+            &#x1F512;
+          </button>
           <button className="close-button" onClick={closeWindow} aria-label="Close">
             <span>&#215;</span>
           </button>
         </div>
 
+        {result && !result.error && (
+          <div className="find-bar">
+            <input
+              type="search"
+              placeholder="Find in result"
+              value={findNeedle}
+              onChange={(e) => findInResult(e.target.value)}
+              onKeyDown={(e) => {
+                if (e.key === 'Enter' && findMatches.length > 0) {
+                  setFindIndex((findIndex + 1) % findMatches.length)
+                }
+              }}
+            />
+            {findNeedle.trim() && (
+              <span className="find-count">
+                {findMatches.length === 0
+                  ? 'No matches'
+                  : `${findIndex + 1}/${findMatches.length}${findMatches[0].fuzzy ? ' (similar)' : ''}`}
+              </span>
+            )}
+          </div>
+        )}
+
         {/* Content area */}
-        <div className="content">
-          {loading && (
+        <div
+          className="content"
+          onTouchStart={(e) => {
+            swipeStart.current = e.touches[0].clientX
+          }}
+          onTouchEnd={(e) => handleSwipeEnd(e.changedTouches[0].clientX)}
+          onDoubleClick={handleWordDoubleClick}
+        >
+          {loading && !result && (
             <div className="loading-content">
               <div className="loading-spinner"></div>
               <p>Looking up definition...</p>
             </div>
           )}
 
-          {!loading && renderContent()}
+          {unreachable && (
+            <p className="connectivity-banner">
+              {unreachable.length === sourceCount
+                ? 'No dictionary service can be reached. Check your network connection.'
+                : `Not responding: ${unreachable.map((source) => source.name).join(', ')}`}{' '}
+              <button className="link-button" onClick={checkSources}>Check again</button>
+            </p>
+          )}
+          {renderContent()}
+          {toast && (
+            <div className="toast">
+              {toast}
+              {toastLink && (
+                <>
+                  {' '}
+                  <button className="link-button" onClick={() => openWikipediaLink(toastLink)}>Open</button>
+                </>
+              )}
+            </div>
+          )}
         </div>
 
         {/* Bottom actions */}
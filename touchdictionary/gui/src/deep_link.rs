@@ -0,0 +1,74 @@
+// `touchdict://lookup?q=ephemeral&lang=en` links, so browser extensions and
+// note tools can start a lookup. The scheme is registered through the
+// deep-link plugin. On Linux and Windows the desktop hands the link to a new
+// process as its only argument, which forwards it to the running instance
+// like any other query; macOS hands it to the running app directly.
+
+use serde::Serialize;
+use tracing::{info, warn};
+use url::Url;
+
+pub const SCHEME: &str = "touchdict";
+
+// What the window should look up, from the command line or a link
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LookupRequest {
+    pub query: String,
+    // Only links can ask for a language; None means the usual one
+    pub lang: Option<String>,
+}
+
+impl LookupRequest {
+    pub fn query(query: String) -> Self {
+        LookupRequest { query, lang: None }
+    }
+
+    // A link passed as the only argument, or the arguments joined as the
+    // query. Malformed links are logged and give None, like no arguments.
+    pub fn from_args(args: &[String]) -> Option<Self> {
+        match args {
+            [] => None,
+            [link] if is_link(link) => match parse(link) {
+                Ok(request) => Some(request),
+                Err(e) => {
+                    warn!("Ignoring link '{}': {}", link, e);
+                    None
+                }
+            },
+            _ => Some(LookupRequest::query(args.join(" "))),
+        }
+    }
+}
+
+fn is_link(arg: &str) -> bool {
+    arg.get(..SCHEME.len() + 1)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(&format!("{}:", SCHEME)))
+}
+
+// `touchdict://lookup?q=...&lang=...`. Percent-escapes and `+` are decoded;
+// parameters other than `q` and `lang` are ignored so newer links still
+// work here.
+pub fn parse(link: &str) -> Result<LookupRequest, String> {
+    let url = Url::parse(link).map_err(|e| e.to_string())?;
+    if url.scheme() != SCHEME {
+        return Err(format!("not a {}: link", SCHEME));
+    }
+    // `touchdict://lookup?...` puts the action in the host,
+    // `touchdict:lookup?...` in the path
+    let action = url.host_str().unwrap_or_else(|| url.path()).trim_matches('/');
+    if action != "lookup" {
+        return Err(format!("unknown action '{}'", action));
+    }
+
+    let mut query = None;
+    let mut lang = None;
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "q" => query = Some(value.trim().to_string()),
+            "lang" => lang = Some(value.trim().to_string()).filter(|lang| !lang.is_empty()),
+            _ => info!("Ignoring unknown link parameter '{}'", key),
+        }
+    }
+    let query = query.filter(|q| !q.is_empty()).ok_or("missing q parameter")?;
+    Ok(LookupRequest { query, lang })
+}
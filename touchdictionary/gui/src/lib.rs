@@ -1,81 +1,1537 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
 use serde_json::json;
-use tauri::{command, generate_handler, Manager, WebviewWindow};
+use tauri::menu::{CheckMenuItem, Menu, MenuItem, PredefinedMenuItem};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+use tauri::{
+    command, generate_handler, Emitter, Manager, PhysicalPosition, State, WebviewUrl, WebviewWindow, WebviewWindowBuilder,
+    WindowEvent,
+};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+use tauri_plugin_deep_link::DeepLinkExt;
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
 use tauri_plugin_opener::OpenerExt;
-use touchdictionary_core::lookup;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+use touchdictionary_core::audio::{self, AudioCache};
+use touchdictionary_core::cache;
+use touchdictionary_core::clipboard::{self, Selection};
+use touchdictionary_core::config::{Config, ConfigProblem};
+use touchdictionary_core::context::LookupContext;
+use touchdictionary_core::deferred;
+use touchdictionary_core::error::LookupError;
+use touchdictionary_core::export::{self, ExportFormat};
+use touchdictionary_core::favorites;
+use touchdictionary_core::format::{self, SearchMatch};
+use touchdictionary_core::guard;
+use touchdictionary_core::history;
+use touchdictionary_core::images::ImageCache;
+use touchdictionary_core::logging;
+use touchdictionary_core::maintenance;
+use touchdictionary_core::notify::{self, Notification};
+use touchdictionary_core::lookup::{self, CompareResult, LookupResult};
+use touchdictionary_core::placement;
+use touchdictionary_core::session::ResultStore;
+use touchdictionary_core::sources::offline;
+use touchdictionary_core::sources::wikipedia::{self, ArticlePage};
+use touchdictionary_core::theme::{self, SystemTheme};
+use touchdictionary_core::watch;
+
+mod deep_link;
+use deep_link::LookupRequest;
+mod preferences;
+use preferences::{ExternalProvider, Preferences};
+
+// Label of the window tauri.conf.json declares
+const MAIN_WINDOW: &str = "main";
+
+// Flag that keeps the app watching the selection instead of taking a query
+const WATCH_FLAG: &str = "--watch";
+
+// Flag that keeps the window hidden at startup, even with a query
+const HIDDEN_FLAG: &str = "--hidden";
+
+// Command-line arguments that make up the initial query
+fn query_args() -> Vec<String> {
+    without_flags(&std::env::args().skip(1).collect::<Vec<_>>())
+}
+
+fn without_flags(args: &[String]) -> Vec<String> {
+    args.iter().filter(|arg| *arg != WATCH_FLAG && *arg != HIDDEN_FLAG).cloned().collect()
+}
+
+// Whether the window starts shown: only for a query on the command line,
+// and never with --hidden. Without one the app waits in the background.
+fn starts_visible(args: &[String]) -> bool {
+    if args.iter().any(|arg| arg == HIDDEN_FLAG) {
+        return false;
+    }
+    LookupRequest::from_args(&without_flags(args)).is_some_and(|request| !request.query.trim().is_empty())
+}
+
+// The window tauri.conf.json declares, or one built with the same settings
+// when it's missing, e.g. because its label was changed there
+fn main_window(app: &tauri::App) -> tauri::Result<WebviewWindow> {
+    if let Some(window) = app.get_webview_window(MAIN_WINDOW) {
+        return Ok(window);
+    }
+    error!("No window labelled '{}' in tauri.conf.json; creating one", MAIN_WINDOW);
+    WebviewWindowBuilder::new(app, MAIN_WINDOW, WebviewUrl::default())
+        .title("TouchDictionary")
+        .inner_size(500.0, 600.0)
+        .min_inner_size(350.0, 300.0)
+        .max_inner_size(600.0, 800.0)
+        .center()
+        .decorations(false)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .visible(false)
+        .build()
+}
+
+// Results `go_back` can return to; older ones are dropped
+const MAX_TRAIL_DEPTH: usize = 25;
+
+// Token of the lookup the frontend is currently waiting on, and the last
+// results it was sent, kept across restarts. Starting a new lookup cancels
+// the previous one so a slow old result can't land last. `trail` holds the
+// results a nested lookup was opened from, newest last.
+struct CurrentLookup {
+    token: Mutex<CancellationToken>,
+    recent: Mutex<ResultStore>,
+    trail: Mutex<VecDeque<(String, LookupResult)>>,
+}
+
+impl CurrentLookup {
+    fn new(recent: ResultStore) -> Self {
+        CurrentLookup { token: Mutex::default(), recent: Mutex::new(recent), trail: Mutex::default() }
+    }
+
+    fn supersede(&self) -> CancellationToken {
+        let token = CancellationToken::new();
+        let previous = std::mem::replace(&mut *self.token.lock().unwrap_or_else(|e| e.into_inner()), token.clone());
+        previous.cancel();
+        token
+    }
+
+    // Keep `result` as the one for `query`
+    fn remember(&self, query: &str, result: &LookupResult) {
+        self.recent.lock().unwrap_or_else(|e| e.into_inner()).remember(query, result);
+    }
+
+    // Put the result shown for `query` on the trail, if it's still known
+    fn push_trail(&self, query: &str) {
+        let Some(result) = self.recent.lock().unwrap_or_else(|e| e.into_inner()).find(query).cloned() else {
+            warn!("No recent result for '{}' to go back to", query);
+            return;
+        };
+        let mut trail = self.trail.lock().unwrap_or_else(|e| e.into_inner());
+        if trail.len() == MAX_TRAIL_DEPTH {
+            trail.pop_front();
+        }
+        trail.push_back((query.to_string(), result));
+    }
+
+    fn pop_trail(&self) -> Option<(String, LookupResult)> {
+        self.trail.lock().unwrap_or_else(|e| e.into_inner()).pop_back()
+    }
+
+    // A lookup that wasn't opened from another result starts a new trail
+    fn clear_trail(&self) {
+        self.trail.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+}
+
+// A result as the frontend expects it, with the query it was asked for
+fn result_payload(query: &str, result: &LookupResult) -> serde_json::Value {
+    let mut payload = json!(result);
+    payload["requested_query"] = json!(query);
+    payload
+}
+
+// Wikipedia text sent with a "summary" result; the rest waits for
+// `get_full_section`
+const SUMMARY_BUDGET_CHARS: usize = 1500;
+
+// Whether the frontend asked for `detail: "full"` rather than the default
+// "summary"
+fn wants_full_detail(detail: Option<&str>) -> bool {
+    match detail {
+        None | Some("summary") => false,
+        Some("full") => true,
+        Some(other) => {
+            warn!("Unknown detail '{}', sending a summary", other);
+            false
+        }
+    }
+}
+
+// `result` as sent for `detail`; the session keeps the full one
+fn detailed(result: &LookupResult, detail: Option<&str>) -> LookupResult {
+    let mut result = result.clone();
+    if !wants_full_detail(detail) {
+        result.sections.truncate(SUMMARY_BUDGET_CHARS);
+    }
+    result
+}
+
+// A streamed section cut down the way `detailed` cuts the whole result
+fn detailed_update(mut update: lookup::SectionUpdate, detail: Option<&str>) -> lookup::SectionUpdate {
+    if wants_full_detail(detail) {
+        return update;
+    }
+    let payload = match update.section.as_str() {
+        "wikipedia" => serde_json::from_value::<lookup::WikipediaSection>(update.payload.clone()).map(|mut section| {
+            section.truncate(SUMMARY_BUDGET_CHARS);
+            json!(section)
+        }),
+        "definitions" => serde_json::from_value::<Vec<lookup::DefinitionSection>>(update.payload.clone()).map(|mut sections| {
+            sections.iter_mut().for_each(|section| {
+                section.truncate_examples();
+            });
+            json!(sections)
+        }),
+        _ => return update,
+    };
+    match payload {
+        Ok(payload) => update.payload = payload,
+        Err(e) => warn!("Sending {} untruncated: {}", update.section, e),
+    }
+    update
+}
+
+// `all_definitions` skips the configured definition limits, for when the
+// user asks to see everything. `pos_filter` keeps only definitions with
+// those parts of speech, e.g. ["verb"]. `detail` is "summary" (the
+// default) or "full", see `detailed`. `compact` trims the result for a
+// small touch screen, see `Sections::compact`.
+// Each argument is a key the frontend may pass, so there are many
+#[allow(clippy::too_many_arguments)]
+#[command]
+async fn run_lookup_command(
+    ctx: State<'_, LookupContext>,
+    current: State<'_, CurrentLookup>,
+    query: String,
+    no_cache: Option<bool>,
+    auto_correct: Option<bool>,
+    lang: Option<String>,
+    all_definitions: Option<bool>,
+    pos_filter: Option<Vec<String>>,
+    detail: Option<String>,
+    compact: Option<bool>,
+) -> Result<serde_json::Value, LookupError> {
+    info!("Lookup command invoked for: {}", query);
+
+    let options = lookup::LookupOptions {
+        use_cache: !no_cache.unwrap_or(false),
+        auto_correct: auto_correct.unwrap_or(false),
+        lang,
+        all_definitions: all_definitions.unwrap_or(false),
+        pos_filter,
+        compact: compact.unwrap_or(false),
+        cancel: current.supersede(),
+        ..lookup::LookupOptions::default()
+    };
 
+    match lookup::lookup_with(&ctx, &query, &options).await {
+        Ok(result) => {
+            info!("Successfully processed lookup for: {}", query);
+            current.clear_trail();
+            current.remember(&query, &result);
+            // Echo the query as sent so the frontend can drop stale replies
+            Ok(result_payload(&query, &detailed(&result, detail.as_deref())))
+        }
+        Err(LookupError::Cancelled) => {
+            info!("Lookup for '{}' superseded by a newer query", query);
+            Err(LookupError::Cancelled)
+        }
+        Err(e) => {
+            error!("Lookup failed for '{}': {}", query, e);
+            Err(e)
+        }
+    }
+}
+
+// Like `run_lookup_command`, but each section is emitted as a
+// `lookup-section` event as soon as its source answers, and the full result
+// follows as `lookup-complete`. Both carry `requested_query`.
+#[allow(clippy::too_many_arguments)]
 #[command]
-async fn run_lookup_command(query: String) -> Result<serde_json::Value, String> {
-    println!(
-        "[INFO] [touchdictionary] [gui] Lookup command invoked for: {}",
-        query
-    );
+async fn run_lookup_streaming(
+    app: tauri::AppHandle,
+    ctx: State<'_, LookupContext>,
+    current: State<'_, CurrentLookup>,
+    query: String,
+    no_cache: Option<bool>,
+    auto_correct: Option<bool>,
+    lang: Option<String>,
+    pos_filter: Option<Vec<String>>,
+    detail: Option<String>,
+) -> Result<serde_json::Value, LookupError> {
+    info!("Streaming lookup invoked for: {}", query);
+
+    let options = lookup::LookupOptions {
+        use_cache: !no_cache.unwrap_or(false),
+        auto_correct: auto_correct.unwrap_or(false),
+        lang,
+        pos_filter,
+        cancel: current.supersede(),
+        ..lookup::LookupOptions::default()
+    };
+
+    let (sink, mut updates) = tokio::sync::mpsc::unbounded_channel();
+    let forward = {
+        let app = app.clone();
+        let query = query.clone();
+        let detail = detail.clone();
+        tauri::async_runtime::spawn(async move {
+            while let Some(update) = updates.recv().await {
+                let mut payload = json!(detailed_update(update, detail.as_deref()));
+                payload["requested_query"] = json!(query);
+                if let Err(e) = app.emit("lookup-section", payload) {
+                    error!("Failed to emit lookup-section: {}", e);
+                }
+            }
+        })
+    };
 
-    match lookup::lookup(&query).await {
+    let result = lookup::lookup_with_progress(&ctx, &query, &options, sink).await;
+    // Every section is out before the result that contains them
+    let _ = forward.await;
+
+    match result {
         Ok(result) => {
-            println!(
-                "[INFO] [touchdictionary] [gui] Successfully processed lookup for: {}",
-                query
-            );
-            Ok(json!(result))
+            current.clear_trail();
+            current.remember(&query, &result);
+            let payload = result_payload(&query, &detailed(&result, detail.as_deref()));
+            if let Err(e) = app.emit("lookup-complete", &payload) {
+                error!("Failed to emit lookup-complete: {}", e);
+            }
+            Ok(payload)
+        }
+        Err(LookupError::Cancelled) => {
+            info!("Lookup for '{}' superseded by a newer query", query);
+            Err(LookupError::Cancelled)
         }
         Err(e) => {
-            println!(
-                "[ERROR] [touchdictionary] [gui] Lookup failed for '{}': {}",
-                query, e
-            );
+            error!("Lookup failed for '{}': {}", query, e);
             Err(e)
         }
     }
 }
 
+// Look up `word` from the text of the result shown for `parent_query`, e.g.
+// a double-clicked word in a definition. The history records where it came
+// from, and `go_back` returns to the parent without fetching it again.
+#[command]
+async fn lookup_nested(
+    ctx: State<'_, LookupContext>,
+    current: State<'_, CurrentLookup>,
+    parent_query: String,
+    word: String,
+    detail: Option<String>,
+) -> Result<serde_json::Value, LookupError> {
+    info!("Nested lookup of '{}' from '{}'", word, parent_query);
+
+    let options = lookup::LookupOptions {
+        referrer: Some(parent_query.clone()),
+        cancel: current.supersede(),
+        ..lookup::LookupOptions::default()
+    };
+
+    match lookup::lookup_with(&ctx, &word, &options).await {
+        Ok(result) => {
+            current.push_trail(&parent_query);
+            current.remember(&word, &result);
+            Ok(result_payload(&word, &detailed(&result, detail.as_deref())))
+        }
+        Err(LookupError::Cancelled) => {
+            info!("Lookup for '{}' superseded by a newer query", word);
+            Err(LookupError::Cancelled)
+        }
+        Err(e) => {
+            error!("Lookup failed for '{}': {}", word, e);
+            Err(e)
+        }
+    }
+}
+
+// The result a nested lookup was opened from, as it was shown; None once
+// the trail is back at its start
+#[command]
+fn go_back(current: State<'_, CurrentLookup>, detail: Option<String>) -> Option<serde_json::Value> {
+    // Whatever is still loading would replace the result being returned to
+    current.supersede();
+    let (query, result) = current.pop_trail()?;
+    current.remember(&query, &result);
+    Some(result_payload(&query, &detailed(&result, detail.as_deref())))
+}
+
+// Whether each enabled source answers, for telling the user whether their
+// network or an upstream service is at fault
+#[command]
+async fn check_sources(ctx: State<'_, LookupContext>) -> Result<Vec<lookup::SourceHealth>, String> {
+    Ok(lookup::health_check(&ctx).await)
+}
+
+// How the last lookup of `query` was reached through nested lookups, oldest
+// first and ending with `query` itself, for a breadcrumb trail
+#[command]
+async fn get_history_chain(query: String) -> Result<Vec<history::HistoryEntry>, String> {
+    history::chain(&query).await.map_err(|e| e.to_string())
+}
+
+// Find `needle` in the definitions, examples and Wikipedia paragraphs of
+// the result last shown for `query_id` (its requested_query), so the
+// frontend can highlight and scroll to each match
+#[command]
+fn search_result(current: State<'_, CurrentLookup>, query_id: String, needle: String) -> Result<Vec<SearchMatch>, String> {
+    let recent = current.recent.lock().unwrap_or_else(|e| e.into_inner());
+    let result = recent
+        .find(&query_id)
+        .ok_or_else(|| format!("No recent result for '{}'", query_id))?;
+    Ok(format::search_sections(&result.sections, &needle))
+}
+
+// One section ("wikipedia", "definitions", ...) of the result last shown
+// for `query`, untruncated, for a summary result the user wants all of
+#[command]
+fn get_full_section(current: State<'_, CurrentLookup>, query: String, section: String) -> Result<serde_json::Value, String> {
+    let recent = current.recent.lock().unwrap_or_else(|e| e.into_inner());
+    let result = recent.find(&query).ok_or_else(|| format!("No recent result for '{}'", query))?;
+    json!(result.sections)
+        .get(&section)
+        .filter(|payload| !payload.is_null())
+        .cloned()
+        .ok_or_else(|| format!("No {} section for '{}'", section, query))
+}
+
+// The result the window showed last, possibly before a restart, marked
+// `from_session_cache`; for putting the view back when it's shown again
+#[command]
+fn get_last_result(current: State<'_, CurrentLookup>) -> Option<serde_json::Value> {
+    let recent = current.recent.lock().unwrap_or_else(|e| e.into_inner());
+    recent.last().map(|(query, result)| result_payload(&query, &result))
+}
+
+// Up to `limit` of the results the window showed, newest first, for going
+// back through earlier lookups without running them again
+#[command]
+fn get_recent_results(current: State<'_, CurrentLookup>, limit: Option<usize>) -> Vec<serde_json::Value> {
+    let recent = current.recent.lock().unwrap_or_else(|e| e.into_inner());
+    recent
+        .recent(limit.unwrap_or(usize::MAX))
+        .iter()
+        .map(|(query, result)| result_payload(query, result))
+        .collect()
+}
+
+// Shown when the app starts without a query
+#[command]
+async fn get_word_of_the_day(ctx: State<'_, LookupContext>) -> Result<serde_json::Value, LookupError> {
+    match lookup::word_of_the_day(&ctx).await {
+        Ok(result) => Ok(json!(result)),
+        Err(e) => {
+            error!("Failed to look up the word of the day: {}", e);
+            Err(e)
+        }
+    }
+}
+
+// Two words side by side, like "affect" and "effect". One failing still
+// returns the other, with the comparison left empty.
+#[command]
+async fn compare_words(ctx: State<'_, LookupContext>, a: String, b: String) -> Result<CompareResult, LookupError> {
+    Ok(lookup::compare(&ctx, &a, &b).await)
+}
+
+// The frontend saw the network come back: run the lookups that failed
+// without it now rather than at the next probe
+#[command]
+async fn network_online(app: tauri::AppHandle) {
+    let ctx = app.state::<LookupContext>();
+    emit_deferred_results(&app, deferred::retry(&ctx).await);
+}
+
+fn emit_deferred_results(app: &tauri::AppHandle, results: Vec<LookupResult>) {
+    for result in results {
+        if let Err(e) = app.emit("deferred-result", &result) {
+            error!("Failed to emit deferred-result: {}", e);
+        }
+    }
+}
+
+// A page of a Wikipedia article's sections, for reading past the summary.
+// Links in the returned HTML carry a `data-title` for the frontend to open.
+#[command]
+async fn get_wikipedia_article(
+    ctx: State<'_, LookupContext>,
+    title: String,
+    lang: Option<String>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> Result<ArticlePage, LookupError> {
+    blocked_if_private(&ctx, "wikipedia")?;
+    let lang = lang.unwrap_or_else(|| ctx.config().lookup_language());
+    let limit = limit.unwrap_or(wikipedia::DEFAULT_ARTICLE_PAGE_SIZE);
+    wikipedia::get_article_sections(&title, &lang, offset.unwrap_or(0), limit)
+        .await
+        .inspect_err(|e| error!("Failed to fetch the article '{}': {}", title, e))
+}
+
+// Private mode for the rest of the session: only local sources and what's
+// already cached are used, and nothing is cached, recorded or saved. A
+// restart goes back to the config's `private_mode`.
+#[command]
+fn set_private_mode(ctx: State<'_, LookupContext>, enabled: bool) {
+    ctx.set_private_mode(enabled);
+}
+
+#[command]
+fn get_private_mode(ctx: State<'_, LookupContext>) -> bool {
+    ctx.is_private()
+}
+
+// For commands that go to the network outside a lookup
+fn blocked_if_private(ctx: &LookupContext, source: &str) -> Result<(), LookupError> {
+    if ctx.is_private() {
+        return Err(LookupError::PrivateModeBlocked { source: source.to_string() });
+    }
+    Ok(())
+}
+
+// Whether lookups can still be answered when the network is down
+#[command]
+fn is_offline_available() -> bool {
+    offline::is_available()
+}
+
+#[command]
+async fn clear_cache() -> Result<usize, String> {
+    match cache::clear().await {
+        Ok(removed) => {
+            info!("Cleared {} cache entries", removed);
+            Ok(removed)
+        }
+        Err(e) => {
+            error!("Failed to clear cache: {}", e);
+            Err(e.to_string())
+        }
+    }
+}
+
+#[command]
+fn copy_to_clipboard(app: tauri::AppHandle, text: String) -> Result<(), String> {
+    copy(&app, text)
+}
+
+// Copy the result shown for `query` as Markdown. One no longer among the
+// recent results is looked up again, without adding to the history.
+#[command]
+async fn copy_result_markdown(
+    app: tauri::AppHandle,
+    ctx: State<'_, LookupContext>,
+    current: State<'_, CurrentLookup>,
+    query: String,
+) -> Result<(), String> {
+    let shown = current.recent.lock().unwrap_or_else(|e| e.into_inner()).find(&query).cloned();
+    let result = match shown {
+        Some(result) => result,
+        None => {
+            let options = lookup::LookupOptions { skip_history: true, ..lookup::LookupOptions::default() };
+            lookup::lookup_with(&ctx, &query, &options).await.map_err(|e| e.to_string())?
+        }
+    };
+    copy(&app, format::render_markdown(&result))
+}
+
+// The plugin keeps serving the text for as long as the app runs
+fn copy(app: &tauri::AppHandle, text: String) -> Result<(), String> {
+    app.clipboard().write_text(text).map_err(|e| {
+        error!("Failed to copy to clipboard: {}", e);
+        e.to_string()
+    })
+}
+
+#[command]
+async fn add_favorite(
+    ctx: State<'_, LookupContext>,
+    word: String,
+    note: Option<String>,
+    tags: Option<Vec<String>>,
+) -> Result<favorites::FavoriteEntry, String> {
+    if ctx.is_private() {
+        return Err(format!("'{}' not saved: private mode is on", word));
+    }
+    favorites::add(&word, note, tags.unwrap_or_default()).await.map_err(|e| {
+        error!("Failed to save favorite '{}': {}", word, e);
+        e.to_string()
+    })
+}
+
+#[command]
+async fn remove_favorite(word: String) -> Result<bool, String> {
+    favorites::remove(&word).await.map_err(|e| {
+        error!("Failed to remove favorite '{}': {}", word, e);
+        e.to_string()
+    })
+}
+
+// Most recently saved first, optionally only those with `tag`
+#[command]
+async fn get_favorites(tag: Option<String>) -> Result<Vec<favorites::FavoriteEntry>, String> {
+    let result = match tag {
+        Some(tag) => favorites::list_by_tag(&tag).await,
+        None => favorites::list().await,
+    };
+
+    result.map_err(|e| {
+        error!("Failed to read favorites: {}", e);
+        e.to_string()
+    })
+}
+
+// Most recent lookups first, optionally filtered by a query substring
+#[command]
+async fn get_history(
+    limit: Option<usize>,
+    search: Option<String>,
+) -> Result<Vec<history::HistoryEntry>, String> {
+    let result = match search {
+        Some(search) => history::search(&search).await.map(|mut entries| {
+            entries.truncate(limit.unwrap_or(usize::MAX));
+            entries
+        }),
+        None => history::recent(limit.unwrap_or(history::DEFAULT_RECENT_LIMIT)).await,
+    };
+
+    result.map_err(|e| {
+        error!("Failed to read history: {}", e);
+        e.to_string()
+    })
+}
+
+#[command]
+async fn clear_history() -> Result<usize, String> {
+    match history::clear().await {
+        Ok(removed) => {
+            info!("Cleared {} history entries", removed);
+            Ok(removed)
+        }
+        Err(e) => {
+            error!("Failed to clear history: {}", e);
+            Err(e.to_string())
+        }
+    }
+}
+
+// Where an export goes when the frontend doesn't say: a dated file in the
+// downloads folder (or the home folder without one)
+fn export_path(app: &tauri::AppHandle, what: &str, format: ExportFormat, path: Option<String>) -> Result<PathBuf, String> {
+    if let Some(path) = path.filter(|p| !p.trim().is_empty()) {
+        return Ok(PathBuf::from(path));
+    }
+    let dir = app.path().download_dir().or_else(|_| app.path().home_dir()).map_err(|e| e.to_string())?;
+    Ok(dir.join(export::default_file_name(what, format)))
+}
+
+// Write the whole history as "csv" or "json", returning the file written
+#[command]
+async fn export_history(app: tauri::AppHandle, format: String, path: Option<String>) -> Result<String, String> {
+    let format: ExportFormat = format.parse()?;
+    let path = export_path(&app, "history", format, path)?;
+    match export::export_history(format, &path).await {
+        Ok(count) => {
+            info!("Exported {} history entries to {}", count, path.display());
+            Ok(path.display().to_string())
+        }
+        Err(e) => {
+            error!("Failed to export history to {}: {}", path.display(), e);
+            Err(e.to_string())
+        }
+    }
+}
+
+#[command]
+async fn export_favorites(app: tauri::AppHandle, format: String, path: Option<String>) -> Result<String, String> {
+    let format: ExportFormat = format.parse()?;
+    let path = export_path(&app, "favorites", format, path)?;
+    match export::export_favorites(format, &path).await {
+        Ok(count) => {
+            info!("Exported {} favorites to {}", count, path.display());
+            Ok(path.display().to_string())
+        }
+        Err(e) => {
+            error!("Failed to export favorites to {}: {}", path.display(), e);
+            Err(e.to_string())
+        }
+    }
+}
+
+// Save a result as a standalone HTML page to print or share, returning the
+// file written. Defaults to `<query>.html` in the downloads folder; the
+// article image is inlined when it's already cached.
+#[command]
+async fn export_result_html(
+    app: tauri::AppHandle,
+    ctx: State<'_, LookupContext>,
+    current: State<'_, CurrentLookup>,
+    query: String,
+    path: Option<String>,
+) -> Result<String, String> {
+    let shown = current.recent.lock().unwrap_or_else(|e| e.into_inner()).find(&query).cloned();
+    let result = match shown {
+        Some(result) => result,
+        None => lookup::lookup_with(&ctx, &query, &lookup::LookupOptions::default())
+            .await
+            .map_err(|e| e.to_string())?,
+    };
+
+    let path = match path.filter(|p| !p.trim().is_empty()) {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let dir = app.path().download_dir().or_else(|_| app.path().home_dir()).map_err(|e| e.to_string())?;
+            let name: String = result.query.chars().map(|c| if c.is_alphanumeric() || c == '-' { c } else { '_' }).collect();
+            dir.join(format!("{}.html", name))
+        }
+    };
+
+    let mut options = format::HtmlOptions::now();
+    let image_url = result.sections.wikipedia.as_ref().and_then(|wiki| wiki.image_url.clone());
+    if let (Some(url), Ok(images)) = (image_url, image_cache(&app)) {
+        if let Some(cached) = images.cached_path(&url) {
+            options.image = tokio::fs::read(cached).await.ok();
+        }
+    }
+
+    match tokio::fs::write(&path, format::render_html_with(&result, &options)).await {
+        Ok(()) => {
+            info!("Saved '{}' to {}", query, path.display());
+            Ok(path.display().to_string())
+        }
+        Err(e) => {
+            error!("Failed to save '{}' to {}: {}", query, path.display(), e);
+            Err(e.to_string())
+        }
+    }
+}
+
+fn image_cache(app: &tauri::AppHandle) -> Result<ImageCache, String> {
+    app.path()
+        .app_cache_dir()
+        .map(|dir| ImageCache::new(dir.join("images")))
+        .map_err(|e| e.to_string())
+}
+
+// Download (or reuse) an article image and return its local path, for the
+// frontend to load through `convertFileSrc`. Only https URLs on the hosts in
+// `images::ALLOWED_HOSTS` are fetched.
+#[command]
+async fn fetch_image(app: tauri::AppHandle, ctx: State<'_, LookupContext>, url: String) -> Result<String, String> {
+    blocked_if_private(&ctx, "images").map_err(|e| e.to_string())?;
+    let path = image_cache(&app)?.fetch(&url).await.map_err(|e| {
+        warn!("Image fetch failed: {}", e);
+        e.to_string()
+    })?;
+    Ok(path.to_string_lossy().into_owned())
+}
+
+#[command]
+async fn clear_image_cache(app: tauri::AppHandle) -> Result<usize, String> {
+    match image_cache(&app)?.clear().await {
+        Ok(removed) => {
+            info!("Cleared {} cached images", removed);
+            Ok(removed)
+        }
+        Err(e) => {
+            error!("Failed to clear image cache: {}", e);
+            Err(e.to_string())
+        }
+    }
+}
+
+// Play a pronunciation recording; only https URLs on the audio hosts in
+// `audio::ALLOWED_HOSTS` are fetched. The window stays up while it plays.
+#[command]
+async fn play_audio(ctx: State<'_, LookupContext>, hold: State<'_, WindowHold>, url: String) -> Result<(), String> {
+    blocked_if_private(&ctx, "audio").map_err(|e| e.to_string())?;
+    info!("Playing audio: {}", url);
+    hold.playing.fetch_add(1, Ordering::SeqCst);
+    let played = async {
+        let path = AudioCache::default().fetch(&url).await.map_err(|e| e.to_string())?;
+        tauri::async_runtime::spawn_blocking(move || play_file(&path)).await.map_err(|e| e.to_string())?
+    }
+    .await;
+    hold.playing.fetch_sub(1, Ordering::SeqCst);
+    played.map_err(|e| {
+        error!("Audio playback failed: {}", e);
+        e
+    })
+}
+
+// Decode a downloaded recording and play it on the default output device,
+// returning once it has finished
+fn play_file(path: &Path) -> Result<(), String> {
+    let (_stream, output) = rodio::OutputStream::try_default().map_err(|e| format!("No audio output: {}", e))?;
+    let sink = rodio::Sink::try_new(&output).map_err(|e| format!("No audio output: {}", e))?;
+    let file = std::fs::File::open(path).map_err(|e| format!("Audio cache error: {}", e))?;
+    let source = rodio::Decoder::new(std::io::BufReader::new(file)).map_err(|e| format!("Cannot play the audio: {}", e))?;
+    sink.append(source);
+    sink.sleep_until_end();
+    Ok(())
+}
+
+// Download a recording ahead of time so playback starts instantly
+#[command]
+async fn prefetch_audio(ctx: State<'_, LookupContext>, url: String) -> Result<(), String> {
+    blocked_if_private(&ctx, "audio").map_err(|e| e.to_string())?;
+    audio::prefetch(&url).await.map_err(|e| {
+        warn!("Audio prefetch failed: {}", e);
+        e.to_string()
+    })
+}
+
+// Warm the dictionary cache for words the user may tap next, such as the
+// synonyms on screen. A handful at a time, so the frontend calls it once
+// the result is shown.
+#[command]
+async fn prefetch_lookups(ctx: State<'_, LookupContext>, words: Vec<String>) -> Result<usize, String> {
+    Ok(lookup::prefetch(&ctx, &words).await)
+}
+
+#[command]
+fn get_config() -> Config {
+    Config::load()
+}
+
+// Takes effect on the next lookup, which sees the file has changed. A config
+// with problems isn't saved; each one names its key, for the settings UI to
+// mark the field.
+#[command]
+fn set_config(config: Config) -> Result<(), Vec<ConfigProblem>> {
+    let problems = config.validate();
+    if !problems.is_empty() {
+        warn!("Not saving a config with {} problems", problems.len());
+        return Err(problems);
+    }
+    config.save().map_err(|e| {
+        error!("Failed to save config: {}", e);
+        vec![ConfigProblem { key: String::new(), message: e.to_string() }]
+    })
+}
+
+fn preferences_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_config_dir()
+        .map(|dir| dir.join(preferences::FILE_NAME))
+        .map_err(|e| e.to_string())
+}
+
+#[command]
+fn get_preferences(app: tauri::AppHandle) -> Preferences {
+    match preferences_path(&app) {
+        Ok(path) => Preferences::load(&path),
+        Err(e) => {
+            warn!("No preferences location: {}", e);
+            Preferences::default()
+        }
+    }
+}
+
+// Merge a partial update into the saved preferences and tell every window.
+// A new hotkey is registered first, and the update rejected if that fails.
+#[command]
+fn set_preferences(app: tauri::AppHandle, prefs: serde_json::Value) -> Result<Preferences, String> {
+    let path = preferences_path(&app)?;
+    let saved = Preferences::load(&path);
+    let preferences = saved.merged(&prefs).map_err(|e| {
+        warn!("Rejected preferences update: {}", e);
+        e
+    })?;
+    if preferences.hotkey != saved.hotkey {
+        register_hotkey(&app, &preferences.hotkey).map_err(|e| {
+            warn!("Rejected shortcut '{}': {}", preferences.hotkey, e);
+            e.to_string()
+        })?;
+    }
+    preferences.save(&path).map_err(|e| {
+        error!("Failed to save preferences: {}", e);
+        e.to_string()
+    })?;
+    if let Err(e) = app.emit("preferences-changed", &preferences) {
+        error!("Failed to emit preferences-changed: {}", e);
+    }
+    Ok(preferences)
+}
+
+// Change the global shortcut, e.g. "Ctrl+Alt+D", or turn it off with an
+// empty string. Fails and keeps the old one when the accelerator is invalid
+// or another application (often the desktop) already has it.
+#[command]
+fn set_hotkey(app: tauri::AppHandle, accel: String) -> Result<Preferences, String> {
+    set_preferences(app, json!({ "hotkey": accel }))
+}
+
+// The registered global shortcut, if any
+#[derive(Default)]
+struct GlobalHotkey(Mutex<Option<Shortcut>>);
+
+// Register `accelerator` in place of the current shortcut, getting the
+// current one back if it can't be had. Empty releases the shortcut.
+fn register_hotkey(app: &tauri::AppHandle, accelerator: &str) -> Result<(), tauri_plugin_global_shortcut::Error> {
+    let shortcut = match accelerator {
+        "" => None,
+        accelerator => Some(accelerator.parse::<Shortcut>()?),
+    };
+    let state = app.state::<GlobalHotkey>();
+    let mut current = state.0.lock().unwrap_or_else(|e| e.into_inner());
+    // Released first, since the new shortcut may share keys with it
+    let previous = current.take();
+    if let Some(previous) = previous {
+        if let Err(e) = app.global_shortcut().unregister(previous) {
+            warn!("Failed to release global shortcut {}: {}", previous, e);
+        }
+    }
+    let Some(shortcut) = shortcut else {
+        info!("Global shortcut turned off");
+        return Ok(());
+    };
+
+    if let Err(e) = app.global_shortcut().register(shortcut) {
+        *current = previous.filter(|&previous| app.global_shortcut().register(previous).is_ok());
+        return Err(e);
+    }
+    info!("Registered global shortcut {}", accelerator);
+    *current = Some(shortcut);
+    Ok(())
+}
+
+// Keep the cache, image cache and history in check: once a while after
+// startup, then daily for as long as the app runs
+fn spawn_maintenance(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(maintenance::STARTUP_DELAY).await;
+        loop {
+            let ctx = app.state::<LookupContext>();
+            match maintenance::run_maintenance(&ctx).await {
+                Ok(_) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => info!("Skipping maintenance: {}", e),
+                Err(e) => warn!("Maintenance failed: {}", e),
+            }
+            tokio::time::sleep(maintenance::INTERVAL).await;
+        }
+    });
+}
+
+// Look up the selection (or the clipboard when nothing is selected), then
+// bring the window up next to the pointer with the result as a
+// `hotkey-lookup` event of `{ query, result }` or `{ query, error }`
+async fn lookup_for_hotkey(app: &tauri::AppHandle) {
+    let ctx = app.state::<LookupContext>();
+    let backend = ctx.config().clipboard_backend();
+    let text = match tokio::task::spawn_blocking(move || clipboard::get_text(Selection::Auto, backend)).await {
+        Ok(Ok(text)) => text,
+        Ok(Err(e)) => {
+            warn!("Nothing to look up: {}", e);
+            return;
+        }
+        Err(e) => {
+            error!("Selection lookup failed: {}", e);
+            return;
+        }
+    };
+
+    let Some(text) = guard_selection(app, &text) else {
+        return;
+    };
+
+    let current = app.state::<CurrentLookup>();
+    let options = lookup::LookupOptions { cancel: current.supersede(), ..lookup::LookupOptions::default() };
+    let payload = match lookup::lookup_with(&ctx, &text, &options).await {
+        Ok(result) => {
+            current.clear_trail();
+            current.remember(&text, &result);
+            json!({ "query": text, "result": result })
+        }
+        Err(LookupError::Cancelled) => return,
+        Err(e) => {
+            error!("Lookup failed for '{}': {}", text, e);
+            json!({ "query": text, "error": e })
+        }
+    };
+    show_at_cursor(app.clone());
+    if let Err(e) = app.emit("hotkey-lookup", payload) {
+        error!("Failed to emit hotkey-lookup: {}", e);
+    }
+}
+
+// What to look up on startup: the command-line words, or a `touchdict:`
+// link the app was launched with
 #[command]
-fn get_initial_query() -> Vec<String> {
-    // Get command-line arguments passed to the app
-    std::env::args().skip(1).collect()
+fn get_initial_query() -> Option<LookupRequest> {
+    LookupRequest::from_args(&query_args())
 }
 
+// Only hides: the tray icon owns the app's lifetime, and Quit there is the
+// way out
 #[command]
 fn close_window(window: WebviewWindow) {
-    println!("[INFO] [touchdictionary] [gui] Closing window");
-    // Use the window handle to close/hide it
+    info!("Closing window");
     if let Err(e) = window.hide() {
-        println!("[ERROR] [touchdictionary] [gui] Failed to hide window: {}", e);
+        error!("Failed to hide window: {}", e);
     }
 }
 
+// Escape: hide the window and tell the frontend to forget the query. A
+// lookup still running is left to finish so it gets recorded.
+#[command]
+fn dismiss(window: WebviewWindow, hold: State<'_, WindowHold>) {
+    info!("Dismissing window");
+    hold.menu_open.store(false, Ordering::SeqCst);
+    if let Err(e) = window.hide() {
+        error!("Failed to hide window: {}", e);
+    }
+    if let Err(e) = window.emit("dismissed", ()) {
+        error!("Failed to emit dismissed: {}", e);
+    }
+}
+
+// How long the window may stay unfocused before hiding, so focus passing
+// through another window for a moment (a tray click, a drag) doesn't
+// dismiss it
+const BLUR_GRACE: Duration = Duration::from_millis(300);
+
+// Reasons to keep the window up when it loses focus
+#[derive(Default)]
+struct WindowHold {
+    // Set by the frontend while one of its context menus is open
+    menu_open: AtomicBool,
+    // Pronunciations being played
+    playing: AtomicUsize,
+}
+
+impl WindowHold {
+    fn is_held(&self) -> bool {
+        self.menu_open.load(Ordering::SeqCst) || self.playing.load(Ordering::SeqCst) > 0
+    }
+}
+
+// Keep the window from hiding on focus loss while `hold` is true
+#[command]
+fn hold_window_open(window_hold: State<'_, WindowHold>, hold: bool) {
+    window_hold.menu_open.store(hold, Ordering::SeqCst);
+}
+
+// Hide `window` if it's still unfocused after `BLUR_GRACE` and nothing is
+// holding it open. Only hides: lookups keep running.
+fn hide_after_blur(window: tauri::Window) {
+    let app = window.app_handle().clone();
+    if !get_preferences(app.clone()).auto_hide_on_blur {
+        return;
+    }
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(BLUR_GRACE).await;
+        if app.state::<WindowHold>().is_held() || window.is_focused().unwrap_or(true) || !window.is_visible().unwrap_or(false) {
+            return;
+        }
+        info!("Hiding window after it lost focus");
+        if let Err(e) = window.hide() {
+            error!("Failed to hide window: {}", e);
+        }
+    });
+}
+
+// The OS's light/dark setting, for the `auto` theme
+#[command]
+async fn get_system_theme(window: WebviewWindow) -> SystemTheme {
+    system_theme(window.theme().ok()).await
+}
+
+// What the window system reported, except on Linux where GTK's "light" is
+// only a default; the settings portal is asked instead
+async fn system_theme(reported: Option<tauri::Theme>) -> SystemTheme {
+    let reported = match reported {
+        Some(tauri::Theme::Dark) => SystemTheme::Dark,
+        Some(tauri::Theme::Light) => SystemTheme::Light,
+        _ => SystemTheme::Unknown,
+    };
+    if cfg!(all(unix, not(target_os = "macos"))) && reported != SystemTheme::Dark {
+        return theme::portal_theme().await;
+    }
+    reported
+}
+
+fn forward_theme_change(window: tauri::Window, reported: tauri::Theme) {
+    tauri::async_runtime::spawn(async move {
+        let theme = system_theme(Some(reported)).await;
+        info!("System theme changed to {:?}", theme);
+        if let Err(e) = window.emit("system-theme-changed", theme) {
+            error!("Failed to emit system-theme-changed: {}", e);
+        }
+    });
+}
+
 #[command]
 fn open_url(app: tauri::AppHandle, url: String) {
-    println!("[INFO] [touchdictionary] [gui] Opening URL: {}", url);
+    info!("Opening URL: {}", url);
     let _ = app.opener().open_url(url, None::<&str>);
 }
 
+// The providers `open_external` knows, for the window's "Open in" menu
+#[command]
+fn get_external_providers(app: tauri::AppHandle) -> Vec<ExternalProvider> {
+    get_preferences(app).external_providers
+}
+
+// Open `query` on one of the configured external sites. Only templates
+// that passed validation get here, and the query is percent-encoded
+// before it goes in.
+#[command]
+fn open_external(app: tauri::AppHandle, provider: String, query: String) -> Result<(), String> {
+    if query.trim().is_empty() {
+        return Err("nothing to look up".to_string());
+    }
+    let preferences = get_preferences(app.clone());
+    let provider = preferences
+        .external_provider(&provider)
+        .ok_or_else(|| format!("unknown external provider '{}'", provider))?;
+    let url = provider.url_for(&query);
+    info!("Opening {} for {:?}: {}", provider.name, query, url);
+    app.opener().open_url(url, None::<&str>).map_err(|e| e.to_string())
+}
+
+fn show_main_window(app: &tauri::AppHandle) {
+    if let Some(window) = app.get_webview_window(MAIN_WINDOW) {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+// Show the window next to the pointer for a selection lookup, unless the
+// config asks for it to stay where it was left
+#[command]
+fn show_at_cursor(app: tauri::AppHandle) {
+    if Config::load().window.follow_cursor {
+        if let Some(window) = app.get_webview_window(MAIN_WINDOW) {
+            if let Err(e) = move_to_cursor(&window) {
+                warn!("Could not place window at cursor: {}", e);
+            }
+        }
+    }
+    show_main_window(&app);
+}
+
+// Move onto the monitor under the pointer, clamped to its work area. The
+// window's size is rescaled first in case that monitor has a different
+// scale factor than the one it's on now.
+fn move_to_cursor(window: &WebviewWindow) -> tauri::Result<()> {
+    let cursor = window.cursor_position()?;
+    let Some(monitor) = window.monitor_from_point(cursor.x, cursor.y)? else {
+        warn!("No monitor under the pointer at {:?}", cursor);
+        return Ok(());
+    };
+
+    let rescale = monitor.scale_factor() / window.scale_factor()?;
+    let size = window.outer_size()?;
+    let width = (size.width as f64 * rescale).round() as u32;
+    let height = (size.height as f64 * rescale).round() as u32;
+    let area = monitor.work_area();
+    let work_area = placement::Rect {
+        x: area.position.x,
+        y: area.position.y,
+        width: area.size.width,
+        height: area.size.height,
+    };
+    let offset = (placement::CURSOR_OFFSET * monitor.scale_factor()).round() as i32;
+
+    let cursor = (cursor.x.round() as i32, cursor.y.round() as i32);
+    let (x, y) = placement::near_cursor(cursor, width, height, work_area, offset);
+    window.set_position(PhysicalPosition::new(x, y))
+}
+
+fn toggle_main_window(app: &tauri::AppHandle) {
+    if let Some(window) = app.get_webview_window(MAIN_WINDOW) {
+        if window.is_visible().unwrap_or(false) {
+            let _ = window.hide();
+        } else {
+            show_main_window(app);
+        }
+    }
+}
+
+// The selection cleaned up for looking up, or None when it mustn't be. A
+// refused one is only reported to the frontend, as a `selection-rejected`
+// event carrying the error, without bringing the window up.
+fn guard_selection(app: &tauri::AppHandle, text: &str) -> Option<String> {
+    let max_length = app.state::<LookupContext>().config().selection.max_length;
+    match guard::check_selection(text, max_length) {
+        Ok(text) => Some(text),
+        Err(e @ LookupError::SelectionRejected { .. }) => {
+            info!("{}", e);
+            if let Err(e) = app.emit("selection-rejected", &e) {
+                error!("Failed to emit selection-rejected: {}", e);
+            }
+            None
+        }
+        Err(_) => None,
+    }
+}
+
+// Probe the network while lookups wait for it, handing each one that then
+// goes through to the frontend
+fn spawn_network_watcher(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let ctx = app.state::<LookupContext>();
+            tokio::time::sleep(Duration::from_secs(ctx.config().deferred.probe_interval_secs)).await;
+            emit_deferred_results(&app, deferred::retry_if_online(&ctx).await);
+        }
+    });
+}
+
+// Set from the tray to ignore selections without stopping the watcher
+#[derive(Default)]
+struct WatchPaused(AtomicBool);
+
+// Show the window and hand each new selection to the frontend
+fn spawn_selection_watcher(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let backend = app.state::<LookupContext>().config().clipboard_backend();
+        let mut selections = watch::spawn(watch::WatchConfig { backend, ..watch::WatchConfig::from_env() });
+        let mut throttle = notify::Throttle::default();
+        while let Some(event) = selections.recv().await {
+            if app.state::<WatchPaused>().0.load(Ordering::Relaxed) {
+                continue;
+            }
+            let Some(text) = guard_selection(&app, &event.text) else {
+                continue;
+            };
+            let event = watch::SelectionEvent { text };
+            // Selections made while the window is hidden become a
+            // notification instead of popping the window up
+            let hidden = app.get_webview_window(MAIN_WINDOW).is_some_and(|w| !w.is_visible().unwrap_or(true));
+            if hidden && app.state::<LookupContext>().config().notifications {
+                notify_selection(&app, event.text, &mut throttle).await;
+                continue;
+            }
+            show_at_cursor(app.clone());
+            if let Err(e) = app.emit("selection-changed", &event) {
+                error!("Failed to emit selection-changed: {}", e);
+            }
+        }
+    });
+}
+
+// Look up a background selection and show its top definition as a
+// notification. Clicking it opens the window on the full result.
+async fn notify_selection(app: &tauri::AppHandle, query: String, throttle: &mut notify::Throttle) {
+    let ctx = app.state::<LookupContext>();
+    let result = match lookup::lookup_with(&ctx, &query, &lookup::LookupOptions::default()).await {
+        Ok(result) => result,
+        Err(e) => {
+            info!("Not notifying about '{}': {}", query, e);
+            return;
+        }
+    };
+    let Some(notification) = Notification::for_result(&result) else {
+        return;
+    };
+    if !throttle.allow(std::time::Instant::now(), ctx.config().notification_interval()) {
+        info!("Skipping notification for '{}', one was shown recently", query);
+        return;
+    }
+
+    // Waiting on the click mustn't hold up the watcher
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        match notify::send(&notification, Some(notify::OPEN_ACTION)).await {
+            Ok(true) => {
+                show_main_window(&app);
+                if let Err(e) = app.emit("new-query", LookupRequest::query(query)) {
+                    error!("Failed to emit new-query: {}", e);
+                }
+            }
+            Ok(false) => {}
+            Err(e) => warn!("{}", e),
+        }
+    });
+}
+
+// Another invocation started while we run: show the window and pass its
+// query on. `argv` still has the program name first.
+fn handle_second_instance(app: &tauri::AppHandle, argv: Vec<String>) {
+    let args = &argv[argv.len().min(1)..];
+    // A malformed link is ignored rather than popping up the window
+    let request = LookupRequest::from_args(args);
+    if request.is_none() && !args.is_empty() {
+        return;
+    }
+    match request {
+        Some(request) => open_request(app, request),
+        None => show_main_window(app),
+    }
+}
+
+// Links macOS hands to the running app rather than to a new process
+fn handle_open_urls(app: &tauri::AppHandle, urls: Vec<url::Url>) {
+    for url in urls {
+        match deep_link::parse(url.as_str()) {
+            Ok(request) => open_request(app, request),
+            Err(e) => warn!("Ignoring link '{}': {}", url, e),
+        }
+    }
+}
+
+fn open_request(app: &tauri::AppHandle, request: LookupRequest) {
+    show_main_window(app);
+    info!("Received forwarded query: {}", request.query);
+    if let Err(e) = app.emit("new-query", request) {
+        error!("Failed to emit new-query: {}", e);
+    }
+}
+
+// Look up whatever is selected right now, as if it had been passed on the
+// command line
+fn lookup_selection(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let backend = app.state::<LookupContext>().config().clipboard_backend();
+        match tokio::task::spawn_blocking(move || clipboard::get_text(Selection::Primary, backend)).await {
+            Ok(Ok(text)) => {
+                let Some(text) = guard_selection(&app, &text) else {
+                    return;
+                };
+                show_at_cursor(app.clone());
+                if let Err(e) = app.emit("new-query", LookupRequest::query(text)) {
+                    error!("Failed to emit new-query: {}", e);
+                }
+            }
+            Ok(Err(e)) => warn!("Nothing to look up: {}", e),
+            Err(e) => error!("Selection lookup failed: {}", e),
+        }
+    });
+}
+
+// Tray icon and its menu. Clicking the icon toggles the window; most Linux
+// trays only open the menu, which is why "Show dictionary" is in it.
+fn build_tray(app: &tauri::App, watching: bool) -> tauri::Result<()> {
+    let show = MenuItem::with_id(app, "show", "Show dictionary", true, None::<&str>)?;
+    let lookup = MenuItem::with_id(app, "lookup-selection", "Lookup selection", true, None::<&str>)?;
+    let pause = CheckMenuItem::with_id(app, "pause-watching", "Pause selection watching", watching, false, None::<&str>)?;
+    let separator = PredefinedMenuItem::separator(app)?;
+    let quit = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
+    let menu = Menu::with_items(app, &[&show, &lookup, &pause, &separator, &quit])?;
+
+    let mut tray = TrayIconBuilder::with_id("main")
+        .tooltip("TouchDictionary")
+        .menu(&menu)
+        .show_menu_on_left_click(false)
+        .on_menu_event(|app, event| match event.id().as_ref() {
+            "show" => show_main_window(app),
+            "lookup-selection" => lookup_selection(app.clone()),
+            "pause-watching" => {
+                let paused = !app.state::<WatchPaused>().0.fetch_xor(true, Ordering::Relaxed);
+                info!("Selection watching {}", if paused { "paused" } else { "resumed" });
+            }
+            "quit" => {
+                info!("Quitting from tray");
+                app.exit(0);
+            }
+            _ => {}
+        })
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                toggle_main_window(tray.app_handle());
+            }
+        });
+    if let Some(icon) = app.default_window_icon() {
+        tray = tray.icon(icon.clone());
+    }
+    tray.build(app)?;
+    Ok(())
+}
+
 pub fn run() {
+    let config = Config::load();
+    if config.log_to_file {
+        match logging::default_log_dir() {
+            Some(dir) => logging::init_with_file(&dir),
+            None => logging::init_stderr(),
+        }
+    } else {
+        logging::init_stderr();
+    }
+
     let context = tauri::generate_context!();
     tauri::Builder::default()
+        // First, so a second instance hands over its arguments and exits
+        // before setting anything else up
+        .plugin(tauri_plugin_single_instance::init(|app, argv, _cwd| handle_second_instance(app, argv)))
+        .plugin(tauri_plugin_deep_link::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .plugin(tauri_plugin_os::init())
         .plugin(tauri_plugin_opener::init())
-        .invoke_handler(generate_handler![run_lookup_command, get_initial_query, close_window, open_url])
+        // Holding the keys down reports one press; the release is ignored
+        .plugin(
+            tauri_plugin_global_shortcut::Builder::new()
+                .with_handler(|app, _shortcut, event| {
+                    if event.state == ShortcutState::Pressed {
+                        let app = app.clone();
+                        tauri::async_runtime::spawn(async move { lookup_for_hotkey(&app).await });
+                    }
+                })
+                .build(),
+        )
+        .manage(CurrentLookup::new(ResultStore::open_default(config.session.max_results)))
+        .manage(WatchPaused::default())
+        .manage(GlobalHotkey::default())
+        .manage(WindowHold::default())
+        .invoke_handler(generate_handler![
+            run_lookup_command,
+            run_lookup_streaming,
+            get_word_of_the_day,
+            compare_words,
+            network_online,
+            get_wikipedia_article,
+            search_result,
+            get_last_result,
+            get_recent_results,
+            get_full_section,
+            is_offline_available,
+            set_private_mode,
+            get_private_mode,
+            clear_cache,
+            get_history,
+            clear_history,
+            export_history,
+            export_favorites,
+            export_result_html,
+            copy_to_clipboard,
+            copy_result_markdown,
+            add_favorite,
+            remove_favorite,
+            get_favorites,
+            get_config,
+            set_config,
+            get_preferences,
+            set_preferences,
+            set_hotkey,
+            get_system_theme,
+            play_audio,
+            prefetch_audio,
+            prefetch_lookups,
+            fetch_image,
+            clear_image_cache,
+            get_initial_query,
+            close_window,
+            dismiss,
+            hold_window_open,
+            show_at_cursor,
+            open_url,
+            open_external,
+            get_external_providers,
+            lookup_nested,
+            go_back,
+            get_history_chain,
+            check_sources
+        ])
         .plugin(tauri_plugin_shell::init())
-        .setup(|app| {
-            // Check if we have command-line arguments
+        .setup(move |app| {
+            // Built once so every lookup reuses the same connections
+            let ctx = LookupContext::load();
+            if let Some(locale) = tauri_plugin_os::locale() {
+                ctx.set_system_locale(&locale);
+            }
+            app.manage(ctx);
+
+            // macOS registers the scheme from the bundle; elsewhere it's
+            // done on every start so the handler follows the binary if it
+            // moves
+            #[cfg(any(target_os = "linux", windows))]
+            if let Err(e) = app.deep_link().register_all() {
+                warn!("Not registering {}: links: {}", deep_link::SCHEME, e);
+            }
+            let handle = app.handle().clone();
+            app.deep_link().on_open_url(move |event| handle_open_urls(&handle, event.urls()));
+
+            let window = main_window(app).inspect_err(|e| error!("Failed to create the main window: {}", e))?;
             let args: Vec<String> = std::env::args().skip(1).collect();
-            
-            if !args.is_empty() {
-                // Show the window immediately on startup with query
-                let webview_window = app.get_webview_window("main").unwrap();
-                webview_window.show().unwrap();
-                webview_window.set_focus().unwrap();
-                println!("[INFO] [touchdictionary] [gui] Window shown for query: {}", args.join(" "));
+            if starts_visible(&args) {
+                if let Err(e) = window.show().and_then(|_| window.set_focus()) {
+                    warn!("Could not show the window: {}", e);
+                }
+                info!("Window shown for query: {}", query_args().join(" "));
             } else {
-                // No args - start hidden in background
-                let webview_window = app.get_webview_window("main").unwrap();
-                webview_window.hide().unwrap();
-                println!("[INFO] [touchdictionary] [gui] No arguments provided, running in background mode");
+                if let Err(e) = window.hide() {
+                    warn!("Could not hide the window: {}", e);
+                }
+                info!("No query given, running in background mode");
+            }
+
+            let watching = std::env::args().any(|arg| arg == WATCH_FLAG);
+            if watching {
+                info!("Watching selection for lookups");
+                spawn_selection_watcher(app.handle().clone());
+            }
+
+            build_tray(app, watching)?;
+            spawn_maintenance(app.handle().clone());
+            if app.state::<LookupContext>().config().deferred.enabled {
+                spawn_network_watcher(app.handle().clone());
+            }
+
+            let accelerator = get_preferences(app.handle().clone()).hotkey;
+            if let Err(e) = register_hotkey(app.handle(), &accelerator) {
+                warn!("Global shortcut {} not registered: {}", accelerator, e);
             }
             
             Ok(())
         })
+        .on_window_event(|window, event| match event {
+            // Closing the window only hides it; the tray keeps the app alive
+            WindowEvent::CloseRequested { api, .. } => {
+                api.prevent_close();
+                let _ = window.hide();
+            }
+            WindowEvent::Focused(false) if window.label() == MAIN_WINDOW => hide_after_blur(window.clone()),
+            WindowEvent::ThemeChanged(theme) => forward_theme_change(window.clone(), *theme),
+            _ => {}
+        })
         .run(context)
-        .expect("[ERROR] [touchdictionary] [gui] Failed to run Tauri application");
+        .expect("Failed to run Tauri application");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_starts_visible() {
+        assert!(starts_visible(&args(&["serendipity"])));
+        assert!(starts_visible(&args(&["ice", "cream"])));
+        assert!(starts_visible(&args(&["--watch", "serendipity"])));
+        assert!(starts_visible(&args(&["touchdict://lookup?q=ephemeral"])));
+
+        assert!(!starts_visible(&args(&[])));
+        assert!(!starts_visible(&args(&["--watch"])));
+        assert!(!starts_visible(&args(&["   "])));
+        assert!(!starts_visible(&args(&[" ", "\t"])));
+        assert!(!starts_visible(&args(&["touchdict://lookup"])));
+        assert!(!starts_visible(&args(&["--hidden"])));
+        assert!(!starts_visible(&args(&["--hidden", "serendipity"])));
+    }
 }
@@ -0,0 +1,223 @@
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::warn;
+use tauri_plugin_global_shortcut::Shortcut;
+use touchdictionary_core::{http, language};
+use url::Url;
+
+pub const FILE_NAME: &str = "preferences.json";
+
+// Result sections the frontend knows how to order
+pub const SECTIONS: &[&str] = &["definitions", "wikipedia", "thesaurus", "etymology", "examples", "acronym", "rhymes"];
+
+const MIN_FONT_SCALE: f64 = 0.5;
+const MAX_FONT_SCALE: f64 = 2.0;
+
+// Global shortcut that looks up the selection unless the user picks another
+pub const DEFAULT_HOTKEY: &str = "Ctrl+Alt+D";
+
+// Stands for the query in an external provider's URL template
+pub const QUERY_PLACEHOLDER: &str = "{query}";
+
+// A site the query can be opened on from the window
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExternalProvider {
+    pub name: String,
+    // https URL containing `QUERY_PLACEHOLDER`
+    pub url: String,
+}
+
+impl ExternalProvider {
+    fn new(name: &str, url: &str) -> Self {
+        ExternalProvider { name: name.to_string(), url: url.to_string() }
+    }
+
+    // The template with `query` percent-encoded into it
+    pub fn url_for(&self, query: &str) -> String {
+        self.url.replace(QUERY_PLACEHOLDER, &http::encode_path_segment(query.trim()))
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("external provider with no name".to_string());
+        }
+        if !self.url.contains(QUERY_PLACEHOLDER) {
+            return Err(format!("external provider '{}' has no {} in its URL", self.name, QUERY_PLACEHOLDER));
+        }
+        // Checked with a sample query filled in, since the braces aren't
+        // valid everywhere in a URL
+        let url = Url::parse(&self.url_for("test"))
+            .map_err(|e| format!("external provider '{}' has an invalid URL: {}", self.name, e))?;
+        if url.scheme() != "https" || url.host_str().is_none_or(str::is_empty) {
+            return Err(format!("external provider '{}' must use an https:// URL", self.name));
+        }
+        Ok(())
+    }
+}
+
+fn default_external_providers() -> Vec<ExternalProvider> {
+    vec![
+        ExternalProvider::new("Wikipedia", "https://en.wikipedia.org/wiki/Special:Search?search={query}"),
+        ExternalProvider::new("Wiktionary", "https://en.wiktionary.org/wiki/Special:Search?search={query}"),
+        ExternalProvider::new("Etymonline", "https://www.etymonline.com/search?q={query}"),
+        ExternalProvider::new("DuckDuckGo", "https://duckduckgo.com/?q={query}"),
+    ]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    // Follow the OS, switching when it does
+    #[serde(alias = "system")]
+    Auto,
+    Light,
+    Dark,
+}
+
+// How the window looks, as opposed to how lookups behave (that's the core
+// config). Lives in `preferences.json` in the app config dir.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preferences {
+    pub theme: Theme,
+    // Multiplies the base font size
+    pub font_scale: f64,
+    // Every entry of `SECTIONS`, in display order
+    pub section_order: Vec<String>,
+    pub show_images: bool,
+    // Language for lookups from the window; the core config's when unset
+    pub default_language: Option<String>,
+    // Global shortcut that looks up the selection; empty turns it off
+    pub hotkey: String,
+    // Hide the window when it loses focus, like a tooltip
+    pub auto_hide_on_blur: bool,
+    // Sites offered for opening the query, in menu order
+    pub external_providers: Vec<ExternalProvider>,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Preferences {
+            theme: Theme::Auto,
+            font_scale: 1.0,
+            section_order: SECTIONS.iter().map(|s| s.to_string()).collect(),
+            show_images: true,
+            default_language: None,
+            hotkey: DEFAULT_HOTKEY.to_string(),
+            auto_hide_on_blur: true,
+            external_providers: default_external_providers(),
+        }
+    }
+}
+
+impl Preferences {
+    // A missing, unreadable or invalid file gives the defaults
+    pub fn load(path: &Path) -> Self {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Preferences::default(),
+            Err(e) => {
+                warn!("Ignoring {}: {}", path.display(), e);
+                return Preferences::default();
+            }
+        };
+
+        match serde_json::from_str::<Preferences>(&text).map_err(|e| e.to_string()).and_then(Preferences::validated) {
+            Ok(preferences) => preferences,
+            Err(e) => {
+                warn!("Ignoring {}: {}", path.display(), e);
+                Preferences::default()
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let text = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        std::fs::write(path, text)
+    }
+
+    pub fn external_provider(&self, name: &str) -> Option<&ExternalProvider> {
+        self.external_providers.iter().find(|provider| provider.name.eq_ignore_ascii_case(name))
+    }
+
+    // These preferences with the fields present in `update` replaced.
+    // Unknown fields and out-of-range values are rejected as a whole.
+    pub fn merged(&self, update: &Value) -> Result<Preferences, String> {
+        let Value::Object(fields) = update else {
+            return Err("preferences must be an object".to_string());
+        };
+        let Ok(Value::Object(mut merged)) = serde_json::to_value(self) else {
+            return Err("preferences could not be serialized".to_string());
+        };
+        for (key, value) in fields {
+            if !merged.contains_key(key) {
+                return Err(format!("unknown preference '{}'", key));
+            }
+            merged.insert(key.clone(), value.clone());
+        }
+
+        let preferences: Preferences = serde_json::from_value(Value::Object(merged)).map_err(|e| e.to_string())?;
+        preferences.validated()
+    }
+
+    fn validated(mut self) -> Result<Preferences, String> {
+        if !(MIN_FONT_SCALE..=MAX_FONT_SCALE).contains(&self.font_scale) {
+            return Err(format!(
+                "font_scale must be between {} and {}, got {}",
+                MIN_FONT_SCALE, MAX_FONT_SCALE, self.font_scale
+            ));
+        }
+
+        let mut order: Vec<String> = Vec::new();
+        for section in &self.section_order {
+            if !SECTIONS.contains(&section.as_str()) {
+                return Err(format!("unknown section '{}'", section));
+            }
+            if order.contains(section) {
+                return Err(format!("section '{}' listed twice", section));
+            }
+            order.push(section.clone());
+        }
+        // Sections left out keep their default place at the end
+        order.extend(SECTIONS.iter().map(|s| s.to_string()).filter(|s| !self.section_order.contains(s)));
+        self.section_order = order;
+
+        if let Some(lang) = self.default_language.take() {
+            let lang = language::normalize(&lang);
+            if !lang.is_empty() {
+                if !language::is_known(&lang) {
+                    return Err(format!("unsupported language '{}'", lang));
+                }
+                self.default_language = Some(lang);
+            }
+        }
+
+        for provider in &mut self.external_providers {
+            provider.name = provider.name.trim().to_string();
+            provider.url = provider.url.trim().to_string();
+            provider.validate()?;
+        }
+        for (i, provider) in self.external_providers.iter().enumerate() {
+            if self.external_providers[..i].iter().any(|other| other.name.eq_ignore_ascii_case(&provider.name)) {
+                return Err(format!("external provider '{}' listed twice", provider.name));
+            }
+        }
+
+        let accelerator = self.hotkey.trim();
+        if !accelerator.is_empty() {
+            let shortcut = accelerator.parse::<Shortcut>().map_err(|e| e.to_string())?;
+            // A bare key would be swallowed in every other application
+            if shortcut.mods.is_empty() {
+                return Err(format!("'{}' needs a modifier such as Ctrl or Alt", accelerator));
+            }
+            self.hotkey = accelerator.to_string();
+        }
+        Ok(self)
+    }
+}